@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use app_error::AppError;
+use async_trait::async_trait;
+use database::database_row_access::{
+  select_database_row_rule, select_database_row_user, select_granted_database_row_ids,
+};
+use database_entity::dto::AFRole;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Access of a user to the rows of a database. The rows of the databases without a row level
+/// rule can be read by anyone who can read the database.
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseRowAccess {
+  restriction: Option<RowRestriction>,
+}
+
+#[derive(Debug, Clone)]
+struct RowRestriction {
+  user_field_id: Option<String>,
+  /// Lowercase uid, uuid and email of the user.
+  user_keys: HashSet<String>,
+  granted_row_ids: HashSet<Uuid>,
+}
+
+impl DatabaseRowAccess {
+  pub fn unrestricted() -> Self {
+    Self::default()
+  }
+
+  /// Only the rows whose cell in `user_field_id` contains one of the `user_keys`, and the
+  /// `granted_row_ids`, can be read.
+  pub fn restricted(
+    user_field_id: Option<String>,
+    user_keys: impl IntoIterator<Item = String>,
+    granted_row_ids: impl IntoIterator<Item = Uuid>,
+  ) -> Self {
+    Self {
+      restriction: Some(RowRestriction {
+        user_field_id,
+        user_keys: user_keys
+          .into_iter()
+          .filter(|key| !key.is_empty())
+          .map(|key| key.to_lowercase())
+          .collect(),
+        granted_row_ids: granted_row_ids.into_iter().collect(),
+      }),
+    }
+  }
+
+  pub fn is_restricted(&self) -> bool {
+    self.restriction.is_some()
+  }
+
+  /// Field whose cell must contain the user, for the rows which are not granted to the user.
+  pub fn user_field_id(&self) -> Option<&str> {
+    self
+      .restriction
+      .as_ref()
+      .and_then(|restriction| restriction.user_field_id.as_deref())
+  }
+
+  /// `user_field_data` is the raw data of the cell of the row in the user field, e.g. the ids of
+  /// the users of a person field, or the emails in a text field.
+  pub fn can_read_row(&self, row_id: &Uuid, user_field_data: Option<&str>) -> bool {
+    let restriction = match &self.restriction {
+      None => return true,
+      Some(restriction) => restriction,
+    };
+    if restriction.granted_row_ids.contains(row_id) {
+      return true;
+    }
+    if restriction.user_field_id.is_none() {
+      return false;
+    }
+    user_field_data
+      .map(|data| {
+        data
+          .split(|c: char| !(c.is_alphanumeric() || matches!(c, '@' | '.' | '-' | '_' | '+')))
+          .filter(|token| !token.is_empty())
+          .any(|token| restriction.user_keys.contains(&token.to_lowercase()))
+      })
+      .unwrap_or(false)
+  }
+}
+
+#[async_trait]
+pub trait DatabaseRowAccessControl: Send + Sync + 'static {
+  /// Returns the access of the user to the rows of the database. The owners of the workspace can
+  /// read all the rows.
+  async fn get_row_access(
+    &self,
+    workspace_id: &Uuid,
+    uid: &i64,
+    database_id: &Uuid,
+  ) -> Result<DatabaseRowAccess, AppError>;
+}
+
+#[derive(Clone)]
+pub struct DatabaseRowAccessControlImpl {
+  pg_pool: PgPool,
+}
+
+impl DatabaseRowAccessControlImpl {
+  pub fn new(pg_pool: PgPool) -> Self {
+    Self { pg_pool }
+  }
+}
+
+#[async_trait]
+impl DatabaseRowAccessControl for DatabaseRowAccessControlImpl {
+  async fn get_row_access(
+    &self,
+    workspace_id: &Uuid,
+    uid: &i64,
+    database_id: &Uuid,
+  ) -> Result<DatabaseRowAccess, AppError> {
+    let rule = match select_database_row_rule(&self.pg_pool, workspace_id, database_id).await? {
+      None => return Ok(DatabaseRowAccess::unrestricted()),
+      Some(rule) => rule,
+    };
+    let user = select_database_row_user(&self.pg_pool, workspace_id, *uid).await?;
+    if user.role_id.map(AFRole::from) == Some(AFRole::Owner) {
+      return Ok(DatabaseRowAccess::unrestricted());
+    }
+    let granted_row_ids =
+      select_granted_database_row_ids(&self.pg_pool, database_id, *uid).await?;
+    Ok(DatabaseRowAccess::restricted(
+      rule.user_field_id,
+      [uid.to_string(), user.uuid.to_string(), user.email],
+      granted_row_ids,
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn unrestricted_rows_can_be_read() {
+    let access = DatabaseRowAccess::unrestricted();
+    assert!(!access.is_restricted());
+    assert!(access.can_read_row(&Uuid::new_v4(), None));
+  }
+
+  #[test]
+  fn restricted_rows_are_matched_by_user_field_or_grant() {
+    let granted_row_id = Uuid::new_v4();
+    let row_id = Uuid::new_v4();
+    let access = DatabaseRowAccess::restricted(
+      Some("assignee".to_string()),
+      ["42".to_string(), "Alice@Example.com".to_string()],
+      [granted_row_id],
+    );
+    assert_eq!(access.user_field_id(), Some("assignee"));
+    assert!(access.can_read_row(&granted_row_id, None));
+    assert!(access.can_read_row(&row_id, Some("bob@example.com, alice@example.com")));
+    assert!(access.can_read_row(&row_id, Some(r#"["7","42"]"#)));
+    assert!(!access.can_read_row(&row_id, Some("420")));
+    assert!(!access.can_read_row(&row_id, Some("malice@example.com")));
+    assert!(!access.can_read_row(&row_id, None));
+  }
+
+  #[test]
+  fn rows_are_only_granted_without_user_field() {
+    let row_id = Uuid::new_v4();
+    let access = DatabaseRowAccess::restricted(None, ["42".to_string()], []);
+    assert!(!access.can_read_row(&row_id, Some("42")));
+  }
+}
@@ -2,6 +2,7 @@ pub mod act;
 #[cfg(feature = "casbin")]
 pub mod casbin;
 pub mod collab;
+pub mod database_row;
 pub mod entity;
 pub mod metrics;
 pub mod noops;
@@ -0,0 +1,32 @@
+use app_error::AppError;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::database_row::{DatabaseRowAccess, DatabaseRowAccessControl};
+
+#[derive(Clone)]
+pub struct DatabaseRowAccessControlImpl;
+
+impl DatabaseRowAccessControlImpl {
+  pub fn new() -> Self {
+    Self {}
+  }
+}
+
+impl Default for DatabaseRowAccessControlImpl {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[async_trait]
+impl DatabaseRowAccessControl for DatabaseRowAccessControlImpl {
+  async fn get_row_access(
+    &self,
+    _workspace_id: &Uuid,
+    _uid: &i64,
+    _database_id: &Uuid,
+  ) -> Result<DatabaseRowAccess, AppError> {
+    Ok(DatabaseRowAccess::unrestricted())
+  }
+}
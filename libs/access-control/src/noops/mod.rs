@@ -1,2 +1,3 @@
 pub mod collab;
+pub mod database_row;
 pub mod workspace;
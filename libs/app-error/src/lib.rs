@@ -176,6 +176,24 @@ pub enum AppError {
     max_length: usize,
   },
 
+  #[error("The published page is password protected")]
+  PublishPasswordRequired,
+
+  #[error("Too many password attempts, please try again later")]
+  PublishPasswordAttemptsExceeded,
+
+  #[error("The domain {0} is already used by another publish namespace")]
+  PublishDomainAlreadyTaken(String),
+
+  #[error("{0}")]
+  PublishDomainVerificationFailed(String),
+
+  #[error("Too many comments, please try again later")]
+  PublishCommentRateLimited,
+
+  #[error("The user is not allowed to comment on the pages published by this workspace")]
+  PublishCommenterBlocked,
+
   #[error("There is an invalid character in the publish namespace: {character}")]
   CustomNamespaceInvalidCharacter { character: char },
 
@@ -285,6 +303,12 @@ impl AppError {
       AppError::PublishNameAlreadyExists { .. } => ErrorCode::PublishNameAlreadyExists,
       AppError::PublishNameInvalidCharacter { .. } => ErrorCode::PublishNameInvalidCharacter,
       AppError::PublishNameTooLong { .. } => ErrorCode::PublishNameTooLong,
+      AppError::PublishPasswordRequired => ErrorCode::PublishPasswordRequired,
+      AppError::PublishPasswordAttemptsExceeded => ErrorCode::PublishPasswordAttemptsExceeded,
+      AppError::PublishDomainAlreadyTaken(_) => ErrorCode::PublishDomainAlreadyTaken,
+      AppError::PublishDomainVerificationFailed(_) => ErrorCode::PublishDomainVerificationFailed,
+      AppError::PublishCommentRateLimited => ErrorCode::PublishCommentRateLimited,
+      AppError::PublishCommenterBlocked => ErrorCode::PublishCommenterBlocked,
       AppError::CustomNamespaceInvalidCharacter { .. } => {
         ErrorCode::CustomNamespaceInvalidCharacter
       },
@@ -483,6 +507,12 @@ pub enum ErrorCode {
   InvalidGuest = 1069,
   FreePlanGuestLimitExceeded = 1070,
   PaidPlanGuestLimitExceeded = 1071,
+  PublishPasswordRequired = 1072,
+  PublishDomainAlreadyTaken = 1073,
+  PublishDomainVerificationFailed = 1074,
+  PublishCommentRateLimited = 1075,
+  PublishCommenterBlocked = 1076,
+  PublishPasswordAttemptsExceeded = 1077,
 }
 
 impl ErrorCode {
@@ -0,0 +1,276 @@
+use crate::pb;
+use crate::pb::message::Payload;
+use crate::shared::Error;
+use prost::Message;
+use std::fmt::{Debug, Formatter};
+
+/// Maximum size of the encoded message, which can be sent as a single WebSocket message.
+/// Messages bigger than that are split into chunks - provided that the receiver supports them.
+pub const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024; // 10 MiB
+
+/// Maximum size of the payload carried by a single chunk.
+pub const CHUNK_SIZE: usize = 4 * 1024 * 1024; // 4 MiB
+
+/// Default upper limit of the reassembled chunked message size. It protects the receiver from
+/// buffering unbounded amount of data.
+pub const MAX_CHUNKED_MESSAGE_SIZE: usize = 256 * 1024 * 1024; // 256 MiB
+
+/// Fragment of an encoded [ClientMessage](crate::ClientMessage) or
+/// [ServerMessage](crate::ServerMessage), which was too big to be sent in one piece.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MessageChunk {
+  pub message_id: u64,
+  pub sequence: u32,
+  pub total: u32,
+  pub checksum: u32,
+  pub payload: Vec<u8>,
+}
+
+impl MessageChunk {
+  /// Splits encoded message bytes into chunks carrying at most `chunk_size` bytes each.
+  pub fn split(message_id: u64, bytes: &[u8], chunk_size: usize) -> Vec<MessageChunk> {
+    let checksum = crc32fast::hash(bytes);
+    let total = bytes.len().div_ceil(chunk_size) as u32;
+    bytes
+      .chunks(chunk_size)
+      .enumerate()
+      .map(|(sequence, payload)| MessageChunk {
+        message_id,
+        sequence: sequence as u32,
+        total,
+        checksum,
+        payload: payload.to_vec(),
+      })
+      .collect()
+  }
+
+  pub fn is_last(&self) -> bool {
+    self.sequence + 1 == self.total
+  }
+
+  pub fn into_bytes(self) -> Vec<u8> {
+    pb::Message::from(self).encode_to_vec()
+  }
+}
+
+impl Debug for MessageChunk {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MessageChunk")
+      .field("message_id", &self.message_id)
+      .field("sequence", &self.sequence)
+      .field("total", &self.total)
+      .field("checksum", &self.checksum)
+      .field("len", &self.payload.len())
+      .finish()
+  }
+}
+
+impl From<MessageChunk> for pb::Message {
+  fn from(value: MessageChunk) -> Self {
+    pb::Message {
+      payload: Some(Payload::Chunk(pb::Chunk {
+        message_id: value.message_id,
+        sequence: value.sequence,
+        total: value.total,
+        checksum: value.checksum,
+        payload: value.payload,
+      })),
+    }
+  }
+}
+
+impl From<pb::Chunk> for MessageChunk {
+  fn from(value: pb::Chunk) -> Self {
+    MessageChunk {
+      message_id: value.message_id,
+      sequence: value.sequence,
+      total: value.total,
+      checksum: value.checksum,
+      payload: value.payload,
+    }
+  }
+}
+
+/// Returns a list of WebSocket messages, that given encoded message should be sent as.
+/// Messages that fit into [MAX_MESSAGE_SIZE] are returned as they are, bigger ones are split
+/// into encoded [MessageChunk]s.
+pub fn into_frames(message_id: u64, bytes: Vec<u8>) -> Vec<Vec<u8>> {
+  if bytes.len() <= MAX_MESSAGE_SIZE {
+    return vec![bytes];
+  }
+  MessageChunk::split(message_id, &bytes, CHUNK_SIZE)
+    .into_iter()
+    .map(MessageChunk::into_bytes)
+    .collect()
+}
+
+/// Reassembles [MessageChunk]s back into encoded message bytes.
+///
+/// Chunks of a single message are always sent one after another, in order, so the assembler
+/// only keeps track of one pending message at the time. Any chunk that doesn't follow up
+/// the pending one is treated as a protocol violation.
+#[derive(Debug)]
+pub struct ChunkAssembler {
+  max_message_size: usize,
+  pending: Option<PendingMessage>,
+}
+
+#[derive(Debug)]
+struct PendingMessage {
+  message_id: u64,
+  total: u32,
+  checksum: u32,
+  next_sequence: u32,
+  buf: Vec<u8>,
+}
+
+impl ChunkAssembler {
+  pub fn new(max_message_size: usize) -> Self {
+    ChunkAssembler {
+      max_message_size,
+      pending: None,
+    }
+  }
+
+  /// Returns true if assembler has received some, but not all, chunks of a message.
+  pub fn is_pending(&self) -> bool {
+    self.pending.is_some()
+  }
+
+  /// Appends a chunk to the pending message. Once the last chunk is received, the reassembled
+  /// message bytes are verified against the checksum and returned.
+  pub fn push(&mut self, chunk: MessageChunk) -> Result<Option<Vec<u8>>, Error> {
+    let result = self.try_push(chunk);
+    if result.is_err() {
+      self.pending = None;
+    }
+    result
+  }
+
+  fn try_push(&mut self, chunk: MessageChunk) -> Result<Option<Vec<u8>>, Error> {
+    if chunk.sequence >= chunk.total {
+      return Err(Error::InvalidChunk("sequence number out of range"));
+    }
+    let pending = match self.pending.take() {
+      None if chunk.sequence == 0 => PendingMessage {
+        message_id: chunk.message_id,
+        total: chunk.total,
+        checksum: chunk.checksum,
+        next_sequence: 0,
+        buf: Vec::new(),
+      },
+      None => return Err(Error::InvalidChunk("missing preceding chunks")),
+      Some(pending) => {
+        if pending.message_id != chunk.message_id {
+          return Err(Error::InvalidChunk(
+            "previous chunked message was not completed",
+          ));
+        }
+        if pending.next_sequence != chunk.sequence
+          || pending.total != chunk.total
+          || pending.checksum != chunk.checksum
+        {
+          return Err(Error::InvalidChunk("chunk doesn't match pending message"));
+        }
+        pending
+      },
+    };
+    let pending = self.pending.insert(pending);
+
+    if pending.buf.len() + chunk.payload.len() > self.max_message_size {
+      return Err(Error::ChunkedMessageTooLarge(self.max_message_size));
+    }
+    pending.buf.extend_from_slice(&chunk.payload);
+    pending.next_sequence += 1;
+
+    if pending.next_sequence < pending.total {
+      return Ok(None);
+    }
+    let pending = self.pending.take().expect("pending message");
+    if crc32fast::hash(&pending.buf) != pending.checksum {
+      return Err(Error::ChunkChecksumMismatch(pending.message_id));
+    }
+    Ok(Some(pending.buf))
+  }
+}
+
+impl Default for ChunkAssembler {
+  fn default() -> Self {
+    Self::new(MAX_CHUNKED_MESSAGE_SIZE)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn message(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+  }
+
+  #[test]
+  fn split_and_reassemble() {
+    let bytes = message(10_000);
+    let chunks = MessageChunk::split(1, &bytes, 3_000);
+    assert_eq!(chunks.len(), 4);
+    assert!(chunks[3].is_last());
+
+    let mut assembler = ChunkAssembler::default();
+    for chunk in &chunks[..3] {
+      assert_eq!(assembler.push(chunk.clone()).unwrap(), None);
+    }
+    assert!(assembler.is_pending());
+    let result = assembler.push(chunks[3].clone()).unwrap();
+    assert_eq!(result, Some(bytes));
+    assert!(!assembler.is_pending());
+  }
+
+  #[test]
+  fn chunk_roundtrip_through_protobuf() {
+    let chunk = MessageChunk::split(7, &message(100), 30).remove(2);
+    let proto = pb::Message::decode(chunk.clone().into_bytes().as_slice()).unwrap();
+    match proto.payload {
+      Some(Payload::Chunk(decoded)) => assert_eq!(MessageChunk::from(decoded), chunk),
+      other => panic!("expected chunk, got: {:?}", other),
+    }
+  }
+
+  #[test]
+  fn small_messages_are_not_chunked() {
+    let bytes = message(1024);
+    let frames = into_frames(1, bytes.clone());
+    assert_eq!(frames, vec![bytes]);
+  }
+
+  #[test]
+  fn out_of_order_chunk_is_rejected() {
+    let chunks = MessageChunk::split(1, &message(100), 30);
+    let mut assembler = ChunkAssembler::default();
+    assembler.push(chunks[0].clone()).unwrap();
+    assert!(assembler.push(chunks[2].clone()).is_err());
+    assert!(!assembler.is_pending());
+  }
+
+  #[test]
+  fn corrupted_message_is_rejected() {
+    let mut chunks = MessageChunk::split(1, &message(100), 60);
+    chunks[1].payload[0] ^= 0xff;
+    let mut assembler = ChunkAssembler::default();
+    assembler.push(chunks[0].clone()).unwrap();
+    assert!(matches!(
+      assembler.push(chunks[1].clone()),
+      Err(Error::ChunkChecksumMismatch(1))
+    ));
+  }
+
+  #[test]
+  fn message_size_limit_is_enforced() {
+    let chunks = MessageChunk::split(1, &message(100), 30);
+    let mut assembler = ChunkAssembler::new(50);
+    assembler.push(chunks[0].clone()).unwrap();
+    assert!(matches!(
+      assembler.push(chunks[1].clone()),
+      Err(Error::ChunkedMessageTooLarge(50))
+    ));
+  }
+}
@@ -1,3 +1,4 @@
+use crate::chunk::MessageChunk;
 use crate::pb;
 use crate::pb::collab_message::Data;
 use crate::pb::message::Payload;
@@ -56,6 +57,15 @@ pub enum ClientMessage {
     collab_type: CollabType,
     awareness: Vec<u8>,
   },
+
+  /// Fragment of another client message, which was too big to be sent in one piece.
+  /// See [ChunkAssembler](crate::ChunkAssembler) for reassembling chunks back.
+  Chunk(MessageChunk),
+
+  /// Authenticates the connection with an access token. Sent as the first message, when
+  /// the token was not provided during connection handshake, or later on to refresh
+  /// the token before it expires.
+  Auth { token: String },
 }
 
 impl Debug for ClientMessage {
@@ -106,17 +116,22 @@ impl Debug for ClientMessage {
           .field("awareness", &awareness)
           .finish()
       },
+      ClientMessage::Chunk(chunk) => chunk.fmt(f),
+      // don't print the token itself
+      ClientMessage::Auth { .. } => f.debug_struct("Auth").finish_non_exhaustive(),
     }
   }
 }
 
 impl ClientMessage {
   /// Returns a reference to the object ID contained in this message.
-  pub fn object_id(&self) -> &ObjectId {
+  /// Message chunks and auth messages are not related to any specific object.
+  pub fn object_id(&self) -> Option<&ObjectId> {
     match self {
-      ClientMessage::Manifest { object_id, .. } => object_id,
-      ClientMessage::Update { object_id, .. } => object_id,
-      ClientMessage::AwarenessUpdate { object_id, .. } => object_id,
+      ClientMessage::Manifest { object_id, .. } => Some(object_id),
+      ClientMessage::Update { object_id, .. } => Some(object_id),
+      ClientMessage::AwarenessUpdate { object_id, .. } => Some(object_id),
+      ClientMessage::Chunk(_) | ClientMessage::Auth { .. } => None,
     }
   }
 
@@ -191,6 +206,10 @@ impl From<ClientMessage> for pb::Message {
           })),
         }
       },
+      ClientMessage::Chunk(chunk) => pb::Message::from(chunk),
+      ClientMessage::Auth { token } => pb::Message {
+        payload: Some(message::Payload::Auth(pb::Auth { token })),
+      },
     }
   }
 }
@@ -241,6 +260,8 @@ impl TryFrom<pb::Message> for ClientMessage {
           }
         },
         Payload::Notification(_) => Err(Error::UnsupportedClientMessage),
+        Payload::Chunk(chunk) => Ok(ClientMessage::Chunk(chunk.into())),
+        Payload::Auth(auth) => Ok(ClientMessage::Auth { token: auth.token }),
       },
     }
   }
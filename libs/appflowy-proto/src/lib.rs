@@ -1,8 +1,10 @@
+mod chunk;
 mod client_message;
 mod pb;
 mod server_message;
 mod shared;
 
+pub use chunk::*;
 pub use client_message::*;
 pub use server_message::*;
 pub use shared::*;
@@ -3,7 +3,7 @@
 /// All messages send between client/server are wrapped into a `Message`.
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Message {
-  #[prost(oneof = "message::Payload", tags = "1, 2")]
+  #[prost(oneof = "message::Payload", tags = "1, 2, 3, 4")]
   pub payload: ::core::option::Option<message::Payload>,
 }
 /// Nested message and enum types in `Message`.
@@ -14,5 +14,47 @@ pub mod message {
     CollabMessage(super::super::collab::CollabMessage),
     #[prost(message, tag = "2")]
     Notification(super::super::notification::WorkspaceNotification),
+    #[prost(message, tag = "3")]
+    Chunk(super::Chunk),
+    #[prost(message, tag = "4")]
+    Auth(super::Auth),
   }
 }
+/// *
+/// Chunk carries a fragment of an encoded `Message`, which was too big to be send as
+/// a single WebSocket message. Sender splits encoded message bytes into `total` chunks,
+/// which are always send one after another, without any other messages in between.
+/// Receiver concatenates chunk payloads and decodes the result as a regular `Message`.
+///
+/// Server only sends chunks to the clients, which advertised support for them.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Chunk {
+  /// Identifier of the chunked message, shared by all of its chunks. It's unique only
+  /// in scope of a single connection and sender.
+  #[prost(uint64, tag = "1")]
+  pub message_id: u64,
+  /// Zero-based position of this chunk within the chunked message.
+  #[prost(uint32, tag = "2")]
+  pub sequence: u32,
+  /// Total number of chunks the message was split into.
+  #[prost(uint32, tag = "3")]
+  pub total: u32,
+  /// CRC32 checksum of the entire, reassembled message bytes.
+  #[prost(uint32, tag = "4")]
+  pub checksum: u32,
+  /// Fragment of the encoded message bytes.
+  #[prost(bytes = "vec", tag = "5")]
+  pub payload: ::prost::alloc::vec::Vec<u8>,
+}
+/// *
+/// Auth carries an access token of the user. It's send by the client either as the first
+/// message of the connection (when the token was not provided during connection handshake)
+/// or at any point later to refresh the token, before the previous one expires.
+///
+/// Only clients send this message.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Auth {
+  /// Access token used to authenticate the user.
+  #[prost(string, tag = "1")]
+  pub token: ::prost::alloc::string::String,
+}
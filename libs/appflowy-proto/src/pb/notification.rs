@@ -1,7 +1,7 @@
 // This file is @generated by prost-build.
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct WorkspaceNotification {
-  #[prost(oneof = "workspace_notification::Payload", tags = "1, 2")]
+  #[prost(oneof = "workspace_notification::Payload", tags = "1, 2, 3")]
   pub payload: ::core::option::Option<workspace_notification::Payload>,
 }
 /// Nested message and enum types in `WorkspaceNotification`.
@@ -12,6 +12,8 @@ pub mod workspace_notification {
     ProfileChange(super::UserProfileChange),
     #[prost(message, tag = "2")]
     PermissionChanged(super::PermissionChanged),
+    #[prost(message, tag = "3")]
+    PresenceChanged(super::CollabPresenceChanged),
   }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -30,3 +32,21 @@ pub struct PermissionChanged {
   #[prost(uint32, tag = "2")]
   pub reason: u32,
 }
+/// *
+/// CollabPresenceChanged is sent when a user device starts or stops viewing/editing
+/// a collab within the workspace. It's derived from the awareness updates.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CollabPresenceChanged {
+  #[prost(string, tag = "1")]
+  pub object_id: ::prost::alloc::string::String,
+  #[prost(int64, tag = "2")]
+  pub uid: i64,
+  #[prost(string, tag = "3")]
+  pub device_id: ::prost::alloc::string::String,
+  /// Flag indicating if user device is currently active over the collab.
+  #[prost(bool, tag = "4")]
+  pub active: bool,
+  /// UNIX epoch timestamp in milliseconds of the last awareness update from the device.
+  #[prost(int64, tag = "5")]
+  pub last_active_at: i64,
+}
@@ -1,7 +1,8 @@
+use crate::chunk::MessageChunk;
 use crate::pb;
 use crate::pb::collab_message::Data;
 use crate::pb::message::Payload;
-use crate::pb::notification::{PermissionChanged, UserProfileChange};
+use crate::pb::notification::{CollabPresenceChanged, PermissionChanged, UserProfileChange};
 #[rustfmt::skip]
 use crate::pb::{SyncRequest, message};
 use crate::shared::{Error, ObjectId, Rid, UpdateFlags};
@@ -62,6 +63,9 @@ pub enum ServerMessage {
   Notification {
     notification: WorkspaceNotification,
   },
+  /// Fragment of another server message, which was too big to be sent in one piece.
+  /// Only sent to clients, which advertised support for chunked messages.
+  Chunk(MessageChunk),
 }
 
 impl ServerMessage {
@@ -143,6 +147,7 @@ impl Debug for ServerMessage {
         .debug_struct("WorkspaceNotification")
         .field("notification", &notification)
         .finish(),
+      ServerMessage::Chunk(chunk) => chunk.fmt(f),
     }
   }
 }
@@ -240,7 +245,29 @@ impl From<ServerMessage> for pb::Message {
             },
           )),
         },
+        WorkspaceNotification::CollabPresenceChanged {
+          object_id,
+          uid,
+          device_id,
+          active,
+          last_active_at,
+        } => pb::Message {
+          payload: Some(message::Payload::Notification(
+            pb::notification::WorkspaceNotification {
+              payload: Some(NotificationPayload::PresenceChanged(
+                CollabPresenceChanged {
+                  object_id: object_id.to_string(),
+                  uid,
+                  device_id,
+                  active,
+                  last_active_at,
+                },
+              )),
+            },
+          )),
+        },
       },
+      ServerMessage::Chunk(chunk) => pb::Message::from(chunk),
     }
   }
 }
@@ -316,8 +343,22 @@ impl TryFrom<pb::Message> for ServerMessage {
                 },
               })
             },
+            NotificationPayload::PresenceChanged(value) => {
+              let object_id = Uuid::parse_str(&value.object_id)?;
+              Ok(ServerMessage::Notification {
+                notification: WorkspaceNotification::CollabPresenceChanged {
+                  object_id,
+                  uid: value.uid,
+                  device_id: value.device_id,
+                  active: value.active,
+                  last_active_at: value.last_active_at,
+                },
+              })
+            },
           },
         },
+        Payload::Chunk(chunk) => Ok(ServerMessage::Chunk(chunk.into())),
+        Payload::Auth(_) => Err(Error::UnsupportedServerMessage),
       },
     }
   }
@@ -334,6 +375,15 @@ pub enum WorkspaceNotification {
     object_id: Uuid,
     reason: AccessChangedReason,
   },
+  /// User device started or stopped viewing/editing a collab within the workspace.
+  CollabPresenceChanged {
+    object_id: Uuid,
+    uid: i64,
+    device_id: String,
+    active: bool,
+    /// UNIX epoch timestamp in milliseconds of the last awareness update from the device.
+    last_active_at: i64,
+  },
 }
 
 impl From<AccessChangedReason> for i32 {
@@ -100,6 +100,14 @@ pub enum Error {
   UnknownCollabType(u8),
   #[error("Message does not match expected client message")]
   UnsupportedClientMessage,
+  #[error("Message does not match expected server message")]
+  UnsupportedServerMessage,
+  #[error("invalid message chunk: {0}")]
+  InvalidChunk(&'static str),
+  #[error("chunked message {0} failed checksum verification")]
+  ChunkChecksumMismatch(u64),
+  #[error("chunked message exceeds maximum size of {0} bytes")]
+  ChunkedMessageTooLarge(usize),
 }
 
 pub struct TimestampedEncodedCollab {
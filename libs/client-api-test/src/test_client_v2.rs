@@ -116,7 +116,8 @@ impl TestClient {
       .workspace_id;
     let device_id = api_client.device_id.clone();
 
-    let workspace_manager = WorkspaceManager::new(device_id.clone(), temp_dir.clone());
+    let workspace_manager =
+      WorkspaceManager::new(device_id.clone(), temp_dir.clone(), api_client.clone());
     let client = Self {
       user: registered_user,
       api_client,
@@ -4,6 +4,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use client_api::v2::{WorkspaceController, WorkspaceControllerOptions, WorkspaceId};
+use client_api::Client;
 use tempfile::TempDir;
 
 use crate::LOCALHOST_WS_V2;
@@ -13,14 +14,17 @@ pub struct WorkspaceManager {
   pub workspaces: DashMap<WorkspaceId, Arc<WorkspaceController>>,
   device_id: String,
   temp_dir: Arc<TempDir>,
+  /// Refreshes of its access token are forwarded to the workspace connections.
+  api_client: Client,
 }
 
 impl WorkspaceManager {
-  pub fn new(device_id: String, temp_dir: Arc<TempDir>) -> Self {
+  pub fn new(device_id: String, temp_dir: Arc<TempDir>, api_client: Client) -> Self {
     Self {
       workspaces: DashMap::new(),
       device_id,
       temp_dir,
+      api_client,
     }
   }
 
@@ -35,6 +39,7 @@ impl WorkspaceManager {
       dashmap::mapref::entry::Entry::Occupied(e) => Ok(e.get().clone()),
       dashmap::mapref::entry::Entry::Vacant(e) => {
         let workspace = self.create_workspace_controller(workspace_id, uid).await?;
+        workspace.watch_access_token(&self.api_client);
 
         if let Some(token) = access_token {
           workspace.connect(token).await?;
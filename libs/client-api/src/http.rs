@@ -37,7 +37,7 @@ use client_api_entity::{AFUserProfile, AFUserWorkspaceInfo, AFWorkspace};
 use client_api_entity::{GotrueTokenResponse, UpdateGotrueUserParams, User};
 use semver::Version;
 use shared_entity::dto::auth_dto::UpdateUserParams;
-use shared_entity::dto::auth_dto::{SignInPasswordResponse, SignInTokenResponse};
+use shared_entity::dto::auth_dto::{SignInPasswordResponse, SignInTokenResponse, UserDataExport};
 use shared_entity::dto::workspace_dto::WorkspaceSpaceUsage;
 use shared_entity::response::{AppResponse, AppResponseError};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -697,6 +697,33 @@ impl Client {
     process_response_data::<AFUserWorkspaceInfo>(resp).await
   }
 
+  /// Requests an archive of all the data of the current user. The archive is built in the
+  /// background. Use [Client::get_user_data_export] to poll for its completion.
+  #[instrument(level = "info", skip_all, err)]
+  pub async fn create_user_data_export(&self) -> Result<UserDataExport, AppResponseError> {
+    let url = format!("{}/api/user/data-export", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<UserDataExport>(resp).await
+  }
+
+  #[instrument(level = "info", skip_all, err)]
+  pub async fn get_user_data_export(
+    &self,
+    export_id: &Uuid,
+  ) -> Result<UserDataExport, AppResponseError> {
+    let url = format!("{}/api/user/data-export/{}", self.base_url, export_id);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<UserDataExport>(resp).await
+  }
+
   #[instrument(level = "info", skip_all, err)]
   pub async fn delete_workspace(&self, workspace_id: &Uuid) -> Result<(), AppResponseError> {
     let url = format!("{}/api/workspace/{}", self.base_url, workspace_id);
@@ -6,7 +6,7 @@ use futures_util::TryStreamExt;
 use mime::Mime;
 use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::{header, Method, StatusCode};
-use shared_entity::dto::workspace_dto::{BlobMetadata, RepeatedBlobMetaData};
+use shared_entity::dto::workspace_dto::{BlobMetadata, OrphanedBlobReport, RepeatedBlobMetaData};
 use shared_entity::response::AppResponseError;
 
 use shared_entity::dto::file_dto::PutFileResponse;
@@ -256,4 +256,22 @@ impl Client {
       .await?;
     process_response_data::<RepeatedBlobMetaData>(resp).await
   }
+
+  /// Lists the blobs no longer referenced by the documents and databases of the workspace, which
+  /// are going to be deleted. Only available to the workspace owner.
+  pub async fn get_workspace_orphaned_blobs(
+    &self,
+    workspace_id: &Uuid,
+  ) -> Result<OrphanedBlobReport, AppResponseError> {
+    let url = format!(
+      "{}/api/file_storage/{}/orphaned_blobs",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<OrphanedBlobReport>(resp).await
+  }
 }
@@ -8,9 +8,14 @@ use app_error::AppError;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use client_api_entity::workspace_dto::{
-  AFDatabase, AFDatabaseField, AFDatabaseRow, AFDatabaseRowDetail, AFInsertDatabaseField,
-  AddDatatabaseRow, DatabaseRowUpdatedItem, ListDatabaseRowDetailParam,
-  ListDatabaseRowUpdatedParam, UpsertDatatabaseRow,
+  AFCreateDatabaseRowTemplate, AFDatabase, AFDatabaseField, AFDatabaseRow, AFDatabaseRowAccessRule,
+  AFDatabaseRowDetail, AFDatabaseRowGrants, AFDatabaseRowTemplate, AFDatabaseView,
+  AFInsertDatabaseField, AFMoveDatabaseField, AFUpdateDatabaseField, AFUpdateDatabaseRowAccessRule,
+  AFUpdateDatabaseRowTemplate, AFUpdateDatabaseView, AddDatatabaseRow, BatchDatabaseRowsParams,
+  BatchDatabaseRowsResponse, DatabaseRowChangesResponse, DatabaseRowUpdatedItem,
+  ExportDatabaseCsvQuery, ImportDatabaseCsvQuery, ImportDatabaseCsvResponse,
+  ListDatabaseRowChangesParam, ListDatabaseRowDetailParam, ListDatabaseRowUpdatedParam,
+  QueryDatabaseRowsParams, QueryDatabaseRowsResponse, UpsertDatatabaseRow,
 };
 use client_api_entity::{
   AFCollabEmbedInfo, BatchQueryCollabParams, BatchQueryCollabResult, CollabParams,
@@ -23,9 +28,11 @@ use futures::Stream;
 use futures_util::stream;
 use prost::Message;
 use rayon::prelude::*;
-use reqwest::{Body, Method};
+use reqwest::{header, Body, Method};
 use serde::Serialize;
-use shared_entity::dto::workspace_dto::{CollabResponse, CollabTypeParam, EmbeddedCollabQuery};
+use shared_entity::dto::workspace_dto::{
+  CollabResponse, CollabTypeParam, EmbeddedCollabQuery, WorkspacePresence, WorkspacePresenceQuery,
+};
 use shared_entity::response::{AppResponseError, ErrorCode};
 use std::collections::HashMap;
 use std::future::Future;
@@ -170,6 +177,24 @@ impl Client {
     process_response_error(resp).await
   }
 
+  /// Returns users currently viewing or editing collabs of a given workspace. When `object_id`
+  /// is provided, only the presence over that collab is returned.
+  #[instrument(level = "info", skip_all, err)]
+  pub async fn get_workspace_presence(
+    &self,
+    workspace_id: &Uuid,
+    object_id: Option<Uuid>,
+  ) -> Result<WorkspacePresence, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/presence", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&WorkspacePresenceQuery { object_id })
+      .send()
+      .await?;
+    process_response_data::<WorkspacePresence>(resp).await
+  }
+
   #[instrument(level = "info", skip_all, err)]
   pub async fn list_databases(
     &self,
@@ -239,6 +264,314 @@ impl Client {
     process_response_data::<String>(resp).await
   }
 
+  // Renames the field, and/or changes its type and type option.
+  pub async fn update_database_field(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    field_id: &str,
+    update_field: &AFUpdateDatabaseField,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/fields/{}",
+      self.base_url, workspace_id, database_id, field_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PATCH, &url)
+      .await?
+      .json(update_field)
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  // Moves the field within the view, or within all views if no view is given.
+  pub async fn move_database_field(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    field_id: &str,
+    move_field: &AFMoveDatabaseField,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/fields/{}/move",
+      self.base_url, workspace_id, database_id, field_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(move_field)
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn delete_database_field(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    field_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/fields/{}",
+      self.base_url, workspace_id, database_id, field_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn list_database_views(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+  ) -> Result<Vec<AFDatabaseView>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/view",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<Vec<AFDatabaseView>>(resp).await
+  }
+
+  pub async fn get_database_view(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    view_id: &str,
+  ) -> Result<AFDatabaseView, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/view/{}",
+      self.base_url, workspace_id, database_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseView>(resp).await
+  }
+
+  /// Replaces the name, hidden fields, filters, sorts or grouping of the database view.
+  /// Returns the updated view.
+  pub async fn update_database_view(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    view_id: &str,
+    update_view: &AFUpdateDatabaseView,
+  ) -> Result<AFDatabaseView, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/view/{}",
+      self.base_url, workspace_id, database_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PATCH, &url)
+      .await?
+      .json(update_view)
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseView>(resp).await
+  }
+
+  /// Returns the row level rule of the database. Only the owners of the workspace can manage
+  /// the rules.
+  pub async fn get_database_row_access(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+  ) -> Result<AFDatabaseRowAccessRule, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-access",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseRowAccessRule>(resp).await
+  }
+
+  /// Creates or replaces the row level rule of the database. Once set, the members who are not
+  /// owners only see the rows whose user field contains them, and the rows granted to them.
+  pub async fn update_database_row_access(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    params: &AFUpdateDatabaseRowAccessRule,
+  ) -> Result<AFDatabaseRowAccessRule, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-access",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseRowAccessRule>(resp).await
+  }
+
+  /// Removes the row level rule of the database and its grants.
+  pub async fn delete_database_row_access(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-access",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn add_database_row_grants(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    grants: &AFDatabaseRowGrants,
+  ) -> Result<AFDatabaseRowAccessRule, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-access/grants",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(grants)
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseRowAccessRule>(resp).await
+  }
+
+  pub async fn remove_database_row_grants(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    grants: &AFDatabaseRowGrants,
+  ) -> Result<AFDatabaseRowAccessRule, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-access/grants",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .json(grants)
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseRowAccessRule>(resp).await
+  }
+
+  pub async fn list_database_row_templates(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+  ) -> Result<Vec<AFDatabaseRowTemplate>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-template",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<Vec<AFDatabaseRowTemplate>>(resp).await
+  }
+
+  /// Creates a row template. When the template has a schedule, e.g. `0 9 * * 1` for every Monday
+  /// at 09:00 UTC, a row is inserted from the template on each run.
+  pub async fn create_database_row_template(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    params: &AFCreateDatabaseRowTemplate,
+  ) -> Result<AFDatabaseRowTemplate, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-template",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseRowTemplate>(resp).await
+  }
+
+  pub async fn update_database_row_template(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    template_id: &str,
+    params: &AFUpdateDatabaseRowTemplate,
+  ) -> Result<AFDatabaseRowTemplate, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-template/{}",
+      self.base_url, workspace_id, database_id, template_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PATCH, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_data::<AFDatabaseRowTemplate>(resp).await
+  }
+
+  pub async fn delete_database_row_template(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    template_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-template/{}",
+      self.base_url, workspace_id, database_id, template_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  /// Inserts a row from the template and returns the id of the row.
+  pub async fn add_database_row_from_template(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    template_id: &str,
+  ) -> Result<String, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row-template/{}/row",
+      self.base_url, workspace_id, database_id, template_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<String>(resp).await
+  }
+
   pub async fn list_database_row_ids_updated(
     &self,
     workspace_id: &Uuid,
@@ -258,6 +591,27 @@ impl Client {
     process_response_data::<Vec<DatabaseRowUpdatedItem>>(resp).await
   }
 
+  /// Returns the changes of the rows of the database after the cursor of the given param.
+  /// Pass the returned cursor to the next call to only get the new changes.
+  pub async fn list_database_row_changes(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    param: &ListDatabaseRowChangesParam,
+  ) -> Result<DatabaseRowChangesResponse, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/changes",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(param)
+      .send()
+      .await?;
+    process_response_data::<DatabaseRowChangesResponse>(resp).await
+  }
+
   pub async fn list_database_row_details(
     &self,
     workspace_id: &Uuid,
@@ -278,6 +632,51 @@ impl Client {
     process_response_data::<Vec<AFDatabaseRowDetail>>(resp).await
   }
 
+  /// Like [Self::list_database_row_details], with the cells of the relation fields expanded to
+  /// the related rows (see `AFRelatedDatabaseRow`). The related rows have their primary field,
+  /// and the `relation_fields` found in their database.
+  pub async fn list_database_row_details_with_relations(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    row_ids: &[&str],
+    with_doc: bool,
+    relation_fields: &[&str],
+  ) -> Result<Vec<AFDatabaseRowDetail>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/detail",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&ListDatabaseRowDetailParam::new(row_ids, with_doc).with_relations(relation_fields))
+      .send()
+      .await?;
+    process_response_data::<Vec<AFDatabaseRowDetail>>(resp).await
+  }
+
+  /// Returns the rows matching the filters, in the order of the sorts. Fetch the next page by
+  /// setting the `cursor` of the params to the `next_cursor` of the response.
+  pub async fn query_database_rows(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    params: &QueryDatabaseRowsParams,
+  ) -> Result<QueryDatabaseRowsResponse, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/query",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_data::<QueryDatabaseRowsResponse>(resp).await
+  }
+
   /// Example payload:
   /// {
   ///   "Name": "some_data",        # using column name
@@ -335,6 +734,95 @@ impl Client {
     process_response_data::<String>(resp).await
   }
 
+  /// Removes the row from all the views of the database and deletes the row.
+  pub async fn delete_database_item(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    row_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/{}",
+      self.base_url, workspace_id, database_id, row_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  /// Exports the rows of the database view, or of the inline view if unset, as CSV.
+  pub async fn export_database_csv(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    query: &ExportDatabaseCsvQuery,
+  ) -> Result<String, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/csv",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(query)
+      .send()
+      .await?;
+    if resp.status().is_success() {
+      Ok(resp.text().await?)
+    } else {
+      process_response_data::<String>(resp).await
+    }
+  }
+
+  /// Imports the CSV into the database, matching the columns to the fields by name.
+  pub async fn import_database_csv(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    query: &ImportDatabaseCsvQuery,
+    csv: String,
+  ) -> Result<ImportDatabaseCsvResponse, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/csv",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .query(query)
+      .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+      .body(csv)
+      .send()
+      .await?;
+    process_response_data::<ImportDatabaseCsvResponse>(resp).await
+  }
+
+  /// Inserts, upserts and deletes up to [MAX_DATABASE_ROW_BATCH_SIZE] rows in a single request.
+  /// Upon success, returns the row id of each operation, in the order of the operations.
+  ///
+  /// [MAX_DATABASE_ROW_BATCH_SIZE]: client_api_entity::workspace_dto::MAX_DATABASE_ROW_BATCH_SIZE
+  pub async fn batch_database_items(
+    &self,
+    workspace_id: &Uuid,
+    database_id: &str,
+    params: &BatchDatabaseRowsParams,
+  ) -> Result<BatchDatabaseRowsResponse, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/batch",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_data::<BatchDatabaseRowsResponse>(resp).await
+  }
+
   #[instrument(level = "debug", skip_all, err)]
   pub async fn post_realtime_msg(
     &self,
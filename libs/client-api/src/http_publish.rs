@@ -1,12 +1,18 @@
 use crate::{process_response_data, process_response_error, Client};
 use bytes::Bytes;
 use client_api_entity::publish_dto::DuplicatePublishedPageResponse;
-use client_api_entity::workspace_dto::{PublishInfoView, PublishedView};
+use client_api_entity::workspace_dto::{
+  AddPublishDomainParams, PublishDomain, PublishDomainResolution, PublishInfoView,
+  PublishedCollabAccess, PublishedCollabAccessParams, PublishedSiteExport, PublishedView,
+};
 use client_api_entity::{workspace_dto::PublishedDuplicate, PublishInfo, UpdatePublishNamespace};
 use client_api_entity::{
-  CreateGlobalCommentParams, CreateReactionParams, DeleteGlobalCommentParams, DeleteReactionParams,
-  GetReactionQueryParams, GlobalComments, PatchPublishedCollab, PublishInfoMeta, Reactions,
-  UpdateDefaultPublishView,
+  BlockPublishedCommenterParams, CreateGlobalCommentParams, CreateReactionParams,
+  DeleteGlobalCommentParams, DeleteReactionParams, GetReactionQueryParams, GlobalComments,
+  PatchPublishedCollab, PublishAnalyticsQueryParams, PublishInfoMeta, PublishNamespaceAnalytics,
+  PublishedCommentBlockedUser, PublishedCommentModerationItem,
+  PublishedCommentModerationQueryParams, PublishedCommentSetting, PublishedCommentStatus,
+  PublishedViewAnalytics, Reactions, UpdateDefaultPublishView, UpdatePublishedCommentStatusParams,
 };
 use reqwest::Method;
 use shared_entity::response::AppResponseError;
@@ -191,6 +197,133 @@ impl Client {
     process_response_error(resp).await
   }
 
+  /// Moderation queue listing the most recent comments on the pages published by the workspace.
+  pub async fn list_published_comments_for_moderation(
+    &self,
+    workspace_id: &Uuid,
+    params: &PublishedCommentModerationQueryParams,
+  ) -> Result<Vec<PublishedCommentModerationItem>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(params)
+      .send()
+      .await?;
+    process_response_data::<Vec<PublishedCommentModerationItem>>(resp).await
+  }
+
+  pub async fn update_published_comment_status(
+    &self,
+    workspace_id: &Uuid,
+    comment_id: &Uuid,
+    status: PublishedCommentStatus,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment/{}/status",
+      self.base_url, workspace_id, comment_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&UpdatePublishedCommentStatusParams { status })
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn get_published_comment_setting(
+    &self,
+    workspace_id: &Uuid,
+  ) -> Result<PublishedCommentSetting, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment-setting",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<PublishedCommentSetting>(resp).await
+  }
+
+  pub async fn update_published_comment_setting(
+    &self,
+    workspace_id: &Uuid,
+    setting: &PublishedCommentSetting,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment-setting",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(setting)
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn list_published_comment_blocked_users(
+    &self,
+    workspace_id: &Uuid,
+  ) -> Result<Vec<PublishedCommentBlockedUser>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment-blocklist",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<Vec<PublishedCommentBlockedUser>>(resp).await
+  }
+
+  pub async fn block_published_commenter(
+    &self,
+    workspace_id: &Uuid,
+    user_uuid: &Uuid,
+    reason: Option<String>,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment-blocklist",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&BlockPublishedCommenterParams {
+        user_uuid: *user_uuid,
+        reason,
+      })
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn unblock_published_commenter(
+    &self,
+    workspace_id: &Uuid,
+    user_uuid: &Uuid,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-comment-blocklist/{}",
+      self.base_url, workspace_id, user_uuid
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
   pub async fn set_default_publish_view(
     &self,
     workspace_id: &Uuid,
@@ -240,6 +373,150 @@ impl Client {
       .await?;
     process_response_data::<PublishInfo>(resp).await
   }
+
+  /// Returns the view stats of all pages published under the workspace namespace, for the past
+  /// `days` days. Only available to workspace owners.
+  pub async fn get_publish_namespace_analytics(
+    &self,
+    workspace_id: &Uuid,
+    days: Option<u32>,
+  ) -> Result<PublishNamespaceAnalytics, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-analytics",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&PublishAnalyticsQueryParams { days })
+      .send()
+      .await?;
+    process_response_data::<PublishNamespaceAnalytics>(resp).await
+  }
+
+  pub async fn get_published_view_analytics(
+    &self,
+    workspace_id: &Uuid,
+    view_id: &Uuid,
+    days: Option<u32>,
+  ) -> Result<PublishedViewAnalytics, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-analytics/{}",
+      self.base_url, workspace_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&PublishAnalyticsQueryParams { days })
+      .send()
+      .await?;
+    process_response_data::<PublishedViewAnalytics>(resp).await
+  }
+
+  /// Requests a static HTML export of all the pages published under the workspace namespace.
+  /// Use [Client::get_published_site_export] to poll for its completion.
+  pub async fn create_published_site_export(
+    &self,
+    workspace_id: &Uuid,
+  ) -> Result<PublishedSiteExport, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/publish-export",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<PublishedSiteExport>(resp).await
+  }
+
+  pub async fn get_published_site_export(
+    &self,
+    workspace_id: &Uuid,
+    export_id: &Uuid,
+  ) -> Result<PublishedSiteExport, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/publish-export/{}",
+      self.base_url, workspace_id, export_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<PublishedSiteExport>(resp).await
+  }
+
+  pub async fn add_publish_domain(
+    &self,
+    workspace_id: &Uuid,
+    domain: &str,
+  ) -> Result<PublishDomain, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/publish-domain",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&AddPublishDomainParams {
+        domain: domain.to_string(),
+      })
+      .send()
+      .await?;
+    process_response_data::<PublishDomain>(resp).await
+  }
+
+  pub async fn list_publish_domains(
+    &self,
+    workspace_id: &Uuid,
+  ) -> Result<Vec<PublishDomain>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/publish-domain",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<Vec<PublishDomain>>(resp).await
+  }
+
+  pub async fn verify_publish_domain(
+    &self,
+    workspace_id: &Uuid,
+    domain: &str,
+  ) -> Result<PublishDomain, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/publish-domain/{}/verify",
+      self.base_url, workspace_id, domain
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<PublishDomain>(resp).await
+  }
+
+  pub async fn delete_publish_domain(
+    &self,
+    workspace_id: &Uuid,
+    domain: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/publish-domain/{}",
+      self.base_url, workspace_id, domain
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
 }
 
 // Optional login
@@ -279,6 +556,22 @@ impl Client {
     process_response_data::<PublishInfo>(resp).await
   }
 
+  /// Resolves a custom domain to the publish namespace served under it.
+  #[instrument(level = "debug", skip_all)]
+  pub async fn resolve_publish_domain(
+    &self,
+    host: &str,
+  ) -> Result<PublishDomainResolution, AppResponseError> {
+    let url = format!("{}/api/workspace/published-domain/resolve", self.base_url);
+    let resp = self
+      .cloud_client
+      .get(&url)
+      .query(&[("host", host)])
+      .send()
+      .await?;
+    process_response_data::<PublishDomainResolution>(resp).await
+  }
+
   #[instrument(level = "debug", skip_all)]
   pub async fn get_published_outline(
     &self,
@@ -352,6 +645,29 @@ impl Client {
   }
 
   #[instrument(level = "debug", skip_all)]
+  /// Unlocks a password protected published page. On success, the server responds with
+  /// an HTTP-only cookie, which grants access to the page until the returned expiration date.
+  pub async fn unlock_published_collab(
+    &self,
+    publish_namespace: &str,
+    publish_name: &str,
+    password: &str,
+  ) -> Result<PublishedCollabAccess, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/v1/published/{}/{}/access",
+      self.base_url, publish_namespace, publish_name
+    );
+    let resp = self
+      .cloud_client
+      .post(&url)
+      .json(&PublishedCollabAccessParams {
+        password: password.to_string(),
+      })
+      .send()
+      .await?;
+    process_response_data::<PublishedCollabAccess>(resp).await
+  }
+
   pub async fn get_published_collab_blob(
     &self,
     publish_namespace: &str,
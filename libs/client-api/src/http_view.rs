@@ -1,17 +1,20 @@
 use client_api_entity::workspace_dto::{
-  AddRecentPagesParams, AppendBlockToPageParams, CreateFolderViewParams,
-  CreatePageDatabaseViewParams, CreatePageParams, CreateSpaceParams, DuplicatePageParams,
-  FavoritePageParams, MovePageParams, Page, PageCollab, PublishPageParams, Space,
+  AddRecentPagesParams, AppendBlockToPageParams, CreateFolderViewParams, CreateMarkdownPageParams,
+  CreatePageDatabaseViewParams, CreatePageParams, CreateSpaceParams, DocumentBlock,
+  DuplicatePageParams, FavoritePageParams, InsertBlockParams, InsertBlockResponse, MoveBlockParams,
+  MovePageParams, Page, PageCollab, PublishPageParams, Space, UpdateBlockParams,
   UpdatePageExtraParams, UpdatePageIconParams, UpdatePageNameParams, UpdatePageParams,
   UpdateSpaceParams,
 };
-use reqwest::Method;
+use reqwest::{header, Method};
 use serde_json::json;
 use shared_entity::response::AppResponseError;
 use uuid::Uuid;
 
 use crate::{process_response_data, process_response_error, Client};
 
+const MARKDOWN_CONTENT_TYPE: &str = "text/markdown; charset=utf-8";
+
 impl Client {
   pub async fn create_folder_view(
     &self,
@@ -46,6 +49,25 @@ impl Client {
     process_response_data::<Page>(resp).await
   }
 
+  /// Creates a document page with the content of the Markdown text.
+  pub async fn create_workspace_page_view_from_markdown(
+    &self,
+    workspace_id: Uuid,
+    params: &CreateMarkdownPageParams,
+    markdown: &str,
+  ) -> Result<Page, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/page-view", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .query(params)
+      .header(header::CONTENT_TYPE, MARKDOWN_CONTENT_TYPE)
+      .body(markdown.to_string())
+      .send()
+      .await?;
+    process_response_data::<Page>(resp).await
+  }
+
   pub async fn favorite_page_view(
     &self,
     workspace_id: Uuid,
@@ -390,6 +412,122 @@ impl Client {
     process_response_error(resp).await
   }
 
+  /// Appends the content of the Markdown text at the end of the page.
+  pub async fn append_markdown_to_page(
+    &self,
+    workspace_id: Uuid,
+    view_id: &Uuid,
+    markdown: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/page-view/{}/append-block",
+      self.base_url, workspace_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .header(header::CONTENT_TYPE, MARKDOWN_CONTENT_TYPE)
+      .body(markdown.to_string())
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn get_page_block(
+    &self,
+    workspace_id: Uuid,
+    view_id: &Uuid,
+    block_id: &str,
+  ) -> Result<DocumentBlock, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/page-view/{}/block/{}",
+      self.base_url, workspace_id, view_id, block_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_data::<DocumentBlock>(resp).await
+  }
+
+  pub async fn insert_block_to_page(
+    &self,
+    workspace_id: Uuid,
+    view_id: &Uuid,
+    params: &InsertBlockParams,
+  ) -> Result<InsertBlockResponse, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/page-view/{}/block",
+      self.base_url, workspace_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_data::<InsertBlockResponse>(resp).await
+  }
+
+  pub async fn update_page_block(
+    &self,
+    workspace_id: Uuid,
+    view_id: &Uuid,
+    block_id: &str,
+    params: &UpdateBlockParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/page-view/{}/block/{}",
+      self.base_url, workspace_id, view_id, block_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PATCH, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn delete_page_block(
+    &self,
+    workspace_id: Uuid,
+    view_id: &Uuid,
+    block_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/page-view/{}/block/{}",
+      self.base_url, workspace_id, view_id, block_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
+  pub async fn move_page_block(
+    &self,
+    workspace_id: Uuid,
+    view_id: &Uuid,
+    block_id: &str,
+    params: &MoveBlockParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/page-view/{}/block/{}/move",
+      self.base_url, workspace_id, view_id, block_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(params)
+      .send()
+      .await?;
+    process_response_error(resp).await
+  }
+
   pub async fn create_database_view(
     &self,
     workspace_id: Uuid,
@@ -1,11 +1,13 @@
 use crate::v2::compactor::ChannelReceiverCompactor;
 use crate::v2::controller::{ConnectionStatus, DisconnectedReason, Options};
 use crate::v2::db::Db;
+use crate::v2::transport::{connect_http, http_endpoint, ConnectionSink, HttpSink, HttpStream};
 use crate::v2::ObjectId;
 use crate::{sync_debug, sync_error, sync_info, sync_trace, sync_warn};
 use app_error::AppError;
 use appflowy_proto::{
-  AccessChangedReason, ClientMessage, Rid, ServerMessage, UpdateFlags, WorkspaceNotification,
+  into_frames, AccessChangedReason, ChunkAssembler, ClientMessage, Rid, ServerMessage, UpdateFlags,
+  WorkspaceNotification,
 };
 use arc_swap::ArcSwap;
 use bytes::BytesMut;
@@ -14,20 +16,23 @@ use collab::core::collab_state::{InitState, State, SyncState};
 use collab::preclude::Collab;
 use collab_rt_protocol::{CollabRef, WeakCollabRef};
 use dashmap::DashMap;
-use futures_util::stream::{SplitSink, SplitStream};
-use futures_util::{SinkExt, StreamExt};
+use futures_util::stream::SplitStream;
+use futures_util::StreamExt;
 use shared_entity::response::AppResponseError;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Write};
 use std::hash::{Hash, Hasher};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 use tokio::select;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
-use tokio::time::{timeout, MissedTickBehavior};
+use tokio::time::{sleep_until, timeout, Instant, MissedTickBehavior};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{connect_async_with_config, MaybeTlsStream};
@@ -53,6 +58,11 @@ pub(super) struct WorkspaceControllerActor {
   notification_tx: tokio::sync::broadcast::Sender<WorkspaceNotification>,
   /// Used to record recently changed collabs
   changed_collab_sender: tokio::sync::broadcast::Sender<ChangedCollab>,
+  /// Identifier assigned to the next message, that has to be chunked before sending.
+  next_chunked_message_id: AtomicU64,
+  /// Set while HTTP fallback transport is used instead of WebSocket connection. WebSocket
+  /// connection is tried again once it's due.
+  http_fallback: std::sync::Mutex<Option<WebSocketRetry>>,
   #[cfg(debug_assertions)]
   pub skip_realtime_message: AtomicBool,
 }
@@ -61,6 +71,10 @@ impl WorkspaceControllerActor {
   const PING_INTERVAL: Duration = Duration::from_secs(4);
   const PING_TIMEOUT: Duration = Duration::from_secs(20);
   const REMOTE_ORIGIN: &'static str = "af";
+  /// Delay after which WebSocket connection is tried again, once HTTP fallback is used. It's
+  /// doubled after each failed attempt.
+  const WS_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(60);
+  const WS_RETRY_MAX_DELAY: Duration = Duration::from_secs(30 * 60);
 
   pub fn new(db: Db, options: Options, last_message_id: Rid) -> Arc<Self> {
     let (changed_collab_sender, _) = tokio::sync::broadcast::channel(10);
@@ -79,6 +93,8 @@ impl WorkspaceControllerActor {
       skip_realtime_message: AtomicBool::new(false),
       notification_tx,
       changed_collab_sender,
+      next_chunked_message_id: AtomicU64::new(0),
+      http_fallback: std::sync::Mutex::new(None),
     });
     tokio::spawn(Self::actor_loop(
       Arc::downgrade(&actor),
@@ -248,6 +264,50 @@ impl WorkspaceControllerActor {
     Ok(())
   }
 
+  /// Switches to HTTP fallback transport for the following connection attempts, until
+  /// WebSocket connection can be established again.
+  pub(crate) fn enable_http_fallback(&self) {
+    let mut http_fallback = self.http_fallback.lock().unwrap();
+    if http_fallback.is_none() {
+      sync_info!(
+        "[{}] switching to HTTP fallback transport",
+        self.db.client_id()
+      );
+      *http_fallback = Some(WebSocketRetry::new(Self::WS_RETRY_INITIAL_DELAY));
+    }
+  }
+
+  fn disable_http_fallback(&self) {
+    if self.http_fallback.lock().unwrap().take().is_some() {
+      sync_info!(
+        "[{}] WebSocket connection restored, leaving HTTP fallback transport",
+        self.db.client_id()
+      );
+    }
+  }
+
+  /// Returns the time of the next WebSocket connection attempt, if HTTP fallback is used.
+  fn websocket_retry_at(&self) -> Option<Instant> {
+    self
+      .http_fallback
+      .lock()
+      .unwrap()
+      .as_ref()
+      .map(|retry| retry.retry_at)
+  }
+
+  /// Postpones the next WebSocket connection attempt, with exponential backoff. Returns false if
+  /// HTTP fallback is not used.
+  fn postpone_websocket_retry(&self) -> bool {
+    match self.http_fallback.lock().unwrap().as_mut() {
+      Some(retry) => {
+        *retry = WebSocketRetry::new((retry.delay * 2).min(Self::WS_RETRY_MAX_DELAY));
+        true
+      },
+      None => false,
+    }
+  }
+
   #[instrument(level = "trace", skip_all)]
   pub(crate) fn set_connection_status(&self, status: ConnectionStatus) {
     sync_info!("set connection status: {:?}", status);
@@ -256,9 +316,7 @@ impl WorkspaceControllerActor {
 
   async fn ping(&self) -> anyhow::Result<()> {
     if let Some(conn) = self.ws_sink() {
-      let mut lock = conn.lock().await;
-      lock.send(Message::Ping(Vec::new())).await?;
-      lock.flush().await?;
+      conn.ping().await?;
     }
     Ok(())
   }
@@ -412,14 +470,16 @@ impl WorkspaceControllerActor {
         Some((*object_id, SyncState::InitSyncBegin))
       },
       ClientMessage::Update { object_id, .. } => Some((*object_id, SyncState::SyncFinished)),
-      ClientMessage::AwarenessUpdate { .. } => None,
+      ClientMessage::AwarenessUpdate { .. }
+      | ClientMessage::Chunk(_)
+      | ClientMessage::Auth { .. } => None,
     };
     if let Some(sink) = self.ws_sink() {
       sync_debug!("[{}] sending message: {:?}", self.db.client_id(), msg);
       {
         let bytes = msg.into_bytes()?;
-        let mut sink = sink.lock().await;
-        sink.send(Message::Binary(bytes)).await?;
+        let message_id = self.next_chunked_message_id.fetch_add(1, Ordering::Relaxed);
+        sink.send(into_frames(message_id, bytes)).await?;
       }
       if let Some((object_id, sync_state)) = sync_state {
         self.set_collab_sync_state(&object_id, sync_state).await;
@@ -484,14 +544,53 @@ impl WorkspaceControllerActor {
 
     let last_message_id = actor.last_message_id.load_full();
     let client_id = actor.db.client_id();
-    let result = Self::establish_connection(
-      &actor.options,
-      client_id,
-      &last_message_id,
-      cancel.clone(),
-      access_token,
-    )
-    .await?;
+    let websocket_due = actor
+      .websocket_retry_at()
+      .map(|retry_at| Instant::now() >= retry_at)
+      .unwrap_or(true);
+    let websocket = if websocket_due {
+      let result = Self::establish_connection(
+        &actor.options,
+        client_id,
+        &last_message_id,
+        cancel.clone(),
+        access_token.clone(),
+      )
+      .await;
+      match result {
+        Ok(connection) => {
+          if connection.is_some() {
+            actor.disable_http_fallback();
+          }
+          Some(connection.map(Transport::WebSocket))
+        },
+        // WebSocket is still unavailable, the connection falls back to HTTP right away
+        Err(err) if actor.postpone_websocket_retry() => {
+          sync_warn!(
+            "[{}] WebSocket connection failed, using HTTP fallback: {}",
+            client_id,
+            err
+          );
+          None
+        },
+        Err(err) => return Err(err),
+      }
+    } else {
+      None
+    };
+    let result = match websocket {
+      Some(result) => result,
+      None => {
+        Self::establish_http_connection(
+          &actor.options,
+          client_id,
+          &last_message_id,
+          cancel.clone(),
+          access_token,
+        )
+        .await?
+      },
+    };
 
     match result {
       None => {
@@ -502,10 +601,10 @@ impl WorkspaceControllerActor {
           )),
         });
       },
-      Some(connection) => {
+      Some(Transport::WebSocket(connection)) => {
         sync_info!("[{}] connected to {}", client_id, actor.options.url);
         let (sink, stream) = connection.split();
-        let sink = Arc::new(Mutex::new(sink));
+        let sink = ConnectionSink::WebSocket(Arc::new(Mutex::new(sink)));
         actor.set_connection_status(ConnectionStatus::Connected {
           sink,
           cancel: cancel.clone(),
@@ -520,6 +619,26 @@ impl WorkspaceControllerActor {
           cancel,
         ));
       },
+      Some(Transport::Http(sink, stream)) => {
+        sync_info!(
+          "[{}] connected to {} using HTTP fallback",
+          client_id,
+          actor.options.url
+        );
+        actor.set_connection_status(ConnectionStatus::Connected {
+          sink: ConnectionSink::Http(Arc::new(sink)),
+          cancel: cancel.clone(),
+        });
+
+        if let Err(err) = actor.publish_pending_collabs().await {
+          sync_error!("failed to publish pending collabs: {}", err);
+        }
+        tokio::spawn(Self::http_receiver_task(
+          Arc::downgrade(actor),
+          stream,
+          cancel,
+        ));
+      },
     }
     Ok(())
   }
@@ -544,12 +663,72 @@ impl WorkspaceControllerActor {
     }
   }
 
+  async fn http_receiver_task(
+    weak_actor: Weak<WorkspaceControllerActor>,
+    stream: HttpStream,
+    cancel: CancellationToken,
+  ) {
+    sync_debug!("http receiver task started");
+    let reason = Self::http_receiver_loop(weak_actor.clone(), stream, cancel.clone())
+      .await
+      .err();
+
+    if let Some(actor) = weak_actor.upgrade() {
+      sync_error!("failed to receive messages from server: {:?}", reason);
+      actor.set_connection_status(ConnectionStatus::Disconnected { reason });
+    }
+  }
+
+  async fn http_receiver_loop(
+    weak_actor: Weak<WorkspaceControllerActor>,
+    mut stream: HttpStream,
+    cancel: CancellationToken,
+  ) -> Result<(), DisconnectedReason> {
+    let mut chunks = ChunkAssembler::default();
+    // the session is ended when WebSocket connection is due to be tried again
+    let websocket_retry_at = weak_actor
+      .upgrade()
+      .and_then(|actor| actor.websocket_retry_at())
+      .unwrap_or_else(|| Instant::now() + Self::WS_RETRY_INITIAL_DELAY);
+    loop {
+      let bytes = select! {
+        _ = cancel.cancelled() => {
+          sync_trace!("http receiver loop cancelled");
+          return Err(DisconnectedReason::UserDisconnect("User disconnect".into()));
+        },
+        _ = sleep_until(websocket_retry_at) => {
+          return Err(DisconnectedReason::Unexpected("retrying WebSocket connection".into()));
+        },
+        res = stream.next() => match res {
+          Some(res) => res?,
+          // unlike WebSocket, event stream can be closed by proxies at any time
+          None => return Err(DisconnectedReason::Unexpected("event stream closed".into())),
+        },
+      };
+      let actor = match weak_actor.upgrade() {
+        Some(inner) => inner,
+        None => {
+          sync_trace!("http receiver loop ended - actor dropped");
+          return Ok(());
+        },
+      };
+      sync_trace!("[HttpMessage] received binary: len:{}", bytes.len());
+      if let Some(msg) = Self::decode_server_message(&mut chunks, &bytes)? {
+        actor
+          .handle_receive(msg)
+          .await
+          .map_err(|err| DisconnectedReason::CannotHandleReceiveMessage(err.to_string().into()))?;
+      }
+    }
+  }
+
   async fn remote_receiver_loop(
     weak_actor: Weak<WorkspaceControllerActor>,
     mut stream: SplitStream<WsConn>,
     cancel: CancellationToken,
   ) -> Result<(), DisconnectedReason> {
     let mut buf = BytesMut::new();
+    let mut chunks = ChunkAssembler::default();
     while let Some(res) = stream.next().await {
       if cancel.is_cancelled() {
         sync_trace!("remote receiver loop cancelled");
@@ -576,10 +755,11 @@ impl WorkspaceControllerActor {
       match msg {
         Message::Binary(bytes) => {
           sync_trace!("[WsMessage] received binary: len:{}", bytes.len());
-          let msg = ServerMessage::from_bytes(&bytes)?;
-          actor.handle_receive(msg).await.map_err(|err| {
-            DisconnectedReason::CannotHandleReceiveMessage(err.to_string().into())
-          })?;
+          if let Some(msg) = Self::decode_server_message(&mut chunks, &bytes)? {
+            actor.handle_receive(msg).await.map_err(|err| {
+              DisconnectedReason::CannotHandleReceiveMessage(err.to_string().into())
+            })?;
+          }
         },
         Message::Text(_) => {
           sync_error!("text messages are not supported");
@@ -595,10 +775,11 @@ impl WorkspaceControllerActor {
               frame.len(),
               bytes.len()
             );
-            let msg = ServerMessage::from_bytes(&bytes)?;
-            actor.handle_receive(msg).await.map_err(|err| {
-              DisconnectedReason::CannotHandleReceiveMessage(err.to_string().into())
-            })?;
+            if let Some(msg) = Self::decode_server_message(&mut chunks, &bytes)? {
+              actor.handle_receive(msg).await.map_err(|err| {
+                DisconnectedReason::CannotHandleReceiveMessage(err.to_string().into())
+              })?;
+            }
           } else {
             sync_trace!("[WsMessage] received frame: len:{}", frame.len());
           }
@@ -606,6 +787,20 @@ impl WorkspaceControllerActor {
         Message::Close(close) => {
           match close {
             None => sync_info!("received close request from server"),
+            // server closes the connection once access token expires or turns out to be invalid
+            Some(frame) if frame.code == CloseCode::Policy => {
+              sync_info!("connection closed by server: {}", frame.reason);
+              return Err(DisconnectedReason::Unauthorized(
+                frame.reason.to_string().into(),
+              ));
+            },
+            // server asks to reconnect later, i.e. when the client exceeded its rate limits
+            Some(frame) if frame.code == CloseCode::Again => {
+              sync_info!("connection closed by server: {}", frame.reason);
+              return Err(DisconnectedReason::Unexpected(
+                frame.reason.to_string().into(),
+              ));
+            },
             Some(frame) => sync_info!(
               "received close request from server: {} - {}",
               frame.code,
@@ -620,6 +815,29 @@ impl WorkspaceControllerActor {
     Ok(())
   }
 
+  /// Decodes a message received from the server. Chunked messages are reassembled first,
+  /// in which case `None` is returned until the last chunk arrives.
+  fn decode_server_message(
+    chunks: &mut ChunkAssembler,
+    bytes: &[u8],
+  ) -> Result<Option<ServerMessage>, appflowy_proto::Error> {
+    match ServerMessage::from_bytes(bytes)? {
+      ServerMessage::Chunk(chunk) => {
+        sync_trace!("[WsMessage] received chunk: {:?}", chunk);
+        match chunks.push(chunk)? {
+          None => Ok(None),
+          Some(bytes) => match ServerMessage::from_bytes(&bytes)? {
+            ServerMessage::Chunk(_) => Err(appflowy_proto::Error::InvalidChunk(
+              "nested message chunks are not supported",
+            )),
+            msg => Ok(Some(msg)),
+          },
+        }
+      },
+      msg => Ok(Some(msg)),
+    }
+  }
+
   async fn handle_receive(&self, msg: ServerMessage) -> anyhow::Result<()> {
     match msg {
       ServerMessage::Manifest {
@@ -710,6 +928,12 @@ impl WorkspaceControllerActor {
         sync_info!("received notification: {:?}", notification);
         self.send_notification(notification).await;
       },
+      ServerMessage::Chunk(chunk) => {
+        anyhow::bail!(
+          "received message chunk that was not reassembled: {:?}",
+          chunk
+        );
+      },
     }
     Ok(())
   }
@@ -718,6 +942,7 @@ impl WorkspaceControllerActor {
     sync_trace!("Receive server notification: {:?}", notification);
     match &notification {
       WorkspaceNotification::UserProfileChange { .. } => {},
+      WorkspaceNotification::CollabPresenceChanged { .. } => {},
       WorkspaceNotification::ObjectAccessChanged { object_id, reason } => {
         if matches!(reason, AccessChangedReason::ObjectDeleted) {
           self.unbind(object_id).await;
@@ -1034,8 +1259,6 @@ impl WorkspaceControllerActor {
       ConnectionStatus::Connected { sink, cancel } => {
         cancel.cancel();
         {
-          let mut sink = sink.lock().await;
-          sink.flush().await?;
           sink.close().await?;
         }
         Ok(())
@@ -1109,31 +1332,72 @@ impl WorkspaceControllerActor {
     self.trigger(WorkspaceAction::Send(msg, ActionSource::Local));
   }
 
-  fn ws_sink(&self) -> Option<Arc<Mutex<SplitSink<WsConn, Message>>>> {
+  fn ws_sink(&self) -> Option<ConnectionSink> {
     match &*self.status_rx.borrow() {
       ConnectionStatus::Connected { sink, .. } => Some(sink.clone()),
       _ => None,
     }
   }
 
-  async fn establish_connection(
+  fn connection_url(
+    base_url: &str,
     options: &Options,
     client_id: ClientID,
     last_message_id: &Rid,
-    cancel: CancellationToken,
-    access_token: String,
-  ) -> Result<Option<WsConn>, AppResponseError> {
+  ) -> String {
+    // access token is passed in request headers, so that it doesn't end up in proxy logs
     let mut url = format!(
-      "{}/{}?clientId={}&deviceId={}",
-      options.url, options.workspace_id, client_id, options.device_id
+      "{}?clientId={}&deviceId={}&supportsChunking=true",
+      base_url, client_id, options.device_id
     );
-    sync_info!("establishing WebSocket connection to: {}", url);
-    // don't include auth token in the log message (or maybe it doesn't matter?)
-    write!(url, "&token={}", access_token).unwrap();
     if options.sync_eagerly {
       write!(url, "&lastMessageId={}", last_message_id).unwrap();
     }
-    let req = url.into_client_request()?;
+    sync_info!("establishing connection to: {}", url);
+    url
+  }
+
+  async fn establish_http_connection(
+    options: &Options,
+    client_id: ClientID,
+    last_message_id: &Rid,
+    cancel: CancellationToken,
+    access_token: String,
+  ) -> Result<Option<Transport>, AppResponseError> {
+    let base_url = format!(
+      "{}/{}/sse",
+      http_endpoint(&options.url),
+      options.workspace_id
+    );
+    let events_url = Self::connection_url(&base_url, options, client_id, last_message_id);
+    let messages_url = format!("{}/{}", base_url, client_id);
+    tokio::select! {
+      res = connect_http(&events_url, messages_url, access_token) => {
+        let (sink, stream) = res?;
+        sync_info!("establishing HTTP fallback connection successfully to {}", options.workspace_id);
+        Ok(Some(Transport::Http(sink, stream)))
+      }
+      _ = cancel.cancelled() => {
+        sync_info!("establishing connection cancelled for {}", options.workspace_id);
+        Ok(None)
+      }
+    }
+  }
+
+  async fn establish_connection(
+    options: &Options,
+    client_id: ClientID,
+    last_message_id: &Rid,
+    cancel: CancellationToken,
+    access_token: String,
+  ) -> Result<Option<WsConn>, AppResponseError> {
+    let base_url = format!("{}/{}", options.url, options.workspace_id);
+    let url = Self::connection_url(&base_url, options, client_id, last_message_id);
+    let mut req = url.into_client_request()?;
+    let protocols = format!("{}, {}", SYNC_PROTOCOL, access_token);
+    let protocols = HeaderValue::from_str(&protocols)
+      .map_err(|err| AppError::InvalidRequest(format!("invalid access token: {}", err)))?;
+    req.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, protocols);
     let config = WebSocketConfig {
       max_frame_size: None,
       ..WebSocketConfig::default()
@@ -1203,6 +1467,28 @@ impl From<Option<Rid>> for ActionSource {
   }
 }
 
+/// Next WebSocket connection attempt while HTTP fallback transport is used.
+#[derive(Debug, Clone, Copy)]
+struct WebSocketRetry {
+  retry_at: Instant,
+  delay: Duration,
+}
+
+impl WebSocketRetry {
+  fn new(delay: Duration) -> Self {
+    Self {
+      retry_at: Instant::now() + delay,
+      delay,
+    }
+  }
+}
+
+/// Connection established with the server using one of the supported transports.
+enum Transport {
+  WebSocket(WsConn),
+  Http(HttpSink, HttpStream),
+}
+
 pub(super) type WsConn = tokio_tungstenite::WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
 
 pub(super) type WorkspaceControllerMailbox = tokio::sync::mpsc::UnboundedSender<WorkspaceAction>;
@@ -1253,6 +1539,9 @@ impl std::borrow::Borrow<ObjectId> for ChangedCollab {
 
 const OBSERVER_KEY: &str = "af";
 
+/// WebSocket subprotocol used to pass access token in `Sec-WebSocket-Protocol` header.
+const SYNC_PROTOCOL: &str = "appflowy-sync-v2";
+
 fn unobserve_awareness(awareness: &Awareness) {
   awareness.unobserve_change(OBSERVER_KEY);
 }
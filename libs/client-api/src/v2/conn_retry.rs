@@ -1,6 +1,7 @@
 use crate::v2::actor::WorkspaceControllerActor;
 use crate::v2::controller::{ConnectionStatus, DisconnectedReason};
 use crate::{sync_error, sync_info, sync_trace};
+use app_error::ErrorCode;
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use shared_entity::response::AppResponseError;
@@ -16,6 +17,10 @@ pub trait ReconnectTarget: Send + Sync {
   async fn attempt_connect(self: Arc<Self>, token: String) -> Result<(), AppResponseError>;
 
   fn set_disconnected(&self, reason: DisconnectedReason);
+
+  /// Switches the target to the fallback transport, used when WebSocket connection cannot be
+  /// established (eg. because of proxies or firewalls not supporting it).
+  fn enable_fallback_transport(&self);
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +31,9 @@ pub struct RetryConfig {
   pub max_delay: Duration,
   /// Maximum number of reconnect attempts before giving up.
   pub max_attempts: u32,
+  /// Number of consecutive failed connection attempts, after which the client switches
+  /// to HTTP fallback transport.
+  pub fallback_after: u32,
 }
 
 impl Default for RetryConfig {
@@ -34,6 +42,7 @@ impl Default for RetryConfig {
       initial_delay: Duration::from_secs(5),
       max_delay: Duration::from_secs(120),
       max_attempts: 5,
+      fallback_after: 3,
     }
   }
 }
@@ -110,6 +119,7 @@ impl ReconnectionManager {
     token: String,
   ) -> bool {
     let mut delay = self.config.initial_delay;
+    let mut failures = 0;
     for attempt in 1..=self.config.max_attempts {
       sync_trace!(attempt, ?delay, "waiting before reconnect");
       sleep(delay).await;
@@ -135,6 +145,13 @@ impl ReconnectionManager {
         },
         Err(err) => {
           sync_error!(attempt, %err, "reconnect attempt failed");
+          // authorization errors won't be fixed by switching the transport
+          if err.code != ErrorCode::UserUnAuthorized {
+            failures += 1;
+            if failures == self.config.fallback_after {
+              target.enable_fallback_transport();
+            }
+          }
           let reason = DisconnectedReason::from(err);
           target.set_disconnected(reason);
         },
@@ -161,7 +178,7 @@ mod tests {
   use shared_entity::response::AppResponseError;
   use std::{
     sync::{
-      atomic::{AtomicUsize, Ordering},
+      atomic::{AtomicBool, AtomicUsize, Ordering},
       Arc,
     },
     time::Duration,
@@ -180,6 +197,7 @@ mod tests {
     call_count: Arc<AtomicUsize>,
     responses: Arc<tokio::sync::Mutex<Vec<Result<(), AppResponseError>>>>,
     last_disconnect_reason: Arc<tokio::sync::Mutex<Option<DisconnectedReason>>>,
+    fallback_enabled: Arc<AtomicBool>,
   }
 
   impl FakeTarget {
@@ -196,6 +214,7 @@ mod tests {
           call_count: call_count.clone(),
           responses: Arc::new(tokio::sync::Mutex::new(responses)),
           last_disconnect_reason: Arc::new(tokio::sync::Mutex::new(None)),
+          fallback_enabled: Arc::new(AtomicBool::new(false)),
         },
         call_count,
       )
@@ -236,6 +255,10 @@ mod tests {
         reason: Some(reason),
       });
     }
+
+    fn enable_fallback_transport(&self) {
+      self.fallback_enabled.store(true, Ordering::SeqCst);
+    }
   }
 
   #[tokio::test]
@@ -251,6 +274,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(1),
         max_attempts: 3,
+        fallback_after: 3,
       },
     );
 
@@ -288,6 +312,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(2),
         max_attempts: 5,
+        fallback_after: 3,
       },
     );
 
@@ -324,6 +349,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(1),
         max_attempts: 2,
+        fallback_after: 3,
       },
     );
 
@@ -349,6 +375,73 @@ mod tests {
     ));
   }
 
+  #[tokio::test]
+  async fn test_fallback_transport_enabled_after_failures() {
+    let responses = vec![
+      Err(AppResponseError {
+        code: ErrorCode::NetworkError,
+        message: "failure 1".into(),
+      }),
+      Err(AppResponseError {
+        code: ErrorCode::NetworkError,
+        message: "failure 2".into(),
+      }),
+      Ok(()),
+    ];
+    let (fake_target, call_count) =
+      FakeTarget::new(ConnectionStatus::Disconnected { reason: None }, responses);
+    let target: Arc<dyn ReconnectTarget + Send + Sync> = Arc::new(fake_target.clone());
+    let manager = ReconnectionManager::with_config_for_target(
+      target.clone(),
+      RetryConfig {
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        max_attempts: 3,
+        fallback_after: 2,
+      },
+    );
+
+    let result = manager
+      .retry_with_exponential_backoff(target.clone(), "test_token".into())
+      .await;
+
+    assert!(
+      result,
+      "Reconnection should succeed using fallback transport"
+    );
+    assert_eq!(call_count.load(Ordering::SeqCst), 3);
+    assert!(fake_target.fallback_enabled.load(Ordering::SeqCst));
+  }
+
+  #[tokio::test]
+  async fn test_fallback_transport_not_enabled_on_unauthorized() {
+    let responses = vec![
+      Err(AppResponseError {
+        code: ErrorCode::UserUnAuthorized,
+        message: "unauthorized".into(),
+      }),
+      Ok(()),
+    ];
+    let (fake_target, _) =
+      FakeTarget::new(ConnectionStatus::Disconnected { reason: None }, responses);
+    let target: Arc<dyn ReconnectTarget + Send + Sync> = Arc::new(fake_target.clone());
+    let manager = ReconnectionManager::with_config_for_target(
+      target.clone(),
+      RetryConfig {
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        max_attempts: 2,
+        fallback_after: 1,
+      },
+    );
+
+    let _ = manager
+      .retry_with_exponential_backoff(target.clone(), "test_token".into())
+      .await;
+
+    assert!(!fake_target.fallback_enabled.load(Ordering::SeqCst));
+  }
+
   #[tokio::test]
   async fn test_aborts_when_already_connecting() {
     let (fake_target, call_count) = FakeTarget::new(
@@ -364,6 +457,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(1),
         max_attempts: 3,
+        fallback_after: 3,
       },
     );
 
@@ -395,6 +489,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(1),
         max_attempts: 3,
+        fallback_after: 3,
       },
     );
 
@@ -423,6 +518,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -479,6 +575,7 @@ mod tests {
         initial_delay: Duration::from_millis(50), // Longer delay to test concurrency
         max_delay: Duration::from_millis(50),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -521,6 +618,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(100),
         max_attempts: 3,
+        fallback_after: 3,
       },
     );
 
@@ -556,6 +654,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -595,6 +694,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -630,6 +730,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -665,6 +766,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     // Don't set access token
@@ -698,6 +800,7 @@ mod tests {
         initial_delay: Duration::from_millis(5),
         max_delay: Duration::from_millis(5),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -747,6 +850,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(1),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -783,6 +887,7 @@ mod tests {
         initial_delay: Duration::from_millis(50), // Long enough to change status during sleep
         max_delay: Duration::from_millis(50),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -851,6 +956,7 @@ mod tests {
         initial_delay: Duration::from_millis(20),
         max_delay: Duration::from_millis(20),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -885,6 +991,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -923,6 +1030,7 @@ mod tests {
           initial_delay: Duration::from_millis(1),
           max_delay: Duration::from_millis(1),
           max_attempts: 1,
+          fallback_after: 3,
         },
       );
 
@@ -1003,6 +1111,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(25), // Should cap at this value
         max_attempts: 5,
+        fallback_after: 3,
       },
     );
 
@@ -1045,6 +1154,7 @@ mod tests {
         initial_delay: Duration::from_millis(5),
         max_delay: Duration::from_millis(5),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -1089,6 +1199,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -1208,6 +1319,7 @@ mod tests {
         initial_delay: Duration::from_millis(0), // Zero delay
         max_delay: Duration::from_millis(100),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -1248,6 +1360,7 @@ mod tests {
         initial_delay: Duration::from_millis(50),
         max_delay: Duration::from_millis(10), // Smaller than initial
         max_attempts: 3,
+        fallback_after: 3,
       },
     );
 
@@ -1274,6 +1387,7 @@ mod tests {
         initial_delay: Duration::from_millis(1),
         max_delay: Duration::from_millis(1),
         max_attempts: 1,
+        fallback_after: 3,
       },
     );
 
@@ -1303,6 +1417,7 @@ mod tests {
         initial_delay: Duration::from_millis(10),
         max_delay: Duration::from_millis(10),
         max_attempts: 1,
+        fallback_after: 3,
       },
     ));
     manager.set_access_token("test_token".into());
@@ -1352,6 +1467,7 @@ mod tests {
           initial_delay: Duration::from_millis(5),
           max_delay: Duration::from_millis(5),
           max_attempts: 1,
+          fallback_after: 3,
         },
       ));
       manager.set_access_token("test_token".into());
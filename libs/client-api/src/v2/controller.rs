@@ -1,25 +1,27 @@
 use super::db::{Db, DbHolder};
 use super::{ChangedCollab, ObjectId, WorkspaceId};
 use crate::entity::CollabType;
+use crate::notify::TokenState;
 use crate::sync_trace;
-use crate::v2::actor::{WorkspaceAction, WorkspaceControllerActor, WsConn};
+use crate::v2::actor::{ActionSource, WorkspaceAction, WorkspaceControllerActor};
 use crate::v2::conn_retry::{ReconnectTarget, ReconnectionManager};
+use crate::v2::transport::ConnectionSink;
+use crate::Client;
 use app_error::ErrorCode;
-use appflowy_proto::{Rid, WorkspaceNotification};
+use appflowy_proto::{ClientMessage, Rid, WorkspaceNotification};
 use async_trait::async_trait;
 use collab::preclude::Collab;
 use collab_rt_protocol::CollabRef;
 use futures_core::Stream;
-use futures_util::stream::SplitSink;
 use shared_entity::response::AppResponseError;
 use std::fmt::{Display, Formatter};
 use std::sync::{Arc, Weak};
-use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time::interval;
 use tokio_stream::wrappers::WatchStream;
 use tokio_stream::StreamExt;
 use tokio_tungstenite::tungstenite::error::ProtocolError;
-use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::tungstenite::Error;
 use tokio_util::sync::CancellationToken;
 use tracing::instrument;
 use yrs::block::ClientID;
@@ -104,6 +106,68 @@ impl WorkspaceController {
     Ok(())
   }
 
+  /// Updates access token used by the connection. If the client is connected, the new token
+  /// is sent to the server, which extends the connection lifetime until the token expires,
+  /// otherwise it's used when reconnecting.
+  pub fn refresh_access_token(&self, access_token: String) {
+    Self::send_access_token(&self.actor, &self.connection_manager, access_token);
+  }
+
+  /// Keeps the access token used by the connection in sync with the token of the `client`:
+  /// every time the client refreshes its token, [Self::refresh_access_token] is called with it.
+  /// Watching stops once the controller is dropped.
+  pub fn watch_access_token(&self, client: &Client) {
+    let mut token_state = client.subscribe_token_state();
+    let client = client.clone();
+    let actor = Arc::downgrade(&self.actor);
+    let connection_manager = Arc::downgrade(&self.connection_manager);
+    tokio::spawn(async move {
+      loop {
+        match token_state.recv().await {
+          // only the latest token matters, which is read below
+          Ok(TokenState::Refresh) | Err(RecvError::Lagged(_)) => {},
+          Ok(TokenState::Invalid) => continue,
+          Err(RecvError::Closed) => break,
+        }
+        let (Some(actor), Some(connection_manager)) =
+          (actor.upgrade(), connection_manager.upgrade())
+        else {
+          break;
+        };
+        if let Ok(access_token) = client.access_token() {
+          Self::send_access_token(&actor, &connection_manager, access_token);
+        }
+      }
+    });
+  }
+
+  fn send_access_token(
+    actor: &Arc<WorkspaceControllerActor>,
+    connection_manager: &ReconnectionManager,
+    access_token: String,
+  ) {
+    if access_token.is_empty() {
+      return;
+    }
+    connection_manager.set_access_token(access_token.clone());
+    let connected = match &*actor.status_channel().borrow() {
+      ConnectionStatus::Connected { sink, .. } => {
+        // the HTTP fallback sends the token with every message, including the auth one below
+        sink.set_access_token(access_token.clone());
+        true
+      },
+      _ => false,
+    };
+    if connected {
+      actor.trigger(WorkspaceAction::Send(
+        ClientMessage::Auth {
+          token: access_token,
+        },
+        ActionSource::Local,
+      ));
+    }
+  }
+
   pub async fn disconnect(&self) -> anyhow::Result<()> {
     let (tx, rx) = tokio::sync::oneshot::channel();
     self.actor.trigger(WorkspaceAction::Disconnect(tx));
@@ -272,7 +336,7 @@ pub enum ConnectionStatus {
     cancel: CancellationToken,
   },
   Connected {
-    sink: Arc<Mutex<SplitSink<WsConn, Message>>>,
+    sink: ConnectionSink,
     cancel: CancellationToken,
   },
   StartReconnect,
@@ -365,6 +429,10 @@ impl ReconnectTarget for WorkspaceControllerActor {
       reason: Some(reason),
     });
   }
+
+  fn enable_fallback_transport(&self) {
+    self.enable_http_fallback();
+  }
 }
 
 pub fn spawn_reconnection(
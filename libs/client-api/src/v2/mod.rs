@@ -3,6 +3,7 @@ mod compactor;
 mod conn_retry;
 mod controller;
 mod db;
+mod transport;
 pub type WorkspaceController = controller::WorkspaceController;
 pub type WorkspaceControllerOptions = controller::Options;
 
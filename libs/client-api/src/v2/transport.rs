@@ -0,0 +1,263 @@
+use crate::v2::actor::WsConn;
+use crate::v2::controller::DisconnectedReason;
+use app_error::AppError;
+use arc_swap::ArcSwap;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_core::Stream;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::StatusCode;
+use shared_entity::response::AppResponseError;
+use std::fmt::{Debug, Formatter};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Sending half of the connection established with the server.
+#[derive(Clone)]
+pub enum ConnectionSink {
+  /// Default WebSocket transport.
+  WebSocket(Arc<Mutex<SplitSink<WsConn, Message>>>),
+  /// HTTP fallback transport, used when WebSocket connection couldn't be established.
+  Http(Arc<HttpSink>),
+}
+
+impl ConnectionSink {
+  /// Sends all frames of a single message. Frames of one message are never interleaved with
+  /// frames of other messages.
+  pub async fn send(&self, frames: Vec<Vec<u8>>) -> anyhow::Result<()> {
+    match self {
+      ConnectionSink::WebSocket(sink) => {
+        let mut sink = sink.lock().await;
+        for frame in frames {
+          sink.send(Message::Binary(frame)).await?;
+        }
+      },
+      ConnectionSink::Http(sink) => sink.send(frames).await?,
+    }
+    Ok(())
+  }
+
+  /// Updates the access token of the transport. WebSocket connections are authenticated once,
+  /// while each HTTP request carries the token.
+  pub fn set_access_token(&self, access_token: String) {
+    match self {
+      ConnectionSink::WebSocket(_) => { /* the token is sent in an auth frame */ },
+      ConnectionSink::Http(sink) => sink.access_token.store(Arc::new(access_token)),
+    }
+  }
+
+  pub async fn ping(&self) -> anyhow::Result<()> {
+    match self {
+      ConnectionSink::WebSocket(sink) => {
+        let mut lock = sink.lock().await;
+        lock.send(Message::Ping(Vec::new())).await?;
+        lock.flush().await?;
+      },
+      ConnectionSink::Http(_) => { /* server keeps the event stream alive on its own */ },
+    }
+    Ok(())
+  }
+
+  pub async fn close(&self) -> anyhow::Result<()> {
+    match self {
+      ConnectionSink::WebSocket(sink) => {
+        let mut sink = sink.lock().await;
+        sink.flush().await?;
+        sink.close().await?;
+      },
+      ConnectionSink::Http(_) => { /* event stream is closed once receiver task is cancelled */ },
+    }
+    Ok(())
+  }
+}
+
+impl Debug for ConnectionSink {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ConnectionSink::WebSocket(_) => write!(f, "WebSocket"),
+      ConnectionSink::Http(sink) => write!(f, "Http({})", sink.url),
+    }
+  }
+}
+
+/// Sends client messages to the server through HTTP fallback transport - one POST request
+/// per message frame.
+pub struct HttpSink {
+  client: reqwest::Client,
+  url: String,
+  /// Latest access token of the client, refreshed while the connection is open.
+  access_token: ArcSwap<String>,
+  /// Requests are sent one by one to preserve the order of messages.
+  lock: Mutex<()>,
+}
+
+impl HttpSink {
+  async fn send(&self, frames: Vec<Vec<u8>>) -> anyhow::Result<()> {
+    let _guard = self.lock.lock().await;
+    for frame in frames {
+      let resp = self
+        .client
+        .post(&self.url)
+        .bearer_auth(self.access_token.load().as_str())
+        .header(CONTENT_TYPE, "application/octet-stream")
+        .body(frame)
+        .send()
+        .await?;
+      if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        anyhow::bail!("failed to send message ({}): {}", status, text);
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Stream of binary server messages received through HTTP fallback transport.
+pub type HttpStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, DisconnectedReason>> + Send>>;
+
+/// Establishes HTTP fallback connection. Server messages are received from Server-Sent Events
+/// stream opened at `events_url`, while client messages are POSTed to `messages_url`.
+pub async fn connect_http(
+  events_url: &str,
+  messages_url: String,
+  access_token: String,
+) -> Result<(HttpSink, HttpStream), AppResponseError> {
+  let client = reqwest::Client::new();
+  let resp = client
+    .get(events_url)
+    .bearer_auth(&access_token)
+    .header(ACCEPT, "text/event-stream")
+    .send()
+    .await
+    .map_err(AppError::from)?;
+  match resp.status() {
+    status if status.is_success() => {},
+    StatusCode::UNAUTHORIZED | StatusCode::NOT_FOUND => {
+      return Err(AppError::UserUnAuthorized("Unauthorized event stream connection".into()).into())
+    },
+    status => {
+      return Err(
+        AppError::Internal(anyhow::anyhow!("failed to open event stream: {}", status)).into(),
+      )
+    },
+  }
+
+  let mut decoder = SseDecoder::default();
+  let stream = resp
+    .bytes_stream()
+    .map(move |chunk| match chunk {
+      Ok(bytes) => decoder.push(&bytes),
+      Err(err) => Err(DisconnectedReason::Unexpected(err.to_string().into())),
+    })
+    .flat_map(|result| {
+      let items: Vec<_> = match result {
+        Ok(messages) => messages.into_iter().map(Ok).collect(),
+        Err(err) => vec![Err(err)],
+      };
+      futures_util::stream::iter(items)
+    });
+  let sink = HttpSink {
+    client,
+    url: messages_url,
+    access_token: ArcSwap::from_pointee(access_token),
+    lock: Mutex::new(()),
+  };
+  Ok((sink, Box::pin(stream)))
+}
+
+/// Converts WebSocket endpoint (`ws://` or `wss://`) into corresponding HTTP endpoint.
+pub fn http_endpoint(ws_url: &str) -> String {
+  if let Some(rest) = ws_url.strip_prefix("wss://") {
+    format!("https://{}", rest)
+  } else if let Some(rest) = ws_url.strip_prefix("ws://") {
+    format!("http://{}", rest)
+  } else {
+    ws_url.to_string()
+  }
+}
+
+/// Incremental parser of Server-Sent Events stream. It extracts binary messages from base64
+/// encoded `data` fields. Comments (used by the server as keep-alive) and other fields are ignored.
+#[derive(Default)]
+struct SseDecoder {
+  buf: Vec<u8>,
+  data: String,
+}
+
+impl SseDecoder {
+  fn push(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, DisconnectedReason> {
+    self.buf.extend_from_slice(chunk);
+    let mut messages = Vec::new();
+    while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+      let line: Vec<u8> = self.buf.drain(..=pos).collect();
+      let line = String::from_utf8_lossy(&line);
+      let line = line.trim_end_matches(['\n', '\r']);
+      if line.is_empty() {
+        // empty line dispatches the event
+        if !self.data.is_empty() {
+          let data = std::mem::take(&mut self.data);
+          let bytes = STANDARD
+            .decode(data)
+            .map_err(|err| DisconnectedReason::MessageDecode(err.to_string().into()))?;
+          messages.push(bytes);
+        }
+      } else if let Some(value) = line.strip_prefix("data:") {
+        self.data.push_str(value.trim_start());
+      }
+    }
+    Ok(messages)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decode_events_split_across_chunks() {
+    let mut decoder = SseDecoder::default();
+    let first = STANDARD.encode([1u8, 2, 3]);
+    let second = STANDARD.encode([4u8, 5]);
+    let stream = format!(
+      ": ping\n\nevent: message\ndata: {}\n\nevent: message\r\ndata: {}\r\n\r\n",
+      first, second
+    );
+    let (a, b) = stream.as_bytes().split_at(17);
+
+    let mut messages = decoder.push(a).unwrap();
+    messages.extend(decoder.push(b).unwrap());
+    assert_eq!(messages, vec![vec![1, 2, 3], vec![4, 5]]);
+  }
+
+  #[test]
+  fn keep_alive_comments_are_ignored() {
+    let mut decoder = SseDecoder::default();
+    assert!(decoder.push(b": ping\n\n: ping\n\n").unwrap().is_empty());
+  }
+
+  #[test]
+  fn invalid_payload_is_rejected() {
+    let mut decoder = SseDecoder::default();
+    assert!(matches!(
+      decoder.push(b"data: not base64!\n\n"),
+      Err(DisconnectedReason::MessageDecode(_))
+    ));
+  }
+
+  #[test]
+  fn convert_websocket_endpoint() {
+    assert_eq!(
+      http_endpoint("wss://example.com/ws/v2"),
+      "https://example.com/ws/v2"
+    );
+    assert_eq!(
+      http_endpoint("ws://localhost:8000/ws/v2"),
+      "http://localhost:8000/ws/v2"
+    );
+  }
+}
@@ -1,10 +1,13 @@
 use crate::error::StreamError;
-use crate::model::AwarenessStreamUpdate;
+use crate::model::{AwarenessStreamUpdate, CollabPresence};
+use chrono::Utc;
+use collab::core::origin::CollabOrigin;
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use redis::aio::MultiplexedConnection;
 use redis::{AsyncCommands, Client, RedisError, Value};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::sync::Mutex;
 use tokio_stream::wrappers::UnboundedReceiverStream;
@@ -14,6 +17,10 @@ use uuid::Uuid;
 type AwarenessSender = UnboundedSender<Arc<AwarenessStreamUpdate>>;
 type ScopedAwarenessSender = UnboundedSender<(Uuid, Arc<AwarenessStreamUpdate>)>;
 
+/// Period of time after which a device, that didn't send any awareness update over a collab,
+/// is no longer considered to be present.
+pub const PRESENCE_TTL: Duration = Duration::from_secs(60);
+
 pub struct AwarenessGossip {
   conn: MultiplexedConnection,
   collabs: Arc<DashMap<Uuid, AwarenessSender>>,
@@ -98,9 +105,34 @@ impl AwarenessGossip {
     let publish_key = format!("af:awareness:{workspace_id}:{object_id}");
     let mut pubsub = self.conn.clone();
     let _: Value = pubsub.publish(publish_key, json).await?;
+    record_presence(&mut pubsub, workspace_id, object_id, update).await?;
     Ok(())
   }
 
+  /// Returns the list of devices, which have been active over collabs of a given workspace
+  /// within [PRESENCE_TTL]. Since presence is stored in Redis, it reflects the awareness state
+  /// gathered across all collaborate nodes.
+  pub async fn workspace_presence(
+    &self,
+    workspace_id: &Uuid,
+  ) -> Result<Vec<CollabPresence>, StreamError> {
+    let key = CollabPresence::presence_key(&workspace_id.to_string());
+    let min_active_at = Utc::now().timestamp_millis() - PRESENCE_TTL.as_millis() as i64;
+    let mut conn = self.conn.clone();
+    let _: Value = conn
+      .zrembyscore(&key, "-inf", format!("({}", min_active_at))
+      .await?;
+    let members: Vec<(String, i64)> = conn
+      .zrangebyscore_withscores(&key, min_active_at, "+inf")
+      .await?;
+    Ok(
+      members
+        .into_iter()
+        .filter_map(|(member, last_active_at)| CollabPresence::from_member(&member, last_active_at))
+        .collect(),
+    )
+  }
+
   pub async fn sink(
     &self,
     workspace_id: &Uuid,
@@ -155,9 +187,39 @@ impl AwarenessGossip {
   }
 }
 
+/// Refreshes (or removes in case of client leaving) the presence of the awareness update sender.
+async fn record_presence(
+  conn: &mut MultiplexedConnection,
+  workspace_id: &str,
+  object_id: &str,
+  update: &AwarenessStreamUpdate,
+) -> Result<(), StreamError> {
+  let client = match &update.sender {
+    CollabOrigin::Client(client) => client,
+    _ => return Ok(()), // only user devices are tracked
+  };
+  let key = CollabPresence::presence_key(workspace_id);
+  let member = CollabPresence::member(object_id, client.uid, &client.device_id);
+  if update.is_leave() {
+    let _: Value = conn.zrem(key, member).await?;
+  } else {
+    let now = Utc::now().timestamp_millis();
+    let _: Value = redis::pipe()
+      .zadd(&key, member, now)
+      .ignore()
+      .pexpire(&key, PRESENCE_TTL.as_millis() as i64)
+      .ignore()
+      .query_async(conn)
+      .await?;
+  }
+  Ok(())
+}
+
 pub struct AwarenessUpdateSink {
   conn: Mutex<MultiplexedConnection>,
   publish_key: String,
+  workspace_id: String,
+  object_id: String,
 }
 
 impl AwarenessUpdateSink {
@@ -166,12 +228,15 @@ impl AwarenessUpdateSink {
     AwarenessUpdateSink {
       conn: conn.into(),
       publish_key,
+      workspace_id: workspace_id.to_string(),
+      object_id: object_id.to_string(),
     }
   }
 
   pub async fn send(&self, msg: &AwarenessStreamUpdate) -> Result<(), StreamError> {
     let mut conn = self.conn.lock().await;
     Self::notify_awareness_change(&mut conn, &self.publish_key, msg).await?;
+    record_presence(&mut conn, &self.workspace_id, &self.object_id, msg).await?;
     Ok(())
   }
 
@@ -403,6 +403,54 @@ pub struct AwarenessStreamUpdate {
   pub sender: CollabOrigin,
 }
 
+impl AwarenessStreamUpdate {
+  /// Returns true if all awareness states carried by this update have been removed. This happens
+  /// when a client closes the collab or disconnects gracefully.
+  pub fn is_leave(&self) -> bool {
+    !self.data.clients.is_empty()
+      && self
+        .data
+        .clients
+        .values()
+        .all(|entry| &*entry.json == "null")
+  }
+}
+
+/// Presence of a single user device over a collab, derived from the awareness updates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollabPresence {
+  pub object_id: Uuid,
+  pub uid: i64,
+  pub device_id: String,
+  /// UNIX epoch timestamp in milliseconds of the last awareness update send by the device.
+  pub last_active_at: i64,
+}
+
+impl CollabPresence {
+  /// Redis sorted set key used to keep the presence of all users in a workspace.
+  /// Members are encoded as `{object_id}:{uid}:{device_id}`, scored with last activity timestamp.
+  pub fn presence_key(workspace_id: &str) -> String {
+    format!("af:presence:{}", workspace_id)
+  }
+
+  pub fn member(object_id: &str, uid: i64, device_id: &str) -> String {
+    format!("{}:{}:{}", object_id, uid, device_id)
+  }
+
+  pub fn from_member(member: &str, last_active_at: i64) -> Option<Self> {
+    let mut segments = member.splitn(3, ':');
+    let object_id = Uuid::parse_str(segments.next()?).ok()?;
+    let uid = segments.next()?.parse().ok()?;
+    let device_id = segments.next()?.to_string();
+    Some(CollabPresence {
+      object_id,
+      uid,
+      device_id,
+      last_active_at,
+    })
+  }
+}
+
 #[repr(transparent)]
 #[derive(Copy, Clone, Eq, PartialEq, Default)]
 pub struct UpdateFlags(u8);
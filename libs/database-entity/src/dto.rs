@@ -2,7 +2,7 @@ use crate::error::EntityError;
 use crate::error::EntityError::{DeserializationError, InvalidData};
 
 use bytes::Bytes;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use collab_entity::CollabType;
 use collab_entity::{proto, EncodedCollab};
 use infra::validate::{validate_not_empty_payload, validate_not_empty_str};
@@ -479,6 +479,12 @@ pub struct PublishInfo {
   pub comments_enabled: bool,
   #[serde(default = "default_duplicate_enabled")]
   pub duplicate_enabled: bool,
+  /// Once expired, the published page behaves as if it was unpublished.
+  #[serde(default)]
+  pub expires_at: Option<DateTime<Utc>>,
+  /// Guests need to provide a password in order to access the published page.
+  #[serde(default)]
+  pub password_protected: bool,
 }
 
 fn default_comments_enabled() -> bool {
@@ -707,6 +713,11 @@ pub struct AFWorkspaceSettings {
 
   #[serde(default)]
   pub ai_model: String,
+
+  /// Number of days a page stays in the trash before it is permanently deleted.
+  /// 0 keeps trashed pages until the trash is emptied manually.
+  #[serde(default)]
+  pub trash_retention_days: u32,
 }
 
 impl Default for AFWorkspaceSettings {
@@ -714,6 +725,7 @@ impl Default for AFWorkspaceSettings {
     Self {
       disable_search_indexing: false,
       ai_model: "Auto".to_string(),
+      trash_retention_days: 0,
     }
   }
 }
@@ -724,6 +736,8 @@ pub struct AFWorkspaceSettingsChange {
   pub disable_search_indexing: Option<bool>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub ai_model: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub trash_retention_days: Option<u32>,
 }
 
 impl AFWorkspaceSettingsChange {
@@ -731,6 +745,7 @@ impl AFWorkspaceSettingsChange {
     Self {
       disable_search_indexing: None,
       ai_model: None,
+      trash_retention_days: None,
     }
   }
   pub fn disable_search_indexing(mut self, disable_search_indexing: bool) -> Self {
@@ -741,6 +756,10 @@ impl AFWorkspaceSettingsChange {
     self.ai_model = Some(ai_model);
     self
   }
+  pub fn trash_retention_days(mut self, trash_retention_days: u32) -> Self {
+    self.trash_retention_days = Some(trash_retention_days);
+    self
+  }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -879,6 +898,29 @@ pub struct PatchPublishedCollab {
   pub publish_name: Option<String>,
   pub comments_enabled: Option<bool>,
   pub duplicate_enabled: Option<bool>,
+  /// `Some(None)` removes the password protection.
+  #[serde(
+    default,
+    deserialize_with = "deserialize_patch_field",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub password: Option<Option<String>>,
+  /// `Some(None)` removes the expiration date.
+  #[serde(
+    default,
+    deserialize_with = "deserialize_patch_field",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub expires_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// Distinguishes a missing field (`None`) from the field explicitly set to `null` (`Some(None)`).
+fn deserialize_patch_field<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+  T: Deserialize<'de>,
+  D: serde::Deserializer<'de>,
+{
+  Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -910,6 +952,28 @@ pub struct GlobalComment {
   pub comment_id: Uuid,
   pub is_deleted: bool,
   pub can_be_deleted: bool,
+  /// Comments pending approval are only returned to their author.
+  #[serde(default)]
+  pub status: PublishedCommentStatus,
+}
+
+#[derive(Deserialize_repr, Serialize_repr, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[repr(i16)]
+pub enum PublishedCommentStatus {
+  #[default]
+  Approved = 0,
+  Pending = 1,
+  Rejected = 2,
+}
+
+impl From<i16> for PublishedCommentStatus {
+  fn from(value: i16) -> Self {
+    match value {
+      1 => PublishedCommentStatus::Pending,
+      2 => PublishedCommentStatus::Rejected,
+      _ => PublishedCommentStatus::Approved,
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -923,6 +987,53 @@ pub struct DeleteGlobalCommentParams {
   pub comment_id: Uuid,
 }
 
+/// Comment listed in the moderation queue of a workspace.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublishedCommentModerationItem {
+  pub comment_id: Uuid,
+  pub view_id: Uuid,
+  pub publish_name: String,
+  pub reply_comment_id: Option<Uuid>,
+  pub content: String,
+  pub user: Option<AFWebUserWithObfuscatedName>,
+  pub status: PublishedCommentStatus,
+  pub is_deleted: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublishedCommentModerationQueryParams {
+  /// Only returns the comments with the given status. Defaults to all statuses.
+  pub status: Option<PublishedCommentStatus>,
+  /// Only returns the comments created before this time, used for pagination.
+  pub before: Option<DateTime<Utc>>,
+  /// Defaults to 50, and cannot be more than 200.
+  pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdatePublishedCommentStatusParams {
+  pub status: PublishedCommentStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PublishedCommentSetting {
+  pub require_approval: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublishedCommentBlockedUser {
+  pub user: AFWebUserWithObfuscatedName,
+  pub reason: Option<String>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlockPublishedCommenterParams {
+  pub user_uuid: Uuid,
+  pub reason: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Reactions {
   pub reactions: Vec<Reaction>,
@@ -952,6 +1063,56 @@ pub struct DeleteReactionParams {
   pub comment_id: Uuid,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublishAnalyticsQueryParams {
+  /// Number of past days (including today) to return the stats for. Defaults to 30.
+  pub days: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishedViewDailyStats {
+  pub day: NaiveDate,
+  pub views: i64,
+  pub unique_visitors: i64,
+}
+
+/// Number of views coming from a given referrer domain or country.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishedViewBreakdown {
+  pub value: String,
+  pub views: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishedViewStatsSummary {
+  pub view_id: Uuid,
+  pub publish_name: Option<String>,
+  pub views: i64,
+  /// Sum of daily unique visitors.
+  pub unique_visitors: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishedViewAnalytics {
+  pub view_id: Uuid,
+  pub views: i64,
+  /// Sum of daily unique visitors.
+  pub unique_visitors: i64,
+  pub daily: Vec<PublishedViewDailyStats>,
+  pub referrers: Vec<PublishedViewBreakdown>,
+  pub countries: Vec<PublishedViewBreakdown>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishNamespaceAnalytics {
+  pub namespace: String,
+  pub views: i64,
+  /// Sum of daily unique visitors.
+  pub unique_visitors: i64,
+  pub daily: Vec<PublishedViewDailyStats>,
+  pub pages: Vec<PublishedViewStatsSummary>,
+}
+
 /// Indexing status of a document.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum IndexingStatus {
@@ -0,0 +1,230 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+
+use crate::error::EntityError;
+
+/// Days searched for the next run. Covers the eight years between two February 29th.
+const MAX_SEARCH_DAYS: u32 = 366 * 8;
+
+/// Cron-like schedule in UTC, made of five fields: minute, hour, day of month, month and day of
+/// week (0 or 7 is Sunday). Each field is `*`, a number, a range `a-b`, a step `*/n` or `a-b/n`,
+/// or a comma separated list of those. `@hourly`, `@daily`, `@weekly` and `@monthly` are
+/// accepted as well.
+///
+/// Like cron, when both the day of month and the day of week are restricted, a day matches if
+/// either of them matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+  minutes: BTreeSet<u32>,
+  hours: BTreeSet<u32>,
+  days_of_month: BTreeSet<u32>,
+  months: BTreeSet<u32>,
+  days_of_week: BTreeSet<u32>,
+  any_day_of_month: bool,
+  any_day_of_week: bool,
+}
+
+impl CronSchedule {
+  /// Returns the first time matching the schedule strictly after `after`, at the start of a
+  /// minute, or `None` if the schedule never matches, e.g. on February 31st.
+  pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+    let start_date = start.date_naive();
+    let mut date = start_date;
+    for _ in 0..MAX_SEARCH_DAYS {
+      if self.matches_date(&date) {
+        let (from_hour, from_minute) = if date == start_date {
+          (start.hour(), start.minute())
+        } else {
+          (0, 0)
+        };
+        for &hour in self.hours.range(from_hour..) {
+          let from_minute = if hour == from_hour { from_minute } else { 0 };
+          if let Some(&minute) = self.minutes.range(from_minute..).next() {
+            return Some(date.and_hms_opt(hour, minute, 0)?.and_utc());
+          }
+        }
+      }
+      date = date.succ_opt()?;
+    }
+    None
+  }
+
+  fn matches_date(&self, date: &NaiveDate) -> bool {
+    if !self.months.contains(&date.month()) {
+      return false;
+    }
+    let day_of_month = self.days_of_month.contains(&date.day());
+    let day_of_week = self
+      .days_of_week
+      .contains(&date.weekday().num_days_from_sunday());
+    match (self.any_day_of_month, self.any_day_of_week) {
+      (true, true) => true,
+      (true, false) => day_of_week,
+      (false, true) => day_of_month,
+      (false, false) => day_of_month || day_of_week,
+    }
+  }
+}
+
+impl FromStr for CronSchedule {
+  type Err = EntityError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let expression = match s.trim() {
+      "@hourly" => "0 * * * *",
+      "@daily" => "0 0 * * *",
+      "@weekly" => "0 0 * * 0",
+      "@monthly" => "0 0 1 * *",
+      expression => expression,
+    };
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    if fields.len() != 5 {
+      return Err(EntityError::InvalidData(format!(
+        "Schedule '{}' must have 5 fields: minute hour day-of-month month day-of-week",
+        s
+      )));
+    }
+    let mut days_of_week = parse_field(fields[4], 0, 7)?;
+    if days_of_week.remove(&7) {
+      days_of_week.insert(0);
+    }
+    Ok(Self {
+      minutes: parse_field(fields[0], 0, 59)?,
+      hours: parse_field(fields[1], 0, 23)?,
+      days_of_month: parse_field(fields[2], 1, 31)?,
+      months: parse_field(fields[3], 1, 12)?,
+      days_of_week,
+      any_day_of_month: fields[2] == "*",
+      any_day_of_week: fields[4] == "*",
+    })
+  }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<BTreeSet<u32>, EntityError> {
+  let invalid = || EntityError::InvalidData(format!("Invalid schedule field: {}", field));
+  let parse_value = |value: &str| {
+    value
+      .parse::<u32>()
+      .ok()
+      .filter(|value| (min..=max).contains(value))
+      .ok_or_else(invalid)
+  };
+
+  let mut values = BTreeSet::new();
+  for part in field.split(',') {
+    let (range, step) = match part.split_once('/') {
+      Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+      None => (part, 1),
+    };
+    if step == 0 {
+      return Err(invalid());
+    }
+    let (start, end) = match range {
+      "*" => (min, max),
+      range => match range.split_once('-') {
+        Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+        // a single value with a step runs until the end of the field
+        None if step > 1 => (parse_value(range)?, max),
+        None => {
+          let value = parse_value(range)?;
+          (value, value)
+        },
+      },
+    };
+    if start > end {
+      return Err(invalid());
+    }
+    values.extend((start..=end).step_by(step as usize));
+  }
+  Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc
+      .with_ymd_and_hms(year, month, day, hour, minute, 0)
+      .unwrap()
+  }
+
+  #[test]
+  fn next_run_of_weekly_schedule() {
+    // every Monday at 09:30, 2025-08-20 is a Wednesday
+    let schedule = CronSchedule::from_str("30 9 * * 1").unwrap();
+    assert_eq!(
+      schedule.next_after(utc(2025, 8, 20, 12, 0)),
+      Some(utc(2025, 8, 25, 9, 30))
+    );
+    assert_eq!(
+      schedule.next_after(utc(2025, 8, 25, 9, 29)),
+      Some(utc(2025, 8, 25, 9, 30))
+    );
+    assert_eq!(
+      schedule.next_after(utc(2025, 8, 25, 9, 30)),
+      Some(utc(2025, 9, 1, 9, 30))
+    );
+  }
+
+  #[test]
+  fn next_run_of_steps_ranges_and_lists() {
+    let schedule = CronSchedule::from_str("*/15 8-17 * * 1-5").unwrap();
+    assert_eq!(
+      schedule.next_after(utc(2025, 8, 22, 17, 50)),
+      Some(utc(2025, 8, 25, 8, 0))
+    );
+    let schedule = CronSchedule::from_str("0 0 1,15 * *").unwrap();
+    assert_eq!(
+      schedule.next_after(utc(2025, 8, 2, 0, 0)),
+      Some(utc(2025, 8, 15, 0, 0))
+    );
+    let schedule = CronSchedule::from_str("@monthly").unwrap();
+    assert_eq!(
+      schedule.next_after(utc(2025, 12, 31, 23, 59)),
+      Some(utc(2026, 1, 1, 0, 0))
+    );
+  }
+
+  #[test]
+  fn day_of_month_or_day_of_week() {
+    // the 13th of the month, or any Friday
+    let schedule = CronSchedule::from_str("0 12 13 * 5").unwrap();
+    assert_eq!(
+      schedule.next_after(utc(2025, 8, 20, 0, 0)),
+      Some(utc(2025, 8, 22, 12, 0))
+    );
+    assert_eq!(
+      schedule.next_after(utc(2025, 9, 6, 0, 0)),
+      Some(utc(2025, 9, 12, 12, 0))
+    );
+    assert_eq!(
+      schedule.next_after(utc(2025, 9, 12, 12, 0)),
+      Some(utc(2025, 9, 13, 12, 0))
+    );
+  }
+
+  #[test]
+  fn invalid_schedules() {
+    for expression in [
+      "",
+      "* * * *",
+      "60 * * * *",
+      "* * 0 * *",
+      "*/0 * * * *",
+      "5-1 * * * *",
+    ] {
+      assert!(
+        CronSchedule::from_str(expression).is_err(),
+        "{}",
+        expression
+      );
+    }
+    let schedule = CronSchedule::from_str("0 0 31 2 *").unwrap();
+    assert_eq!(schedule.next_after(utc(2025, 1, 1, 0, 0)), None);
+  }
+}
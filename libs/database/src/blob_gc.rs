@@ -0,0 +1,223 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::AFOrphanedBlobRow;
+
+/// Returns the workspaces owning blobs, ordered by id, starting after the given workspace.
+pub async fn select_workspace_ids_with_blobs<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  after: Option<Uuid>,
+  limit: i64,
+) -> Result<Vec<Uuid>, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT DISTINCT workspace_id
+      FROM af_blob_metadata
+      WHERE $1::UUID IS NULL OR workspace_id > $1
+      ORDER BY workspace_id
+      LIMIT $2
+    "#,
+    after,
+    limit,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the blobs that can be garbage collected when unreferenced: blobs uploaded under a
+/// document, database or database row of the workspace before the given time. Blobs uploaded
+/// elsewhere, like chat attachments, are never collected.
+pub async fn select_collectable_blob_ids<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  modified_before: DateTime<Utc>,
+) -> Result<Vec<String>, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT m.file_id
+      FROM af_blob_metadata m
+      WHERE m.workspace_id = $1
+        AND m.modified_at < $2
+        AND EXISTS (
+          SELECT 1 FROM af_collab c
+          WHERE c.workspace_id = m.workspace_id
+            AND c.partition_key IN (0, 1, 4)
+            AND c.oid::TEXT = SPLIT_PART(m.file_id, '_', 1)
+        )
+    "#,
+    workspace_id,
+    modified_before,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the documents, databases, database rows and folder of the workspace, which can
+/// reference blobs. The folder references them through the icons and covers of the views.
+pub async fn select_blob_referencing_collabs<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<(Uuid, i32)>, AppError> {
+  let res = sqlx::query!(
+    r#"
+      SELECT oid, partition_key
+      FROM af_collab
+      WHERE workspace_id = $1
+        AND partition_key IN (0, 1, 3, 4)
+        AND deleted_at IS NULL
+    "#,
+    workspace_id,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(
+    res
+      .into_iter()
+      .map(|row| (row.oid, row.partition_key))
+      .collect(),
+  )
+}
+
+/// Returns the file names found in the snapshots, the version history or the published data of
+/// the workspace, which can be restored or served after the live collabs stopped referencing
+/// them.
+pub async fn select_file_names_referenced_by_history<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  file_names: &[String],
+) -> Result<Vec<String>, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT file_name AS "file_name!"
+      FROM UNNEST($2::TEXT[]) AS file_name
+      WHERE EXISTS (
+          SELECT 1 FROM af_collab_snapshot s
+          WHERE s.workspace_id = $1
+            AND s.deleted_at IS NULL
+            AND POSITION(CONVERT_TO(file_name, 'UTF8') IN s.blob) > 0
+        )
+        OR EXISTS (
+          SELECT 1 FROM af_snapshot_state h
+          WHERE h.workspace_id = $1
+            AND POSITION(CONVERT_TO(file_name, 'UTF8') IN h.doc_state) > 0
+        )
+        OR EXISTS (
+          SELECT 1 FROM af_published_collab p
+          WHERE p.workspace_id = $1
+            AND (
+              POSITION(CONVERT_TO(file_name, 'UTF8') IN p.blob) > 0
+              OR STRPOS(p.metadata::TEXT, file_name) > 0
+            )
+        )
+    "#,
+    workspace_id,
+    file_names,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Marks the blobs as unreferenced, and unmarks the other blobs of the workspace. Blobs already
+/// marked keep their deletion time.
+pub async fn update_orphaned_blob_marks<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  file_ids: &[String],
+  delete_after: DateTime<Utc>,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      WITH unmarked AS (
+        DELETE FROM af_blob_orphan
+        WHERE workspace_id = $1
+          AND NOT (file_id = ANY($2))
+      )
+      INSERT INTO af_blob_orphan (workspace_id, file_id, delete_after)
+      SELECT $1, file_id, $3 FROM UNNEST($2::VARCHAR[]) AS file_id
+      ON CONFLICT (workspace_id, file_id) DO NOTHING
+    "#,
+    workspace_id,
+    file_ids,
+    delete_after,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+/// Returns the blobs that stayed unreferenced for the whole grace period.
+pub async fn select_expired_orphaned_blob_ids<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<String>, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT file_id
+      FROM af_blob_orphan
+      WHERE workspace_id = $1
+        AND delete_after < NOW()
+    "#,
+    workspace_id,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_orphaned_blobs<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFOrphanedBlobRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFOrphanedBlobRow,
+    r#"
+      SELECT
+        m.file_id,
+        m.file_type,
+        m.file_size,
+        m.modified_at,
+        o.marked_at,
+        o.delete_after
+      FROM af_blob_orphan o
+      JOIN af_blob_metadata m ON m.workspace_id = o.workspace_id AND m.file_id = o.file_id
+      WHERE o.workspace_id = $1
+      ORDER BY o.delete_after
+    "#,
+    workspace_id,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn delete_blob_metadata_bulk<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  file_ids: &[String],
+) -> Result<u64, AppError> {
+  let res = sqlx::query!(
+    r#"
+      DELETE FROM af_blob_metadata
+      WHERE workspace_id = $1
+        AND file_id = ANY($2)
+    "#,
+    workspace_id,
+    file_ids,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(res.rows_affected())
+}
@@ -566,6 +566,28 @@ pub async fn select_workspace_database_oid<'a, E: Executor<'a, Database = Postgr
   .await
 }
 
+/// Returns the workspace of the collab, or None if the collab doesn't exist.
+pub async fn select_collab_workspace_id<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  oid: &Uuid,
+  collab_type: &CollabType,
+) -> Result<Option<Uuid>, sqlx::Error> {
+  let partition_key = partition_key_from_collab_type(collab_type);
+  sqlx::query_scalar!(
+    r#"
+      SELECT workspace_id
+      FROM af_collab
+      WHERE oid = $1
+        AND partition_key = $2
+        AND deleted_at IS NULL
+    "#,
+    oid,
+    partition_key,
+  )
+  .fetch_optional(executor)
+  .await
+}
+
 pub async fn select_last_updated_database_row_ids(
   pg_pool: &PgPool,
   workspace_id: &Uuid,
@@ -592,6 +614,28 @@ pub async fn select_last_updated_database_row_ids(
   Ok(updated_row_items)
 }
 
+/// Marks the collabs of the workspace as deleted.
+pub async fn update_collabs_as_deleted<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  object_ids: &[Uuid],
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_collab
+      SET deleted_at = NOW()
+      WHERE workspace_id = $1
+        AND oid = ANY($2)
+        AND deleted_at IS NULL
+    "#,
+    workspace_id,
+    object_ids,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
 pub async fn select_collab_embed_info<'a, E>(
   tx: E,
   object_id: &Uuid,
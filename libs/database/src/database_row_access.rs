@@ -0,0 +1,226 @@
+use app_error::AppError;
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::{AFDatabaseRowGrantRow, AFDatabaseRowRuleRow, AFDatabaseRowUserRow};
+
+pub async fn select_database_row_rule<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  database_id: &Uuid,
+) -> Result<Option<AFDatabaseRowRuleRow>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let rule = sqlx::query_as!(
+    AFDatabaseRowRuleRow,
+    r#"
+      SELECT database_id, user_field_id, updated_at
+      FROM af_database_row_rule
+      WHERE workspace_id = $1
+        AND database_id = $2
+    "#,
+    workspace_id,
+    database_id,
+  )
+  .fetch_optional(executor)
+  .await?;
+  Ok(rule)
+}
+
+/// Returns the databases of the workspace which have a row level rule.
+pub async fn select_database_row_rule_ids<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<Uuid>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let database_ids = sqlx::query_scalar!(
+    r#"
+      SELECT database_id
+      FROM af_database_row_rule
+      WHERE workspace_id = $1
+    "#,
+    workspace_id,
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(database_ids)
+}
+
+pub async fn upsert_database_row_rule<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  database_id: &Uuid,
+  user_field_id: Option<&str>,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      INSERT INTO af_database_row_rule (database_id, workspace_id, user_field_id)
+      VALUES ($1, $2, $3)
+      ON CONFLICT (database_id) DO UPDATE
+        SET user_field_id = EXCLUDED.user_field_id,
+            updated_at = CURRENT_TIMESTAMP
+    "#,
+    database_id,
+    workspace_id,
+    user_field_id,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Deletes the rule of the database, with its grants.
+pub async fn delete_database_row_rule<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  database_id: &Uuid,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      DELETE FROM af_database_row_rule
+      WHERE workspace_id = $1
+        AND database_id = $2
+    "#,
+    workspace_id,
+    database_id,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Grants each of the rows to each of the users.
+pub async fn insert_database_row_grants<'a, E>(
+  executor: E,
+  database_id: &Uuid,
+  row_ids: &[Uuid],
+  uids: &[i64],
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      INSERT INTO af_database_row_grant (database_id, row_id, uid)
+      SELECT $1, r.row_id, u.uid
+      FROM UNNEST($2::uuid[]) AS r(row_id)
+      CROSS JOIN UNNEST($3::bigint[]) AS u(uid)
+      ON CONFLICT DO NOTHING
+    "#,
+    database_id,
+    row_ids,
+    uids,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+pub async fn delete_database_row_grants<'a, E>(
+  executor: E,
+  database_id: &Uuid,
+  row_ids: &[Uuid],
+  uids: &[i64],
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      DELETE FROM af_database_row_grant
+      WHERE database_id = $1
+        AND row_id = ANY($2)
+        AND uid = ANY($3)
+    "#,
+    database_id,
+    row_ids,
+    uids,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+pub async fn select_database_row_grants<'a, E>(
+  executor: E,
+  database_id: &Uuid,
+) -> Result<Vec<AFDatabaseRowGrantRow>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let grants = sqlx::query_as!(
+    AFDatabaseRowGrantRow,
+    r#"
+      SELECT row_id, uid
+      FROM af_database_row_grant
+      WHERE database_id = $1
+      ORDER BY created_at
+    "#,
+    database_id,
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(grants)
+}
+
+pub async fn select_granted_database_row_ids<'a, E>(
+  executor: E,
+  database_id: &Uuid,
+  uid: i64,
+) -> Result<Vec<Uuid>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let row_ids = sqlx::query_scalar!(
+    r#"
+      SELECT row_id
+      FROM af_database_row_grant
+      WHERE database_id = $1
+        AND uid = $2
+    "#,
+    database_id,
+    uid,
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(row_ids)
+}
+
+/// Returns the identifiers of the user, which are matched against the cells of the user field of
+/// the rule, and the role of the user in the workspace, if the user is a member.
+pub async fn select_database_row_user<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  uid: i64,
+) -> Result<AFDatabaseRowUserRow, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let user = sqlx::query_as!(
+    AFDatabaseRowUserRow,
+    r#"
+      SELECT
+        u.uuid,
+        u.email,
+        m.role_id AS "role_id?"
+      FROM af_user u
+      LEFT JOIN af_workspace_member m
+        ON m.uid = u.uid AND m.workspace_id = $1
+      WHERE u.uid = $2
+    "#,
+    workspace_id,
+    uid,
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(user)
+}
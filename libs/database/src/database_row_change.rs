@@ -0,0 +1,222 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::AFDatabaseRowChangeRow;
+
+pub const ROW_CREATED: i16 = 0;
+pub const ROW_UPDATED: i16 = 1;
+pub const ROW_DELETED: i16 = 2;
+
+/// Changes are kept in the feed for this many days.
+pub const ROW_CHANGE_RETENTION_DAYS: i64 = 30;
+/// States of the collabs which have not been updated for this many days are dropped.
+pub const ROW_CHANGE_BASELINE_IDLE_DAYS: i64 = 1;
+
+/// A change of a row, recorded from the collab update stream.
+#[derive(Debug, Clone)]
+pub struct NewDatabaseRowChange {
+  pub database_id: Uuid,
+  pub row_id: Uuid,
+  /// One of [ROW_CREATED], [ROW_UPDATED] or [ROW_DELETED].
+  pub change_type: i16,
+  /// Changed cells by field id.
+  pub cells: serde_json::Value,
+  pub changed_at: DateTime<Utc>,
+}
+
+/// Appends the changes to the feeds of their database, in the given order.
+pub async fn insert_database_row_changes<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  changes: Vec<NewDatabaseRowChange>,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let count = changes.len();
+  let mut database_ids: Vec<Uuid> = Vec::with_capacity(count);
+  let mut row_ids: Vec<Uuid> = Vec::with_capacity(count);
+  let mut change_types: Vec<i16> = Vec::with_capacity(count);
+  let mut cells: Vec<serde_json::Value> = Vec::with_capacity(count);
+  let mut changed_ats: Vec<DateTime<Utc>> = Vec::with_capacity(count);
+  for change in changes {
+    database_ids.push(change.database_id);
+    row_ids.push(change.row_id);
+    change_types.push(change.change_type);
+    cells.push(change.cells);
+    changed_ats.push(change.changed_at);
+  }
+  sqlx::query!(
+    r#"
+      INSERT INTO af_database_row_change
+        (workspace_id, database_id, row_id, change_type, cells, changed_at)
+      SELECT $1, c.database_id, c.row_id, c.change_type, c.cells, c.changed_at
+      FROM UNNEST($2::UUID[], $3::UUID[], $4::SMALLINT[], $5::JSONB[], $6::TIMESTAMPTZ[])
+        WITH ORDINALITY AS c(database_id, row_id, change_type, cells, changed_at, position)
+      ORDER BY c.position
+    "#,
+    workspace_id,
+    &database_ids,
+    &row_ids,
+    &change_types,
+    &cells,
+    &changed_ats,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Returns the recorded states of the collabs, by object id.
+pub async fn select_database_row_change_baselines<'a, E>(
+  executor: E,
+  object_ids: &[Uuid],
+) -> Result<Vec<(Uuid, Vec<u8>)>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let baselines = sqlx::query!(
+    r#"
+      SELECT object_id, doc_state
+      FROM af_database_row_change_baseline
+      WHERE object_id = ANY($1)
+    "#,
+    object_ids,
+  )
+  .fetch_all(executor)
+  .await?
+  .into_iter()
+  .map(|row| (row.object_id, row.doc_state))
+  .collect();
+  Ok(baselines)
+}
+
+/// Records the states of the collabs, after their last recorded update.
+pub async fn upsert_database_row_change_baselines<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  object_ids: &[Uuid],
+  doc_states: &[Vec<u8>],
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      INSERT INTO af_database_row_change_baseline (object_id, workspace_id, doc_state)
+      SELECT object_id, $1, doc_state
+      FROM UNNEST($2::UUID[], $3::BYTEA[]) AS b(object_id, doc_state)
+      ON CONFLICT (object_id) DO UPDATE
+        SET doc_state = EXCLUDED.doc_state,
+            updated_at = CURRENT_TIMESTAMP
+    "#,
+    workspace_id,
+    object_ids,
+    doc_states,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+pub async fn delete_database_row_change_baselines<'a, E>(
+  executor: E,
+  object_ids: &[Uuid],
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      DELETE FROM af_database_row_change_baseline
+      WHERE object_id = ANY($1)
+    "#,
+    object_ids,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Deletes the changes of the workspace made before `before`.
+pub async fn delete_database_row_changes_before<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  before: DateTime<Utc>,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      DELETE FROM af_database_row_change
+      WHERE workspace_id = $1
+        AND changed_at < $2
+    "#,
+    workspace_id,
+    before,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Deletes the states of the collabs of the workspace, which have not been updated since
+/// `before`.
+pub async fn delete_database_row_change_baselines_before<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  before: DateTime<Utc>,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      DELETE FROM af_database_row_change_baseline
+      WHERE workspace_id = $1
+        AND updated_at < $2
+    "#,
+    workspace_id,
+    before,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Returns the changes of the database after the change `after`, in the order they happened.
+pub async fn select_database_row_changes<'a, E>(
+  executor: E,
+  database_id: &Uuid,
+  after: i64,
+  limit: i64,
+) -> Result<Vec<AFDatabaseRowChangeRow>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let changes = sqlx::query_as!(
+    AFDatabaseRowChangeRow,
+    r#"
+      SELECT
+        change_id,
+        row_id,
+        change_type,
+        cells,
+        changed_at
+      FROM af_database_row_change
+      WHERE database_id = $1
+        AND change_id > $2
+      ORDER BY change_id
+      LIMIT $3
+    "#,
+    database_id,
+    after,
+    limit,
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(changes)
+}
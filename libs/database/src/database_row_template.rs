@@ -0,0 +1,344 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::{
+  AFDatabaseRowTemplateRow, AFDatabaseRowTemplateRunRow, AFDueDatabaseRowTemplateRow,
+};
+
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_database_row_template<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  database_id: &Uuid,
+  name: &str,
+  cells: &serde_json::Value,
+  row_doc_content: Option<&str>,
+  schedule: Option<&str>,
+  next_run_at: Option<DateTime<Utc>>,
+  created_by: i64,
+) -> Result<AFDatabaseRowTemplateRow, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let template = sqlx::query_as!(
+    AFDatabaseRowTemplateRow,
+    r#"
+      INSERT INTO af_database_row_template (
+        workspace_id, database_id, name, cells, row_doc_content, schedule, next_run_at, created_by
+      )
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+      RETURNING
+        template_id,
+        workspace_id,
+        database_id,
+        name,
+        cells,
+        row_doc_content,
+        schedule,
+        next_run_at,
+        created_by,
+        created_at,
+        updated_at
+    "#,
+    workspace_id,
+    database_id,
+    name,
+    cells,
+    row_doc_content,
+    schedule,
+    next_run_at,
+    created_by,
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(template)
+}
+
+pub async fn select_database_row_templates<'a, E>(
+  executor: E,
+  workspace_id: &Uuid,
+  database_id: &Uuid,
+) -> Result<Vec<AFDatabaseRowTemplateRow>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let templates = sqlx::query_as!(
+    AFDatabaseRowTemplateRow,
+    r#"
+      SELECT
+        template_id,
+        workspace_id,
+        database_id,
+        name,
+        cells,
+        row_doc_content,
+        schedule,
+        next_run_at,
+        created_by,
+        created_at,
+        updated_at
+      FROM af_database_row_template
+      WHERE workspace_id = $1
+        AND database_id = $2
+      ORDER BY created_at
+    "#,
+    workspace_id,
+    database_id,
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(templates)
+}
+
+pub async fn select_database_row_template<'a, E>(
+  executor: E,
+  template_id: &Uuid,
+) -> Result<Option<AFDatabaseRowTemplateRow>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let template = sqlx::query_as!(
+    AFDatabaseRowTemplateRow,
+    r#"
+      SELECT
+        template_id,
+        workspace_id,
+        database_id,
+        name,
+        cells,
+        row_doc_content,
+        schedule,
+        next_run_at,
+        created_by,
+        created_at,
+        updated_at
+      FROM af_database_row_template
+      WHERE template_id = $1
+    "#,
+    template_id,
+  )
+  .fetch_optional(executor)
+  .await?;
+  Ok(template)
+}
+
+/// Replaces the content and the schedule of the template.
+pub async fn update_database_row_template<'a, E>(
+  executor: E,
+  template_id: &Uuid,
+  name: &str,
+  cells: &serde_json::Value,
+  row_doc_content: Option<&str>,
+  schedule: Option<&str>,
+  next_run_at: Option<DateTime<Utc>>,
+) -> Result<AFDatabaseRowTemplateRow, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let template = sqlx::query_as!(
+    AFDatabaseRowTemplateRow,
+    r#"
+      UPDATE af_database_row_template
+      SET name = $2,
+          cells = $3,
+          row_doc_content = $4,
+          schedule = $5,
+          next_run_at = $6,
+          updated_at = CURRENT_TIMESTAMP
+      WHERE template_id = $1
+      RETURNING
+        template_id,
+        workspace_id,
+        database_id,
+        name,
+        cells,
+        row_doc_content,
+        schedule,
+        next_run_at,
+        created_by,
+        created_at,
+        updated_at
+    "#,
+    template_id,
+    name,
+    cells,
+    row_doc_content,
+    schedule,
+    next_run_at,
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(template)
+}
+
+/// Deletes the template, with its pending runs.
+pub async fn delete_database_row_template<'a, E>(
+  executor: E,
+  template_id: &Uuid,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      DELETE FROM af_database_row_template
+      WHERE template_id = $1
+    "#,
+    template_id,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Returns the scheduled templates whose next run is due, locked until the end of the
+/// transaction. The templates locked by another scheduler are skipped.
+pub async fn select_due_database_row_templates<'a, E>(
+  executor: E,
+  limit: i64,
+) -> Result<Vec<AFDueDatabaseRowTemplateRow>, AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  let templates = sqlx::query_as!(
+    AFDueDatabaseRowTemplateRow,
+    r#"
+      SELECT
+        template_id,
+        schedule,
+        next_run_at AS "next_run_at!"
+      FROM af_database_row_template
+      WHERE next_run_at <= NOW()
+      ORDER BY next_run_at
+      LIMIT $1
+      FOR UPDATE SKIP LOCKED
+    "#,
+    limit,
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(templates)
+}
+
+pub async fn update_database_row_template_next_run<'a, E>(
+  executor: E,
+  template_id: &Uuid,
+  next_run_at: Option<DateTime<Utc>>,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      UPDATE af_database_row_template
+      SET next_run_at = $2
+      WHERE template_id = $1
+    "#,
+    template_id,
+    next_run_at,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Queues a run of the template. A run which has already been queued for the same time is
+/// ignored.
+pub async fn insert_database_row_template_run<'a, E>(
+  executor: E,
+  template_id: &Uuid,
+  scheduled_at: DateTime<Utc>,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      INSERT INTO af_database_row_template_run (template_id, scheduled_at)
+      VALUES ($1, $2)
+      ON CONFLICT (template_id, scheduled_at) DO NOTHING
+    "#,
+    template_id,
+    scheduled_at,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Claims the oldest pending run, or a run which has been running for longer than `stale_secs`.
+pub async fn claim_next_database_row_template_run(
+  pg_pool: &PgPool,
+  stale_secs: i64,
+) -> Result<Option<AFDatabaseRowTemplateRunRow>, AppError> {
+  let run = sqlx::query_as!(
+    AFDatabaseRowTemplateRunRow,
+    r#"
+      UPDATE af_database_row_template_run
+      SET status = 1, started_at = NOW()
+      WHERE run_id = (
+        SELECT run_id
+        FROM af_database_row_template_run
+        WHERE status = 0
+          OR (status = 1 AND started_at < NOW() - make_interval(secs => $1))
+        ORDER BY created_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+      )
+      RETURNING
+        run_id,
+        template_id,
+        scheduled_at
+    "#,
+    stale_secs as f64,
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+  Ok(run)
+}
+
+pub async fn update_database_row_template_run_completed<'a, E>(
+  executor: E,
+  run_id: &Uuid,
+  row_id: &Uuid,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      UPDATE af_database_row_template_run
+      SET status = 2, row_id = $2, completed_at = NOW()
+      WHERE run_id = $1
+    "#,
+    run_id,
+    row_id,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+pub async fn update_database_row_template_run_failed<'a, E>(
+  executor: E,
+  run_id: &Uuid,
+  error: &str,
+) -> Result<(), AppError>
+where
+  E: Executor<'a, Database = Postgres>,
+{
+  sqlx::query!(
+    r#"
+      UPDATE af_database_row_template_run
+      SET status = 3, error = $2, completed_at = NOW()
+      WHERE run_id = $1
+    "#,
+    run_id,
+    error,
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
@@ -100,6 +100,36 @@ impl AwsS3BucketClientImpl {
     Ok(public_url)
   }
 
+  /// Generates a url to download the object, which is valid for the given duration.
+  pub async fn gen_presigned_download_url(
+    &self,
+    s3_key: &str,
+    expires_in_secs: u64,
+  ) -> Result<String, AppError> {
+    let config = PresigningConfig::builder()
+      .start_time(SystemTime::now())
+      .expires_in(Duration::from_secs(expires_in_secs))
+      .build()
+      .map_err(|e| AppError::S3ResponseError(e.to_string()))?;
+    let get_object_req = self
+      .client
+      .get_object()
+      .bucket(&self.bucket)
+      .key(s3_key)
+      .presigned(config)
+      .await
+      .map_err(|err| AppError::Internal(anyhow!("Generate presigned url failed: {:?}", err)))?;
+    let url = get_object_req.uri().to_string();
+    Ok(
+      self
+        .presigned_url_endpoint
+        .as_ref()
+        .map_or(url.clone(), |presigned| {
+          url.replace(&self.endpoint, presigned)
+        }),
+    )
+  }
+
   async fn complete_upload_and_get_metadata(
     &self,
     object_key: &str,
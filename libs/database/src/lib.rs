@@ -1,6 +1,10 @@
 pub mod access_request;
+pub mod blob_gc;
 pub mod chat;
 pub mod collab;
+pub mod database_row_access;
+pub mod database_row_change;
+pub mod database_row_template;
 pub mod file;
 pub mod history;
 pub mod index;
@@ -8,8 +12,14 @@ pub mod listener;
 pub mod notification;
 pub mod pg_row;
 pub mod publish;
+pub mod publish_analytics;
+pub mod publish_comment;
+pub mod publish_domain;
+pub mod publish_export;
 pub mod quick_note;
 pub mod resource_usage;
 pub mod template;
+pub mod trash;
 pub mod user;
+pub mod user_data_export;
 pub mod workspace;
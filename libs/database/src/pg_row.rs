@@ -5,8 +5,9 @@ use chrono::{DateTime, Utc};
 use database_entity::dto::{
   AFAccessLevel, AFRole, AFUserProfile, AFWebUser, AFWebUserWithObfuscatedName, AFWorkspace,
   AFWorkspaceInvitationStatus, AFWorkspaceMember, AccessRequestMinimal, AccessRequestStatus,
-  AccessRequestWithViewId, AccessRequesterInfo, AccountLink, GlobalComment, QuickNote, Reaction,
-  Template, TemplateCategory, TemplateCategoryMinimal, TemplateCategoryType, TemplateCreator,
+  AccessRequestWithViewId, AccessRequesterInfo, AccountLink, GlobalComment,
+  PublishedCommentBlockedUser, PublishedCommentModerationItem, QuickNote, Reaction, Template,
+  TemplateCategory, TemplateCategoryMinimal, TemplateCategoryType, TemplateCreator,
   TemplateCreatorMinimal, TemplateGroup, TemplateMinimal,
 };
 use serde::{Deserialize, Serialize};
@@ -443,6 +444,7 @@ pub struct AFGlobalCommentRow {
   pub comment_id: Uuid,
   pub is_deleted: bool,
   pub can_be_deleted: bool,
+  pub status: i16,
 }
 
 impl From<AFGlobalCommentRow> for GlobalComment {
@@ -456,10 +458,63 @@ impl From<AFGlobalCommentRow> for GlobalComment {
       comment_id: val.comment_id,
       is_deleted: val.is_deleted,
       can_be_deleted: val.can_be_deleted,
+      status: val.status.into(),
+    }
+  }
+}
+
+pub struct AFPublishedCommentModerationRow {
+  pub comment_id: Uuid,
+  pub view_id: Uuid,
+  pub publish_name: String,
+  pub reply_comment_id: Option<Uuid>,
+  pub content: String,
+  pub user: Option<AFWebUserWithEmailColumn>,
+  pub status: i16,
+  pub is_deleted: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<AFPublishedCommentModerationRow> for PublishedCommentModerationItem {
+  fn from(val: AFPublishedCommentModerationRow) -> Self {
+    PublishedCommentModerationItem {
+      comment_id: val.comment_id,
+      view_id: val.view_id,
+      publish_name: val.publish_name,
+      reply_comment_id: val.reply_comment_id,
+      content: val.content,
+      user: val.user.map(|x| x.into()),
+      status: val.status.into(),
+      is_deleted: val.is_deleted,
+      created_at: val.created_at,
+    }
+  }
+}
+
+pub struct AFPublishedCommentBlockedUserRow {
+  pub user: AFWebUserWithEmailColumn,
+  pub reason: Option<String>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<AFPublishedCommentBlockedUserRow> for PublishedCommentBlockedUser {
+  fn from(val: AFPublishedCommentBlockedUserRow) -> Self {
+    PublishedCommentBlockedUser {
+      user: val.user.into(),
+      reason: val.reason,
+      created_at: val.created_at,
     }
   }
 }
 
+/// Moderation rules applying to a new comment on a published view.
+pub struct AFPublishedCommentPolicyRow {
+  pub workspace_id: Uuid,
+  pub require_approval: bool,
+  pub is_blocked: bool,
+  pub is_member: bool,
+}
+
 pub struct AFReactionRow {
   pub reaction_type: String,
   pub react_users: Vec<AFWebUserWithEmailColumn>,
@@ -825,6 +880,179 @@ pub struct AFPublishViewWithPublishInfo {
   pub duplicate_enabled: bool,
 }
 
+#[derive(Debug)]
+pub struct AFPublishedCollabAccess {
+  pub view_id: Uuid,
+  pub password_hash: Option<String>,
+}
+
+/// A published page, which is publicly listed in the sitemap and feeds of its namespace.
+#[derive(Debug, FromRow)]
+pub struct AFPublishedFeedEntry {
+  pub view_id: Uuid,
+  pub publish_name: String,
+  pub metadata: serde_json::Value,
+  pub published_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFPublishDomainRow {
+  pub workspace_id: Uuid,
+  pub domain: String,
+  pub verification_token: String,
+  pub verified_at: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFPublishedSiteExportRow {
+  pub export_id: Uuid,
+  pub workspace_id: Uuid,
+  pub namespace: String,
+  pub status: i16,
+  pub object_prefix: Option<String>,
+  pub page_count: i32,
+  pub error: Option<String>,
+  pub created_at: DateTime<Utc>,
+  pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFWorkspaceTrashRetentionRow {
+  pub workspace_id: Uuid,
+  pub owner_uid: i64,
+  pub trash_retention_days: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFOrphanedBlobRow {
+  pub file_id: String,
+  pub file_type: String,
+  pub file_size: i64,
+  pub modified_at: DateTime<Utc>,
+  pub marked_at: DateTime<Utc>,
+  pub delete_after: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFUserDataExportRow {
+  pub export_id: Uuid,
+  pub uid: i64,
+  pub status: i16,
+  pub object_key: Option<String>,
+  pub file_size: i64,
+  pub error: Option<String>,
+  pub created_at: DateTime<Utc>,
+  pub completed_at: Option<DateTime<Utc>>,
+  pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFUserOwnedCollabRow {
+  pub workspace_id: Uuid,
+  pub oid: Uuid,
+  pub partition_key: i32,
+}
+
+#[derive(FromRow, Serialize, Debug)]
+pub struct AFUserChatMessageRow {
+  pub workspace_id: Uuid,
+  pub chat_id: Uuid,
+  pub chat_name: String,
+  pub message_id: i64,
+  pub author: serde_json::Value,
+  pub content: String,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Serialize, Debug)]
+pub struct AFUserQuickNoteRow {
+  pub workspace_id: Uuid,
+  pub quick_note_id: Uuid,
+  pub data: serde_json::Value,
+  pub created_at: Option<DateTime<Utc>>,
+  pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow, Serialize, Debug)]
+pub struct AFUserPublishedCommentRow {
+  pub comment_id: Uuid,
+  pub view_id: Uuid,
+  pub reply_comment_id: Option<Uuid>,
+  pub content: String,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFUserBlobRow {
+  pub workspace_id: Uuid,
+  pub file_id: String,
+  pub file_type: String,
+  pub file_size: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFDatabaseRowChangeRow {
+  pub change_id: i64,
+  pub row_id: Uuid,
+  pub change_type: i16,
+  pub cells: serde_json::Value,
+  pub changed_at: DateTime<Utc>,
+}
+
+/// Row level access rule of a database.
+#[derive(Debug, FromRow)]
+pub struct AFDatabaseRowRuleRow {
+  pub database_id: Uuid,
+  pub user_field_id: Option<String>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFDatabaseRowGrantRow {
+  pub row_id: Uuid,
+  pub uid: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFDatabaseRowUserRow {
+  pub uuid: Uuid,
+  pub email: String,
+  /// Role of the user in the workspace, if the user is a member.
+  pub role_id: Option<i32>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFDatabaseRowTemplateRow {
+  pub template_id: Uuid,
+  pub workspace_id: Uuid,
+  pub database_id: Uuid,
+  pub name: String,
+  pub cells: serde_json::Value,
+  pub row_doc_content: Option<String>,
+  pub schedule: Option<String>,
+  pub next_run_at: Option<DateTime<Utc>>,
+  pub created_by: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFDueDatabaseRowTemplateRow {
+  pub template_id: Uuid,
+  pub schedule: Option<String>,
+  pub next_run_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AFDatabaseRowTemplateRunRow {
+  pub run_id: Uuid,
+  pub template_id: Uuid,
+  pub scheduled_at: DateTime<Utc>,
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -1,11 +1,12 @@
 use app_error::AppError;
+use chrono::{DateTime, Utc};
 use database_entity::dto::{
   PatchPublishedCollab, PublishCollabItem, PublishCollabKey, PublishInfo, WorkspaceNamespace,
 };
 use sqlx::{Executor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
-use crate::pg_row::AFPublishViewWithPublishInfo;
+use crate::pg_row::{AFPublishViewWithPublishInfo, AFPublishedCollabAccess, AFPublishedFeedEntry};
 
 pub async fn select_user_is_collab_publisher_for_all_views(
   pg_pool: &PgPool,
@@ -242,11 +243,24 @@ async fn delete_published_collabs(
 }
 
 #[inline]
+/// Access settings of a published collab, written along with it.
+#[derive(Debug)]
+pub struct PublishedCollabAccessUpdate {
+  pub view_id: Uuid,
+  /// Hash of the password, `Some(None)` removes the password protection.
+  pub password_hash: Option<Option<String>>,
+  /// `Some(None)` removes the expiration date.
+  pub expires_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// Publishes the collabs, and updates their access settings in the same transaction, so that a
+/// protected collab is never visible without its protection.
 pub async fn insert_or_replace_publish_collabs(
   pg_pool: &PgPool,
   workspace_id: &Uuid,
   publisher_uuid: &Uuid,
   publish_items: Vec<PublishCollabItem<serde_json::Value, Vec<u8>>>,
+  access_updates: &[PublishedCollabAccessUpdate],
 ) -> Result<(), AppError> {
   let item_count = publish_items.len();
   let mut view_ids: Vec<Uuid> = Vec::with_capacity(item_count);
@@ -306,6 +320,31 @@ pub async fn insert_or_replace_publish_collabs(
     );
   }
 
+  let expiration_patches: Vec<PatchPublishedCollab> = access_updates
+    .iter()
+    .filter(|update| update.expires_at.is_some())
+    .map(|update| PatchPublishedCollab {
+      view_id: update.view_id,
+      publish_name: None,
+      comments_enabled: None,
+      duplicate_enabled: None,
+      password: None,
+      expires_at: update.expires_at,
+    })
+    .collect();
+  update_published_collabs(&mut txn, workspace_id, &expiration_patches).await?;
+  for update in access_updates {
+    if let Some(password_hash) = &update.password_hash {
+      update_published_collab_password(
+        &mut txn,
+        workspace_id,
+        &update.view_id,
+        password_hash.as_deref(),
+      )
+      .await?;
+    }
+  }
+
   txn.commit().await?;
   Ok(())
 }
@@ -322,6 +361,7 @@ pub async fn select_publish_collab_meta<'a, E: Executor<'a, Database = Postgres>
     FROM af_published_collab
     WHERE workspace_id = (SELECT workspace_id FROM af_workspace_namespace WHERE namespace = $1)
       AND unpublished_at IS NULL
+      AND (expires_at IS NULL OR expires_at > NOW())
       AND publish_name = $2
     "#,
     publish_namespace,
@@ -404,9 +444,22 @@ pub async fn update_published_collabs(
       if !first_set {
         query_builder.push(",");
       }
+      first_set = false;
       query_builder.push(" publish_name = ");
       query_builder.push_bind(publish_name);
     }
+    if let Some(expires_at) = patch.expires_at {
+      if !first_set {
+        query_builder.push(",");
+      }
+      first_set = false;
+      query_builder.push(" expires_at = ");
+      query_builder.push_bind(expires_at);
+    }
+    if first_set {
+      // nothing to update, e.g. only the password is patched
+      continue;
+    }
     query_builder.push(" WHERE workspace_id = ");
     query_builder.push_bind(workspace_id);
     query_builder.push(" AND view_id = ");
@@ -427,6 +480,120 @@ pub async fn update_published_collabs(
   Ok(())
 }
 
+/// Sets or removes (if `password_hash` is `None`) password protection of a published collab.
+#[inline]
+pub async fn update_published_collab_password(
+  txn: &mut sqlx::Transaction<'_, Postgres>,
+  workspace_id: &Uuid,
+  view_id: &Uuid,
+  password_hash: Option<&str>,
+) -> Result<(), AppError> {
+  let res = sqlx::query!(
+    r#"
+      UPDATE af_published_collab
+      SET password_hash = $3
+      WHERE workspace_id = $1
+        AND view_id = $2
+    "#,
+    workspace_id,
+    view_id,
+    password_hash,
+  )
+  .execute(txn.as_mut())
+  .await?;
+
+  if res.rows_affected() != 1 {
+    tracing::error!(
+      "Failed to update published collab password, workspace_id: {}, view_id: {}, rows_affected: {}",
+      workspace_id,
+      view_id,
+      res.rows_affected()
+    );
+  }
+
+  Ok(())
+}
+
+/// Returns access settings of the published collab, unless it was unpublished or has expired.
+#[inline]
+pub async fn select_published_collab_access<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  publish_namespace: &str,
+  publish_name: &str,
+) -> Result<Option<AFPublishedCollabAccess>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedCollabAccess,
+    r#"
+      SELECT view_id, password_hash
+      FROM af_published_collab
+      WHERE workspace_id = (SELECT workspace_id FROM af_workspace_namespace WHERE namespace = $1)
+        AND unpublished_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
+        AND publish_name = $2
+    "#,
+    publish_namespace,
+    publish_name,
+  )
+  .fetch_optional(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the pages, which are publicly accessible under the namespace, most recently published
+/// first. Password protected pages are excluded.
+pub async fn select_published_feed_entries(
+  pg_pool: &PgPool,
+  publish_namespace: &str,
+) -> Result<Vec<AFPublishedFeedEntry>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedFeedEntry,
+    r#"
+      SELECT
+        view_id,
+        publish_name,
+        metadata,
+        created_at AS published_at,
+        updated_at
+      FROM af_published_collab
+      WHERE workspace_id = (SELECT workspace_id FROM af_workspace_namespace WHERE namespace = $1)
+        AND unpublished_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
+        AND password_hash IS NULL
+      ORDER BY created_at DESC
+    "#,
+    publish_namespace,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+/// Same as [select_published_collab_access], for the published collab identified by its view id.
+#[inline]
+pub async fn select_published_view_access<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  view_id: &Uuid,
+) -> Result<Option<AFPublishedCollabAccess>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedCollabAccess,
+    r#"
+      SELECT view_id, password_hash
+      FROM af_published_collab
+      WHERE view_id = $1
+        AND unpublished_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
+    "#,
+    view_id,
+  )
+  .fetch_optional(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the metadata of the published collab, unless it was unpublished or has expired.
 #[inline]
 pub async fn select_published_metadata_for_view_id(
   pg_pool: &PgPool,
@@ -437,6 +604,8 @@ pub async fn select_published_metadata_for_view_id(
       SELECT workspace_id, metadata
       FROM af_published_collab
       WHERE view_id = $1
+        AND unpublished_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
     "#,
     view_id,
   )
@@ -445,6 +614,8 @@ pub async fn select_published_metadata_for_view_id(
   Ok(res.map(|res| (res.workspace_id, res.metadata)))
 }
 
+/// Returns the metadata and data of the published collab, unless it was unpublished or has
+/// expired.
 #[inline]
 pub async fn select_published_data_for_view_id(
   pg_pool: &PgPool,
@@ -455,6 +626,8 @@ pub async fn select_published_data_for_view_id(
       SELECT metadata, blob
       FROM af_published_collab
       WHERE view_id = $1
+        AND unpublished_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
     "#,
     view_id,
   )
@@ -475,6 +648,7 @@ pub async fn select_published_collab_workspace_view_id<'a, E: Executor<'a, Datab
       SELECT workspace_id, view_id
       FROM af_published_collab
       WHERE workspace_id = (SELECT workspace_id FROM af_workspace_namespace WHERE namespace = $1)
+      AND (expires_at IS NULL OR expires_at > NOW())
       AND publish_name = $2
     "#,
     publish_namespace,
@@ -497,6 +671,7 @@ pub async fn select_published_collab_blob<'a, E: Executor<'a, Database = Postgre
       FROM af_published_collab
       WHERE workspace_id = (SELECT workspace_id FROM af_workspace_namespace WHERE namespace = $1)
         AND unpublished_at IS NULL
+        AND (expires_at IS NULL OR expires_at > NOW())
         AND publish_name = $2
     "#,
     publish_namespace,
@@ -581,9 +756,14 @@ pub async fn select_publish_info_for_view_ids(
         apc.view_id,
         au.email AS publisher_email,
         apc.created_at AS publish_timestamp,
-        apc.unpublished_at AS unpublished_timestamp,
+        COALESCE(
+          apc.unpublished_at,
+          CASE WHEN apc.expires_at <= NOW() THEN apc.expires_at END
+        ) AS unpublished_timestamp,
         apc.comments_enabled,
-        apc.duplicate_enabled
+        apc.duplicate_enabled,
+        apc.expires_at,
+        apc.password_hash IS NOT NULL AS "password_protected!"
       FROM af_published_collab apc
       JOIN af_user au ON apc.published_by = au.uid
       JOIN af_workspace aw ON apc.workspace_id = aw.workspace_id
@@ -634,12 +814,16 @@ pub async fn select_all_published_collab_info(
         apc.created_at AS publish_timestamp,
         apc.unpublished_at AS unpublished_timestamp,
         apc.comments_enabled,
-        apc.duplicate_enabled
+        apc.duplicate_enabled,
+        apc.expires_at,
+        apc.password_hash IS NOT NULL AS "password_protected!"
       FROM af_published_collab apc
       JOIN af_user au ON apc.published_by = au.uid
       JOIN af_workspace aw ON apc.workspace_id = aw.workspace_id
       JOIN af_workspace_namespace awn ON aw.workspace_id = awn.workspace_id AND awn.is_original = TRUE
-      WHERE apc.workspace_id = $1 AND apc.unpublished_at IS NULL;
+      WHERE apc.workspace_id = $1
+        AND apc.unpublished_at IS NULL
+        AND (apc.expires_at IS NULL OR apc.expires_at > NOW());
     "#,
     workspace_id,
   )
@@ -696,6 +880,7 @@ pub async fn select_published_view_ids_for_workspace<'a, E: Executor<'a, Databas
       FROM af_published_collab
       WHERE workspace_id = $1
       AND unpublished_at IS NULL
+      AND (expires_at IS NULL OR expires_at > NOW())
     "#,
     workspace_id,
   )
@@ -726,6 +911,7 @@ pub async fn select_published_view_ids_with_publish_info_for_workspace<
       JOIN af_user au ON apc.published_by = au.uid
       WHERE workspace_id = $1
       AND unpublished_at IS NULL
+      AND (expires_at IS NULL OR expires_at > NOW())
     "#,
     workspace_id,
   )
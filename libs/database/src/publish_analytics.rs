@@ -0,0 +1,265 @@
+use app_error::AppError;
+use chrono::NaiveDate;
+use database_entity::dto::{
+  PublishedViewBreakdown, PublishedViewDailyStats, PublishedViewStatsSummary,
+};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+pub const BREAKDOWN_REFERRER: &str = "referrer";
+pub const BREAKDOWN_COUNTRY: &str = "country";
+
+/// Inserts visitors seen on a given day, returning only the ones which were not seen yet.
+pub async fn insert_published_view_visitors(
+  txn: &mut sqlx::Transaction<'_, Postgres>,
+  view_ids: &[Uuid],
+  days: &[NaiveDate],
+  visitor_hashes: &[Vec<u8>],
+) -> Result<Vec<(Uuid, NaiveDate)>, AppError> {
+  let rows = sqlx::query!(
+    r#"
+      INSERT INTO af_published_view_daily_visitor (view_id, day, visitor_hash)
+      SELECT * FROM UNNEST($1::uuid[], $2::date[], $3::bytea[])
+      ON CONFLICT DO NOTHING
+      RETURNING view_id, day
+    "#,
+    view_ids,
+    days,
+    visitor_hashes,
+  )
+  .fetch_all(txn.as_mut())
+  .await?;
+
+  Ok(rows.into_iter().map(|row| (row.view_id, row.day)).collect())
+}
+
+pub async fn upsert_published_view_daily_stats(
+  txn: &mut sqlx::Transaction<'_, Postgres>,
+  workspace_ids: &[Uuid],
+  view_ids: &[Uuid],
+  days: &[NaiveDate],
+  views: &[i64],
+  unique_visitors: &[i64],
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      INSERT INTO af_published_view_daily_stats (workspace_id, view_id, day, views, unique_visitors)
+      SELECT * FROM UNNEST($1::uuid[], $2::uuid[], $3::date[], $4::bigint[], $5::bigint[])
+      ON CONFLICT (view_id, day) DO UPDATE
+      SET views = af_published_view_daily_stats.views + EXCLUDED.views,
+          unique_visitors = af_published_view_daily_stats.unique_visitors + EXCLUDED.unique_visitors
+    "#,
+    workspace_ids,
+    view_ids,
+    days,
+    views,
+    unique_visitors,
+  )
+  .execute(txn.as_mut())
+  .await?;
+
+  Ok(())
+}
+
+pub async fn upsert_published_view_daily_breakdown(
+  txn: &mut sqlx::Transaction<'_, Postgres>,
+  workspace_ids: &[Uuid],
+  view_ids: &[Uuid],
+  days: &[NaiveDate],
+  dimensions: &[String],
+  values: &[String],
+  views: &[i64],
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      INSERT INTO af_published_view_daily_breakdown (workspace_id, view_id, day, dimension, value, views)
+      SELECT * FROM UNNEST($1::uuid[], $2::uuid[], $3::date[], $4::text[], $5::text[], $6::bigint[])
+      ON CONFLICT (view_id, day, dimension, value) DO UPDATE
+      SET views = af_published_view_daily_breakdown.views + EXCLUDED.views
+    "#,
+    workspace_ids,
+    view_ids,
+    days,
+    dimensions,
+    values,
+    views,
+  )
+  .execute(txn.as_mut())
+  .await?;
+
+  Ok(())
+}
+
+/// Removes visitor hashes, which are no longer needed to count unique visitors.
+pub async fn delete_published_view_visitors_before<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  day: NaiveDate,
+) -> Result<u64, AppError> {
+  let res = sqlx::query!(
+    r#"
+      DELETE FROM af_published_view_daily_visitor
+      WHERE day < $1
+    "#,
+    day,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(res.rows_affected())
+}
+
+/// Returns the salt of the visitor hashes of the given day, using the given salt if none was set
+/// for the day yet.
+pub async fn upsert_published_view_daily_salt<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  day: NaiveDate,
+  salt: &[u8],
+) -> Result<Vec<u8>, AppError> {
+  let salt = sqlx::query_scalar!(
+    r#"
+      INSERT INTO af_published_view_daily_salt (day, salt)
+      VALUES ($1, $2)
+      ON CONFLICT (day) DO UPDATE SET salt = af_published_view_daily_salt.salt
+      RETURNING salt
+    "#,
+    day,
+    salt,
+  )
+  .fetch_one(executor)
+  .await?;
+
+  Ok(salt)
+}
+
+pub async fn delete_published_view_daily_salts_before<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  day: NaiveDate,
+) -> Result<u64, AppError> {
+  let res = sqlx::query!(
+    r#"
+      DELETE FROM af_published_view_daily_salt
+      WHERE day < $1
+    "#,
+    day,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(res.rows_affected())
+}
+
+pub async fn select_published_view_daily_stats(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  view_id: &Uuid,
+  since: NaiveDate,
+) -> Result<Vec<PublishedViewDailyStats>, AppError> {
+  let res = sqlx::query_as!(
+    PublishedViewDailyStats,
+    r#"
+      SELECT day, views, unique_visitors
+      FROM af_published_view_daily_stats
+      WHERE workspace_id = $1
+        AND view_id = $2
+        AND day >= $3
+      ORDER BY day
+    "#,
+    workspace_id,
+    view_id,
+    since,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_published_view_breakdown(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  view_id: &Uuid,
+  since: NaiveDate,
+  dimension: &str,
+  limit: i64,
+) -> Result<Vec<PublishedViewBreakdown>, AppError> {
+  let res = sqlx::query_as!(
+    PublishedViewBreakdown,
+    r#"
+      SELECT value, SUM(views)::BIGINT AS "views!"
+      FROM af_published_view_daily_breakdown
+      WHERE workspace_id = $1
+        AND view_id = $2
+        AND day >= $3
+        AND dimension = $4
+      GROUP BY value
+      ORDER BY 2 DESC
+      LIMIT $5
+    "#,
+    workspace_id,
+    view_id,
+    since,
+    dimension,
+    limit,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_workspace_published_daily_stats(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  since: NaiveDate,
+) -> Result<Vec<PublishedViewDailyStats>, AppError> {
+  let res = sqlx::query_as!(
+    PublishedViewDailyStats,
+    r#"
+      SELECT
+        day,
+        SUM(views)::BIGINT AS "views!",
+        SUM(unique_visitors)::BIGINT AS "unique_visitors!"
+      FROM af_published_view_daily_stats
+      WHERE workspace_id = $1
+        AND day >= $2
+      GROUP BY day
+      ORDER BY day
+    "#,
+    workspace_id,
+    since,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_workspace_published_view_stats(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  since: NaiveDate,
+) -> Result<Vec<PublishedViewStatsSummary>, AppError> {
+  let res = sqlx::query_as!(
+    PublishedViewStatsSummary,
+    r#"
+      SELECT
+        s.view_id,
+        apc.publish_name AS "publish_name?",
+        SUM(s.views)::BIGINT AS "views!",
+        SUM(s.unique_visitors)::BIGINT AS "unique_visitors!"
+      FROM af_published_view_daily_stats s
+      LEFT JOIN af_published_collab apc
+        ON apc.workspace_id = s.workspace_id AND apc.view_id = s.view_id
+      WHERE s.workspace_id = $1
+        AND s.day >= $2
+      GROUP BY s.view_id, apc.publish_name
+      ORDER BY 3 DESC
+    "#,
+    workspace_id,
+    since,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
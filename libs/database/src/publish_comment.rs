@@ -0,0 +1,246 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use database_entity::dto::{PublishedCommentSetting, PublishedCommentStatus};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::{
+  AFPublishedCommentBlockedUserRow, AFPublishedCommentModerationRow, AFPublishedCommentPolicyRow,
+  AFWebUserWithEmailColumn,
+};
+
+/// Returns the moderation rules applying to a new comment of the user on the published view, or
+/// None if the view is not published, was unpublished or has expired.
+pub async fn select_published_comment_policy<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  view_id: &Uuid,
+  user_uuid: &Uuid,
+) -> Result<Option<AFPublishedCommentPolicyRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedCommentPolicyRow,
+    r#"
+      WITH commenter AS (SELECT uid FROM af_user WHERE uuid = $2)
+      SELECT
+        apc.workspace_id,
+        COALESCE(setting.require_approval, FALSE) AS "require_approval!",
+        EXISTS(
+          SELECT 1 FROM af_published_comment_blocklist blocklist
+          WHERE blocklist.workspace_id = apc.workspace_id
+            AND blocklist.uid = (SELECT uid FROM commenter)
+        ) AS "is_blocked!",
+        EXISTS(
+          SELECT 1 FROM af_workspace_member member
+          WHERE member.workspace_id = apc.workspace_id
+            AND member.uid = (SELECT uid FROM commenter)
+        ) AS "is_member!"
+      FROM af_published_collab apc
+      LEFT JOIN af_published_comment_setting setting ON setting.workspace_id = apc.workspace_id
+      WHERE apc.view_id = $1
+        AND apc.unpublished_at IS NULL
+        AND (apc.expires_at IS NULL OR apc.expires_at > NOW())
+    "#,
+    view_id,
+    user_uuid,
+  )
+  .fetch_optional(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the view the comment belongs to, or None if the comment does not exist or can no
+/// longer be replied to.
+pub async fn select_view_id_of_repliable_comment<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  comment_id: &Uuid,
+) -> Result<Option<Uuid>, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT view_id
+      FROM af_published_view_comment
+      WHERE comment_id = $1
+        AND NOT is_deleted
+        AND status = 0
+    "#,
+    comment_id,
+  )
+  .fetch_optional(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_published_comments_for_moderation<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  status: Option<PublishedCommentStatus>,
+  before: Option<DateTime<Utc>>,
+  limit: i64,
+) -> Result<Vec<AFPublishedCommentModerationRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedCommentModerationRow,
+    r#"
+      SELECT
+        avc.comment_id,
+        avc.view_id,
+        apc.publish_name,
+        avc.reply_comment_id,
+        avc.content,
+        (au.uuid, au.name, au.email, au.metadata ->> 'icon_url') AS "user: AFWebUserWithEmailColumn",
+        avc.status,
+        avc.is_deleted,
+        avc.created_at
+      FROM af_published_view_comment avc
+      JOIN af_published_collab apc ON apc.view_id = avc.view_id
+      LEFT OUTER JOIN af_user au ON avc.created_by = au.uid
+      WHERE apc.workspace_id = $1
+        AND ($2::SMALLINT IS NULL OR avc.status = $2)
+        AND ($3::TIMESTAMPTZ IS NULL OR avc.created_at < $3)
+      ORDER BY avc.created_at DESC
+      LIMIT $4
+    "#,
+    workspace_id,
+    status.map(|status| status as i16),
+    before,
+    limit,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns false if the comment does not belong to a view published by the workspace.
+pub async fn update_published_comment_status<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  comment_id: &Uuid,
+  status: PublishedCommentStatus,
+) -> Result<bool, AppError> {
+  let res = sqlx::query!(
+    r#"
+      UPDATE af_published_view_comment
+      SET status = $3, updated_at = NOW()
+      WHERE comment_id = $2
+        AND view_id IN (SELECT view_id FROM af_published_collab WHERE workspace_id = $1)
+    "#,
+    workspace_id,
+    comment_id,
+    status as i16,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(res.rows_affected() > 0)
+}
+
+pub async fn select_published_comment_setting<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<PublishedCommentSetting, AppError> {
+  let require_approval = sqlx::query_scalar!(
+    r#"
+      SELECT require_approval
+      FROM af_published_comment_setting
+      WHERE workspace_id = $1
+    "#,
+    workspace_id,
+  )
+  .fetch_optional(executor)
+  .await?;
+
+  Ok(PublishedCommentSetting {
+    require_approval: require_approval.unwrap_or(false),
+  })
+}
+
+pub async fn upsert_published_comment_setting<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  setting: &PublishedCommentSetting,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      INSERT INTO af_published_comment_setting (workspace_id, require_approval)
+      VALUES ($1, $2)
+      ON CONFLICT (workspace_id) DO UPDATE
+      SET require_approval = EXCLUDED.require_approval,
+          updated_at = NOW()
+    "#,
+    workspace_id,
+    setting.require_approval,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+pub async fn select_published_comment_blocklist(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFPublishedCommentBlockedUserRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedCommentBlockedUserRow,
+    r#"
+      SELECT
+        (au.uuid, au.name, au.email, au.metadata ->> 'icon_url') AS "user!: AFWebUserWithEmailColumn",
+        blocklist.reason,
+        blocklist.created_at
+      FROM af_published_comment_blocklist blocklist
+      JOIN af_user au ON au.uid = blocklist.uid
+      WHERE blocklist.workspace_id = $1
+      ORDER BY blocklist.created_at DESC
+    "#,
+    workspace_id,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn insert_published_comment_blocked_user<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  uid: i64,
+  reason: Option<&str>,
+  blocked_by: i64,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      INSERT INTO af_published_comment_blocklist (workspace_id, uid, reason, created_by)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (workspace_id, uid) DO UPDATE
+      SET reason = EXCLUDED.reason
+    "#,
+    workspace_id,
+    uid,
+    reason,
+    blocked_by,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+pub async fn delete_published_comment_blocked_user<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  uid: i64,
+) -> Result<bool, AppError> {
+  let res = sqlx::query!(
+    r#"
+      DELETE FROM af_published_comment_blocklist
+      WHERE workspace_id = $1
+        AND uid = $2
+    "#,
+    workspace_id,
+    uid,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(res.rows_affected() > 0)
+}
@@ -0,0 +1,144 @@
+use app_error::AppError;
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::AFPublishDomainRow;
+
+/// Claims the domain for the workspace. Claiming a domain, which was already claimed by the same
+/// workspace, returns the existing claim.
+pub async fn insert_publish_domain(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  domain: &str,
+  verification_token: &str,
+) -> Result<AFPublishDomainRow, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishDomainRow,
+    r#"
+      INSERT INTO af_publish_domain (workspace_id, domain, verification_token)
+      VALUES ($1, $2, $3)
+      ON CONFLICT (workspace_id, domain) DO UPDATE
+      SET domain = EXCLUDED.domain
+      RETURNING workspace_id, domain, verification_token, verified_at, created_at
+    "#,
+    workspace_id,
+    domain,
+    verification_token,
+  )
+  .fetch_one(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_publish_domains(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFPublishDomainRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishDomainRow,
+    r#"
+      SELECT workspace_id, domain, verification_token, verified_at, created_at
+      FROM af_publish_domain
+      WHERE workspace_id = $1
+      ORDER BY created_at
+    "#,
+    workspace_id,
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_publish_domain(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  domain: &str,
+) -> Result<Option<AFPublishDomainRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishDomainRow,
+    r#"
+      SELECT workspace_id, domain, verification_token, verified_at, created_at
+      FROM af_publish_domain
+      WHERE workspace_id = $1
+        AND domain = $2
+    "#,
+    workspace_id,
+    domain,
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the workspace, which verified the ownership of the domain.
+pub async fn select_workspace_id_for_publish_domain<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  domain: &str,
+) -> Result<Option<Uuid>, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT workspace_id
+      FROM af_publish_domain
+      WHERE domain = $1
+        AND verified_at IS NOT NULL
+    "#,
+    domain,
+  )
+  .fetch_optional(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn update_publish_domain_verified<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  domain: &str,
+) -> Result<AFPublishDomainRow, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishDomainRow,
+    r#"
+      UPDATE af_publish_domain
+      SET verified_at = COALESCE(verified_at, NOW())
+      WHERE workspace_id = $1
+        AND domain = $2
+      RETURNING workspace_id, domain, verification_token, verified_at, created_at
+    "#,
+    workspace_id,
+    domain,
+  )
+  .fetch_one(executor)
+  .await
+  .map_err(|err| match &err {
+    // Another workspace verified the domain in the meantime
+    sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+      AppError::PublishDomainAlreadyTaken(domain.to_string())
+    },
+    _ => AppError::from(err),
+  })?;
+
+  Ok(res)
+}
+
+pub async fn delete_publish_domain<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  domain: &str,
+) -> Result<bool, AppError> {
+  let res = sqlx::query!(
+    r#"
+      DELETE FROM af_publish_domain
+      WHERE workspace_id = $1
+        AND domain = $2
+    "#,
+    workspace_id,
+    domain,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(res.rows_affected() > 0)
+}
@@ -0,0 +1,171 @@
+use app_error::AppError;
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::AFPublishedSiteExportRow;
+
+pub async fn insert_published_site_export(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  namespace: &str,
+  uid: i64,
+) -> Result<AFPublishedSiteExportRow, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedSiteExportRow,
+    r#"
+      INSERT INTO af_published_site_export (workspace_id, namespace, created_by)
+      VALUES ($1, $2, $3)
+      RETURNING
+        export_id,
+        workspace_id,
+        namespace,
+        status,
+        object_prefix,
+        page_count,
+        error,
+        created_at,
+        completed_at
+    "#,
+    workspace_id,
+    namespace,
+    uid,
+  )
+  .fetch_one(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_published_site_export(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  export_id: &Uuid,
+) -> Result<Option<AFPublishedSiteExportRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedSiteExportRow,
+    r#"
+      SELECT
+        export_id,
+        workspace_id,
+        namespace,
+        status,
+        object_prefix,
+        page_count,
+        error,
+        created_at,
+        completed_at
+      FROM af_published_site_export
+      WHERE workspace_id = $1
+        AND export_id = $2
+    "#,
+    workspace_id,
+    export_id,
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns true if the workspace already has an export, which is pending or running.
+pub async fn select_published_site_export_in_progress(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<bool, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT EXISTS (
+        SELECT 1
+        FROM af_published_site_export
+        WHERE workspace_id = $1
+          AND status IN (0, 1)
+      ) AS "exists!"
+    "#,
+    workspace_id,
+  )
+  .fetch_one(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+/// Marks the oldest pending export as running and returns it. Exports, which were left running
+/// for longer than `stale_secs` (e.g. because the server was restarted), are picked up again.
+pub async fn claim_next_published_site_export(
+  pg_pool: &PgPool,
+  stale_secs: i64,
+) -> Result<Option<AFPublishedSiteExportRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFPublishedSiteExportRow,
+    r#"
+      UPDATE af_published_site_export
+      SET status = 1, started_at = NOW()
+      WHERE export_id = (
+        SELECT export_id
+        FROM af_published_site_export
+        WHERE status = 0
+          OR (status = 1 AND started_at < NOW() - make_interval(secs => $1))
+        ORDER BY created_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+      )
+      RETURNING
+        export_id,
+        workspace_id,
+        namespace,
+        status,
+        object_prefix,
+        page_count,
+        error,
+        created_at,
+        completed_at
+    "#,
+    stale_secs as f64,
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn update_published_site_export_completed<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  export_id: &Uuid,
+  object_prefix: &str,
+  page_count: i32,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_published_site_export
+      SET status = 2, object_prefix = $2, page_count = $3, error = NULL, completed_at = NOW()
+      WHERE export_id = $1
+    "#,
+    export_id,
+    object_prefix,
+    page_count,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+pub async fn update_published_site_export_failed<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  export_id: &Uuid,
+  error: &str,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_published_site_export
+      SET status = 3, error = $2, completed_at = NOW()
+      WHERE export_id = $1
+    "#,
+    export_id,
+    error,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
@@ -131,6 +131,27 @@ pub async fn delete_blob_metadata(
   Ok(())
 }
 
+/// Deletes the metadata of the blobs uploaded under the given parent directory, whose metadata
+/// keys are `{parent_dir}_{file_id}`, and returns the number of deleted rows.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn delete_blob_metadata_in_dir<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  parent_dir: &str,
+) -> Result<u64, AppError> {
+  let result = sqlx::query!(
+    r#"
+        DELETE FROM af_blob_metadata
+        WHERE workspace_id = $1 AND STARTS_WITH(file_id, $2 || '_')
+        "#,
+    workspace_id,
+    parent_dir,
+  )
+  .execute(executor)
+  .await?;
+  Ok(result.rows_affected())
+}
+
 #[instrument(level = "trace", skip_all, err)]
 pub async fn get_blob_metadata(
   pg_pool: &PgPool,
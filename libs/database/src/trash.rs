@@ -0,0 +1,51 @@
+use app_error::AppError;
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::AFWorkspaceTrashRetentionRow;
+
+/// Returns the workspaces with a trash retention period, ordered by id and starting after the
+/// given workspace.
+pub async fn select_workspaces_with_trash_retention<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  after_workspace_id: Option<Uuid>,
+  limit: i64,
+) -> Result<Vec<AFWorkspaceTrashRetentionRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFWorkspaceTrashRetentionRow,
+    r#"
+      SELECT
+        workspace_id,
+        owner_uid,
+        (settings ->> 'trash_retention_days')::BIGINT AS "trash_retention_days!"
+      FROM af_workspace
+      WHERE COALESCE((settings ->> 'trash_retention_days')::BIGINT, 0) > 0
+        AND ($1::UUID IS NULL OR workspace_id > $1)
+      ORDER BY workspace_id
+      LIMIT $2
+    "#,
+    after_workspace_id,
+    limit,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn delete_collab_embeddings<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  oids: &[Uuid],
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      DELETE FROM af_collab_embeddings
+      WHERE oid = ANY($1)
+    "#,
+    oids,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
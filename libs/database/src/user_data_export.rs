@@ -0,0 +1,342 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::pg_row::{
+  AFUserBlobRow, AFUserChatMessageRow, AFUserDataExportRow, AFUserOwnedCollabRow,
+  AFUserPublishedCommentRow, AFUserQuickNoteRow,
+};
+
+pub async fn insert_user_data_export(
+  pg_pool: &PgPool,
+  uid: i64,
+) -> Result<AFUserDataExportRow, AppError> {
+  let res = sqlx::query_as!(
+    AFUserDataExportRow,
+    r#"
+      INSERT INTO af_user_data_export (uid)
+      VALUES ($1)
+      RETURNING
+        export_id,
+        uid,
+        status,
+        object_key,
+        file_size,
+        error,
+        created_at,
+        completed_at,
+        expires_at
+    "#,
+    uid,
+  )
+  .fetch_one(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_user_data_export(
+  pg_pool: &PgPool,
+  uid: i64,
+  export_id: &Uuid,
+) -> Result<Option<AFUserDataExportRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserDataExportRow,
+    r#"
+      SELECT
+        export_id,
+        uid,
+        status,
+        object_key,
+        file_size,
+        error,
+        created_at,
+        completed_at,
+        expires_at
+      FROM af_user_data_export
+      WHERE uid = $1
+        AND export_id = $2
+    "#,
+    uid,
+    export_id,
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns true if the user already has an export, which is pending or running.
+pub async fn select_user_data_export_in_progress(
+  pg_pool: &PgPool,
+  uid: i64,
+) -> Result<bool, AppError> {
+  let res = sqlx::query_scalar!(
+    r#"
+      SELECT EXISTS (
+        SELECT 1
+        FROM af_user_data_export
+        WHERE uid = $1
+          AND status IN (0, 1)
+      ) AS "exists!"
+    "#,
+    uid,
+  )
+  .fetch_one(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+/// Marks the oldest pending export as running and returns it. Exports, which were left running
+/// for longer than `stale_secs` (e.g. because the server was restarted), are picked up again.
+pub async fn claim_next_user_data_export(
+  pg_pool: &PgPool,
+  stale_secs: i64,
+) -> Result<Option<AFUserDataExportRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserDataExportRow,
+    r#"
+      UPDATE af_user_data_export
+      SET status = 1, started_at = NOW()
+      WHERE export_id = (
+        SELECT export_id
+        FROM af_user_data_export
+        WHERE status = 0
+          OR (status = 1 AND started_at < NOW() - make_interval(secs => $1))
+        ORDER BY created_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+      )
+      RETURNING
+        export_id,
+        uid,
+        status,
+        object_key,
+        file_size,
+        error,
+        created_at,
+        completed_at,
+        expires_at
+    "#,
+    stale_secs as f64,
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn update_user_data_export_completed<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  export_id: &Uuid,
+  object_key: &str,
+  file_size: i64,
+  expires_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_user_data_export
+      SET status = 2, object_key = $2, file_size = $3, expires_at = $4, error = NULL,
+        completed_at = NOW()
+      WHERE export_id = $1
+    "#,
+    export_id,
+    object_key,
+    file_size,
+    expires_at,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+pub async fn update_user_data_export_failed<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  export_id: &Uuid,
+  error: &str,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_user_data_export
+      SET status = 3, error = $2, completed_at = NOW()
+      WHERE export_id = $1
+    "#,
+    export_id,
+    error,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+/// Returns the id and object key of the expired exports, whose archive is still stored.
+pub async fn select_expired_user_data_exports<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  limit: i64,
+) -> Result<Vec<(Uuid, String)>, AppError> {
+  let res = sqlx::query!(
+    r#"
+      SELECT export_id, object_key AS "object_key!"
+      FROM af_user_data_export
+      WHERE object_key IS NOT NULL
+        AND expires_at < NOW()
+      ORDER BY expires_at
+      LIMIT $1
+    "#,
+    limit,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(
+    res
+      .into_iter()
+      .map(|row| (row.export_id, row.object_key))
+      .collect(),
+  )
+}
+
+pub async fn clear_user_data_export_object_key<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  export_id: &Uuid,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_user_data_export
+      SET object_key = NULL
+      WHERE export_id = $1
+    "#,
+    export_id,
+  )
+  .execute(executor)
+  .await?;
+
+  Ok(())
+}
+
+/// Returns the collabs created by the user, which have not been deleted.
+pub async fn select_user_owned_collabs<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  uid: i64,
+) -> Result<Vec<AFUserOwnedCollabRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserOwnedCollabRow,
+    r#"
+      SELECT workspace_id, oid, partition_key
+      FROM af_collab
+      WHERE owner_uid = $1
+        AND deleted_at IS NULL
+      ORDER BY workspace_id, partition_key
+    "#,
+    uid,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns all the messages, including the AI answers, of the chats the user took part in.
+pub async fn select_user_chat_messages<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  uid: i64,
+) -> Result<Vec<AFUserChatMessageRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserChatMessageRow,
+    r#"
+      SELECT
+        c.workspace_id,
+        c.chat_id,
+        c.name AS chat_name,
+        m.message_id,
+        m.author,
+        m.content,
+        m.created_at
+      FROM af_chat_messages m
+      JOIN af_chat c ON c.chat_id = m.chat_id
+      WHERE m.deleted_at IS NULL
+        AND c.deleted_at IS NULL
+        AND m.chat_id IN (
+          SELECT chat_id
+          FROM af_chat_messages
+          WHERE (author->>'author_id')::BIGINT = $1
+        )
+      ORDER BY c.chat_id, m.message_id
+    "#,
+    uid,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_user_quick_notes<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  uid: i64,
+) -> Result<Vec<AFUserQuickNoteRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserQuickNoteRow,
+    r#"
+      SELECT workspace_id, quick_note_id, data, created_at, updated_at
+      FROM af_quick_note
+      WHERE uid = $1
+      ORDER BY created_at
+    "#,
+    uid,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+pub async fn select_user_published_comments<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  uid: i64,
+) -> Result<Vec<AFUserPublishedCommentRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserPublishedCommentRow,
+    r#"
+      SELECT comment_id, view_id, reply_comment_id, content, created_at, updated_at
+      FROM af_published_view_comment
+      WHERE created_by = $1
+        AND NOT is_deleted
+      ORDER BY created_at
+    "#,
+    uid,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
+
+/// Returns the files uploaded to the collabs created by the user.
+pub async fn select_user_collab_blobs<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  uid: i64,
+) -> Result<Vec<AFUserBlobRow>, AppError> {
+  let res = sqlx::query_as!(
+    AFUserBlobRow,
+    r#"
+      SELECT m.workspace_id, m.file_id, m.file_type, m.file_size
+      FROM af_blob_metadata m
+      JOIN af_collab c
+        ON c.workspace_id = m.workspace_id
+        AND c.oid::TEXT = SPLIT_PART(m.file_id, '_', 1)
+      WHERE c.owner_uid = $1
+        AND c.deleted_at IS NULL
+      ORDER BY m.workspace_id, m.file_id
+    "#,
+    uid,
+  )
+  .fetch_all(executor)
+  .await?;
+
+  Ok(res)
+}
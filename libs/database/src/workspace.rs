@@ -2,8 +2,8 @@ use chrono::{DateTime, Utc};
 use database_entity::dto::{
   AFRole, AFWorkspaceInvitation, AFWorkspaceInvitationStatus, AFWorkspaceSettings, GlobalComment,
   InvitationCodeInfo, MentionableWorkspaceMemberOrGuest,
-  MentionableWorkspaceMemberOrGuestWithLastMentionedTime, PageMentionUpdate, Reaction,
-  WorkspaceMemberProfile,
+  MentionableWorkspaceMemberOrGuestWithLastMentionedTime, PageMentionUpdate,
+  PublishedCommentStatus, Reaction, WorkspaceMemberProfile,
 };
 use futures_util::stream::BoxStream;
 use sqlx::{types::uuid, Executor, PgPool, Postgres, Transaction};
@@ -1326,10 +1326,13 @@ pub async fn select_comments_for_published_view_ordered_by_recency<
         avc.reply_comment_id,
         avc.is_deleted,
         (au.uuid, au.name, au.email, au.metadata ->> 'icon_url') AS "user: AFWebUserWithEmailColumn",
-        (NOT avc.is_deleted AND ($2 OR au.uuid = $3)) AS "can_be_deleted!"
+        (NOT avc.is_deleted AND ($2 OR au.uuid = $3)) AS "can_be_deleted!",
+        avc.status
       FROM af_published_view_comment avc
       LEFT OUTER JOIN af_user au ON avc.created_by = au.uid
       WHERE view_id = $1
+        -- comments pending approval are only visible to their author
+        AND (avc.status = 0 OR (avc.status = 1 AND au.uuid = $3))
       ORDER BY avc.created_at DESC
     "#,
     view_id,
@@ -1342,34 +1345,47 @@ pub async fn select_comments_for_published_view_ordered_by_recency<
   Ok(comments)
 }
 
+/// Inserts the comment, unless a rate limit is given and the user already created that many
+/// comments on published views since the given time. The limit is checked by the insert itself.
+/// Returns false if the comment was not inserted.
 pub async fn insert_comment_to_published_view<'a, E: Executor<'a, Database = Postgres>>(
   executor: E,
   view_id: &Uuid,
   user_uuid: &Uuid,
   content: &str,
   reply_comment_id: &Option<Uuid>,
-) -> Result<(), AppError> {
+  status: PublishedCommentStatus,
+  rate_limit: Option<(DateTime<Utc>, i64)>,
+) -> Result<bool, AppError> {
+  let (since, max_comments) = rate_limit.unzip();
   let res = sqlx::query!(
     r#"
-      INSERT INTO af_published_view_comment (view_id, created_by, content, reply_comment_id)
-      VALUES ($1, (SELECT uid FROM af_user WHERE uuid = $2), $3, $4)
+      INSERT INTO af_published_view_comment (view_id, created_by, content, reply_comment_id, status)
+      SELECT $1, au.uid, $3, $4, $5
+      FROM af_user au
+      WHERE au.uuid = $2
+        AND (
+          $7::BIGINT IS NULL
+          OR (
+            SELECT COUNT(*)
+            FROM af_published_view_comment avc
+            WHERE avc.created_by = au.uid
+              AND avc.created_at >= $6
+          ) < $7
+        )
     "#,
     view_id,
     user_uuid,
     content,
     reply_comment_id.clone(),
+    status as i16,
+    since,
+    max_comments,
   )
   .execute(executor)
   .await?;
 
-  if res.rows_affected() != 1 {
-    tracing::error!(
-      "Failed to insert comment to published view, view_id: {}, user_id: {}, content: {}, rows_affected: {}",
-      view_id, user_uuid, content, res.rows_affected()
-    );
-  }
-
-  Ok(())
+  Ok(res.rows_affected() == 1)
 }
 
 pub async fn update_comment_deletion_status<'a, E: Executor<'a, Database = Postgres>>(
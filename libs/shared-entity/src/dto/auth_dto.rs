@@ -1,8 +1,11 @@
 // Data Transfer Objects (DTO)
 
+use chrono::{DateTime, Utc};
 use gotrue_entity::dto::GotrueTokenResponse;
 use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::collections::HashMap;
+use uuid::Uuid;
 
 #[derive(Deserialize, Serialize)]
 pub struct SignInParams {
@@ -77,3 +80,38 @@ pub struct UserAuthInfo {
   pub exists: bool,
   pub has_custom_password: bool,
 }
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize_repr, Deserialize_repr)]
+#[repr(i16)]
+pub enum UserDataExportStatus {
+  Pending = 0,
+  Running = 1,
+  Completed = 2,
+  Failed = 3,
+}
+
+impl From<i16> for UserDataExportStatus {
+  fn from(value: i16) -> Self {
+    match value {
+      1 => UserDataExportStatus::Running,
+      2 => UserDataExportStatus::Completed,
+      3 => UserDataExportStatus::Failed,
+      _ => UserDataExportStatus::Pending,
+    }
+  }
+}
+
+/// Archive of all the data of a user: profile, workspace memberships, collabs, chat history,
+/// quick notes, comments and uploaded files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDataExport {
+  pub export_id: Uuid,
+  pub status: UserDataExportStatus,
+  pub file_size: i64,
+  /// Expiring link to download the archive, once the export is completed and until it expires.
+  pub download_url: Option<String>,
+  pub error: Option<String>,
+  pub created_at: DateTime<Utc>,
+  pub completed_at: Option<DateTime<Utc>>,
+  pub expires_at: Option<DateTime<Utc>>,
+}
@@ -1,7 +1,9 @@
 use app_error::AppError;
 use chrono::{DateTime, Utc};
 use collab_entity::{CollabType, EncodedCollab};
-use database_entity::dto::{AFRole, AFWebUser, AFWorkspaceInvitationStatus, PublishInfo};
+use database_entity::dto::{
+  AFRole, AFWebUser, AFWorkspaceInvitationStatus, PublishInfo, PublishInfoMeta,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_repr::{Deserialize_repr, Serialize_repr};
@@ -109,6 +111,24 @@ pub struct BlobMetadata {
   pub modified_at: DateTime<Utc>,
 }
 
+/// Blobs found unreferenced by the documents and databases of the workspace, which will be
+/// deleted unless they are referenced again before `delete_after`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrphanedBlobReport {
+  pub blobs: Vec<OrphanedBlob>,
+  pub total_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrphanedBlob {
+  pub file_id: String,
+  pub file_type: String,
+  pub file_size: i64,
+  pub modified_at: DateTime<Utc>,
+  pub marked_at: DateTime<Utc>,
+  pub delete_after: DateTime<Utc>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CreateWorkspaceParam {
   pub workspace_name: Option<String>,
@@ -204,6 +224,14 @@ pub struct CreatePageParams {
   pub collab_id: Option<Uuid>,
 }
 
+/// Query parameters of a page creation request, whose body is the Markdown content of the page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMarkdownPageParams {
+  pub parent_view_id: Uuid,
+  pub name: Option<String>,
+  pub view_id: Option<Uuid>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateOrphanedViewParams {
   pub document_id: Uuid,
@@ -243,6 +271,57 @@ pub struct AppendBlockToPageParams {
   pub blocks: Vec<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsertBlockParams {
+  /// Block to insert, in the same format as the blocks of [AppendBlockToPageParams]. It may
+  /// contain children.
+  pub block: serde_json::Value,
+  /// Parent of the new block. Defaults to the parent of `prev_block_id`, or to the page block.
+  #[serde(default)]
+  pub parent_block_id: Option<String>,
+  /// The new block is inserted after this block, or as the first child of its parent if unset.
+  #[serde(default)]
+  pub prev_block_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsertBlockResponse {
+  pub block_id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateBlockParams {
+  /// Entries merged into the data of the block. Entries set to null are removed.
+  #[serde(default)]
+  pub data: Option<HashMap<String, Value>>,
+  /// Delta applied to the text of the block, e.g. `[{"retain": 5}, {"insert": "!"}]`.
+  #[serde(default)]
+  pub delta: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveBlockParams {
+  /// New parent of the block. Defaults to the parent of `prev_block_id`, or to the current
+  /// parent of the block.
+  #[serde(default)]
+  pub parent_block_id: Option<String>,
+  /// The block is moved after this block, or becomes the first child of its parent if unset.
+  #[serde(default)]
+  pub prev_block_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentBlock {
+  pub id: String,
+  #[serde(rename = "type")]
+  pub ty: String,
+  pub parent_id: String,
+  pub children: Vec<String>,
+  pub data: HashMap<String, Value>,
+  /// Text of the block, as a delta.
+  pub delta: Option<Vec<Value>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MovePageParams {
   pub new_parent_view_id: String,
@@ -370,6 +449,94 @@ pub struct PublishPageParams {
   pub visible_database_view_ids: Option<Vec<Uuid>>,
   pub comments_enabled: Option<bool>,
   pub duplicate_enabled: Option<bool>,
+  /// Guests will need to provide this password in order to access the published page.
+  #[serde(default)]
+  pub password: Option<String>,
+  /// Published page behaves as unpublished after this date.
+  #[serde(default)]
+  pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishedCollabAccessParams {
+  pub password: String,
+}
+
+/// Access granted to a password protected published page. The access token itself is stored
+/// in an HTTP-only cookie.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishedCollabAccess {
+  pub view_id: Uuid,
+  pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize_repr, Deserialize_repr)]
+#[repr(i16)]
+pub enum PublishedSiteExportStatus {
+  Pending = 0,
+  Running = 1,
+  Completed = 2,
+  Failed = 3,
+}
+
+impl From<i16> for PublishedSiteExportStatus {
+  fn from(value: i16) -> Self {
+    match value {
+      1 => PublishedSiteExportStatus::Running,
+      2 => PublishedSiteExportStatus::Completed,
+      3 => PublishedSiteExportStatus::Failed,
+      _ => PublishedSiteExportStatus::Pending,
+    }
+  }
+}
+
+/// Static HTML export of all the pages published under a namespace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedSiteExport {
+  pub export_id: Uuid,
+  pub namespace: String,
+  pub status: PublishedSiteExportStatus,
+  /// Prefix of the exported files in the object storage, once the export is completed.
+  pub object_prefix: Option<String>,
+  /// URL of the exported site's index page, if a CDN is configured for exports.
+  pub url: Option<String>,
+  pub page_count: i32,
+  pub error: Option<String>,
+  pub created_at: DateTime<Utc>,
+  pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddPublishDomainParams {
+  pub domain: String,
+}
+
+/// Custom domain serving the publish namespace of a workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishDomain {
+  pub domain: String,
+  pub namespace: String,
+  pub verified: bool,
+  pub verified_at: Option<DateTime<Utc>>,
+  /// Name of the DNS TXT record, which must contain `txt_record_value` to verify the domain.
+  pub txt_record_name: String,
+  pub txt_record_value: String,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishDomainQueryParams {
+  /// Host to resolve. Defaults to the `Host` header of the request.
+  pub host: Option<String>,
+}
+
+/// Publish namespace served under a verified custom domain.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishDomainResolution {
+  pub domain: String,
+  pub namespace: String,
+  /// Page served at the root of the domain, if the namespace has a default published view.
+  pub default_view: Option<PublishInfoMeta<Value>>,
 }
 
 #[derive(Eq, PartialEq, Debug, Hash, Clone, Serialize_repr, Deserialize_repr)]
@@ -447,6 +614,12 @@ pub struct ListDatabaseRowDetailParam {
   // if set to true, document data will be fetched (if exist)
   // as markdown
   pub with_doc: Option<bool>,
+  // if set to true, the cells of the relation fields are the related rows
+  // (see [AFRelatedDatabaseRow]) instead of being left out
+  pub expand_relations: Option<bool>,
+  // Comma separated fields (id or name) of the related rows, returned
+  // with their primary field. Implies `expand_relations`.
+  pub relation_fields: Option<String>,
 }
 
 #[derive(Default, Debug, Deserialize, Serialize)]
@@ -460,13 +633,76 @@ pub struct DatabaseRowUpdatedItem {
   pub row_id: String,
 }
 
+#[derive(Default, Debug, Deserialize, Serialize)]
+pub struct ListDatabaseRowChangesParam {
+  /// Returns the changes after this cursor, or from the start of the feed if unset.
+  pub cursor: Option<String>,
+  pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseRowChangeType {
+  Created,
+  Updated,
+  Deleted,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DatabaseRowChange {
+  pub cursor: String,
+  pub row_id: String,
+  pub change_type: DatabaseRowChangeType,
+  /// Changed cell data by field id, with null for the cells removed from the row. All the cells
+  /// of a created row, and none for a deleted row.
+  pub cells: HashMap<String, serde_json::Value>,
+  pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DatabaseRowChangesResponse {
+  pub changes: Vec<DatabaseRowChange>,
+  /// Cursor to get the next changes: the cursor of the last change, or the given cursor if
+  /// there is no new change.
+  pub cursor: Option<String>,
+  pub has_more: bool,
+}
+
 impl ListDatabaseRowDetailParam {
   pub fn new(ids: &[&str], with_doc: bool) -> Self {
     Self {
       ids: ids.join(","),
       with_doc: Some(with_doc),
+      expand_relations: None,
+      relation_fields: None,
+    }
+  }
+
+  /// Expands the relations to the related rows, with the given fields of the related rows in
+  /// addition to their primary field.
+  pub fn with_relations(mut self, relation_fields: &[&str]) -> Self {
+    self.expand_relations = Some(true);
+    if !relation_fields.is_empty() {
+      self.relation_fields = Some(relation_fields.join(","));
     }
+    self
   }
+
+  pub fn expands_relations(&self) -> bool {
+    self.expand_relations.unwrap_or_default() || self.relation_fields.is_some()
+  }
+
+  pub fn relation_fields(&self) -> Vec<String> {
+    self
+      .relation_fields
+      .iter()
+      .flat_map(|fields| fields.split(','))
+      .map(str::trim)
+      .filter(|field| !field.is_empty())
+      .map(str::to_string)
+      .collect()
+  }
+
   pub fn into_ids(&self) -> Result<Vec<Uuid>, AppError> {
     let mut res = Vec::new();
     for uuid in self.ids.split(',') {
@@ -526,6 +762,18 @@ pub struct AFDatabaseRowDetail {
   pub doc: Option<String>,
 }
 
+/// Row related through a relation field, in the cells of the rows listed with
+/// `expand_relations` in [ListDatabaseRowDetailParam].
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFRelatedDatabaseRow {
+  pub id: String,
+  /// Value of the primary field of the related row.
+  pub primary: serde_json::Value,
+  /// Requested fields of the related row, by field name.
+  #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+  pub cells: HashMap<String, serde_json::Value>,
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AFDatabaseField {
   pub id: String,
@@ -540,6 +788,66 @@ pub struct AFInsertDatabaseField {
   pub name: String,
   pub field_type: i64,                             // FieldType ID
   pub type_option_data: Option<serde_json::Value>, // TypeOptionData
+  /// Makes the field computed by the server. The values are converted to the field type.
+  #[serde(default)]
+  pub computed: Option<AFComputedField>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFUpdateDatabaseField {
+  #[serde(default)]
+  pub name: Option<String>,
+  /// New FieldType ID. Existing cells are converted when the conversion is lossless, e.g. from
+  /// single select to multi select.
+  #[serde(default)]
+  pub field_type: Option<i64>,
+  /// Replaces the type option of the (new) field type, e.g. to add select options.
+  #[serde(default)]
+  pub type_option_data: Option<serde_json::Value>,
+  /// Replaces the formula or rollup of the field, or makes the field computed.
+  #[serde(default)]
+  pub computed: Option<AFComputedField>,
+}
+
+/// Value of a field, which is computed by the server whenever the rows are read, instead of
+/// being stored in the cells.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AFComputedField {
+  /// Expression over the cells of the row, e.g. `{Price} * {Quantity}` or
+  /// `if({Done}, "done", concat("due ", format_date({Due}, "%Y-%m-%d")))`. Fields are
+  /// referenced by id or name within braces.
+  Formula { expression: String },
+  /// Aggregates a field of the rows related through a relation field of the row.
+  Rollup {
+    /// Id or name of the relation field.
+    relation_field: String,
+    /// Id or name of the field of the related database. Not needed to count the related rows.
+    #[serde(default)]
+    target_field: Option<String>,
+    function: RollupFunction,
+  },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RollupFunction {
+  /// Number of related rows.
+  Count,
+  Sum,
+  Avg,
+  Min,
+  Max,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFMoveDatabaseField {
+  /// View in which the field is moved. Defaults to all the views of the database.
+  #[serde(default)]
+  pub view_id: Option<String>,
+  /// The field is moved after this field, or becomes the first field if unset.
+  #[serde(default)]
+  pub prev_field_id: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -554,3 +862,307 @@ pub struct UpsertDatatabaseRow {
   pub cells: HashMap<String, serde_json::Value>,
   pub document: Option<String>,
 }
+
+/// Maximum number of operations in a single [BatchDatabaseRowsParams].
+pub const MAX_DATABASE_ROW_BATCH_SIZE: usize = 1000;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BatchDatabaseRowsParams {
+  pub operations: Vec<DatabaseRowOperation>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DatabaseRowOperation {
+  Insert(AddDatatabaseRow),
+  Upsert(UpsertDatatabaseRow),
+  Delete { row_id: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchDatabaseRowsResponse {
+  /// Id of the row of each operation, in the order of the operations.
+  pub row_ids: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDatabaseCsvQuery {
+  /// View to export, with its visible fields, filters and sorts. Defaults to the inline view.
+  #[serde(default)]
+  pub view_id: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ImportDatabaseCsvQuery {
+  /// Only validate the CSV, without writing any row.
+  #[serde(default)]
+  pub dry_run: bool,
+  /// Column whose values are used as `pre_hash` to upsert the rows, like in
+  /// [UpsertDatatabaseRow]. The rows are inserted when unset.
+  #[serde(default)]
+  pub pre_hash_column: Option<String>,
+  /// Identifies the import when the rows are inserted: the same import writes the same rows, so
+  /// retrying a failed import doesn't duplicate the rows already written. Defaults to the hash of
+  /// the CSV.
+  #[serde(default)]
+  pub import_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportDatabaseCsvResponse {
+  /// Id of the row of each CSV record. Empty for a dry run.
+  pub row_ids: Vec<String>,
+  /// Columns which don't match the name of a writable field, and are ignored.
+  pub unmapped_columns: Vec<String>,
+  pub invalid_values: Vec<CsvInvalidValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvInvalidValue {
+  /// 1-based index of the record, excluding the header.
+  pub row: usize,
+  pub column: String,
+  pub value: String,
+  pub reason: String,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct QueryDatabaseRowsParams {
+  /// When set, the filters and sorts saved on this database view are applied, before the ones
+  /// given below, and the rows are returned in the order of the view.
+  pub view_id: Option<String>,
+  #[serde(default)]
+  pub filters: Vec<DatabaseRowFilter>,
+  #[serde(default)]
+  pub sorts: Vec<DatabaseRowSort>,
+  /// `next_cursor` of the previous page.
+  pub cursor: Option<String>,
+  pub limit: Option<u32>,
+  pub with_doc: Option<bool>,
+}
+
+/// Condition on the cells of a field, identified by its id or name. All the filters of a query
+/// must match for a row to be returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseRowFilter {
+  pub field: String,
+  #[serde(flatten)]
+  pub condition: DatabaseRowFilterCondition,
+}
+
+/// Text conditions are case insensitive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum DatabaseRowFilterCondition {
+  TextContains {
+    value: String,
+  },
+  TextIs {
+    value: String,
+  },
+  IsEmpty,
+  IsNotEmpty,
+  /// Inclusive range, either bound can be omitted.
+  NumberRange {
+    min: Option<f64>,
+    max: Option<f64>,
+  },
+  /// The cell has at least one of the options, given by id or name.
+  SelectOptionIs {
+    options: Vec<String>,
+  },
+  /// The cell has none of the options, given by id or name.
+  SelectOptionIsNot {
+    options: Vec<String>,
+  },
+  Checkbox {
+    checked: bool,
+  },
+  /// Inclusive range of unix timestamps in seconds, either bound can be omitted.
+  DateRange {
+    start: Option<i64>,
+    end: Option<i64>,
+  },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseRowSort {
+  /// Id or name of the field.
+  pub field: String,
+  #[serde(default)]
+  pub descending: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFDatabaseView {
+  pub id: String,
+  pub name: String,
+  /// `Grid`, `Board` or `Calendar`.
+  pub layout: String,
+  /// Whether the view is the one the database was created with.
+  pub is_inline: bool,
+  /// Ids of the fields, in the order of the view.
+  pub field_order: Vec<String>,
+  /// Ids of the fields hidden in the view.
+  pub hidden_fields: Vec<String>,
+  /// Filters saved on the view. Filters, which cannot be expressed as a [DatabaseRowFilter],
+  /// e.g. nested filter groups, are omitted.
+  pub filters: Vec<DatabaseRowFilter>,
+  pub sorts: Vec<DatabaseRowSort>,
+  /// Id of the field the rows are grouped by.
+  pub group_by_field: Option<String>,
+  /// Settings of the calendar layout, set once the view has been a calendar.
+  pub calendar: Option<AFCalendarViewSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFCalendarViewSettings {
+  /// Id of the date field which places the rows on the calendar.
+  pub date_field: String,
+  /// 0 for Sunday, 1 for Monday.
+  pub first_day_of_week: i32,
+  pub show_weekends: bool,
+  pub show_week_numbers: bool,
+}
+
+/// Settings to change on a database view. The settings which are set replace the current ones.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFUpdateDatabaseView {
+  #[serde(default)]
+  pub name: Option<String>,
+  /// Ids or names of the fields to hide, the other fields are shown. The primary field cannot be
+  /// hidden.
+  #[serde(default)]
+  pub hidden_fields: Option<Vec<String>>,
+  #[serde(default)]
+  pub filters: Option<Vec<DatabaseRowFilter>>,
+  #[serde(default)]
+  pub sorts: Option<Vec<DatabaseRowSort>>,
+  /// Id or name of the field to group the rows by, or an empty string to remove the grouping.
+  /// Boards must stay grouped.
+  #[serde(default)]
+  pub group_by_field: Option<String>,
+  /// `Grid`, `Board` or `Calendar`. A board must be grouped by a field, and a calendar needs a
+  /// date field, which defaults to the first date field of the database.
+  #[serde(default)]
+  pub layout: Option<String>,
+  #[serde(default)]
+  pub calendar: Option<AFUpdateCalendarViewSettings>,
+}
+
+/// Calendar settings to change on a database view.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFUpdateCalendarViewSettings {
+  /// Id or name of the date field.
+  #[serde(default)]
+  pub date_field: Option<String>,
+  #[serde(default)]
+  pub first_day_of_week: Option<i32>,
+  #[serde(default)]
+  pub show_weekends: Option<bool>,
+  #[serde(default)]
+  pub show_week_numbers: Option<bool>,
+}
+
+/// Row level rule of a database. The members who are not owners of the workspace only see the
+/// rows whose cell in the user field contains them, e.g. a person field, and the rows granted to
+/// them.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFDatabaseRowAccessRule {
+  /// Without a user field, only the granted rows can be seen.
+  pub user_field_id: Option<String>,
+  pub grants: Vec<AFDatabaseRowGrant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AFDatabaseRowGrant {
+  pub row_id: String,
+  pub uid: i64,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFUpdateDatabaseRowAccessRule {
+  /// Id or name of the field whose cells contain the users who can see the row.
+  #[serde(default)]
+  pub user_field: Option<String>,
+}
+
+/// Grants, or revokes, each of the rows to each of the users.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFDatabaseRowGrants {
+  pub row_ids: Vec<String>,
+  pub uids: Vec<i64>,
+}
+
+/// Template of the rows of a database, inserted on demand or on a schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFDatabaseRowTemplate {
+  pub id: String,
+  pub name: String,
+  /// Default cell values, by field id or name, like the cells of [AddDatatabaseRow].
+  pub cells: HashMap<String, serde_json::Value>,
+  /// Content of the document of the inserted rows, in markdown.
+  pub row_doc_content: Option<String>,
+  /// Cron-like schedule in UTC, e.g. `0 9 * * 1` for every Monday at 09:00.
+  pub schedule: Option<String>,
+  pub next_run_at: Option<DateTime<Utc>>,
+  pub created_by: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFCreateDatabaseRowTemplate {
+  pub name: String,
+  #[serde(default)]
+  pub cells: HashMap<String, serde_json::Value>,
+  #[serde(default)]
+  pub row_doc_content: Option<String>,
+  #[serde(default)]
+  pub schedule: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AFUpdateDatabaseRowTemplate {
+  #[serde(default)]
+  pub name: Option<String>,
+  #[serde(default)]
+  pub cells: Option<HashMap<String, serde_json::Value>>,
+  /// An empty string removes the content of the row document.
+  #[serde(default)]
+  pub row_doc_content: Option<String>,
+  /// An empty string removes the schedule.
+  #[serde(default)]
+  pub schedule: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct QueryDatabaseRowsResponse {
+  pub rows: Vec<AFDatabaseRowDetail>,
+  /// Set if there are more rows to fetch.
+  pub next_cursor: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspacePresenceQuery {
+  /// When set, only the presence over a given collab is returned.
+  pub object_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActiveCollabUser {
+  pub uid: i64,
+  pub device_id: String,
+  pub last_active_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollabPresenceInfo {
+  pub object_id: Uuid,
+  pub active_users: Vec<ActiveCollabUser>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspacePresence {
+  pub objects: Vec<CollabPresenceInfo>,
+}
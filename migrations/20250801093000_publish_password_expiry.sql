@@ -0,0 +1,3 @@
+ALTER TABLE af_published_collab
+ADD COLUMN password_hash TEXT,
+ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,35 @@
+-- Daily aggregates of published page views. Raw view events are never stored.
+CREATE TABLE IF NOT EXISTS af_published_view_daily_stats (
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    view_id UUID NOT NULL,
+    day DATE NOT NULL,
+    views BIGINT NOT NULL DEFAULT 0,
+    unique_visitors BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (view_id, day)
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_published_view_daily_stats_workspace_day
+    ON af_published_view_daily_stats (workspace_id, day);
+
+-- Views broken down by referrer domain or visitor country.
+CREATE TABLE IF NOT EXISTS af_published_view_daily_breakdown (
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    view_id UUID NOT NULL,
+    day DATE NOT NULL,
+    dimension TEXT NOT NULL CHECK (dimension IN ('referrer', 'country')),
+    value TEXT NOT NULL,
+    views BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (view_id, day, dimension, value)
+);
+
+-- Salted hashes of visitors (IP address + user agent) seen on a given day, used to count unique
+-- visitors. Salt changes every day, and rows older than a day are purged.
+CREATE TABLE IF NOT EXISTS af_published_view_daily_visitor (
+    view_id UUID NOT NULL,
+    day DATE NOT NULL,
+    visitor_hash BYTEA NOT NULL,
+    PRIMARY KEY (view_id, day, visitor_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_published_view_daily_visitor_day
+    ON af_published_view_daily_visitor (day);
@@ -0,0 +1,20 @@
+-- Jobs rendering all pages published under a namespace to a static HTML bundle.
+-- status: 0 => pending, 1 => running, 2 => completed, 3 => failed
+CREATE TABLE IF NOT EXISTS af_published_site_export (
+    export_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    namespace TEXT NOT NULL,
+    status SMALLINT NOT NULL DEFAULT 0,
+    object_prefix TEXT,
+    page_count INT NOT NULL DEFAULT 0,
+    error TEXT,
+    created_by BIGINT REFERENCES af_user(uid) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_published_site_export_workspace_id
+    ON af_published_site_export (workspace_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_af_published_site_export_pending
+    ON af_published_site_export (created_at) WHERE status IN (0, 1);
@@ -0,0 +1,14 @@
+-- Custom domains serving the publish namespace of a workspace.
+-- A domain can be claimed by several workspaces, but only one of them can verify it, by
+-- publishing the verification token in a DNS TXT record.
+CREATE TABLE IF NOT EXISTS af_publish_domain (
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    domain TEXT NOT NULL,
+    verification_token TEXT NOT NULL,
+    verified_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, domain)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_af_publish_domain_verified
+    ON af_publish_domain (domain) WHERE verified_at IS NOT NULL;
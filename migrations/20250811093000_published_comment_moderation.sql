@@ -0,0 +1,26 @@
+-- 0: approved, 1: pending approval, 2: rejected.
+-- Existing comments were visible to everyone, so they are approved.
+ALTER TABLE af_published_view_comment ADD COLUMN IF NOT EXISTS status SMALLINT NOT NULL DEFAULT 0;
+
+-- Used to rate limit commenters
+CREATE INDEX IF NOT EXISTS idx_created_by_created_at_on_af_published_view_comment
+    ON af_published_view_comment (created_by, created_at);
+
+-- Moderation settings applying to all the pages published by a workspace
+CREATE TABLE IF NOT EXISTS af_published_comment_setting (
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    -- new comments must be approved by the workspace owner before being visible to others
+    require_approval BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id)
+);
+
+-- Users who are not allowed to comment on the pages published by a workspace
+CREATE TABLE IF NOT EXISTS af_published_comment_blocklist (
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    uid BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    reason TEXT,
+    created_by BIGINT REFERENCES af_user(uid) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, uid)
+);
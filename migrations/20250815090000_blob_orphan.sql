@@ -0,0 +1,11 @@
+-- Blobs found unreferenced by the documents and databases of their workspace. A blob is deleted
+-- once it has stayed unreferenced until `delete_after`; it is unmarked as soon as it is
+-- referenced again.
+CREATE TABLE IF NOT EXISTS af_blob_orphan (
+    workspace_id UUID NOT NULL,
+    file_id VARCHAR NOT NULL,
+    marked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delete_after TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (workspace_id, file_id),
+    FOREIGN KEY (workspace_id, file_id) REFERENCES af_blob_metadata (workspace_id, file_id) ON DELETE CASCADE
+);
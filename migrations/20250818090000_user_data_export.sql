@@ -0,0 +1,21 @@
+-- Jobs collecting all the data of a user into an archive, which can be downloaded until it expires.
+-- status: 0 => pending, 1 => running, 2 => completed, 3 => failed
+CREATE TABLE IF NOT EXISTS af_user_data_export (
+    export_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    uid BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    status SMALLINT NOT NULL DEFAULT 0,
+    object_key TEXT,
+    file_size BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    expires_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_user_data_export_uid
+    ON af_user_data_export (uid, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_af_user_data_export_pending
+    ON af_user_data_export (created_at) WHERE status IN (0, 1);
+CREATE INDEX IF NOT EXISTS idx_af_user_data_export_expires_at
+    ON af_user_data_export (expires_at) WHERE object_key IS NOT NULL;
@@ -0,0 +1,33 @@
+-- Change feed of the rows of databases, ordered by change_id, which is used as cursor. The
+-- changes are recorded from the collab update stream when the updates of a workspace are
+-- flushed, and are kept for a limited time.
+-- change_type: 0 => created, 1 => updated, 2 => deleted
+CREATE TABLE IF NOT EXISTS af_database_row_change (
+    change_id BIGSERIAL PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    database_id UUID NOT NULL,
+    row_id UUID NOT NULL,
+    change_type SMALLINT NOT NULL,
+    -- changed cells, by field id. Removed cells are null.
+    cells JSONB NOT NULL DEFAULT '{}'::jsonb,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_database_row_change_database
+    ON af_database_row_change (database_id, change_id);
+CREATE INDEX IF NOT EXISTS idx_af_database_row_change_workspace_changed_at
+    ON af_database_row_change (workspace_id, changed_at);
+
+-- State of the database and database row collabs as of their last recorded update. The updates
+-- of the stream are replayed on it one at a time, so that each change is compared with the
+-- state it was made on, even if the collab was saved in between. The states of the collabs
+-- which are not updated anymore are dropped, and rebuilt from the saved collab when needed.
+CREATE TABLE IF NOT EXISTS af_database_row_change_baseline (
+    object_id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    doc_state BYTEA NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_database_row_change_baseline_workspace_updated_at
+    ON af_database_row_change_baseline (workspace_id, updated_at);
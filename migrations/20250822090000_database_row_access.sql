@@ -0,0 +1,21 @@
+-- Row level access rule of a database. When a database has a rule, the members of the workspace,
+-- other than its owners, only see the rows the rule lets them see: the rows whose cell in
+-- user_field_id contains them, and the rows granted to them in af_database_row_grant.
+CREATE TABLE IF NOT EXISTS af_database_row_rule (
+    database_id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    user_field_id TEXT,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Rows explicitly granted to users, in the databases with a row level access rule.
+CREATE TABLE IF NOT EXISTS af_database_row_grant (
+    database_id UUID NOT NULL REFERENCES af_database_row_rule(database_id) ON DELETE CASCADE,
+    row_id UUID NOT NULL,
+    uid BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (database_id, row_id, uid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_database_row_grant_uid
+    ON af_database_row_grant (database_id, uid);
@@ -0,0 +1,40 @@
+-- Templates of the rows of a database: default cell values, by field id or name, and the content
+-- of the row document in markdown. A template with a schedule is inserted by the worker at
+-- next_run_at, which is moved to the next time matching the cron-like schedule.
+CREATE TABLE IF NOT EXISTS af_database_row_template (
+    template_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    database_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    cells JSONB NOT NULL DEFAULT '{}'::jsonb,
+    row_doc_content TEXT,
+    schedule TEXT,
+    next_run_at TIMESTAMP WITH TIME ZONE,
+    -- the scheduled rows are inserted on behalf of the creator of the template
+    created_by BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_database_row_template_database
+    ON af_database_row_template (workspace_id, database_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_af_database_row_template_next_run_at
+    ON af_database_row_template (next_run_at) WHERE next_run_at IS NOT NULL;
+
+-- Scheduled insertions of templates, queued by the worker and run by the server.
+-- status: 0 => pending, 1 => running, 2 => completed, 3 => failed
+CREATE TABLE IF NOT EXISTS af_database_row_template_run (
+    run_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template_id UUID NOT NULL REFERENCES af_database_row_template(template_id) ON DELETE CASCADE,
+    scheduled_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    status SMALLINT NOT NULL DEFAULT 0,
+    row_id UUID,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (template_id, scheduled_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_af_database_row_template_run_pending
+    ON af_database_row_template_run (created_at) WHERE status IN (0, 1);
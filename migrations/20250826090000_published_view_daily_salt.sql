@@ -0,0 +1,6 @@
+-- Random salt of the visitor hashes of a given day. Salts older than a day are purged together
+-- with the visitor hashes, so the hashes can't be linked to visitors afterwards.
+CREATE TABLE IF NOT EXISTS af_published_view_daily_salt (
+    day DATE PRIMARY KEY,
+    salt BYTEA NOT NULL
+);
@@ -132,6 +132,10 @@ impl CollabCache {
     &self.metrics
   }
 
+  pub fn pg_pool(&self) -> &PgPool {
+    self.disk_cache.pg_pool()
+  }
+
   /// **Note: This function will override any existing values without timestamp comparison.**
   /// Use the single insert methods if you need conditional insertion based on timestamps.
   #[instrument(level = "trace", skip_all)]
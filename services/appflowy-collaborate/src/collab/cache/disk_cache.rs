@@ -107,6 +107,10 @@ impl CollabDiskCache {
     self.s3.clone()
   }
 
+  pub fn pg_pool(&self) -> &PgPool {
+    &self.pg_pool
+  }
+
   pub async fn upsert_collab_with_transaction(
     workspace_id: &Uuid,
     uid: &i64,
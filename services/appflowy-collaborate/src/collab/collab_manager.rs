@@ -1,7 +1,13 @@
 use crate::collab::cache::mem_cache::MillisSeconds;
 use crate::collab::cache::CollabCache;
+use crate::collab::row_access::{can_read_row, row_document_id};
+use crate::collab::row_change::{
+  created_row_cells, database_body, database_row_ids, replay_database_row_updates,
+  replay_database_updates, ReplayedRowChanges,
+};
 use access_control::act::Action;
 use access_control::collab::CollabAccessControl;
+use access_control::database_row::DatabaseRowAccessControl;
 use anyhow::anyhow;
 use app_error::AppError;
 use appflowy_proto::{ObjectId, Rid, TimestampedEncodedCollab, UpdateFlags, WorkspaceId};
@@ -11,6 +17,7 @@ use collab::core::collab::{default_client_id, CollabOptions};
 use collab::core::origin::CollabOrigin;
 use collab::entity::{EncodedCollab, EncoderVersion};
 use collab::preclude::Collab;
+use collab_database::rows::RowDetail;
 use collab_document::document::DocumentBody;
 use collab_entity::CollabType;
 use collab_folder::Folder;
@@ -19,7 +26,14 @@ use collab_stream::lease::Lease;
 use collab_stream::model::{AwarenessStreamUpdate, MessageId, UpdateStreamMessage};
 use collab_stream::stream_router::StreamRouter;
 use database::collab::AppResult;
-use database_entity::dto::{CollabParams, CollabUpdateData, QueryCollab};
+use database::database_row_access::select_database_row_rule_ids;
+use database::database_row_change::{
+  delete_database_row_change_baselines, delete_database_row_change_baselines_before,
+  delete_database_row_changes_before, insert_database_row_changes,
+  select_database_row_change_baselines, upsert_database_row_change_baselines,
+  ROW_CHANGE_BASELINE_IDLE_DAYS, ROW_CHANGE_RETENTION_DAYS, ROW_CREATED, ROW_DELETED,
+};
+use database_entity::dto::{CollabParams, CollabUpdateData, QueryCollab, QueryCollabResult};
 use indexer::scheduler::{IndexerScheduler, UnindexedCollabTask, UnindexedData};
 use infra::thread_pool::ThreadPoolNoAbort;
 use itertools::Itertools;
@@ -27,7 +41,7 @@ use rayon::prelude::*;
 use redis::aio::ConnectionManager;
 use redis::streams::{StreamTrimOptions, StreamTrimmingMode};
 use redis::AsyncCommands;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -43,6 +57,7 @@ use yrs::{ReadTxn, StateVector, Update};
 pub struct CollabManager {
   collab_cache: Arc<CollabCache>,
   access_control: Arc<dyn CollabAccessControl>,
+  database_row_access_control: Arc<dyn DatabaseRowAccessControl>,
   update_streams: Arc<StreamRouter>,
   awareness_broadcast: Arc<AwarenessGossip>,
   connection_manager: ConnectionManager,
@@ -55,6 +70,7 @@ impl CollabManager {
   pub fn new(
     thread_pool: Arc<ThreadPoolNoAbort>,
     access_control: Arc<dyn CollabAccessControl>,
+    database_row_access_control: Arc<dyn DatabaseRowAccessControl>,
     collab_cache: Arc<CollabCache>,
     connection_manager: ConnectionManager,
     update_streams: Arc<StreamRouter>,
@@ -63,6 +79,7 @@ impl CollabManager {
   ) -> Arc<Self> {
     Arc::new(Self {
       access_control,
+      database_row_access_control,
       collab_cache,
       update_streams,
       awareness_broadcast,
@@ -169,6 +186,106 @@ impl CollabManager {
       .await
   }
 
+  /// Checks the row level rule of the database for the database rows and the documents of the
+  /// rows. Rows which don't exist yet can be read, like other collabs.
+  pub async fn enforce_read_row_collab(
+    &self,
+    workspace_id: &WorkspaceId,
+    uid: &i64,
+    object_id: &ObjectId,
+    collab_type: &CollabType,
+  ) -> AppResult<()> {
+    let row_id = match collab_type {
+      CollabType::DatabaseRow => *object_id,
+      CollabType::Document => {
+        match self
+          .restricted_row_of_document(workspace_id, uid, object_id)
+          .await?
+        {
+          Some(row_id) => row_id,
+          None => return Ok(()),
+        }
+      },
+      _ => return Ok(()),
+    };
+    let query = QueryCollab::new(row_id, CollabType::DatabaseRow);
+    let encoded_collab = match self
+      .collab_cache
+      .get_full_collab(workspace_id, query, None, EncoderVersion::V1)
+      .await
+    {
+      Ok(collab) => collab.encoded_collab,
+      Err(AppError::RecordNotFound(_)) => return Ok(()),
+      Err(err) => return Err(err),
+    };
+    let options = CollabOptions::new(row_id.to_string(), default_client_id())
+      .with_data_source(encoded_collab.into());
+    let collab = Collab::new_with_options(CollabOrigin::Server, options)
+      .map_err(|err| AppError::Internal(anyhow!("Unable to decode row {}: {}", row_id, err)))?;
+    let row = RowDetail::from_collab(&collab)
+      .ok_or_else(|| AppError::Internal(anyhow!("Unable to decode row {}", row_id)))?
+      .row;
+    let database_id = Uuid::from_str(&row.database_id)?;
+
+    let row_access = self
+      .database_row_access_control
+      .get_row_access(workspace_id, uid, &database_id)
+      .await?;
+    if can_read_row(&row_access, &row_id, &row) {
+      Ok(())
+    } else {
+      Err(AppError::NotEnoughPermissions)
+    }
+  }
+
+  /// Returns the row of the document, if it belongs to a database which restricts the rows the
+  /// user can read. The ids of the row documents are derived from the row ids, so the rows of
+  /// these databases are listed to find it.
+  async fn restricted_row_of_document(
+    &self,
+    workspace_id: &WorkspaceId,
+    uid: &i64,
+    document_id: &ObjectId,
+  ) -> AppResult<Option<Uuid>> {
+    let pg_pool = self.collab_cache.pg_pool();
+    for database_id in select_database_row_rule_ids(pg_pool, workspace_id).await? {
+      let row_access = self
+        .database_row_access_control
+        .get_row_access(workspace_id, uid, &database_id)
+        .await?;
+      if !row_access.is_restricted() {
+        continue;
+      }
+      let query = QueryCollab::new(database_id, CollabType::Database);
+      let encoded_collab = match self
+        .collab_cache
+        .get_full_collab(workspace_id, query, None, EncoderVersion::V1)
+        .await
+      {
+        Ok(collab) => collab.encoded_collab,
+        Err(AppError::RecordNotFound(_)) => continue,
+        Err(err) => return Err(err),
+      };
+      let options = CollabOptions::new(database_id.to_string(), default_client_id())
+        .with_data_source(encoded_collab.into());
+      let collab = Collab::new_with_options(CollabOrigin::Server, options).map_err(|err| {
+        AppError::Internal(anyhow!(
+          "Unable to decode database {}: {}",
+          database_id,
+          err
+        ))
+      })?;
+      let db_body = database_body(default_client_id(), &collab);
+      let row_id = database_row_ids(&collab, db_body.as_ref())
+        .into_iter()
+        .find(|row_id| row_document_id(row_id).as_ref() == Some(document_id));
+      if row_id.is_some() {
+        return Ok(row_id);
+      }
+    }
+    Ok(None)
+  }
+
   pub async fn get_collabs_created_since(
     &self,
     workspace_id: Uuid,
@@ -336,6 +453,33 @@ impl CollabManager {
     if let Some(_lease) = self.acquire_workspace_lock(workspace_id).await? {
       let snapshot_start = std::time::Instant::now();
 
+      // Record the row changes before the updates are pruned. A failure does not hold back the
+      // snapshot: the changes of these updates are missed, and the next updates of the collabs
+      // are replayed on their saved state.
+      if let Err(err) = self
+        .record_database_row_changes(workspace_id, &all_object_updates)
+        .await
+      {
+        warn!(
+          "Failed to record the row changes of workspace {}: {}",
+          workspace_id, err
+        );
+        let object_ids: Vec<Uuid> = all_object_updates.keys().copied().collect();
+        let pg_pool = self.collab_cache.pg_pool();
+        if let Err(err) = delete_database_row_change_baselines(pg_pool, &object_ids).await {
+          warn!(
+            "Failed to reset the row change baselines of workspace {}: {}",
+            workspace_id, err
+          );
+        }
+      }
+      if let Err(err) = self.prune_database_row_changes(workspace_id).await {
+        warn!(
+          "Failed to prune the row changes of workspace {}: {}",
+          workspace_id, err
+        );
+      }
+
       // Count total updates for metrics
       let total_updates: usize = all_object_updates
         .values()
@@ -387,6 +531,213 @@ impl CollabManager {
     Ok(())
   }
 
+  /// Appends the changes of the rows made by the updates to the feed of their database. The
+  /// updates are replayed one at a time on the state of each collab as of its last recorded
+  /// update, so that each update is compared with the state it was made on, even if the collab
+  /// was saved in between, as the rows written by the server are.
+  async fn record_database_row_changes(
+    &self,
+    workspace_id: WorkspaceId,
+    all_object_updates: &HashMap<Uuid, Vec<UpdateStreamMessage>>,
+  ) -> anyhow::Result<()> {
+    let mut database_objects = vec![];
+    let mut row_objects = vec![];
+    for (object_id, updates) in all_object_updates {
+      match updates.first().map(|update| update.collab_type) {
+        Some(CollabType::Database) => database_objects.push((*object_id, updates.as_slice())),
+        Some(CollabType::DatabaseRow) => row_objects.push((*object_id, updates.as_slice())),
+        _ => {},
+      }
+    }
+    if database_objects.is_empty() && row_objects.is_empty() {
+      return Ok(());
+    }
+
+    let pg_pool = self.collab_cache.pg_pool();
+    let object_ids: Vec<Uuid> = database_objects
+      .iter()
+      .chain(row_objects.iter())
+      .map(|(object_id, _)| *object_id)
+      .collect();
+    let mut baselines: HashMap<Uuid, Bytes> =
+      select_database_row_change_baselines(pg_pool, &object_ids)
+        .await?
+        .into_iter()
+        .map(|(object_id, doc_state)| (object_id, Bytes::from(doc_state)))
+        .collect();
+
+    // the databases updated for the first time start from their saved state
+    for (object_id, _) in &database_objects {
+      if !baselines.contains_key(object_id) {
+        let (_, doc_state) = self
+          .get_snapshot(workspace_id, *object_id, CollabType::Database)
+          .await?;
+        baselines.insert(*object_id, doc_state);
+      }
+    }
+    let mut replayed =
+      self.replay_row_changes(&database_objects, &baselines, replay_database_updates)?;
+
+    // the rows created by the updates start from an empty state, so that the update which
+    // initializes them gives their cells, and the other rows from their saved state
+    let created_row_ids: HashSet<Uuid> = replayed
+      .iter()
+      .filter_map(|(_, result)| result.as_ref().ok())
+      .flat_map(|replayed| replayed.changes.iter())
+      .filter(|(_, change)| change.change_type == ROW_CREATED)
+      .map(|(_, change)| change.row_id)
+      .collect();
+    for (object_id, _) in &row_objects {
+      if !baselines.contains_key(object_id) {
+        let doc_state = if created_row_ids.contains(object_id) {
+          Bytes::new()
+        } else {
+          self
+            .get_snapshot(workspace_id, *object_id, CollabType::DatabaseRow)
+            .await?
+            .1
+        };
+        baselines.insert(*object_id, doc_state);
+      }
+    }
+    replayed.extend(self.replay_row_changes(
+      &row_objects,
+      &baselines,
+      replay_database_row_updates,
+    )?);
+
+    let mut changes = vec![];
+    let mut initial_cells = HashMap::new();
+    let mut recorded_object_ids = vec![];
+    let mut doc_states = vec![];
+    let mut reset_object_ids = vec![];
+    for (object_id, result) in replayed {
+      match result {
+        Ok(replayed) => {
+          recorded_object_ids.push(object_id);
+          doc_states.push(replayed.doc_state);
+          changes.extend(replayed.changes);
+          if let Some(cells) = replayed.initial_cells {
+            initial_cells.insert(object_id, cells);
+          }
+        },
+        Err(err) => {
+          // the next updates are replayed on the saved state
+          warn!("Failed to record the row changes of {}: {}", object_id, err);
+          reset_object_ids.push(object_id);
+        },
+      }
+    }
+    changes.sort_by_key(|(rid, _)| *rid);
+
+    // the rows created before their updates were recorded are read from their saved state
+    let queries: Vec<QueryCollab> = created_row_ids
+      .iter()
+      .filter(|row_id| !initial_cells.contains_key(*row_id))
+      .map(|row_id| QueryCollab::new(*row_id, CollabType::DatabaseRow))
+      .collect();
+    if !queries.is_empty() {
+      let client_id = default_client_id();
+      let saved_rows = self
+        .collab_cache
+        .batch_get_full_collab(&workspace_id, queries, None, EncoderVersion::V1)
+        .await;
+      for (row_id, result) in saved_rows {
+        if let QueryCollabResult::Success { encode_collab_v1 } = result {
+          let cells = EncodedCollab::decode_from_bytes(&encode_collab_v1)
+            .map_err(|err| anyhow!("failed to decode row {}: {}", row_id, err))
+            .and_then(|encoded_collab| created_row_cells(client_id, row_id, encoded_collab));
+          match cells {
+            Ok(cells) => {
+              initial_cells.insert(row_id, cells);
+            },
+            Err(err) => warn!(
+              "Failed to read the cells of the created row {}: {}",
+              row_id, err
+            ),
+          }
+        }
+      }
+    }
+    for (_, change) in changes.iter_mut() {
+      if change.change_type == ROW_CREATED {
+        if let Some(cells) = initial_cells.get(&change.row_id) {
+          change.cells = cells.clone();
+        }
+      }
+    }
+    // the deleted rows are not updated anymore
+    reset_object_ids.extend(
+      changes
+        .iter()
+        .filter(|(_, change)| change.change_type == ROW_DELETED)
+        .map(|(_, change)| change.row_id),
+    );
+
+    let mut txn = pg_pool.begin().await?;
+    if !changes.is_empty() {
+      let changes = changes.into_iter().map(|(_, change)| change).collect();
+      insert_database_row_changes(&mut *txn, &workspace_id, changes).await?;
+    }
+    upsert_database_row_change_baselines(
+      &mut *txn,
+      &workspace_id,
+      &recorded_object_ids,
+      &doc_states,
+    )
+    .await?;
+    if !reset_object_ids.is_empty() {
+      delete_database_row_change_baselines(&mut *txn, &reset_object_ids).await?;
+    }
+    txn.commit().await?;
+    Ok(())
+  }
+
+  /// Deletes the row changes older than their retention period, and the recorded states of the
+  /// collabs which are not updated anymore.
+  async fn prune_database_row_changes(&self, workspace_id: WorkspaceId) -> anyhow::Result<()> {
+    let pg_pool = self.collab_cache.pg_pool();
+    let now = Utc::now();
+    delete_database_row_changes_before(
+      pg_pool,
+      &workspace_id,
+      now - chrono::Duration::days(ROW_CHANGE_RETENTION_DAYS),
+    )
+    .await?;
+    delete_database_row_change_baselines_before(
+      pg_pool,
+      &workspace_id,
+      now - chrono::Duration::days(ROW_CHANGE_BASELINE_IDLE_DAYS),
+    )
+    .await?;
+    Ok(())
+  }
+
+  /// Replays the updates of the collabs in parallel on their baseline.
+  fn replay_row_changes(
+    &self,
+    objects: &[(ObjectId, &[UpdateStreamMessage])],
+    baselines: &HashMap<Uuid, Bytes>,
+    replay: ReplayRowChanges,
+  ) -> anyhow::Result<Vec<(ObjectId, anyhow::Result<ReplayedRowChanges>)>> {
+    let client_id = default_client_id();
+    self
+      .snapshot_thread_pool
+      .install(|| {
+        objects
+          .par_iter()
+          .map(|(object_id, updates)| {
+            let baseline = baselines
+              .get(object_id)
+              .map(|doc_state| &doc_state[..])
+              .unwrap_or_default();
+            (*object_id, replay(client_id, *object_id, baseline, updates))
+          })
+          .collect()
+      })
+      .map_err(|err| anyhow!("Thread pool panic during row change recording: {}", err))
+  }
+
   /// Acquires a distributed lock for workspace snapshotting
   async fn acquire_workspace_lock(
     &self,
@@ -738,6 +1089,9 @@ pub fn decode_update(update: &[u8]) -> AppResult<Update> {
   }
 }
 
+type ReplayRowChanges =
+  fn(ClientID, ObjectId, &[u8], &[UpdateStreamMessage]) -> anyhow::Result<ReplayedRowChanges>;
+
 struct SnapshotTask {
   workspace_id: WorkspaceId,
   object_id: ObjectId,
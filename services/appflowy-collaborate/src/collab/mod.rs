@@ -1,4 +1,6 @@
 pub mod cache;
 pub mod collab_manager;
 pub mod collab_store;
+pub mod row_access;
+pub mod row_change;
 pub mod snapshot_scheduler;
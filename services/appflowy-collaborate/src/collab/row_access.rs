@@ -0,0 +1,19 @@
+use access_control::database_row::DatabaseRowAccess;
+use collab_database::rows::{meta_id_from_row_id, Row, RowMetaKey};
+use collab_database::template::entity::CELL_DATA;
+use uuid::Uuid;
+
+/// Checks the row against the row level rule of its database.
+pub fn can_read_row(row_access: &DatabaseRowAccess, row_id: &Uuid, row: &Row) -> bool {
+  let user_field_data = row_access
+    .user_field_id()
+    .and_then(|field_id| row.cells.get(field_id))
+    .and_then(|cell| cell.get(CELL_DATA))
+    .map(|data| data.to_string());
+  row_access.can_read_row(row_id, user_field_data.as_deref())
+}
+
+/// Returns the id of the document of the row, which is derived from the id of the row.
+pub fn row_document_id(row_id: &Uuid) -> Option<Uuid> {
+  Uuid::parse_str(&meta_id_from_row_id(row_id, RowMetaKey::DocumentId)).ok()
+}
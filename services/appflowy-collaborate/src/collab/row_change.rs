@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use appflowy_proto::{ObjectId, Rid, UpdateFlags};
+use chrono::{DateTime, Utc};
+use collab::core::collab::CollabOptions;
+use collab::core::origin::CollabOrigin;
+use collab::entity::EncodedCollab;
+use collab::preclude::Collab;
+use collab_database::database::DatabaseBody;
+use collab_database::database_trait::NoPersistenceDatabaseCollabService;
+use collab_database::rows::{Cells, RowDetail};
+use collab_database::template::entity::CELL_DATA;
+use collab_stream::model::UpdateStreamMessage;
+use database::database_row_change::{NewDatabaseRowChange, ROW_CREATED, ROW_DELETED, ROW_UPDATED};
+use serde_json::json;
+use uuid::Uuid;
+use yrs::block::ClientID;
+use yrs::updates::decoder::Decode;
+use yrs::{ReadTxn, StateVector, Update};
+
+use super::collab_manager::decode_update;
+
+/// Result of replaying the updates of a database or database row collab.
+pub struct ReplayedRowChanges {
+  pub object_id: ObjectId,
+  /// Changes with the id of the update they were made by, in the order of the updates. The cells
+  /// of the created rows are left empty, as they are not part of the database collab.
+  pub changes: Vec<(Rid, NewDatabaseRowChange)>,
+  /// Cells of a row initialized by the updates, which are the cells of its created change.
+  pub initial_cells: Option<serde_json::Value>,
+  /// State of the collab after the last update, on which the next updates are replayed.
+  pub doc_state: Vec<u8>,
+}
+
+/// Replays the updates of a database row one at a time on `baseline`, and returns an update
+/// change for each update which changed the data of cells, with the changed cells by field id.
+/// The cells removed from the row are null. The update which initializes the row is not a
+/// change, the row is recorded as created when it is added to its database.
+pub fn replay_database_row_updates(
+  client_id: ClientID,
+  object_id: ObjectId,
+  baseline: &[u8],
+  updates: &[UpdateStreamMessage],
+) -> anyhow::Result<ReplayedRowChanges> {
+  let mut collab = baseline_collab(client_id, object_id, baseline)?;
+  let mut changes = vec![];
+  let mut initial_cells = None;
+  let mut before = RowDetail::from_collab(&collab).map(|detail| detail.row);
+  for message in updates {
+    apply_message(&mut collab, message)?;
+    let after = RowDetail::from_collab(&collab).map(|detail| detail.row);
+    if let (None, Some(after)) = (&before, &after) {
+      initial_cells = Some(json!(changed_cells(&Cells::default(), &after.cells)));
+    }
+    if let (Some(before), Some(after)) = (&before, &after) {
+      let cells = changed_cells(&before.cells, &after.cells);
+      if !cells.is_empty() {
+        let change = NewDatabaseRowChange {
+          database_id: Uuid::from_str(&after.database_id)?,
+          row_id: object_id,
+          change_type: ROW_UPDATED,
+          cells: json!(cells),
+          changed_at: changed_at(message),
+        };
+        changes.push((message.last_message_id, change));
+      }
+    }
+    before = after;
+  }
+  Ok(ReplayedRowChanges {
+    object_id,
+    changes,
+    initial_cells,
+    doc_state: collab.transact().encode_diff_v1(&StateVector::default()),
+  })
+}
+
+/// Replays the updates of a database one at a time on `baseline`, and returns a created or
+/// deleted change for each row added to or removed from the database by an update.
+pub fn replay_database_updates(
+  client_id: ClientID,
+  object_id: ObjectId,
+  baseline: &[u8],
+  updates: &[UpdateStreamMessage],
+) -> anyhow::Result<ReplayedRowChanges> {
+  let mut collab = baseline_collab(client_id, object_id, baseline)?;
+  let mut changes = vec![];
+  let mut db_body = database_body(client_id, &collab);
+  let mut before = database_row_ids(&collab, db_body.as_ref());
+  for message in updates {
+    apply_message(&mut collab, message)?;
+    if db_body.is_none() {
+      db_body = database_body(client_id, &collab);
+    }
+    let after = database_row_ids(&collab, db_body.as_ref());
+    let changed_at = changed_at(message);
+    for (row_ids, others, change_type) in [
+      (&after, &before, ROW_CREATED),
+      (&before, &after, ROW_DELETED),
+    ] {
+      changes.extend(
+        row_ids
+          .iter()
+          .filter(|row_id| !others.contains(*row_id))
+          .map(|row_id| {
+            let change = NewDatabaseRowChange {
+              database_id: object_id,
+              row_id: *row_id,
+              change_type,
+              cells: json!({}),
+              changed_at,
+            };
+            (message.last_message_id, change)
+          }),
+      );
+    }
+    before = after;
+  }
+  Ok(ReplayedRowChanges {
+    object_id,
+    changes,
+    initial_cells: None,
+    doc_state: collab.transact().encode_diff_v1(&StateVector::default()),
+  })
+}
+
+/// Returns the data of all the cells of a row, by field id, for the rows which were created
+/// before their updates were recorded.
+pub fn created_row_cells(
+  client_id: ClientID,
+  row_id: ObjectId,
+  encoded_collab: EncodedCollab,
+) -> anyhow::Result<serde_json::Value> {
+  let options =
+    CollabOptions::new(row_id.to_string(), client_id).with_data_source(encoded_collab.into());
+  let collab = Collab::new_with_options(CollabOrigin::Server, options)
+    .map_err(|err| anyhow!("failed to decode row {}: {}", row_id, err))?;
+  let row = RowDetail::from_collab(&collab)
+    .ok_or_else(|| anyhow!("failed to decode row {}", row_id))?
+    .row;
+  Ok(json!(changed_cells(&Cells::default(), &row.cells)))
+}
+
+fn baseline_collab(
+  client_id: ClientID,
+  object_id: ObjectId,
+  baseline: &[u8],
+) -> anyhow::Result<Collab> {
+  let options = CollabOptions::new(object_id.to_string(), client_id);
+  let mut collab = Collab::new_with_options(CollabOrigin::Server, options)
+    .map_err(|err| anyhow!("failed to create collab: {}", err))?;
+  if !baseline.is_empty() {
+    collab
+      .transact_mut()
+      .apply_update(decode_update(baseline)?)?;
+  }
+  Ok(collab)
+}
+
+fn apply_message(collab: &mut Collab, message: &UpdateStreamMessage) -> anyhow::Result<()> {
+  let update = match message.update_flags {
+    UpdateFlags::Lib0v1 => Update::decode_v1(&message.update),
+    UpdateFlags::Lib0v2 => Update::decode_v2(&message.update),
+  }?;
+  collab.transact_mut().apply_update(update)?;
+  Ok(())
+}
+
+fn changed_at(message: &UpdateStreamMessage) -> DateTime<Utc> {
+  DateTime::from_timestamp_millis(message.last_message_id.timestamp as i64).unwrap_or_else(Utc::now)
+}
+
+pub(crate) fn database_body(client_id: ClientID, collab: &Collab) -> Option<DatabaseBody> {
+  DatabaseBody::from_collab(
+    collab,
+    Arc::new(NoPersistenceDatabaseCollabService::new(client_id)),
+    None,
+  )
+}
+
+/// Returns the rows of the database, read from the row orders of its inline view.
+pub(crate) fn database_row_ids(collab: &Collab, db_body: Option<&DatabaseBody>) -> HashSet<Uuid> {
+  let Some(db_body) = db_body else {
+    return HashSet::new();
+  };
+  let txn = collab.transact();
+  let inline_view_id = db_body.get_inline_view_id(&txn);
+  db_body
+    .views
+    .get_view(&txn, &inline_view_id)
+    .map(|view| {
+      view
+        .row_orders
+        .iter()
+        .flat_map(|row_order| Uuid::parse_str(&row_order.id.to_string()))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Returns the data of the cells which changed, by field id, with null for the removed cells.
+fn changed_cells(before: &Cells, after: &Cells) -> HashMap<String, serde_json::Value> {
+  let data = |cells: &Cells, field_id: &str| {
+    cells
+      .get(field_id)
+      .and_then(|cell| cell.get(CELL_DATA))
+      .cloned()
+  };
+  before
+    .keys()
+    .chain(after.keys())
+    .filter(|field_id| data(before, field_id.as_str()) != data(after, field_id.as_str()))
+    .map(|field_id| {
+      let value = data(after, field_id)
+        .and_then(|value| serde_json::to_value(value).ok())
+        .unwrap_or_default();
+      (field_id.clone(), value)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use collab::core::collab::default_client_id;
+  use collab_database::rows::{Cell, DatabaseRowBody, Row, RowId};
+  use collab_entity::CollabType;
+  use yrs::Any;
+
+  fn message(object_id: Uuid, seq_no: u16, update: Vec<u8>) -> UpdateStreamMessage {
+    UpdateStreamMessage {
+      last_message_id: Rid::new(1_700_000_000_000, seq_no),
+      sender: CollabOrigin::Server,
+      object_id,
+      collab_type: CollabType::DatabaseRow,
+      update_flags: UpdateFlags::Lib0v1,
+      update: update.into(),
+    }
+  }
+
+  #[test]
+  fn each_row_update_is_recorded_with_its_changed_cells() {
+    let client_id = default_client_id();
+    let row_id = Uuid::new_v4();
+    let database_id = Uuid::new_v4();
+    let options = CollabOptions::new(row_id.to_string(), client_id);
+    let mut collab = Collab::new_with_options(CollabOrigin::Empty, options).unwrap();
+    let row_body = DatabaseRowBody::create(
+      RowId::from(row_id.to_string()),
+      &mut collab,
+      Row::empty(RowId::from(row_id.to_string()), &database_id.to_string()),
+    );
+    let initial_state = collab
+      .transact()
+      .encode_state_as_update_v1(&StateVector::default());
+    let mut updates = vec![message(row_id, 0, initial_state)];
+    for (seq_no, text) in [(1, "v1"), (2, "v2")] {
+      let mut txn = collab.transact_mut();
+      row_body.update(&mut txn, |row_update| {
+        row_update.update_cells(|cells_update| {
+          let cell = Cell::from([(CELL_DATA.to_string(), Any::String(text.into()))]);
+          cells_update.insert_cell("description", cell);
+        });
+      });
+      updates.push(message(row_id, seq_no, txn.encode_update_v1()));
+    }
+
+    let replayed = replay_database_row_updates(client_id, row_id, &[], &updates).unwrap();
+    assert_eq!(replayed.initial_cells, Some(json!({})));
+    let changes: Vec<_> = replayed
+      .changes
+      .iter()
+      .map(|(_, change)| (change.database_id, change.change_type, change.cells.clone()))
+      .collect();
+    assert_eq!(
+      changes,
+      vec![
+        (database_id, ROW_UPDATED, json!({ "description": "v1" })),
+        (database_id, ROW_UPDATED, json!({ "description": "v2" })),
+      ]
+    );
+
+    // the updates already recorded are not recorded again
+    let replayed =
+      replay_database_row_updates(client_id, row_id, &replayed.doc_state, &updates).unwrap();
+    assert!(replayed.changes.is_empty());
+  }
+}
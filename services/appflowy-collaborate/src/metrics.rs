@@ -157,6 +157,14 @@ pub struct CollabMetrics {
   pub snapshot_duration: Histogram,
   /// Number of updates processed in each workspace snapshot
   pub snapshot_updates_count: Histogram,
+  /// Number of client messages delayed because of exceeded rate limits
+  pub rate_limit_throttled: Counter,
+  /// Number of awareness updates dropped because of exceeded rate limits
+  pub rate_limit_dropped: Counter,
+  /// Number of sessions disconnected because of exceeded rate limits
+  pub rate_limit_disconnects: Counter,
+  /// How long client messages were delayed by rate limits in milliseconds
+  pub rate_limit_delay: Histogram,
 }
 
 impl CollabMetrics {
@@ -228,6 +236,26 @@ impl CollabMetrics {
       "number of updates processed in each workspace snapshot",
       metrics.snapshot_updates_count.clone(),
     );
+    realtime_registry.register(
+      "rate_limit_throttled",
+      "number of client messages delayed because of exceeded rate limits",
+      metrics.rate_limit_throttled.clone(),
+    );
+    realtime_registry.register(
+      "rate_limit_dropped",
+      "number of awareness updates dropped because of exceeded rate limits",
+      metrics.rate_limit_dropped.clone(),
+    );
+    realtime_registry.register(
+      "rate_limit_disconnects",
+      "number of sessions disconnected because of exceeded rate limits",
+      metrics.rate_limit_disconnects.clone(),
+    );
+    realtime_registry.register(
+      "rate_limit_delay",
+      "time (in milliseconds) client messages were delayed by rate limits",
+      metrics.rate_limit_delay.clone(),
+    );
 
     metrics
   }
@@ -245,6 +273,10 @@ impl CollabMetrics {
   pub fn observe_snapshot_updates_count(&self, count: usize) {
     self.snapshot_updates_count.observe(count as f64);
   }
+
+  pub fn observe_rate_limit_delay(&self, duration: std::time::Duration) {
+    self.rate_limit_delay.observe(duration.as_millis() as f64);
+  }
 }
 
 impl Default for CollabMetrics {
@@ -280,6 +312,13 @@ impl Default for CollabMetrics {
         ]
         .into_iter(),
       ),
+      rate_limit_throttled: Default::default(),
+      rate_limit_dropped: Default::default(),
+      rate_limit_disconnects: Default::default(),
+      // Rate limit delay buckets: 10ms, 50ms, 100ms, 500ms, 1s, 2s, 5s
+      rate_limit_delay: Histogram::new(
+        [10.0, 50.0, 100.0, 500.0, 1000.0, 2000.0, 5000.0].into_iter(),
+      ),
     }
   }
 }
@@ -0,0 +1,162 @@
+use super::session::ExtraMessageReceiver;
+use actix::{Actor, AsyncContext, SpawnHandle};
+use app_error::AppError;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// WebSocket subprotocol used by the clients, which pass their access token through
+/// `Sec-WebSocket-Protocol` header: `Sec-WebSocket-Protocol: appflowy-sync-v2, {token}`.
+/// The server always responds with this protocol alone, so the token is never echoed back.
+pub const SYNC_PROTOCOL: &str = "appflowy-sync-v2";
+
+/// Time given to the client, which connected without an access token, to send it in
+/// the first message.
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of access tokens a single session can send within [AUTH_ATTEMPTS_WINDOW].
+/// Clients only need to refresh their token before it expires.
+const MAX_AUTH_ATTEMPTS: usize = 5;
+const AUTH_ATTEMPTS_WINDOW: Duration = Duration::from_secs(60);
+
+/// User verified by the [SessionAuthenticator].
+#[derive(Debug, Clone, Copy)]
+pub struct AuthenticatedUser {
+  pub uid: i64,
+  /// Unix timestamp (in seconds) at which the access token expires.
+  pub expires_at: Option<i64>,
+}
+
+/// Authentication state of a session at the moment of establishing the connection.
+#[derive(Debug, Clone, Copy)]
+pub enum SessionAuth {
+  /// Access token was provided and verified during connection handshake.
+  Authenticated(AuthenticatedUser),
+  /// Access token is expected to arrive as the first client message. Until then, the session
+  /// doesn't join the workspace and its [SessionInfo::user_id](super::SessionInfo) is not known.
+  Pending,
+}
+
+/// Verifies access tokens sent by the clients over established sync sessions: either as
+/// the first message or when refreshing the token before it expires.
+#[async_trait]
+pub trait SessionAuthenticator: Send + Sync + 'static {
+  async fn authenticate(&self, token: &str) -> Result<AuthenticatedUser, AppError>;
+
+  /// Returns a receiver of user-specific messages, which should be sent through the session
+  /// of authenticated user.
+  fn subscribe_user_messages(&self, uid: i64) -> ExtraMessageReceiver;
+}
+
+pub type SessionAuthenticatorRef = Arc<dyn SessionAuthenticator>;
+
+/// Closes the session once its access token expires, unless the token gets refreshed first.
+#[derive(Default)]
+pub struct TokenExpiry {
+  handle: Option<SpawnHandle>,
+}
+
+impl TokenExpiry {
+  /// Schedules `on_expired` to be called at `expires_at`, cancelling the previously scheduled
+  /// callback. Tokens without expiration date never expire.
+  pub fn schedule<A, F>(&mut self, expires_at: Option<i64>, ctx: &mut A::Context, on_expired: F)
+  where
+    A: Actor,
+    A::Context: AsyncContext<A>,
+    F: FnOnce(&mut A, &mut A::Context) + 'static,
+  {
+    if let Some(handle) = self.handle.take() {
+      ctx.cancel_future(handle);
+    }
+    if let Some(expires_at) = expires_at {
+      self.handle = Some(ctx.run_later(time_left(expires_at), on_expired));
+    }
+  }
+}
+
+/// Sliding window of the access tokens received by a session. Verifying the token is not
+/// free, so sessions sending more than [MAX_AUTH_ATTEMPTS] of them are closed.
+#[derive(Default)]
+pub struct AuthAttempts {
+  received_at: VecDeque<Instant>,
+}
+
+impl AuthAttempts {
+  /// Records the access token received now. Returns false if the session exceeded the limit.
+  pub fn try_record(&mut self) -> bool {
+    self.try_record_at(Instant::now())
+  }
+
+  fn try_record_at(&mut self, now: Instant) -> bool {
+    while let Some(received_at) = self.received_at.front() {
+      if now.saturating_duration_since(*received_at) < AUTH_ATTEMPTS_WINDOW {
+        break;
+      }
+      self.received_at.pop_front();
+    }
+    if self.received_at.len() >= MAX_AUTH_ATTEMPTS {
+      return false;
+    }
+    self.received_at.push_back(now);
+    true
+  }
+}
+
+fn time_left(expires_at: i64) -> Duration {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs() as i64;
+  Duration::from_secs(expires_at.saturating_sub(now).max(0) as u64)
+}
+
+/// Extracts the access token from `Sec-WebSocket-Protocol` header value. Clients put it next to
+/// the [SYNC_PROTOCOL], which has to be present.
+pub fn token_from_protocols(header: &str) -> Option<&str> {
+  let mut protocols = header.split(',').map(str::trim);
+  if !protocols.clone().any(|p| p == SYNC_PROTOCOL) {
+    return None;
+  }
+  protocols.find(|p| !p.is_empty() && *p != SYNC_PROTOCOL)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn token_is_read_from_protocols_header() {
+    assert_eq!(
+      token_from_protocols("appflowy-sync-v2, eyJhbGciOi.eyJzdWIiOi.c2lnbmF0dXJl"),
+      Some("eyJhbGciOi.eyJzdWIiOi.c2lnbmF0dXJl")
+    );
+    assert_eq!(
+      token_from_protocols("token-first,appflowy-sync-v2"),
+      Some("token-first")
+    );
+    assert_eq!(token_from_protocols("appflowy-sync-v2"), None);
+    assert_eq!(token_from_protocols("graphql-ws, something"), None);
+  }
+
+  #[test]
+  fn expired_token_has_no_time_left() {
+    assert_eq!(time_left(0), Duration::ZERO);
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap()
+      .as_secs() as i64;
+    assert!(time_left(now + 60) > Duration::from_secs(50));
+  }
+
+  #[test]
+  fn auth_attempts_are_limited_within_window() {
+    let mut attempts = AuthAttempts::default();
+    let now = Instant::now();
+    for _ in 0..MAX_AUTH_ATTEMPTS {
+      assert!(attempts.try_record_at(now));
+    }
+    assert!(!attempts.try_record_at(now + Duration::from_secs(1)));
+    assert!(attempts.try_record_at(now + AUTH_ATTEMPTS_WINDOW));
+  }
+}
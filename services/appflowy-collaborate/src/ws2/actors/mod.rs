@@ -1,7 +1,11 @@
+mod auth;
 mod server;
 mod session;
+mod sse_session;
 mod workspace;
 
+pub use auth::*;
 pub use server::*;
 pub use session::*;
+pub use sse_session::*;
 pub use workspace::*;
@@ -1,4 +1,4 @@
-use super::session::{WsInput, WsSession};
+use super::session::WsInput;
 use super::workspace::{Terminate, Workspace};
 use crate::collab::collab_manager::CollabManager;
 use crate::collab::snapshot_scheduler::SnapshotScheduler;
@@ -154,8 +154,9 @@ pub struct Join {
   pub session_id: ClientID,
   pub collab_origin: CollabOrigin,
   pub last_message_id: Option<Rid>,
-  /// Actix WebSocket session actor address.
-  pub addr: Addr<WsSession>,
+  /// Session actor address, used to send messages back to the client. It can be either
+  /// a WebSocket or HTTP fallback transport session.
+  pub addr: Recipient<WsOutput>,
   /// Workspace to join.
   pub workspace_id: WorkspaceId,
 }
@@ -1,17 +1,26 @@
+use super::auth::{
+  AuthAttempts, AuthenticatedUser, SessionAuth, SessionAuthenticatorRef, TokenExpiry, AUTH_TIMEOUT,
+};
 use super::server::{Join, Leave, WsOutput, WsServer};
+use crate::ws2::{RateLimitDecision, SessionRateLimiter};
 use actix::{
   fut, Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, ContextFutureSpawner, Handler,
   Running, StreamHandler, WrapFuture,
 };
 use actix_http::ws::{CloseCode, CloseReason, Item, ProtocolError};
 use actix_web_actors::ws;
-use appflowy_proto::{ClientMessage, ObjectId, Rid, ServerMessage, UpdateFlags, WorkspaceId};
+use appflowy_proto::{
+  into_frames, ChunkAssembler, ClientMessage, ObjectId, Rid, ServerMessage, UpdateFlags,
+  WorkspaceId,
+};
 use bytes::{Bytes, BytesMut};
 use collab::core::origin::{CollabClient, CollabOrigin};
 use collab_entity::CollabType;
+use collab_rt_entity::max_sync_message_size;
 use collab_stream::model::MessageId;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use tracing::error;
 use yrs::block::ClientID;
 use yrs::sync::AwarenessUpdate;
 use yrs::updates::decoder::Decode;
@@ -20,12 +29,26 @@ use yrs::StateVector;
 pub const HEARTBEAT: Duration = Duration::from_secs(5);
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(15);
 
+/// Maximum number of chunked client messages, which are reassembled at the same time by all
+/// sessions of this server.
+const MAX_PENDING_CHUNKED_MESSAGES: usize = 8;
+static PENDING_CHUNKED_MESSAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Upper limit of the reassembled client message. Clients only chunk the collab updates bigger
+/// than a single message, so it's a few times the realtime message size rather than the client
+/// side [MAX_CHUNKED_MESSAGE_SIZE](appflowy_proto::MAX_CHUNKED_MESSAGE_SIZE).
+fn max_chunked_client_message_size() -> usize {
+  4 * max_sync_message_size()
+}
+
 #[derive(Debug)]
 pub struct SessionInfo {
   pub client_id: ClientID,
   pub user_id: i64,
   pub device_id: String,
   pub last_message_id: Option<MessageId>,
+  /// Client is able to receive messages split into chunks.
+  pub supports_chunking: bool,
 }
 
 impl SessionInfo {
@@ -34,12 +57,14 @@ impl SessionInfo {
     user_id: i64,
     device_id: String,
     last_message_id: Option<MessageId>,
+    supports_chunking: bool,
   ) -> Self {
     Self {
       client_id,
       user_id,
       device_id,
       last_message_id,
+      supports_chunking,
     }
   }
 
@@ -55,7 +80,14 @@ pub struct WsSession {
   server: Addr<WsServer>,
   hb: Instant,
   buf: Option<BytesMut>,
-  extra_message_rx: Option<ExtraMessageReceiver>,
+  codec: SessionCodec,
+  authenticator: SessionAuthenticatorRef,
+  /// Authentication state at the moment of establishing the connection. Once the session
+  /// is authenticated, it's always [SessionAuth::Authenticated].
+  auth: SessionAuth,
+  auth_attempts: AuthAttempts,
+  token_expiry: TokenExpiry,
+  rate_limiter: SessionRateLimiter,
 }
 
 impl WsSession {
@@ -63,18 +95,29 @@ impl WsSession {
     workspace: WorkspaceId,
     info: SessionInfo,
     server: Addr<WsServer>,
-    extra_message_rx: ExtraMessageReceiver,
+    authenticator: SessionAuthenticatorRef,
+    auth: SessionAuth,
+    rate_limiter: SessionRateLimiter,
   ) -> Self {
     WsSession {
+      codec: SessionCodec::new(info.supports_chunking),
       info,
       server,
       current_workspace: workspace,
       hb: Instant::now(),
       buf: None,
-      extra_message_rx: Some(extra_message_rx),
+      authenticator,
+      auth,
+      auth_attempts: AuthAttempts::default(),
+      token_expiry: TokenExpiry::default(),
+      rate_limiter,
     }
   }
 
+  fn is_authenticated(&self) -> bool {
+    matches!(self.auth, SessionAuth::Authenticated(_))
+  }
+
   pub fn uid(&self) -> i64 {
     self.info.user_id
   }
@@ -104,61 +147,169 @@ impl WsSession {
   }
 
   fn handle_protocol(&mut self, bytes: Bytes, ctx: &mut ws::WebsocketContext<Self>) {
-    match ClientMessage::from_bytes(&bytes) {
-      Ok(message) => {
-        tracing::trace!(
-          "received message from session `{}`: {:#?}",
-          self.id(),
-          message
-        );
-        let object_id = *message.object_id();
-        let message = match InputMessage::try_from(message) {
-          Ok(msg) => msg,
-          Err(err) => return ctx.close(Some(CloseReason::from((CloseCode::Invalid, err)))),
-        };
-        self.server.do_send(WsInput {
+    if !self.is_authenticated() {
+      // message chunks are not accepted either, so that unauthenticated clients can't make
+      // the server buffer them
+      match SessionCodec::decode_auth(&bytes) {
+        Ok(token) => self.authenticate_rate_limited(token, bytes.len(), ctx),
+        Err(err) => {
+          tracing::warn!("client {} send message before authenticating", self.id());
+          Self::close(ctx, err.code, &err.reason);
+        },
+      }
+      return;
+    }
+    match self.codec.decode(&bytes) {
+      Ok(None) => { /* wait for remaining message chunks */ },
+      Ok(Some(SessionInput::Auth(token))) => {
+        self.authenticate_rate_limited(token, bytes.len(), ctx)
+      },
+      Ok(Some(SessionInput::Message {
+        object_id,
+        message,
+        size,
+      })) => {
+        let decision = self.rate_limiter.check(&message, size);
+        let input = WsInput {
           message,
           workspace_id: self.current_workspace,
           object_id,
           client_id: self.id(),
           sender: self.info.collab_origin(),
-        });
+        };
+        match decision {
+          RateLimitDecision::Allow => self.server.do_send(input),
+          RateLimitDecision::Delay(delay) => {
+            // stop reading from the socket until the message fits into the limits
+            tracing::trace!("session `{}` throttled for {:?}", self.id(), delay);
+            actix::clock::sleep(delay)
+              .into_actor(self)
+              .map(move |_, act, _| act.server.do_send(input))
+              .wait(ctx);
+          },
+          RateLimitDecision::Drop => {
+            tracing::trace!("session `{}` awareness update dropped", self.id());
+          },
+          RateLimitDecision::Disconnect => {
+            tracing::warn!("session `{}` exceeded rate limits", self.id());
+            Self::close(ctx, CloseCode::Again, "rate limit exceeded");
+          },
+        }
       },
       Err(err) => {
         tracing::warn!("client {} send invalid message: {}", self.id(), err);
-        ctx.close(Some(CloseReason::from((
-          CloseCode::Invalid,
-          err.to_string(),
-        ))));
+        ctx.close(Some(CloseReason::from((err.code, err.reason))));
       },
     }
   }
-}
-
-impl Actor for WsSession {
-  type Context = ws::WebsocketContext<Self>;
 
-  fn started(&mut self, ctx: &mut Self::Context) {
-    tracing::trace!("starting session `{}`", self.id());
-    self.hb(ctx);
+  /// Authenticates the session once the access token fits into the rate limits.
+  fn authenticate_rate_limited(
+    &mut self,
+    token: String,
+    size: usize,
+    ctx: &mut ws::WebsocketContext<Self>,
+  ) {
+    match self.rate_limiter.check_auth(size) {
+      RateLimitDecision::Allow => self.authenticate(token, ctx),
+      RateLimitDecision::Delay(delay) => {
+        tracing::trace!("session `{}` throttled for {:?}", self.id(), delay);
+        actix::clock::sleep(delay)
+          .into_actor(self)
+          .map(move |_, act, ctx| act.authenticate(token, ctx))
+          .wait(ctx);
+      },
+      RateLimitDecision::Drop | RateLimitDecision::Disconnect => {
+        tracing::warn!("session `{}` exceeded rate limits", self.id());
+        Self::close(ctx, CloseCode::Again, "rate limit exceeded");
+      },
+    }
+  }
 
-    let recipient = ctx.address().recipient();
-    if let Some(mut external_source) = self.extra_message_rx.take() {
-      actix::spawn(async move {
-        while let Some(message) = external_source.recv().await {
-          let output = WsOutput { message };
-          let _ = recipient.send(output).await;
+  /// Verifies the access token sent by the client. Unauthenticated sessions join the workspace
+  /// once verification succeeds, while authenticated ones only extend their expiration time.
+  /// No other messages are processed until verification completes.
+  fn authenticate(&mut self, token: String, ctx: &mut ws::WebsocketContext<Self>) {
+    if !self.auth_attempts.try_record() {
+      tracing::warn!("session `{}` sent too many access tokens", self.id());
+      Self::close(ctx, CloseCode::Policy, "too many authentication attempts");
+      return;
+    }
+    let authenticator = self.authenticator.clone();
+    async move { authenticator.authenticate(&token).await }
+      .into_actor(self)
+      .then(|res, act, ctx| {
+        match res {
+          Ok(user) if !act.is_authenticated() => {
+            tracing::trace!("session `{}` authenticated as user {}", act.id(), user.uid);
+            act.info.user_id = user.uid;
+            act.auth = SessionAuth::Authenticated(user);
+            act.join(user, ctx);
+          },
+          Ok(user) if user.uid == act.info.user_id => {
+            tracing::trace!("session `{}` refreshed access token", act.id());
+            act.auth = SessionAuth::Authenticated(user);
+            act.schedule_token_expiry(user.expires_at, ctx);
+          },
+          Ok(user) => {
+            tracing::warn!(
+              "session `{}` of user {} tried to refresh token of user {}",
+              act.id(),
+              act.info.user_id,
+              user.uid
+            );
+            Self::close(
+              ctx,
+              CloseCode::Policy,
+              "access token belongs to another user",
+            );
+          },
+          Err(err) => {
+            tracing::debug!("session `{}` failed to authenticate: {}", act.id(), err);
+            Self::close(ctx, CloseCode::Policy, "invalid access token");
+          },
         }
+        fut::ready(())
+      })
+      .wait(ctx);
+  }
+
+  fn schedule_token_expiry(
+    &mut self,
+    expires_at: Option<i64>,
+    ctx: &mut ws::WebsocketContext<Self>,
+  ) {
+    self
+      .token_expiry
+      .schedule::<Self, _>(expires_at, ctx, |act, ctx| {
+        tracing::debug!("session `{}` access token expired", act.id());
+        Self::close(ctx, CloseCode::Policy, "access token expired");
       });
-    } else {
-      error!("extra_message_sender only take once");
-    }
+  }
+
+  fn close(ctx: &mut ws::WebsocketContext<Self>, code: CloseCode, reason: &str) {
+    ctx.close(Some(CloseReason::from((code, reason))));
+    ctx.stop();
+  }
+
+  /// Joins the workspace on behalf of authenticated user.
+  fn join(&mut self, user: AuthenticatedUser, ctx: &mut ws::WebsocketContext<Self>) {
+    self.schedule_token_expiry(user.expires_at, ctx);
+
+    let recipient = ctx.address().recipient();
+    let mut external_source = self.authenticator.subscribe_user_messages(user.uid);
+    actix::spawn(async move {
+      while let Some(message) = external_source.recv().await {
+        let output = WsOutput { message };
+        let _ = recipient.send(output).await;
+      }
+    });
 
     let join = Join {
       uid: self.info.user_id,
       session_id: self.id(),
       collab_origin: self.info.collab_origin(),
-      addr: ctx.address(),
+      addr: ctx.address().recipient(),
       last_message_id: self.info.last_message_id.map(MessageId::into),
       workspace_id: self.current_workspace,
     };
@@ -175,13 +326,36 @@ impl Actor for WsSession {
       })
       .wait(ctx);
   }
+}
+
+impl Actor for WsSession {
+  type Context = ws::WebsocketContext<Self>;
+
+  fn started(&mut self, ctx: &mut Self::Context) {
+    tracing::trace!("starting session `{}`", self.id());
+    self.hb(ctx);
+
+    match self.auth {
+      SessionAuth::Authenticated(user) => self.join(user, ctx),
+      SessionAuth::Pending => {
+        ctx.run_later(AUTH_TIMEOUT, |act, ctx| {
+          if !act.is_authenticated() {
+            tracing::debug!("session `{}` didn't authenticate in time", act.id());
+            Self::close(ctx, CloseCode::Policy, "authentication timeout");
+          }
+        });
+      },
+    }
+  }
 
   fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
     tracing::trace!("stopping session `{}`", self.id());
-    self.server.do_send(Leave {
-      session_id: self.id(),
-      workspace_id: self.current_workspace,
-    });
+    if self.is_authenticated() {
+      self.server.do_send(Leave {
+        session_id: self.id(),
+        workspace_id: self.current_workspace,
+      });
+    }
     Running::Stop
   }
 }
@@ -195,8 +369,10 @@ impl Handler<WsOutput> for WsSession {
       self.id(),
       msg.message
     );
-    if let Ok(bytes) = msg.message.into_bytes() {
-      ctx.binary(bytes);
+    if let Ok(frames) = self.codec.encode(msg.message) {
+      for frame in frames {
+        ctx.binary(frame);
+      }
     };
   }
 }
@@ -232,6 +408,9 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                 CloseCode::Unsupported,
                 "only binary content is supported",
               )))),
+              Item::FirstBinary(_) if !self.is_authenticated() => {
+                Self::close(ctx, CloseCode::Policy, "session is not authenticated")
+              },
               Item::FirstBinary(bytes) => self.buf = Some(bytes.into()),
               Item::Continue(bytes) => {
                 if let Some(ref mut buf) = self.buf {
@@ -272,6 +451,164 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
   }
 }
 
+/// Transport-agnostic part of the client session protocol: decodes messages received from the
+/// client and encodes messages sent to it, taking care of message chunking.
+pub struct SessionCodec {
+  /// Reassembles messages sent by the client in chunks.
+  chunks: ChunkAssembler,
+  /// Held while a chunked message of the client is being reassembled.
+  chunks_permit: Option<PendingChunksPermit>,
+  /// Client is able to receive messages split into [MessageChunk](appflowy_proto::MessageChunk)s.
+  supports_chunking: bool,
+  /// Identifier assigned to the next message, that has to be chunked before sending.
+  next_chunked_message_id: u64,
+}
+
+impl SessionCodec {
+  pub fn new(supports_chunking: bool) -> Self {
+    SessionCodec {
+      chunks: ChunkAssembler::new(max_chunked_client_message_size()),
+      chunks_permit: None,
+      supports_chunking,
+      next_chunked_message_id: 0,
+    }
+  }
+
+  /// Decodes a message received from the client. Chunked messages are reassembled first,
+  /// in which case `None` is returned until the last chunk arrives.
+  pub fn decode(&mut self, bytes: &[u8]) -> Result<Option<SessionInput>, InvalidInput> {
+    let mut size = bytes.len();
+    let message = match ClientMessage::from_bytes(bytes)? {
+      ClientMessage::Chunk(chunk) => {
+        tracing::trace!("received message chunk: {:?}", chunk);
+        if self.chunks_permit.is_none() {
+          self.chunks_permit = Some(PendingChunksPermit::acquire().ok_or_else(|| {
+            InvalidInput::new(CloseCode::Again, "too many chunked messages in flight")
+          })?);
+        }
+        let result = self.chunks.push(chunk);
+        if !self.chunks.is_pending() {
+          self.chunks_permit = None;
+        }
+        let bytes = match result {
+          Ok(Some(bytes)) => bytes,
+          Ok(None) => return Ok(None),
+          Err(err) => return Err(InvalidInput::new(CloseCode::Protocol, err.to_string())),
+        };
+        size = bytes.len();
+        match ClientMessage::from_bytes(&bytes)? {
+          ClientMessage::Chunk(_) => {
+            return Err(InvalidInput::new(
+              CloseCode::Protocol,
+              "nested message chunks are not supported",
+            ))
+          },
+          message => message,
+        }
+      },
+      message => message,
+    };
+    tracing::trace!("received message: {:#?}", message);
+    if let ClientMessage::Auth { token } = message {
+      return Ok(Some(SessionInput::Auth(token)));
+    }
+    let object_id = *message
+      .object_id()
+      .ok_or_else(|| InvalidInput::new(CloseCode::Invalid, "message is missing object id"))?;
+    let message =
+      InputMessage::try_from(message).map_err(|err| InvalidInput::new(CloseCode::Invalid, err))?;
+    Ok(Some(SessionInput::Message {
+      object_id,
+      message,
+      size,
+    }))
+  }
+
+  /// Decodes the first message of the client, which has connected without an access token.
+  /// Only the [ClientMessage::Auth] sent in one piece is accepted.
+  pub fn decode_auth(bytes: &[u8]) -> Result<String, InvalidInput> {
+    match ClientMessage::from_bytes(bytes)? {
+      ClientMessage::Auth { token } => Ok(token),
+      _ => Err(InvalidInput::new(
+        CloseCode::Policy,
+        "session is not authenticated",
+      )),
+    }
+  }
+
+  /// Encodes a message sent to the client into one or more binary frames.
+  pub fn encode(&mut self, message: ServerMessage) -> Result<Vec<Vec<u8>>, appflowy_proto::Error> {
+    let bytes = message.into_bytes()?;
+    if self.supports_chunking {
+      self.next_chunked_message_id = self.next_chunked_message_id.wrapping_add(1);
+      Ok(into_frames(self.next_chunked_message_id, bytes))
+    } else {
+      Ok(vec![bytes])
+    }
+  }
+}
+
+/// Slot of [MAX_PENDING_CHUNKED_MESSAGES], released on drop.
+struct PendingChunksPermit;
+
+impl PendingChunksPermit {
+  fn acquire() -> Option<Self> {
+    PENDING_CHUNKED_MESSAGES
+      .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+        (pending < MAX_PENDING_CHUNKED_MESSAGES).then_some(pending + 1)
+      })
+      .ok()
+      .map(|_| PendingChunksPermit)
+  }
+}
+
+impl Drop for PendingChunksPermit {
+  fn drop(&mut self) {
+    PENDING_CHUNKED_MESSAGES.fetch_sub(1, Ordering::AcqRel);
+  }
+}
+
+/// Message received from the client, decoded by [SessionCodec].
+pub enum SessionInput {
+  /// Collab message, which should be forwarded to the workspace.
+  Message {
+    object_id: ObjectId,
+    message: InputMessage,
+    /// Size of the encoded message in bytes (after reassembling the chunks).
+    size: usize,
+  },
+  /// Access token sent by the client to authenticate the session or to refresh it.
+  Auth(String),
+}
+
+/// Message received from the client was malformed or violated the protocol.
+#[derive(Debug)]
+pub struct InvalidInput {
+  pub code: CloseCode,
+  pub reason: String,
+}
+
+impl InvalidInput {
+  pub fn new<S: Into<String>>(code: CloseCode, reason: S) -> Self {
+    InvalidInput {
+      code,
+      reason: reason.into(),
+    }
+  }
+}
+
+impl Display for InvalidInput {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{:?}: {}", self.code, self.reason)
+  }
+}
+
+impl From<appflowy_proto::Error> for InvalidInput {
+  fn from(err: appflowy_proto::Error) -> Self {
+    InvalidInput::new(CloseCode::Invalid, err.to_string())
+  }
+}
+
 #[derive(actix::Message)]
 #[rtype(result = "()")]
 pub struct WsInput {
@@ -320,6 +657,8 @@ impl TryFrom<ClientMessage> for InputMessage {
         let awareness = AwarenessUpdate::decode_v1(&awareness).map_err(|err| err.to_string())?;
         Ok(InputMessage::AwarenessUpdate(awareness))
       },
+      ClientMessage::Chunk(_) => Err("message chunk must be reassembled first".to_string()),
+      ClientMessage::Auth { .. } => Err("auth message is not a collab message".to_string()),
     }
   }
 }
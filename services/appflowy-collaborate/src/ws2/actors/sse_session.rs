@@ -0,0 +1,540 @@
+use super::auth::{AuthAttempts, AuthenticatedUser, SessionAuthenticatorRef, TokenExpiry};
+use super::server::{Join, Leave, WsOutput, WsServer};
+use super::session::{InvalidInput, SessionCodec, SessionInfo, SessionInput, WsInput, HEARTBEAT};
+use crate::ws2::{RateLimitDecision, SessionRateLimiter};
+use actix::{
+  fut, Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, ContextFutureSpawner, Handler,
+  Running, WrapFuture,
+};
+use actix_http::ws::CloseCode;
+use app_error::AppError;
+use appflowy_proto::WorkspaceId;
+use bytes::Bytes;
+use dashmap::DashMap;
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncCommands, Client, RedisError, Script, Value};
+use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+use yrs::block::ClientID;
+
+/// Frame send to the client through the Server-Sent Events stream.
+#[derive(Debug)]
+pub enum SseFrame {
+  /// Encoded server message.
+  Message(Vec<u8>),
+  /// Keep-alive frame, used to detect disconnected clients.
+  Ping,
+}
+
+pub type SseFrameReceiver = Receiver<SseFrame>;
+
+/// Maximum number of frames buffered for a single event stream. Clients which don't keep up with
+/// it are disconnected and resume from their last message id after reconnecting.
+const SSE_OUTBOX_CAPACITY: usize = 1024;
+
+/// Session registry entries expire unless refreshed by the heartbeat of the session.
+const SSE_SESSION_TTL_SECS: u64 = 3 * HEARTBEAT.as_secs();
+
+/// Registry of active HTTP fallback sessions. Since client messages are delivered with separate
+/// POST requests, it's used to find the session they should be routed to. Requests which reach
+/// a node other than the one holding the event stream are relayed through the Redis pub/sub
+/// channel of that node.
+#[derive(Clone)]
+pub struct SseSessions {
+  sessions: Arc<DashMap<(WorkspaceId, ClientID), Addr<SseSession>>>,
+  conn: MultiplexedConnection,
+  /// Identifies this node in the registry entries of its sessions.
+  node_id: Uuid,
+}
+
+impl SseSessions {
+  pub async fn new(client: &Client) -> Result<Self, RedisError> {
+    let sessions: Arc<DashMap<(WorkspaceId, ClientID), Addr<SseSession>>> =
+      Arc::new(DashMap::new());
+    let node_id = Uuid::new_v4();
+    let mut pub_sub = client.get_async_pubsub().await?;
+    pub_sub.subscribe(relay_channel(&node_id)).await?;
+    let conn = client.get_multiplexed_async_connection().await?;
+
+    let weak_sessions = Arc::downgrade(&sessions);
+    tokio::spawn(async move {
+      let mut stream = pub_sub.into_on_message();
+      while let Some(message) = stream.next().await {
+        let Some(sessions) = weak_sessions.upgrade() else {
+          return; // dropped registry
+        };
+        match parse_relayed_input(message.get_payload_bytes()) {
+          Some((key, input)) => {
+            // the session could have been closed in the meantime
+            if let Some(session) = sessions.get(&key) {
+              session.do_send(input);
+            }
+          },
+          None => tracing::warn!(
+            "failed to parse relayed http fallback message: {}",
+            message.get_channel_name()
+          ),
+        }
+      }
+    });
+    Ok(SseSessions {
+      sessions,
+      conn,
+      node_id,
+    })
+  }
+
+  /// Delivers client message to the session it belongs to, whichever node it's connected to.
+  pub async fn deliver(
+    &self,
+    workspace_id: WorkspaceId,
+    client_id: ClientID,
+    input: SseInput,
+  ) -> Result<(), AppError> {
+    if let Some(session) = self.get(&workspace_id, client_id) {
+      return session
+        .send(input)
+        .await
+        .map_err(|err| AppError::Internal(err.into()))?
+        .map_err(|err| AppError::InvalidRequest(err.to_string()));
+    }
+
+    let mut conn = self.conn.clone();
+    let entry: Option<String> = conn
+      .get(registry_key(&workspace_id, client_id))
+      .await
+      .map_err(|err| AppError::Internal(err.into()))?;
+    let node_id = entry
+      .as_deref()
+      .and_then(registry_entry_node_id)
+      .ok_or_else(|| {
+        AppError::RecordNotFound(format!(
+          "session {} not found in workspace {}",
+          client_id, workspace_id
+        ))
+      })?;
+    let payload = relayed_input_payload(&workspace_id, client_id, &input);
+    let _: Value = conn
+      .publish(relay_channel(&node_id), payload)
+      .await
+      .map_err(|err| AppError::Internal(err.into()))?;
+    Ok(())
+  }
+
+  pub fn get(&self, workspace_id: &WorkspaceId, client_id: ClientID) -> Option<Addr<SseSession>> {
+    self
+      .sessions
+      .get(&(*workspace_id, client_id))
+      .map(|entry| entry.value().clone())
+  }
+
+  fn insert(&self, workspace_id: WorkspaceId, client_id: ClientID, addr: Addr<SseSession>) {
+    self.sessions.insert((workspace_id, client_id), addr);
+  }
+
+  fn remove(&self, workspace_id: WorkspaceId, client_id: ClientID, addr: &Addr<SseSession>) {
+    // session could have been already replaced by the reconnected client
+    self
+      .sessions
+      .remove_if(&(workspace_id, client_id), |_, current| current == addr);
+  }
+
+  /// Marks the session as connected to this node, so that other nodes relay its messages here.
+  fn register(&self, workspace_id: WorkspaceId, client_id: ClientID, token: Uuid) {
+    let mut conn = self.conn.clone();
+    let entry = registry_entry(&self.node_id, &token);
+    actix::spawn(async move {
+      let key = registry_key(&workspace_id, client_id);
+      let res: Result<Value, _> = conn.set_ex(key, entry, SSE_SESSION_TTL_SECS).await;
+      if let Err(err) = res {
+        tracing::warn!("failed to register http fallback session: {}", err);
+      }
+    });
+  }
+
+  fn unregister(&self, workspace_id: WorkspaceId, client_id: ClientID, token: Uuid) {
+    let mut conn = self.conn.clone();
+    let entry = registry_entry(&self.node_id, &token);
+    actix::spawn(async move {
+      // the client could have already reconnected to another node
+      let res: Result<Value, _> = Script::new(
+        "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('DEL', KEYS[1]) end return 0",
+      )
+      .key(registry_key(&workspace_id, client_id))
+      .arg(entry)
+      .invoke_async(&mut conn)
+      .await;
+      if let Err(err) = res {
+        tracing::warn!("failed to unregister http fallback session: {}", err);
+      }
+    });
+  }
+}
+
+fn registry_key(workspace_id: &WorkspaceId, client_id: ClientID) -> String {
+  format!("af:sse-session:{workspace_id}:{client_id}")
+}
+
+/// Registry entry of a session: the node holding its event stream, and the token of the session.
+fn registry_entry(node_id: &Uuid, token: &Uuid) -> String {
+  format!("{node_id}:{token}")
+}
+
+fn registry_entry_node_id(entry: &str) -> Option<Uuid> {
+  entry.split(':').next()?.parse().ok()
+}
+
+fn relay_channel(node_id: &Uuid) -> String {
+  format!("af:sse:{node_id}")
+}
+
+/// Encodes the client message relayed by [SseSessions::deliver]: the workspace and client id of
+/// the session, the user id and the client message.
+fn relayed_input_payload(
+  workspace_id: &WorkspaceId,
+  client_id: ClientID,
+  input: &SseInput,
+) -> Vec<u8> {
+  let mut payload = Vec::with_capacity(32 + input.bytes.len());
+  payload.extend_from_slice(workspace_id.as_bytes());
+  payload.extend_from_slice(&client_id.to_be_bytes());
+  payload.extend_from_slice(&input.uid.to_be_bytes());
+  payload.extend_from_slice(&input.bytes);
+  payload
+}
+
+/// Parses the message encoded by [relayed_input_payload].
+fn parse_relayed_input(payload: &[u8]) -> Option<((WorkspaceId, ClientID), SseInput)> {
+  if payload.len() < 32 {
+    return None;
+  }
+  let (workspace_id, rest) = payload.split_at(16);
+  let (client_id, rest) = rest.split_at(8);
+  let (uid, bytes) = rest.split_at(8);
+  let workspace_id = WorkspaceId::from_slice(workspace_id).ok()?;
+  let client_id = ClientID::from_be_bytes(client_id.try_into().ok()?);
+  let input = SseInput {
+    uid: i64::from_be_bytes(uid.try_into().ok()?),
+    bytes: Bytes::copy_from_slice(bytes),
+  };
+  Some(((workspace_id, client_id), input))
+}
+
+/// HTTP fallback counterpart of [WsSession](super::WsSession), used by clients which are not able
+/// to establish WebSocket connection. Server messages are streamed to the client as Server-Sent
+/// Events, while client messages arrive as [SseInput] sent by POST request handler.
+pub struct SseSession {
+  current_workspace: WorkspaceId,
+  info: SessionInfo,
+  server: Addr<WsServer>,
+  sessions: SseSessions,
+  /// Identifies this session in the registry shared with the other nodes.
+  registry_token: Uuid,
+  codec: SessionCodec,
+  outbox: Sender<SseFrame>,
+  authenticator: SessionAuthenticatorRef,
+  user: AuthenticatedUser,
+  auth_attempts: AuthAttempts,
+  token_expiry: TokenExpiry,
+  rate_limiter: SessionRateLimiter,
+}
+
+impl SseSession {
+  pub fn new(
+    workspace: WorkspaceId,
+    info: SessionInfo,
+    server: Addr<WsServer>,
+    sessions: SseSessions,
+    authenticator: SessionAuthenticatorRef,
+    user: AuthenticatedUser,
+    rate_limiter: SessionRateLimiter,
+  ) -> (Self, SseFrameReceiver) {
+    let (outbox, frames) = tokio::sync::mpsc::channel(SSE_OUTBOX_CAPACITY);
+    let session = SseSession {
+      codec: SessionCodec::new(info.supports_chunking),
+      current_workspace: workspace,
+      info,
+      server,
+      sessions,
+      registry_token: Uuid::new_v4(),
+      outbox,
+      authenticator,
+      user,
+      auth_attempts: AuthAttempts::default(),
+      token_expiry: TokenExpiry::default(),
+      rate_limiter,
+    };
+    (session, frames)
+  }
+
+  /// Unique identifier of current session.
+  fn id(&self) -> ClientID {
+    self.info.client_id
+  }
+
+  fn hb(&self, ctx: &mut actix::Context<Self>) {
+    ctx.run_interval(HEARTBEAT, |act, ctx| {
+      // once the client disconnects, the receiving end of the event stream is dropped, while
+      // a full outbox already guarantees that the client will be pinged
+      if let Err(TrySendError::Closed(_)) = act.outbox.try_send(SseFrame::Ping) {
+        tracing::trace!("session `{}` event stream closed", act.id());
+        ctx.stop();
+        return;
+      }
+      act
+        .sessions
+        .register(act.current_workspace, act.id(), act.registry_token);
+    });
+  }
+
+  fn schedule_token_expiry(&mut self, expires_at: Option<i64>, ctx: &mut actix::Context<Self>) {
+    self
+      .token_expiry
+      .schedule::<Self, _>(expires_at, ctx, |act, ctx| {
+        // closing the event stream makes the client reconnect with a new token
+        tracing::debug!("session `{}` access token expired", act.id());
+        ctx.stop();
+      });
+  }
+
+  /// Verifies the access token sent by the client to refresh the session.
+  fn authenticate(&mut self, token: String, ctx: &mut actix::Context<Self>) {
+    if !self.auth_attempts.try_record() {
+      tracing::warn!("session `{}` sent too many access tokens", self.id());
+      ctx.stop();
+      return;
+    }
+    let authenticator = self.authenticator.clone();
+    async move { authenticator.authenticate(&token).await }
+      .into_actor(self)
+      .then(|res, act, ctx| {
+        match res {
+          Ok(user) if user.uid == act.user.uid => {
+            tracing::trace!("session `{}` refreshed access token", act.id());
+            act.user = user;
+            act.schedule_token_expiry(user.expires_at, ctx);
+          },
+          Ok(_) => {
+            tracing::warn!("session `{}` received token of another user", act.id());
+            ctx.stop();
+          },
+          Err(err) => {
+            tracing::debug!("session `{}` failed to refresh token: {}", act.id(), err);
+            ctx.stop();
+          },
+        }
+        fut::ready(())
+      })
+      .wait(ctx);
+  }
+}
+
+impl Actor for SseSession {
+  type Context = actix::Context<Self>;
+
+  fn started(&mut self, ctx: &mut Self::Context) {
+    tracing::trace!("starting http fallback session `{}`", self.id());
+    self
+      .sessions
+      .insert(self.current_workspace, self.id(), ctx.address());
+    self
+      .sessions
+      .register(self.current_workspace, self.id(), self.registry_token);
+    self.hb(ctx);
+    self.schedule_token_expiry(self.user.expires_at, ctx);
+
+    let recipient = ctx.address().recipient();
+    let mut external_source = self.authenticator.subscribe_user_messages(self.user.uid);
+    actix::spawn(async move {
+      while let Some(message) = external_source.recv().await {
+        let output = WsOutput { message };
+        let _ = recipient.send(output).await;
+      }
+    });
+
+    let join = Join {
+      uid: self.info.user_id,
+      session_id: self.id(),
+      collab_origin: self.info.collab_origin(),
+      addr: ctx.address().recipient(),
+      last_message_id: self.info.last_message_id.map(Into::into),
+      workspace_id: self.current_workspace,
+    };
+    self
+      .server
+      .send(join)
+      .into_actor(self)
+      .then(|res, act, ctx| {
+        if let Err(err) = res {
+          tracing::warn!("session `{}` can't join: {}", act.id(), err);
+          ctx.stop();
+        }
+        fut::ready(())
+      })
+      .wait(ctx);
+  }
+
+  fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+    tracing::trace!("stopping http fallback session `{}`", self.id());
+    self
+      .sessions
+      .remove(self.current_workspace, self.id(), &ctx.address());
+    self
+      .sessions
+      .unregister(self.current_workspace, self.id(), self.registry_token);
+    self.server.do_send(Leave {
+      session_id: self.id(),
+      workspace_id: self.current_workspace,
+    });
+    Running::Stop
+  }
+}
+
+impl Handler<WsOutput> for SseSession {
+  type Result = ();
+
+  fn handle(&mut self, msg: WsOutput, ctx: &mut Self::Context) {
+    tracing::trace!(
+      "sending message through session `{}`: {:#?}",
+      self.id(),
+      msg.message
+    );
+    if let Ok(frames) = self.codec.encode(msg.message) {
+      for frame in frames {
+        match self.outbox.try_send(SseFrame::Message(frame)) {
+          Ok(()) => {},
+          Err(TrySendError::Full(_)) => {
+            tracing::warn!("session `{}` event stream is not keeping up", self.id());
+            ctx.stop();
+            return;
+          },
+          Err(TrySendError::Closed(_)) => {
+            tracing::trace!("session `{}` event stream closed", self.id());
+            ctx.stop();
+            return;
+          },
+        }
+      }
+    }
+  }
+}
+
+/// Binary client message delivered through HTTP fallback transport.
+#[derive(actix::Message)]
+#[rtype(result = "Result<(), InvalidInput>")]
+pub struct SseInput {
+  /// User, who has sent the message.
+  pub uid: i64,
+  pub bytes: Bytes,
+}
+
+impl Handler<SseInput> for SseSession {
+  type Result = Result<(), InvalidInput>;
+
+  fn handle(&mut self, msg: SseInput, ctx: &mut Self::Context) -> Self::Result {
+    if msg.uid != self.user.uid {
+      return Err(InvalidInput::new(
+        CloseCode::Policy,
+        "session belongs to another user",
+      ));
+    }
+    match self.codec.decode(&msg.bytes) {
+      Ok(None) => Ok(()), // wait for remaining message chunks
+      Ok(Some(SessionInput::Auth(token))) => match self.rate_limiter.check_auth(msg.bytes.len()) {
+        RateLimitDecision::Allow => {
+          self.authenticate(token, ctx);
+          Ok(())
+        },
+        RateLimitDecision::Delay(delay) => {
+          actix::clock::sleep(delay)
+            .into_actor(self)
+            .map(move |_, act, ctx| act.authenticate(token, ctx))
+            .wait(ctx);
+          Ok(())
+        },
+        RateLimitDecision::Drop | RateLimitDecision::Disconnect => {
+          tracing::warn!("session `{}` exceeded rate limits", self.id());
+          ctx.stop();
+          Err(InvalidInput::new(CloseCode::Again, "rate limit exceeded"))
+        },
+      },
+      Ok(Some(SessionInput::Message {
+        object_id,
+        message,
+        size,
+      })) => {
+        let decision = self.rate_limiter.check(&message, size);
+        let input = WsInput {
+          message,
+          workspace_id: self.current_workspace,
+          object_id,
+          client_id: self.id(),
+          sender: self.info.collab_origin(),
+        };
+        match decision {
+          RateLimitDecision::Allow => self.server.do_send(input),
+          RateLimitDecision::Delay(delay) => {
+            // response to the POST request is delayed as well, slowing down the client
+            actix::clock::sleep(delay)
+              .into_actor(self)
+              .map(move |_, act, _| act.server.do_send(input))
+              .wait(ctx);
+          },
+          RateLimitDecision::Drop => {
+            tracing::trace!("session `{}` awareness update dropped", self.id());
+          },
+          RateLimitDecision::Disconnect => {
+            tracing::warn!("session `{}` exceeded rate limits", self.id());
+            ctx.stop();
+            return Err(InvalidInput::new(CloseCode::Again, "rate limit exceeded"));
+          },
+        }
+        Ok(())
+      },
+      Err(err) => {
+        tracing::warn!("client {} send invalid message: {}", self.id(), err);
+        ctx.stop();
+        Err(err)
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{
+    parse_relayed_input, registry_entry, registry_entry_node_id, relayed_input_payload, SseInput,
+  };
+  use appflowy_proto::WorkspaceId;
+  use bytes::Bytes;
+  use uuid::Uuid;
+
+  #[test]
+  fn relayed_input_round_trip() {
+    let workspace_id = WorkspaceId::new_v4();
+    let input = SseInput {
+      uid: 7,
+      bytes: Bytes::from_static(b"message"),
+    };
+    let payload = relayed_input_payload(&workspace_id, 42, &input);
+
+    let ((parsed_workspace, client_id), input) = parse_relayed_input(&payload).unwrap();
+    assert_eq!(parsed_workspace, workspace_id);
+    assert_eq!(client_id, 42);
+    assert_eq!(input.uid, 7);
+    assert_eq!(&input.bytes[..], b"message");
+
+    assert!(parse_relayed_input(&payload[..20]).is_none());
+  }
+
+  #[test]
+  fn registry_entry_identifies_node() {
+    let node_id = Uuid::new_v4();
+    let entry = registry_entry(&node_id, &Uuid::new_v4());
+    assert_eq!(registry_entry_node_id(&entry), Some(node_id));
+    assert_eq!(registry_entry_node_id("not a node"), None);
+  }
+}
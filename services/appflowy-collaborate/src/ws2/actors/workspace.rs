@@ -1,5 +1,5 @@
 use super::server::{Join, Leave, WsOutput};
-use super::session::{InputMessage, WsInput, WsSession};
+use super::session::{InputMessage, WsInput};
 use crate::collab::collab_manager::CollabManager;
 use crate::collab::snapshot_scheduler::SnapshotScheduler;
 use crate::ws2::{
@@ -7,16 +7,20 @@ use crate::ws2::{
 };
 use actix::ActorFutureExt;
 use actix::{
-  fut, Actor, ActorContext, Addr, AsyncContext, AtomicResponse, Handler, Recipient,
-  ResponseActFuture, Running, SpawnHandle, StreamHandler, WrapFuture,
+  fut, Actor, ActorContext, AsyncContext, AtomicResponse, Handler, Recipient, ResponseActFuture,
+  Running, SpawnHandle, StreamHandler, WrapFuture,
 };
 use anyhow::anyhow;
 use app_error::AppError;
-use appflowy_proto::{AccessChangedReason, ObjectId, Rid, ServerMessage, UpdateFlags, WorkspaceId};
-use chrono::DateTime;
+use appflowy_proto::{
+  AccessChangedReason, ObjectId, Rid, ServerMessage, UpdateFlags, WorkspaceId,
+  WorkspaceNotification,
+};
+use chrono::{DateTime, Utc};
 use collab::core::origin::CollabOrigin;
 use collab::entity::EncoderVersion;
 use collab_entity::CollabType;
+use collab_stream::awareness_gossip::PRESENCE_TTL;
 use collab_stream::model::{AwarenessStreamUpdate, UpdateStreamMessage};
 use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
@@ -35,16 +39,20 @@ pub struct Workspace {
   manager: Arc<CollabManager>,
   snapshot_scheduler: SnapshotScheduler,
   sessions_by_client_id: HashMap<ClientID, WorkspaceSessionHandle>,
+  /// Last activity timestamps (in milliseconds) of user devices present over the workspace collabs.
+  presence: HashMap<(ObjectId, i64, String), i64>,
   updates_handle: Option<SpawnHandle>,
   awareness_handle: Option<SpawnHandle>,
   snapshot_handle: Option<SpawnHandle>,
   termination_handle: Option<SpawnHandle>,
   permission_cache_cleanup_handle: Option<SpawnHandle>,
+  presence_cleanup_handle: Option<SpawnHandle>,
 }
 
 impl Workspace {
   pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
   pub const PERMISSION_CACHE_CLEANUP_INTERVAL: Duration = Duration::from_secs(180); // 3 minutes
+  pub const PRESENCE_CLEANUP_INTERVAL: Duration = Duration::from_secs(30);
   pub const PUBLISH_COLLAB_LIMIT: usize = 500;
 
   pub fn new(
@@ -60,11 +68,13 @@ impl Workspace {
       snapshot_scheduler,
       last_message_id: Rid::default(),
       sessions_by_client_id: HashMap::new(),
+      presence: HashMap::new(),
       updates_handle: None,
       awareness_handle: None,
       snapshot_handle: None,
       termination_handle: None,
       permission_cache_cleanup_handle: None,
+      presence_cleanup_handle: None,
     }
   }
 
@@ -94,6 +104,26 @@ impl Workspace {
   async fn hande_ws_input(store: Arc<CollabManager>, sender: WorkspaceSessionHandle, msg: WsInput) {
     match msg.message {
       InputMessage::Manifest(collab_type, rid, state_vector) => {
+        if !sender
+          .can_read_row_collab(&store, &msg.object_id, &collab_type)
+          .await
+        {
+          tracing::trace!(
+            "user {} lack of permission to read row collab {}",
+            sender.uid,
+            msg.object_id,
+          );
+          sender.conn.do_send(WsOutput {
+            message: ServerMessage::AccessChanges {
+              object_id: msg.object_id,
+              collab_type,
+              can_read: false,
+              can_write: false,
+              reason: AccessChangedReason::PermissionDenied,
+            },
+          });
+          return;
+        }
         match store
           .get_latest_state(
             msg.workspace_id,
@@ -189,6 +219,17 @@ impl Workspace {
           );
           return;
         }
+        if !sender
+          .can_read_row_collab(&store, &msg.object_id, &collab_type)
+          .await
+        {
+          tracing::trace!(
+            "user {} lack of permission to write to row collab {}",
+            sender.uid,
+            msg.object_id,
+          );
+          return;
+        }
 
         if let Err(err) = store
           .publish_update(
@@ -225,7 +266,7 @@ impl Workspace {
     client_id: ClientID,
     workspace_id: WorkspaceId,
     last_message_id: Rid,
-    reply_to: Addr<WsSession>,
+    reply_to: Recipient<WsOutput>,
     limit: usize,
   ) -> Result<(), AppError> {
     let since =
@@ -258,6 +299,12 @@ impl Workspace {
           );
           continue;
         };
+        if !session_handle
+          .can_read_row_collab(&store, &collab.object_id, &collab.collab_type)
+          .await
+        {
+          continue;
+        }
 
         // [0,0] is an empty Yrs document update encoded in v1 encoding
         if !collab.encoded_collab.doc_state.is_empty() && *collab.encoded_collab.doc_state != [0, 0]
@@ -309,6 +356,12 @@ impl Workspace {
         );
         continue;
       };
+      if !session_handle
+        .can_read_row_collab(&store, &update.object_id, &update.collab_type)
+        .await
+      {
+        continue;
+      }
       reply_to.do_send(WsOutput {
         message: ServerMessage::Update {
           object_id: update.object_id,
@@ -323,6 +376,68 @@ impl Workspace {
     Ok(())
   }
 
+  /// Tracks the presence of awareness update sender and returns a notification if the sender
+  /// device has just started or stopped being active over a given collab.
+  fn update_presence(
+    &mut self,
+    object_id: ObjectId,
+    update: &AwarenessStreamUpdate,
+  ) -> Option<WorkspaceNotification> {
+    let client = match &update.sender {
+      CollabOrigin::Client(client) => client,
+      _ => return None,
+    };
+    let key = (object_id, client.uid, client.device_id.clone());
+    let now = Utc::now().timestamp_millis();
+    if update.is_leave() {
+      let last_active_at = self.presence.remove(&key)?;
+      Some(WorkspaceNotification::CollabPresenceChanged {
+        object_id,
+        uid: client.uid,
+        device_id: client.device_id.clone(),
+        active: false,
+        last_active_at,
+      })
+    } else if self.presence.insert(key, now).is_none() {
+      Some(WorkspaceNotification::CollabPresenceChanged {
+        object_id,
+        uid: client.uid,
+        device_id: client.device_id.clone(),
+        active: true,
+        last_active_at: now,
+      })
+    } else {
+      None
+    }
+  }
+
+  /// Sends presence notification to all sessions, which are allowed to read a given collab.
+  fn broadcast_presence(
+    &self,
+    object_id: ObjectId,
+    notification: WorkspaceNotification,
+    ctx: &mut actix::Context<Self>,
+  ) {
+    let store = self.manager.clone();
+    let sessions: Vec<WorkspaceSessionHandle> =
+      self.sessions_by_client_id.values().cloned().collect();
+    ctx.spawn(
+      async move {
+        for session in sessions {
+          if session.can_read_collab(&store, &object_id).await.is_ok() {
+            session.conn.do_send(WsOutput {
+              message: ServerMessage::Notification {
+                notification: notification.clone(),
+              },
+            });
+          }
+        }
+      }
+      .into_actor(self)
+      .map(|_, _, _| ()),
+    );
+  }
+
   /// Schedules a termination signal for the workspace in 1min.
   /// If there was a previous termination handle, it will be canceled.
   fn schedule_terminate(&mut self, ctx: &mut actix::Context<Self>) {
@@ -360,6 +475,8 @@ impl Actor for Workspace {
       CleanupPermissionCaches,
       Self::PERMISSION_CACHE_CLEANUP_INTERVAL,
     ));
+    self.presence_cleanup_handle =
+      Some(ctx.notify_later(CleanupPresence, Self::PRESENCE_CLEANUP_INTERVAL));
   }
 
   fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
@@ -387,6 +504,9 @@ impl Actor for Workspace {
     if let Some(handle) = self.permission_cache_cleanup_handle.take() {
       ctx.cancel_future(handle);
     }
+    if let Some(handle) = self.presence_cleanup_handle.take() {
+      ctx.cancel_future(handle);
+    }
     Running::Stop
   }
 }
@@ -434,7 +554,11 @@ impl StreamHandler<anyhow::Result<UpdateStreamMessage>> for Workspace {
           let store = Arc::clone(&store);
           let update = update.clone();
           async move {
-            if session.can_read_collab(&store, &object_id).await.is_ok() {
+            if session.can_read_collab(&store, &object_id).await.is_ok()
+              && session
+                .can_read_row_collab(&store, &object_id, &collab_type)
+                .await
+            {
               session.conn.do_send(WsOutput {
                 message: ServerMessage::Update {
                   object_id,
@@ -465,13 +589,16 @@ impl StreamHandler<(ObjectId, Arc<AwarenessStreamUpdate>)> for Workspace {
   fn handle(
     &mut self,
     (object_id, msg): (ObjectId, Arc<AwarenessStreamUpdate>),
-    _: &mut Self::Context,
+    ctx: &mut Self::Context,
   ) {
     tracing::trace!(
       "received awareness update for {}/{}",
       self.workspace_id,
       object_id
     );
+    if let Some(notification) = self.update_presence(object_id, &msg) {
+      self.broadcast_presence(object_id, notification, ctx);
+    }
     for (session_id, sender) in self.sessions_by_client_id.iter() {
       if sender.collab_origin == msg.sender {
         continue; // skip the sender
@@ -661,6 +788,35 @@ impl Handler<CleanupPermissionCaches> for Workspace {
   }
 }
 
+impl Handler<CleanupPresence> for Workspace {
+  type Result = ();
+
+  fn handle(&mut self, _: CleanupPresence, ctx: &mut Self::Context) -> Self::Result {
+    self.presence_cleanup_handle =
+      Some(ctx.notify_later(CleanupPresence, Self::PRESENCE_CLEANUP_INTERVAL));
+
+    // devices which didn't leave gracefully (i.e. connection dropped) are removed after TTL
+    let min_active_at = Utc::now().timestamp_millis() - PRESENCE_TTL.as_millis() as i64;
+    let expired: Vec<_> = self
+      .presence
+      .iter()
+      .filter(|(_, last_active_at)| **last_active_at < min_active_at)
+      .map(|(key, last_active_at)| (key.clone(), *last_active_at))
+      .collect();
+    for ((object_id, uid, device_id), last_active_at) in expired {
+      self.presence.remove(&(object_id, uid, device_id.clone()));
+      let notification = WorkspaceNotification::CollabPresenceChanged {
+        object_id,
+        uid,
+        device_id,
+        active: false,
+        last_active_at,
+      };
+      self.broadcast_presence(object_id, notification, ctx);
+    }
+  }
+}
+
 #[derive(actix::Message)]
 #[rtype(result = "()")]
 pub struct Terminate {
@@ -675,6 +831,10 @@ struct Snapshot;
 #[rtype(result = "()")]
 struct CleanupPermissionCaches;
 
+#[derive(actix::Message)]
+#[rtype(result = "()")]
+struct CleanupPresence;
+
 impl Handler<UpdateUserPermissions> for Workspace {
   type Result = ();
 
@@ -777,9 +937,11 @@ struct WorkspaceSessionHandle {
   uid: i64,
   workspace_id: WorkspaceId,
   collab_origin: CollabOrigin,
-  conn: Addr<WsSession>,
+  conn: Recipient<WsOutput>,
   // Permission cache with expiration
   permission_cache: Arc<RwLock<HashMap<ObjectId, (PermissionType, Instant)>>>,
+  // Row level access of the database rows and their documents, cached with the same expiration
+  row_access_cache: Arc<RwLock<HashMap<ObjectId, (bool, Instant)>>>,
   cache_ttl: Duration,
 }
 
@@ -788,7 +950,7 @@ impl WorkspaceSessionHandle {
     uid: i64,
     workspace_id: WorkspaceId,
     collab_origin: CollabOrigin,
-    conn: Addr<WsSession>,
+    conn: Recipient<WsOutput>,
   ) -> Self {
     Self::new_with_cache_ttl(
       uid,
@@ -803,7 +965,7 @@ impl WorkspaceSessionHandle {
     uid: i64,
     workspace_id: WorkspaceId,
     collab_origin: CollabOrigin,
-    conn: Addr<WsSession>,
+    conn: Recipient<WsOutput>,
     cache_ttl: Duration,
   ) -> Self {
     Self {
@@ -812,6 +974,7 @@ impl WorkspaceSessionHandle {
       collab_origin,
       conn,
       permission_cache: Arc::new(RwLock::new(HashMap::new())),
+      row_access_cache: Arc::new(RwLock::new(HashMap::new())),
       cache_ttl,
     }
   }
@@ -883,6 +1046,43 @@ impl WorkspaceSessionHandle {
     Ok(has_permission)
   }
 
+  /// Checks the row level rule of the database for the database rows and the documents of the
+  /// rows. Other collabs are only subject to [Self::can_read_collab].
+  async fn can_read_row_collab(
+    &self,
+    store: &Arc<CollabManager>,
+    object_id: &ObjectId,
+    collab_type: &CollabType,
+  ) -> bool {
+    if !matches!(collab_type, CollabType::DatabaseRow | CollabType::Document) {
+      return true;
+    }
+    let now = Instant::now();
+    if let Some((can_read, cached_at)) = self.row_access_cache.read().await.get(object_id) {
+      if now.duration_since(*cached_at) < self.cache_ttl {
+        return *can_read;
+      }
+    }
+
+    let can_read = match store
+      .enforce_read_row_collab(&self.workspace_id, &self.uid, object_id, collab_type)
+      .await
+    {
+      Ok(()) => true,
+      Err(AppError::NotEnoughPermissions) => false,
+      Err(err) => {
+        tracing::warn!("failed to check row access of {}: {}", object_id, err);
+        false
+      },
+    };
+    self
+      .row_access_cache
+      .write()
+      .await
+      .insert(*object_id, (can_read, now));
+    can_read
+  }
+
   /// Apply permission updates and send WebSocket notification
   async fn apply_permission_updates(&self, updates: Vec<PermissionUpdate>) {
     let mut cache = self.permission_cache.write().await;
@@ -909,6 +1109,7 @@ impl WorkspaceSessionHandle {
   async fn clear_permission_cache(&self) {
     let mut cache = self.permission_cache.write().await;
     cache.clear();
+    self.row_access_cache.write().await.clear();
   }
 
   /// Remove expired entries from cache
@@ -916,6 +1117,8 @@ impl WorkspaceSessionHandle {
     let now = Instant::now();
     let mut cache = self.permission_cache.write().await;
     cache.retain(|_, (_, cached_at)| now.duration_since(*cached_at) < self.cache_ttl);
+    let mut row_access_cache = self.row_access_cache.write().await;
+    row_access_cache.retain(|_, (_, cached_at)| now.duration_since(*cached_at) < self.cache_ttl);
   }
 }
 
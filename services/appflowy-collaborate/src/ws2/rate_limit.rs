@@ -0,0 +1,334 @@
+use crate::metrics::CollabMetrics;
+use crate::ws2::InputMessage;
+use appflowy_proto::WorkspaceId;
+use dashmap::DashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// Sustained rate and burst size of a single token bucket.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+  /// Number of tokens added to the bucket every second.
+  pub per_second: u64,
+  /// Maximum number of tokens the bucket can hold.
+  pub burst: u64,
+}
+
+impl RateLimit {
+  pub fn new(per_second: u64, burst: u64) -> Self {
+    Self { per_second, burst }
+  }
+}
+
+/// Limits applied to a single scope (session or workspace).
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimits {
+  /// Number of collab updates (and sync requests).
+  pub updates: RateLimit,
+  /// Number of awareness updates.
+  pub awareness: RateLimit,
+  /// Total size of messages in bytes.
+  pub bytes: RateLimit,
+}
+
+/// What happens with the session, which sends updates faster than allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitAction {
+  /// Stop reading messages from the session until enough tokens are available. Sessions
+  /// which would have to wait longer than [RateLimitConfig::max_delay] are disconnected.
+  Throttle,
+  /// Disconnect the session right away.
+  Disconnect,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+  pub enabled: bool,
+  pub session: RateLimits,
+  pub workspace: RateLimits,
+  pub action: RateLimitAction,
+  pub max_delay: Duration,
+}
+
+impl Default for RateLimitConfig {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      session: RateLimits {
+        updates: RateLimit::new(50, 500),
+        awareness: RateLimit::new(20, 100),
+        bytes: RateLimit::new(1024 * 1024, 10 * 1024 * 1024),
+      },
+      workspace: RateLimits {
+        updates: RateLimit::new(500, 5000),
+        awareness: RateLimit::new(200, 1000),
+        bytes: RateLimit::new(10 * 1024 * 1024, 100 * 1024 * 1024),
+      },
+      action: RateLimitAction::Throttle,
+      max_delay: Duration::from_secs(5),
+    }
+  }
+}
+
+/// Outcome of checking a client message against the rate limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+  Allow,
+  /// Message can be processed after given delay. Session should not process any other
+  /// messages until then.
+  Delay(Duration),
+  /// Message should be dropped. Only used for awareness updates, which are ephemeral and
+  /// superseded by the following ones anyway.
+  Drop,
+  Disconnect,
+}
+
+/// Classic token bucket. Requests bigger than the bucket capacity are allowed once the bucket
+/// is full, leaving it in debt, so that big messages are never blocked forever.
+#[derive(Debug)]
+struct TokenBucket {
+  limit: RateLimit,
+  tokens: f64,
+  last_refill: Instant,
+}
+
+impl TokenBucket {
+  fn new(limit: RateLimit, now: Instant) -> Self {
+    Self {
+      limit,
+      tokens: limit.burst as f64,
+      last_refill: now,
+    }
+  }
+
+  fn refill(&mut self, now: Instant) {
+    let elapsed = now
+      .saturating_duration_since(self.last_refill)
+      .as_secs_f64();
+    self.tokens =
+      (self.tokens + elapsed * self.limit.per_second as f64).min(self.limit.burst as f64);
+    self.last_refill = now;
+  }
+
+  /// Returns time left until `n` tokens are available, or zero if they are available already.
+  fn wait_time(&mut self, n: u64, now: Instant) -> Duration {
+    self.refill(now);
+    let required = n.min(self.limit.burst) as f64;
+    if self.tokens >= required {
+      Duration::ZERO
+    } else if self.limit.per_second == 0 {
+      Duration::MAX
+    } else {
+      Duration::from_secs_f64((required - self.tokens) / self.limit.per_second as f64)
+    }
+  }
+
+  /// Takes `n` tokens out of the bucket. It should only be called after [Self::wait_time]
+  /// returned zero, or the returned time has passed.
+  fn consume(&mut self, n: u64) {
+    self.tokens -= n as f64;
+  }
+}
+
+#[derive(Debug)]
+struct Buckets {
+  updates: TokenBucket,
+  awareness: TokenBucket,
+  bytes: TokenBucket,
+}
+
+impl Buckets {
+  fn new(limits: &RateLimits, now: Instant) -> Self {
+    Self {
+      updates: TokenBucket::new(limits.updates, now),
+      awareness: TokenBucket::new(limits.awareness, now),
+      bytes: TokenBucket::new(limits.bytes, now),
+    }
+  }
+
+  fn messages(&mut self, awareness: bool) -> &mut TokenBucket {
+    if awareness {
+      &mut self.awareness
+    } else {
+      &mut self.updates
+    }
+  }
+}
+
+/// Registry of rate limiters shared by all sessions, which keeps track of per-workspace limits.
+#[derive(Clone)]
+pub struct RateLimiters {
+  config: Arc<RateLimitConfig>,
+  workspaces: Arc<DashMap<WorkspaceId, Weak<Mutex<Buckets>>>>,
+  metrics: Arc<CollabMetrics>,
+}
+
+impl RateLimiters {
+  pub fn new(config: RateLimitConfig, metrics: Arc<CollabMetrics>) -> Self {
+    Self {
+      config: Arc::new(config),
+      workspaces: Arc::new(DashMap::new()),
+      metrics,
+    }
+  }
+
+  /// Creates a rate limiter for a new session connected to a given workspace.
+  pub fn session_limiter(&self, workspace_id: WorkspaceId) -> SessionRateLimiter {
+    let now = Instant::now();
+    let workspace = {
+      let mut entry = self.workspaces.entry(workspace_id).or_default();
+      match entry.upgrade() {
+        Some(buckets) => buckets,
+        None => {
+          let buckets = Arc::new(Mutex::new(Buckets::new(&self.config.workspace, now)));
+          *entry = Arc::downgrade(&buckets);
+          buckets
+        },
+      }
+    };
+    SessionRateLimiter {
+      workspace_id,
+      session: Buckets::new(&self.config.session, now),
+      workspace,
+      registry: self.clone(),
+    }
+  }
+}
+
+/// Rate limiter of a single session. It checks client messages against both session and
+/// workspace limits.
+pub struct SessionRateLimiter {
+  workspace_id: WorkspaceId,
+  session: Buckets,
+  workspace: Arc<Mutex<Buckets>>,
+  registry: RateLimiters,
+}
+
+impl SessionRateLimiter {
+  pub fn check(&mut self, message: &InputMessage, size: usize) -> RateLimitDecision {
+    let awareness = matches!(message, InputMessage::AwarenessUpdate(_));
+    self.check_input(awareness, size)
+  }
+
+  /// Checks the access token sent by the client. Tokens are accounted for as collab updates,
+  /// since each one of them has to be verified.
+  pub fn check_auth(&mut self, size: usize) -> RateLimitDecision {
+    self.check_input(false, size)
+  }
+
+  fn check_input(&mut self, awareness: bool, size: usize) -> RateLimitDecision {
+    let config = &self.registry.config;
+    if !config.enabled {
+      return RateLimitDecision::Allow;
+    }
+    let now = Instant::now();
+    let size = size as u64;
+    let mut workspace = self.workspace.lock().unwrap_or_else(|err| err.into_inner());
+    let wait = [
+      self.session.messages(awareness).wait_time(1, now),
+      self.session.bytes.wait_time(size, now),
+      workspace.messages(awareness).wait_time(1, now),
+      workspace.bytes.wait_time(size, now),
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or_default();
+
+    let decision = if wait.is_zero() {
+      RateLimitDecision::Allow
+    } else if config.action == RateLimitAction::Disconnect {
+      RateLimitDecision::Disconnect
+    } else if awareness {
+      RateLimitDecision::Drop
+    } else if wait > config.max_delay {
+      RateLimitDecision::Disconnect
+    } else {
+      RateLimitDecision::Delay(wait)
+    };
+
+    let metrics = &self.registry.metrics;
+    match decision {
+      RateLimitDecision::Allow | RateLimitDecision::Delay(_) => {
+        // delayed messages are processed once tokens become available, so they can be
+        // accounted for upfront
+        self.session.messages(awareness).consume(1);
+        self.session.bytes.consume(size);
+        workspace.messages(awareness).consume(1);
+        workspace.bytes.consume(size);
+        if let RateLimitDecision::Delay(wait) = decision {
+          metrics.rate_limit_throttled.inc();
+          metrics.observe_rate_limit_delay(wait);
+        }
+      },
+      RateLimitDecision::Drop => {
+        metrics.rate_limit_dropped.inc();
+      },
+      RateLimitDecision::Disconnect => {
+        metrics.rate_limit_disconnects.inc();
+      },
+    }
+    decision
+  }
+}
+
+impl Drop for SessionRateLimiter {
+  fn drop(&mut self) {
+    // remove workspace limits, once there are no more sessions using them
+    self
+      .registry
+      .workspaces
+      .remove_if(&self.workspace_id, |_, buckets| buckets.strong_count() <= 1);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn bucket_allows_burst_and_refills() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(RateLimit::new(10, 5), now);
+    for _ in 0..5 {
+      assert_eq!(bucket.wait_time(1, now), Duration::ZERO);
+      bucket.consume(1);
+    }
+    assert_eq!(bucket.wait_time(1, now), Duration::from_millis(100));
+
+    let later = now + Duration::from_millis(100);
+    assert_eq!(bucket.wait_time(1, later), Duration::ZERO);
+  }
+
+  #[test]
+  fn oversized_request_goes_into_debt() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(RateLimit::new(10, 10), now);
+    assert_eq!(bucket.wait_time(100, now), Duration::ZERO);
+    bucket.consume(100);
+    // 90 tokens of debt + 1 token required
+    let wait = bucket.wait_time(1, now);
+    assert!(wait > Duration::from_secs(9) && wait <= Duration::from_millis(9100));
+  }
+
+  #[test]
+  fn refill_does_not_exceed_burst() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(RateLimit::new(10, 5), now);
+    let later = now + Duration::from_secs(60);
+    assert_eq!(bucket.wait_time(5, later), Duration::ZERO);
+    bucket.consume(5);
+    assert!(bucket.wait_time(1, later) > Duration::ZERO);
+  }
+
+  #[test]
+  fn auth_messages_use_update_limits() {
+    let mut config = RateLimitConfig::default();
+    config.session.updates = RateLimit::new(1, 2);
+    config.action = RateLimitAction::Disconnect;
+    let limiters = RateLimiters::new(config, Arc::new(CollabMetrics::default()));
+    let mut limiter = limiters.session_limiter(WorkspaceId::new_v4());
+    assert_eq!(limiter.check_auth(100), RateLimitDecision::Allow);
+    assert_eq!(limiter.check_auth(100), RateLimitDecision::Allow);
+    assert_eq!(limiter.check_auth(100), RateLimitDecision::Disconnect);
+  }
+}
@@ -14,14 +14,23 @@ use axum::Router;
 
 use crate::mailer::AFWorkerMailer;
 use crate::metric::ImportMetrics;
+use appflowy_collaborate::collab::cache::CollabCache;
+use appflowy_collaborate::CollabMetrics;
+use appflowy_worker::blob_gc_worker::{run_blob_gc_worker, BlobGcConfig};
 use appflowy_worker::indexer_worker::{run_background_indexer, BackgroundIndexerConfig};
+use appflowy_worker::row_template_worker::{
+  run_row_template_scheduler, RowTemplateSchedulerConfig,
+};
+use appflowy_worker::trash_worker::{run_trash_purge_worker, TrashPurgeConfig};
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::routing::get;
+use database::file::s3_client_impl::AwsS3BucketClientImpl;
 use indexer::metrics::EmbeddingMetrics;
 use indexer::vector::embedder::get_open_ai_config;
 use infra::env_util::get_env_var;
+use infra::thread_pool::ThreadPoolNoAbortBuilder;
 use mailer::sender::Mailer;
 use secrecy::ExposeSecret;
 use std::sync::{Arc, Once};
@@ -140,6 +149,79 @@ pub async fn create_app(listener: TcpListener, config: Config) -> Result<(), Err
     indexer_config,
   ));
 
+  let bucket_client = AwsS3BucketClientImpl::new(
+    state.s3_client.inner.clone(),
+    config.s3_setting.bucket.clone(),
+    config.s3_setting.minio_url.clone(),
+    None,
+  );
+  let thread_pool = Arc::new(
+    ThreadPoolNoAbortBuilder::new()
+      .thread_name(|idx| format!("af-worker-collab-{}", idx))
+      .num_threads(2)
+      .build()
+      .expect("Failed to create collab thread pool"),
+  );
+  let collab_cache = CollabCache::new(
+    thread_pool,
+    state.redis_client.clone(),
+    state.pg_pool.clone(),
+    bucket_client.clone(),
+    Arc::new(CollabMetrics::default()),
+    get_env_var("APPFLOWY_COLLAB_S3_THRESHOLD", "8000")
+      .parse::<usize>()
+      .unwrap_or(8000),
+  );
+  let trash_purge_config = TrashPurgeConfig {
+    enable: get_env_var("APPFLOWY_WORKER_TRASH_PURGE_ENABLED", "true")
+      .parse::<bool>()
+      .unwrap_or(true),
+    tick_interval_secs: get_env_var("APPFLOWY_WORKER_TRASH_PURGE_INTERVAL_SECS", "3600")
+      .parse::<u64>()
+      .unwrap_or(3600),
+  };
+  tokio::spawn(run_trash_purge_worker(
+    state.pg_pool.clone(),
+    state.redis_client.clone(),
+    collab_cache.clone(),
+    bucket_client.clone(),
+    trash_purge_config,
+  ));
+
+  let blob_gc_config = BlobGcConfig {
+    enable: get_env_var("APPFLOWY_WORKER_BLOB_GC_ENABLED", "true")
+      .parse::<bool>()
+      .unwrap_or(true),
+    tick_interval_secs: get_env_var("APPFLOWY_WORKER_BLOB_GC_INTERVAL_SECS", "3600")
+      .parse::<u64>()
+      .unwrap_or(3600),
+    grace_period_secs: get_env_var("APPFLOWY_WORKER_BLOB_GC_GRACE_PERIOD_SECS", "604800")
+      .parse::<u64>()
+      .unwrap_or(604800),
+    workspaces_per_tick: get_env_var("APPFLOWY_WORKER_BLOB_GC_WORKSPACES_PER_TICK", "50")
+      .parse::<i64>()
+      .unwrap_or(50),
+  };
+  tokio::spawn(run_blob_gc_worker(
+    state.pg_pool.clone(),
+    collab_cache,
+    bucket_client,
+    blob_gc_config,
+  ));
+
+  let row_template_config = RowTemplateSchedulerConfig {
+    enable: get_env_var("APPFLOWY_WORKER_ROW_TEMPLATE_ENABLED", "true")
+      .parse::<bool>()
+      .unwrap_or(true),
+    tick_interval_secs: get_env_var("APPFLOWY_WORKER_ROW_TEMPLATE_INTERVAL_SECS", "60")
+      .parse::<u64>()
+      .unwrap_or(60),
+  };
+  tokio::spawn(run_row_template_scheduler(
+    state.pg_pool.clone(),
+    row_template_config,
+  ));
+
   let app = Router::new()
     .route("/metrics", get(metrics_handler))
     .with_state(Arc::new(state));
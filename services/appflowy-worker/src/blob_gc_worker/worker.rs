@@ -0,0 +1,296 @@
+use app_error::AppError;
+use appflowy_collaborate::collab::cache::mem_cache::MillisSeconds;
+use appflowy_collaborate::collab::cache::CollabCache;
+use chrono::Utc;
+use collab::core::collab::{default_client_id, CollabOptions, DataSource};
+use collab::core::origin::CollabOrigin;
+use collab::entity::{EncodedCollab, EncoderVersion};
+use collab::preclude::Collab;
+use collab_entity::CollabType;
+use database::blob_gc::{
+  delete_blob_metadata_bulk, select_blob_referencing_collabs, select_collectable_blob_ids,
+  select_expired_orphaned_blob_ids, select_file_names_referenced_by_history,
+  select_workspace_ids_with_blobs, update_orphaned_blob_marks,
+};
+use database::file::s3_client_impl::AwsS3BucketClientImpl;
+use database::file::BucketClient;
+use database_entity::dto::{QueryCollab, QueryCollabResult};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info, trace};
+use uuid::Uuid;
+
+/// Number of collabs loaded at once when looking for blob references.
+const COLLAB_BATCH_SIZE: usize = 50;
+/// Maximum number of objects deleted by a single S3 request.
+const DELETE_BATCH_SIZE: usize = 1000;
+
+pub struct BlobGcConfig {
+  pub enable: bool,
+  pub tick_interval_secs: u64,
+  /// How long a blob must stay unreferenced before it is deleted. Blobs modified within this
+  /// period are never considered unreferenced.
+  pub grace_period_secs: u64,
+  /// Number of workspaces scanned per tick.
+  pub workspaces_per_tick: i64,
+}
+
+/// Periodically deletes the blobs that are no longer referenced by the documents, databases and
+/// folder of their workspace, nor by their history or published data. Unreferenced blobs are
+/// first marked, and only deleted if they are still unreferenced once the grace period is over.
+pub async fn run_blob_gc_worker(
+  pg_pool: PgPool,
+  collab_cache: Arc<CollabCache>,
+  bucket_client: AwsS3BucketClientImpl,
+  config: BlobGcConfig,
+) {
+  if !config.enable {
+    info!("Blob garbage collection is disabled");
+    return;
+  }
+
+  info!("Starting blob garbage collection worker...");
+  let grace_period = chrono::Duration::seconds(config.grace_period_secs as i64);
+  let mut last_workspace_id = None;
+  let mut interval = interval(Duration::from_secs(config.tick_interval_secs));
+  interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+  loop {
+    interval.tick().await;
+    let workspace_ids = match select_workspace_ids_with_blobs(
+      &pg_pool,
+      last_workspace_id,
+      config.workspaces_per_tick,
+    )
+    .await
+    {
+      Ok(workspace_ids) => workspace_ids,
+      Err(err) => {
+        error!("[Blob GC] Failed to select workspaces: {}", err);
+        continue;
+      },
+    };
+    // Start again from the first workspace once all of them have been scanned
+    last_workspace_id = workspace_ids.last().copied();
+
+    for workspace_id in workspace_ids {
+      match collect_workspace_blobs(
+        &pg_pool,
+        &collab_cache,
+        &bucket_client,
+        workspace_id,
+        grace_period,
+      )
+      .await
+      {
+        Ok(0) => {},
+        Ok(count) => info!(
+          "[Blob GC] deleted {} unreferenced blobs of workspace {}",
+          count, workspace_id
+        ),
+        Err(err) => error!(
+          "[Blob GC] Failed to collect the blobs of workspace {}: {}",
+          workspace_id, err
+        ),
+      }
+    }
+  }
+}
+
+async fn collect_workspace_blobs(
+  pg_pool: &PgPool,
+  collab_cache: &CollabCache,
+  bucket_client: &AwsS3BucketClientImpl,
+  workspace_id: Uuid,
+  grace_period: chrono::Duration,
+) -> Result<usize, AppError> {
+  let now = Utc::now();
+  let candidates = select_collectable_blob_ids(pg_pool, &workspace_id, now - grace_period).await?;
+  let mut unreferenced = if candidates.is_empty() {
+    vec![]
+  } else {
+    let referenced = get_referenced_file_names(pg_pool, collab_cache, workspace_id).await?;
+    candidates
+      .into_iter()
+      .filter(|file_id| !is_blob_referenced(file_id, &referenced))
+      .collect::<Vec<_>>()
+  };
+  if !unreferenced.is_empty() {
+    // Blobs removed from the live collabs can still be restored from their history, or be served
+    // by a published view
+    let file_names = unreferenced
+      .iter()
+      .map(|file_id| blob_file_name(file_id).to_string())
+      .collect::<Vec<_>>();
+    let referenced: HashSet<String> =
+      select_file_names_referenced_by_history(pg_pool, &workspace_id, &file_names)
+        .await?
+        .into_iter()
+        .collect();
+    unreferenced.retain(|file_id| !is_blob_referenced(file_id, &referenced));
+  }
+  update_orphaned_blob_marks(pg_pool, &workspace_id, &unreferenced, now + grace_period).await?;
+
+  let expired = select_expired_orphaned_blob_ids(pg_pool, &workspace_id).await?;
+  for file_ids in expired.chunks(DELETE_BATCH_SIZE) {
+    let object_keys = file_ids
+      .iter()
+      .map(|file_id| blob_object_key(&workspace_id, file_id))
+      .collect::<Vec<_>>();
+    bucket_client.delete_blobs(object_keys).await?;
+    delete_blob_metadata_bulk(pg_pool, &workspace_id, file_ids).await?;
+  }
+  Ok(expired.len())
+}
+
+/// Returns the names of all the files referenced by the documents, databases and folder of the
+/// workspace. Fails if any of them cannot be loaded, so that blobs are never deleted based on a
+/// partial view of the workspace.
+async fn get_referenced_file_names(
+  pg_pool: &PgPool,
+  collab_cache: &CollabCache,
+  workspace_id: Uuid,
+) -> Result<HashSet<String>, AppError> {
+  let collabs = select_blob_referencing_collabs(pg_pool, &workspace_id).await?;
+  let mut file_names = HashSet::new();
+  for chunk in collabs.chunks(COLLAB_BATCH_SIZE) {
+    let queries = chunk
+      .iter()
+      .map(|(oid, partition_key)| {
+        // The snapshots may not contain the latest updates published by the server instances
+        collab_cache.mark_as_dirty(*oid, MillisSeconds::now());
+        QueryCollab::new(*oid, collab_type_from_partition_key(*partition_key))
+      })
+      .collect::<Vec<_>>();
+    let query_count = queries.len();
+    let results = collab_cache
+      .batch_get_full_collab(&workspace_id, queries, None, EncoderVersion::V1)
+      .await;
+    if results.len() != query_count {
+      return Err(AppError::Internal(anyhow::anyhow!(
+        "Only {} of {} collabs could be loaded",
+        results.len(),
+        query_count
+      )));
+    }
+    for (oid, result) in results {
+      match result {
+        QueryCollabResult::Success { encode_collab_v1 } => {
+          let json = collab_json(&oid, &encode_collab_v1)?;
+          file_names.extend(extract_blob_file_names(&json));
+        },
+        QueryCollabResult::Failed { error } => {
+          return Err(AppError::Internal(anyhow::anyhow!(
+            "Failed to load collab {}: {}",
+            oid,
+            error
+          )));
+        },
+      }
+    }
+  }
+  trace!(
+    "[Blob GC] workspace {} references {} files",
+    workspace_id,
+    file_names.len()
+  );
+  Ok(file_names)
+}
+
+fn collab_type_from_partition_key(partition_key: i32) -> CollabType {
+  match partition_key {
+    1 => CollabType::Database,
+    3 => CollabType::Folder,
+    4 => CollabType::DatabaseRow,
+    _ => CollabType::Document,
+  }
+}
+
+fn collab_json(oid: &Uuid, encode_collab_v1: &[u8]) -> Result<String, AppError> {
+  let encoded_collab = EncodedCollab::decode_from_bytes(encode_collab_v1)
+    .map_err(|err| AppError::Internal(err.into()))?;
+  let options = CollabOptions::new(oid.to_string(), default_client_id())
+    .with_data_source(DataSource::DocStateV1(encoded_collab.doc_state.to_vec()));
+  let collab = Collab::new_with_options(CollabOrigin::Server, options)
+    .map_err(|err| AppError::Internal(err.into()))?;
+  Ok(collab.to_json_value().to_string())
+}
+
+/// Extracts the file names of the blob urls found in the text, i.e. the last path segment of
+/// `.../blob/{file_id}` and `.../v1/blob/{parent_dir}/{file_id}`.
+fn extract_blob_file_names(text: &str) -> HashSet<String> {
+  const BLOB_PATH: &str = "/blob/";
+  let mut file_names = HashSet::new();
+  for (index, _) in text.match_indices(BLOB_PATH) {
+    let path = &text[index + BLOB_PATH.len()..];
+    let end = path
+      .find(|c: char| {
+        c == '"' || c == '\\' || c == '?' || c == '#' || c == ')' || c.is_whitespace()
+      })
+      .unwrap_or(path.len());
+    if let Some(file_name) = path[..end].rsplit('/').next() {
+      if !file_name.is_empty() {
+        file_names.insert(file_name.to_string());
+      }
+    }
+  }
+  file_names
+}
+
+/// The metadata key of a blob uploaded under a collab is `{parent_dir}_{file_id}`.
+fn blob_file_name(file_id: &str) -> &str {
+  match file_id.split_once('_') {
+    Some((_, file_name)) => file_name,
+    None => file_id,
+  }
+}
+
+fn is_blob_referenced(file_id: &str, referenced_file_names: &HashSet<String>) -> bool {
+  referenced_file_names.contains(blob_file_name(file_id))
+}
+
+fn blob_object_key(workspace_id: &Uuid, file_id: &str) -> String {
+  match file_id.split_once('_') {
+    Some((parent_dir, file_name)) => format!("{}/{}/{}", workspace_id, parent_dir, file_name),
+    None => format!("{}/{}", workspace_id, file_id),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn extract_blob_file_names_test() {
+    let json = r#"{"url":"https://cloud.appflowy.io/api/file_storage/6f4d3a8e-0b7d-4c1f-9a2e-1d2c3b4a5f60/v1/blob/c1e7a7f0-2a3b-4c5d-8e9f-0a1b2c3d4e5f/Hk3xQ.png","image":"http://localhost/api/file_storage/ws/blob/legacy.jpg?download=1","text":"no blob here"}"#;
+    let names = extract_blob_file_names(json);
+    assert_eq!(
+      names,
+      HashSet::from(["Hk3xQ.png".to_string(), "legacy.jpg".to_string()])
+    );
+  }
+
+  #[test]
+  fn blob_reference_test() {
+    let referenced = HashSet::from(["Hk3xQ.png".to_string()]);
+    assert!(is_blob_referenced(
+      "c1e7a7f0-2a3b-4c5d-8e9f-0a1b2c3d4e5f_Hk3xQ.png",
+      &referenced
+    ));
+    assert!(!is_blob_referenced(
+      "c1e7a7f0-2a3b-4c5d-8e9f-0a1b2c3d4e5f_other.png",
+      &referenced
+    ));
+  }
+
+  #[test]
+  fn blob_object_key_test() {
+    let workspace_id = Uuid::nil();
+    assert_eq!(
+      blob_object_key(&workspace_id, "c1e7a7f0_Hk3xQ.png"),
+      format!("{}/c1e7a7f0/Hk3xQ.png", workspace_id)
+    );
+  }
+}
@@ -1,6 +1,9 @@
+pub mod blob_gc_worker;
 pub mod error;
 pub mod import_worker;
 pub mod indexer_worker;
 mod mailer;
 pub mod metric;
+pub mod row_template_worker;
 pub mod s3_client;
+pub mod trash_worker;
@@ -0,0 +1,67 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use app_error::AppError;
+use chrono::Utc;
+use database::database_row_template::{
+  insert_database_row_template_run, select_due_database_row_templates,
+  update_database_row_template_next_run,
+};
+use database_entity::schedule::CronSchedule;
+use sqlx::PgPool;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info, trace, warn};
+
+/// Maximum number of due row templates handled per tick.
+const SCHEDULE_BATCH_SIZE: i64 = 100;
+
+pub struct RowTemplateSchedulerConfig {
+  pub enable: bool,
+  pub tick_interval_secs: u64,
+}
+
+/// Periodically queues a run for each scheduled row template that is due, and moves the
+/// template to its next run. The rows are inserted by the server, which picks up the queued
+/// runs. The runs missed while the scheduler was down are collapsed into a single run.
+pub async fn run_row_template_scheduler(pg_pool: PgPool, config: RowTemplateSchedulerConfig) {
+  if !config.enable {
+    info!("Row template scheduler is disabled");
+    return;
+  }
+
+  info!("Starting row template scheduler...");
+  let mut interval = interval(Duration::from_secs(config.tick_interval_secs));
+  interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+  loop {
+    interval.tick().await;
+    match schedule_due_row_templates(&pg_pool).await {
+      Ok(0) => trace!("[RowTemplate] no row template is due"),
+      Ok(count) => info!("[RowTemplate] queued {} row template runs", count),
+      Err(err) => error!("[RowTemplate] Failed to schedule row templates: {}", err),
+    }
+  }
+}
+
+async fn schedule_due_row_templates(pg_pool: &PgPool) -> Result<usize, AppError> {
+  let mut txn = pg_pool.begin().await?;
+  let templates = select_due_database_row_templates(&mut *txn, SCHEDULE_BATCH_SIZE).await?;
+  let now = Utc::now();
+  for template in &templates {
+    insert_database_row_template_run(&mut *txn, &template.template_id, template.next_run_at)
+      .await?;
+    let next_run_at = match template.schedule.as_deref().map(CronSchedule::from_str) {
+      Some(Ok(schedule)) => schedule.next_after(now),
+      Some(Err(err)) => {
+        warn!(
+          "[RowTemplate] Invalid schedule of row template {}: {}",
+          template.template_id, err
+        );
+        None
+      },
+      None => None,
+    };
+    update_database_row_template_next_run(&mut *txn, &template.template_id, next_run_at).await?;
+  }
+  txn.commit().await?;
+  Ok(templates.len())
+}
@@ -0,0 +1,333 @@
+use anyhow::anyhow;
+use app_error::AppError;
+use appflowy_collaborate::collab::cache::mem_cache::MillisSeconds;
+use appflowy_collaborate::collab::cache::CollabCache;
+use collab::core::collab::default_client_id;
+use collab::core::origin::CollabOrigin;
+use collab::entity::EncoderVersion;
+use collab_entity::CollabType;
+use collab_folder::{Folder, Section};
+use collab_stream::model::{UpdateFlags, UpdateStreamMessage};
+use database::file::s3_client_impl::AwsS3BucketClientImpl;
+use database::file::BucketClient;
+use database::pg_row::AFWorkspaceTrashRetentionRow;
+use database::resource_usage::delete_blob_metadata_in_dir;
+use database::trash::{delete_collab_embeddings, select_workspaces_with_trash_retention};
+use database::workspace::select_workspace_member_uids;
+use database_entity::dto::QueryCollab;
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info, trace, warn};
+use uuid::Uuid;
+
+/// Number of workspaces loaded at once.
+const WORKSPACE_BATCH_SIZE: i64 = 100;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub struct TrashPurgeConfig {
+  pub enable: bool,
+  pub tick_interval_secs: u64,
+}
+
+/// Periodically deletes the pages that have been in the trash for longer than the trash
+/// retention period of their workspace, together with their collabs, embeddings and the blobs
+/// uploaded to them.
+pub async fn run_trash_purge_worker(
+  pg_pool: PgPool,
+  mut redis_client: ConnectionManager,
+  collab_cache: Arc<CollabCache>,
+  bucket_client: AwsS3BucketClientImpl,
+  config: TrashPurgeConfig,
+) {
+  if !config.enable {
+    info!("Trash purge worker is disabled");
+    return;
+  }
+
+  info!("Starting trash purge worker...");
+  let mut interval = interval(Duration::from_secs(config.tick_interval_secs));
+  interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+  loop {
+    interval.tick().await;
+    let mut after_workspace_id = None;
+    loop {
+      let workspaces = match select_workspaces_with_trash_retention(
+        &pg_pool,
+        after_workspace_id,
+        WORKSPACE_BATCH_SIZE,
+      )
+      .await
+      {
+        Ok(workspaces) => workspaces,
+        Err(err) => {
+          error!(
+            "[Trash] Failed to select workspaces with trash retention: {}",
+            err
+          );
+          break;
+        },
+      };
+      let Some(last) = workspaces.last() else {
+        break;
+      };
+      after_workspace_id = Some(last.workspace_id);
+
+      for workspace in workspaces {
+        match purge_workspace_trash(
+          &pg_pool,
+          &mut redis_client,
+          &collab_cache,
+          &bucket_client,
+          &workspace,
+        )
+        .await
+        {
+          Ok(0) => {},
+          Ok(count) => info!(
+            "[Trash] purged {} expired pages of workspace {}",
+            count, workspace.workspace_id
+          ),
+          Err(err) => error!(
+            "[Trash] Failed to purge the trash of workspace {}: {}",
+            workspace.workspace_id, err
+          ),
+        }
+      }
+    }
+  }
+}
+
+/// Deletes the pages of the workspace that have been in the trash for longer than its retention
+/// period. The time a page was moved to the trash is the one of its trash section item.
+async fn purge_workspace_trash(
+  pg_pool: &PgPool,
+  redis_client: &mut ConnectionManager,
+  collab_cache: &CollabCache,
+  bucket_client: &AwsS3BucketClientImpl,
+  workspace: &AFWorkspaceTrashRetentionRow,
+) -> Result<usize, AppError> {
+  let workspace_id = workspace.workspace_id;
+  let trashed_before =
+    chrono::Utc::now().timestamp() - workspace.trash_retention_days * SECONDS_PER_DAY;
+  let mut folder = get_latest_folder(collab_cache, workspace_id).await?;
+  let member_uids = select_workspace_member_uids(pg_pool, &workspace_id).await?;
+  let (update, purged_view_ids) = remove_expired_views_from_folder(
+    &mut folder,
+    workspace.owner_uid,
+    &member_uids,
+    trashed_before,
+  );
+  let Some(update) = update else {
+    trace!("[Trash] no expired pages in workspace {}", workspace_id);
+    return Ok(0);
+  };
+  publish_folder_update(redis_client, workspace_id, update).await?;
+
+  for view_id in &purged_view_ids {
+    collab_cache.delete_collab(&workspace_id, view_id).await?;
+    delete_blob_metadata_in_dir(pg_pool, &workspace_id, &view_id.to_string()).await?;
+    if let Err(err) = bucket_client
+      .remove_dir(&format!("{}/{}/", workspace_id, view_id))
+      .await
+    {
+      warn!(
+        "[Trash] Failed to remove the blobs of view {}: {}",
+        view_id, err
+      );
+    }
+  }
+  delete_collab_embeddings(pg_pool, &purged_view_ids).await?;
+  Ok(purged_view_ids.len())
+}
+
+/// Returns the folder including the updates that have not been written to its snapshot yet.
+async fn get_latest_folder(
+  collab_cache: &CollabCache,
+  workspace_id: Uuid,
+) -> Result<Folder, AppError> {
+  // Updates are published by the server instances, so the dirty state tracked by this process
+  // is always stale. Force replaying the pending updates on top of the snapshot.
+  collab_cache.mark_as_dirty(workspace_id, MillisSeconds::now());
+  let encoded_collab = collab_cache
+    .get_full_collab(
+      &workspace_id,
+      QueryCollab::new(workspace_id, CollabType::Folder),
+      None,
+      EncoderVersion::V1,
+    )
+    .await?
+    .encoded_collab;
+
+  Folder::from_collab_doc_state(
+    CollabOrigin::Server,
+    encoded_collab.into(),
+    &workspace_id.to_string(),
+    default_client_id(),
+  )
+  .map_err(|err| {
+    AppError::Internal(anyhow!(
+      "Unable to decode workspace folder {}: {}",
+      workspace_id,
+      err
+    ))
+  })
+}
+
+/// Deletes the views moved to the trash before the given timestamp, in seconds, along with their
+/// descendants, and returns the folder update and the ids of the deleted views. The views are
+/// removed from the trash of every member.
+fn remove_expired_views_from_folder(
+  folder: &mut Folder,
+  owner_uid: i64,
+  member_uids: &[i64],
+  trashed_before: i64,
+) -> (Option<Vec<u8>>, Vec<Uuid>) {
+  let expired_view_ids: Vec<String> = folder
+    .get_all_trash_sections(owner_uid)
+    .into_iter()
+    .filter(|item| item.timestamp < trashed_before)
+    .map(|item| item.id)
+    .collect();
+  if expired_view_ids.is_empty() {
+    return (None, vec![]);
+  }
+
+  let mut view_ids: HashSet<String> = HashSet::new();
+  for view_id in &expired_view_ids {
+    view_ids.extend(
+      folder
+        .get_views_belong_to(view_id, owner_uid)
+        .iter()
+        .map(|view| view.id.clone()),
+    );
+    view_ids.insert(view_id.clone());
+  }
+
+  let update = {
+    let mut txn = folder.collab.transact_mut();
+    for uid in member_uids {
+      if let Some(op) = folder.body.section.section_op(&txn, Section::Trash, *uid) {
+        op.delete_section_items_with_txn(&mut txn, expired_view_ids.clone());
+      }
+    }
+    folder
+      .body
+      .views
+      .delete_views(&mut txn, view_ids.iter().cloned().collect::<Vec<_>>());
+    txn.encode_update_v1()
+  };
+
+  let view_ids = view_ids
+    .iter()
+    .flat_map(|view_id| Uuid::parse_str(view_id).ok())
+    .collect();
+  (Some(update), view_ids)
+}
+
+async fn publish_folder_update(
+  redis_client: &mut ConnectionManager,
+  workspace_id: Uuid,
+  update: Vec<u8>,
+) -> Result<(), AppError> {
+  let key = UpdateStreamMessage::stream_key(&workspace_id);
+  let _: String = UpdateStreamMessage::prepare_command(
+    &key,
+    &workspace_id,
+    CollabType::Folder,
+    &CollabOrigin::Server,
+    update,
+    UpdateFlags::default(),
+  )
+  .query_async(redis_client)
+  .await
+  .map_err(|err| AppError::Internal(err.into()))?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use collab::core::collab::CollabOptions;
+  use collab::preclude::Collab;
+  use collab_folder::hierarchy_builder::NestedChildViewBuilder;
+  use collab_folder::{FolderData, SectionItem, Workspace};
+
+  fn insert_view(folder: &mut Folder, parent_view_id: &str, uid: i64) -> String {
+    let view = NestedChildViewBuilder::new(uid, parent_view_id.to_string())
+      .with_view_id(Uuid::new_v4())
+      .build()
+      .view;
+    let view_id = view.id.clone();
+    let mut txn = folder.collab.transact_mut();
+    folder.body.views.insert(&mut txn, view, None, uid);
+    view_id
+  }
+
+  fn move_to_trash(folder: &mut Folder, view_id: &str, uid: i64, timestamp: i64) {
+    let mut txn = folder.collab.transact_mut();
+    let op = folder
+      .body
+      .section
+      .section_op(&txn, Section::Trash, uid)
+      .unwrap();
+    op.add_sections_item(
+      &mut txn,
+      vec![SectionItem {
+        id: view_id.to_string(),
+        timestamp,
+      }],
+    );
+  }
+
+  #[test]
+  fn only_views_trashed_before_the_retention_period_are_removed() {
+    let owner_uid = 1;
+    let member_uid = 2;
+    let workspace_id = Uuid::new_v4().to_string();
+    let folder_data = FolderData::new(
+      owner_uid,
+      Workspace::new(workspace_id.clone(), "Workspace".to_string(), owner_uid),
+    );
+    let options = CollabOptions::new(workspace_id.clone(), default_client_id());
+    let collab = Collab::new_with_options(CollabOrigin::Empty, options).unwrap();
+    let mut folder = Folder::create(collab, None, folder_data);
+
+    let expired_view_id = insert_view(&mut folder, &workspace_id, owner_uid);
+    let expired_child_view_id = insert_view(&mut folder, &expired_view_id, owner_uid);
+    let recent_view_id = insert_view(&mut folder, &workspace_id, member_uid);
+    let kept_view_id = insert_view(&mut folder, &workspace_id, owner_uid);
+    move_to_trash(&mut folder, &expired_view_id, member_uid, 100);
+    move_to_trash(&mut folder, &recent_view_id, member_uid, 300);
+
+    let (update, purged_view_ids) =
+      remove_expired_views_from_folder(&mut folder, owner_uid, &[owner_uid, member_uid], 200);
+    assert!(update.is_some());
+    let purged_view_ids: HashSet<String> = purged_view_ids
+      .iter()
+      .map(|view_id| view_id.to_string())
+      .collect();
+    assert_eq!(
+      purged_view_ids,
+      HashSet::from([expired_view_id.clone(), expired_child_view_id.clone()])
+    );
+    assert!(folder.get_view(&expired_view_id, owner_uid).is_none());
+    assert!(folder.get_view(&expired_child_view_id, owner_uid).is_none());
+    assert!(folder.get_view(&kept_view_id, owner_uid).is_some());
+    let trash: Vec<String> = folder
+      .get_all_trash_sections(owner_uid)
+      .into_iter()
+      .map(|item| item.id)
+      .collect();
+    assert_eq!(trash, vec![recent_view_id]);
+
+    // nothing left to purge
+    let (update, purged_view_ids) =
+      remove_expired_views_from_folder(&mut folder, owner_uid, &[owner_uid, member_uid], 200);
+    assert!(update.is_none());
+    assert!(purged_view_ids.is_empty());
+  }
+}
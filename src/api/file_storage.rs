@@ -13,8 +13,10 @@ use actix_web::{HttpResponse, Result};
 use app_error::AppError;
 
 use chrono::DateTime;
+use database::blob_gc::select_orphaned_blobs;
 use database::file::BlobKey;
 use database::resource_usage::{get_all_workspace_blob_metadata, get_workspace_usage_size};
+use database_entity::dto::AFRole;
 use database_entity::file_dto::{
   CompleteUploadRequest, CreateUploadRequest, CreateUploadResponse, UploadPartData,
   UploadPartResponse,
@@ -30,7 +32,9 @@ use collab_importer::util::FileId;
 use database::pg_row::{AFBlobSource, AFBlobStatus};
 use serde::Deserialize;
 use shared_entity::dto::file_dto::PutFileResponse;
-use shared_entity::dto::workspace_dto::{BlobMetadata, RepeatedBlobMetaData, WorkspaceSpaceUsage};
+use shared_entity::dto::workspace_dto::{
+  BlobMetadata, OrphanedBlob, OrphanedBlobReport, RepeatedBlobMetaData, WorkspaceSpaceUsage,
+};
 use shared_entity::response::{AppResponse, AppResponseError, JsonAppResponse};
 use sqlx::types::Uuid;
 use std::pin::Pin;
@@ -59,6 +63,10 @@ pub fn file_storage_scope() -> Scope {
       web::resource("/{workspace_id}/blobs")
         .route(web::get().to(get_all_workspace_blob_metadata_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/orphaned_blobs")
+        .route(web::get().to(get_orphaned_blobs_handler)),
+    )
     .service(web::resource("/{workspace_id}/create_upload").route(web::post().to(create_upload)))
     .service(
       web::resource("/{workspace_id}/upload_part/{parent_dir}/{file_id}/{upload_id}/{part_num}")
@@ -538,6 +546,41 @@ async fn get_all_workspace_blob_metadata_handler(
       .into(),
   )
 }
+
+/// Dry-run report of the blob garbage collection: lists the blobs that will be deleted by the
+/// worker if they are not referenced again.
+#[instrument(level = "debug", skip(state), err)]
+async fn get_orphaned_blobs_handler(
+  user_uuid: UserUuid,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+) -> Result<JsonAppResponse<OrphanedBlobReport>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let blobs = select_orphaned_blobs(&state.pg_pool, &workspace_id)
+    .await?
+    .into_iter()
+    .map(|row| OrphanedBlob {
+      file_id: row.file_id,
+      file_type: row.file_type,
+      file_size: row.file_size,
+      modified_at: row.modified_at,
+      marked_at: row.marked_at,
+      delete_after: row.delete_after,
+    })
+    .collect::<Vec<_>>();
+  let total_size = blobs.iter().map(|blob| blob.file_size).sum();
+  Ok(
+    AppResponse::Ok()
+      .with_data(OrphanedBlobReport { blobs, total_size })
+      .into(),
+  )
+}
+
 fn payload_to_async_read(payload: Payload) -> Pin<Box<dyn AsyncRead>> {
   let mapped =
     payload.map(|chunk| chunk.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)));
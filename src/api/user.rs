@@ -1,5 +1,6 @@
 use crate::api::util::client_version_from_headers;
 use crate::biz::authentication::jwt::{Authorization, UserUuid};
+use crate::biz::user::user_data_export::{get_user_data_export, request_user_data_export};
 use crate::biz::user::user_delete::delete_user;
 use crate::biz::user::user_info::{get_profile, get_user_workspace_info, update_user, get_user_auth_info};
 use crate::biz::user::user_verify::verify_token;
@@ -9,10 +10,11 @@ use actix_web::{web, Scope};
 use actix_web::{HttpRequest, Result};
 use database_entity::dto::{AFUserProfile, AFUserWorkspaceInfo};
 use semver::Version;
-use shared_entity::dto::auth_dto::{DeleteUserQuery, SignInTokenResponse, UpdateUserParams, UserAuthInfo};
+use shared_entity::dto::auth_dto::{DeleteUserQuery, SignInTokenResponse, UpdateUserParams, UserAuthInfo, UserDataExport};
 use shared_entity::response::AppResponseError;
 use shared_entity::response::{AppResponse, JsonAppResponse};
 use serde::Deserialize;
+use uuid::Uuid;
 
 #[derive(Deserialize, Debug)]
 pub struct AuthInfoQuery {
@@ -26,6 +28,10 @@ pub fn user_scope() -> Scope {
     .service(web::resource("/profile").route(web::get().to(get_user_profile_handler)))
     .service(web::resource("/workspace").route(web::get().to(get_user_workspace_info_handler)))
     .service(web::resource("/auth-info").route(web::get().to(get_user_auth_info_handler)))
+    .service(web::resource("/data-export").route(web::post().to(post_user_data_export_handler)))
+    .service(
+      web::resource("/data-export/{export_id}").route(web::get().to(get_user_data_export_handler)),
+    )
     .service(web::resource("").route(web::delete().to(delete_user_handler)))
 }
 
@@ -119,3 +125,25 @@ async fn delete_user_handler(
   .await?;
   Ok(AppResponse::Ok().into())
 }
+
+#[tracing::instrument(skip(state), err)]
+async fn post_user_data_export_handler(
+  user_uuid: UserUuid,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<UserDataExport>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let export = request_user_data_export(&state.pg_pool, uid).await?;
+  Ok(AppResponse::Ok().with_data(export).into())
+}
+
+#[tracing::instrument(skip(state), err)]
+async fn get_user_data_export_handler(
+  user_uuid: UserUuid,
+  path: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<UserDataExport>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let export_id = path.into_inner();
+  let export = get_user_data_export(&state.pg_pool, &state.bucket_client, uid, &export_id).await?;
+  Ok(AppResponse::Ok().with_data(export).into())
+}
@@ -18,15 +18,38 @@ use crate::biz::workspace::ops::{
   get_reactions_on_published_view, get_workspace_owner, remove_comment_on_published_view,
   remove_reaction_on_comment, update_workspace_member_profile,
 };
+use crate::biz::workspace::page_block::{
+  delete_page_block, get_page_block, insert_page_block, move_page_block, update_page_block,
+};
 use crate::biz::workspace::page_view::{
   add_recent_pages, append_block_at_the_end_of_page, create_database_view, create_folder_view,
   create_orphaned_view, create_page, create_space, delete_all_pages_from_trash, delete_trash,
-  favorite_page, get_page_view_collab, move_page, move_page_to_trash, publish_page,
-  reorder_favorite_page, restore_all_pages_from_trash, restore_page_from_trash, unpublish_page,
-  update_page, update_page_collab_data, update_page_extra, update_page_icon, update_page_name,
-  update_space,
+  favorite_page, get_page_view_collab, markdown_to_serde_blocks, move_page, move_page_to_trash,
+  publish_page, reorder_favorite_page, restore_all_pages_from_trash, restore_page_from_trash,
+  unpublish_page, update_page, update_page_collab_data, update_page_extra, update_page_icon,
+  update_page_name, update_space,
 };
+use crate::biz::workspace::presence::get_workspace_presence;
 use crate::biz::workspace::publish::get_workspace_default_publish_view_info_meta;
+use crate::biz::workspace::publish_access::{publish_access_cookie_name, unlock_published_collab};
+use crate::biz::workspace::publish_analytics::{
+  get_publish_namespace_analytics, get_published_view_analytics, PublishVisitor,
+};
+use crate::biz::workspace::publish_comment::{
+  block_published_commenter, get_published_comment_setting, list_published_comment_blocked_users,
+  list_published_comments_for_moderation, moderate_published_comment, unblock_published_commenter,
+  update_published_comment_setting,
+};
+use crate::biz::workspace::publish_domain::{
+  add_publish_domain, list_publish_domains, remove_publish_domain, resolve_publish_domain,
+  verify_publish_domain,
+};
+use crate::biz::workspace::publish_export::{
+  get_published_site_export, request_published_site_export,
+};
+use crate::biz::workspace::publish_feed::{
+  list_published_feed_pages, render_atom, render_rss, render_sitemap,
+};
 use crate::biz::workspace::quick_note::{
   create_quick_note, delete_quick_note, list_quick_notes, update_quick_note,
 };
@@ -35,6 +58,10 @@ use crate::domain::compression::{
 };
 use crate::state::AppState;
 use access_control::act::Action;
+use actix_web::cookie::time::Duration as CookieDuration;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::guard::{self, GuardContext};
+use actix_web::http::header::ContentType;
 use actix_web::web::{Bytes, Path, Payload};
 use actix_web::web::{Data, Json, PayloadConfig};
 use actix_web::{web, HttpResponse, ResponseError, Scope};
@@ -70,14 +97,15 @@ use prost::Message as ProstMessage;
 use rayon::prelude::*;
 
 use semver::Version;
-use sha2::{Digest, Sha256};
 use shared_entity::dto::billing_dto::WorkspaceUsageAndLimit;
 use shared_entity::dto::publish_dto::DuplicatePublishedPageResponse;
 use shared_entity::dto::workspace_dto::*;
 use shared_entity::response::AppResponseError;
 use shared_entity::response::{AppResponse, JsonAppResponse};
 use sqlx::types::uuid;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
+use std::net::{IpAddr, SocketAddr};
 use std::time::Instant;
 use tokio_stream::StreamExt;
 use tokio_tungstenite::tungstenite::Message;
@@ -205,7 +233,13 @@ pub fn workspace_scope() -> Scope {
       web::resource("/{workspace_id}/folder-view").route(web::post().to(post_folder_view_handler)),
     )
     .service(
-      web::resource("/{workspace_id}/page-view").route(web::post().to(post_page_view_handler)),
+      web::resource("/{workspace_id}/page-view")
+        .route(
+          web::post()
+            .guard(guard::fn_guard(is_markdown_request))
+            .to(post_markdown_page_view_handler),
+        )
+        .route(web::post().to(post_page_view_handler)),
     )
     .service(
       web::resource("/{workspace_id}/page-view/{view_id}")
@@ -242,8 +276,27 @@ pub fn workspace_scope() -> Scope {
     )
     .service(
       web::resource("/{workspace_id}/page-view/{view_id}/append-block")
+        .route(
+          web::post()
+            .guard(guard::fn_guard(is_markdown_request))
+            .to(append_markdown_to_page_handler),
+        )
         .route(web::post().to(append_block_to_page_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/page-view/{view_id}/block")
+        .route(web::post().to(insert_page_block_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/page-view/{view_id}/block/{block_id}")
+        .route(web::get().to(get_page_block_handler))
+        .route(web::patch().to(update_page_block_handler))
+        .route(web::delete().to(delete_page_block_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/page-view/{view_id}/block/{block_id}/move")
+        .route(web::post().to(move_page_block_handler)),
+    )
     .service(
       web::resource("/{workspace_id}/page-view/{view_id}/move")
         .route(web::post().to(move_page_handler)),
@@ -302,6 +355,10 @@ pub fn workspace_scope() -> Scope {
     .service(
       web::resource("/{workspace_id}/usage-and-limit").route(web::get().to(get_workspace_usage_and_limit_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/presence")
+        .route(web::get().to(get_workspace_presence_handler)),
+    )
     .service(
       web::resource("/published/{publish_namespace}")
         .route(web::get().to(get_default_published_collab_info_meta_handler)),
@@ -310,10 +367,73 @@ pub fn workspace_scope() -> Scope {
       web::resource("/v1/published/{publish_namespace}/{publish_name}")
         .route(web::get().to(get_v1_published_collab_handler)),
     )
+    .service(
+      web::resource("/v1/published/{publish_namespace}/{publish_name}/access")
+        .route(web::post().to(post_published_collab_access_handler)),
+    )
+    .service(
+      web::resource("/published/{publish_namespace}/sitemap.xml")
+        .route(web::get().to(get_published_sitemap_handler)),
+    )
+    .service(
+      web::resource("/published/{publish_namespace}/rss.xml")
+        .route(web::get().to(get_published_rss_handler)),
+    )
+    .service(
+      web::resource("/published/{publish_namespace}/atom.xml")
+        .route(web::get().to(get_published_atom_handler)),
+    )
     .service(
       web::resource("/published/{publish_namespace}/{publish_name}/blob")
         .route(web::get().to(get_published_collab_blob_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/published-comment")
+        .route(web::get().to(list_published_comments_for_moderation_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/published-comment/{comment_id}/status")
+        .route(web::put().to(put_published_comment_status_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/published-comment-setting")
+        .route(web::get().to(get_published_comment_setting_handler))
+        .route(web::put().to(put_published_comment_setting_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/published-comment-blocklist")
+        .route(web::get().to(list_published_comment_blocklist_handler))
+        .route(web::post().to(post_published_comment_blocklist_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/published-comment-blocklist/{user_uuid}")
+        .route(web::delete().to(delete_published_comment_blocklist_handler)),
+    )
+    .service(
+      web::resource("/published-domain/resolve")
+        .route(web::get().to(resolve_publish_domain_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/publish-domain")
+        .route(web::get().to(list_publish_domains_handler))
+        .route(web::post().to(post_publish_domain_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/publish-domain/{domain}")
+        .route(web::delete().to(delete_publish_domain_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/publish-domain/{domain}/verify")
+        .route(web::post().to(verify_publish_domain_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/publish-export")
+        .route(web::post().to(post_published_site_export_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/publish-export/{export_id}")
+        .route(web::get().to(get_published_site_export_handler)),
+    )
     .service(
       web::resource("{workspace_id}/published-duplicate")
         .route(web::post().to(post_published_duplicate_handler)),
@@ -322,6 +442,14 @@ pub fn workspace_scope() -> Scope {
       web::resource("/{workspace_id}/published-info")
         .route(web::get().to(list_published_collab_info_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/published-analytics")
+        .route(web::get().to(get_publish_namespace_analytics_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/published-analytics/{view_id}")
+        .route(web::get().to(get_published_view_analytics_handler)),
+    )
     .service(
       // deprecated since 0.7.4
       web::resource("/published-info/{view_id}")
@@ -394,14 +522,79 @@ pub fn workspace_scope() -> Scope {
         .route(web::get().to(get_database_fields_handler))
         .route(web::post().to(post_database_fields_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/fields/{field_id}")
+        .route(web::patch().to(patch_database_field_handler))
+        .route(web::delete().to(delete_database_field_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/fields/{field_id}/move")
+        .route(web::post().to(move_database_field_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/view")
+        .route(web::get().to(list_database_views_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/view/{view_id}")
+        .route(web::get().to(get_database_view_handler))
+        .route(web::patch().to(patch_database_view_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row-access")
+        .route(web::get().to(get_database_row_access_handler))
+        .route(web::put().to(put_database_row_access_handler))
+        .route(web::delete().to(delete_database_row_access_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row-access/grants")
+        .route(web::post().to(post_database_row_grants_handler))
+        .route(web::delete().to(delete_database_row_grants_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row-template")
+        .route(web::get().to(list_database_row_templates_handler))
+        .route(web::post().to(post_database_row_template_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row-template/{template_id}")
+        .route(web::patch().to(patch_database_row_template_handler))
+        .route(web::delete().to(delete_database_row_template_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row-template/{template_id}/row")
+        .route(web::post().to(post_database_row_from_template_handler)),
+    )
     .service(
       web::resource("/{workspace_id}/database/{database_id}/row/updated")
         .route(web::get().to(list_database_row_id_updated_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row/changes")
+        .route(web::get().to(list_database_row_changes_handler)),
+    )
     .service(
       web::resource("/{workspace_id}/database/{database_id}/row/detail")
         .route(web::get().to(list_database_row_details_handler)),
     )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row/query")
+        .route(web::post().to(query_database_rows_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/csv")
+        .app_data(PayloadConfig::new(10 * 1024 * 1024)) // 10 MB
+        .route(web::get().to(export_database_csv_handler))
+        .route(web::post().to(import_database_csv_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row/batch")
+        .route(web::post().to(batch_database_rows_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/database/{database_id}/row/{row_id}")
+        .route(web::delete().to(delete_database_row_handler)),
+    )
     .service(
       web::resource("/{workspace_id}/quick-note")
         .route(web::get().to(list_quick_notes_handler))
@@ -1238,6 +1431,14 @@ async fn get_collab_handler(
   params
     .validate()
     .map_err(|err| AppError::InvalidRequest(err.to_string()))?;
+  biz::collab::row_access::enforce_read_row_collab(
+    &state,
+    uid,
+    params.workspace_id,
+    params.object_id,
+    params.collab_type,
+  )
+  .await?;
 
   let encode_collab = state
     .collab_storage
@@ -1271,6 +1472,14 @@ async fn v1_get_collab_handler(
     .get_user_uid(&user_uuid)
     .await
     .map_err(AppResponseError::from)?;
+  biz::collab::row_access::enforce_read_row_collab(
+    &state,
+    uid,
+    workspace_id,
+    object_id,
+    query.collab_type,
+  )
+  .await?;
 
   let encode_collab = state
     .collab_storage
@@ -1301,6 +1510,14 @@ async fn get_collab_json_handler(
     .get_user_uid(&user_uuid)
     .await
     .map_err(AppResponseError::from)?;
+  biz::collab::row_access::enforce_read_row_collab(
+    &state,
+    uid,
+    workspace_id,
+    object_id,
+    collab_type,
+  )
+  .await?;
 
   let doc_state = state
     .collab_storage
@@ -1453,6 +1670,46 @@ async fn post_page_view_handler(
   Ok(Json(AppResponse::Ok().with_data(page)))
 }
 
+/// Matches requests whose body is Markdown, e.g. `Content-Type: text/markdown; charset=utf-8`.
+fn is_markdown_request(ctx: &GuardContext) -> bool {
+  ctx
+    .header::<ContentType>()
+    .map(|content_type| content_type.0.essence_str() == "text/markdown")
+    .unwrap_or(false)
+}
+
+async fn post_markdown_page_view_handler(
+  user_uuid: UserUuid,
+  path: web::Path<Uuid>,
+  query: web::Query<CreateMarkdownPageParams>,
+  markdown: String,
+  state: Data<AppState>,
+  req: HttpRequest,
+) -> Result<Json<AppResponse<Page>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let workspace_uuid = path.into_inner();
+  let user = realtime_user_for_web_request(req.headers(), uid)?;
+  let page_data = serde_json::to_value(SerdeBlock {
+    ty: "page".to_string(),
+    data: HashMap::new(),
+    children: markdown_to_serde_blocks(&markdown)?,
+  })
+  .map_err(|err| AppError::InvalidPageData(err.to_string()))?;
+  let page = create_page(
+    &state,
+    user,
+    workspace_uuid,
+    &query.parent_view_id,
+    &ViewLayout::Document,
+    query.name.as_deref(),
+    Some(&page_data),
+    query.view_id,
+    None,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(page)))
+}
+
 async fn post_orphaned_view_handler(
   user_uuid: UserUuid,
   path: web::Path<Uuid>,
@@ -1497,6 +1754,121 @@ async fn append_block_to_page_handler(
   Ok(Json(AppResponse::Ok()))
 }
 
+async fn append_markdown_to_page_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String)>,
+  markdown: String,
+  state: Data<AppState>,
+  req: HttpRequest,
+) -> Result<Json<AppResponse<()>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let (workspace_uuid, view_id) = path.into_inner();
+  let user = realtime_user_for_web_request(req.headers(), uid)?;
+  let serde_blocks = markdown_to_serde_blocks(&markdown)?;
+  append_block_at_the_end_of_page(&state, user, workspace_uuid, &view_id, &serde_blocks).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn get_page_block_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<DocumentBlock>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let (workspace_id, view_id, block_id) = path.into_inner();
+  let block = get_page_block(&state, uid, workspace_id, view_id, &block_id).await?;
+  Ok(Json(AppResponse::Ok().with_data(block)))
+}
+
+async fn insert_page_block_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, Uuid)>,
+  payload: Json<InsertBlockParams>,
+  state: Data<AppState>,
+  req: HttpRequest,
+) -> Result<Json<AppResponse<InsertBlockResponse>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let (workspace_id, view_id) = path.into_inner();
+  let user = realtime_user_for_web_request(req.headers(), uid)?;
+  let InsertBlockParams {
+    block,
+    parent_block_id,
+    prev_block_id,
+  } = payload.into_inner();
+  let serde_block: SerdeBlock =
+    serde_json::from_value(block).map_err(|err| AppError::InvalidBlock(err.to_string()))?;
+  let block_id = insert_page_block(
+    &state,
+    user,
+    workspace_id,
+    view_id,
+    &serde_block,
+    parent_block_id,
+    prev_block_id,
+  )
+  .await?;
+  Ok(Json(
+    AppResponse::Ok().with_data(InsertBlockResponse { block_id }),
+  ))
+}
+
+async fn update_page_block_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, Uuid, String)>,
+  payload: Json<UpdateBlockParams>,
+  state: Data<AppState>,
+  req: HttpRequest,
+) -> Result<Json<AppResponse<()>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let (workspace_id, view_id, block_id) = path.into_inner();
+  let user = realtime_user_for_web_request(req.headers(), uid)?;
+  update_page_block(
+    &state,
+    user,
+    workspace_id,
+    view_id,
+    &block_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn delete_page_block_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+  req: HttpRequest,
+) -> Result<Json<AppResponse<()>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let (workspace_id, view_id, block_id) = path.into_inner();
+  let user = realtime_user_for_web_request(req.headers(), uid)?;
+  delete_page_block(&state, user, workspace_id, view_id, &block_id).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn move_page_block_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, Uuid, String)>,
+  payload: Json<MoveBlockParams>,
+  state: Data<AppState>,
+  req: HttpRequest,
+) -> Result<Json<AppResponse<()>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let (workspace_id, view_id, block_id) = path.into_inner();
+  let user = realtime_user_for_web_request(req.headers(), uid)?;
+  move_page_block(
+    &state,
+    user,
+    workspace_id,
+    view_id,
+    &block_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
 async fn move_page_handler(
   user_uuid: UserUuid,
   path: web::Path<(Uuid, String)>,
@@ -1682,6 +2054,8 @@ async fn publish_page_handler(
     visible_database_view_ids,
     comments_enabled,
     duplicate_enabled,
+    password,
+    expires_at,
   } = payload.into_inner();
   publish_page(
     &state,
@@ -1693,6 +2067,8 @@ async fn publish_page_handler(
     publish_name,
     comments_enabled.unwrap_or(true),
     duplicate_enabled.unwrap_or(true),
+    password,
+    expires_at,
   )
   .await?;
   Ok(Json(AppResponse::Ok()))
@@ -1897,13 +2273,32 @@ async fn batch_get_collab_handler(
     .get_user_uid(&user_uuid)
     .await
     .map_err(AppResponseError::from)?;
-  let result = BatchQueryCollabResult(
-    state
-      .collab_storage
-      .batch_get_collab(&uid, workspace_id, payload.into_inner().0)
-      .await,
-  );
-  Ok(Json(AppResponse::Ok().with_data(result)))
+  let queries = payload.into_inner().0;
+  let hidden_collabs = biz::collab::row_access::hidden_row_collabs(
+    &state,
+    uid,
+    workspace_id,
+    &queries
+      .iter()
+      .map(|query| (query.object_id, query.collab_type))
+      .collect::<Vec<_>>(),
+  )
+  .await?;
+  let mut results = state
+    .collab_storage
+    .batch_get_collab(&uid, workspace_id, queries)
+    .await;
+  for object_id in hidden_collabs {
+    results.insert(
+      object_id,
+      QueryCollabResult::Failed {
+        error: AppError::NotEnoughPermissions.to_string(),
+      },
+    );
+  }
+  Ok(Json(
+    AppResponse::Ok().with_data(BatchQueryCollabResult(results)),
+  ))
 }
 
 #[instrument(skip(state, payload), err)]
@@ -2079,34 +2474,45 @@ async fn get_publish_namespace_handler(
 }
 
 async fn get_default_published_collab_info_meta_handler(
+  req: HttpRequest,
   publish_namespace: web::Path<String>,
   state: Data<AppState>,
 ) -> Result<Json<AppResponse<PublishInfoMeta<serde_json::Value>>>> {
   let publish_namespace = publish_namespace.into_inner();
   let (info, meta) =
     get_workspace_default_publish_view_info_meta(&state.pg_pool, &publish_namespace).await?;
+  check_published_view_access(&req, &state, &info.view_id).await?;
   Ok(Json(
     AppResponse::Ok().with_data(PublishInfoMeta { info, meta }),
   ))
 }
 
 async fn get_v1_published_collab_handler(
+  req: HttpRequest,
   path_param: web::Path<(String, String)>,
   state: Data<AppState>,
 ) -> Result<Json<AppResponse<serde_json::Value>>> {
   let (workspace_namespace, publish_name) = path_param.into_inner();
+  check_published_collab_access(&req, &state, &workspace_namespace, &publish_name).await?;
   let metadata = state
     .published_collab_store
     .get_collab_metadata(&workspace_namespace, &publish_name)
     .await?;
+  state.publish_analytics.record_view(
+    &workspace_namespace,
+    &publish_name,
+    publish_visitor_from_request(&req),
+  );
   Ok(Json(AppResponse::Ok().with_data(metadata)))
 }
 
 async fn get_published_collab_blob_handler(
+  req: HttpRequest,
   path_param: web::Path<(String, String)>,
   state: Data<AppState>,
 ) -> Result<Vec<u8>> {
   let (publish_namespace, publish_name) = path_param.into_inner();
+  check_published_collab_access(&req, &state, &publish_namespace, &publish_name).await?;
   let collab_data = state
     .published_collab_store
     .get_collab_blob_by_publish_namespace(&publish_namespace, &publish_name)
@@ -2114,7 +2520,105 @@ async fn get_published_collab_blob_handler(
   Ok(collab_data)
 }
 
+async fn post_published_collab_access_handler(
+  req: HttpRequest,
+  path_param: web::Path<(String, String)>,
+  state: Data<AppState>,
+  params: Json<PublishedCollabAccessParams>,
+) -> Result<HttpResponse> {
+  let (publish_namespace, publish_name) = path_param.into_inner();
+  let (access, token) = unlock_published_collab(
+    &state.pg_pool,
+    &state.redis_connection_manager,
+    &state.config.gotrue.jwt_secret,
+    &publish_namespace,
+    &publish_name,
+    params.into_inner().password,
+    client_ip(&req),
+  )
+  .await?;
+  let max_age = (access.expires_at - Utc::now()).num_seconds();
+  let cookie = Cookie::build(publish_access_cookie_name(&access.view_id), token)
+    .path(WORKSPACE_PATTERN)
+    .http_only(true)
+    .secure(req.connection_info().scheme() == "https")
+    .same_site(SameSite::Lax)
+    .max_age(CookieDuration::seconds(max_age))
+    .finish();
+  Ok(
+    HttpResponse::Ok()
+      .cookie(cookie)
+      .json(AppResponse::Ok().with_data(access)),
+  )
+}
+
+/// Guests need a valid access cookie to read password protected published pages.
+async fn check_published_collab_access(
+  req: &HttpRequest,
+  state: &AppState,
+  publish_namespace: &str,
+  publish_name: &str,
+) -> Result<(), AppError> {
+  biz::workspace::publish_access::check_published_collab_access(
+    &state.pg_pool,
+    &state.config.gotrue.jwt_secret,
+    publish_namespace,
+    publish_name,
+    |view_id| {
+      req
+        .cookie(&publish_access_cookie_name(view_id))
+        .map(|cookie| cookie.value().to_string())
+    },
+  )
+  .await
+}
+
+/// Same as [check_published_collab_access], for the published pages identified by view id.
+async fn check_published_view_access(
+  req: &HttpRequest,
+  state: &AppState,
+  view_id: &Uuid,
+) -> Result<(), AppError> {
+  biz::workspace::publish_access::check_published_view_access(
+    &state.pg_pool,
+    &state.config.gotrue.jwt_secret,
+    view_id,
+    |view_id| {
+      req
+        .cookie(&publish_access_cookie_name(view_id))
+        .map(|cookie| cookie.value().to_string())
+    },
+  )
+  .await
+}
+
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+  req.connection_info().realip_remote_addr().and_then(|addr| {
+    addr
+      .parse::<IpAddr>()
+      .ok()
+      .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+  })
+}
+
+fn publish_visitor_from_request(req: &HttpRequest) -> PublishVisitor {
+  let ip = client_ip(req);
+  let header_value = |name| {
+    req
+      .headers()
+      .get(name)
+      .and_then(|value| value.to_str().ok())
+      .map(|value| value.to_string())
+  };
+  PublishVisitor {
+    ip,
+    user_agent: header_value(actix_web::http::header::USER_AGENT),
+    referrer: header_value(actix_web::http::header::REFERER),
+  }
+}
+
 async fn post_published_duplicate_handler(
+  req: HttpRequest,
   user_uuid: UserUuid,
   workspace_id: web::Path<Uuid>,
   state: Data<AppState>,
@@ -2127,6 +2631,7 @@ async fn post_published_duplicate_handler(
     .enforce_action(&uid, &workspace_id, Action::Write)
     .await?;
   let params = params.into_inner();
+  check_published_view_access(&req, &state, &params.published_view_id).await?;
   let root_view_id_for_duplicate =
     biz::workspace::publish_dup::duplicate_published_collab_to_workspace(
       &state,
@@ -2160,15 +2665,54 @@ async fn list_published_collab_info_handler(
   Ok(Json(AppResponse::Ok().with_data(publish_infos)))
 }
 
-// Deprecated since 0.7.4
-async fn get_published_collab_info_handler(
-  view_id: web::Path<Uuid>,
+async fn get_publish_namespace_analytics_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  query: web::Query<PublishAnalyticsQueryParams>,
   state: Data<AppState>,
-) -> Result<Json<AppResponse<PublishInfo>>> {
-  let view_id = view_id.into_inner();
-  let collab_data = state
-    .published_collab_store
-    .get_collab_publish_info(&view_id)
+) -> Result<Json<AppResponse<PublishNamespaceAnalytics>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let analytics =
+    get_publish_namespace_analytics(&state.pg_pool, workspace_id, query.into_inner().days).await?;
+  Ok(Json(AppResponse::Ok().with_data(analytics)))
+}
+
+async fn get_published_view_analytics_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  query: web::Query<PublishAnalyticsQueryParams>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishedViewAnalytics>>> {
+  let (workspace_id, view_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let analytics = get_published_view_analytics(
+    &state.pg_pool,
+    workspace_id,
+    view_id,
+    query.into_inner().days,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(analytics)))
+}
+
+// Deprecated since 0.7.4
+async fn get_published_collab_info_handler(
+  view_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishInfo>>> {
+  let view_id = view_id.into_inner();
+  let collab_data = state
+    .published_collab_store
+    .get_collab_publish_info(&view_id)
     .await?;
   if collab_data.unpublished_timestamp.is_some() {
     return Err(AppError::RecordNotFound("Collab is unpublished".to_string()).into());
@@ -2189,11 +2733,13 @@ async fn get_v1_published_collab_info_handler(
 }
 
 async fn get_published_collab_comment_handler(
+  req: HttpRequest,
   view_id: web::Path<Uuid>,
   optional_user_uuid: OptionalUserUuid,
   state: Data<AppState>,
 ) -> Result<JsonAppResponse<GlobalComments>> {
   let view_id = view_id.into_inner();
+  check_published_view_access(&req, &state, &view_id).await?;
   let comments =
     get_comments_on_published_view(&state.pg_pool, &view_id, &optional_user_uuid).await?;
   let resp = GlobalComments { comments };
@@ -2201,18 +2747,21 @@ async fn get_published_collab_comment_handler(
 }
 
 async fn post_published_collab_comment_handler(
+  req: HttpRequest,
   user_uuid: UserUuid,
   view_id: web::Path<Uuid>,
   state: Data<AppState>,
   data: Json<CreateGlobalCommentParams>,
 ) -> Result<JsonAppResponse<()>> {
   let view_id = view_id.into_inner();
+  check_published_view_access(&req, &state, &view_id).await?;
   create_comment_on_published_view(
     &state.pg_pool,
     &view_id,
     &data.reply_comment_id,
     &data.content,
     &user_uuid,
+    state.config.published_collab.comment_rate_limit_per_minute,
   )
   .await?;
   Ok(Json(AppResponse::Ok()))
@@ -2230,11 +2779,13 @@ async fn delete_published_collab_comment_handler(
 }
 
 async fn get_published_collab_reaction_handler(
+  req: HttpRequest,
   view_id: web::Path<Uuid>,
   query: web::Query<GetReactionQueryParams>,
   state: Data<AppState>,
 ) -> Result<JsonAppResponse<Reactions>> {
   let view_id = view_id.into_inner();
+  check_published_view_access(&req, &state, &view_id).await?;
   let reactions =
     get_reactions_on_published_view(&state.pg_pool, &view_id, &query.comment_id).await?;
   let resp = Reactions { reactions };
@@ -2242,12 +2793,14 @@ async fn get_published_collab_reaction_handler(
 }
 
 async fn post_published_collab_reaction_handler(
+  req: HttpRequest,
   user_uuid: UserUuid,
   view_id: web::Path<Uuid>,
   data: Json<CreateReactionParams>,
   state: Data<AppState>,
 ) -> Result<JsonAppResponse<()>> {
   let view_id = view_id.into_inner();
+  check_published_view_access(&req, &state, &view_id).await?;
   create_reaction_on_comment(
     &state.pg_pool,
     &data.comment_id,
@@ -2333,7 +2886,7 @@ async fn post_publish_collabs_handler(
   }
   state
     .published_collab_store
-    .publish_collabs(accumulator, &workspace_id, &user_uuid)
+    .publish_collabs(accumulator, &workspace_id, &user_uuid, &[])
     .await?;
   Ok(Json(AppResponse::Ok()))
 }
@@ -2470,6 +3023,29 @@ async fn get_workspace_usage_and_limit_handler(
   Ok(Json(AppResponse::Ok().with_data(usage_and_limit)))
 }
 
+async fn get_workspace_presence_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  query: web::Query<WorkspacePresenceQuery>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<WorkspacePresence>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+  let presence = get_workspace_presence(
+    &state.awareness_gossip,
+    state.realtime_access_control.as_ref(),
+    uid,
+    workspace_id,
+    query.into_inner().object_id,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(presence)))
+}
+
 async fn get_workspace_folder_handler(
   user_uuid: UserUuid,
   workspace_id: web::Path<Uuid>,
@@ -2556,6 +3132,293 @@ async fn get_trash_views_handler(
   Ok(Json(AppResponse::Ok().with_data(section_items)))
 }
 
+async fn get_published_sitemap_handler(
+  publish_namespace: web::Path<String>,
+  state: Data<AppState>,
+) -> Result<HttpResponse> {
+  let publish_namespace = publish_namespace.into_inner();
+  let pages = list_published_feed_pages(&state.pg_pool, &publish_namespace).await?;
+  let xml = render_sitemap(&state.config.appflowy_web_url, &publish_namespace, &pages)?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type("application/xml; charset=utf-8")
+      .body(xml),
+  )
+}
+
+async fn get_published_rss_handler(
+  publish_namespace: web::Path<String>,
+  state: Data<AppState>,
+) -> Result<HttpResponse> {
+  let publish_namespace = publish_namespace.into_inner();
+  let pages = list_published_feed_pages(&state.pg_pool, &publish_namespace).await?;
+  let xml = render_rss(&state.config.appflowy_web_url, &publish_namespace, &pages)?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type("application/rss+xml; charset=utf-8")
+      .body(xml),
+  )
+}
+
+async fn get_published_atom_handler(
+  publish_namespace: web::Path<String>,
+  state: Data<AppState>,
+) -> Result<HttpResponse> {
+  let publish_namespace = publish_namespace.into_inner();
+  let pages = list_published_feed_pages(&state.pg_pool, &publish_namespace).await?;
+  let xml = render_atom(&state.config.appflowy_web_url, &publish_namespace, &pages)?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type("application/atom+xml; charset=utf-8")
+      .body(xml),
+  )
+}
+
+async fn post_published_site_export_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishedSiteExport>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let export = request_published_site_export(
+    &state.pg_pool,
+    &workspace_id,
+    uid,
+    state.config.published_collab.site_export.cdn_url.as_deref(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(export)))
+}
+
+async fn get_published_site_export_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishedSiteExport>>> {
+  let (workspace_id, export_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let export = get_published_site_export(
+    &state.pg_pool,
+    &workspace_id,
+    &export_id,
+    state.config.published_collab.site_export.cdn_url.as_deref(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(export)))
+}
+
+async fn list_published_comments_for_moderation_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  query: web::Query<PublishedCommentModerationQueryParams>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<Vec<PublishedCommentModerationItem>>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let comments =
+    list_published_comments_for_moderation(&state.pg_pool, &workspace_id, &query).await?;
+  Ok(Json(AppResponse::Ok().with_data(comments)))
+}
+
+async fn put_published_comment_status_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  payload: Json<UpdatePublishedCommentStatusParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, comment_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  moderate_published_comment(&state.pg_pool, &workspace_id, &comment_id, payload.status).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn get_published_comment_setting_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishedCommentSetting>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let setting = get_published_comment_setting(&state.pg_pool, &workspace_id).await?;
+  Ok(Json(AppResponse::Ok().with_data(setting)))
+}
+
+async fn put_published_comment_setting_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<PublishedCommentSetting>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  update_published_comment_setting(&state.pg_pool, &workspace_id, &payload).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn list_published_comment_blocklist_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<Vec<PublishedCommentBlockedUser>>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let blocked_users = list_published_comment_blocked_users(&state.pg_pool, &workspace_id).await?;
+  Ok(Json(AppResponse::Ok().with_data(blocked_users)))
+}
+
+async fn post_published_comment_blocklist_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<BlockPublishedCommenterParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  block_published_commenter(
+    &state.pg_pool,
+    &workspace_id,
+    &payload.user_uuid,
+    payload.reason.as_deref(),
+    uid,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn delete_published_comment_blocklist_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, blocked_user_uuid) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  unblock_published_commenter(&state.pg_pool, &workspace_id, &blocked_user_uuid).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn list_publish_domains_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<Vec<PublishDomain>>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let domains = list_publish_domains(&state.pg_pool, &workspace_id).await?;
+  Ok(Json(AppResponse::Ok().with_data(domains)))
+}
+
+async fn post_publish_domain_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<AddPublishDomainParams>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishDomain>>> {
+  let workspace_id = workspace_id.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let domain = add_publish_domain(
+    &state.pg_pool,
+    &state.config.appflowy_web_url,
+    &workspace_id,
+    &payload.domain,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(domain)))
+}
+
+async fn verify_publish_domain_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishDomain>>> {
+  let (workspace_id, domain) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  let domain = verify_publish_domain(
+    &state.pg_pool,
+    state.publish_domain_resolver.as_ref(),
+    &workspace_id,
+    &domain,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(domain)))
+}
+
+async fn delete_publish_domain_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, domain) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+  remove_publish_domain(&state.pg_pool, &workspace_id, &domain).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn resolve_publish_domain_handler(
+  req: HttpRequest,
+  query: web::Query<PublishDomainQueryParams>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<PublishDomainResolution>>> {
+  let host = match query.into_inner().host {
+    Some(host) => host,
+    None => req.connection_info().host().to_string(),
+  };
+  let resolution = resolve_publish_domain(&state.pg_pool, &host).await?;
+  Ok(Json(AppResponse::Ok().with_data(resolution)))
+}
+
 async fn get_workspace_publish_outline_handler(
   publish_namespace: web::Path<String>,
   state: Data<AppState>,
@@ -2602,8 +3465,30 @@ async fn list_database_row_id_handler(
     .enforce_action(&uid, &workspace_id, Action::Read)
     .await?;
 
-  let db_rows =
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &db_id)
+    .await?;
+  let mut db_rows =
     biz::collab::ops::list_database_row_ids(&state.collab_storage, workspace_id, db_id).await?;
+  if row_access.is_restricted() {
+    let row_ids = db_rows
+      .iter()
+      .flat_map(|row| Uuid::parse_str(&row.id))
+      .collect();
+    let readable_row_ids: HashSet<String> = biz::collab::row_access::readable_row_ids(
+      &state.collab_storage,
+      uid,
+      workspace_id,
+      &row_access,
+      row_ids,
+    )
+    .await
+    .into_iter()
+    .map(|row_id| row_id.to_string())
+    .collect();
+    db_rows.retain(|row| readable_row_ids.contains(&row.id));
+  }
   Ok(Json(AppResponse::Ok().with_data(db_rows)))
 }
 
@@ -2647,29 +3532,60 @@ async fn put_database_row_handler(
     document,
   } = upsert_db_row.into_inner();
 
-  let row_id = {
-    let mut hasher = Sha256::new();
-    hasher.update(workspace_id);
-    hasher.update(db_id);
-    hasher.update(pre_hash);
-    let hash = hasher.finalize();
-    Uuid::from_bytes([
-      // take 16 out of 32 bytes
-      hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7], hash[8], hash[9],
-      hash[10], hash[11], hash[12], hash[13], hash[14], hash[15],
-    ])
-  };
+  let row_id = biz::collab::ops::database_row_id_from_pre_hash(workspace_id, db_id, &pre_hash);
+  biz::collab::row_access::enforce_write_rows(&state, uid, workspace_id, db_id, &[row_id]).await?;
 
   biz::collab::ops::upsert_database_row(&state, workspace_id, db_id, uid, row_id, cells, document)
     .await?;
   Ok(Json(AppResponse::Ok().with_data(row_id.to_string())))
 }
 
-async fn get_database_fields_handler(
+async fn delete_database_row_handler(
   user_uuid: UserUuid,
-  path_param: web::Path<(Uuid, Uuid)>,
+  path_param: web::Path<(Uuid, Uuid, Uuid)>,
   state: Data<AppState>,
-) -> Result<Json<AppResponse<Vec<AFDatabaseField>>>> {
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, db_id, row_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  biz::collab::row_access::enforce_write_rows(&state, uid, workspace_id, db_id, &[row_id]).await?;
+  biz::collab::ops::delete_database_row(&state, workspace_id, db_id, uid, row_id).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn batch_database_rows_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  params: Json<BatchDatabaseRowsParams>,
+) -> Result<Json<AppResponse<BatchDatabaseRowsResponse>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  let BatchDatabaseRowsParams { operations } = params.into_inner();
+  let written_row_ids = biz::collab::row_access::written_row_ids(workspace_id, db_id, &operations);
+  biz::collab::row_access::enforce_write_rows(&state, uid, workspace_id, db_id, &written_row_ids)
+    .await?;
+  let row_ids =
+    biz::collab::ops::batch_database_rows(&state, workspace_id, db_id, uid, operations).await?;
+  Ok(Json(
+    AppResponse::Ok().with_data(BatchDatabaseRowsResponse { row_ids }),
+  ))
+}
+
+async fn get_database_fields_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<Vec<AFDatabaseField>>>> {
   let (workspace_id, db_id) = path_param.into_inner();
   let uid = state.user_cache.get_user_uid(&user_uuid).await?;
   state
@@ -2702,6 +3618,352 @@ async fn post_database_fields_handler(
   Ok(Json(AppResponse::Ok().with_data(field_id)))
 }
 
+async fn patch_database_field_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+  update_field: Json<AFUpdateDatabaseField>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, db_id, field_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  biz::collab::ops::update_database_field(
+    &state,
+    workspace_id,
+    db_id,
+    uid,
+    &field_id,
+    update_field.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn move_database_field_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+  move_field: Json<AFMoveDatabaseField>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, db_id, field_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  biz::collab::ops::move_database_field(
+    &state,
+    workspace_id,
+    db_id,
+    &field_id,
+    move_field.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn delete_database_field_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, db_id, field_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  biz::collab::ops::delete_database_field(&state, workspace_id, db_id, &field_id).await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn list_database_views_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<Vec<AFDatabaseView>>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+
+  let views =
+    biz::collab::database_view::list_database_views(&state.collab_storage, workspace_id, db_id)
+      .await?;
+  Ok(Json(AppResponse::Ok().with_data(views)))
+}
+
+async fn get_database_view_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<AFDatabaseView>>> {
+  let (workspace_id, db_id, view_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+
+  let view = biz::collab::database_view::get_database_view(
+    &state.collab_storage,
+    workspace_id,
+    db_id,
+    &view_id,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(view)))
+}
+
+async fn patch_database_view_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, String)>,
+  state: Data<AppState>,
+  update_view: Json<AFUpdateDatabaseView>,
+) -> Result<Json<AppResponse<AFDatabaseView>>> {
+  let (workspace_id, db_id, view_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  let view = biz::collab::database_view::update_database_view(
+    &state,
+    uid,
+    workspace_id,
+    db_id,
+    &view_id,
+    update_view.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(view)))
+}
+
+async fn get_database_row_access_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<AFDatabaseRowAccessRule>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+
+  let rule =
+    biz::collab::row_access::get_database_row_access_rule(&state.pg_pool, workspace_id, db_id)
+      .await?;
+  Ok(Json(AppResponse::Ok().with_data(rule)))
+}
+
+async fn put_database_row_access_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  params: Json<AFUpdateDatabaseRowAccessRule>,
+) -> Result<Json<AppResponse<AFDatabaseRowAccessRule>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+
+  let rule = biz::collab::row_access::set_database_row_access_rule(
+    &state.pg_pool,
+    &state.collab_storage,
+    workspace_id,
+    db_id,
+    params.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(rule)))
+}
+
+async fn delete_database_row_access_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+
+  biz::collab::row_access::remove_database_row_access_rule(&state.pg_pool, workspace_id, db_id)
+    .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn post_database_row_grants_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  grants: Json<AFDatabaseRowGrants>,
+) -> Result<Json<AppResponse<AFDatabaseRowAccessRule>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+
+  let rule = biz::collab::row_access::add_database_row_grants(
+    &state.pg_pool,
+    workspace_id,
+    db_id,
+    grants.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(rule)))
+}
+
+async fn delete_database_row_grants_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  grants: Json<AFDatabaseRowGrants>,
+) -> Result<Json<AppResponse<AFDatabaseRowAccessRule>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_role_strong(&uid, &workspace_id, AFRole::Owner)
+    .await?;
+
+  let rule = biz::collab::row_access::remove_database_row_grants(
+    &state.pg_pool,
+    workspace_id,
+    db_id,
+    grants.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(rule)))
+}
+
+async fn list_database_row_templates_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<Vec<AFDatabaseRowTemplate>>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+
+  let templates =
+    biz::collab::row_template::list_database_row_templates(&state.pg_pool, workspace_id, db_id)
+      .await?;
+  Ok(Json(AppResponse::Ok().with_data(templates)))
+}
+
+async fn post_database_row_template_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  params: Json<AFCreateDatabaseRowTemplate>,
+) -> Result<Json<AppResponse<AFDatabaseRowTemplate>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  let template = biz::collab::row_template::create_database_row_template(
+    &state,
+    uid,
+    workspace_id,
+    db_id,
+    params.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(template)))
+}
+
+async fn patch_database_row_template_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, Uuid)>,
+  state: Data<AppState>,
+  params: Json<AFUpdateDatabaseRowTemplate>,
+) -> Result<Json<AppResponse<AFDatabaseRowTemplate>>> {
+  let (workspace_id, db_id, template_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  let template = biz::collab::row_template::update_row_template(
+    &state,
+    workspace_id,
+    db_id,
+    template_id,
+    params.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(template)))
+}
+
+async fn delete_database_row_template_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, db_id, template_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  biz::collab::row_template::remove_database_row_template(
+    &state.pg_pool,
+    workspace_id,
+    db_id,
+    template_id,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+async fn post_database_row_from_template_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<String>>> {
+  let (workspace_id, db_id, template_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  let row_id = biz::collab::row_template::insert_row_from_template(
+    &state,
+    uid,
+    workspace_id,
+    db_id,
+    template_id,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(row_id)))
+}
+
 async fn list_database_row_id_updated_handler(
   user_uuid: UserUuid,
   path_param: web::Path<(Uuid, Uuid)>,
@@ -2721,17 +3983,53 @@ async fn list_database_row_id_updated_handler(
     .after
     .unwrap_or_else(|| Utc::now() - Duration::hours(1));
 
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &db_id)
+    .await?;
   let db_rows = biz::collab::ops::list_database_row_ids_updated(
     &state.collab_storage,
     &state.pg_pool,
     workspace_id,
     db_id,
     &after,
+    uid,
+    &row_access,
   )
   .await?;
   Ok(Json(AppResponse::Ok().with_data(db_rows)))
 }
 
+async fn list_database_row_changes_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  param: web::Query<ListDatabaseRowChangesParam>,
+) -> Result<Json<AppResponse<DatabaseRowChangesResponse>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &db_id)
+    .await?;
+  let resp = biz::collab::row_change::list_database_row_changes(
+    &state.collab_storage,
+    &state.pg_pool,
+    uid,
+    workspace_id,
+    db_id,
+    param.into_inner(),
+    &row_access,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(resp)))
+}
+
 async fn list_database_row_details_handler(
   user_uuid: UserUuid,
   path_param: web::Path<(Uuid, Uuid)>,
@@ -2743,6 +4041,13 @@ async fn list_database_row_details_handler(
   let list_db_row_query = param.into_inner();
   let with_doc = list_db_row_query.with_doc.unwrap_or_default();
   let row_ids = list_db_row_query.into_ids()?;
+  let relation_expansion =
+    list_db_row_query
+      .expands_relations()
+      .then(|| biz::collab::relation::RelationExpansion {
+        fields: list_db_row_query.relation_fields(),
+        related_access: biz::collab::row_access::RelatedDatabaseAccess::new(&state),
+      });
 
   state
     .workspace_access_control
@@ -2751,19 +4056,122 @@ async fn list_database_row_details_handler(
 
   static UNSUPPORTED_FIELD_TYPES: &[FieldType] = &[FieldType::Relation];
 
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &db_id)
+    .await?;
   let db_rows = biz::collab::ops::list_database_row_details(
     &state.collab_storage,
+    &biz::collab::row_access::RelatedDatabaseAccess::new(&state),
     uid,
     workspace_id,
     db_id,
     &row_ids,
     UNSUPPORTED_FIELD_TYPES,
     with_doc,
+    &row_access,
+    relation_expansion.as_ref(),
   )
   .await?;
   Ok(Json(AppResponse::Ok().with_data(db_rows)))
 }
 
+async fn query_database_rows_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  payload: Json<QueryDatabaseRowsParams>,
+) -> Result<Json<AppResponse<QueryDatabaseRowsResponse>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+
+  static UNSUPPORTED_FIELD_TYPES: &[FieldType] = &[FieldType::Relation];
+
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &db_id)
+    .await?;
+  let resp = biz::collab::row_query::query_database_rows(
+    &state.collab_storage,
+    &biz::collab::row_access::RelatedDatabaseAccess::new(&state),
+    uid,
+    workspace_id,
+    db_id,
+    payload.into_inner(),
+    UNSUPPORTED_FIELD_TYPES,
+    &row_access,
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(resp)))
+}
+
+async fn export_database_csv_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  query: web::Query<ExportDatabaseCsvQuery>,
+) -> Result<HttpResponse> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Read)
+    .await?;
+
+  static UNSUPPORTED_FIELD_TYPES: &[FieldType] = &[FieldType::Relation];
+
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &db_id)
+    .await?;
+  let csv = biz::collab::database_csv::export_database_csv(
+    &state.collab_storage,
+    &biz::collab::row_access::RelatedDatabaseAccess::new(&state),
+    uid,
+    workspace_id,
+    db_id,
+    query.into_inner().view_id,
+    UNSUPPORTED_FIELD_TYPES,
+    &row_access,
+  )
+  .await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type("text/csv; charset=utf-8")
+      .body(csv),
+  )
+}
+
+async fn import_database_csv_handler(
+  user_uuid: UserUuid,
+  path_param: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+  query: web::Query<ImportDatabaseCsvQuery>,
+  csv: String,
+) -> Result<Json<AppResponse<ImportDatabaseCsvResponse>>> {
+  let (workspace_id, db_id) = path_param.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &workspace_id, Action::Write)
+    .await?;
+
+  let resp = biz::collab::database_csv::import_database_csv(
+    &state,
+    uid,
+    workspace_id,
+    db_id,
+    &csv,
+    query.into_inner(),
+  )
+  .await?;
+  Ok(Json(AppResponse::Ok().with_data(resp)))
+}
+
 #[inline]
 async fn parser_realtime_msg(
   payload: Bytes,
@@ -2919,6 +4327,14 @@ async fn collab_full_sync_handler(
     .get_user_uid(&user_uuid)
     .await
     .map_err(AppResponseError::from)?;
+  biz::collab::row_access::enforce_read_row_collab(
+    &state,
+    uid,
+    workspace_id,
+    object_id,
+    collab_type,
+  )
+  .await?;
 
   let user = RealtimeUser {
     uid,
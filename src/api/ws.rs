@@ -1,27 +1,37 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::biz::authentication::jwt::{authorization_from_token, UserUuid};
 use crate::state::AppState;
-use actix::Addr;
-use actix_http::header::AUTHORIZATION;
+use actix::{Actor, Addr};
+use actix_http::header::{AUTHORIZATION, CACHE_CONTROL, SEC_WEBSOCKET_PROTOCOL};
 use actix_web::web::{Data, Path, Payload};
 use actix_web::{get, web, HttpRequest, HttpResponse, Result, Scope};
 use actix_web_actors::ws;
 use app_error::AppError;
 use appflowy_collaborate::actix_ws::client::rt_client::RealtimeClient;
 use appflowy_collaborate::actix_ws::server::RealtimeServerActor;
-use appflowy_collaborate::ws2::{SessionInfo, WsSession};
+use appflowy_collaborate::ws2::{
+  token_from_protocols, AuthenticatedUser, ExtraMessageReceiver, SessionAuth, SessionAuthenticator,
+  SessionAuthenticatorRef, SessionInfo, SseFrame, SseInput, SseSession, WsSession, SYNC_PROTOCOL,
+};
 use appflowy_proto::{ServerMessage, WorkspaceNotification};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
 use collab_rt_entity::user::{AFUserChange, RealtimeUser, UserMessage};
 use collab_rt_entity::{max_sync_message_size, RealtimeMessage};
 use collab_stream::model::MessageId;
+use futures_util::StreamExt;
 use secrecy::Secret;
 use semver::Version;
-use shared_entity::response::AppResponseError;
+use shared_entity::response::{AppResponse, AppResponseError, JsonAppResponse};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, instrument, trace};
 use uuid::Uuid;
 
@@ -30,6 +40,14 @@ pub fn ws_scope() -> Scope {
     //.service(establish_ws_connection)
     .service(web::resource("/v1").route(web::get().to(establish_ws_connection_v1)))
     .service(web::resource("/v2/{workspace_id}").route(web::get().to(establish_ws_connection_v2)))
+    .service(
+      web::resource("/v2/{workspace_id}/sse").route(web::get().to(establish_sse_connection_v2)),
+    )
+    .service(
+      web::resource("/v2/{workspace_id}/sse/{client_id}")
+        .app_data(web::PayloadConfig::new(max_sync_message_size()))
+        .route(web::post().to(post_sse_message_v2)),
+    )
 }
 const MAX_FRAME_SIZE: usize = 65_536; // 64 KiB
 
@@ -116,14 +134,21 @@ pub async fn establish_ws_connection_v2(
   let workspace_id = path.into_inner();
   let ws_server = state.ws_server.clone();
   let params = WsConnectionV2Params::parse(&request)?;
-  let auth = authorization_from_token(params.access_token.as_str(), &jwt_secret)?;
-  let user_uuid = UserUuid::from_auth(auth)?;
-  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let authenticator = AccessTokenAuthenticator::new(&state, &jwt_secret);
+  let (uid, auth) = match &params.access_token {
+    Some(token) => {
+      let user = authenticator.authenticate(token).await?;
+      (user.uid, SessionAuth::Authenticated(user))
+    },
+    // user is resolved once the client sends its access token as the first message
+    None => (0, SessionAuth::Pending),
+  };
   let info = SessionInfo::new(
     params.client_id,
     uid,
     params.device_id,
     params.last_message_id,
+    params.supports_chunking,
   );
   tracing::debug!(
     "accepting new session {} (client id: {}) for workspace: {}",
@@ -132,6 +157,137 @@ pub async fn establish_ws_connection_v2(
     workspace_id
   );
 
+  ws::WsResponseBuilder::new(
+    WsSession::new(
+      workspace_id,
+      info,
+      ws_server,
+      authenticator,
+      auth,
+      state.ws_rate_limiters.session_limiter(workspace_id),
+    ),
+    &request,
+    payload,
+  )
+  .protocols(&[SYNC_PROTOCOL])
+  .frame_size(max_sync_message_size())
+  .start()
+}
+
+/// HTTP fallback for [establish_ws_connection_v2], used by clients which are not able to
+/// upgrade to WebSocket connection. It accepts the same query parameters and streams server
+/// messages back as Server-Sent Events, each one carrying base64 encoded protobuf message.
+/// Client messages are delivered separately through [post_sse_message_v2].
+#[instrument(skip_all, err)]
+pub async fn establish_sse_connection_v2(
+  request: HttpRequest,
+  path: Path<Uuid>,
+  state: Data<AppState>,
+  jwt_secret: Data<Secret<String>>,
+) -> Result<HttpResponse> {
+  let workspace_id = path.into_inner();
+  let params = WsConnectionV2Params::parse(&request)?;
+  let token = params
+    .access_token
+    .as_deref()
+    .ok_or_else(|| AppError::UserUnAuthorized("Missing access token".into()))?;
+  let authenticator = AccessTokenAuthenticator::new(&state, &jwt_secret);
+  let user = authenticator.authenticate(token).await?;
+  let info = SessionInfo::new(
+    params.client_id,
+    user.uid,
+    params.device_id,
+    params.last_message_id,
+    params.supports_chunking,
+  );
+  tracing::debug!(
+    "accepting new http fallback session {} (client id: {}) for workspace: {}",
+    info.collab_origin(),
+    params.client_id,
+    workspace_id
+  );
+
+  let (session, frames) = SseSession::new(
+    workspace_id,
+    info,
+    state.ws_server.clone(),
+    state.sse_sessions.clone(),
+    authenticator,
+    user,
+    state.ws_rate_limiters.session_limiter(workspace_id),
+  );
+  session.start();
+
+  let events = ReceiverStream::new(frames).map(|frame| {
+    let event = match frame {
+      SseFrame::Message(bytes) => format!("event: message\ndata: {}\n\n", STANDARD.encode(bytes)),
+      SseFrame::Ping => ": ping\n\n".to_string(),
+    };
+    Ok::<_, AppError>(Bytes::from(event))
+  });
+  Ok(
+    HttpResponse::Ok()
+      .content_type("text/event-stream")
+      .insert_header((CACHE_CONTROL, "no-cache"))
+      // prevent reverse proxies from buffering the event stream
+      .insert_header(("X-Accel-Buffering", "no"))
+      .streaming(events),
+  )
+}
+
+/// Accepts a single binary protobuf client message for the session previously established with
+/// [establish_sse_connection_v2].
+#[instrument(skip_all, err)]
+pub async fn post_sse_message_v2(
+  user_uuid: UserUuid,
+  path: Path<(Uuid, u64)>,
+  state: Data<AppState>,
+  body: Bytes,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, client_id) = path.into_inner();
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  state
+    .sse_sessions
+    .deliver(workspace_id, client_id, SseInput { uid, bytes: body })
+    .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+/// Verifies access tokens of sync v2 sessions, both when connecting and when the client refreshes
+/// its token over established connection.
+struct AccessTokenAuthenticator {
+  state: Data<AppState>,
+  jwt_secret: Data<Secret<String>>,
+}
+
+impl AccessTokenAuthenticator {
+  fn new(state: &Data<AppState>, jwt_secret: &Data<Secret<String>>) -> SessionAuthenticatorRef {
+    Arc::new(AccessTokenAuthenticator {
+      state: state.clone(),
+      jwt_secret: jwt_secret.clone(),
+    })
+  }
+}
+
+#[async_trait]
+impl SessionAuthenticator for AccessTokenAuthenticator {
+  async fn authenticate(&self, token: &str) -> Result<AuthenticatedUser, AppError> {
+    let auth = authorization_from_token(token, &self.jwt_secret)
+      .map_err(|err| AppError::UserUnAuthorized(err.to_string()))?;
+    let expires_at = auth.claims.exp;
+    let user_uuid =
+      UserUuid::from_auth(auth).map_err(|err| AppError::UserUnAuthorized(err.to_string()))?;
+    let uid = self.state.user_cache.get_user_uid(&user_uuid).await?;
+    Ok(AuthenticatedUser { uid, expires_at })
+  }
+
+  fn subscribe_user_messages(&self, uid: i64) -> ExtraMessageReceiver {
+    subscribe_user_profile_changes(&self.state, uid)
+  }
+}
+
+/// Forwards user profile changes of the connected user to its sync v2 session.
+fn subscribe_user_profile_changes(state: &AppState, uid: i64) -> mpsc::Receiver<ServerMessage> {
   let (tx, rx) = mpsc::channel(10);
   let mut user_change_recv = state.pg_listeners.subscribe_user_change(uid);
   actix::spawn(async move {
@@ -149,14 +305,7 @@ pub async fn establish_ws_connection_v2(
       }
     }
   });
-
-  ws::WsResponseBuilder::new(
-    WsSession::new(workspace_id, info, ws_server, rx),
-    &request,
-    payload,
-  )
-  .frame_size(max_sync_message_size())
-  .start()
+  rx
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -324,10 +473,13 @@ impl ConnectInfo {
 }
 
 struct WsConnectionV2Params {
-  access_token: String,
+  /// Access token is optional, since the client can also send it as the first message
+  /// after establishing the connection.
+  access_token: Option<String>,
   device_id: String,
   client_id: u64,
   last_message_id: Option<MessageId>,
+  supports_chunking: bool,
 }
 
 impl WsConnectionV2Params {
@@ -335,8 +487,9 @@ impl WsConnectionV2Params {
     let url = req.full_url();
     let query = url.query_pairs().collect::<HashMap<_, _>>();
 
-    let access_token = Self::from_url(&query, "token")
-      .ok_or_else(|| AppError::InvalidRequest("Missing access token".into()))?;
+    // passing the token in the URL is still supported for older clients, but since URLs end up
+    // in proxy logs, newer ones use `Sec-WebSocket-Protocol` or `Authorization` header instead
+    let access_token = Self::from_headers(req).or_else(|| Self::from_url(&query, "token"));
     let device_id = Self::from_url(&query, "deviceId")
       .ok_or_else(|| AppError::InvalidRequest("Missing device id".into()))?;
     let client_id: u64 = Self::from_url(&query, "clientId")
@@ -349,14 +502,35 @@ impl WsConnectionV2Params {
         AppError::InvalidRequest("Couldn't parse 'X-AF-Last-Message-ID' head value".into())
       })?),
     };
+    // clients which don't advertise support for chunked messages, will receive them in one piece
+    let supports_chunking = Self::from_url(&query, "supportsChunking")
+      .map(|value| value == "true" || value == "1")
+      .unwrap_or(false);
     Ok(WsConnectionV2Params {
       access_token,
       device_id,
       client_id,
       last_message_id,
+      supports_chunking,
     })
   }
 
+  fn from_headers(req: &HttpRequest) -> Option<String> {
+    let headers = req.headers();
+    if let Some(token) = headers
+      .get(SEC_WEBSOCKET_PROTOCOL)
+      .and_then(|value| value.to_str().ok())
+      .and_then(token_from_protocols)
+    {
+      return Some(token.to_string());
+    }
+    headers
+      .get(AUTHORIZATION)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.strip_prefix("Bearer "))
+      .map(|token| token.trim().to_string())
+  }
+
   fn from_url(url_params: &HashMap<Cow<str>, Cow<str>>, param: &str) -> Option<String> {
     // we use params provided from URL as a backup since browser API doesn't allow to
     // establish WebSocket connection with custom HTTP headers
@@ -6,10 +6,12 @@ use access_control::casbin::access::AccessControl;
 use access_control::casbin::collab::{CollabAccessControlImpl, RealtimeCollabAccessControlImpl};
 use access_control::casbin::workspace::WorkspaceAccessControlImpl;
 use access_control::collab::{CollabAccessControl, RealtimeAccessControl};
+use access_control::database_row::{DatabaseRowAccessControl, DatabaseRowAccessControlImpl};
 use access_control::noops::collab::{
   CollabAccessControlImpl as NoOpsCollabAccessControlImpl,
   RealtimeCollabAccessControlImpl as NoOpsRealtimeCollabAccessControlImpl,
 };
+use access_control::noops::database_row::DatabaseRowAccessControlImpl as NoOpsDatabaseRowAccessControlImpl;
 use access_control::noops::workspace::WorkspaceAccessControlImpl as NoOpsWorkspaceAccessControlImpl;
 use access_control::workspace::WorkspaceAccessControl;
 use actix::{Actor, Supervisor};
@@ -37,7 +39,7 @@ use appflowy_ai_client::client::AppFlowyAIClient;
 use appflowy_collaborate::actix_ws::server::RealtimeServerActor;
 use appflowy_collaborate::collab::cache::CollabCache;
 use appflowy_collaborate::collab::collab_store::CollabStoreImpl;
-use appflowy_collaborate::ws2::{CollabManager, WsServer};
+use appflowy_collaborate::ws2::{CollabManager, RateLimiters, SseSessions, WsServer};
 use appflowy_collaborate::CollaborationServer;
 use collab_stream::awareness_gossip::AwarenessGossip;
 use collab_stream::metrics::CollabStreamMetrics;
@@ -66,11 +68,16 @@ use crate::api::template::template_scope;
 use crate::api::user::user_scope;
 use crate::api::workspace::{collab_scope, workspace_scope};
 use crate::api::ws::ws_scope;
+use crate::biz::collab::row_template::DatabaseRowTemplateWorker;
 use crate::biz::notification::email::EmailNotificationWorker;
 use crate::biz::pg_listener::PgListeners;
+use crate::biz::user::user_data_export::UserDataExportWorker;
 use crate::biz::workspace::publish::{
   PublishedCollabPostgresStore, PublishedCollabS3StoreWithPostgresFallback, PublishedCollabStore,
 };
+use crate::biz::workspace::publish_analytics::PublishAnalytics;
+use crate::biz::workspace::publish_domain::HickoryTxtResolver;
+use crate::biz::workspace::publish_export::PublishedSiteExportWorker;
 use crate::config::config::{
   Config, DatabaseSetting, GoTrueSetting, PublishedCollabStorageBackend, S3Setting,
 };
@@ -136,6 +143,16 @@ pub async fn run_actix_server(
   .unwrap();
 
   let realtime_server_actor = Supervisor::start(|_| RealtimeServerActor(realtime_server));
+
+  info!("Setting up database row template worker...");
+  let row_template_worker = DatabaseRowTemplateWorker {
+    state: state.clone(),
+    interval_secs: config.database_row_template.interval_secs,
+  };
+  tokio::spawn(async move {
+    row_template_worker.start_task().await;
+  });
+
   let mut server = HttpServer::new(move || {
     let app = App::new()
       .wrap(NormalizePath::trim())
@@ -236,7 +253,7 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
 
   // Redis
   info!("Connecting to Redis...");
-  let (redis_conn_manager, redis_stream_router, awareness_gossip) = get_redis_client(
+  let (redis_conn_manager, redis_stream_router, awareness_gossip, sse_sessions) = get_redis_client(
     config.redis_uri.expose_secret(),
     config.redis_worker_count,
     metrics.collab_stream_metrics.clone(),
@@ -285,6 +302,12 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
     } else {
       Arc::new(NoOpsWorkspaceAccessControlImpl::new())
     };
+  let database_row_access_control: Arc<dyn DatabaseRowAccessControl> =
+    if config.access_control.is_enabled {
+      Arc::new(DatabaseRowAccessControlImpl::new(pg_pool.clone()))
+    } else {
+      Arc::new(NoOpsDatabaseRowAccessControlImpl::new())
+    };
 
   // thread pool
   let thread_pool = Arc::new(
@@ -336,6 +359,38 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
     });
   }
 
+  let (publish_analytics, publish_analytics_worker) =
+    PublishAnalytics::new(pg_pool.clone(), &config.published_collab.analytics);
+  if let Some(worker) = publish_analytics_worker {
+    info!("Setting up published page analytics worker...");
+    tokio::spawn(worker.start_task());
+  }
+
+  info!("Setting up published site export worker...");
+  let site_export_worker = PublishedSiteExportWorker {
+    pg_pool: pg_pool.clone(),
+    published_collab_store: published_collab_store.clone(),
+    bucket_client: s3_client.clone(),
+    appflowy_web_url: config.appflowy_web_url.clone(),
+    interval_secs: config.published_collab.site_export.interval_secs,
+  };
+  tokio::spawn(async move {
+    site_export_worker.start_task().await;
+  });
+
+  info!("Setting up user data export worker...");
+  let user_data_export_worker = UserDataExportWorker {
+    pg_pool: pg_pool.clone(),
+    collab_storage: collab_access_control_storage.clone(),
+    bucket_client: s3_client.clone(),
+    mailer: mailer.clone(),
+    interval_secs: config.user_data_export.interval_secs,
+    link_expires_secs: config.user_data_export.link_expires_secs,
+  };
+  tokio::spawn(async move {
+    user_data_export_worker.start_task().await;
+  });
+
   info!("Setting up Indexer scheduler...");
   let (open_ai_config, azure_ai_config) = get_open_ai_config();
   let embedder_config = IndexerConfiguration {
@@ -362,6 +417,7 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
   let manager = CollabManager::new(
     thread_pool.clone(),
     collab_access_control.clone(),
+    database_row_access_control.clone(),
     collab_cache.clone(),
     redis_conn_manager.clone(),
     redis_stream_router.clone(),
@@ -415,16 +471,24 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
     collab_access_control,
     workspace_access_control,
     realtime_access_control,
+    database_row_access_control,
     bucket_storage,
     published_collab_store,
     bucket_client: s3_client,
     pg_listeners,
+    ws_rate_limiters: RateLimiters::new(
+      config.websocket.rate_limit.clone(),
+      metrics.collab_metrics.clone(),
+    ),
+    publish_analytics,
+    publish_domain_resolver: Arc::new(HickoryTxtResolver::new()),
     metrics,
     gotrue_admin,
     mailer,
     ai_client: appflowy_ai_client,
     indexer_scheduler,
     ws_server,
+    sse_sessions,
     sms_service,
   })
 }
@@ -449,6 +513,7 @@ async fn get_redis_client(
     redis::aio::ConnectionManager,
     Arc<StreamRouter>,
     Arc<AwarenessGossip>,
+    SseSessions,
   ),
   Error,
 > {
@@ -456,6 +521,7 @@ async fn get_redis_client(
   let client = redis::Client::open(redis_uri).context("failed to connect to redis")?;
 
   let awareness_gossip = AwarenessGossip::new(&client).await?;
+  let sse_sessions = SseSessions::new(&client).await?;
   let router = StreamRouter::with_options(
     &client,
     metrics,
@@ -471,7 +537,12 @@ async fn get_redis_client(
     .get_connection_manager()
     .await
     .context("failed to get the connection manager")?;
-  Ok((manager, router.into(), awareness_gossip.into()))
+  Ok((
+    manager,
+    router.into(),
+    awareness_gossip.into(),
+    sse_sessions,
+  ))
 }
 
 pub async fn get_aws_s3_client(s3_setting: &S3Setting) -> Result<aws_sdk_s3::Client, Error> {
@@ -0,0 +1,611 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use access_control::database_row::DatabaseRowAccess;
+use app_error::AppError;
+use chrono::Utc;
+use collab_database::entity::FieldType;
+use collab_database::fields::{Field, TypeOptionCellReader, TypeOptionCellWriter, TypeOptionData};
+use collab_database::rows::Row;
+use collab_database::template::entity::CELL_DATA;
+use database::collab::CollabStore;
+use serde_json::json;
+use shared_entity::dto::workspace_dto::{AFComputedField, RollupFunction};
+use uuid::Uuid;
+use yrs::Any;
+
+use super::database_view::find_field;
+use super::formula::{evaluate_formula, parse_formula, Expr, FormulaValue};
+use super::row_access::{can_read_row, RelatedDatabaseAccess};
+use super::row_query::{
+  cell_timestamp, get_database_rows, is_cell_checked, json_to_text, row_cell,
+};
+use super::utils::{
+  get_latest_collab_database_body, type_option_reader_by_id, type_option_writer_by_id,
+};
+
+/// Key of the formula or rollup in the type option of a computed field. The value is stored as
+/// stringified JSON, like the options of the select fields.
+pub const COMPUTED_TYPE_OPTION_KEY: &str = "computed";
+/// Key of the related database in the type option of a relation field.
+const RELATION_DATABASE_ID: &str = "database_id";
+
+/// Returns the formula or rollup of the field, if the field is computed.
+pub fn computed_field(field: &Field) -> Option<AFComputedField> {
+  let type_option = field
+    .type_options
+    .get(&FieldType::from(field.field_type).type_id())?;
+  match type_option.get(COMPUTED_TYPE_OPTION_KEY)? {
+    Any::String(computed) => serde_json::from_str(computed)
+      .inspect_err(|err| tracing::warn!("Invalid computed field {}: {}", field.id, err))
+      .ok(),
+    _ => None,
+  }
+}
+
+pub fn set_computed_type_option(
+  type_option_data: &mut TypeOptionData,
+  computed: &AFComputedField,
+) -> Result<(), AppError> {
+  let computed = serde_json::to_string(computed).map_err(|err| AppError::Internal(err.into()))?;
+  type_option_data.insert(
+    COMPUTED_TYPE_OPTION_KEY.to_string(),
+    Any::String(computed.into()),
+  );
+  Ok(())
+}
+
+/// Checks that the fields referenced by the formula or rollup of the field exist, and that the
+/// formula does not reference itself, directly or through other formulas.
+pub fn validate_computed_field(
+  fields: &[Field],
+  field_id: &str,
+  computed: &AFComputedField,
+) -> Result<(), AppError> {
+  match computed {
+    AFComputedField::Formula { expression } => {
+      let expr = parse_formula(expression)?;
+      let mut pending = vec![];
+      for field_ref in expr.field_refs() {
+        let field = find_field(fields, field_ref)?;
+        pending.push(field.id.clone());
+      }
+      let mut visited = HashSet::new();
+      while let Some(id) = pending.pop() {
+        if id == field_id {
+          return Err(AppError::InvalidRequest(
+            "Formula must not reference itself".to_string(),
+          ));
+        }
+        if !visited.insert(id.clone()) {
+          continue;
+        }
+        let referenced = find_field(fields, &id).ok().and_then(computed_field);
+        if let Some(AFComputedField::Formula { expression }) = referenced {
+          if let Ok(expr) = parse_formula(&expression) {
+            pending.extend(
+              expr
+                .field_refs()
+                .into_iter()
+                .filter_map(|field_ref| find_field(fields, field_ref).ok())
+                .map(|field| field.id.clone()),
+            );
+          }
+        }
+      }
+    },
+    AFComputedField::Rollup {
+      relation_field,
+      target_field,
+      function,
+    } => {
+      let field = find_field(fields, relation_field)?;
+      if FieldType::from(field.field_type) != FieldType::Relation {
+        return Err(AppError::InvalidRequest(format!(
+          "Field {} is not a relation field",
+          field.name
+        )));
+      }
+      if target_field.is_none() && *function != RollupFunction::Count {
+        return Err(AppError::InvalidRequest(format!(
+          "Rollup function {:?} requires a target field",
+          function
+        )));
+      }
+    },
+  }
+  Ok(())
+}
+
+/// Returns the ids of the rows related through the relation field.
+pub fn relation_row_ids(row: &Row, field: &Field) -> Vec<Uuid> {
+  match row
+    .cells
+    .get(&field.id)
+    .and_then(|cell| cell.get(CELL_DATA))
+  {
+    Some(Any::Array(row_ids)) => row_ids
+      .iter()
+      .filter_map(|row_id| match row_id {
+        Any::String(row_id) => Uuid::parse_str(row_id).ok(),
+        _ => None,
+      })
+      .collect(),
+    _ => vec![],
+  }
+}
+
+/// Returns the database related through the relation field.
+pub fn relation_database_id(field: &Field) -> Option<Uuid> {
+  let type_option = field
+    .type_options
+    .get(&FieldType::Relation.type_id())?
+    .get(RELATION_DATABASE_ID)?;
+  match type_option {
+    Any::String(database_id) => Uuid::parse_str(database_id).ok(),
+    _ => None,
+  }
+}
+
+struct Rollup<'a> {
+  relation_field: &'a Field,
+  target_field: Option<String>,
+  function: RollupFunction,
+}
+
+/// Evaluates the formula and rollup fields of a database, and writes their values to the cells
+/// of the rows, so that they are read like the other cells.
+pub struct ComputedFields<'a> {
+  fields: &'a [Field],
+  computed_ids: HashSet<&'a str>,
+  reader_by_id: HashMap<String, Box<dyn TypeOptionCellReader>>,
+  writer_by_id: HashMap<String, Box<dyn TypeOptionCellWriter>>,
+  /// Formula fields, after the formulas they reference. The formulas, which are invalid or
+  /// reference themselves, have no expression and are always empty.
+  formulas: Vec<(&'a Field, Option<Expr>)>,
+  rollups: Vec<(&'a Field, Rollup<'a>)>,
+}
+
+impl<'a> ComputedFields<'a> {
+  pub fn new(fields: &'a [Field]) -> Self {
+    let mut computed_ids = HashSet::new();
+    let mut exprs: HashMap<&str, Option<Expr>> = HashMap::new();
+    let mut rollups = vec![];
+    for field in fields {
+      let computed = match computed_field(field) {
+        Some(computed) => computed,
+        None => continue,
+      };
+      computed_ids.insert(field.id.as_str());
+      match computed {
+        AFComputedField::Formula { expression } => {
+          let expr = parse_formula(&expression)
+            .inspect_err(|err| tracing::warn!("Invalid formula in field {}: {}", field.id, err))
+            .ok();
+          exprs.insert(field.id.as_str(), expr);
+        },
+        AFComputedField::Rollup {
+          relation_field,
+          target_field,
+          function,
+        } => match find_field(fields, &relation_field) {
+          Ok(relation_field)
+            if FieldType::from(relation_field.field_type) == FieldType::Relation =>
+          {
+            rollups.push((
+              field,
+              Rollup {
+                relation_field,
+                target_field,
+                function,
+              },
+            ))
+          },
+          _ => tracing::warn!(
+            "Relation field {} of rollup {} not found",
+            relation_field,
+            field.id
+          ),
+        },
+      }
+    }
+
+    // Order the formulas by their references to other formulas
+    let deps: HashMap<&str, Vec<&str>> = exprs
+      .iter()
+      .map(|(id, expr)| {
+        let refs = expr
+          .iter()
+          .flat_map(|expr| expr.field_refs())
+          .filter_map(|field_ref| find_field(fields, field_ref).ok())
+          .map(|field| field.id.as_str())
+          .filter(|id| exprs.contains_key(id))
+          .collect();
+        (*id, refs)
+      })
+      .collect();
+    let mut visited = HashMap::new();
+    let mut order = vec![];
+    for field in fields {
+      if deps.contains_key(field.id.as_str()) {
+        visit_formula(field.id.as_str(), &deps, &mut visited, &mut order);
+      }
+    }
+    let mut formulas: Vec<(&Field, Option<Expr>)> = order
+      .iter()
+      .filter_map(|id| find_field(fields, id).ok())
+      .map(|field| (field, exprs.remove(field.id.as_str()).flatten()))
+      .collect();
+    for field in fields {
+      if exprs.contains_key(field.id.as_str()) {
+        tracing::warn!("Formula in field {} references itself", field.id);
+        formulas.push((field, None));
+      }
+    }
+
+    Self {
+      fields,
+      computed_ids,
+      reader_by_id: type_option_reader_by_id(fields),
+      writer_by_id: type_option_writer_by_id(fields),
+      formulas,
+      rollups,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.formulas.is_empty() && self.rollups.is_empty()
+  }
+
+  /// Writes the values of the computed fields to the cells of the rows. Rollups read the related
+  /// rows from the storage, leaving out the rows which cannot be read by the user.
+  pub async fn fill_rows(
+    &self,
+    collab_storage: &Arc<dyn CollabStore>,
+    related_access: &RelatedDatabaseAccess,
+    uid: i64,
+    workspace_id: Uuid,
+    rows: Vec<&mut Row>,
+  ) {
+    if self.is_empty() {
+      return;
+    }
+    let rollup_values = self
+      .evaluate_rollups(collab_storage, related_access, uid, workspace_id, &rows)
+      .await;
+    let now = Utc::now().timestamp();
+    for (row, rollup_values) in rows.into_iter().zip(rollup_values) {
+      self.fill_row(row, rollup_values, now);
+    }
+  }
+
+  fn fill_row(&self, row: &mut Row, mut values: HashMap<String, FormulaValue>, now: i64) {
+    for (field, expr) in &self.formulas {
+      let value = match expr {
+        Some(expr) => {
+          let resolve_field = |field_ref: &str| self.field_value(row, &values, field_ref);
+          evaluate_formula(expr, &resolve_field, now).unwrap_or_else(|err| {
+            tracing::trace!("Failed to evaluate formula in field {}: {}", field.id, err);
+            FormulaValue::Null
+          })
+        },
+        None => FormulaValue::Null,
+      };
+      values.insert(field.id.clone(), value);
+    }
+
+    for (field_id, value) in values {
+      let field = match self.fields.iter().find(|field| field.id == field_id) {
+        Some(field) => field,
+        None => continue,
+      };
+      let cell_value = computed_cell_json(&value, &FieldType::from(field.field_type));
+      match (cell_value, self.writer_by_id.get(&field_id)) {
+        (serde_json::Value::Null, _) | (_, None) => {
+          row.cells.remove(&field_id);
+        },
+        (cell_value, Some(writer)) => {
+          row
+            .cells
+            .insert(field_id, writer.convert_json_to_cell(cell_value));
+        },
+      }
+    }
+  }
+
+  fn field_value(
+    &self,
+    row: &Row,
+    computed_values: &HashMap<String, FormulaValue>,
+    field_ref: &str,
+  ) -> FormulaValue {
+    let field = match find_field(self.fields, field_ref) {
+      Ok(field) => field,
+      Err(_) => return FormulaValue::Null,
+    };
+    if let Some(value) = computed_values.get(&field.id) {
+      return value.clone();
+    }
+    if self.computed_ids.contains(field.id.as_str()) {
+      return FormulaValue::Null;
+    }
+    cell_value(row, field, &self.reader_by_id)
+  }
+
+  async fn evaluate_rollups(
+    &self,
+    collab_storage: &Arc<dyn CollabStore>,
+    related_access: &RelatedDatabaseAccess,
+    uid: i64,
+    workspace_id: Uuid,
+    rows: &[&mut Row],
+  ) -> Vec<HashMap<String, FormulaValue>> {
+    let mut values = vec![HashMap::new(); rows.len()];
+    let mut related = RelatedDatabases::new(related_access);
+    for (field, rollup) in &self.rollups {
+      let related_row_ids: Vec<Vec<Uuid>> = rows
+        .iter()
+        .map(|row| relation_row_ids(row, rollup.relation_field))
+        .collect();
+      // Counting the related rows does not need to load them
+      let target_field = match &rollup.target_field {
+        Some(target_field) if rollup.function != RollupFunction::Count => {
+          let target_field = related
+            .target_field(
+              collab_storage,
+              uid,
+              workspace_id,
+              rollup.relation_field,
+              target_field,
+            )
+            .await;
+          match target_field {
+            Some(target_field) => Some(target_field),
+            None => {
+              tracing::warn!("Target field of rollup {} not found", field.id);
+              continue;
+            },
+          }
+        },
+        _ => None,
+      };
+      let target = match &target_field {
+        Some((target_field, database_id, fields)) => {
+          related
+            .load_rows(
+              collab_storage,
+              uid,
+              workspace_id,
+              *database_id,
+              fields,
+              related_row_ids.iter().flatten(),
+            )
+            .await;
+          let reader_by_id = type_option_reader_by_id(std::slice::from_ref(target_field));
+          Some((target_field, reader_by_id))
+        },
+        None => None,
+      };
+
+      for (row_values, row_ids) in values.iter_mut().zip(related_row_ids) {
+        let value = match &target {
+          Some((target_field, reader_by_id)) => {
+            let numbers: Vec<f64> = row_ids
+              .iter()
+              .filter_map(|row_id| related.rows.get(row_id))
+              .filter_map(|row| cell_value(row, target_field, reader_by_id).to_number())
+              .collect();
+            rollup_value(rollup.function, &numbers)
+          },
+          None => FormulaValue::Number(row_ids.len() as f64),
+        };
+        row_values.insert(field.id.clone(), value);
+      }
+    }
+    values
+  }
+}
+
+/// Fields and rows of the databases related through the relation fields, loaded once for all
+/// the rollups.
+struct RelatedDatabases<'a> {
+  related_access: &'a RelatedDatabaseAccess,
+  fields: HashMap<Uuid, Option<Vec<Field>>>,
+  row_access: HashMap<Uuid, DatabaseRowAccess>,
+  rows: HashMap<Uuid, Row>,
+}
+
+impl<'a> RelatedDatabases<'a> {
+  fn new(related_access: &'a RelatedDatabaseAccess) -> Self {
+    Self {
+      related_access,
+      fields: HashMap::new(),
+      row_access: HashMap::new(),
+      rows: HashMap::new(),
+    }
+  }
+
+  /// Returns the target field of a rollup, with the id and all the fields of the related
+  /// database. The related database must belong to the workspace and be readable by the user.
+  async fn target_field(
+    &mut self,
+    collab_storage: &Arc<dyn CollabStore>,
+    uid: i64,
+    workspace_id: Uuid,
+    relation_field: &Field,
+    target_field: &str,
+  ) -> Option<(Field, Uuid, Vec<Field>)> {
+    let database_id = relation_database_id(relation_field)?;
+    if !self.fields.contains_key(&database_id) {
+      let fields = match self
+        .load_fields(collab_storage, uid, workspace_id, database_id)
+        .await
+      {
+        Ok(fields) => Some(fields),
+        Err(err) => {
+          tracing::warn!("Failed to get related database {}: {}", database_id, err);
+          None
+        },
+      };
+      self.fields.insert(database_id, fields);
+    }
+    let fields = self.fields.get(&database_id)?.as_ref()?;
+    let target_field = find_field(fields, target_field).ok()?.clone();
+    Some((target_field, database_id, fields.clone()))
+  }
+
+  /// Loads the fields of the related database, after checking the access of the user to it.
+  async fn load_fields(
+    &mut self,
+    collab_storage: &Arc<dyn CollabStore>,
+    uid: i64,
+    workspace_id: Uuid,
+    database_id: Uuid,
+  ) -> Result<Vec<Field>, AppError> {
+    let row_access = self
+      .related_access
+      .enforce_read(uid, workspace_id, database_id)
+      .await?;
+    let (db_collab, db_body) =
+      get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+    self.row_access.insert(database_id, row_access);
+    Ok(db_body.fields.get_all_fields(&db_collab.transact()))
+  }
+
+  /// Loads the related rows, which are not loaded yet, with the values of their formulas. The
+  /// rows which cannot be read by the user, or which do not belong to the related database, are
+  /// left out. Rollups of the related rows are not evaluated.
+  async fn load_rows(
+    &mut self,
+    collab_storage: &Arc<dyn CollabStore>,
+    uid: i64,
+    workspace_id: Uuid,
+    database_id: Uuid,
+    fields: &[Field],
+    row_ids: impl Iterator<Item = &Uuid>,
+  ) {
+    let missing_row_ids: Vec<Uuid> = row_ids
+      .filter(|row_id| !self.rows.contains_key(row_id))
+      .copied()
+      .collect::<HashSet<_>>()
+      .into_iter()
+      .collect();
+    if missing_row_ids.is_empty() {
+      return;
+    }
+    let Some(row_access) = self.row_access.get(&database_id) else {
+      return;
+    };
+
+    // the relation cells are written by the clients, so the rows are checked against the
+    // related database
+    let database_id = database_id.to_string();
+    let mut rows = get_database_rows(collab_storage, uid, workspace_id, &missing_row_ids).await;
+    rows.retain(|row_id, row| {
+      row.database_id == database_id && can_read_row(row_access, row_id, row)
+    });
+    let computed_fields = ComputedFields::new(fields);
+    if !computed_fields.is_empty() {
+      let now = Utc::now().timestamp();
+      for row in rows.values_mut() {
+        computed_fields.fill_row(row, HashMap::new(), now);
+      }
+    }
+    self.rows.extend(rows);
+  }
+}
+
+fn visit_formula<'a>(
+  id: &'a str,
+  deps: &HashMap<&'a str, Vec<&'a str>>,
+  visited: &mut HashMap<&'a str, bool>,
+  order: &mut Vec<&'a str>,
+) -> bool {
+  if let Some(done) = visited.get(id) {
+    return *done;
+  }
+  visited.insert(id, false);
+  let done = deps[id]
+    .iter()
+    .all(|dep| visit_formula(dep, deps, visited, order));
+  if done {
+    visited.insert(id, true);
+    order.push(id);
+  }
+  done
+}
+
+fn rollup_value(function: RollupFunction, numbers: &[f64]) -> FormulaValue {
+  match function {
+    RollupFunction::Count => FormulaValue::Number(numbers.len() as f64),
+    RollupFunction::Sum => FormulaValue::Number(numbers.iter().sum()),
+    RollupFunction::Avg if numbers.is_empty() => FormulaValue::Null,
+    RollupFunction::Avg => FormulaValue::Number(numbers.iter().sum::<f64>() / numbers.len() as f64),
+    RollupFunction::Min => numbers
+      .iter()
+      .copied()
+      .reduce(f64::min)
+      .map(FormulaValue::Number)
+      .unwrap_or(FormulaValue::Null),
+    RollupFunction::Max => numbers
+      .iter()
+      .copied()
+      .reduce(f64::max)
+      .map(FormulaValue::Number)
+      .unwrap_or(FormulaValue::Null),
+  }
+}
+
+/// Returns the value of the cell of the field, as referenced by a formula.
+fn cell_value(
+  row: &Row,
+  field: &Field,
+  reader_by_id: &HashMap<String, Box<dyn TypeOptionCellReader>>,
+) -> FormulaValue {
+  match FieldType::from(field.field_type) {
+    FieldType::Number => reader_by_id
+      .get(&field.id)
+      .zip(row.cells.get(&field.id))
+      .and_then(|(reader, cell)| reader.numeric_cell(cell))
+      .map(FormulaValue::Number)
+      .unwrap_or(FormulaValue::Null),
+    FieldType::Checkbox => FormulaValue::Bool(is_cell_checked(row, field)),
+    FieldType::DateTime | FieldType::CreatedTime | FieldType::LastEditedTime => {
+      cell_timestamp(row, field)
+        .map(FormulaValue::Date)
+        .unwrap_or(FormulaValue::Null)
+    },
+    _ => {
+      let text = reader_by_id
+        .get(&field.id)
+        .map(|reader| json_to_text(&reader.json_cell(&row_cell(row, field))))
+        .unwrap_or_default();
+      if text.is_empty() {
+        FormulaValue::Null
+      } else {
+        FormulaValue::Text(text)
+      }
+    },
+  }
+}
+
+/// Converts the value of a computed field to the JSON value written to a cell of its type.
+fn computed_cell_json(value: &FormulaValue, field_type: &FieldType) -> serde_json::Value {
+  if *value == FormulaValue::Null {
+    return serde_json::Value::Null;
+  }
+  match field_type {
+    FieldType::Number => match value.to_number() {
+      // integers are written without a fraction, like the numbers entered by the users
+      Some(number) if number.fract() == 0.0 && number.abs() < 1e15 => json!(number as i64),
+      Some(number) => json!(number),
+      None => serde_json::Value::Null,
+    },
+    FieldType::Checkbox => json!(value.is_truthy()),
+    FieldType::DateTime => value
+      .to_timestamp()
+      .map(|timestamp| json!(timestamp))
+      .unwrap_or_default(),
+    _ => json!(value.to_text()),
+  }
+}
@@ -11,8 +11,10 @@ use collab_database::{
   entity::FieldType,
   error::DatabaseError,
   fields::{
-    date_type_option::DateTypeOption, default_field_settings_for_fields,
-    select_type_option::SingleSelectTypeOption, Field, TypeOptionData,
+    date_type_option::DateTypeOption,
+    default_field_settings_for_fields,
+    select_type_option::{MultiSelectTypeOption, SingleSelectTypeOption},
+    Field, TypeOptionData,
   },
   views::{
     BoardLayoutSetting, CalendarLayoutSetting, DatabaseLayout, FieldSettingsByFieldIdMap, Group,
@@ -69,29 +71,7 @@ fn resolve_board_dependencies(
     },
   };
   let field_settings = default_field_settings_for_fields(&all_fields, database_layout);
-  let group_ids = match FieldType::from(group_field.field_type) {
-    FieldType::SingleSelect => {
-      let mut group_ids = vec![group_field.id.clone()];
-      let single_select_type_option_ids = single_select_type_option_ids_from_field(&group_field)?;
-      group_ids.extend(single_select_type_option_ids);
-      Ok(group_ids)
-    },
-    FieldType::Checkbox => Ok(vec!["Yes".to_string(), "No".to_string()]),
-    field_type => Err(AppError::Internal(anyhow::anyhow!(format!(
-      "invalid dep field type({}) for board layout",
-      field_type
-    )))),
-  }?;
-
-  let groups = group_ids.iter().map(|id| Group::new(id.clone())).collect();
-  let group_settings: Vec<GroupSettingMap> = vec![GroupSetting {
-    id: gen_database_group_id(),
-    field_id: group_field.id.clone(),
-    field_type: group_field.field_type,
-    groups,
-    content: Default::default(),
-  }
-  .into()];
+  let group_settings = vec![group_setting_for_field(&group_field)?];
 
   let mut layout_settings = LayoutSettings::default();
   layout_settings.insert(database_layout, BoardLayoutSetting::new().into());
@@ -103,23 +83,57 @@ fn resolve_board_dependencies(
   })
 }
 
-fn single_select_type_option_ids_from_field(field: &Field) -> Result<Vec<String>, AppError> {
-  let type_option_data: Option<&TypeOptionData> =
-    field.type_options.get(&FieldType::SingleSelect.to_string());
-  match type_option_data {
-    Some(type_option_data) => {
-      let single_select_type_option = SingleSelectTypeOption::from(type_option_data.to_owned());
-      let single_select_type_option_ids: Vec<String> = single_select_type_option
-        .options
-        .iter()
-        .map(|option| option.id.clone())
-        .collect();
-      Ok(single_select_type_option_ids)
+/// Groups the rows by the options of a select field, or by the state of a checkbox field. The
+/// rows without option go to the group whose id is the id of the field.
+pub fn group_setting_for_field(field: &Field) -> Result<GroupSettingMap, AppError> {
+  let field_type = FieldType::from(field.field_type);
+  let group_ids = match field_type {
+    FieldType::SingleSelect | FieldType::MultiSelect => {
+      let mut group_ids = vec![field.id.clone()];
+      group_ids.extend(select_type_option_ids_from_field(field, &field_type)?);
+      group_ids
     },
-    None => Err(AppError::Internal(anyhow::anyhow!(
-      "invalid field for single select type options",
-    ))),
-  }
+    FieldType::Checkbox => vec!["Yes".to_string(), "No".to_string()],
+    field_type => {
+      return Err(AppError::InvalidRequest(format!(
+        "Rows cannot be grouped by field {} of type {:?}",
+        field.name, field_type
+      )))
+    },
+  };
+
+  let groups = group_ids.into_iter().map(Group::new).collect();
+  Ok(
+    GroupSetting {
+      id: gen_database_group_id(),
+      field_id: field.id.clone(),
+      field_type: field.field_type,
+      groups,
+      content: Default::default(),
+    }
+    .into(),
+  )
+}
+
+fn select_type_option_ids_from_field(
+  field: &Field,
+  field_type: &FieldType,
+) -> Result<Vec<String>, AppError> {
+  let type_option_data: Option<&TypeOptionData> = field.type_options.get(&field_type.to_string());
+  let options = match (type_option_data, field_type) {
+    (Some(type_option_data), FieldType::SingleSelect) => {
+      SingleSelectTypeOption::from(type_option_data.to_owned()).options
+    },
+    (Some(type_option_data), FieldType::MultiSelect) => {
+      MultiSelectTypeOption::from(type_option_data.to_owned()).options
+    },
+    _ => {
+      return Err(AppError::Internal(anyhow::anyhow!(
+        "invalid field for select type options",
+      )))
+    },
+  };
+  Ok(options.into_iter().map(|option| option.id).collect())
 }
 
 fn resolve_calendar_dependencies(fields: &[Field]) -> Result<LinkedViewDependencies, AppError> {
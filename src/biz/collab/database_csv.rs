@@ -0,0 +1,442 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use access_control::database_row::DatabaseRowAccess;
+use app_error::AppError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use collab_database::entity::FieldType;
+use collab_database::fields::Field;
+use database::collab::CollabStore;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use shared_entity::dto::workspace_dto::{
+  CsvInvalidValue, DatabaseRowOperation, ImportDatabaseCsvQuery, ImportDatabaseCsvResponse,
+  QueryDatabaseRowsParams, UpsertDatatabaseRow, MAX_DATABASE_ROW_BATCH_SIZE,
+};
+use uuid::Uuid;
+
+use super::computed_field::computed_field;
+use super::database_view::is_field_hidden;
+use super::ops::{batch_database_rows, list_database_row_details};
+use super::row_access::{enforce_write_rows, written_row_ids, RelatedDatabaseAccess};
+use super::row_query::{query_database_row_ids, select_option_ids, MAX_QUERY_LIMIT};
+use super::utils::{field_by_id_name_uniq, field_by_name_uniq, get_latest_collab_database_body};
+use crate::state::AppState;
+
+/// Values starting with these are evaluated as formulas by spreadsheets.
+const CSV_FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Exports the rows of the database view as CSV, with the visible fields of the view as columns,
+/// in the order of the view. The rows are filtered and sorted like in the view. Values which
+/// spreadsheets would evaluate as formulas are escaped, and dates are written in a form the
+/// import reads back.
+#[allow(clippy::too_many_arguments)]
+pub async fn export_database_csv(
+  collab_storage: &Arc<dyn CollabStore>,
+  related_access: &RelatedDatabaseAccess,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  view_id: Option<String>,
+  unsupported_field_types: &[FieldType],
+  row_access: &DatabaseRowAccess,
+) -> Result<String, AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+  let (fields, view) = {
+    let txn = db_collab.transact();
+    let view_id = view_id.unwrap_or_else(|| db_body.get_inline_view_id(&txn));
+    let view = db_body
+      .views
+      .get_view(&txn, &view_id)
+      .ok_or_else(|| AppError::RecordNotFound(format!("Database view {} not found", view_id)))?;
+    (db_body.fields.get_all_fields(&txn), view)
+  };
+
+  // the cells of the rows are keyed by the unique names of the fields
+  let field_by_id = field_by_id_name_uniq(fields);
+  let columns: Vec<&Field> = view
+    .field_orders
+    .iter()
+    .filter_map(|field_order| field_by_id.get(&field_order.id))
+    .filter(|field| field.is_primary || !is_field_hidden(&view, &field.id))
+    .filter(|field| !unsupported_field_types.contains(&FieldType::from(field.field_type)))
+    .collect();
+
+  let mut writer = csv::Writer::from_writer(vec![]);
+  writer
+    .write_record(columns.iter().map(|field| escape_csv_formula(&field.name)))
+    .map_err(|err| AppError::Internal(err.into()))?;
+  let params = QueryDatabaseRowsParams {
+    view_id: Some(view.id.clone()),
+    ..Default::default()
+  };
+  let (row_ids, _) = query_database_row_ids(
+    collab_storage,
+    related_access,
+    uid,
+    workspace_id,
+    database_id,
+    &params,
+    None,
+    unsupported_field_types,
+    row_access,
+  )
+  .await?;
+  for chunk in row_ids.chunks(MAX_QUERY_LIMIT) {
+    let mut row_by_id: HashMap<String, _> = list_database_row_details(
+      collab_storage,
+      related_access,
+      uid,
+      workspace_id,
+      database_id,
+      chunk,
+      unsupported_field_types,
+      false,
+      row_access,
+      None,
+    )
+    .await?
+    .into_iter()
+    .map(|row| (row.id.clone(), row))
+    .collect();
+    for row_id in chunk {
+      let Some(row) = row_by_id.remove(&row_id.to_string()) else {
+        continue;
+      };
+      writer
+        .write_record(columns.iter().map(|field| {
+          row
+            .cells
+            .get(&field.name)
+            .map(csv_cell_text)
+            .unwrap_or_default()
+        }))
+        .map_err(|err| AppError::Internal(err.into()))?;
+    }
+  }
+
+  let csv = writer
+    .into_inner()
+    .map_err(|err| AppError::Internal(anyhow::anyhow!("Failed to write CSV: {}", err)))?;
+  String::from_utf8(csv).map_err(|err| AppError::Internal(err.into()))
+}
+
+/// Inserts, or upserts if a `pre_hash_column` is given, a row for each CSV record. The columns
+/// are matched to the fields by name. Nothing is written if a value is invalid, or for a dry run.
+///
+/// The rows are written in several batches. The inserted rows are upserted by the import id and
+/// the number of their record, so that retrying an import which failed midway overwrites the rows
+/// it already wrote, instead of duplicating them.
+pub async fn import_database_csv(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  csv_content: &str,
+  query: ImportDatabaseCsvQuery,
+) -> Result<ImportDatabaseCsvResponse, AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_id, database_id).await?;
+  let field_by_name = field_by_name_uniq(db_body.fields.get_all_fields(&db_collab.transact()));
+
+  let mut reader = csv::ReaderBuilder::new()
+    .flexible(true)
+    .from_reader(csv_content.as_bytes());
+  let headers: Vec<String> = reader
+    .headers()
+    .map_err(|err| AppError::InvalidRequest(format!("Invalid CSV: {}", err)))?
+    .iter()
+    .map(|header| unescape_csv_formula(header.trim()).to_string())
+    .collect();
+  let pre_hash_index = match &query.pre_hash_column {
+    Some(column) => Some(
+      headers
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| AppError::InvalidRequest(format!("Column {} not found in CSV", column)))?,
+    ),
+    None => None,
+  };
+
+  let mut unmapped_columns = vec![];
+  let columns: Vec<Option<&Field>> = headers
+    .iter()
+    .enumerate()
+    .map(|(index, header)| match field_by_name.get(header) {
+      Some(field) if is_importable_field(field) => Some(field),
+      _ => {
+        if pre_hash_index != Some(index) {
+          unmapped_columns.push(header.clone());
+        }
+        None
+      },
+    })
+    .collect();
+
+  let import_id = query
+    .import_id
+    .clone()
+    .unwrap_or_else(|| format!("{:x}", Sha256::digest(csv_content.as_bytes())));
+  let mut operations = vec![];
+  let mut invalid_values = vec![];
+  let mut pre_hashes = HashSet::new();
+  for (index, record) in reader.records().enumerate() {
+    let record = record.map_err(|err| AppError::InvalidRequest(format!("Invalid CSV: {}", err)))?;
+    let row = index + 1;
+    let mut cells = HashMap::new();
+    for (column, value) in record.iter().enumerate() {
+      let field = match columns.get(column).copied().flatten() {
+        Some(field) => field,
+        None => continue,
+      };
+      // empty values leave the cells untouched
+      let value = unescape_csv_formula(value.trim());
+      if value.is_empty() {
+        continue;
+      }
+      match csv_cell_value(field, value) {
+        Ok(cell_value) => {
+          cells.insert(field.id.clone(), cell_value);
+        },
+        Err(reason) => invalid_values.push(CsvInvalidValue {
+          row,
+          column: headers[column].clone(),
+          value: value.to_string(),
+          reason,
+        }),
+      }
+    }
+
+    let operation = match pre_hash_index {
+      Some(pre_hash_index) => {
+        let pre_hash = record.get(pre_hash_index).unwrap_or_default().trim();
+        let reason = if pre_hash.is_empty() {
+          Some("Missing value to upsert the row")
+        } else if !pre_hashes.insert(pre_hash.to_string()) {
+          Some("Duplicated value to upsert the row")
+        } else {
+          None
+        };
+        if let Some(reason) = reason {
+          invalid_values.push(CsvInvalidValue {
+            row,
+            column: headers[pre_hash_index].clone(),
+            value: pre_hash.to_string(),
+            reason: reason.to_string(),
+          });
+          continue;
+        }
+        DatabaseRowOperation::Upsert(UpsertDatatabaseRow {
+          pre_hash: pre_hash.to_string(),
+          cells,
+          document: None,
+        })
+      },
+      None => DatabaseRowOperation::Upsert(UpsertDatatabaseRow {
+        pre_hash: format!("csv-import:{}:{}", import_id, row),
+        cells,
+        document: None,
+      }),
+    };
+    operations.push(operation);
+  }
+
+  if query.dry_run {
+    return Ok(ImportDatabaseCsvResponse {
+      row_ids: vec![],
+      unmapped_columns,
+      invalid_values,
+    });
+  }
+  if !invalid_values.is_empty() {
+    return Err(AppError::InvalidRequest(format!(
+      "The CSV contains {} invalid values, use a dry run to list them",
+      invalid_values.len()
+    )));
+  }
+
+  // the rows hidden from the user by the row level rule cannot be overwritten
+  let written_row_ids = written_row_ids(workspace_id, database_id, &operations);
+  enforce_write_rows(state, uid, workspace_id, database_id, &written_row_ids).await?;
+
+  let mut row_ids = Vec::with_capacity(operations.len());
+  while !operations.is_empty() {
+    let remaining = operations.split_off(operations.len().min(MAX_DATABASE_ROW_BATCH_SIZE));
+    let batch = std::mem::replace(&mut operations, remaining);
+    row_ids.extend(batch_database_rows(state, workspace_id, database_id, uid, batch).await?);
+  }
+  Ok(ImportDatabaseCsvResponse {
+    row_ids,
+    unmapped_columns,
+    invalid_values,
+  })
+}
+
+fn is_importable_field(field: &Field) -> bool {
+  computed_field(field).is_none()
+    && matches!(
+      FieldType::from(field.field_type),
+      FieldType::RichText
+        | FieldType::URL
+        | FieldType::Number
+        | FieldType::Checkbox
+        | FieldType::SingleSelect
+        | FieldType::MultiSelect
+        | FieldType::DateTime
+    )
+}
+
+/// Renders the JSON value of a cell as a CSV value.
+fn csv_cell_text(value: &serde_json::Value) -> String {
+  escape_csv_formula(&cell_text(value))
+}
+
+/// Prefixes the values, which spreadsheets would evaluate as formulas, with a quote.
+fn escape_csv_formula(value: &str) -> String {
+  if value.starts_with(CSV_FORMULA_PREFIXES) {
+    format!("'{}", value)
+  } else {
+    value.to_string()
+  }
+}
+
+/// Reverts [escape_csv_formula], so that the exported values are imported unchanged.
+fn unescape_csv_formula(value: &str) -> &str {
+  match value.strip_prefix('\'') {
+    Some(unescaped) if unescaped.starts_with(CSV_FORMULA_PREFIXES) => unescaped,
+    _ => value,
+  }
+}
+
+/// Renders the JSON value of a cell as text: select options by name, dates as RFC 3339 date
+/// times, date ranges as ISO 8601 intervals, and objects with a name, such as persons, by their
+/// name.
+fn cell_text(value: &serde_json::Value) -> String {
+  match value {
+    serde_json::Value::Null => String::new(),
+    serde_json::Value::Bool(checked) => if *checked { "Yes" } else { "No" }.to_string(),
+    serde_json::Value::String(text) => text.clone(),
+    serde_json::Value::Array(values) => values.iter().map(cell_text).collect::<Vec<_>>().join(", "),
+    serde_json::Value::Object(object) => {
+      if let Some(serde_json::Value::String(start)) = object.get("start") {
+        match object.get("end") {
+          Some(serde_json::Value::String(end)) => format!("{}/{}", start, end),
+          _ => start.clone(),
+        }
+      } else if let Some(serde_json::Value::String(start)) = object.get("pretty_start_datetime") {
+        match object.get("pretty_end_datetime") {
+          Some(serde_json::Value::String(end)) => format!("{} - {}", start, end),
+          _ => start.clone(),
+        }
+      } else if let Some(serde_json::Value::String(name)) = object.get("name") {
+        name.clone()
+      } else {
+        value.to_string()
+      }
+    },
+    value => value.to_string(),
+  }
+}
+
+/// Converts the CSV value to the JSON value written to the cell of the field, or returns the
+/// reason why the value is invalid.
+fn csv_cell_value(field: &Field, value: &str) -> Result<serde_json::Value, String> {
+  match FieldType::from(field.field_type) {
+    FieldType::Number => {
+      if let Ok(number) = value.parse::<i64>() {
+        Ok(json!(number))
+      } else {
+        value
+          .parse::<f64>()
+          .ok()
+          .filter(|number| number.is_finite())
+          .map(|number| json!(number))
+          .ok_or_else(|| "Not a number".to_string())
+      }
+    },
+    FieldType::Checkbox => match value.to_lowercase().as_str() {
+      "yes" | "true" | "1" | "checked" => Ok(json!(true)),
+      "no" | "false" | "0" | "unchecked" => Ok(json!(false)),
+      _ => Err("Not a checkbox value, expected yes or no".to_string()),
+    },
+    FieldType::SingleSelect => {
+      select_option_ids(field, &[value.to_string()]).map_err(|err| err.to_string())?;
+      Ok(json!(value))
+    },
+    FieldType::MultiSelect => {
+      let options: Vec<String> = value
+        .split(',')
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect();
+      select_option_ids(field, &options).map_err(|err| err.to_string())?;
+      Ok(json!(options))
+    },
+    FieldType::DateTime => parse_timestamp(value)
+      .map(|timestamp| json!(timestamp))
+      .ok_or_else(|| {
+        "Not a date, expected a timestamp, an RFC 3339 date time or YYYY-MM-DD".to_string()
+      }),
+    _ => Ok(json!(value)),
+  }
+}
+
+/// Parses a date, or the start of an ISO 8601 interval as exported for date ranges.
+fn parse_timestamp(value: &str) -> Option<i64> {
+  let value = match value.split_once('/') {
+    Some((start, _)) => start,
+    None => value,
+  };
+  if let Ok(timestamp) = value.parse::<i64>() {
+    return Some(timestamp);
+  }
+  if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+    return Some(date_time.timestamp());
+  }
+  if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+    return Some(date_time.and_utc().timestamp());
+  }
+  NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .ok()
+    .and_then(|date| date.and_hms_opt(0, 0, 0))
+    .map(|date_time| date_time.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn formula_values_are_escaped_and_imported_unchanged() {
+    for value in ["=1+1", "+1", "-1", "@SUM(A1)", "\tvalue", "\rvalue"] {
+      let exported = csv_cell_text(&json!(value));
+      assert_eq!(exported, format!("'{}", value));
+      assert_eq!(unescape_csv_formula(&exported), value);
+    }
+    assert_eq!(csv_cell_text(&json!("plain")), "plain");
+    assert_eq!(unescape_csv_formula("'quoted"), "'quoted");
+  }
+
+  #[test]
+  fn exported_dates_are_imported() {
+    let date = json!({
+      "start": "2024-03-01T10:00:00+00:00",
+      "pretty_start_datetime": "Mar 1, 2024 10:00",
+    });
+    let exported = csv_cell_text(&date);
+    assert_eq!(exported, "2024-03-01T10:00:00+00:00");
+    assert_eq!(parse_timestamp(&exported), Some(1709287200));
+
+    let range = json!({
+      "start": "2024-03-01T10:00:00+00:00",
+      "end": "2024-03-02T10:00:00+00:00",
+    });
+    let exported = csv_cell_text(&range);
+    assert_eq!(
+      exported,
+      "2024-03-01T10:00:00+00:00/2024-03-02T10:00:00+00:00"
+    );
+    assert_eq!(parse_timestamp(&exported), Some(1709287200));
+  }
+}
@@ -0,0 +1,581 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use app_error::AppError;
+use collab_database::database::{gen_database_filter_id, gen_database_sort_id, timestamp};
+use collab_database::entity::FieldType;
+use collab_database::fields::Field;
+use collab_database::views::{
+  BoardLayoutSetting, CalendarLayoutSetting, DatabaseLayout, DatabaseView, GroupSetting,
+};
+use collab_entity::CollabType;
+use collab_folder::CollabOrigin;
+use database::collab::CollabStore;
+use serde_json::json;
+use shared_entity::dto::workspace_dto::{
+  AFCalendarViewSettings, AFDatabaseView, AFUpdateCalendarViewSettings, AFUpdateDatabaseView,
+  DatabaseRowFilter, DatabaseRowFilterCondition, DatabaseRowSort,
+};
+use uuid::Uuid;
+use yrs::Any;
+
+use super::database::group_setting_for_field;
+use super::ops::update_all_database_views;
+use super::row_query::{any_to_i64, any_to_string, saved_filter_condition, select_option_ids};
+use super::utils::get_latest_collab_database_body;
+use crate::state::AppState;
+
+/// Key of the visibility in the field settings of a view.
+const FIELD_VISIBILITY: &str = "visibility";
+const FIELD_VISIBILITY_ALWAYS_SHOWN: i64 = 0;
+const FIELD_VISIBILITY_ALWAYS_HIDDEN: i64 = 2;
+
+/// Filter types of the filters saved by the AppFlowy clients: a condition on a field, or a group
+/// of filters which must all match.
+const SAVED_DATA_FILTER_TYPE: i64 = 0;
+const SAVED_AND_FILTER_TYPE: i64 = 1;
+const SAVED_SORT_ASCENDING: i64 = 0;
+const SAVED_SORT_DESCENDING: i64 = 1;
+
+pub async fn list_database_views(
+  collab_storage: &Arc<dyn CollabStore>,
+  workspace_id: Uuid,
+  database_id: Uuid,
+) -> Result<Vec<AFDatabaseView>, AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+  let txn = db_collab.transact();
+  let inline_view_id = db_body.get_inline_view_id(&txn);
+  let fields = db_body.fields.get_all_fields(&txn);
+  Ok(
+    db_body
+      .views
+      .get_all_views(&txn)
+      .iter()
+      .map(|view| database_view_to_dto(view, &fields, &inline_view_id))
+      .collect(),
+  )
+}
+
+pub async fn get_database_view(
+  collab_storage: &Arc<dyn CollabStore>,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  view_id: &str,
+) -> Result<AFDatabaseView, AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+  let txn = db_collab.transact();
+  let view = db_body.views.get_view(&txn, view_id).ok_or_else(|| {
+    AppError::RecordNotFound(format!(
+      "View {} not found in database {}",
+      view_id, database_id
+    ))
+  })?;
+  Ok(database_view_to_dto(
+    &view,
+    &db_body.fields.get_all_fields(&txn),
+    &db_body.get_inline_view_id(&txn),
+  ))
+}
+
+/// Replaces the settings of the view which are set in `update`, and returns the updated view.
+pub async fn update_database_view(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  view_id: &str,
+  update: AFUpdateDatabaseView,
+) -> Result<AFDatabaseView, AppError> {
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_id, database_id).await?;
+  let (view, fields, inline_view_id) = {
+    let txn = db_collab.transact();
+    let view = db_body.views.get_view(&txn, view_id).ok_or_else(|| {
+      AppError::RecordNotFound(format!(
+        "View {} not found in database {}",
+        view_id, database_id
+      ))
+    })?;
+    (
+      view,
+      db_body.fields.get_all_fields(&txn),
+      db_body.get_inline_view_id(&txn),
+    )
+  };
+
+  // Everything is validated before the view is written.
+  let hidden_field_ids = match &update.hidden_fields {
+    Some(hidden_fields) => {
+      let mut hidden_field_ids = HashSet::with_capacity(hidden_fields.len());
+      for id_or_name in hidden_fields {
+        let field = find_field(&fields, id_or_name)?;
+        if field.is_primary {
+          return Err(AppError::InvalidRequest(
+            "The primary field cannot be hidden".to_string(),
+          ));
+        }
+        hidden_field_ids.insert(field.id.clone());
+      }
+      Some(hidden_field_ids)
+    },
+    None => None,
+  };
+  let filters = match &update.filters {
+    Some(filters) => Some(saved_filters(&fields, filters)?),
+    None => None,
+  };
+  let sorts = match &update.sorts {
+    Some(sorts) => Some(
+      sorts
+        .iter()
+        .map(|sort| saved_sort(&fields, sort))
+        .collect::<Result<Vec<_>, _>>()?,
+    ),
+    None => None,
+  };
+  let layout = match update.layout.as_deref() {
+    Some(layout) => Some(database_layout(layout)?),
+    None => None,
+  };
+  let new_layout = layout.unwrap_or(view.layout);
+  let group_settings = match update.group_by_field.as_deref() {
+    Some("") => Some(vec![]),
+    Some(id_or_name) => Some(vec![group_setting_for_field(find_field(
+      &fields, id_or_name,
+    )?)?]),
+    None => None,
+  };
+  if new_layout == DatabaseLayout::Board
+    && (layout.is_some() || group_settings.is_some())
+    && group_settings
+      .as_ref()
+      .unwrap_or(&view.group_settings)
+      .is_empty()
+  {
+    return Err(AppError::InvalidRequest(
+      "The rows of a board must be grouped by a field".to_string(),
+    ));
+  }
+  let calendar_setting = if layout == Some(DatabaseLayout::Calendar) || update.calendar.is_some() {
+    Some(calendar_setting(
+      &view,
+      &fields,
+      update.calendar.clone().unwrap_or_default(),
+    )?)
+  } else {
+    None
+  };
+
+  let db_collab_update = {
+    let mut txn = db_collab.transact_mut();
+    update_all_database_views(&mut txn, &db_body, |db_view| {
+      if db_view.id != view_id {
+        return;
+      }
+      if let Some(name) = &update.name {
+        db_view.name.clone_from(name);
+      }
+      if let Some(hidden_field_ids) = &hidden_field_ids {
+        for field in &fields {
+          let hidden = hidden_field_ids.contains(&field.id);
+          if !hidden && !is_field_hidden(db_view, &field.id) {
+            continue;
+          }
+          let visibility = if hidden {
+            FIELD_VISIBILITY_ALWAYS_HIDDEN
+          } else {
+            FIELD_VISIBILITY_ALWAYS_SHOWN
+          };
+          db_view
+            .field_settings
+            .entry(field.id.clone())
+            .or_default()
+            .insert(FIELD_VISIBILITY.to_string(), Any::BigInt(visibility));
+        }
+      }
+      if let Some(filters) = &filters {
+        db_view.filters.clone_from(filters);
+      }
+      if let Some(sorts) = &sorts {
+        db_view.sorts.clone_from(sorts);
+      }
+      if let Some(group_settings) = &group_settings {
+        db_view.group_settings.clone_from(group_settings);
+      }
+      if let Some(layout) = layout {
+        db_view.layout = layout;
+        if layout == DatabaseLayout::Board {
+          db_view
+            .layout_settings
+            .entry(layout)
+            .or_insert_with(|| BoardLayoutSetting::new().into());
+        }
+      }
+      if let Some(calendar_setting) = &calendar_setting {
+        db_view
+          .layout_settings
+          .insert(DatabaseLayout::Calendar, calendar_setting.clone().into());
+      }
+      db_view.modified_at = timestamp();
+    });
+    txn.encode_update_v1()
+  };
+
+  state
+    .ws_server
+    .publish_update(
+      workspace_id,
+      database_id,
+      CollabType::Database,
+      &CollabOrigin::Server,
+      db_collab_update,
+    )
+    .await?;
+
+  // The folder shows the view with the icon of its layout
+  if let Some(layout) = layout.filter(|layout| *layout != view.layout) {
+    let folder_layout = match layout {
+      DatabaseLayout::Grid => collab_folder::ViewLayout::Grid,
+      DatabaseLayout::Board => collab_folder::ViewLayout::Board,
+      DatabaseLayout::Calendar => collab_folder::ViewLayout::Calendar,
+    };
+    let mut folder = state.ws_server.get_folder(workspace_id).await?;
+    let folder_update = {
+      let mut txn = folder.collab.transact_mut();
+      folder.body.views.update_view(
+        &mut txn,
+        view_id,
+        |view_update| view_update.set_layout(folder_layout).done(),
+        uid,
+      );
+      txn.encode_update_v1()
+    };
+    state
+      .ws_server
+      .publish_update(
+        workspace_id,
+        workspace_id,
+        CollabType::Folder,
+        &CollabOrigin::Server,
+        folder_update,
+      )
+      .await?;
+  }
+
+  let view = db_body
+    .views
+    .get_view(&db_collab.transact(), view_id)
+    .ok_or_else(|| AppError::Internal(anyhow::anyhow!("View {} not found", view_id)))?;
+  Ok(database_view_to_dto(&view, &fields, &inline_view_id))
+}
+
+fn database_layout(layout: &str) -> Result<DatabaseLayout, AppError> {
+  match layout.to_lowercase().as_str() {
+    "grid" => Ok(DatabaseLayout::Grid),
+    "board" => Ok(DatabaseLayout::Board),
+    "calendar" => Ok(DatabaseLayout::Calendar),
+    _ => Err(AppError::InvalidRequest(format!(
+      "Unknown layout {}, expected Grid, Board or Calendar",
+      layout
+    ))),
+  }
+}
+
+/// Returns the calendar setting of the view with the changes of `update`. A view which has not
+/// been a calendar yet gets the first date field of the database.
+fn calendar_setting(
+  view: &DatabaseView,
+  fields: &[Field],
+  update: AFUpdateCalendarViewSettings,
+) -> Result<CalendarLayoutSetting, AppError> {
+  let is_date_field = |field: &Field| FieldType::from(field.field_type) == FieldType::DateTime;
+  let current = view
+    .layout_settings
+    .get(&DatabaseLayout::Calendar)
+    .cloned()
+    .map(CalendarLayoutSetting::from);
+  let date_field = match &update.date_field {
+    Some(id_or_name) => {
+      let field = find_field(fields, id_or_name)?;
+      if !is_date_field(field) {
+        return Err(AppError::InvalidRequest(format!(
+          "Field {} is not a date field",
+          field.name
+        )));
+      }
+      field
+    },
+    None => current
+      .as_ref()
+      .and_then(|current| fields.iter().find(|field| field.id == current.field_id))
+      .or_else(|| fields.iter().find(|field| is_date_field(field)))
+      .ok_or_else(|| {
+        AppError::InvalidRequest("A calendar needs a date field, but there is none".to_string())
+      })?,
+  };
+  let mut setting = current.unwrap_or_else(|| CalendarLayoutSetting::new(date_field.id.clone()));
+  setting.field_id.clone_from(&date_field.id);
+  if let Some(first_day_of_week) = update.first_day_of_week {
+    if !(0..7).contains(&first_day_of_week) {
+      return Err(AppError::InvalidRequest(format!(
+        "Invalid first day of week {}, expected 0 (Sunday) to 6 (Saturday)",
+        first_day_of_week
+      )));
+    }
+    setting.first_day_of_week = first_day_of_week;
+  }
+  if let Some(show_weekends) = update.show_weekends {
+    setting.show_weekends = show_weekends;
+  }
+  if let Some(show_week_numbers) = update.show_week_numbers {
+    setting.show_week_numbers = show_week_numbers;
+  }
+  Ok(setting)
+}
+
+pub fn is_field_hidden(view: &DatabaseView, field_id: &str) -> bool {
+  view
+    .field_settings
+    .get(field_id)
+    .and_then(|settings| settings.get(FIELD_VISIBILITY))
+    .and_then(any_to_i64)
+    == Some(FIELD_VISIBILITY_ALWAYS_HIDDEN)
+}
+
+fn database_view_to_dto(
+  view: &DatabaseView,
+  fields: &[Field],
+  inline_view_id: &str,
+) -> AFDatabaseView {
+  let field_by_id: HashMap<&str, &Field> = fields
+    .iter()
+    .map(|field| (field.id.as_str(), field))
+    .collect();
+  let field_order = view
+    .field_orders
+    .iter()
+    .filter(|order| field_by_id.contains_key(order.id.as_str()))
+    .map(|order| order.id.clone())
+    .collect::<Vec<_>>();
+  let hidden_fields = field_order
+    .iter()
+    .filter(|field_id| is_field_hidden(view, field_id))
+    .cloned()
+    .collect();
+
+  let mut filters = vec![];
+  for filter in &view.filters {
+    match filter.get("filter_type").and_then(any_to_i64) {
+      // The AppFlowy clients save the filters as the children of a single "and" filter
+      Some(SAVED_AND_FILTER_TYPE) => {
+        if let Some(Any::Array(children)) = filter.get("children") {
+          filters.extend(children.iter().filter_map(|child| match child {
+            Any::Map(child) => filter_from_saved(&field_by_id, child),
+            _ => None,
+          }));
+        }
+      },
+      _ => filters.extend(filter_from_saved(&field_by_id, filter)),
+    }
+  }
+  let sorts = view
+    .sorts
+    .iter()
+    .filter_map(|sort| {
+      let field_id = sort.get("field_id").and_then(any_to_string)?;
+      field_by_id.get(field_id.as_str())?;
+      Some(DatabaseRowSort {
+        field: field_id,
+        descending: sort.get("condition").and_then(any_to_i64) == Some(SAVED_SORT_DESCENDING),
+      })
+    })
+    .collect();
+  let group_by_field = view
+    .group_settings
+    .first()
+    .and_then(|group_setting| GroupSetting::try_from(group_setting.clone()).ok())
+    .map(|group_setting| group_setting.field_id);
+  let calendar = view
+    .layout_settings
+    .get(&DatabaseLayout::Calendar)
+    .cloned()
+    .map(CalendarLayoutSetting::from)
+    .map(|setting| AFCalendarViewSettings {
+      date_field: setting.field_id,
+      first_day_of_week: setting.first_day_of_week,
+      show_weekends: setting.show_weekends,
+      show_week_numbers: setting.show_week_numbers,
+    });
+
+  AFDatabaseView {
+    id: view.id.clone(),
+    name: view.name.clone(),
+    layout: format!("{:?}", view.layout),
+    is_inline: view.id == inline_view_id,
+    field_order,
+    hidden_fields,
+    filters,
+    sorts,
+    group_by_field,
+    calendar,
+  }
+}
+
+/// Converts a filter saved by the AppFlowy clients, when it can be expressed as a
+/// [DatabaseRowFilter]. The conditions are the ones read by the row queries.
+fn filter_from_saved(
+  field_by_id: &HashMap<&str, &Field>,
+  filter: &HashMap<String, Any>,
+) -> Option<DatabaseRowFilter> {
+  if filter.contains_key("children") {
+    return None;
+  }
+  let field = field_by_id.get(filter.get("field_id").and_then(any_to_string)?.as_str())?;
+  let condition_type = filter.get("condition").and_then(any_to_i64)?;
+  let content = filter
+    .get("content")
+    .and_then(any_to_string)
+    .unwrap_or_default();
+  Some(DatabaseRowFilter {
+    field: field.id.clone(),
+    condition: saved_filter_condition(field, condition_type, &content)?,
+  })
+}
+
+/// Converts the filters to the format of the AppFlowy clients: the children of a single "and"
+/// filter. Number ranges with distinct bounds are saved as two filters, and date ranges are
+/// saved with the precision of a day.
+fn saved_filters(
+  fields: &[Field],
+  filters: &[DatabaseRowFilter],
+) -> Result<Vec<HashMap<String, Any>>, AppError> {
+  if filters.is_empty() {
+    return Ok(vec![]);
+  }
+  let mut children = vec![];
+  for filter in filters {
+    let field = find_field(fields, &filter.field)?;
+    for (condition, content) in saved_conditions(field, &filter.condition)? {
+      children.push(Any::Map(Arc::new(HashMap::from([
+        ("id".to_string(), Any::from(gen_database_filter_id())),
+        (
+          "filter_type".to_string(),
+          Any::BigInt(SAVED_DATA_FILTER_TYPE),
+        ),
+        ("field_id".to_string(), Any::from(field.id.clone())),
+        ("ty".to_string(), Any::BigInt(field.field_type)),
+        ("condition".to_string(), Any::BigInt(condition)),
+        ("content".to_string(), Any::from(content)),
+      ]))));
+    }
+  }
+  Ok(vec![HashMap::from([
+    ("id".to_string(), Any::from(gen_database_filter_id())),
+    (
+      "filter_type".to_string(),
+      Any::BigInt(SAVED_AND_FILTER_TYPE),
+    ),
+    ("children".to_string(), Any::Array(children.into())),
+  ])])
+}
+
+/// Returns the condition types and contents of the saved filters, which match like the filter.
+fn saved_conditions(
+  field: &Field,
+  condition: &DatabaseRowFilterCondition,
+) -> Result<Vec<(i64, String)>, AppError> {
+  let field_type = FieldType::from(field.field_type);
+  let conditions = match (&field_type, condition) {
+    (
+      FieldType::RichText | FieldType::URL | FieldType::Summary | FieldType::Translate,
+      condition,
+    ) => match condition {
+      DatabaseRowFilterCondition::TextIs { value } => vec![(0, value.clone())],
+      DatabaseRowFilterCondition::TextContains { value } => vec![(2, value.clone())],
+      DatabaseRowFilterCondition::IsEmpty => vec![(6, String::new())],
+      DatabaseRowFilterCondition::IsNotEmpty => vec![(7, String::new())],
+      _ => vec![],
+    },
+    (FieldType::Number, condition) => match condition {
+      DatabaseRowFilterCondition::NumberRange { min, max } => match (min, max) {
+        (Some(min), Some(max)) if min == max => vec![(0, min.to_string())],
+        (min, max) => min
+          .map(|min| (4, min.to_string()))
+          .into_iter()
+          .chain(max.map(|max| (5, max.to_string())))
+          .collect(),
+      },
+      DatabaseRowFilterCondition::IsEmpty => vec![(6, String::new())],
+      DatabaseRowFilterCondition::IsNotEmpty => vec![(7, String::new())],
+      _ => vec![],
+    },
+    (FieldType::Checkbox, DatabaseRowFilterCondition::Checkbox { checked }) => {
+      vec![(if *checked { 0 } else { 1 }, String::new())]
+    },
+    (FieldType::SingleSelect | FieldType::MultiSelect, condition) => {
+      // Multi selects use the "contains" conditions, which match any of the options
+      let offset = if field_type == FieldType::MultiSelect {
+        2
+      } else {
+        0
+      };
+      match condition {
+        DatabaseRowFilterCondition::SelectOptionIs { options } => {
+          vec![(offset, select_option_ids(field, options)?.join(","))]
+        },
+        DatabaseRowFilterCondition::SelectOptionIsNot { options } => {
+          vec![(offset + 1, select_option_ids(field, options)?.join(","))]
+        },
+        DatabaseRowFilterCondition::IsEmpty => vec![(4, String::new())],
+        DatabaseRowFilterCondition::IsNotEmpty => vec![(5, String::new())],
+        _ => vec![],
+      }
+    },
+    (FieldType::DateTime | FieldType::CreatedTime | FieldType::LastEditedTime, condition) => {
+      match condition {
+        DatabaseRowFilterCondition::DateRange { start, end } => match (start, end) {
+          (Some(start), Some(end)) => vec![(4, json!({ "start": start, "end": end }).to_string())],
+          (Some(start), None) => vec![(3, json!({ "timestamp": start }).to_string())],
+          (None, Some(end)) => vec![(2, json!({ "timestamp": end }).to_string())],
+          (None, None) => vec![],
+        },
+        DatabaseRowFilterCondition::IsEmpty => vec![(5, String::new())],
+        DatabaseRowFilterCondition::IsNotEmpty => vec![(6, String::new())],
+        _ => vec![],
+      }
+    },
+    _ => vec![],
+  };
+  if conditions.is_empty() {
+    return Err(AppError::InvalidRequest(format!(
+      "The filter {:?} cannot be saved on field {} of type {:?}",
+      condition, field.name, field_type
+    )));
+  }
+  Ok(conditions)
+}
+
+fn saved_sort(fields: &[Field], sort: &DatabaseRowSort) -> Result<HashMap<String, Any>, AppError> {
+  let field = find_field(fields, &sort.field)?;
+  let condition = if sort.descending {
+    SAVED_SORT_DESCENDING
+  } else {
+    SAVED_SORT_ASCENDING
+  };
+  Ok(HashMap::from([
+    ("id".to_string(), Any::from(gen_database_sort_id())),
+    ("field_id".to_string(), Any::from(field.id.clone())),
+    ("condition".to_string(), Any::BigInt(condition)),
+  ]))
+}
+
+/// Finds a field by id, or by name.
+pub fn find_field<'a>(fields: &'a [Field], id_or_name: &str) -> Result<&'a Field, AppError> {
+  fields
+    .iter()
+    .find(|field| field.id == id_or_name)
+    .or_else(|| fields.iter().find(|field| field.name == id_or_name))
+    .ok_or_else(|| AppError::InvalidRequest(format!("Field {} not found", id_or_name)))
+}
@@ -0,0 +1,1002 @@
+use app_error::AppError;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+
+use super::utils::SECONDS_PER_DAY;
+
+/// Maximum length of the expression of a formula field.
+pub const MAX_FORMULA_LENGTH: usize = 4096;
+/// Maximum nesting of parentheses and function calls, which keeps the recursive parser and
+/// evaluator within the stack.
+const MAX_FORMULA_DEPTH: usize = 64;
+/// Maximum length in bytes of a text built by a formula, as nested text functions can grow it
+/// exponentially.
+const MAX_FORMULA_TEXT_LENGTH: usize = 64 * 1024;
+
+/// Value of a formula, or of a cell referenced by a formula.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaValue {
+  Null,
+  Number(f64),
+  Text(String),
+  Bool(bool),
+  /// Unix timestamp in seconds.
+  Date(i64),
+}
+
+impl FormulaValue {
+  pub fn is_truthy(&self) -> bool {
+    match self {
+      FormulaValue::Null => false,
+      FormulaValue::Number(number) => *number != 0.0,
+      FormulaValue::Text(text) => !text.is_empty(),
+      FormulaValue::Bool(value) => *value,
+      FormulaValue::Date(_) => true,
+    }
+  }
+
+  pub fn to_text(&self) -> String {
+    match self {
+      FormulaValue::Null => String::new(),
+      FormulaValue::Number(number) => format_number(*number),
+      FormulaValue::Text(text) => text.clone(),
+      FormulaValue::Bool(value) => value.to_string(),
+      FormulaValue::Date(timestamp) => DateTime::from_timestamp(*timestamp, 0)
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_default(),
+    }
+  }
+
+  pub fn to_number(&self) -> Option<f64> {
+    match self {
+      FormulaValue::Number(number) => Some(*number),
+      FormulaValue::Text(text) => text.trim().parse().ok(),
+      FormulaValue::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+      FormulaValue::Date(timestamp) => Some(*timestamp as f64),
+      FormulaValue::Null => None,
+    }
+  }
+
+  pub fn to_timestamp(&self) -> Option<i64> {
+    match self {
+      FormulaValue::Date(timestamp) => Some(*timestamp),
+      FormulaValue::Number(number) => Some(*number as i64),
+      FormulaValue::Text(text) => parse_date(text),
+      _ => None,
+    }
+  }
+}
+
+fn format_number(number: f64) -> String {
+  if number.fract() == 0.0 && number.abs() < 1e15 {
+    format!("{}", number as i64)
+  } else {
+    number.to_string()
+  }
+}
+
+/// Parses RFC 3339 dates, `YYYY-MM-DD` dates and unix timestamps in seconds.
+fn parse_date(text: &str) -> Option<i64> {
+  let text = text.trim();
+  if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+    return Some(date.timestamp());
+  }
+  if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+    return Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp());
+  }
+  text.parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+  Literal(FormulaValue),
+  /// Id or name of a field.
+  Field(String),
+  Unary(UnaryOp, Box<Expr>),
+  Binary(BinaryOp, Box<Expr>, Box<Expr>),
+  Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+  Neg,
+  Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+  Concat,
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  And,
+  Or,
+}
+
+impl Expr {
+  /// Returns the fields referenced by the expression.
+  pub fn field_refs(&self) -> Vec<&str> {
+    let mut refs = vec![];
+    self.collect_field_refs(&mut refs);
+    refs
+  }
+
+  fn collect_field_refs<'a>(&'a self, refs: &mut Vec<&'a str>) {
+    match self {
+      Expr::Literal(_) => {},
+      Expr::Field(field) => refs.push(field.as_str()),
+      Expr::Unary(_, expr) => expr.collect_field_refs(refs),
+      Expr::Binary(_, lhs, rhs) => {
+        lhs.collect_field_refs(refs);
+        rhs.collect_field_refs(refs);
+      },
+      Expr::Call(_, args) => args.iter().for_each(|arg| arg.collect_field_refs(refs)),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Number(f64),
+  Text(String),
+  Ident(String),
+  Field(String),
+  Op(&'static str),
+  LParen,
+  RParen,
+  Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+  let chars: Vec<char> = expression.chars().collect();
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    match c {
+      c if c.is_whitespace() => i += 1,
+      '(' => {
+        tokens.push(Token::LParen);
+        i += 1;
+      },
+      ')' => {
+        tokens.push(Token::RParen);
+        i += 1;
+      },
+      ',' => {
+        tokens.push(Token::Comma);
+        i += 1;
+      },
+      '{' => {
+        let end = chars[i + 1..]
+          .iter()
+          .position(|c| *c == '}')
+          .ok_or("Unterminated field reference")?;
+        let field: String = chars[i + 1..i + 1 + end].iter().collect();
+        if field.trim().is_empty() {
+          return Err("Empty field reference".to_string());
+        }
+        tokens.push(Token::Field(field.trim().to_string()));
+        i += end + 2;
+      },
+      '"' | '\'' => {
+        let mut text = String::new();
+        i += 1;
+        loop {
+          match chars.get(i) {
+            None => return Err("Unterminated string".to_string()),
+            Some('\\') => {
+              let escaped = chars.get(i + 1).ok_or("Unterminated string")?;
+              text.push(match escaped {
+                'n' => '\n',
+                't' => '\t',
+                escaped => *escaped,
+              });
+              i += 2;
+            },
+            Some(quote) if *quote == c => {
+              i += 1;
+              break;
+            },
+            Some(other) => {
+              text.push(*other);
+              i += 1;
+            },
+          }
+        }
+        tokens.push(Token::Text(text));
+      },
+      c if c.is_ascii_digit() || c == '.' => {
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+          i += 1;
+        }
+        let number: String = chars[start..i].iter().collect();
+        let number = number
+          .parse()
+          .map_err(|_| format!("Invalid number: {}", number))?;
+        tokens.push(Token::Number(number));
+      },
+      c if c.is_alphabetic() || c == '_' => {
+        let start = i;
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+          i += 1;
+        }
+        tokens.push(Token::Ident(chars[start..i].iter().collect()));
+      },
+      _ => {
+        let next = chars.get(i + 1).copied();
+        let (op, len) = match (c, next) {
+          ('&', Some('&')) => ("&&", 2),
+          ('|', Some('|')) => ("||", 2),
+          ('=', Some('=')) => ("=", 2),
+          ('!', Some('=')) => ("!=", 2),
+          ('<', Some('>')) => ("!=", 2),
+          ('<', Some('=')) => ("<=", 2),
+          ('>', Some('=')) => (">=", 2),
+          ('+', _) => ("+", 1),
+          ('-', _) => ("-", 1),
+          ('*', _) => ("*", 1),
+          ('/', _) => ("/", 1),
+          ('%', _) => ("%", 1),
+          ('&', _) => ("&", 1),
+          ('=', _) => ("=", 1),
+          ('!', _) => ("!", 1),
+          ('<', _) => ("<", 1),
+          ('>', _) => (">", 1),
+          _ => return Err(format!("Unexpected character: {}", c)),
+        };
+        tokens.push(Token::Op(op));
+        i += len;
+      },
+    }
+  }
+  Ok(tokens)
+}
+
+/// Parses the expression of a formula field.
+pub fn parse_formula(expression: &str) -> Result<Expr, AppError> {
+  if expression.len() > MAX_FORMULA_LENGTH {
+    return Err(AppError::InvalidRequest(format!(
+      "Formula must not be longer than {} characters",
+      MAX_FORMULA_LENGTH
+    )));
+  }
+  let tokens = tokenize(expression)
+    .map_err(|err| AppError::InvalidRequest(format!("Invalid formula: {}", err)))?;
+  let mut parser = Parser {
+    tokens,
+    pos: 0,
+    depth: 0,
+  };
+  let expr = parser
+    .parse_expr()
+    .and_then(|expr| match parser.peek() {
+      None => Ok(expr),
+      Some(token) => Err(format!("Unexpected token: {:?}", token)),
+    })
+    .map_err(|err| AppError::InvalidRequest(format!("Invalid formula: {}", err)))?;
+  Ok(expr)
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  pos: usize,
+  depth: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.pos).cloned();
+    self.pos += 1;
+    token
+  }
+
+  fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+    match self.peek() {
+      Some(Token::Op(op)) if ops.contains(op) => {
+        let op = *op;
+        self.pos += 1;
+        Some(op)
+      },
+      _ => None,
+    }
+  }
+
+  fn parse_expr(&mut self) -> Result<Expr, String> {
+    self.depth += 1;
+    if self.depth > MAX_FORMULA_DEPTH {
+      return Err("Formula is nested too deeply".to_string());
+    }
+    let expr = self.parse_or();
+    self.depth -= 1;
+    expr
+  }
+
+  fn parse_or(&mut self) -> Result<Expr, String> {
+    let mut lhs = self.parse_and()?;
+    while self.eat_op(&["||"]).is_some() {
+      lhs = Expr::Binary(BinaryOp::Or, Box::new(lhs), Box::new(self.parse_and()?));
+    }
+    Ok(lhs)
+  }
+
+  fn parse_and(&mut self) -> Result<Expr, String> {
+    let mut lhs = self.parse_comparison()?;
+    while self.eat_op(&["&&"]).is_some() {
+      lhs = Expr::Binary(
+        BinaryOp::And,
+        Box::new(lhs),
+        Box::new(self.parse_comparison()?),
+      );
+    }
+    Ok(lhs)
+  }
+
+  fn parse_comparison(&mut self) -> Result<Expr, String> {
+    let lhs = self.parse_additive()?;
+    let op = match self.eat_op(&["=", "!=", "<", "<=", ">", ">="]) {
+      Some("=") => BinaryOp::Eq,
+      Some("!=") => BinaryOp::Ne,
+      Some("<") => BinaryOp::Lt,
+      Some("<=") => BinaryOp::Le,
+      Some(">") => BinaryOp::Gt,
+      Some(">=") => BinaryOp::Ge,
+      _ => return Ok(lhs),
+    };
+    Ok(Expr::Binary(
+      op,
+      Box::new(lhs),
+      Box::new(self.parse_additive()?),
+    ))
+  }
+
+  fn parse_additive(&mut self) -> Result<Expr, String> {
+    let mut lhs = self.parse_multiplicative()?;
+    while let Some(op) = self.eat_op(&["+", "-", "&"]) {
+      let op = match op {
+        "+" => BinaryOp::Add,
+        "-" => BinaryOp::Sub,
+        _ => BinaryOp::Concat,
+      };
+      lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.parse_multiplicative()?));
+    }
+    Ok(lhs)
+  }
+
+  fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+    let mut lhs = self.parse_unary()?;
+    while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+      let op = match op {
+        "*" => BinaryOp::Mul,
+        "/" => BinaryOp::Div,
+        _ => BinaryOp::Rem,
+      };
+      lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.parse_unary()?));
+    }
+    Ok(lhs)
+  }
+
+  fn parse_unary(&mut self) -> Result<Expr, String> {
+    match self.eat_op(&["-", "!"]) {
+      Some(op) => {
+        let op = if op == "-" {
+          UnaryOp::Neg
+        } else {
+          UnaryOp::Not
+        };
+        self.depth += 1;
+        if self.depth > MAX_FORMULA_DEPTH {
+          return Err("Formula is nested too deeply".to_string());
+        }
+        let expr = self.parse_unary();
+        self.depth -= 1;
+        Ok(Expr::Unary(op, Box::new(expr?)))
+      },
+      None => self.parse_primary(),
+    }
+  }
+
+  fn parse_primary(&mut self) -> Result<Expr, String> {
+    match self.next() {
+      Some(Token::Number(number)) => Ok(Expr::Literal(FormulaValue::Number(number))),
+      Some(Token::Text(text)) => Ok(Expr::Literal(FormulaValue::Text(text))),
+      Some(Token::Field(field)) => Ok(Expr::Field(field)),
+      Some(Token::LParen) => {
+        let expr = self.parse_expr()?;
+        match self.next() {
+          Some(Token::RParen) => Ok(expr),
+          _ => Err("Expected )".to_string()),
+        }
+      },
+      Some(Token::Ident(ident)) => match ident.to_lowercase().as_str() {
+        "true" => Ok(Expr::Literal(FormulaValue::Bool(true))),
+        "false" => Ok(Expr::Literal(FormulaValue::Bool(false))),
+        "null" => Ok(Expr::Literal(FormulaValue::Null)),
+        name => {
+          if !FUNCTIONS.contains(&name) {
+            return Err(format!("Unknown function: {}", ident));
+          }
+          if self.next() != Some(Token::LParen) {
+            return Err(format!("Expected ( after {}", ident));
+          }
+          let mut args = vec![];
+          if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+          } else {
+            loop {
+              args.push(self.parse_expr()?);
+              match self.next() {
+                Some(Token::Comma) => {},
+                Some(Token::RParen) => break,
+                _ => return Err(format!("Expected , or ) in {}", ident)),
+              }
+            }
+          }
+          Ok(Expr::Call(name.to_string(), args))
+        },
+      },
+      Some(token) => Err(format!("Unexpected token: {:?}", token)),
+      None => Err("Unexpected end of formula".to_string()),
+    }
+  }
+}
+
+const FUNCTIONS: &[&str] = &[
+  "if",
+  "empty",
+  "not",
+  "concat",
+  "upper",
+  "lower",
+  "trim",
+  "length",
+  "contains",
+  "replace",
+  "left",
+  "right",
+  "text",
+  "number",
+  "abs",
+  "round",
+  "floor",
+  "ceil",
+  "sqrt",
+  "pow",
+  "min",
+  "max",
+  "sum",
+  "now",
+  "today",
+  "date",
+  "date_add",
+  "date_diff",
+  "year",
+  "month",
+  "day",
+  "format_date",
+];
+
+/// Evaluates the expression. `resolve_field` returns the value of the cell of a referenced
+/// field, and `now` is the unix timestamp used by `now()` and `today()`.
+pub fn evaluate_formula(
+  expr: &Expr,
+  resolve_field: &dyn Fn(&str) -> FormulaValue,
+  now: i64,
+) -> Result<FormulaValue, String> {
+  Evaluator { resolve_field, now }.eval(expr)
+}
+
+struct Evaluator<'a> {
+  resolve_field: &'a dyn Fn(&str) -> FormulaValue,
+  now: i64,
+}
+
+impl Evaluator<'_> {
+  fn eval(&self, expr: &Expr) -> Result<FormulaValue, String> {
+    match expr {
+      Expr::Literal(value) => Ok(value.clone()),
+      Expr::Field(field) => Ok((self.resolve_field)(field)),
+      Expr::Unary(UnaryOp::Neg, expr) => match self.eval(expr)? {
+        FormulaValue::Null => Ok(FormulaValue::Null),
+        value => Ok(FormulaValue::Number(-number_arg(&value)?)),
+      },
+      Expr::Unary(UnaryOp::Not, expr) => Ok(FormulaValue::Bool(!self.eval(expr)?.is_truthy())),
+      Expr::Binary(BinaryOp::And, lhs, rhs) => Ok(FormulaValue::Bool(
+        self.eval(lhs)?.is_truthy() && self.eval(rhs)?.is_truthy(),
+      )),
+      Expr::Binary(BinaryOp::Or, lhs, rhs) => Ok(FormulaValue::Bool(
+        self.eval(lhs)?.is_truthy() || self.eval(rhs)?.is_truthy(),
+      )),
+      Expr::Binary(op, lhs, rhs) => binary(*op, self.eval(lhs)?, self.eval(rhs)?),
+      Expr::Call(name, args) => self.call(name, args),
+    }
+  }
+
+  fn call(&self, name: &str, args: &[Expr]) -> Result<FormulaValue, String> {
+    // The branches of if are evaluated lazily
+    if name == "if" {
+      expect_args(name, args, 2, 3)?;
+      return if self.eval(&args[0])?.is_truthy() {
+        self.eval(&args[1])
+      } else {
+        args
+          .get(2)
+          .map(|arg| self.eval(arg))
+          .unwrap_or(Ok(FormulaValue::Null))
+      };
+    }
+
+    let values = args
+      .iter()
+      .map(|arg| self.eval(arg))
+      .collect::<Result<Vec<_>, _>>()?;
+    let value = match name {
+      "empty" => {
+        expect_args(name, args, 1, 1)?;
+        FormulaValue::Bool(match &values[0] {
+          FormulaValue::Null => true,
+          FormulaValue::Text(text) => text.is_empty(),
+          _ => false,
+        })
+      },
+      "not" => {
+        expect_args(name, args, 1, 1)?;
+        FormulaValue::Bool(!values[0].is_truthy())
+      },
+      "concat" => {
+        let texts: Vec<String> = values.iter().map(|value| value.to_text()).collect();
+        check_text_length(texts.iter().map(|text| text.len()).sum())?;
+        FormulaValue::Text(texts.concat())
+      },
+      "upper" => {
+        expect_args(name, args, 1, 1)?;
+        let text = values[0].to_text().to_uppercase();
+        check_text_length(text.len())?;
+        FormulaValue::Text(text)
+      },
+      "lower" => {
+        expect_args(name, args, 1, 1)?;
+        let text = values[0].to_text().to_lowercase();
+        check_text_length(text.len())?;
+        FormulaValue::Text(text)
+      },
+      "trim" => {
+        expect_args(name, args, 1, 1)?;
+        FormulaValue::Text(values[0].to_text().trim().to_string())
+      },
+      "length" => {
+        expect_args(name, args, 1, 1)?;
+        FormulaValue::Number(values[0].to_text().chars().count() as f64)
+      },
+      "contains" => {
+        expect_args(name, args, 2, 2)?;
+        FormulaValue::Bool(values[0].to_text().contains(&values[1].to_text()))
+      },
+      "replace" => {
+        expect_args(name, args, 3, 3)?;
+        let (text, from, to) = (
+          values[0].to_text(),
+          values[1].to_text(),
+          values[2].to_text(),
+        );
+        // The length is checked before replacing, as an empty pattern matches between every char
+        let matches = text.matches(from.as_str()).count();
+        check_text_length((text.len() - matches * from.len()).saturating_add(matches * to.len()))?;
+        FormulaValue::Text(text.replace(&from, &to))
+      },
+      "left" | "right" => {
+        expect_args(name, args, 2, 2)?;
+        let text: Vec<char> = values[0].to_text().chars().collect();
+        let count = (number_arg(&values[1])?.max(0.0) as usize).min(text.len());
+        let chars = if name == "left" {
+          &text[..count]
+        } else {
+          &text[text.len() - count..]
+        };
+        FormulaValue::Text(chars.iter().collect())
+      },
+      "text" => {
+        expect_args(name, args, 1, 1)?;
+        FormulaValue::Text(values[0].to_text())
+      },
+      "number" => {
+        expect_args(name, args, 1, 1)?;
+        values[0]
+          .to_number()
+          .map(FormulaValue::Number)
+          .unwrap_or(FormulaValue::Null)
+      },
+      "abs" | "floor" | "ceil" | "sqrt" => {
+        expect_args(name, args, 1, 1)?;
+        if values[0] == FormulaValue::Null {
+          return Ok(FormulaValue::Null);
+        }
+        let number = number_arg(&values[0])?;
+        FormulaValue::Number(match name {
+          "abs" => number.abs(),
+          "floor" => number.floor(),
+          "ceil" => number.ceil(),
+          _ if number < 0.0 => return Err("sqrt of a negative number".to_string()),
+          _ => number.sqrt(),
+        })
+      },
+      "round" => {
+        expect_args(name, args, 1, 2)?;
+        if values[0] == FormulaValue::Null {
+          return Ok(FormulaValue::Null);
+        }
+        let digits = match values.get(1) {
+          Some(digits) => number_arg(digits)?.clamp(0.0, 15.0) as i32,
+          None => 0,
+        };
+        let factor = 10f64.powi(digits);
+        FormulaValue::Number((number_arg(&values[0])? * factor).round() / factor)
+      },
+      "pow" => {
+        expect_args(name, args, 2, 2)?;
+        FormulaValue::Number(number_arg(&values[0])?.powf(number_arg(&values[1])?))
+      },
+      "min" | "max" | "sum" => {
+        let numbers = values
+          .iter()
+          .filter(|value| **value != FormulaValue::Null)
+          .map(number_arg)
+          .collect::<Result<Vec<_>, _>>()?;
+        match name {
+          "sum" => FormulaValue::Number(numbers.iter().sum()),
+          "min" => numbers
+            .into_iter()
+            .reduce(f64::min)
+            .map(FormulaValue::Number)
+            .unwrap_or(FormulaValue::Null),
+          _ => numbers
+            .into_iter()
+            .reduce(f64::max)
+            .map(FormulaValue::Number)
+            .unwrap_or(FormulaValue::Null),
+        }
+      },
+      "now" => {
+        expect_args(name, args, 0, 0)?;
+        FormulaValue::Date(self.now)
+      },
+      "today" => {
+        expect_args(name, args, 0, 0)?;
+        FormulaValue::Date(self.now - self.now.rem_euclid(SECONDS_PER_DAY))
+      },
+      "date" => {
+        expect_args(name, args, 1, 1)?;
+        values[0]
+          .to_timestamp()
+          .map(FormulaValue::Date)
+          .unwrap_or(FormulaValue::Null)
+      },
+      "date_add" => {
+        expect_args(name, args, 3, 3)?;
+        let timestamp = match values[0].to_timestamp() {
+          Some(timestamp) => timestamp,
+          None => return Ok(FormulaValue::Null),
+        };
+        let amount = number_arg(&values[1])?;
+        FormulaValue::Date(date_add(timestamp, amount, &values[2].to_text())?)
+      },
+      "date_diff" => {
+        expect_args(name, args, 3, 3)?;
+        match (values[0].to_timestamp(), values[1].to_timestamp()) {
+          (Some(end), Some(start)) => {
+            FormulaValue::Number(date_diff(end, start, &values[2].to_text())?)
+          },
+          _ => FormulaValue::Null,
+        }
+      },
+      "year" | "month" | "day" => {
+        expect_args(name, args, 1, 1)?;
+        let date = match values[0].to_timestamp() {
+          Some(timestamp) => datetime(timestamp)?,
+          None => return Ok(FormulaValue::Null),
+        };
+        FormulaValue::Number(match name {
+          "year" => date.year() as f64,
+          "month" => date.month() as f64,
+          _ => date.day() as f64,
+        })
+      },
+      "format_date" => {
+        expect_args(name, args, 2, 2)?;
+        let date = match values[0].to_timestamp() {
+          Some(timestamp) => datetime(timestamp)?,
+          None => return Ok(FormulaValue::Null),
+        };
+        let format = values[1].to_text();
+        let items: Vec<Item> = StrftimeItems::new(&format).collect();
+        if items.iter().any(|item| matches!(item, Item::Error)) {
+          return Err(format!("Invalid date format: {}", format));
+        }
+        FormulaValue::Text(date.format_with_items(items.into_iter()).to_string())
+      },
+      _ => return Err(format!("Unknown function: {}", name)),
+    };
+    Ok(value)
+  }
+}
+
+fn expect_args(name: &str, args: &[Expr], min: usize, max: usize) -> Result<(), String> {
+  if args.len() < min || args.len() > max {
+    return Err(format!(
+      "{} expects {} arguments, got {}",
+      name,
+      if min == max {
+        min.to_string()
+      } else {
+        format!("{} to {}", min, max)
+      },
+      args.len()
+    ));
+  }
+  Ok(())
+}
+
+fn number_arg(value: &FormulaValue) -> Result<f64, String> {
+  value
+    .to_number()
+    .ok_or_else(|| format!("Expected a number, got {:?}", value))
+}
+
+fn binary(op: BinaryOp, lhs: FormulaValue, rhs: FormulaValue) -> Result<FormulaValue, String> {
+  match op {
+    BinaryOp::Concat => concat_text(&lhs, &rhs),
+    BinaryOp::Add
+      if matches!(lhs, FormulaValue::Text(_)) || matches!(rhs, FormulaValue::Text(_)) =>
+    {
+      concat_text(&lhs, &rhs)
+    },
+    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+      // Arithmetic on an empty cell gives an empty result
+      if lhs == FormulaValue::Null || rhs == FormulaValue::Null {
+        return Ok(FormulaValue::Null);
+      }
+      let (a, b) = (number_arg(&lhs)?, number_arg(&rhs)?);
+      let number = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        _ if b == 0.0 => return Err("Division by zero".to_string()),
+        BinaryOp::Div => a / b,
+        _ => a % b,
+      };
+      Ok(FormulaValue::Number(number))
+    },
+    BinaryOp::Eq => Ok(FormulaValue::Bool(values_equal(&lhs, &rhs))),
+    BinaryOp::Ne => Ok(FormulaValue::Bool(!values_equal(&lhs, &rhs))),
+    BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+      let ordering = match (&lhs, &rhs) {
+        (FormulaValue::Null, _) | (_, FormulaValue::Null) => return Ok(FormulaValue::Bool(false)),
+        (FormulaValue::Text(a), FormulaValue::Text(b)) => a.cmp(b),
+        (FormulaValue::Date(a), FormulaValue::Date(b)) => a.cmp(b),
+        _ => number_arg(&lhs)?
+          .partial_cmp(&number_arg(&rhs)?)
+          .ok_or("Cannot compare NaN")?,
+      };
+      Ok(FormulaValue::Bool(match op {
+        BinaryOp::Lt => ordering.is_lt(),
+        BinaryOp::Le => ordering.is_le(),
+        BinaryOp::Gt => ordering.is_gt(),
+        _ => ordering.is_ge(),
+      }))
+    },
+    BinaryOp::And | BinaryOp::Or => unreachable!("logical operators are evaluated lazily"),
+  }
+}
+
+fn concat_text(lhs: &FormulaValue, rhs: &FormulaValue) -> Result<FormulaValue, String> {
+  let (lhs, rhs) = (lhs.to_text(), rhs.to_text());
+  check_text_length(lhs.len() + rhs.len())?;
+  Ok(FormulaValue::Text(lhs + &rhs))
+}
+
+fn check_text_length(length: usize) -> Result<(), String> {
+  if length > MAX_FORMULA_TEXT_LENGTH {
+    return Err(format!(
+      "Text is longer than the maximum of {} bytes",
+      MAX_FORMULA_TEXT_LENGTH
+    ));
+  }
+  Ok(())
+}
+
+fn values_equal(lhs: &FormulaValue, rhs: &FormulaValue) -> bool {
+  match (lhs, rhs) {
+    (FormulaValue::Number(a), FormulaValue::Number(b)) => a == b,
+    (FormulaValue::Date(a), FormulaValue::Date(b)) => a == b,
+    (FormulaValue::Bool(a), FormulaValue::Bool(b)) => a == b,
+    _ => lhs.to_text() == rhs.to_text(),
+  }
+}
+
+fn datetime(timestamp: i64) -> Result<DateTime<Utc>, String> {
+  DateTime::from_timestamp(timestamp, 0).ok_or_else(|| format!("Invalid date: {}", timestamp))
+}
+
+fn unit_seconds(unit: &str) -> Option<i64> {
+  match unit.trim_end_matches('s') {
+    "second" => Some(1),
+    "minute" => Some(60),
+    "hour" => Some(3_600),
+    "day" => Some(SECONDS_PER_DAY),
+    "week" => Some(7 * SECONDS_PER_DAY),
+    _ => None,
+  }
+}
+
+fn unit_months(unit: &str) -> Option<i64> {
+  match unit.trim_end_matches('s') {
+    "month" => Some(1),
+    "year" => Some(12),
+    _ => None,
+  }
+}
+
+fn date_add(timestamp: i64, amount: f64, unit: &str) -> Result<i64, String> {
+  let out_of_range = || "Date out of range".to_string();
+  if let Some(seconds) = unit_seconds(unit) {
+    let delta = (amount * seconds as f64).trunc();
+    // The conversion to i64 saturates, so the delta is checked to be in range first
+    if !delta.is_finite() || delta.abs() >= i64::MAX as f64 {
+      return Err(out_of_range());
+    }
+    let timestamp = timestamp
+      .checked_add(delta as i64)
+      .ok_or_else(out_of_range)?;
+    return datetime(timestamp).map(|date| date.timestamp());
+  }
+  let months = unit_months(unit).ok_or_else(|| format!("Unknown date unit: {}", unit))?;
+  let amount = amount.trunc();
+  if !amount.is_finite() || amount.abs() > u32::MAX as f64 {
+    return Err(out_of_range());
+  }
+  let months = (amount as i64)
+    .checked_mul(months)
+    .and_then(|months| u32::try_from(months.unsigned_abs()).ok())
+    .map(Months::new)
+    .ok_or_else(out_of_range)?;
+  let date = datetime(timestamp)?;
+  let date = if amount >= 0.0 {
+    date.checked_add_months(months)
+  } else {
+    date.checked_sub_months(months)
+  };
+  date.map(|date| date.timestamp()).ok_or_else(out_of_range)
+}
+
+/// Number of whole units from `start` to `end`, negative if `end` is before `start`.
+fn date_diff(end: i64, start: i64, unit: &str) -> Result<f64, String> {
+  if let Some(seconds) = unit_seconds(unit) {
+    let diff = end
+      .checked_sub(start)
+      .ok_or_else(|| "Date out of range".to_string())?;
+    return Ok((diff / seconds) as f64);
+  }
+  let months = unit_months(unit).ok_or_else(|| format!("Unknown date unit: {}", unit))?;
+  let (end_date, start_date) = (datetime(end)?, datetime(start)?);
+  let mut diff = (end_date.year() as i64 - start_date.year() as i64) * 12
+    + (end_date.month() as i64 - start_date.month() as i64);
+  // Only count the last month once it is complete
+  let offset_in_month = |date: &DateTime<Utc>| (date.day(), date.time());
+  if diff > 0 && offset_in_month(&end_date) < offset_in_month(&start_date) {
+    diff -= 1;
+  } else if diff < 0 && offset_in_month(&end_date) > offset_in_month(&start_date) {
+    diff += 1;
+  }
+  Ok((diff / months) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn eval(expression: &str) -> Result<FormulaValue, String> {
+    let expr = parse_formula(expression).unwrap();
+    let resolve_field = |field: &str| match field {
+      "Price" => FormulaValue::Number(2.5),
+      "Quantity" => FormulaValue::Number(4.0),
+      "Name" => FormulaValue::Text("Pony".to_string()),
+      "Due" => FormulaValue::Date(1_735_689_600), // 2025-01-01
+      _ => FormulaValue::Null,
+    };
+    evaluate_formula(&expr, &resolve_field, 1_736_000_000)
+  }
+
+  #[test]
+  fn evaluate_arithmetic_and_logic() {
+    assert_eq!(
+      eval("{Price} * {Quantity} + 1").unwrap(),
+      FormulaValue::Number(11.0)
+    );
+    assert_eq!(
+      eval("-(1 + 2) * 3 % 4").unwrap(),
+      FormulaValue::Number(-1.0)
+    );
+    assert_eq!(eval("{Price} * {Missing}").unwrap(), FormulaValue::Null);
+    assert_eq!(
+      eval("if({Quantity} >= 4 && !empty({Name}), \"bulk\", 'single')").unwrap(),
+      FormulaValue::Text("bulk".to_string())
+    );
+    assert_eq!(
+      eval("round(10 / 3, 2)").unwrap(),
+      FormulaValue::Number(3.33)
+    );
+    assert!(eval("1 / 0").is_err());
+  }
+
+  #[test]
+  fn evaluate_text_functions() {
+    assert_eq!(
+      eval("concat(upper({Name}), \"-\", length({Name}))").unwrap(),
+      FormulaValue::Text("PONY-4".to_string())
+    );
+    assert_eq!(
+      eval("{Name} & \" x\" & {Quantity}").unwrap(),
+      FormulaValue::Text("Pony x4".to_string())
+    );
+    assert_eq!(
+      eval("replace(left({Name}, 3), \"o\", \"0\")").unwrap(),
+      FormulaValue::Text("P0n".to_string())
+    );
+    // Texts which grow with each nested call are capped
+    let nested = (0..20).fold("{Name}".to_string(), |expr, _| {
+      format!("replace({}, \"\", \"ab\")", expr)
+    });
+    assert!(eval(&nested).is_err());
+  }
+
+  #[test]
+  fn evaluate_date_functions() {
+    assert_eq!(
+      eval("format_date(date_add({Due}, 1, \"month\"), \"%Y-%m-%d\")").unwrap(),
+      FormulaValue::Text("2025-02-01".to_string())
+    );
+    assert_eq!(
+      eval("date_diff(date(\"2025-03-15\"), {Due}, \"days\")").unwrap(),
+      FormulaValue::Number(73.0)
+    );
+    assert_eq!(
+      eval("date_diff(date(\"2025-03-15\"), {Due}, \"months\")").unwrap(),
+      FormulaValue::Number(2.0)
+    );
+    assert_eq!(eval("year({Due})").unwrap(), FormulaValue::Number(2025.0));
+    assert_eq!(eval("today() > {Due}").unwrap(), FormulaValue::Bool(true));
+    assert!(eval("format_date({Due}, \"%Q\")").is_err());
+    assert!(eval("date_add({Due}, \"1e300\", \"days\")").is_err());
+    assert!(eval("date_add({Due}, 4294967296, \"months\")").is_err());
+    assert!(eval("date_add({Due}, -1000000000000, \"years\")").is_err());
+    assert!(
+      eval("date_diff(date(9223372036854775807), date(-9223372036854775807), \"days\")").is_err()
+    );
+  }
+
+  #[test]
+  fn parse_invalid_formulas() {
+    assert!(parse_formula("1 +").is_err());
+    assert!(parse_formula("unknown(1)").is_err());
+    assert!(parse_formula("{Name").is_err());
+    assert!(parse_formula("(1))").is_err());
+    assert!(parse_formula(&"(".repeat(100)).is_err());
+    assert_eq!(
+      parse_formula("{A} + max({B}, {A})").unwrap().field_refs(),
+      vec!["A", "B", "A"]
+    );
+  }
+}
@@ -1,5 +1,14 @@
+pub mod computed_field;
 pub mod database;
+pub mod database_csv;
+pub mod database_view;
 pub mod folder_view;
+pub mod formula;
 pub mod ops;
 pub mod publish_outline;
+pub mod relation;
+pub mod row_access;
+pub mod row_change;
+pub mod row_query;
+pub mod row_template;
 pub mod utils;
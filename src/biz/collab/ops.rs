@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 
+use access_control::database_row::DatabaseRowAccess;
 use app_error::AppError;
 use chrono::DateTime;
 use chrono::Utc;
 use collab::preclude::Collab;
 use collab_database::database::gen_field_id;
+use collab_database::database::DatabaseBody;
 use collab_database::entity::FieldType;
 use collab_database::fields::Field;
+use collab_database::fields::TypeOptionData;
 use collab_database::fields::TypeOptions;
 use collab_database::rows::meta_id_from_row_id;
 use collab_database::rows::CreateRowParams;
@@ -15,6 +18,9 @@ use collab_database::rows::Row;
 use collab_database::rows::RowDetail;
 use collab_database::rows::RowId;
 use collab_database::rows::RowMetaKey;
+use collab_database::rows::RowOrder;
+use collab_database::rows::CELL_FIELD_TYPE;
+use collab_database::views::DatabaseView;
 use collab_database::views::OrderObjectPosition;
 use collab_database::workspace_database::WorkspaceDatabase;
 use collab_database::workspace_database::WorkspaceDatabaseBody;
@@ -28,6 +34,7 @@ use collab_folder::{CollabOrigin, SpaceInfo};
 use collab_rt_entity::user::RealtimeUser;
 use database::collab::select_last_updated_database_row_ids;
 use database::collab::select_workspace_database_oid;
+use database::collab::update_collabs_as_deleted;
 use database::collab::{CollabStore, GetCollabOrigin};
 use database::publish::select_published_view_ids_for_workspace;
 use database::publish::select_published_view_ids_with_publish_info_for_workspace;
@@ -36,26 +43,43 @@ use database_entity::dto::CollabParams;
 use database_entity::dto::QueryCollab;
 use database_entity::dto::QueryCollabResult;
 
+use sha2::Digest;
+use sha2::Sha256;
 use shared_entity::dto::workspace_dto::AFDatabase;
 use shared_entity::dto::workspace_dto::AFDatabaseField;
 use shared_entity::dto::workspace_dto::AFDatabaseRow;
 use shared_entity::dto::workspace_dto::AFDatabaseRowDetail;
 use shared_entity::dto::workspace_dto::AFInsertDatabaseField;
+use shared_entity::dto::workspace_dto::AFMoveDatabaseField;
+use shared_entity::dto::workspace_dto::AFUpdateDatabaseField;
+use shared_entity::dto::workspace_dto::AddDatatabaseRow;
+use shared_entity::dto::workspace_dto::DatabaseRowOperation;
 use shared_entity::dto::workspace_dto::DatabaseRowUpdatedItem;
 use shared_entity::dto::workspace_dto::FavoriteFolderView;
 use shared_entity::dto::workspace_dto::FolderViewMinimal;
 use shared_entity::dto::workspace_dto::PublishedViewInfo;
 use shared_entity::dto::workspace_dto::RecentFolderView;
 use shared_entity::dto::workspace_dto::TrashFolderView;
+use shared_entity::dto::workspace_dto::UpsertDatatabaseRow;
+use shared_entity::dto::workspace_dto::MAX_DATABASE_ROW_BATCH_SIZE;
 use sqlx::PgPool;
+use sqlx::Postgres;
+use sqlx::Transaction;
+use yrs::Any;
 use yrs::Map;
+use yrs::TransactionMut;
 
+use super::computed_field::set_computed_type_option;
+use super::computed_field::validate_computed_field;
+use super::computed_field::ComputedFields;
 use super::folder_view::collab_folder_to_folder_view;
 use super::folder_view::section_items_to_favorite_folder_view;
 use super::folder_view::section_items_to_recent_folder_view;
 use super::folder_view::section_items_to_trash_folder_view;
 use super::folder_view::to_dto_folder_view_miminal;
 use super::publish_outline::collab_folder_to_published_outline;
+use super::relation::{expand_relations, RelationExpansion};
+use super::row_access::{can_read_row, readable_row_ids, RelatedDatabaseAccess};
 use super::utils::collab_to_bin;
 use super::utils::create_row_document;
 use super::utils::field_by_id_name_uniq;
@@ -435,6 +459,96 @@ pub async fn insert_database_row(
   row_doc_content: Option<String>,
 ) -> Result<String, AppError> {
   let new_db_row_id = new_db_row_id.unwrap_or_else(Uuid::new_v4);
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_uuid, database_uuid).await?;
+  let new_row = create_database_row(
+    state,
+    workspace_uuid,
+    database_uuid,
+    uid,
+    &db_body,
+    new_db_row_id,
+    cell_value_by_id,
+    row_doc_content,
+  )
+  .await?;
+
+  // For each database view, add the new row order
+  let mut updates = PendingCollabUpdates::default();
+  updates.push(
+    database_uuid,
+    CollabType::Database,
+    update_database_row_orders(
+      &mut db_collab,
+      &db_body,
+      vec![new_row.row_order.clone()],
+      &HashSet::new(),
+    ),
+  );
+
+  let mut db_txn = state.pg_pool.begin().await?;
+  save_new_database_row(
+    state,
+    workspace_uuid,
+    uid,
+    new_row,
+    &mut db_txn,
+    &mut updates,
+  )
+  .await?;
+  db_txn.commit().await?;
+  updates.publish(state, workspace_uuid).await?;
+  Ok(new_db_row_id.to_string())
+}
+
+/// Collab updates, which are only broadcast once the database transaction saving the collabs is
+/// committed, so that no client receives changes which are rolled back.
+#[derive(Default)]
+struct PendingCollabUpdates {
+  updates: Vec<(Uuid, CollabType, Vec<u8>)>,
+}
+
+impl PendingCollabUpdates {
+  fn push(&mut self, object_id: Uuid, collab_type: CollabType, update: Vec<u8>) {
+    self.updates.push((object_id, collab_type, update));
+  }
+
+  async fn publish(self, state: &AppState, workspace_uuid: Uuid) -> Result<(), AppError> {
+    for (object_id, collab_type, update) in self.updates {
+      state
+        .ws_server
+        .publish_update(
+          workspace_uuid,
+          object_id,
+          collab_type,
+          &CollabOrigin::Server,
+          update,
+        )
+        .await?;
+    }
+    Ok(())
+  }
+}
+
+/// A database row, which is not yet part of the database views nor saved.
+struct NewDatabaseRow {
+  row_id: Uuid,
+  row_order: RowOrder,
+  row_ec_v1: Vec<u8>,
+  row_doc: Option<(Uuid, CreatedRowDocument)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn create_database_row(
+  state: &AppState,
+  workspace_uuid: Uuid,
+  database_uuid: Uuid,
+  uid: i64,
+  db_body: &DatabaseBody,
+  new_db_row_id: Uuid,
+  cell_value_by_id: HashMap<String, serde_json::Value>,
+  row_doc_content: Option<String>,
+) -> Result<NewDatabaseRow, AppError> {
   let new_db_row_id_str = RowId::from(new_db_row_id.to_string());
   let creation_time = Utc::now();
   let client_id = default_client_id();
@@ -481,10 +595,8 @@ pub async fn insert_database_row(
     _ => None,
   };
 
-  let (mut db_collab, db_body) =
-    get_latest_collab_database_body(&state.collab_storage, workspace_uuid, database_uuid).await?;
   write_to_database_row(
-    &db_body,
+    db_body,
     &mut new_db_row_collab.transact_mut(),
     &new_db_row_body,
     cell_value_by_id,
@@ -513,47 +625,65 @@ pub async fn insert_database_row(
     .await
     .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to create row: {:?}", e)))?;
 
-  let new_db_row_ec_v1 = collab_to_bin(new_db_row_collab, CollabType::DatabaseRow).await?;
+  let row_ec_v1 = collab_to_bin(new_db_row_collab, CollabType::DatabaseRow).await?;
+  Ok(NewDatabaseRow {
+    row_id: new_db_row_id,
+    row_order,
+    row_ec_v1,
+    row_doc: new_row_doc_creation,
+  })
+}
 
-  // For each database view, add the new row order
-  let db_collab_update = {
-    let mut txn = db_collab.transact_mut();
-    let mut db_views = db_body.views.get_all_views(&txn);
-    for db_view in db_views.iter_mut() {
-      db_view.row_orders.push(row_order.clone());
-    }
-    db_body.views.clear(&mut txn);
-    for view in db_views {
-      db_body.views.insert_view(&mut txn, view);
-    }
-    txn.encode_update_v1()
-  };
+/// Appends the new rows to, and removes the deleted rows from, all the views of the database.
+/// Returns the update of the database collab.
+fn update_database_row_orders(
+  db_collab: &mut Collab,
+  db_body: &DatabaseBody,
+  new_row_orders: Vec<RowOrder>,
+  deleted_row_ids: &HashSet<String>,
+) -> Vec<u8> {
+  let mut txn = db_collab.transact_mut();
+  update_all_database_views(&mut txn, db_body, |db_view| {
+    db_view
+      .row_orders
+      .retain(|row_order| !deleted_row_ids.contains(row_order.id.as_str()));
+    db_view.row_orders.extend(new_row_orders.iter().cloned());
+  });
+  txn.encode_update_v1()
+}
 
-  state
-    .ws_server
-    .publish_update(
-      workspace_uuid,
-      database_uuid,
-      CollabType::Database,
-      &CollabOrigin::Server,
-      db_collab_update.clone(),
-    )
-    .await?;
+/// Rewrites all the views of the database after applying `f` to each of them.
+pub fn update_all_database_views(
+  txn: &mut TransactionMut,
+  db_body: &DatabaseBody,
+  mut f: impl FnMut(&mut DatabaseView),
+) {
+  let mut db_views = db_body.views.get_all_views(txn);
+  for db_view in db_views.iter_mut() {
+    f(db_view);
+  }
+  db_body.views.clear(txn);
+  for view in db_views {
+    db_body.views.insert_view(txn, view);
+  }
+}
 
-  let collab_storage = state.collab_storage.clone();
-  let mut db_txn = state.pg_pool.begin().await?;
+async fn save_new_database_row(
+  state: &AppState,
+  workspace_uuid: Uuid,
+  uid: i64,
+  new_row: NewDatabaseRow,
+  db_txn: &mut Transaction<'_, Postgres>,
+  updates: &mut PendingCollabUpdates,
+) -> Result<(), AppError> {
+  let collab_storage = &state.collab_storage;
   // handle row document (if provided)
-  if let Some((doc_id, created_doc)) = new_row_doc_creation {
-    state
-      .ws_server
-      .publish_update(
-        workspace_uuid,
-        workspace_uuid,
-        CollabType::Folder,
-        &CollabOrigin::Server,
-        created_doc.folder_updates,
-      )
-      .await?;
+  if let Some((doc_id, created_doc)) = new_row.row_doc {
+    updates.push(
+      workspace_uuid,
+      CollabType::Folder,
+      created_doc.folder_updates,
+    );
 
     // insert document
     collab_storage
@@ -566,30 +696,54 @@ pub async fn insert_database_row(
           collab_type: CollabType::Document,
           updated_at: None,
         },
-        &mut db_txn,
+        db_txn,
         "inserting new database row document from server",
       )
       .await?;
   };
 
+  // the state of the row is published too, so that the row change feed records its cells
+  let row_doc_state = EncodedCollab::decode_from_bytes(&new_row.row_ec_v1)?.doc_state;
+  updates.push(
+    new_row.row_id,
+    CollabType::DatabaseRow,
+    row_doc_state.to_vec(),
+  );
+
   // insert row
   collab_storage
     .upsert_new_collab_with_transaction(
       workspace_uuid,
       &uid,
       CollabParams {
-        object_id: new_db_row_id,
-        encoded_collab_v1: new_db_row_ec_v1.into(),
+        object_id: new_row.row_id,
+        encoded_collab_v1: new_row.row_ec_v1.into(),
         collab_type: CollabType::DatabaseRow,
         updated_at: None,
       },
-      &mut db_txn,
+      db_txn,
       "inserting new database row from server",
     )
     .await?;
+  Ok(())
+}
 
-  db_txn.commit().await?;
-  Ok(new_db_row_id.to_string())
+/// Returns the id of the row upserted with the given `pre_hash`.
+pub fn database_row_id_from_pre_hash(
+  workspace_uuid: Uuid,
+  database_uuid: Uuid,
+  pre_hash: &str,
+) -> Uuid {
+  let mut hasher = Sha256::new();
+  hasher.update(workspace_uuid);
+  hasher.update(database_uuid);
+  hasher.update(pre_hash);
+  let hash = hasher.finalize();
+  Uuid::from_bytes([
+    // take 16 out of 32 bytes
+    hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7], hash[8], hash[9],
+    hash[10], hash[11], hash[12], hash[13], hash[14], hash[15],
+  ])
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -603,7 +757,7 @@ pub async fn upsert_database_row(
   row_doc_content: Option<String>,
 ) -> Result<(), AppError> {
   let collab_storage = &state.collab_storage;
-  let (mut db_row_collab, db_row_body) =
+  let (db_row_collab, db_row_body) =
     match get_latest_collab_database_row_body(collab_storage, workspace_uuid, row_id).await {
       Ok(res) => res,
       Err(err) => match err {
@@ -629,9 +783,45 @@ pub async fn upsert_database_row(
   let (_db_collab, db_body) =
     get_latest_collab_database_body(collab_storage, workspace_uuid, database_uuid).await?;
 
+  let mut db_txn = state.pg_pool.begin().await?;
+  let mut updates = PendingCollabUpdates::default();
+  update_database_row(
+    state,
+    workspace_uuid,
+    uid,
+    row_id,
+    &db_body,
+    db_row_collab,
+    db_row_body,
+    cell_value_by_id,
+    row_doc_content,
+    &mut db_txn,
+    &mut updates,
+  )
+  .await?;
+  db_txn.commit().await?;
+  updates.publish(state, workspace_uuid).await?;
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn update_database_row(
+  state: &AppState,
+  workspace_uuid: Uuid,
+  uid: i64,
+  row_id: Uuid,
+  db_body: &DatabaseBody,
+  mut db_row_collab: Collab,
+  db_row_body: DatabaseRowBody,
+  cell_value_by_id: HashMap<String, serde_json::Value>,
+  row_doc_content: Option<String>,
+  db_txn: &mut Transaction<'_, Postgres>,
+  updates: &mut PendingCollabUpdates,
+) -> Result<(), AppError> {
+  let collab_storage = &state.collab_storage;
   let mut db_row_txn = db_row_collab.transact_mut();
   write_to_database_row(
-    &db_body,
+    db_body,
     &mut db_row_txn,
     &db_row_body,
     cell_value_by_id,
@@ -655,20 +845,10 @@ pub async fn upsert_database_row(
   // finalize update for database row
   let db_row_collab_updates = db_row_txn.encode_update_v1();
   drop(db_row_txn);
-  state
-    .ws_server
-    .publish_update(
-      workspace_uuid,
-      row_id,
-      CollabType::DatabaseRow,
-      &CollabOrigin::Server,
-      db_row_collab_updates,
-    )
-    .await?;
+  updates.push(row_id, CollabType::DatabaseRow, db_row_collab_updates);
 
   let db_row_ec_v1 = collab_to_bin(db_row_collab, CollabType::DatabaseRow).await?;
-  // write to disk and broadcast changes
-  let mut db_txn = state.pg_pool.begin().await?;
+  // write to disk, the changes are broadcast once committed
   collab_storage
     .upsert_new_collab_with_transaction(
       workspace_uuid,
@@ -679,7 +859,7 @@ pub async fn upsert_database_row(
         collab_type: CollabType::DatabaseRow,
         updated_at: None,
       },
-      &mut db_txn,
+      db_txn,
       "inserting new database row from server",
     )
     .await?;
@@ -698,38 +878,18 @@ pub async fn upsert_database_row(
               collab_type: CollabType::Document,
               updated_at: None,
             },
-            &mut db_txn,
+            db_txn,
             "updating database row document from server",
           )
           .await?;
-
-        state
-          .ws_server
-          .publish_update(
-            workspace_uuid,
-            doc_id,
-            CollabType::Document,
-            &CollabOrigin::Server,
-            doc_update,
-          )
-          .await?;
+        updates.push(doc_id, CollabType::Document, doc_update);
       },
       DocChanges::Insert(created_doc) => {
         let CreatedRowDocument {
           folder_updates,
           doc_ec_bytes,
         } = created_doc;
-
-        state
-          .ws_server
-          .publish_update(
-            workspace_uuid,
-            workspace_uuid,
-            CollabType::Folder,
-            &CollabOrigin::Server,
-            folder_updates,
-          )
-          .await?;
+        updates.push(workspace_uuid, CollabType::Folder, folder_updates);
 
         // insert document
         collab_storage
@@ -742,18 +902,218 @@ pub async fn upsert_database_row(
               collab_type: CollabType::Document,
               updated_at: None,
             },
-            &mut db_txn,
+            db_txn,
             "inserting new database row document from server",
           )
           .await?;
       },
     }
   }
+  Ok(())
+}
 
-  db_txn.commit().await?;
+/// Returns the ids of the rows present in any view of the database.
+fn database_row_ids(db_collab: &Collab, db_body: &DatabaseBody) -> HashSet<String> {
+  let txn = db_collab.transact();
+  db_body
+    .views
+    .get_all_views(&txn)
+    .into_iter()
+    .flat_map(|view| view.row_orders)
+    .map(|row_order| row_order.id.to_string())
+    .collect()
+}
+
+/// Removes the row from all the views of the database, and marks its collab as deleted.
+pub async fn delete_database_row(
+  state: &AppState,
+  workspace_uuid: Uuid,
+  database_uuid: Uuid,
+  uid: i64,
+  row_id: Uuid,
+) -> Result<(), AppError> {
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_uuid, database_uuid).await?;
+  let row_id_str = row_id.to_string();
+  if !database_row_ids(&db_collab, &db_body).contains(&row_id_str) {
+    return Err(AppError::RecordNotFound(format!(
+      "Row {} not found in database {}",
+      row_id, database_uuid
+    )));
+  }
+
+  let db_collab_update = update_database_row_orders(
+    &mut db_collab,
+    &db_body,
+    vec![],
+    &HashSet::from([row_id_str]),
+  );
+  state
+    .ws_server
+    .publish_update(
+      workspace_uuid,
+      database_uuid,
+      CollabType::Database,
+      &CollabOrigin::Server,
+      db_collab_update,
+    )
+    .await?;
+  state
+    .collab_storage
+    .delete_collab(&workspace_uuid, &uid, &row_id)
+    .await?;
   Ok(())
 }
 
+/// Applies the operations in order, and returns the id of the row of each operation. Either all
+/// the operations are applied or none: the rows are saved and deleted in a single database
+/// transaction, and the changes are only broadcast once it is committed. The rows are added to,
+/// and removed from, the database views in a single update of the database collab.
+pub async fn batch_database_rows(
+  state: &AppState,
+  workspace_uuid: Uuid,
+  database_uuid: Uuid,
+  uid: i64,
+  operations: Vec<DatabaseRowOperation>,
+) -> Result<Vec<String>, AppError> {
+  if operations.len() > MAX_DATABASE_ROW_BATCH_SIZE {
+    return Err(AppError::InvalidRequest(format!(
+      "A batch can contain at most {} operations",
+      MAX_DATABASE_ROW_BATCH_SIZE
+    )));
+  }
+
+  let collab_storage = &state.collab_storage;
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(collab_storage, workspace_uuid, database_uuid).await?;
+  // Validate the operations before applying any of them
+  let existing_row_ids = database_row_ids(&db_collab, &db_body);
+  let mut upserted_row_ids = HashSet::new();
+  for operation in &operations {
+    match operation {
+      DatabaseRowOperation::Delete { row_id } => {
+        if !existing_row_ids.contains(row_id) {
+          return Err(AppError::RecordNotFound(format!(
+            "Row {} not found in database {}",
+            row_id, database_uuid
+          )));
+        }
+      },
+      DatabaseRowOperation::Upsert(UpsertDatatabaseRow { pre_hash, .. }) => {
+        let row_id = database_row_id_from_pre_hash(workspace_uuid, database_uuid, pre_hash);
+        if !upserted_row_ids.insert(row_id) {
+          return Err(AppError::InvalidRequest(format!(
+            "The row with pre hash {} is upserted more than once",
+            pre_hash
+          )));
+        }
+      },
+      DatabaseRowOperation::Insert(_) => {},
+    }
+  }
+
+  let mut db_txn = state.pg_pool.begin().await?;
+  let mut updates = PendingCollabUpdates::default();
+  let mut new_rows: Vec<NewDatabaseRow> = vec![];
+  let mut deleted_row_ids: HashSet<Uuid> = HashSet::new();
+  let mut row_ids = Vec::with_capacity(operations.len());
+  for operation in operations {
+    let (row_id, cells, document) = match operation {
+      DatabaseRowOperation::Insert(AddDatatabaseRow { cells, document }) => {
+        (Uuid::new_v4(), cells, document)
+      },
+      DatabaseRowOperation::Upsert(UpsertDatatabaseRow {
+        pre_hash,
+        cells,
+        document,
+      }) => {
+        let row_id = database_row_id_from_pre_hash(workspace_uuid, database_uuid, &pre_hash);
+        match get_latest_collab_database_row_body(collab_storage, workspace_uuid, row_id).await {
+          Ok((db_row_collab, db_row_body)) => {
+            update_database_row(
+              state,
+              workspace_uuid,
+              uid,
+              row_id,
+              &db_body,
+              db_row_collab,
+              db_row_body,
+              cells,
+              document,
+              &mut db_txn,
+              &mut updates,
+            )
+            .await?;
+            row_ids.push(row_id.to_string());
+            continue;
+          },
+          Err(AppError::RecordNotFound(_)) => (row_id, cells, document),
+          Err(err) => return Err(err),
+        }
+      },
+      DatabaseRowOperation::Delete { row_id } => {
+        let row_id = Uuid::parse_str(&row_id)?;
+        deleted_row_ids.insert(row_id);
+        row_ids.push(row_id.to_string());
+        continue;
+      },
+    };
+    let new_row = create_database_row(
+      state,
+      workspace_uuid,
+      database_uuid,
+      uid,
+      &db_body,
+      row_id,
+      cells,
+      document,
+    )
+    .await?;
+    new_rows.push(new_row);
+    row_ids.push(row_id.to_string());
+  }
+
+  if !new_rows.is_empty() || !deleted_row_ids.is_empty() {
+    let db_collab_update = update_database_row_orders(
+      &mut db_collab,
+      &db_body,
+      new_rows.iter().map(|row| row.row_order.clone()).collect(),
+      &deleted_row_ids.iter().map(|id| id.to_string()).collect(),
+    );
+    updates.push(database_uuid, CollabType::Database, db_collab_update);
+  }
+  for new_row in new_rows {
+    save_new_database_row(
+      state,
+      workspace_uuid,
+      uid,
+      new_row,
+      &mut db_txn,
+      &mut updates,
+    )
+    .await?;
+  }
+  let deleted_row_ids: Vec<Uuid> = deleted_row_ids.into_iter().collect();
+  update_collabs_as_deleted(&mut *db_txn, &workspace_uuid, &deleted_row_ids).await?;
+  db_txn.commit().await?;
+  updates.publish(state, workspace_uuid).await?;
+
+  // The deleted rows are already marked as such, their cached copies are dropped as well
+  for row_id in deleted_row_ids {
+    if let Err(err) = collab_storage
+      .delete_collab(&workspace_uuid, &uid, &row_id)
+      .await
+    {
+      tracing::warn!(
+        "Failed to delete the collab of the deleted row {}: {}",
+        row_id,
+        err
+      );
+    }
+  }
+  Ok(row_ids)
+}
+
 pub async fn get_database_fields(
   collab_storage: &Arc<dyn CollabStore>,
   workspace_uuid: Uuid,
@@ -793,16 +1153,13 @@ pub async fn add_database_field(
   let type_option_data = insert_field
     .type_option_data
     .unwrap_or(serde_json::json!({}));
-
-  match serde_json::from_value(type_option_data) {
-    Ok(tod) => type_options.insert(insert_field.field_type.to_string(), tod),
-    Err(err) => {
-      return Err(AppError::InvalidRequest(format!(
-        "Failed to parse type option: {:?}",
-        err
-      )));
-    },
-  };
+  let mut type_option_data = type_option_data_from_json(type_option_data)?;
+  if let Some(computed) = &insert_field.computed {
+    let fields = db_body.fields.get_all_fields(&db_collab.transact());
+    validate_computed_field(&fields, &new_id, computed)?;
+    set_computed_type_option(&mut type_option_data, computed)?;
+  }
+  type_options.insert(insert_field.field_type.to_string(), type_option_data);
 
   let new_field = Field {
     id: new_id.clone(),
@@ -838,41 +1195,349 @@ pub async fn add_database_field(
   Ok(new_id)
 }
 
+fn type_option_data_from_json(
+  type_option_data: serde_json::Value,
+) -> Result<TypeOptionData, AppError> {
+  serde_json::from_value(type_option_data)
+    .map_err(|err| AppError::InvalidRequest(format!("Failed to parse type option: {:?}", err)))
+}
+
+fn get_database_field(
+  db_collab: &Collab,
+  db_body: &DatabaseBody,
+  database_id: Uuid,
+  field_id: &str,
+) -> Result<Field, AppError> {
+  db_body
+    .fields
+    .get_field(&db_collab.transact(), field_id)
+    .ok_or_else(|| {
+      AppError::RecordNotFound(format!(
+        "Field {} not found in database {}",
+        field_id, database_id
+      ))
+    })
+}
+
+/// Returns true if the cells of a field can be kept as they are, apart from their field type,
+/// when the type of the field changes.
+fn is_lossless_field_type_change(from: &FieldType, to: &FieldType) -> bool {
+  matches!(
+    (from, to),
+    (FieldType::RichText, FieldType::URL)
+      | (FieldType::URL, FieldType::RichText)
+      | (FieldType::Number, FieldType::RichText)
+      | (FieldType::Checkbox, FieldType::RichText)
+      | (FieldType::SingleSelect, FieldType::MultiSelect)
+  )
+}
+
+// renames the field, and/or changes its type and type option.
+// cells are converted to the new field type when the conversion is lossless.
+pub async fn update_database_field(
+  state: &AppState,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  uid: i64,
+  field_id: &str,
+  update_field: AFUpdateDatabaseField,
+) -> Result<(), AppError> {
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_id, database_id).await?;
+  let field = get_database_field(&db_collab, &db_body, database_id, field_id)?;
+
+  let old_field_type = FieldType::from(field.field_type);
+  let new_field_type_id = update_field.field_type.unwrap_or(field.field_type);
+  let new_field_type = FieldType::from(new_field_type_id);
+  let type_option_data = match update_field.type_option_data {
+    Some(type_option_data) => Some(type_option_data_from_json(type_option_data)?),
+    // keep the select options when switching between select fields
+    None
+      if matches!(
+        (&old_field_type, &new_field_type),
+        (FieldType::SingleSelect, FieldType::MultiSelect)
+          | (FieldType::MultiSelect, FieldType::SingleSelect)
+      ) && !field.type_options.contains_key(&new_field_type.type_id()) =>
+    {
+      field.type_options.get(&old_field_type.type_id()).cloned()
+    },
+    None => None,
+  };
+  let type_option_data = match &update_field.computed {
+    Some(computed) => {
+      let fields = db_body.fields.get_all_fields(&db_collab.transact());
+      validate_computed_field(&fields, field_id, computed)?;
+      let mut type_option_data = type_option_data
+        .or_else(|| field.type_options.get(&new_field_type.type_id()).cloned())
+        .unwrap_or_default();
+      set_computed_type_option(&mut type_option_data, computed)?;
+      Some(type_option_data)
+    },
+    None => type_option_data,
+  };
+
+  let db_collab_update = {
+    let mut txn = db_collab.transact_mut();
+    db_body.fields.update_field(&mut txn, field_id, |update| {
+      let update = update
+        .set_name_if_not_none(update_field.name)
+        .set_field_type(new_field_type_id);
+      if let Some(type_option_data) = type_option_data {
+        update.set_type_option(new_field_type_id, Some(type_option_data));
+      }
+    });
+    txn.encode_update_v1()
+  };
+
+  // The field is only changed once its converted cells are saved
+  let mut updates = PendingCollabUpdates::default();
+  updates.push(database_id, CollabType::Database, db_collab_update);
+  if field.field_type != new_field_type_id
+    && is_lossless_field_type_change(&old_field_type, &new_field_type)
+  {
+    convert_database_field_cells(
+      state,
+      workspace_id,
+      database_id,
+      uid,
+      field_id,
+      field.field_type,
+      new_field_type_id,
+      &mut updates,
+    )
+    .await?;
+  }
+  updates.publish(state, workspace_id).await?;
+  Ok(())
+}
+
+/// Sets the field type of the cells of the field in all the rows of the database, leaving the
+/// cell data untouched. The rows are saved in a single transaction, and their updates are added to
+/// `updates`, to be published once it is committed.
+#[allow(clippy::too_many_arguments)]
+async fn convert_database_field_cells(
+  state: &AppState,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  uid: i64,
+  field_id: &str,
+  old_field_type: i64,
+  new_field_type: i64,
+  updates: &mut PendingCollabUpdates,
+) -> Result<(), AppError> {
+  let collab_storage = &state.collab_storage;
+  let rows = list_database_row_ids(collab_storage, workspace_id, database_id).await?;
+  let mut db_txn = state.pg_pool.begin().await?;
+  for row in rows {
+    let row_id = Uuid::parse_str(&row.id)?;
+    let (mut db_row_collab, db_row_body) =
+      match get_latest_collab_database_row_body(collab_storage, workspace_id, row_id).await {
+        Ok(res) => res,
+        Err(AppError::RecordNotFound(_)) => continue,
+        Err(err) => return Err(err),
+      };
+
+    let db_row_update = {
+      let mut txn = db_row_collab.transact_mut();
+      let mut cell = match db_row_body
+        .cells(&txn)
+        .and_then(|cells| cells.get(field_id).cloned())
+      {
+        Some(cell) => cell,
+        None => continue,
+      };
+      if cell.get(CELL_FIELD_TYPE) != Some(&Any::BigInt(old_field_type)) {
+        continue;
+      }
+      cell.insert(CELL_FIELD_TYPE.to_string(), Any::BigInt(new_field_type));
+      db_row_body.update(&mut txn, |row_update| {
+        row_update.update_cells(|cells_update| {
+          cells_update.insert_cell(field_id, cell);
+        });
+      });
+      txn.encode_update_v1()
+    };
+
+    updates.push(row_id, CollabType::DatabaseRow, db_row_update);
+    let db_row_ec_v1 = collab_to_bin(db_row_collab, CollabType::DatabaseRow).await?;
+    collab_storage
+      .upsert_new_collab_with_transaction(
+        workspace_id,
+        &uid,
+        CollabParams {
+          object_id: row_id,
+          encoded_collab_v1: db_row_ec_v1.into(),
+          collab_type: CollabType::DatabaseRow,
+          updated_at: None,
+        },
+        &mut db_txn,
+        "converting database row cells from server",
+      )
+      .await?;
+  }
+  db_txn.commit().await?;
+  Ok(())
+}
+
+// moves the field after `prev_field_id`, or to the first position if unset,
+// in the given view or in all views of the database.
+pub async fn move_database_field(
+  state: &AppState,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  field_id: &str,
+  move_field: AFMoveDatabaseField,
+) -> Result<(), AppError> {
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_id, database_id).await?;
+  get_database_field(&db_collab, &db_body, database_id, field_id)?;
+  if let Some(prev_field_id) = &move_field.prev_field_id {
+    get_database_field(&db_collab, &db_body, database_id, prev_field_id)?;
+  }
+  if let Some(view_id) = &move_field.view_id {
+    if db_body
+      .views
+      .get_view(&db_collab.transact(), view_id)
+      .is_none()
+    {
+      return Err(AppError::RecordNotFound(format!(
+        "View {} not found in database {}",
+        view_id, database_id
+      )));
+    }
+  }
+
+  let db_collab_update = {
+    let mut txn = db_collab.transact_mut();
+    update_all_database_views(&mut txn, &db_body, |db_view| {
+      if move_field
+        .view_id
+        .as_ref()
+        .is_some_and(|view_id| *view_id != db_view.id)
+      {
+        return;
+      }
+      let field_orders = &mut db_view.field_orders;
+      let field_order = match field_orders.iter().position(|order| order.id == field_id) {
+        Some(index) => field_orders.remove(index),
+        None => return,
+      };
+      let index = match &move_field.prev_field_id {
+        Some(prev_field_id) => field_orders
+          .iter()
+          .position(|order| order.id == *prev_field_id)
+          .map_or(field_orders.len(), |index| index + 1),
+        None => 0,
+      };
+      field_orders.insert(index, field_order);
+    });
+    txn.encode_update_v1()
+  };
+
+  state
+    .ws_server
+    .publish_update(
+      workspace_id,
+      database_id,
+      CollabType::Database,
+      &CollabOrigin::Server,
+      db_collab_update,
+    )
+    .await?;
+  Ok(())
+}
+
+// deletes the field from the database and all its views.
+// the primary field cannot be deleted.
+pub async fn delete_database_field(
+  state: &AppState,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  field_id: &str,
+) -> Result<(), AppError> {
+  let (mut db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_id, database_id).await?;
+  let field = get_database_field(&db_collab, &db_body, database_id, field_id)?;
+  if field.is_primary {
+    return Err(AppError::InvalidRequest(
+      "The primary field cannot be deleted".to_string(),
+    ));
+  }
+
+  let db_collab_update = {
+    let mut txn = db_collab.transact_mut();
+    update_all_database_views(&mut txn, &db_body, |db_view| {
+      db_view.field_orders.retain(|order| order.id != field_id);
+    });
+    db_body.fields.delete_field(&mut txn, field_id);
+    txn.encode_update_v1()
+  };
+
+  state
+    .ws_server
+    .publish_update(
+      workspace_id,
+      database_id,
+      CollabType::Database,
+      &CollabOrigin::Server,
+      db_collab_update,
+    )
+    .await?;
+  Ok(())
+}
+
 pub async fn list_database_row_ids_updated(
   collab_storage: &Arc<dyn CollabStore>,
   pg_pool: &PgPool,
   workspace_uuid: Uuid,
   database_uuid: Uuid,
   after: &DateTime<Utc>,
+  uid: i64,
+  row_access: &DatabaseRowAccess,
 ) -> Result<Vec<DatabaseRowUpdatedItem>, AppError> {
   let row_ids: Vec<_> = list_database_row_ids(collab_storage, workspace_uuid, database_uuid)
     .await?
     .into_iter()
     .flat_map(|row| Uuid::parse_str(&row.id))
     .collect();
+  let row_ids = readable_row_ids(collab_storage, uid, workspace_uuid, row_access, row_ids).await;
 
   let updated_row_ids =
     select_last_updated_database_row_ids(pg_pool, &workspace_uuid, &row_ids, after).await?;
   Ok(updated_row_ids)
 }
 
+/// Returns the details of the rows which can be read with `row_access`. The other rows are left
+/// out, like the rows which don't exist.
+///
+/// With `relation_expansion`, the cells of the relation fields are the related rows, even if
+/// relation fields are unsupported.
+#[allow(clippy::too_many_arguments)]
 pub async fn list_database_row_details(
   collab_storage: &Arc<dyn CollabStore>,
+  related_access: &RelatedDatabaseAccess,
   uid: i64,
   workspace_uuid: Uuid,
   database_uuid: Uuid,
   row_ids: &[Uuid],
   unsupported_field_types: &[FieldType],
   with_doc: bool,
+  row_access: &DatabaseRowAccess,
+  relation_expansion: Option<&RelationExpansion>,
 ) -> Result<Vec<AFDatabaseRowDetail>, AppError> {
   let (database_collab, db_body) =
     get_latest_collab_database_body(collab_storage, workspace_uuid, database_uuid).await?;
 
-  let all_fields: Vec<Field> = db_body
-    .fields
-    .get_all_fields(&database_collab.transact())
-    .into_iter()
-    .filter(|field| !unsupported_field_types.contains(&FieldType::from(field.field_type)))
+  let fields = db_body.fields.get_all_fields(&database_collab.transact());
+  let computed_fields = ComputedFields::new(&fields);
+  let all_fields: Vec<Field> = fields
+    .iter()
+    .filter(|field| {
+      let field_type = FieldType::from(field.field_type);
+      (relation_expansion.is_some() && field_type == FieldType::Relation)
+        || !unsupported_field_types.contains(&field_type)
+    })
+    .cloned()
     .collect();
   if all_fields.is_empty() {
     return Ok(vec![]);
@@ -888,50 +1553,71 @@ pub async fn list_database_row_details(
       collab_type: CollabType::DatabaseRow,
     })
     .collect();
-  let mut db_row_details = collab_storage
+  let mut row_details: Vec<(Uuid, RowDetail)> = collab_storage
     .batch_get_collab(&uid, workspace_uuid, query_collabs)
     .await
     .into_iter()
-    .flat_map(|(id, result)| match result {
-      QueryCollabResult::Success { encode_collab_v1 } => {
-        let ec = match EncodedCollab::decode_from_bytes(&encode_collab_v1) {
-          Ok(ec) => ec,
-          Err(err) => {
-            tracing::error!("Failed to decode encoded collab: {:?}", err);
-            return None;
-          },
-        };
-        let id = id.to_string();
-        let options = collab::core::collab::CollabOptions::new(id.to_string(), client_id)
-          .with_data_source(ec.into());
-        let collab = match Collab::new_with_options(CollabOrigin::Server, options) {
-          Ok(collab) => collab,
-          Err(err) => {
-            tracing::error!("Failed to create collab: {:?}", err);
-            return None;
-          },
-        };
-        let row_detail = match RowDetail::from_collab(&collab) {
-          Some(row_detail) => row_detail,
-          None => {
-            tracing::error!("Failed to get row detail from collab: {:?}", collab);
-            return None;
-          },
-        };
-
-        let has_doc = !row_detail.meta.is_document_empty;
-        let cells = get_row_details_serde(row_detail, &field_by_id, &type_option_reader_by_id);
-        Some(AFDatabaseRowDetail {
-          id,
-          cells,
-          has_doc,
-          doc: None,
-        })
-      },
-      QueryCollabResult::Failed { error } => {
-        tracing::warn!("Failed to get collab: {:?}", error);
-        None
-      },
+    .flat_map(|(id, result)| {
+      row_detail_from_query_result(client_id, &id, result).map(|row_detail| (id, row_detail))
+    })
+    .filter(|(id, row_detail)| can_read_row(row_access, id, &row_detail.row))
+    .collect();
+  computed_fields
+    .fill_rows(
+      collab_storage,
+      related_access,
+      uid,
+      workspace_uuid,
+      row_details
+        .iter_mut()
+        .map(|(_, row_detail)| &mut row_detail.row)
+        .collect(),
+    )
+    .await;
+  // field name -> relation field
+  let relation_fields: Vec<(&String, &Field)> = field_by_id
+    .iter()
+    .filter(|(_, field)| FieldType::from(field.field_type) == FieldType::Relation)
+    .collect();
+  let mut expanded_relations = match relation_expansion {
+    Some(expansion) if !relation_fields.is_empty() => {
+      expand_relations(
+        collab_storage,
+        uid,
+        workspace_uuid,
+        &relation_fields
+          .iter()
+          .map(|(_, field)| *field)
+          .collect::<Vec<_>>(),
+        &row_details
+          .iter()
+          .map(|(_, row_detail)| &row_detail.row)
+          .collect::<Vec<_>>(),
+        expansion,
+      )
+      .await
+    },
+    _ => vec![],
+  }
+  .into_iter();
+  let mut db_row_details = row_details
+    .into_iter()
+    .map(|(id, row_detail)| {
+      let has_doc = !row_detail.meta.is_document_empty;
+      let mut cells = get_row_details_serde(row_detail, &field_by_id, &type_option_reader_by_id);
+      if let Some(mut expanded) = expanded_relations.next() {
+        for (name, field) in &relation_fields {
+          if let Some(related_rows) = expanded.remove(&field.id) {
+            cells.insert((*name).clone(), related_rows);
+          }
+        }
+      }
+      AFDatabaseRowDetail {
+        id: id.to_string(),
+        cells,
+        has_doc,
+        doc: None,
+      }
     })
     .collect::<Vec<AFDatabaseRowDetail>>();
 
@@ -972,6 +1658,42 @@ pub async fn list_database_row_details(
   Ok(db_row_details)
 }
 
+/// Decodes the row of a database row collab fetched with `batch_get_collab`.
+pub fn row_detail_from_query_result(
+  client_id: ClientID,
+  row_id: &Uuid,
+  result: QueryCollabResult,
+) -> Option<RowDetail> {
+  let encode_collab_v1 = match result {
+    QueryCollabResult::Success { encode_collab_v1 } => encode_collab_v1,
+    QueryCollabResult::Failed { error } => {
+      tracing::warn!("Failed to get collab: {:?}", error);
+      return None;
+    },
+  };
+  let ec = match EncodedCollab::decode_from_bytes(&encode_collab_v1) {
+    Ok(ec) => ec,
+    Err(err) => {
+      tracing::error!("Failed to decode encoded collab: {:?}", err);
+      return None;
+    },
+  };
+  let options = collab::core::collab::CollabOptions::new(row_id.to_string(), client_id)
+    .with_data_source(ec.into());
+  let collab = match Collab::new_with_options(CollabOrigin::Server, options) {
+    Ok(collab) => collab,
+    Err(err) => {
+      tracing::error!("Failed to create collab: {:?}", err);
+      return None;
+    },
+  };
+  let row_detail = RowDetail::from_collab(&collab);
+  if row_detail.is_none() {
+    tracing::error!("Failed to get row detail from collab: {:?}", collab);
+  }
+  row_detail
+}
+
 fn fill_in_db_row_doc(
   client_id: ClientID,
   row_detail: &mut AFDatabaseRowDetail,
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use app_error::AppError;
+use collab_database::fields::{Field, TypeOptionCellReader};
+use collab_database::rows::Row;
+use database::collab::CollabStore;
+use serde_json::json;
+use shared_entity::dto::workspace_dto::AFRelatedDatabaseRow;
+use uuid::Uuid;
+
+use super::computed_field::{relation_database_id, relation_row_ids, ComputedFields};
+use super::database_view::find_field;
+use super::row_access::{can_read_row, RelatedDatabaseAccess};
+use super::row_query::{get_database_rows, row_cell};
+use super::utils::{get_latest_collab_database_body, type_option_reader_by_id};
+
+/// Expansion of the relation fields to the related rows.
+pub struct RelationExpansion {
+  /// Fields of the related rows, by id or name, returned with their primary field. The fields
+  /// missing from a related database are ignored.
+  pub fields: Vec<String>,
+  /// Checks the access of the user to the related databases and their rows.
+  pub related_access: RelatedDatabaseAccess,
+}
+
+/// Returns the cells of the relation fields of each row, by field id, as the list of the related
+/// rows. The related rows which cannot be read by the user, or which do not belong to the related
+/// database, are left out.
+pub async fn expand_relations(
+  collab_storage: &Arc<dyn CollabStore>,
+  uid: i64,
+  workspace_id: Uuid,
+  relation_fields: &[&Field],
+  rows: &[&Row],
+  expansion: &RelationExpansion,
+) -> Vec<HashMap<String, serde_json::Value>> {
+  let mut row_ids_by_database: HashMap<Uuid, HashSet<Uuid>> = HashMap::new();
+  for field in relation_fields {
+    match relation_database_id(field) {
+      Some(database_id) => row_ids_by_database
+        .entry(database_id)
+        .or_default()
+        .extend(rows.iter().flat_map(|row| relation_row_ids(row, field))),
+      None => tracing::warn!("Related database of relation field {} not found", field.id),
+    }
+  }
+
+  let mut related_databases = HashMap::with_capacity(row_ids_by_database.len());
+  for (database_id, row_ids) in row_ids_by_database {
+    let related = RelatedDatabase::load(
+      collab_storage,
+      uid,
+      workspace_id,
+      database_id,
+      row_ids.into_iter().collect(),
+      expansion,
+    )
+    .await;
+    match related {
+      Ok(related) => {
+        related_databases.insert(database_id, related);
+      },
+      Err(err) => tracing::warn!(
+        "Failed to expand the relations to database {}: {}",
+        database_id,
+        err
+      ),
+    }
+  }
+
+  rows
+    .iter()
+    .map(|row| {
+      relation_fields
+        .iter()
+        .map(|field| {
+          let related_rows: Vec<AFRelatedDatabaseRow> = relation_database_id(field)
+            .and_then(|database_id| related_databases.get(&database_id))
+            .map(|related| {
+              relation_row_ids(row, field)
+                .iter()
+                .filter_map(|row_id| related.related_row(row_id))
+                .collect()
+            })
+            .unwrap_or_default();
+          (field.id.clone(), json!(related_rows))
+        })
+        .collect()
+    })
+    .collect()
+}
+
+/// Readable related rows of a database, with the fields returned for them.
+struct RelatedDatabase {
+  primary_field: Option<Field>,
+  fields: Vec<Field>,
+  reader_by_id: HashMap<String, Box<dyn TypeOptionCellReader>>,
+  rows: HashMap<Uuid, Row>,
+}
+
+impl RelatedDatabase {
+  async fn load(
+    collab_storage: &Arc<dyn CollabStore>,
+    uid: i64,
+    workspace_id: Uuid,
+    database_id: Uuid,
+    row_ids: Vec<Uuid>,
+    expansion: &RelationExpansion,
+  ) -> Result<Self, AppError> {
+    let row_access = expansion
+      .related_access
+      .enforce_read(uid, workspace_id, database_id)
+      .await?;
+    let (db_collab, db_body) =
+      get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+    let all_fields = db_body.fields.get_all_fields(&db_collab.transact());
+
+    // the relation cells are written by the clients, so the rows are checked against the
+    // related database
+    let database_id = database_id.to_string();
+    let mut rows = get_database_rows(collab_storage, uid, workspace_id, &row_ids).await;
+    rows.retain(|row_id, row| {
+      row.database_id == database_id && can_read_row(&row_access, row_id, row)
+    });
+    ComputedFields::new(&all_fields)
+      .fill_rows(
+        collab_storage,
+        &expansion.related_access,
+        uid,
+        workspace_id,
+        rows.values_mut().collect(),
+      )
+      .await;
+
+    let primary_field = all_fields.iter().find(|field| field.is_primary).cloned();
+    let fields: Vec<Field> = expansion
+      .fields
+      .iter()
+      .filter_map(|field| find_field(&all_fields, field).ok())
+      .cloned()
+      .collect();
+    let reader_by_id = type_option_reader_by_id(
+      &primary_field
+        .iter()
+        .chain(fields.iter())
+        .cloned()
+        .collect::<Vec<_>>(),
+    );
+    Ok(Self {
+      primary_field,
+      fields,
+      reader_by_id,
+      rows,
+    })
+  }
+
+  fn related_row(&self, row_id: &Uuid) -> Option<AFRelatedDatabaseRow> {
+    let row = self.rows.get(row_id)?;
+    let primary = self
+      .primary_field
+      .as_ref()
+      .map(|field| self.cell_value(row, field))
+      .unwrap_or_default();
+    let cells = self
+      .fields
+      .iter()
+      .map(|field| (field.name.clone(), self.cell_value(row, field)))
+      .collect();
+    Some(AFRelatedDatabaseRow {
+      id: row_id.to_string(),
+      primary,
+      cells,
+    })
+  }
+
+  fn cell_value(&self, row: &Row, field: &Field) -> serde_json::Value {
+    self
+      .reader_by_id
+      .get(&field.id)
+      .map(|reader| reader.json_cell(&row_cell(row, field)))
+      .unwrap_or_default()
+  }
+}
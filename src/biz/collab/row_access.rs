@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use access_control::act::Action;
+use access_control::collab::CollabAccessControl;
+use access_control::database_row::{DatabaseRowAccess, DatabaseRowAccessControl};
+use app_error::AppError;
+pub use appflowy_collaborate::collab::row_access::can_read_row;
+use appflowy_collaborate::collab::row_access::row_document_id;
+use collab_entity::CollabType;
+use database::collab::{select_collab_workspace_id, CollabStore};
+use database::database_row_access::{
+  delete_database_row_grants, delete_database_row_rule, insert_database_row_grants,
+  select_database_row_grants, select_database_row_rule, select_database_row_rule_ids,
+  upsert_database_row_rule,
+};
+use shared_entity::dto::workspace_dto::{
+  AFDatabaseRowAccessRule, AFDatabaseRowGrant, AFDatabaseRowGrants, AFUpdateDatabaseRowAccessRule,
+  DatabaseRowOperation, UpsertDatatabaseRow,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::database_view::find_field;
+use super::ops::{database_row_id_from_pre_hash, list_database_row_ids};
+use super::row_query::get_database_rows;
+use super::utils::get_latest_collab_database_body;
+use crate::state::AppState;
+
+/// Access of the user to the databases related through the relation fields, whose ids are
+/// written by the clients.
+#[derive(Clone)]
+pub struct RelatedDatabaseAccess {
+  pg_pool: PgPool,
+  collab_access_control: Arc<dyn CollabAccessControl>,
+  row_access_control: Arc<dyn DatabaseRowAccessControl>,
+}
+
+impl RelatedDatabaseAccess {
+  pub fn new(state: &AppState) -> Self {
+    Self {
+      pg_pool: state.pg_pool.clone(),
+      collab_access_control: state.collab_access_control.clone(),
+      row_access_control: state.database_row_access_control.clone(),
+    }
+  }
+
+  /// Checks that the related database belongs to the workspace and can be read by the user.
+  /// Returns the row level rule of the database for the user.
+  pub async fn enforce_read(
+    &self,
+    uid: i64,
+    workspace_id: Uuid,
+    database_id: Uuid,
+  ) -> Result<DatabaseRowAccess, AppError> {
+    let database_workspace_id =
+      select_collab_workspace_id(&self.pg_pool, &database_id, &CollabType::Database).await?;
+    if database_workspace_id != Some(workspace_id) {
+      return Err(AppError::RecordNotFound(format!(
+        "Database {} not found",
+        database_id
+      )));
+    }
+    self
+      .collab_access_control
+      .enforce_action(&workspace_id, &uid, &database_id, Action::Read)
+      .await?;
+    self
+      .row_access_control
+      .get_row_access(&workspace_id, &uid, &database_id)
+      .await
+  }
+}
+
+/// Returns the rows which can be read, in the given order. The rows are only loaded if the
+/// database has a row level rule for the user.
+pub async fn readable_row_ids(
+  collab_storage: &Arc<dyn CollabStore>,
+  uid: i64,
+  workspace_id: Uuid,
+  row_access: &DatabaseRowAccess,
+  row_ids: Vec<Uuid>,
+) -> Vec<Uuid> {
+  if !row_access.is_restricted() {
+    return row_ids;
+  }
+  let rows = get_database_rows(collab_storage, uid, workspace_id, &row_ids).await;
+  row_ids
+    .into_iter()
+    .filter(|row_id| {
+      rows
+        .get(row_id)
+        .map(|row| can_read_row(row_access, row_id, row))
+        .unwrap_or(false)
+    })
+    .collect()
+}
+
+/// Checks the row level rule of the database of the rows which are written. The rows the user
+/// cannot read cannot be written either, the rows which don't exist yet can.
+pub async fn enforce_write_rows(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  row_ids: &[Uuid],
+) -> Result<(), AppError> {
+  let row_access = state
+    .database_row_access_control
+    .get_row_access(&workspace_id, &uid, &database_id)
+    .await?;
+  if !row_access.is_restricted() || row_ids.is_empty() {
+    return Ok(());
+  }
+  let rows = get_database_rows(&state.collab_storage, uid, workspace_id, row_ids).await;
+  if rows
+    .iter()
+    .any(|(row_id, row)| !can_read_row(&row_access, row_id, row))
+  {
+    return Err(AppError::NotEnoughPermissions);
+  }
+  Ok(())
+}
+
+/// Returns the rows written by the operations, which may exist already: the upserted and the
+/// deleted rows.
+pub fn written_row_ids(
+  workspace_id: Uuid,
+  database_id: Uuid,
+  operations: &[DatabaseRowOperation],
+) -> Vec<Uuid> {
+  operations
+    .iter()
+    .filter_map(|operation| match operation {
+      DatabaseRowOperation::Insert(_) => None,
+      DatabaseRowOperation::Upsert(UpsertDatatabaseRow { pre_hash, .. }) => Some(
+        database_row_id_from_pre_hash(workspace_id, database_id, pre_hash),
+      ),
+      DatabaseRowOperation::Delete { row_id } => Uuid::parse_str(row_id).ok(),
+    })
+    .collect()
+}
+
+/// Checks the row level rules for reading the collab, which applies to the database rows and
+/// to the documents of the rows.
+pub async fn enforce_read_row_collab(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  object_id: Uuid,
+  collab_type: CollabType,
+) -> Result<(), AppError> {
+  let hidden = hidden_row_collabs(state, uid, workspace_id, &[(object_id, collab_type)]).await?;
+  if hidden.contains(&object_id) {
+    return Err(AppError::NotEnoughPermissions);
+  }
+  Ok(())
+}
+
+/// Returns the collabs, among the given ones, which the row level rules hide from the user: the
+/// rows the user cannot read, and their documents. The documents of the rows are found in the
+/// databases which restrict the user, as their ids are derived from the ids of the rows.
+pub async fn hidden_row_collabs(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  collabs: &[(Uuid, CollabType)],
+) -> Result<HashSet<Uuid>, AppError> {
+  let is_row_collab = |collab_type: &CollabType| {
+    matches!(collab_type, CollabType::DatabaseRow | CollabType::Document)
+  };
+  if !collabs
+    .iter()
+    .any(|(_, collab_type)| is_row_collab(collab_type))
+  {
+    return Ok(HashSet::new());
+  }
+  let mut restricted = HashMap::new();
+  for database_id in select_database_row_rule_ids(&state.pg_pool, &workspace_id).await? {
+    let row_access = state
+      .database_row_access_control
+      .get_row_access(&workspace_id, &uid, &database_id)
+      .await?;
+    if row_access.is_restricted() {
+      restricted.insert(database_id, row_access);
+    }
+  }
+  if restricted.is_empty() {
+    return Ok(HashSet::new());
+  }
+
+  let mut row_by_document = HashMap::new();
+  if collabs
+    .iter()
+    .any(|(_, collab_type)| *collab_type == CollabType::Document)
+  {
+    for database_id in restricted.keys() {
+      let rows =
+        match list_database_row_ids(&state.collab_storage, workspace_id, *database_id).await {
+          Ok(rows) => rows,
+          Err(AppError::RecordNotFound(_)) => continue,
+          Err(err) => return Err(err),
+        };
+      for row_id in rows.iter().flat_map(|row| Uuid::parse_str(&row.id)) {
+        if let Some(document_id) = row_document_id(&row_id) {
+          row_by_document.insert(document_id, row_id);
+        }
+      }
+    }
+  }
+
+  // the collabs, with the row they are read as
+  let row_collabs: Vec<(Uuid, Uuid)> = collabs
+    .iter()
+    .filter_map(|(object_id, collab_type)| match collab_type {
+      CollabType::DatabaseRow => Some((*object_id, *object_id)),
+      CollabType::Document => row_by_document
+        .get(object_id)
+        .map(|row_id| (*object_id, *row_id)),
+      _ => None,
+    })
+    .collect();
+  let row_ids: Vec<Uuid> = row_collabs
+    .iter()
+    .map(|(_, row_id)| *row_id)
+    .collect::<HashSet<_>>()
+    .into_iter()
+    .collect();
+  let rows = get_database_rows(&state.collab_storage, uid, workspace_id, &row_ids).await;
+  Ok(
+    row_collabs
+      .into_iter()
+      .filter(|(_, row_id)| {
+        // rows which don't exist yet can be read, like other collabs
+        rows.get(row_id).is_some_and(|row| {
+          Uuid::parse_str(&row.database_id)
+            .ok()
+            .and_then(|database_id| restricted.get(&database_id))
+            .is_some_and(|row_access| !can_read_row(row_access, row_id, row))
+        })
+      })
+      .map(|(object_id, _)| object_id)
+      .collect(),
+  )
+}
+
+pub async fn get_database_row_access_rule(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+) -> Result<AFDatabaseRowAccessRule, AppError> {
+  let rule = select_database_row_rule(pg_pool, &workspace_id, &database_id)
+    .await?
+    .ok_or_else(|| {
+      AppError::RecordNotFound(format!("Database {} has no row access rule", database_id))
+    })?;
+  let grants = select_database_row_grants(pg_pool, &database_id)
+    .await?
+    .into_iter()
+    .map(|grant| AFDatabaseRowGrant {
+      row_id: grant.row_id.to_string(),
+      uid: grant.uid,
+    })
+    .collect();
+  Ok(AFDatabaseRowAccessRule {
+    user_field_id: rule.user_field_id,
+    grants,
+  })
+}
+
+/// Creates or replaces the row level rule of the database. The grants are kept.
+pub async fn set_database_row_access_rule(
+  pg_pool: &PgPool,
+  collab_storage: &Arc<dyn CollabStore>,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  params: AFUpdateDatabaseRowAccessRule,
+) -> Result<AFDatabaseRowAccessRule, AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+  let user_field_id = match &params.user_field {
+    Some(user_field) => {
+      let fields = db_body.fields.get_all_fields(&db_collab.transact());
+      Some(find_field(&fields, user_field)?.id.clone())
+    },
+    None => None,
+  };
+  upsert_database_row_rule(
+    pg_pool,
+    &workspace_id,
+    &database_id,
+    user_field_id.as_deref(),
+  )
+  .await?;
+  get_database_row_access_rule(pg_pool, workspace_id, database_id).await
+}
+
+pub async fn remove_database_row_access_rule(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+) -> Result<(), AppError> {
+  delete_database_row_rule(pg_pool, &workspace_id, &database_id).await
+}
+
+pub async fn add_database_row_grants(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  grants: AFDatabaseRowGrants,
+) -> Result<AFDatabaseRowAccessRule, AppError> {
+  let row_ids = parse_row_ids(&grants.row_ids)?;
+  // the grants belong to the rule of the database
+  get_database_row_access_rule(pg_pool, workspace_id, database_id).await?;
+  insert_database_row_grants(pg_pool, &database_id, &row_ids, &grants.uids).await?;
+  get_database_row_access_rule(pg_pool, workspace_id, database_id).await
+}
+
+pub async fn remove_database_row_grants(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  grants: AFDatabaseRowGrants,
+) -> Result<AFDatabaseRowAccessRule, AppError> {
+  let row_ids = parse_row_ids(&grants.row_ids)?;
+  get_database_row_access_rule(pg_pool, workspace_id, database_id).await?;
+  delete_database_row_grants(pg_pool, &database_id, &row_ids, &grants.uids).await?;
+  get_database_row_access_rule(pg_pool, workspace_id, database_id).await
+}
+
+fn parse_row_ids(row_ids: &[String]) -> Result<Vec<Uuid>, AppError> {
+  row_ids
+    .iter()
+    .map(|row_id| {
+      Uuid::parse_str(row_id)
+        .map_err(|_| AppError::InvalidRequest(format!("Invalid row id: {}", row_id)))
+    })
+    .collect()
+}
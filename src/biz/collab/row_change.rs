@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use access_control::database_row::DatabaseRowAccess;
+use app_error::AppError;
+use database::collab::CollabStore;
+use database::database_row_change::{select_database_row_changes, ROW_CREATED, ROW_DELETED};
+use itertools::Itertools;
+use shared_entity::dto::workspace_dto::{
+  DatabaseRowChange, DatabaseRowChangeType, DatabaseRowChangesResponse, ListDatabaseRowChangesParam,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::row_access::readable_row_ids;
+
+const DEFAULT_CHANGES_LIMIT: i64 = 100;
+const MAX_CHANGES_LIMIT: i64 = 1000;
+
+/// Returns the changes of the rows of the database after the given cursor. The changes are
+/// recorded from the collab update stream when the updates of the workspace are flushed, one per
+/// update, so they are listed once the updates are saved. Changes older than
+/// [database::database_row_change::ROW_CHANGE_RETENTION_DAYS] days are pruned.
+pub async fn list_database_row_changes(
+  collab_storage: &Arc<dyn CollabStore>,
+  pg_pool: &PgPool,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  param: ListDatabaseRowChangesParam,
+  row_access: &DatabaseRowAccess,
+) -> Result<DatabaseRowChangesResponse, AppError> {
+  let after = match &param.cursor {
+    Some(cursor) => cursor
+      .parse::<i64>()
+      .map_err(|_| AppError::InvalidRequest(format!("Invalid cursor: {}", cursor)))?,
+    None => 0,
+  };
+  let limit = param
+    .limit
+    .map(|limit| (limit as i64).clamp(1, MAX_CHANGES_LIMIT))
+    .unwrap_or(DEFAULT_CHANGES_LIMIT);
+
+  let mut rows = select_database_row_changes(pg_pool, &database_id, after, limit + 1).await?;
+  let has_more = rows.len() as i64 > limit;
+  rows.truncate(limit as usize);
+  // the cursor moves past the changes of the rows which can't be read
+  let cursor = rows
+    .last()
+    .map(|row| row.change_id.to_string())
+    .or(param.cursor);
+  if row_access.is_restricted() {
+    let changed_row_ids: Vec<Uuid> = rows
+      .iter()
+      .filter(|row| row.change_type != ROW_DELETED)
+      .map(|row| row.row_id)
+      .unique()
+      .collect();
+    let readable_row_ids: HashSet<Uuid> = readable_row_ids(
+      collab_storage,
+      uid,
+      workspace_id,
+      row_access,
+      changed_row_ids,
+    )
+    .await
+    .into_iter()
+    .collect();
+    rows.retain(|row| row.change_type == ROW_DELETED || readable_row_ids.contains(&row.row_id));
+  }
+  let changes: Vec<DatabaseRowChange> = rows
+    .into_iter()
+    .map(|row| DatabaseRowChange {
+      cursor: row.change_id.to_string(),
+      row_id: row.row_id.to_string(),
+      change_type: match row.change_type {
+        ROW_CREATED => DatabaseRowChangeType::Created,
+        ROW_DELETED => DatabaseRowChangeType::Deleted,
+        _ => DatabaseRowChangeType::Updated,
+      },
+      cells: serde_json::from_value(row.cells).unwrap_or_default(),
+      changed_at: row.changed_at,
+    })
+    .collect();
+  Ok(DatabaseRowChangesResponse {
+    changes,
+    cursor,
+    has_more,
+  })
+}
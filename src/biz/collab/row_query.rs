@@ -0,0 +1,910 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+use access_control::database_row::DatabaseRowAccess;
+use app_error::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use collab::core::collab::default_client_id;
+use collab_database::entity::FieldType;
+use collab_database::fields::select_type_option::{
+  MultiSelectTypeOption, SelectOption, SingleSelectTypeOption,
+};
+use collab_database::fields::{Field, TypeOptionCellReader};
+use collab_database::rows::{Cell, Row};
+use collab_database::template::entity::CELL_DATA;
+use collab_database::template::timestamp_parse::TimestampCellData;
+use collab_entity::CollabType;
+use database::collab::CollabStore;
+use database_entity::dto::QueryCollab;
+use serde::{Deserialize, Serialize};
+use shared_entity::dto::workspace_dto::{
+  DatabaseRowFilter, DatabaseRowFilterCondition, DatabaseRowSort, QueryDatabaseRowsParams,
+  QueryDatabaseRowsResponse,
+};
+use uuid::Uuid;
+use yrs::Any;
+
+use super::computed_field::ComputedFields;
+use super::database_view::find_field;
+use super::ops::{list_database_row_details, row_detail_from_query_result};
+use super::row_access::{can_read_row, RelatedDatabaseAccess};
+use super::utils::{get_latest_collab_database_body, type_option_reader_by_id, SECONDS_PER_DAY};
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+pub const MAX_QUERY_LIMIT: usize = 1000;
+/// Minimum number of row collabs loaded at once.
+const ROW_BATCH_SIZE: usize = 100;
+
+/// Filter type of a saved filter, whose children match if any of them matches.
+const SAVED_OR_FILTER_TYPE: i64 = 2;
+const SAVED_SORT_DESCENDING: i64 = 1;
+
+/// Returns a page of the rows of the database, which match the filters, in the order of the
+/// sorts. The rows are loaded from their collabs, so the filters see the latest cell values.
+#[allow(clippy::too_many_arguments)]
+pub async fn query_database_rows(
+  collab_storage: &Arc<dyn CollabStore>,
+  related_access: &RelatedDatabaseAccess,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  params: QueryDatabaseRowsParams,
+  unsupported_field_types: &[FieldType],
+  row_access: &DatabaseRowAccess,
+) -> Result<QueryDatabaseRowsResponse, AppError> {
+  let limit = params
+    .limit
+    .map(|limit| (limit as usize).clamp(1, MAX_QUERY_LIMIT))
+    .unwrap_or(DEFAULT_QUERY_LIMIT);
+  let (page_row_ids, next_cursor) = query_database_row_ids(
+    collab_storage,
+    related_access,
+    uid,
+    workspace_id,
+    database_id,
+    &params,
+    Some(limit),
+    unsupported_field_types,
+    row_access,
+  )
+  .await?;
+
+  let mut page_rows = list_database_row_details(
+    collab_storage,
+    related_access,
+    uid,
+    workspace_id,
+    database_id,
+    &page_row_ids,
+    unsupported_field_types,
+    params.with_doc.unwrap_or_default(),
+    row_access,
+    None,
+  )
+  .await?;
+  let position_by_id: HashMap<String, usize> = page_row_ids
+    .iter()
+    .enumerate()
+    .map(|(position, row_id)| (row_id.to_string(), position))
+    .collect();
+  page_rows.sort_by_key(|row| position_by_id.get(&row.id).copied());
+
+  Ok(QueryDatabaseRowsResponse {
+    rows: page_rows,
+    next_cursor,
+  })
+}
+
+/// Returns the ids of the rows of the database, which match the filters, in the order of the
+/// sorts, from the cursor of the params. With a limit, only the first rows are returned, along
+/// with the cursor of the next page if there are more.
+///
+/// Without sorts, the rows are read in the order of the view from the row of the cursor, until
+/// the page is full. Sorted rows are ordered by their sort keys then by id, and the cursor holds
+/// the keys of the last row of the page, so that rows added or removed in between do not shift
+/// the following pages.
+#[allow(clippy::too_many_arguments)]
+pub async fn query_database_row_ids(
+  collab_storage: &Arc<dyn CollabStore>,
+  related_access: &RelatedDatabaseAccess,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  params: &QueryDatabaseRowsParams,
+  limit: Option<usize>,
+  unsupported_field_types: &[FieldType],
+  row_access: &DatabaseRowAccess,
+) -> Result<(Vec<Uuid>, Option<String>), AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(collab_storage, workspace_id, database_id).await?;
+  let (all_fields, view) = {
+    let txn = db_collab.transact();
+    let view_id = match &params.view_id {
+      Some(view_id) => view_id.clone(),
+      None => db_body.get_inline_view_id(&txn),
+    };
+    let view = db_body
+      .views
+      .get_view(&txn, &view_id)
+      .ok_or_else(|| AppError::RecordNotFound(format!("Database view {} not found", view_id)))?;
+    (db_body.fields.get_all_fields(&txn), view)
+  };
+  let computed_fields = ComputedFields::new(&all_fields);
+  let fields: Vec<Field> = all_fields
+    .iter()
+    .filter(|field| !unsupported_field_types.contains(&FieldType::from(field.field_type)))
+    .cloned()
+    .collect();
+  let query = RowQuery::new(&fields);
+
+  let mut filters = vec![];
+  let mut sorts = vec![];
+  if params.view_id.is_some() {
+    filters.extend(
+      view
+        .filters
+        .iter()
+        .filter_map(|filter| query.saved_filter(filter)),
+    );
+    sorts.extend(view.sorts.iter().filter_map(|sort| query.saved_sort(sort)));
+  }
+  for filter in &params.filters {
+    filters.push(query.filter(filter)?);
+  }
+  for sort in &params.sorts {
+    sorts.push(query.sort(sort)?);
+  }
+
+  let cursor = params
+    .cursor
+    .as_deref()
+    .map(|cursor| RowCursor::decode(cursor, sorts.len()))
+    .transpose()?;
+
+  let row_ids: Vec<Uuid> = view
+    .row_orders
+    .iter()
+    .flat_map(|row_order| Uuid::parse_str(row_order.id.as_str()))
+    .collect();
+  let loader = QueryRows {
+    collab_storage,
+    related_access,
+    uid,
+    workspace_id,
+    row_access,
+    computed_fields: &computed_fields,
+  };
+  let mut matched_rows: Vec<(Vec<Option<SortKey>>, Uuid)> = vec![];
+  if sorts.is_empty() {
+    let start = match &cursor {
+      Some(cursor) => {
+        row_ids
+          .iter()
+          .position(|row_id| *row_id == cursor.row_id)
+          .ok_or_else(|| {
+            AppError::InvalidRequest(
+              "The last row of the previous page is no longer in the database view".to_string(),
+            )
+          })?
+          + 1
+      },
+      None => 0,
+    };
+    let batch_size = limit.map_or(ROW_BATCH_SIZE, |limit| (limit + 1).max(ROW_BATCH_SIZE));
+    for batch in row_ids[start..].chunks(batch_size) {
+      let batch_rows = loader.load(batch).await;
+      matched_rows.extend(
+        batch
+          .iter()
+          .filter(|row_id| {
+            batch_rows
+              .get(row_id)
+              .is_some_and(|row| filters.iter().all(|filter| query.matches(row, filter)))
+          })
+          .map(|row_id| (vec![], *row_id)),
+      );
+      if limit.is_some_and(|limit| matched_rows.len() > limit) {
+        break;
+      }
+    }
+  } else {
+    // Every row is needed to order them, but only the sort keys of the rows after the cursor
+    // are kept
+    for batch in row_ids.chunks(ROW_BATCH_SIZE) {
+      let batch_rows = loader.load(batch).await;
+      for row_id in batch {
+        let Some(row) = batch_rows.get(row_id) else {
+          continue;
+        };
+        if !filters.iter().all(|filter| query.matches(row, filter)) {
+          continue;
+        }
+        let keys = sorts
+          .iter()
+          .map(|sort| query.sort_key(row, &sort.field_id))
+          .collect::<Vec<_>>();
+        let after_cursor = cursor.as_ref().is_none_or(|cursor| {
+          compare_rows(&keys, row_id, &cursor.keys, &cursor.row_id, &sorts) == Ordering::Greater
+        });
+        if after_cursor {
+          matched_rows.push((keys, *row_id));
+        }
+      }
+    }
+    matched_rows
+      .sort_by(|(a_keys, a_id), (b_keys, b_id)| compare_rows(a_keys, a_id, b_keys, b_id, &sorts));
+  }
+
+  let mut next_cursor = None;
+  if let Some(limit) = limit.filter(|limit| matched_rows.len() > *limit) {
+    matched_rows.truncate(limit);
+    if let Some((keys, row_id)) = matched_rows.last() {
+      let cursor = RowCursor {
+        keys: keys.clone(),
+        row_id: *row_id,
+      };
+      next_cursor = Some(cursor.encode()?);
+    }
+  }
+  let row_ids = matched_rows.into_iter().map(|(_, row_id)| row_id).collect();
+  Ok((row_ids, next_cursor))
+}
+
+/// Loads the rows, which the user can read, with the values of their computed fields.
+struct QueryRows<'a> {
+  collab_storage: &'a Arc<dyn CollabStore>,
+  related_access: &'a RelatedDatabaseAccess,
+  uid: i64,
+  workspace_id: Uuid,
+  row_access: &'a DatabaseRowAccess,
+  computed_fields: &'a ComputedFields<'a>,
+}
+
+impl QueryRows<'_> {
+  async fn load(&self, row_ids: &[Uuid]) -> HashMap<Uuid, Row> {
+    let mut rows =
+      get_database_rows(self.collab_storage, self.uid, self.workspace_id, row_ids).await;
+    rows.retain(|row_id, row| can_read_row(self.row_access, row_id, row));
+    // The filters and sorts apply to the values of the computed fields too
+    self
+      .computed_fields
+      .fill_rows(
+        self.collab_storage,
+        self.related_access,
+        self.uid,
+        self.workspace_id,
+        rows.values_mut().collect(),
+      )
+      .await;
+    rows
+  }
+}
+
+/// Position after the last row of a page.
+#[derive(Serialize, Deserialize)]
+struct RowCursor {
+  /// Sort keys of the row, empty when the rows are not sorted.
+  keys: Vec<Option<SortKey>>,
+  row_id: Uuid,
+}
+
+impl RowCursor {
+  fn encode(&self) -> Result<String, AppError> {
+    Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?))
+  }
+
+  fn decode(cursor: &str, sort_count: usize) -> Result<Self, AppError> {
+    URL_SAFE_NO_PAD
+      .decode(cursor)
+      .ok()
+      .and_then(|data| serde_json::from_slice::<Self>(&data).ok())
+      .filter(|cursor| cursor.keys.len() == sort_count)
+      .ok_or_else(|| AppError::InvalidRequest(format!("Invalid cursor: {}", cursor)))
+  }
+}
+
+pub async fn get_database_rows(
+  collab_storage: &Arc<dyn CollabStore>,
+  uid: i64,
+  workspace_id: Uuid,
+  row_ids: &[Uuid],
+) -> HashMap<Uuid, Row> {
+  let client_id = default_client_id();
+  let query_collabs: Vec<QueryCollab> = row_ids
+    .iter()
+    .map(|id| QueryCollab {
+      object_id: *id,
+      collab_type: CollabType::DatabaseRow,
+    })
+    .collect();
+  collab_storage
+    .batch_get_collab(&uid, workspace_id, query_collabs)
+    .await
+    .into_iter()
+    .flat_map(|(id, result)| {
+      row_detail_from_query_result(client_id, &id, result).map(|row_detail| (id, row_detail.row))
+    })
+    .collect()
+}
+
+enum RowFilter {
+  Cell {
+    field_id: String,
+    condition: CellCondition,
+  },
+  And(Vec<RowFilter>),
+  Or(Vec<RowFilter>),
+}
+
+enum CellCondition {
+  Text {
+    op: TextOp,
+    value: String,
+  },
+  Empty(bool),
+  Number {
+    min: Bound<f64>,
+    max: Bound<f64>,
+    negate: bool,
+  },
+  SelectOption {
+    option_ids: Vec<String>,
+    negate: bool,
+  },
+  Checkbox(bool),
+  Date {
+    start: Option<i64>,
+    end: Option<i64>,
+  },
+}
+
+impl CellCondition {
+  /// Lowercases the text of a text condition, as the text of the cells is compared in lowercase.
+  fn ignore_case(self) -> Self {
+    match self {
+      CellCondition::Text { op, value } => CellCondition::Text {
+        op,
+        value: value.to_lowercase(),
+      },
+      condition => condition,
+    }
+  }
+}
+
+enum TextOp {
+  Is,
+  IsNot,
+  Contains,
+  DoesNotContain,
+  StartsWith,
+  EndsWith,
+}
+
+struct RowSort {
+  field_id: String,
+  descending: bool,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+enum SortKey {
+  Number(f64),
+  Text(String),
+}
+
+impl SortKey {
+  /// Orders numbers before texts. Numbers are totally ordered, so a parsed NaN sorts after infinity.
+  fn total_cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+      (SortKey::Text(a), SortKey::Text(b)) => a.cmp(b),
+      (SortKey::Number(_), SortKey::Text(_)) => Ordering::Less,
+      (SortKey::Text(_), SortKey::Number(_)) => Ordering::Greater,
+    }
+  }
+}
+
+struct RowQuery<'a> {
+  fields: &'a [Field],
+  field_by_id: HashMap<&'a str, &'a Field>,
+  reader_by_id: HashMap<String, Box<dyn TypeOptionCellReader>>,
+}
+
+impl<'a> RowQuery<'a> {
+  fn new(fields: &'a [Field]) -> Self {
+    Self {
+      fields,
+      field_by_id: fields
+        .iter()
+        .map(|field| (field.id.as_str(), field))
+        .collect(),
+      reader_by_id: type_option_reader_by_id(fields),
+    }
+  }
+
+  fn filter(&self, filter: &DatabaseRowFilter) -> Result<RowFilter, AppError> {
+    let field = find_field(self.fields, &filter.field)?;
+    let condition = match &filter.condition {
+      DatabaseRowFilterCondition::TextContains { value } => CellCondition::Text {
+        op: TextOp::Contains,
+        value: value.clone(),
+      },
+      DatabaseRowFilterCondition::TextIs { value } => CellCondition::Text {
+        op: TextOp::Is,
+        value: value.clone(),
+      },
+      DatabaseRowFilterCondition::IsEmpty => CellCondition::Empty(true),
+      DatabaseRowFilterCondition::IsNotEmpty => CellCondition::Empty(false),
+      DatabaseRowFilterCondition::NumberRange { min, max } => CellCondition::Number {
+        min: min.map(Bound::Included).unwrap_or(Bound::Unbounded),
+        max: max.map(Bound::Included).unwrap_or(Bound::Unbounded),
+        negate: false,
+      },
+      DatabaseRowFilterCondition::SelectOptionIs { options } => CellCondition::SelectOption {
+        option_ids: select_option_ids(field, options)?,
+        negate: false,
+      },
+      DatabaseRowFilterCondition::SelectOptionIsNot { options } => CellCondition::SelectOption {
+        option_ids: select_option_ids(field, options)?,
+        negate: true,
+      },
+      DatabaseRowFilterCondition::Checkbox { checked } => CellCondition::Checkbox(*checked),
+      DatabaseRowFilterCondition::DateRange { start, end } => CellCondition::Date {
+        start: *start,
+        end: *end,
+      },
+    };
+    Ok(RowFilter::Cell {
+      field_id: field.id.clone(),
+      condition: condition.ignore_case(),
+    })
+  }
+
+  fn sort(&self, sort: &DatabaseRowSort) -> Result<RowSort, AppError> {
+    Ok(RowSort {
+      field_id: find_field(self.fields, &sort.field)?.id.clone(),
+      descending: sort.descending,
+    })
+  }
+
+  /// Converts a filter saved on a database view by the AppFlowy clients. Filters, which are not
+  /// supported or refer to a deleted field, are ignored.
+  fn saved_filter(&self, filter: &HashMap<String, Any>) -> Option<RowFilter> {
+    if let Some(Any::Array(children)) = filter.get("children") {
+      let children = children
+        .iter()
+        .filter_map(|child| match child {
+          Any::Map(child) => self.saved_filter(child),
+          _ => None,
+        })
+        .collect();
+      return match filter.get("filter_type").and_then(any_to_i64) {
+        Some(SAVED_OR_FILTER_TYPE) => Some(RowFilter::Or(children)),
+        _ => Some(RowFilter::And(children)),
+      };
+    }
+
+    let field = self
+      .field_by_id
+      .get(filter.get("field_id").and_then(any_to_string)?.as_str())?;
+    let condition_type = filter.get("condition").and_then(any_to_i64)?;
+    let content = filter
+      .get("content")
+      .and_then(any_to_string)
+      .unwrap_or_default();
+    let condition = saved_cell_condition(field, condition_type, &content);
+    if condition.is_none() {
+      tracing::debug!(
+        "Ignore unsupported filter condition {} on field {}",
+        condition_type,
+        field.id
+      );
+    }
+    Some(RowFilter::Cell {
+      field_id: field.id.clone(),
+      condition: condition?.ignore_case(),
+    })
+  }
+
+  fn saved_sort(&self, sort: &HashMap<String, Any>) -> Option<RowSort> {
+    let field_id = sort.get("field_id").and_then(any_to_string)?;
+    self.field_by_id.get(field_id.as_str())?;
+    Some(RowSort {
+      field_id,
+      descending: sort.get("condition").and_then(any_to_i64) == Some(SAVED_SORT_DESCENDING),
+    })
+  }
+
+  fn matches(&self, row: &Row, filter: &RowFilter) -> bool {
+    match filter {
+      RowFilter::And(children) => children.iter().all(|child| self.matches(row, child)),
+      RowFilter::Or(children) => {
+        children.is_empty() || children.iter().any(|child| self.matches(row, child))
+      },
+      RowFilter::Cell {
+        field_id,
+        condition,
+      } => match self.field_by_id.get(field_id.as_str()) {
+        Some(field) => self.cell_matches(row, field, condition),
+        None => true,
+      },
+    }
+  }
+
+  fn cell_matches(&self, row: &Row, field: &Field, condition: &CellCondition) -> bool {
+    match condition {
+      CellCondition::Text { op, value } => {
+        let text = self.cell_text(row, field).to_lowercase();
+        match op {
+          TextOp::Is => text == *value,
+          TextOp::IsNot => text != *value,
+          TextOp::Contains => text.contains(value.as_str()),
+          TextOp::DoesNotContain => !text.contains(value.as_str()),
+          TextOp::StartsWith => text.starts_with(value.as_str()),
+          TextOp::EndsWith => text.ends_with(value.as_str()),
+        }
+      },
+      CellCondition::Empty(empty) => self.is_cell_empty(row, field) == *empty,
+      CellCondition::Number { min, max, negate } => {
+        let in_range = self
+          .cell_number(row, field)
+          .map(|number| (*min, *max).contains(&number))
+          .unwrap_or(false);
+        in_range != *negate
+      },
+      CellCondition::SelectOption { option_ids, negate } => {
+        let selected = cell_select_option_ids(row, field)
+          .iter()
+          .any(|id| option_ids.contains(id));
+        selected != *negate
+      },
+      CellCondition::Checkbox(checked) => is_cell_checked(row, field) == *checked,
+      CellCondition::Date { start, end } => match cell_timestamp(row, field) {
+        Some(timestamp) => {
+          start.is_none_or(|start| timestamp >= start) && end.is_none_or(|end| timestamp <= end)
+        },
+        None => false,
+      },
+    }
+  }
+
+  fn sort_key(&self, row: &Row, field_id: &str) -> Option<SortKey> {
+    let field = self.field_by_id.get(field_id)?;
+    match FieldType::from(field.field_type) {
+      FieldType::Number => self.cell_number(row, field).map(SortKey::Number),
+      FieldType::DateTime | FieldType::CreatedTime | FieldType::LastEditedTime => {
+        cell_timestamp(row, field).map(|timestamp| SortKey::Number(timestamp as f64))
+      },
+      FieldType::Checkbox => Some(SortKey::Number(if is_cell_checked(row, field) {
+        1.0
+      } else {
+        0.0
+      })),
+      _ => {
+        let text = self.cell_text(row, field);
+        (!text.is_empty()).then(|| SortKey::Text(text.to_lowercase()))
+      },
+    }
+  }
+
+  fn cell_text(&self, row: &Row, field: &Field) -> String {
+    let value = match self.reader_by_id.get(&field.id) {
+      Some(reader) => reader.json_cell(&row_cell(row, field)),
+      None => serde_json::Value::Null,
+    };
+    json_to_text(&value)
+  }
+
+  fn cell_number(&self, row: &Row, field: &Field) -> Option<f64> {
+    self
+      .reader_by_id
+      .get(&field.id)?
+      .numeric_cell(row.cells.get(&field.id)?)
+  }
+
+  fn is_cell_empty(&self, row: &Row, field: &Field) -> bool {
+    match FieldType::from(field.field_type) {
+      FieldType::Checkbox => !is_cell_checked(row, field),
+      FieldType::SingleSelect | FieldType::MultiSelect => {
+        cell_select_option_ids(row, field).is_empty()
+      },
+      FieldType::DateTime => cell_timestamp(row, field).is_none(),
+      _ => self.cell_text(row, field).trim().is_empty(),
+    }
+  }
+}
+
+/// Orders the rows by their sort keys, then by id.
+fn compare_rows(
+  a_keys: &[Option<SortKey>],
+  a_id: &Uuid,
+  b_keys: &[Option<SortKey>],
+  b_id: &Uuid,
+  sorts: &[RowSort],
+) -> Ordering {
+  compare_sort_keys(a_keys, b_keys, sorts).then_with(|| a_id.cmp(b_id))
+}
+
+fn compare_sort_keys(a: &[Option<SortKey>], b: &[Option<SortKey>], sorts: &[RowSort]) -> Ordering {
+  for ((a, b), sort) in a.iter().zip(b).zip(sorts) {
+    let ordering = match (a, b) {
+      (Some(a), Some(b)) => {
+        let ordering = a.total_cmp(b);
+        if sort.descending {
+          ordering.reverse()
+        } else {
+          ordering
+        }
+      },
+      // Empty cells always come last
+      (Some(_), None) => Ordering::Less,
+      (None, Some(_)) => Ordering::Greater,
+      (None, None) => Ordering::Equal,
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+  Ordering::Equal
+}
+
+/// Maps the conditions of the AppFlowy clients' text, number, checkbox, select option and date
+/// filters.
+fn saved_cell_condition(field: &Field, condition: i64, content: &str) -> Option<CellCondition> {
+  match FieldType::from(field.field_type) {
+    FieldType::RichText | FieldType::URL | FieldType::Summary | FieldType::Translate => {
+      let op = match condition {
+        0 => TextOp::Is,
+        1 => TextOp::IsNot,
+        2 => TextOp::Contains,
+        3 => TextOp::DoesNotContain,
+        4 => TextOp::StartsWith,
+        5 => TextOp::EndsWith,
+        6 => return Some(CellCondition::Empty(true)),
+        7 => return Some(CellCondition::Empty(false)),
+        _ => return None,
+      };
+      Some(CellCondition::Text {
+        op,
+        value: content.to_string(),
+      })
+    },
+    FieldType::Number => {
+      let (min, max, negate) = match condition {
+        6 => return Some(CellCondition::Empty(true)),
+        7 => return Some(CellCondition::Empty(false)),
+        _ => {
+          let number = content.trim().parse::<f64>().ok()?;
+          match condition {
+            0 => (Bound::Included(number), Bound::Included(number), false),
+            1 => (Bound::Included(number), Bound::Included(number), true),
+            2 => (Bound::Excluded(number), Bound::Unbounded, false),
+            3 => (Bound::Unbounded, Bound::Excluded(number), false),
+            4 => (Bound::Included(number), Bound::Unbounded, false),
+            5 => (Bound::Unbounded, Bound::Included(number), false),
+            _ => return None,
+          }
+        },
+      };
+      Some(CellCondition::Number { min, max, negate })
+    },
+    FieldType::Checkbox => match condition {
+      0 => Some(CellCondition::Checkbox(true)),
+      1 => Some(CellCondition::Checkbox(false)),
+      _ => None,
+    },
+    FieldType::SingleSelect | FieldType::MultiSelect => {
+      let option_ids = content
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+        .collect();
+      match condition {
+        0 | 2 => Some(CellCondition::SelectOption {
+          option_ids,
+          negate: false,
+        }),
+        1 | 3 => Some(CellCondition::SelectOption {
+          option_ids,
+          negate: true,
+        }),
+        4 => Some(CellCondition::Empty(true)),
+        5 => Some(CellCondition::Empty(false)),
+        _ => None,
+      }
+    },
+    FieldType::DateTime | FieldType::CreatedTime | FieldType::LastEditedTime => {
+      // The dates are compared by day
+      let content = serde_json::from_str::<serde_json::Value>(content).unwrap_or_default();
+      let day = |key: &str| {
+        content
+          .get(key)
+          .and_then(|value| value.as_i64())
+          .map(|timestamp| timestamp - timestamp.rem_euclid(SECONDS_PER_DAY))
+      };
+      let (start, end) = match condition {
+        0 => (None, Some(day("timestamp")? - 1)),
+        1 => (Some(day("timestamp")? + SECONDS_PER_DAY), None),
+        2 => (None, Some(day("timestamp")? + SECONDS_PER_DAY - 1)),
+        3 => (Some(day("timestamp")?), None),
+        4 => (Some(day("start")?), Some(day("end")? + SECONDS_PER_DAY - 1)),
+        5 => return Some(CellCondition::Empty(true)),
+        6 => return Some(CellCondition::Empty(false)),
+        _ => return None,
+      };
+      Some(CellCondition::Date { start, end })
+    },
+    _ => None,
+  }
+}
+
+/// Converts a filter condition saved by the AppFlowy clients to a [DatabaseRowFilterCondition],
+/// when it can be expressed as one.
+pub fn saved_filter_condition(
+  field: &Field,
+  condition: i64,
+  content: &str,
+) -> Option<DatabaseRowFilterCondition> {
+  let condition = match saved_cell_condition(field, condition, content)? {
+    CellCondition::Text {
+      op: TextOp::Is,
+      value,
+    } => DatabaseRowFilterCondition::TextIs { value },
+    CellCondition::Text {
+      op: TextOp::Contains,
+      value,
+    } => DatabaseRowFilterCondition::TextContains { value },
+    CellCondition::Text { .. } => return None,
+    CellCondition::Empty(true) => DatabaseRowFilterCondition::IsEmpty,
+    CellCondition::Empty(false) => DatabaseRowFilterCondition::IsNotEmpty,
+    CellCondition::Number {
+      min,
+      max,
+      negate: false,
+    } => {
+      let bound = |bound: Bound<f64>| match bound {
+        Bound::Included(number) => Some(Some(number)),
+        Bound::Excluded(_) => None,
+        Bound::Unbounded => Some(None),
+      };
+      DatabaseRowFilterCondition::NumberRange {
+        min: bound(min)?,
+        max: bound(max)?,
+      }
+    },
+    CellCondition::Number { .. } => return None,
+    CellCondition::SelectOption { option_ids, negate } => {
+      if negate {
+        DatabaseRowFilterCondition::SelectOptionIsNot {
+          options: option_ids,
+        }
+      } else {
+        DatabaseRowFilterCondition::SelectOptionIs {
+          options: option_ids,
+        }
+      }
+    },
+    CellCondition::Checkbox(checked) => DatabaseRowFilterCondition::Checkbox { checked },
+    CellCondition::Date { start, end } => DatabaseRowFilterCondition::DateRange { start, end },
+  };
+  Some(condition)
+}
+
+/// Resolves the options of a select field, given by id or name.
+pub fn select_option_ids(field: &Field, options: &[String]) -> Result<Vec<String>, AppError> {
+  let field_options: Vec<SelectOption> = match FieldType::from(field.field_type) {
+    FieldType::SingleSelect => field
+      .type_options
+      .get(&FieldType::SingleSelect.to_string())
+      .map(|data| SingleSelectTypeOption::from(data.clone()).options.clone())
+      .unwrap_or_default(),
+    FieldType::MultiSelect => field
+      .type_options
+      .get(&FieldType::MultiSelect.to_string())
+      .map(|data| MultiSelectTypeOption::from(data.clone()).options.clone())
+      .unwrap_or_default(),
+    _ => {
+      return Err(AppError::InvalidRequest(format!(
+        "Field {} is not a select field",
+        field.name
+      )))
+    },
+  };
+  options
+    .iter()
+    .map(|option| {
+      field_options
+        .iter()
+        .find(|field_option| field_option.id == *option || field_option.name == *option)
+        .map(|field_option| field_option.id.clone())
+        .ok_or_else(|| {
+          AppError::InvalidRequest(format!(
+            "Option {} not found in field {}",
+            option, field.name
+          ))
+        })
+    })
+    .collect()
+}
+
+/// Returns the cell of the field, with the time of the row for the created and last edited time
+/// fields, which are not stored in the cells.
+pub fn row_cell(row: &Row, field: &Field) -> Cell {
+  if let Some(cell) = row.cells.get(&field.id) {
+    return cell.clone();
+  }
+  let field_type = FieldType::from(field.field_type);
+  match field_type {
+    FieldType::CreatedTime => TimestampCellData::new(Some(row.created_at)).to_cell(field_type),
+    FieldType::LastEditedTime => TimestampCellData::new(Some(row.modified_at)).to_cell(field_type),
+    _ => Cell::new(),
+  }
+}
+
+fn raw_cell_data(row: &Row, field: &Field) -> Option<String> {
+  row
+    .cells
+    .get(&field.id)?
+    .get(CELL_DATA)
+    .and_then(any_to_string)
+}
+
+fn cell_select_option_ids(row: &Row, field: &Field) -> Vec<String> {
+  raw_cell_data(row, field)
+    .map(|data| {
+      data
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+pub fn is_cell_checked(row: &Row, field: &Field) -> bool {
+  raw_cell_data(row, field)
+    .map(|data| matches!(data.to_lowercase().as_str(), "yes" | "true" | "1"))
+    .unwrap_or(false)
+}
+
+pub fn cell_timestamp(row: &Row, field: &Field) -> Option<i64> {
+  match FieldType::from(field.field_type) {
+    FieldType::CreatedTime => Some(row.created_at),
+    FieldType::LastEditedTime => Some(row.modified_at),
+    _ => raw_cell_data(row, field)?.trim().parse().ok(),
+  }
+}
+
+pub fn json_to_text(value: &serde_json::Value) -> String {
+  match value {
+    serde_json::Value::Null => String::new(),
+    serde_json::Value::String(text) => text.clone(),
+    serde_json::Value::Array(values) => values
+      .iter()
+      .map(json_to_text)
+      .collect::<Vec<_>>()
+      .join(", "),
+    value => value.to_string(),
+  }
+}
+
+pub fn any_to_string(value: &Any) -> Option<String> {
+  match value {
+    Any::String(value) => Some(value.to_string()),
+    Any::BigInt(value) => Some(value.to_string()),
+    Any::Number(value) => Some(value.to_string()),
+    Any::Bool(value) => Some(value.to_string()),
+    _ => None,
+  }
+}
+
+pub fn any_to_i64(value: &Any) -> Option<i64> {
+  match value {
+    Any::BigInt(value) => Some(*value),
+    Any::Number(value) => Some(*value as i64),
+    Any::String(value) => value.parse().ok(),
+    _ => None,
+  }
+}
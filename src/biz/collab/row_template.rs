@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use access_control::act::Action;
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use database::database_row_template::{
+  claim_next_database_row_template_run, delete_database_row_template, insert_database_row_template,
+  select_database_row_template, select_database_row_templates, update_database_row_template,
+  update_database_row_template_run_completed, update_database_row_template_run_failed,
+};
+use database::pg_row::{AFDatabaseRowTemplateRow, AFDatabaseRowTemplateRunRow};
+use database_entity::schedule::CronSchedule;
+use serde_json::json;
+use shared_entity::dto::workspace_dto::{
+  AFCreateDatabaseRowTemplate, AFDatabaseRowTemplate, AFUpdateDatabaseRowTemplate,
+};
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::database_view::find_field;
+use super::ops::{database_row_id_from_pre_hash, insert_database_row};
+use super::utils::{get_latest_collab_database_body, get_latest_collab_database_row_body};
+use crate::state::AppState;
+
+/// Runs which have been running for longer are claimed again.
+const STALE_RUN_SECS: i64 = 600;
+
+pub async fn list_database_row_templates(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+) -> Result<Vec<AFDatabaseRowTemplate>, AppError> {
+  let templates = select_database_row_templates(pg_pool, &workspace_id, &database_id)
+    .await?
+    .into_iter()
+    .map(row_template_from_row)
+    .collect();
+  Ok(templates)
+}
+
+pub async fn create_database_row_template(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  params: AFCreateDatabaseRowTemplate,
+) -> Result<AFDatabaseRowTemplate, AppError> {
+  let name = params.name.trim();
+  if name.is_empty() {
+    return Err(AppError::InvalidRequest(
+      "The name of the row template cannot be empty".to_string(),
+    ));
+  }
+  validate_template_cells(state, workspace_id, database_id, &params.cells).await?;
+  let schedule = params
+    .schedule
+    .as_deref()
+    .map(str::trim)
+    .filter(|schedule| !schedule.is_empty());
+  let next_run_at = schedule.map(next_run_at).transpose()?;
+  let row_doc_content = params
+    .row_doc_content
+    .as_deref()
+    .filter(|content| !content.is_empty());
+
+  let template = insert_database_row_template(
+    &state.pg_pool,
+    &workspace_id,
+    &database_id,
+    name,
+    &json!(params.cells),
+    row_doc_content,
+    schedule,
+    next_run_at,
+    uid,
+  )
+  .await?;
+  Ok(row_template_from_row(template))
+}
+
+/// Updates the given parts of the template. Changing the schedule restarts it from now.
+pub async fn update_row_template(
+  state: &AppState,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  template_id: Uuid,
+  params: AFUpdateDatabaseRowTemplate,
+) -> Result<AFDatabaseRowTemplate, AppError> {
+  let template =
+    get_database_row_template(&state.pg_pool, workspace_id, database_id, template_id).await?;
+
+  let name = match &params.name {
+    Some(name) if name.trim().is_empty() => {
+      return Err(AppError::InvalidRequest(
+        "The name of the row template cannot be empty".to_string(),
+      ));
+    },
+    Some(name) => name.trim().to_string(),
+    None => template.name,
+  };
+  let cells = match params.cells {
+    Some(cells) => {
+      validate_template_cells(state, workspace_id, database_id, &cells).await?;
+      json!(cells)
+    },
+    None => template.cells,
+  };
+  let row_doc_content = match params.row_doc_content {
+    Some(content) if content.is_empty() => None,
+    Some(content) => Some(content),
+    None => template.row_doc_content,
+  };
+  let (schedule, next_run_at) = match params.schedule.as_deref().map(str::trim) {
+    Some("") => (None, None),
+    Some(schedule) => (Some(schedule.to_string()), Some(next_run_at(schedule)?)),
+    None => (template.schedule, template.next_run_at),
+  };
+
+  let template = update_database_row_template(
+    &state.pg_pool,
+    &template_id,
+    &name,
+    &cells,
+    row_doc_content.as_deref(),
+    schedule.as_deref(),
+    next_run_at,
+  )
+  .await?;
+  Ok(row_template_from_row(template))
+}
+
+pub async fn remove_database_row_template(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  template_id: Uuid,
+) -> Result<(), AppError> {
+  get_database_row_template(pg_pool, workspace_id, database_id, template_id).await?;
+  delete_database_row_template(pg_pool, &template_id).await
+}
+
+/// Inserts a row with the cells and the row document of the template. Returns the id of the row.
+pub async fn insert_row_from_template(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  template_id: Uuid,
+) -> Result<String, AppError> {
+  let template =
+    get_database_row_template(&state.pg_pool, workspace_id, database_id, template_id).await?;
+  insert_template_row(state, uid, template, None).await
+}
+
+/// Inserts the row of the template, with a new id or the given one. When the row with the given
+/// id exists already, it is kept as it is.
+async fn insert_template_row(
+  state: &AppState,
+  uid: i64,
+  template: AFDatabaseRowTemplateRow,
+  row_id: Option<Uuid>,
+) -> Result<String, AppError> {
+  if let Some(row_id) = row_id {
+    match get_latest_collab_database_row_body(&state.collab_storage, template.workspace_id, row_id)
+      .await
+    {
+      Ok(_) => return Ok(row_id.to_string()),
+      Err(AppError::RecordNotFound(_)) => {},
+      Err(err) => return Err(err),
+    }
+  }
+  let cells: HashMap<String, serde_json::Value> =
+    serde_json::from_value(template.cells).unwrap_or_default();
+  insert_database_row(
+    state,
+    template.workspace_id,
+    template.database_id,
+    uid,
+    row_id,
+    cells,
+    template.row_doc_content,
+  )
+  .await
+}
+
+async fn get_database_row_template(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  template_id: Uuid,
+) -> Result<AFDatabaseRowTemplateRow, AppError> {
+  select_database_row_template(pg_pool, &template_id)
+    .await?
+    .filter(|template| template.workspace_id == workspace_id && template.database_id == database_id)
+    .ok_or_else(|| AppError::RecordNotFound(format!("Row template {} not found", template_id)))
+}
+
+async fn validate_template_cells(
+  state: &AppState,
+  workspace_id: Uuid,
+  database_id: Uuid,
+  cells: &HashMap<String, serde_json::Value>,
+) -> Result<(), AppError> {
+  let (db_collab, db_body) =
+    get_latest_collab_database_body(&state.collab_storage, workspace_id, database_id).await?;
+  let fields = db_body.fields.get_all_fields(&db_collab.transact());
+  for field in cells.keys() {
+    find_field(&fields, field)?;
+  }
+  Ok(())
+}
+
+fn next_run_at(schedule: &str) -> Result<DateTime<Utc>, AppError> {
+  CronSchedule::from_str(schedule)
+    .map_err(|err| AppError::InvalidRequest(err.to_string()))?
+    .next_after(Utc::now())
+    .ok_or_else(|| AppError::InvalidRequest(format!("Schedule '{}' never runs", schedule)))
+}
+
+fn row_template_from_row(row: AFDatabaseRowTemplateRow) -> AFDatabaseRowTemplate {
+  AFDatabaseRowTemplate {
+    id: row.template_id.to_string(),
+    name: row.name,
+    cells: serde_json::from_value(row.cells).unwrap_or_default(),
+    row_doc_content: row.row_doc_content,
+    schedule: row.schedule,
+    next_run_at: row.next_run_at,
+    created_by: row.created_by,
+    created_at: row.created_at,
+    updated_at: row.updated_at,
+  }
+}
+
+/// Inserts the rows of the scheduled row templates, whose runs are queued by the row template
+/// scheduler of the worker.
+pub struct DatabaseRowTemplateWorker {
+  pub state: AppState,
+  pub interval_secs: u64,
+}
+
+impl DatabaseRowTemplateWorker {
+  pub async fn start_task(&self) {
+    let mut interval = interval(Duration::from_secs(self.interval_secs.max(1)));
+    loop {
+      interval.tick().await;
+      loop {
+        match claim_next_database_row_template_run(&self.state.pg_pool, STALE_RUN_SECS).await {
+          Ok(Some(run)) => self.run_template(run).await,
+          Ok(None) => break,
+          Err(err) => {
+            error!("Failed to claim database row template run: {}", err);
+            break;
+          },
+        }
+      }
+    }
+  }
+
+  async fn run_template(&self, run: AFDatabaseRowTemplateRunRow) {
+    let pg_pool = &self.state.pg_pool;
+    let result = match self.insert_scheduled_row(&run).await {
+      Ok(row_id) => {
+        info!(
+          "Inserted row {} from template {} scheduled at {}",
+          row_id, run.template_id, run.scheduled_at
+        );
+        update_database_row_template_run_completed(pg_pool, &run.run_id, &row_id).await
+      },
+      Err(err) => {
+        error!(
+          "Failed to insert row from template {}: {}",
+          run.template_id, err
+        );
+        update_database_row_template_run_failed(pg_pool, &run.run_id, &err.to_string()).await
+      },
+    };
+    if let Err(err) = result {
+      error!(
+        "Failed to update database row template run {}: {}",
+        run.run_id, err
+      );
+    }
+  }
+
+  async fn insert_scheduled_row(
+    &self,
+    run: &AFDatabaseRowTemplateRunRow,
+  ) -> Result<Uuid, AppError> {
+    let template = select_database_row_template(&self.state.pg_pool, &run.template_id)
+      .await?
+      .ok_or_else(|| {
+        AppError::RecordNotFound(format!("Row template {} not found", run.template_id))
+      })?;
+    // the rows are inserted on behalf of the creator, who may have left the workspace since
+    self
+      .state
+      .workspace_access_control
+      .enforce_action(&template.created_by, &template.workspace_id, Action::Write)
+      .await?;
+    // the row id is derived from the run, so that a run claimed again after it went stale does
+    // not insert the row twice
+    let row_id = database_row_id_from_pre_hash(
+      template.workspace_id,
+      template.database_id,
+      &run.run_id.to_string(),
+    );
+    let row_id =
+      insert_template_row(&self.state, template.created_by, template, Some(row_id)).await?;
+    Ok(Uuid::parse_str(&row_id)?)
+  }
+}
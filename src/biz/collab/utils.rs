@@ -41,8 +41,12 @@ use uuid::Uuid;
 use yrs::block::ClientID;
 use yrs::Map;
 
+use super::computed_field::{computed_field, COMPUTED_TYPE_OPTION_KEY};
+
 pub const DEFAULT_SPACE_ICON: &str = "interface_essential/home-3";
 pub const DEFAULT_SPACE_ICON_COLOR: &str = "0xFFA34AFD";
+/// Dates are compared by day, in UTC, by the filters and formulas of the databases.
+pub const SECONDS_PER_DAY: i64 = 86_400;
 
 #[instrument(level = "debug", skip_all)]
 pub fn get_row_details_serde(
@@ -193,6 +197,13 @@ pub fn type_options_serde(
           }
         }
       },
+      _ if key == COMPUTED_TYPE_OPTION_KEY => {
+        if let yrs::Any::String(arc_str) = value {
+          if let Ok(serde_value) = serde_json::from_str::<serde_json::Value>(arc_str) {
+            result.insert(key.clone(), serde_value);
+          }
+        }
+      },
       _ => {
         result.insert(key.clone(), serde_json::to_value(value).unwrap_or_default());
       },
@@ -458,6 +469,10 @@ pub async fn write_to_database_row(
         },
       },
     };
+    if computed_field(field).is_some() {
+      tracing::warn!("Ignore value for computed field: {}", field.id);
+      continue;
+    }
     let cell_writer = match type_option_reader_by_id.get(&field.id) {
       Some(cell_writer) => cell_writer,
       None => {
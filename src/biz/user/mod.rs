@@ -1,4 +1,5 @@
 pub mod phone_auth;
+pub mod user_data_export;
 pub mod user_delete;
 pub mod user_info;
 pub mod user_init;
@@ -0,0 +1,651 @@
+use std::env::temp_dir;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use app_error::AppError;
+use async_zip::base::write::ZipFileWriter;
+use async_zip::{Compression, ZipEntryBuilder};
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::Utc;
+use collab::core::collab::default_client_id;
+use collab_document::blocks::{Block, DocumentData};
+use collab_document::document::Document;
+use collab_entity::CollabType;
+use database::collab::{CollabStore, GetCollabOrigin};
+use database::file::s3_client_impl::AwsS3BucketClientImpl;
+use database::file::{BucketClient, ResponseBlob};
+use database::pg_row::{AFUserBlobRow, AFUserDataExportRow, AFUserOwnedCollabRow};
+use database::user::select_uuid_from_uid;
+use database::user_data_export::{
+  claim_next_user_data_export, clear_user_data_export_object_key, insert_user_data_export,
+  select_expired_user_data_exports, select_user_chat_messages, select_user_collab_blobs,
+  select_user_data_export, select_user_data_export_in_progress, select_user_owned_collabs,
+  select_user_published_comments, select_user_quick_notes, update_user_data_export_completed,
+  update_user_data_export_failed,
+};
+use database::workspace::select_all_user_workspaces;
+use database_entity::dto::AFWorkspace;
+use futures_util::AsyncWriteExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use shared_entity::dto::auth_dto::UserDataExport;
+use sqlx::PgPool;
+use tokio::fs::File;
+use tokio::time::interval;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::biz::collab::utils::get_latest_collab;
+use crate::biz::user::user_info::get_profile;
+use crate::biz::workspace::document_walker::{plain_text, text_delta, BlockWalker};
+use crate::mailer::{AFCloudMailer, UserDataExportMailerParam};
+
+/// Exports left running for longer than this are assumed to be abandoned, and are retried.
+const STALE_EXPORT_SECS: i64 = 60 * 60;
+/// Presigned urls can't be valid for longer than 7 days.
+const MAX_LINK_EXPIRES_SECS: u64 = 7 * 24 * 60 * 60;
+/// Maximum number of expired archives deleted per tick.
+const EXPIRED_EXPORT_BATCH_SIZE: i64 = 100;
+
+pub async fn request_user_data_export(
+  pg_pool: &PgPool,
+  uid: i64,
+) -> Result<UserDataExport, AppError> {
+  if select_user_data_export_in_progress(pg_pool, uid).await? {
+    return Err(AppError::InvalidRequest(
+      "An export of your data is already in progress".to_string(),
+    ));
+  }
+  let row = insert_user_data_export(pg_pool, uid).await?;
+  Ok(to_user_data_export(row, None))
+}
+
+pub async fn get_user_data_export(
+  pg_pool: &PgPool,
+  bucket_client: &AwsS3BucketClientImpl,
+  uid: i64,
+  export_id: &Uuid,
+) -> Result<UserDataExport, AppError> {
+  let row = select_user_data_export(pg_pool, uid, export_id)
+    .await?
+    .ok_or_else(|| AppError::RecordNotFound(format!("User data export {} not found", export_id)))?;
+  let download_url = match (row.object_key.as_ref(), row.expires_at) {
+    (Some(object_key), Some(expires_at)) if expires_at > Utc::now() => {
+      let expires_in_secs = (expires_at - Utc::now()).num_seconds().max(1) as u64;
+      Some(
+        bucket_client
+          .gen_presigned_download_url(object_key, expires_in_secs)
+          .await?,
+      )
+    },
+    _ => None,
+  };
+  Ok(to_user_data_export(row, download_url))
+}
+
+fn to_user_data_export(row: AFUserDataExportRow, download_url: Option<String>) -> UserDataExport {
+  UserDataExport {
+    export_id: row.export_id,
+    status: row.status.into(),
+    file_size: row.file_size,
+    download_url,
+    error: row.error,
+    created_at: row.created_at,
+    completed_at: row.completed_at,
+    expires_at: row.expires_at,
+  }
+}
+
+/// Collects all the data of a user into a zip archive, uploads it to the object storage and
+/// emails the user a link to download it. Archives are deleted once the link expires.
+pub struct UserDataExportWorker {
+  pub pg_pool: PgPool,
+  pub collab_storage: Arc<dyn CollabStore>,
+  pub bucket_client: AwsS3BucketClientImpl,
+  pub mailer: AFCloudMailer,
+  pub interval_secs: u64,
+  pub link_expires_secs: u64,
+}
+
+impl UserDataExportWorker {
+  pub async fn start_task(&self) {
+    let mut interval = interval(Duration::from_secs(self.interval_secs.max(1)));
+    loop {
+      interval.tick().await;
+      self.delete_expired_exports().await;
+      loop {
+        match claim_next_user_data_export(&self.pg_pool, STALE_EXPORT_SECS).await {
+          Ok(Some(export)) => self.run_export(export).await,
+          Ok(None) => break,
+          Err(err) => {
+            error!("Failed to claim user data export: {}", err);
+            break;
+          },
+        }
+      }
+    }
+  }
+
+  async fn delete_expired_exports(&self) {
+    let exports =
+      match select_expired_user_data_exports(&self.pg_pool, EXPIRED_EXPORT_BATCH_SIZE).await {
+        Ok(exports) => exports,
+        Err(err) => {
+          error!("Failed to select expired user data exports: {}", err);
+          return;
+        },
+      };
+    for (export_id, object_key) in exports {
+      if let Err(err) = self.bucket_client.delete_blob(&object_key).await {
+        warn!(
+          "Failed to delete the archive of user data export {}: {}",
+          export_id, err
+        );
+        continue;
+      }
+      if let Err(err) = clear_user_data_export_object_key(&self.pg_pool, &export_id).await {
+        error!(
+          "Failed to update expired user data export {}: {}",
+          export_id, err
+        );
+      }
+    }
+  }
+
+  async fn run_export(&self, export: AFUserDataExportRow) {
+    info!(
+      "Exporting data of user {}, export_id: {}",
+      export.uid, export.export_id
+    );
+    let link_expires_secs = self.link_expires_secs.clamp(1, MAX_LINK_EXPIRES_SECS);
+    let expires_at = Utc::now() + chrono::Duration::seconds(link_expires_secs as i64);
+    let result = match self.export_user_data(&export).await {
+      Ok((object_key, file_size)) => {
+        let result = update_user_data_export_completed(
+          &self.pg_pool,
+          &export.export_id,
+          &object_key,
+          file_size,
+          expires_at,
+        )
+        .await;
+        if result.is_ok() {
+          if let Err(err) = self
+            .notify_user(&export, &object_key, link_expires_secs, expires_at)
+            .await
+          {
+            warn!(
+              "Failed to notify user {} of data export {}: {}",
+              export.uid, export.export_id, err
+            );
+          }
+        }
+        result
+      },
+      Err(err) => {
+        error!("Failed to export user data {}: {}", export.export_id, err);
+        update_user_data_export_failed(&self.pg_pool, &export.export_id, &err.to_string()).await
+      },
+    };
+    if let Err(err) = result {
+      error!(
+        "Failed to update user data export {}: {}",
+        export.export_id, err
+      );
+    }
+  }
+
+  async fn notify_user(
+    &self,
+    export: &AFUserDataExportRow,
+    object_key: &str,
+    link_expires_secs: u64,
+    expires_at: chrono::DateTime<Utc>,
+  ) -> Result<(), AppError> {
+    let uuid = select_uuid_from_uid(&self.pg_pool, export.uid).await?;
+    let profile = get_profile(&self.pg_pool, &uuid).await?;
+    let Some(email) = profile.email.filter(|email| !email.is_empty()) else {
+      return Ok(());
+    };
+    let download_url = self
+      .bucket_client
+      .gen_presigned_download_url(object_key, link_expires_secs)
+      .await?;
+    let param = UserDataExportMailerParam {
+      username: profile.name.unwrap_or_else(|| email.clone()),
+      download_url,
+      expires_at: expires_at.format("%B %-d, %Y %H:%M UTC").to_string(),
+    };
+    self
+      .mailer
+      .send_user_data_export(&email, param)
+      .await
+      .map_err(AppError::Internal)
+  }
+
+  /// Writes the archive to a temporary file before uploading it, as it includes the files
+  /// uploaded by the user.
+  async fn export_user_data(
+    &self,
+    export: &AFUserDataExportRow,
+  ) -> Result<(String, i64), AppError> {
+    let file_path = temp_dir().join(format!("user_data_export_{}.zip", export.export_id));
+    let result = self.write_archive(export.uid, &file_path).await;
+    let result = match result {
+      Ok(()) => {
+        let object_key = format!("user-data-export/{}/{}.zip", export.uid, export.export_id);
+        self.upload(&object_key, &file_path).await
+      },
+      Err(err) => Err(err),
+    };
+    if let Err(err) = tokio::fs::remove_file(&file_path).await {
+      warn!("Failed to delete the archive {:?}: {}", file_path, err);
+    }
+    result
+  }
+
+  async fn upload(&self, object_key: &str, file_path: &Path) -> Result<(String, i64), AppError> {
+    let file_size = tokio::fs::metadata(file_path).await?.len() as i64;
+    let stream = ByteStream::from_path(file_path)
+      .await
+      .map_err(|err| AppError::Internal(err.into()))?;
+    self
+      .bucket_client
+      .put_blob_with_content_type(object_key, stream, "application/zip")
+      .await?;
+    Ok((object_key.to_string(), file_size))
+  }
+
+  async fn write_archive(&self, uid: i64, file_path: &Path) -> Result<(), AppError> {
+    let uuid = select_uuid_from_uid(&self.pg_pool, uid).await?;
+    let mut archive = UserDataArchive::new(File::create(file_path).await?);
+    let mut skipped = vec![];
+
+    let mut profile = get_profile(&self.pg_pool, &uuid).await?;
+    profile.password = None;
+    archive.write_json("profile.json", &profile).await?;
+
+    let workspaces = select_all_user_workspaces(&self.pg_pool, &uuid)
+      .await?
+      .into_iter()
+      .flat_map(|row| AFWorkspace::try_from(row).ok())
+      .collect::<Vec<_>>();
+    archive.write_json("workspaces.json", &workspaces).await?;
+
+    let mut collab_count = 0;
+    for collab in select_user_owned_collabs(&self.pg_pool, uid).await? {
+      match self.write_collab(&mut archive, &collab).await {
+        Ok(()) => collab_count += 1,
+        Err(err) => {
+          warn!(
+            "Skipping collab {} in user data export: {}",
+            collab.oid, err
+          );
+          skipped.push(format!("collab {}", collab.oid));
+        },
+      }
+    }
+
+    let chat_messages = select_user_chat_messages(&self.pg_pool, uid).await?;
+    archive.write_json("chats.json", &chat_messages).await?;
+    let quick_notes = select_user_quick_notes(&self.pg_pool, uid).await?;
+    archive.write_json("quick_notes.json", &quick_notes).await?;
+    let comments = select_user_published_comments(&self.pg_pool, uid).await?;
+    archive.write_json("comments.json", &comments).await?;
+
+    let mut file_count = 0;
+    for blob in select_user_collab_blobs(&self.pg_pool, uid).await? {
+      match self.write_blob(&mut archive, &blob).await {
+        Ok(()) => file_count += 1,
+        Err(err) => {
+          warn!(
+            "Skipping file {} in user data export: {}",
+            blob.file_id, err
+          );
+          skipped.push(format!("file {}", blob.file_id));
+        },
+      }
+    }
+
+    let manifest = json!({
+      "exported_at": Utc::now(),
+      "collab_count": collab_count,
+      "chat_message_count": chat_messages.len(),
+      "quick_note_count": quick_notes.len(),
+      "comment_count": comments.len(),
+      "file_count": file_count,
+      "skipped": skipped,
+    });
+    archive.write_json("manifest.json", &manifest).await?;
+    archive.close().await
+  }
+
+  /// Writes the collab as JSON, and documents as Markdown as well.
+  async fn write_collab(
+    &self,
+    archive: &mut UserDataArchive,
+    row: &AFUserOwnedCollabRow,
+  ) -> Result<(), AppError> {
+    let collab_type = CollabType::from(row.partition_key);
+    let collab = get_latest_collab(
+      &self.collab_storage,
+      GetCollabOrigin::Server,
+      row.workspace_id,
+      row.oid,
+      collab_type,
+      default_client_id(),
+    )
+    .await?;
+    let path = format!("workspaces/{}/collabs/{}", row.workspace_id, row.oid);
+    let collab_json = json!({
+      "object_id": row.oid,
+      "collab_type": format!("{:?}", collab_type),
+      "data": collab.to_json_value(),
+    });
+    archive
+      .write_json(&format!("{}.json", path), &collab_json)
+      .await?;
+
+    if collab_type == CollabType::Document {
+      let document = Document::open(collab).map_err(|e| AppError::Unhandled(e.to_string()))?;
+      let data = document
+        .get_document_data()
+        .map_err(|e| AppError::Unhandled(e.to_string()))?;
+      archive
+        .write_file(
+          &format!("{}.md", path),
+          document_to_markdown(&data).as_bytes(),
+        )
+        .await?;
+    }
+    Ok(())
+  }
+
+  async fn write_blob(
+    &self,
+    archive: &mut UserDataArchive,
+    blob: &AFUserBlobRow,
+  ) -> Result<(), AppError> {
+    let (object_key, path) = match blob.file_id.split_once('_') {
+      Some((parent_dir, file_name)) => (
+        format!("{}/{}/{}", blob.workspace_id, parent_dir, file_name),
+        format!(
+          "workspaces/{}/files/{}/{}",
+          blob.workspace_id,
+          sanitize_filename::sanitize(parent_dir),
+          sanitize_filename::sanitize(file_name)
+        ),
+      ),
+      None => (
+        format!("{}/{}", blob.workspace_id, blob.file_id),
+        format!(
+          "workspaces/{}/files/{}",
+          blob.workspace_id,
+          sanitize_filename::sanitize(&blob.file_id)
+        ),
+      ),
+    };
+    let data = self.bucket_client.get_blob(&object_key).await?.to_blob();
+    archive.write_file(&path, &data).await
+  }
+}
+
+struct UserDataArchive {
+  writer: ZipFileWriter<Compat<File>>,
+}
+
+impl UserDataArchive {
+  fn new(file: File) -> Self {
+    Self {
+      writer: ZipFileWriter::new(file.compat_write()),
+    }
+  }
+
+  async fn write_json<T: Serialize>(&mut self, path: &str, value: &T) -> Result<(), AppError> {
+    let data = serde_json::to_vec_pretty(value)?;
+    self.write_file(path, &data).await
+  }
+
+  async fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), AppError> {
+    let builder = ZipEntryBuilder::new(path.to_string().into(), Compression::Deflate);
+    self
+      .writer
+      .write_entry_whole(builder, data)
+      .await
+      .map_err(|err| AppError::Internal(err.into()))
+  }
+
+  async fn close(self) -> Result<(), AppError> {
+    let mut file = self
+      .writer
+      .close()
+      .await
+      .map_err(|err| AppError::Internal(err.into()))?;
+    file.flush().await?;
+    Ok(())
+  }
+}
+
+/// Renders a document to Markdown. Blocks without a Markdown equivalent are rendered as
+/// paragraphs.
+fn document_to_markdown(data: &DocumentData) -> String {
+  let mut markdown = String::new();
+  let mut walker = BlockWalker::default();
+  write_markdown_children(data, &mut walker, &data.page_id, 0, &mut markdown);
+  markdown
+}
+
+/// Writes the children of a block. `depth` is the indentation of the nested list items.
+fn write_markdown_children(
+  data: &DocumentData,
+  walker: &mut BlockWalker,
+  block_id: &str,
+  depth: usize,
+  md: &mut String,
+) {
+  let Some(children) = walker.enter(data, block_id) else {
+    return;
+  };
+  let mut number = 0;
+  let mut in_list = false;
+  for block in children {
+    let is_list = matches!(
+      block.ty.as_str(),
+      "bulleted_list" | "numbered_list" | "todo_list"
+    );
+    if in_list && !is_list {
+      md.push('\n');
+    }
+    in_list = is_list;
+    number = if block.ty == "numbered_list" {
+      number + 1
+    } else {
+      0
+    };
+    write_markdown_block(data, walker, block, depth, number, md);
+  }
+  if in_list && depth == 0 {
+    md.push('\n');
+  }
+  walker.leave();
+}
+
+fn write_markdown_block(
+  data: &DocumentData,
+  walker: &mut BlockWalker,
+  block: &Block,
+  depth: usize,
+  number: usize,
+  md: &mut String,
+) {
+  let indent = "    ".repeat(depth);
+  let text = delta_to_markdown(&text_delta(data, block).unwrap_or_default());
+  let data_str = |key: &str| {
+    block
+      .data
+      .get(key)
+      .and_then(Value::as_str)
+      .unwrap_or_default()
+      .to_string()
+  };
+  let list_item = match block.ty.as_str() {
+    "bulleted_list" => Some("- ".to_string()),
+    "numbered_list" => Some(format!("{}. ", number)),
+    "todo_list" => {
+      let checked = block
+        .data
+        .get("checked")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+      Some(format!("- [{}] ", if checked { "x" } else { " " }))
+    },
+    _ => None,
+  };
+  if let Some(marker) = list_item {
+    md.push_str(&format!("{}{}{}\n", indent, marker, text));
+    write_markdown_children(data, walker, &block.id, depth + 1, md);
+    return;
+  }
+
+  match block.ty.as_str() {
+    "heading" => {
+      let level = block
+        .data
+        .get("level")
+        .and_then(Value::as_u64)
+        .unwrap_or(1)
+        .clamp(1, 6) as usize;
+      md.push_str(&format!("{}{} {}\n\n", indent, "#".repeat(level), text));
+    },
+    "quote" => md.push_str(&format!("{}> {}\n\n", indent, text)),
+    "callout" => md.push_str(&format!("{}> {} {}\n\n", indent, data_str("icon"), text)),
+    "code" => {
+      let code = plain_text(data, block);
+      md.push_str(&format!("```{}\n{}\n```\n\n", data_str("language"), code));
+    },
+    "divider" => md.push_str("---\n\n"),
+    "math_equation" => md.push_str(&format!("$$\n{}\n$$\n\n", data_str("formula"))),
+    "image" => md.push_str(&format!("{}![]({})\n\n", indent, data_str("url"))),
+    "file" | "link_preview" | "bookmark" => {
+      let url = data_str("url");
+      let name = Some(data_str("name"))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| url.clone());
+      md.push_str(&format!("{}[{}]({})\n\n", indent, name, url));
+    },
+    "subpage" | "grid" | "board" | "calendar" => {
+      let view_id = data_str("view_id");
+      md.push_str(&format!("{}[{}](./{}.md)\n\n", indent, view_id, view_id));
+    },
+    _ => {
+      if !text.is_empty() {
+        md.push_str(&format!("{}{}\n\n", indent, text));
+      }
+    },
+  }
+  write_markdown_children(data, walker, &block.id, depth, md);
+}
+
+/// Renders the rich text of a block, stored as a delta, to Markdown.
+fn delta_to_markdown(ops: &[Value]) -> String {
+  let mut md = String::new();
+  for op in ops {
+    let Some(insert) = op.get("insert").and_then(Value::as_str) else {
+      continue;
+    };
+    let attributes = op.get("attributes");
+    let attr = |name: &str| attributes.and_then(|attributes| attributes.get(name));
+    let is_set = |name: &str| attr(name).and_then(Value::as_bool).unwrap_or(false);
+
+    if let Some(mention) = attr("mention") {
+      match mention.get("type").and_then(Value::as_str) {
+        Some("page") | Some("childPage") => {
+          if let Some(page_id) = mention.get("page_id").and_then(Value::as_str) {
+            md.push_str(&format!("[{}](./{}.md)", page_id, page_id));
+          }
+        },
+        Some("date") => {
+          if let Some(date) = mention.get("date").and_then(Value::as_str) {
+            md.push_str(date);
+          }
+        },
+        _ => {},
+      }
+      continue;
+    }
+    let mut text = insert.to_string();
+    if is_set("code") {
+      text = format!("`{}`", text);
+    }
+    if is_set("bold") {
+      text = format!("**{}**", text);
+    }
+    if is_set("italic") {
+      text = format!("_{}_", text);
+    }
+    if is_set("strikethrough") {
+      text = format!("~~{}~~", text);
+    }
+    if let Some(href) = attr("href").and_then(Value::as_str) {
+      text = format!("[{}]({})", text, href);
+    }
+    md.push_str(&text);
+  }
+  md
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use collab_document::blocks::DocumentMeta;
+  use std::collections::HashMap;
+
+  #[test]
+  fn delta_to_markdown_test() {
+    let ops = serde_json::from_str::<Vec<Value>>(
+      r#"[
+        {"insert":"Hello "},
+        {"insert":"bold","attributes":{"bold":true}},
+        {"insert":" and "},
+        {"insert":"link","attributes":{"href":"https://appflowy.io","italic":true}},
+        {"insert":"$","attributes":{"mention":{"type":"page","page_id":"abc"}}}
+      ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+      delta_to_markdown(&ops),
+      "Hello **bold** and [_link_](https://appflowy.io)[abc](./abc.md)"
+    );
+  }
+
+  #[test]
+  fn cyclic_blocks_are_written_once() {
+    let block = |id: &str, ty: &str, parent: &str| Block {
+      id: id.to_string(),
+      ty: ty.to_string(),
+      data: HashMap::new(),
+      parent: parent.to_string(),
+      children: format!("{}_children", id),
+      external_id: None,
+      external_type: None,
+    };
+    // `item` lists the page as its own child
+    let data = DocumentData {
+      page_id: "page".to_string(),
+      blocks: HashMap::from([
+        ("page".to_string(), block("page", "page", "")),
+        ("item".to_string(), block("item", "bulleted_list", "page")),
+      ]),
+      meta: DocumentMeta {
+        children_map: HashMap::from([
+          ("page_children".to_string(), vec!["item".to_string()]),
+          ("item_children".to_string(), vec!["page".to_string()]),
+        ]),
+        text_map: None,
+      },
+    };
+    assert_eq!(document_to_markdown(&data), "- \n\n");
+  }
+}
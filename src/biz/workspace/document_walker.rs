@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use collab_document::blocks::{Block, DocumentData};
+use serde_json::Value;
+
+/// Blocks nested deeper are not walked.
+pub const MAX_BLOCK_DEPTH: usize = 64;
+
+/// Walks the blocks of a document from the page block down. The children map is written by the
+/// clients, so a block may be listed as its own descendant: the children of a block are only
+/// walked once per document, and never deeper than [MAX_BLOCK_DEPTH].
+#[derive(Default)]
+pub struct BlockWalker {
+  visited: HashSet<String>,
+  depth: usize,
+}
+
+impl BlockWalker {
+  /// Starts walking another document.
+  pub fn reset(&mut self) {
+    self.visited.clear();
+    self.depth = 0;
+  }
+
+  /// Returns the children of the block, or None if they were already walked or are nested too
+  /// deep. Once the returned children are walked, [BlockWalker::leave] must be called.
+  pub fn enter<'d>(&mut self, data: &'d DocumentData, block_id: &str) -> Option<Vec<&'d Block>> {
+    if self.depth >= MAX_BLOCK_DEPTH || !self.visited.insert(block_id.to_string()) {
+      return None;
+    }
+    self.depth += 1;
+    let children = data
+      .blocks
+      .get(block_id)
+      .and_then(|block| data.meta.children_map.get(&block.children))
+      .map(|child_ids| {
+        child_ids
+          .iter()
+          .filter_map(|child_id| data.blocks.get(child_id))
+          .collect()
+      })
+      .unwrap_or_default();
+    Some(children)
+  }
+
+  pub fn leave(&mut self) {
+    self.depth = self.depth.saturating_sub(1);
+  }
+
+  pub fn depth(&self) -> usize {
+    self.depth
+  }
+}
+
+/// Returns the rich text of a block, stored as a delta in the text map.
+pub fn text_delta(data: &DocumentData, block: &Block) -> Option<Vec<Value>> {
+  let text_id = block.external_id.as_ref()?;
+  let delta = data.meta.text_map.as_ref()?.get(text_id)?;
+  serde_json::from_str::<Vec<Value>>(delta).ok()
+}
+
+/// Returns the rich text of a block without its formatting.
+pub fn plain_text(data: &DocumentData, block: &Block) -> String {
+  text_delta(data, block)
+    .unwrap_or_default()
+    .iter()
+    .filter_map(|op| op.get("insert").and_then(Value::as_str))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use collab_document::blocks::DocumentMeta;
+  use std::collections::HashMap;
+
+  #[test]
+  fn deeply_nested_blocks_are_not_walked() {
+    let block = |id: usize| Block {
+      id: id.to_string(),
+      ty: "paragraph".to_string(),
+      data: HashMap::new(),
+      parent: id.saturating_sub(1).to_string(),
+      children: format!("{}_children", id),
+      external_id: None,
+      external_type: None,
+    };
+    let count = MAX_BLOCK_DEPTH * 2;
+    let data = DocumentData {
+      page_id: "0".to_string(),
+      blocks: (0..count).map(|id| (id.to_string(), block(id))).collect(),
+      meta: DocumentMeta {
+        children_map: (0..count)
+          .map(|id| (format!("{}_children", id), vec![(id + 1).to_string()]))
+          .collect(),
+        text_map: None,
+      },
+    };
+
+    let mut walker = BlockWalker::default();
+    let mut block_id = data.page_id.clone();
+    let mut walked = 0;
+    while let Some(children) = walker.enter(&data, &block_id) {
+      walked += 1;
+      match children.first() {
+        Some(child) => block_id = child.id.clone(),
+        None => break,
+      }
+    }
+    assert_eq!(walked, MAX_BLOCK_DEPTH);
+    for _ in 0..walked {
+      walker.leave();
+    }
+    assert_eq!(walker.depth(), 0);
+  }
+}
@@ -1,7 +1,16 @@
+pub mod document_walker;
 pub mod duplicate;
 pub mod invite;
 pub mod ops;
+pub mod page_block;
 pub mod page_view;
+pub mod presence;
 pub mod publish;
+pub mod publish_access;
+pub mod publish_analytics;
+pub mod publish_comment;
+pub mod publish_domain;
 pub mod publish_dup;
+pub mod publish_export;
+pub mod publish_feed;
 pub mod quick_note;
@@ -32,6 +32,7 @@ use crate::biz::user::user_init::{
   create_user_awareness, create_workspace_collab, create_workspace_database_collab,
   initialize_workspace_for_user,
 };
+use crate::biz::workspace::publish_comment::insert_published_comment;
 use crate::mailer::{AFCloudMailer, WorkspaceInviteMailerParam};
 use crate::state::RedisConnectionManager;
 use shared_entity::dto::workspace_dto::{
@@ -41,6 +42,8 @@ use shared_entity::response::AppResponseError;
 use workspace_template::document::getting_started::GettingStartedTemplate;
 
 pub(crate) const MAX_COMMENT_LENGTH: usize = 5000;
+/// Longest trash retention period, in days.
+const MAX_TRASH_RETENTION_DAYS: u32 = 3650;
 
 pub async fn delete_workspace_for_user(
   pg_pool: PgPool,
@@ -190,14 +193,22 @@ pub async fn create_comment_on_published_view(
   reply_comment_id: &Option<Uuid>,
   content: &str,
   user_uuid: &Uuid,
+  rate_limit_per_minute: u32,
 ) -> Result<(), AppError> {
   if content.len() > MAX_COMMENT_LENGTH {
     return Err(AppError::StringLengthLimitReached(
       "comment content exceed limit".to_string(),
     ));
   }
-  insert_comment_to_published_view(pg_pool, view_id, user_uuid, content, reply_comment_id).await?;
-  Ok(())
+  insert_published_comment(
+    pg_pool,
+    view_id,
+    reply_comment_id,
+    content,
+    user_uuid,
+    rate_limit_per_minute,
+  )
+  .await
 }
 
 pub async fn remove_comment_on_published_view(
@@ -662,6 +673,19 @@ pub async fn update_workspace_settings(
     setting.ai_model = ai_model;
   }
 
+  if let Some(trash_retention_days) = change.trash_retention_days {
+    if trash_retention_days > MAX_TRASH_RETENTION_DAYS {
+      return Err(
+        AppError::InvalidRequest(format!(
+          "Trash retention period must be at most {} days",
+          MAX_TRASH_RETENTION_DAYS
+        ))
+        .into(),
+      );
+    }
+    setting.trash_retention_days = trash_retention_days;
+  }
+
   // Update the workspace settings in the database
   upsert_workspace_settings(&mut tx, workspace_id, &setting).await?;
   tx.commit().await?;
@@ -0,0 +1,402 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use app_error::AppError;
+use collab::core::collab::default_client_id;
+use collab_document::blocks::{Block, DocumentData};
+use collab_document::document::DocumentBody;
+use collab_entity::CollabType;
+use collab_rt_entity::user::RealtimeUser;
+use database::collab::GetCollabOrigin;
+use serde_json::Value;
+use shared_entity::dto::workspace_dto::{DocumentBlock, MoveBlockParams, UpdateBlockParams};
+use uuid::Uuid;
+use workspace_template::document::parser::{JsonToDocumentParser, SerdeBlock};
+use yrs::TransactionMut;
+
+use super::page_view::update_page_collab_data;
+use crate::biz::collab::utils::get_latest_collab;
+use crate::state::AppState;
+
+pub async fn get_page_block(
+  state: &AppState,
+  uid: i64,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  block_id: &str,
+) -> Result<DocumentBlock, AppError> {
+  let collab = get_latest_collab(
+    &state.collab_storage,
+    GetCollabOrigin::User { uid },
+    workspace_id,
+    view_id,
+    CollabType::Document,
+    default_client_id(),
+  )
+  .await?;
+  let document_body = DocumentBody::from_collab(&collab)
+    .ok_or_else(|| AppError::Internal(anyhow!("invalid document collab")))?;
+  let document_data = document_body
+    .get_document_data(&collab.transact())
+    .map_err(|err| AppError::Internal(anyhow!(err.to_string())))?;
+  document_block(&document_data, block_id)
+}
+
+/// Inserts a block, along with its children, after `prev_block_id`, or as the first child of
+/// its parent. Returns the id of the new block.
+pub async fn insert_page_block(
+  state: &AppState,
+  user: RealtimeUser,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  serde_block: &SerdeBlock,
+  parent_block_id: Option<String>,
+  prev_block_id: Option<String>,
+) -> Result<String, AppError> {
+  edit_page_document(
+    state,
+    user,
+    workspace_id,
+    view_id,
+    |document_body, txn, document_data| {
+      let parent_id = resolve_parent_id(
+        document_data,
+        parent_block_id,
+        prev_block_id.as_deref(),
+        &document_data.page_id,
+      )?;
+      let (blocks, text_map) = JsonToDocumentParser::generate_blocks(serde_block, None, parent_id);
+      let block_id = blocks
+        .keys()
+        .next()
+        .cloned()
+        .ok_or_else(|| AppError::InvalidBlock("empty block".to_string()))?;
+
+      // Children are inserted after their previous sibling
+      let mut last_child_ids: HashMap<String, String> = HashMap::new();
+      for (id, block) in blocks {
+        let prev_id = if id == block_id {
+          prev_block_id.clone()
+        } else {
+          last_child_ids.get(&block.parent).cloned()
+        };
+        last_child_ids.insert(block.parent.clone(), id);
+        document_body
+          .insert_block(txn, block, prev_id)
+          .map_err(|err| AppError::InvalidBlock(err.to_string()))?;
+      }
+      for (text_id, text) in text_map.iter() {
+        let delta = serde_json::from_str(text).unwrap_or_else(|_| vec![]);
+        document_body
+          .text_operation
+          .apply_delta(txn, text_id, delta);
+      }
+      Ok(block_id)
+    },
+  )
+  .await
+}
+
+pub async fn update_page_block(
+  state: &AppState,
+  user: RealtimeUser,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  block_id: &str,
+  params: UpdateBlockParams,
+) -> Result<(), AppError> {
+  edit_page_document(
+    state,
+    user,
+    workspace_id,
+    view_id,
+    |document_body, txn, document_data| {
+      let block = get_block(document_data, block_id)?;
+      if let Some(changes) = params.data {
+        let mut data = block.data.clone();
+        for (key, value) in changes {
+          if value.is_null() {
+            data.remove(&key);
+          } else {
+            data.insert(key, value);
+          }
+        }
+        document_body
+          .update_block(txn, block_id, data)
+          .map_err(|err| AppError::InvalidBlock(err.to_string()))?;
+      }
+      if let Some(delta) = params.delta {
+        let text_id = block.external_id.as_ref().ok_or_else(|| {
+          AppError::InvalidRequest(format!("Block {} does not have any text", block_id))
+        })?;
+        let delta = serde_json::from_value(Value::Array(delta))
+          .map_err(|err| AppError::InvalidBlock(err.to_string()))?;
+        document_body
+          .text_operation
+          .apply_delta(txn, text_id, delta);
+      }
+      Ok(())
+    },
+  )
+  .await
+}
+
+/// Deletes a block and all its descendants.
+pub async fn delete_page_block(
+  state: &AppState,
+  user: RealtimeUser,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  block_id: &str,
+) -> Result<(), AppError> {
+  edit_page_document(
+    state,
+    user,
+    workspace_id,
+    view_id,
+    |document_body, txn, document_data| {
+      get_block(document_data, block_id)?;
+      if block_id == document_data.page_id {
+        return Err(AppError::InvalidRequest(
+          "The page block cannot be deleted".to_string(),
+        ));
+      }
+      // Delete the descendants first, so that every deleted block is a leaf
+      let block_ids = descendant_ids(document_data, block_id);
+      for id in block_ids.iter().rev() {
+        document_body
+          .delete_block(txn, id)
+          .map_err(|err| AppError::InvalidBlock(err.to_string()))?;
+      }
+      Ok(())
+    },
+  )
+  .await
+}
+
+pub async fn move_page_block(
+  state: &AppState,
+  user: RealtimeUser,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  block_id: &str,
+  params: MoveBlockParams,
+) -> Result<(), AppError> {
+  edit_page_document(
+    state,
+    user,
+    workspace_id,
+    view_id,
+    |document_body, txn, document_data| {
+      let block = get_block(document_data, block_id)?;
+      if block_id == document_data.page_id {
+        return Err(AppError::InvalidRequest(
+          "The page block cannot be moved".to_string(),
+        ));
+      }
+      if params.prev_block_id.as_deref() == Some(block_id) {
+        return Err(AppError::InvalidRequest(
+          "A block cannot be moved after itself".to_string(),
+        ));
+      }
+      let parent_id = resolve_parent_id(
+        document_data,
+        params.parent_block_id,
+        params.prev_block_id.as_deref(),
+        &block.parent,
+      )?;
+      if is_same_or_descendant(document_data, &parent_id, block_id) {
+        return Err(AppError::InvalidRequest(
+          "A block cannot be moved into itself or its descendants".to_string(),
+        ));
+      }
+      document_body
+        .move_block(txn, block_id, Some(parent_id), params.prev_block_id)
+        .map_err(|err| AppError::InvalidBlock(err.to_string()))?;
+      Ok(())
+    },
+  )
+  .await
+}
+
+/// Applies the changes made by `edit` to the latest state of the document, and publishes them
+/// to the connected clients.
+async fn edit_page_document<T>(
+  state: &AppState,
+  user: RealtimeUser,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  edit: impl FnOnce(&DocumentBody, &mut TransactionMut, &DocumentData) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+  let mut collab = get_latest_collab(
+    &state.collab_storage,
+    GetCollabOrigin::User { uid: user.uid },
+    workspace_id,
+    view_id,
+    CollabType::Document,
+    default_client_id(),
+  )
+  .await?;
+  let document_body = DocumentBody::from_collab(&collab)
+    .ok_or_else(|| AppError::Internal(anyhow!("invalid document collab")))?;
+  let document_data = document_body
+    .get_document_data(&collab.transact())
+    .map_err(|err| AppError::Internal(anyhow!(err.to_string())))?;
+  let (update, result) = {
+    let mut txn = collab.transact_mut();
+    let result = edit(&document_body, &mut txn, &document_data)?;
+    (txn.encode_update_v1(), result)
+  };
+  update_page_collab_data(
+    state,
+    user,
+    workspace_id,
+    view_id,
+    CollabType::Document,
+    update,
+  )
+  .await?;
+  Ok(result)
+}
+
+fn get_block<'a>(document_data: &'a DocumentData, block_id: &str) -> Result<&'a Block, AppError> {
+  document_data
+    .blocks
+    .get(block_id)
+    .ok_or_else(|| AppError::RecordNotFound(format!("Block {} not found", block_id)))
+}
+
+fn child_ids(document_data: &DocumentData, block_id: &str) -> Vec<String> {
+  document_data
+    .blocks
+    .get(block_id)
+    .and_then(|block| document_data.meta.children_map.get(&block.children))
+    .cloned()
+    .unwrap_or_default()
+}
+
+/// Returns the parent of a block inserted or moved after `prev_block_id`, which must be a child
+/// of the requested parent if both are set.
+fn resolve_parent_id(
+  document_data: &DocumentData,
+  parent_block_id: Option<String>,
+  prev_block_id: Option<&str>,
+  default_parent_id: &str,
+) -> Result<String, AppError> {
+  let prev_parent_id = match prev_block_id {
+    Some(prev_block_id) => Some(get_block(document_data, prev_block_id)?.parent.clone()),
+    None => None,
+  };
+  let parent_id = match (parent_block_id, prev_parent_id) {
+    (Some(parent_id), Some(prev_parent_id)) if parent_id != prev_parent_id => {
+      return Err(AppError::InvalidRequest(format!(
+        "Block {} is not a child of block {}",
+        prev_block_id.unwrap_or_default(),
+        parent_id
+      )));
+    },
+    (Some(parent_id), _) | (None, Some(parent_id)) => parent_id,
+    (None, None) => default_parent_id.to_string(),
+  };
+  get_block(document_data, &parent_id)?;
+  Ok(parent_id)
+}
+
+/// Returns the block and its descendants, parents before their children. The children map is
+/// written by the clients and may be cyclic or list a block twice, so each block is returned once.
+fn descendant_ids(document_data: &DocumentData, block_id: &str) -> Vec<String> {
+  let mut visited = HashSet::from([block_id.to_string()]);
+  let mut block_ids = vec![block_id.to_string()];
+  let mut index = 0;
+  while index < block_ids.len() {
+    for child_id in child_ids(document_data, &block_ids[index]) {
+      if visited.insert(child_id.clone()) {
+        block_ids.push(child_id);
+      }
+    }
+    index += 1;
+  }
+  block_ids
+}
+
+fn is_same_or_descendant(document_data: &DocumentData, block_id: &str, ancestor_id: &str) -> bool {
+  // The parents may be cyclic too, so the walk stops at the first block seen twice
+  let mut visited = HashSet::new();
+  let mut current = Some(block_id);
+  while let Some(id) = current {
+    if id == ancestor_id {
+      return true;
+    }
+    if !visited.insert(id) {
+      return false;
+    }
+    current = document_data
+      .blocks
+      .get(id)
+      .map(|block| block.parent.as_str())
+      .filter(|parent| !parent.is_empty());
+  }
+  false
+}
+
+fn document_block(document_data: &DocumentData, block_id: &str) -> Result<DocumentBlock, AppError> {
+  let block = get_block(document_data, block_id)?;
+  let delta = block
+    .external_id
+    .as_ref()
+    .and_then(|text_id| document_data.meta.text_map.as_ref()?.get(text_id))
+    .and_then(|delta| serde_json::from_str::<Vec<Value>>(delta).ok());
+  Ok(DocumentBlock {
+    id: block.id.clone(),
+    ty: block.ty.clone(),
+    parent_id: block.parent.clone(),
+    children: child_ids(document_data, block_id),
+    data: block.data.clone(),
+    delta,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use collab_document::blocks::DocumentMeta;
+
+  fn cyclic_document() -> DocumentData {
+    let block = |id: &str, parent: &str| Block {
+      id: id.to_string(),
+      ty: "paragraph".to_string(),
+      data: HashMap::new(),
+      parent: parent.to_string(),
+      children: format!("{}_children", id),
+      external_id: None,
+      external_type: None,
+    };
+    DocumentData {
+      page_id: "page".to_string(),
+      blocks: HashMap::from([
+        ("page".to_string(), block("page", "")),
+        ("a".to_string(), block("a", "b")),
+        ("b".to_string(), block("b", "a")),
+      ]),
+      meta: DocumentMeta {
+        children_map: HashMap::from([
+          ("page_children".to_string(), vec!["a".to_string()]),
+          (
+            "a_children".to_string(),
+            vec!["b".to_string(), "b".to_string()],
+          ),
+          ("b_children".to_string(), vec!["a".to_string()]),
+        ]),
+        text_map: None,
+      },
+    }
+  }
+
+  #[test]
+  fn cyclic_blocks_are_walked_once() {
+    let document_data = cyclic_document();
+    assert_eq!(descendant_ids(&document_data, "a"), vec!["a", "b"]);
+    assert!(is_same_or_descendant(&document_data, "b", "a"));
+    assert!(!is_same_or_descendant(&document_data, "b", "page"));
+  }
+}
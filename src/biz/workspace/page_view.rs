@@ -1,4 +1,6 @@
+use super::document_walker::{BlockWalker, MAX_BLOCK_DEPTH};
 use super::publish::PublishedCollabStore;
+use super::publish_access::hash_publish_password;
 use crate::api::metrics::AppFlowyWebMetrics;
 use crate::biz::chat::ops::create_chat;
 use crate::biz::collab::database::{
@@ -18,7 +20,7 @@ use crate::state::AppState;
 use anyhow::anyhow;
 use app_error::AppError;
 use appflowy_collaborate::ws2::{CollabUpdatePublisher, WorkspaceCollabInstanceCache};
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use collab::core::collab::{default_client_id, Collab, CollabOptions};
 use collab::core::origin::CollabClient;
 use collab_database::database::{
@@ -40,8 +42,10 @@ use collab_database::views::{
 };
 use collab_database::workspace_database::WorkspaceDatabase;
 use collab_database::{database::DatabaseBody, rows::RowId};
+use collab_document::blocks::DocumentData;
 use collab_document::document::{Document, DocumentBody};
 use collab_document::document_data::default_document_data;
+use collab_document::importer::md_importer::MDImporter;
 use collab_entity::{CollabType, EncodedCollab};
 use collab_folder::hierarchy_builder::NestedChildViewBuilder;
 use collab_folder::{timestamp, CollabOrigin, Folder, SectionItem, SpaceInfo, View};
@@ -49,7 +53,7 @@ use collab_rt_entity::user::RealtimeUser;
 use database::collab::{
   select_collab_meta_from_af_collab, select_workspace_database_oid, CollabStore, GetCollabOrigin,
 };
-use database::publish::select_published_view_ids_for_workspace;
+use database::publish::{select_published_view_ids_for_workspace, PublishedCollabAccessUpdate};
 use database::user::{select_uuid_from_uid, select_web_user_from_uid};
 use database::workspace::{
   select_workspace_member_uuid_exclude_guest, select_workspace_mentionable_members_or_guests,
@@ -540,6 +544,48 @@ pub async fn append_block_at_the_end_of_page(
   update_page_collab_data(state, user, workspace_id, oid, CollabType::Document, update).await
 }
 
+/// Converts Markdown into the top level blocks of a document, along with their children. Markdown
+/// nested deeper than [MAX_BLOCK_DEPTH] blocks is rejected.
+pub fn markdown_to_serde_blocks(markdown: &str) -> Result<Vec<SerdeBlock>, AppError> {
+  let document_data = MDImporter::new(None)
+    .import(&Uuid::new_v4().to_string(), markdown.to_string())
+    .map_err(|err| AppError::InvalidPageData(format!("Failed to import markdown: {:?}", err)))?;
+  let mut walker = BlockWalker::default();
+  serde_block_children(&document_data, &mut walker, &document_data.page_id)
+}
+
+fn serde_block_children(
+  document_data: &DocumentData,
+  walker: &mut BlockWalker,
+  block_id: &str,
+) -> Result<Vec<SerdeBlock>, AppError> {
+  let children = walker.enter(document_data, block_id).ok_or_else(|| {
+    AppError::InvalidRequest(format!(
+      "Markdown can not be nested deeper than {} blocks",
+      MAX_BLOCK_DEPTH
+    ))
+  })?;
+  let mut serde_blocks = Vec::with_capacity(children.len());
+  for block in children {
+    let mut data = block.data.clone();
+    let delta = block
+      .external_id
+      .as_ref()
+      .and_then(|text_id| document_data.meta.text_map.as_ref()?.get(text_id))
+      .and_then(|delta| serde_json::from_str::<serde_json::Value>(delta).ok());
+    if let Some(delta) = delta {
+      data.insert("delta".to_string(), delta);
+    }
+    serde_blocks.push(SerdeBlock {
+      ty: block.ty.clone(),
+      data,
+      children: serde_block_children(document_data, walker, &block.id)?,
+    });
+  }
+  walker.leave();
+  Ok(serde_blocks)
+}
+
 async fn append_block_to_document_collab(
   uid: i64,
   collab_storage: &Arc<dyn CollabStore>,
@@ -1670,7 +1716,20 @@ pub async fn publish_page(
   publish_name: Option<impl ToString>,
   comments_enabled: bool,
   duplicate_enabled: bool,
+  password: Option<String>,
+  expires_at: Option<DateTime<Utc>>,
 ) -> Result<(), AppError> {
+  if matches!(expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+    return Err(AppError::InvalidRequest(
+      "Publish expiration date must be in the future".to_string(),
+    ));
+  }
+  // the password is checked before anything is published, so that the page is never public
+  // without its protection
+  let password_hash = match password {
+    Some(password) => Some(hash_publish_password(password).await?),
+    None => None,
+  };
   let folder = state.ws_server.get_folder(workspace_id).await?;
   let view = folder
     .get_view(&view_id.to_string(), uid)
@@ -1739,6 +1798,12 @@ pub async fn publish_page(
       }],
       &workspace_id,
       &user_uuid,
+      // republishing keeps the access settings, unless they are explicitly given
+      &[PublishedCollabAccessUpdate {
+        view_id,
+        password_hash: password_hash.map(Some),
+        expires_at: expires_at.map(Some),
+      }],
     )
     .await?;
   Ok(())
@@ -2516,3 +2581,28 @@ async fn get_all_user_uuids_with_guest_access_to_page(
   // Note: the open source version of AppFlowy Cloud does not support guest access, hence this will always return empty.
   Ok(vec![])
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nested_list(depth: usize) -> String {
+    (0..depth)
+      .map(|level| format!("{}- item {}\n", "  ".repeat(level), level))
+      .collect()
+  }
+
+  #[test]
+  fn nested_markdown_is_converted() {
+    let blocks = markdown_to_serde_blocks(&nested_list(3)).unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].children.len(), 1);
+    assert_eq!(blocks[0].children[0].children.len(), 1);
+  }
+
+  #[test]
+  fn deeply_nested_markdown_is_rejected() {
+    let result = markdown_to_serde_blocks(&nested_list(MAX_BLOCK_DEPTH * 4));
+    assert!(matches!(result, Err(AppError::InvalidRequest(_))));
+  }
+}
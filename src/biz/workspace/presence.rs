@@ -0,0 +1,52 @@
+use access_control::collab::RealtimeAccessControl;
+use app_error::AppError;
+use chrono::DateTime;
+use collab_stream::awareness_gossip::AwarenessGossip;
+use itertools::Itertools;
+use shared_entity::dto::workspace_dto::{ActiveCollabUser, CollabPresenceInfo, WorkspacePresence};
+use uuid::Uuid;
+
+/// Returns users currently viewing or editing the collabs of a given workspace. Collabs, which
+/// the requesting user is not allowed to read, are omitted.
+pub async fn get_workspace_presence(
+  awareness_gossip: &AwarenessGossip,
+  access_control: &dyn RealtimeAccessControl,
+  uid: i64,
+  workspace_id: Uuid,
+  object_id: Option<Uuid>,
+) -> Result<WorkspacePresence, AppError> {
+  let presence = awareness_gossip
+    .workspace_presence(&workspace_id)
+    .await
+    .map_err(|err| AppError::Internal(err.into()))?;
+  let presence_by_object = presence
+    .into_iter()
+    .filter(|presence| object_id.is_none_or(|object_id| presence.object_id == object_id))
+    .into_group_map_by(|presence| presence.object_id);
+
+  let mut objects = Vec::with_capacity(presence_by_object.len());
+  for (object_id, presence) in presence_by_object {
+    if !access_control
+      .can_read_collab(&workspace_id, &uid, &object_id)
+      .await?
+    {
+      continue;
+    }
+    let active_users = presence
+      .into_iter()
+      .sorted_by(|a, b| b.last_active_at.cmp(&a.last_active_at))
+      .filter_map(|presence| {
+        Some(ActiveCollabUser {
+          uid: presence.uid,
+          device_id: presence.device_id,
+          last_active_at: DateTime::from_timestamp_millis(presence.last_active_at)?,
+        })
+      })
+      .collect();
+    objects.push(CollabPresenceInfo {
+      object_id,
+      active_users,
+    });
+  }
+  Ok(WorkspacePresence { objects })
+}
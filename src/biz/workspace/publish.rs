@@ -3,8 +3,8 @@ use database::{
     insert_non_orginal_workspace_publish_namespace, select_all_published_collab_info,
     select_default_published_view_id, select_default_published_view_id_for_namespace,
     select_workspace_publish_namespace, select_workspace_publish_namespaces,
-    update_published_collabs, update_workspace_default_publish_view,
-    update_workspace_default_publish_view_set_null,
+    update_published_collab_password, update_published_collabs,
+    update_workspace_default_publish_view, update_workspace_default_publish_view_set_null,
   },
   workspace::{select_publish_name_exists, select_view_id_from_publish_name},
 };
@@ -31,12 +31,14 @@ use database::{
     select_published_data_for_view_id, select_published_metadata_for_view_id,
     select_user_is_collab_publisher_for_all_views, select_workspace_publish_namespace_exists,
     set_published_collabs_as_unpublished, update_non_orginal_workspace_publish_namespace,
+    PublishedCollabAccessUpdate,
   },
   workspace::select_user_is_workspace_owner,
 };
 
 use crate::{
   api::metrics::PublishedCollabMetrics, biz::collab::folder_view::to_dto_folder_view_miminal,
+  biz::workspace::publish_access::hash_publish_password,
 };
 
 use appflowy_collaborate::ws2::WorkspaceCollabInstanceCache;
@@ -252,11 +254,13 @@ async fn check_workspace_namespace(new_namespace: &str) -> Result<(), AppError>
 
 #[async_trait]
 pub trait PublishedCollabStore: Sync + Send + 'static {
+  /// Publishes the collabs, along with the given changes of their access settings.
   async fn publish_collabs(
     &self,
     published_items: Vec<PublishCollabItem<serde_json::Value, Vec<u8>>>,
     workspace_id: &Uuid,
     user_uuid: &Uuid,
+    access_updates: &[PublishedCollabAccessUpdate],
   ) -> Result<(), AppError>;
 
   async fn get_collab_with_view_metadata_by_view_id(
@@ -316,6 +320,7 @@ impl PublishedCollabStore for PublishedCollabPostgresStore {
     publish_items: Vec<PublishCollabItem<serde_json::Value, Vec<u8>>>,
     workspace_id: &Uuid,
     user_uuid: &Uuid,
+    access_updates: &[PublishedCollabAccessUpdate],
   ) -> Result<(), AppError> {
     for publish_item in &publish_items {
       check_collab_publish_name(publish_item.meta.publish_name.as_str())?;
@@ -328,9 +333,14 @@ impl PublishedCollabStore for PublishedCollabPostgresStore {
       .await?;
     }
     let publish_items_batch_size = publish_items.len() as i64;
-    let result =
-      insert_or_replace_publish_collabs(&self.pg_pool, workspace_id, user_uuid, publish_items)
-        .await;
+    let result = insert_or_replace_publish_collabs(
+      &self.pg_pool,
+      workspace_id,
+      user_uuid,
+      publish_items,
+      access_updates,
+    )
+    .await;
     if result.is_err() {
       self
         .metrics
@@ -445,6 +455,7 @@ impl PublishedCollabStore for PublishedCollabS3StoreWithPostgresFallback {
     publish_items: Vec<PublishCollabItem<serde_json::Value, Vec<u8>>>,
     workspace_id: &Uuid,
     user_uuid: &Uuid,
+    access_updates: &[PublishedCollabAccessUpdate],
   ) -> Result<(), AppError> {
     let publish_items_batch_size = publish_items.len() as i64;
     let mut handles: Vec<tokio::task::JoinHandle<()>> = vec![];
@@ -477,9 +488,14 @@ impl PublishedCollabStore for PublishedCollabS3StoreWithPostgresFallback {
       handle.await?;
     }
 
-    let result =
-      insert_or_replace_publish_collabs(&self.pg_pool, workspace_id, user_uuid, publish_items)
-        .await;
+    let result = insert_or_replace_publish_collabs(
+      &self.pg_pool,
+      workspace_id,
+      user_uuid,
+      publish_items,
+      access_updates,
+    )
+    .await;
     if result.is_err() {
       self
         .metrics
@@ -626,9 +642,23 @@ async fn patch_collabs(
     }
   }
   check_workspace_owner_or_publisher(pg_pool, user_uuid, workspace_id, &view_ids).await?;
+  let mut password_hashes = Vec::new();
+  for patch in patches {
+    if let Some(password) = &patch.password {
+      let password_hash = match password {
+        Some(password) => Some(hash_publish_password(password.clone()).await?),
+        None => None,
+      };
+      password_hashes.push((patch.view_id, password_hash));
+    }
+  }
 
   let mut txn = pg_pool.begin().await?;
   update_published_collabs(&mut txn, workspace_id, patches).await?;
+  for (view_id, password_hash) in password_hashes {
+    update_published_collab_password(&mut txn, workspace_id, &view_id, password_hash.as_deref())
+      .await?;
+  }
   txn.commit().await?;
   Ok(())
 }
@@ -0,0 +1,291 @@
+use anyhow::anyhow;
+use app_error::AppError;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use database::pg_row::AFPublishedCollabAccess;
+use database::publish::{select_published_collab_access, select_published_view_access};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use shared_entity::dto::workspace_dto::PublishedCollabAccess;
+use sqlx::PgPool;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use crate::state::RedisConnectionManager;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long the access to a password protected published page is valid, once the guest
+/// provided the correct password.
+const PUBLISH_ACCESS_TTL_SECS: i64 = 60 * 60;
+
+/// Number of passwords a guest can try for a single published page within
+/// [PASSWORD_ATTEMPTS_WINDOW_SECS].
+const MAX_PASSWORD_ATTEMPTS: i64 = 10;
+const PASSWORD_ATTEMPTS_WINDOW_SECS: i64 = 15 * 60;
+
+/// Name of the cookie, which stores the access token to a password protected published page.
+pub fn publish_access_cookie_name(view_id: &Uuid) -> String {
+  format!("af_publish_access_{}", view_id.simple())
+}
+
+/// Hashes the password of a published page. Hashing is slow on purpose, so it runs on a
+/// blocking thread.
+pub async fn hash_publish_password(password: String) -> Result<String, AppError> {
+  tokio::task::spawn_blocking(move || hash_password(&password)).await?
+}
+
+fn hash_password(password: &str) -> Result<String, AppError> {
+  if password.is_empty() {
+    return Err(AppError::InvalidRequest(
+      "Publish password must not be empty".to_string(),
+    ));
+  }
+  let salt = SaltString::generate(&mut OsRng);
+  let hash = Argon2::default()
+    .hash_password(password.as_bytes(), &salt)
+    .map_err(|err| AppError::Internal(anyhow!("Failed to hash publish password: {}", err)))?;
+  Ok(hash.to_string())
+}
+
+fn verify_publish_password(password_hash: &str, password: &str) -> bool {
+  match PasswordHash::new(password_hash) {
+    Ok(hash) => Argon2::default()
+      .verify_password(password.as_bytes(), &hash)
+      .is_ok(),
+    Err(err) => {
+      tracing::error!("Invalid publish password hash: {}", err);
+      false
+    },
+  }
+}
+
+/// The signature covers the password hash, so that changing or removing the password revokes
+/// the access granted so far.
+fn access_signature(
+  secret: &Secret<String>,
+  view_id: &Uuid,
+  password_hash: &str,
+  expires_at: i64,
+) -> HmacSha256 {
+  let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+    .expect("HMAC can take key of any size");
+  mac.update(view_id.as_bytes());
+  mac.update(&expires_at.to_be_bytes());
+  mac.update(password_hash.as_bytes());
+  mac
+}
+
+/// Access token has the form of `{expires_at}.{signature}`.
+fn sign_publish_access(
+  secret: &Secret<String>,
+  view_id: &Uuid,
+  password_hash: &str,
+  expires_at: DateTime<Utc>,
+) -> String {
+  let expires_at = expires_at.timestamp();
+  let signature = access_signature(secret, view_id, password_hash, expires_at)
+    .finalize()
+    .into_bytes();
+  format!("{}.{}", expires_at, URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn verify_publish_access(
+  secret: &Secret<String>,
+  view_id: &Uuid,
+  password_hash: &str,
+  token: &str,
+  now: DateTime<Utc>,
+) -> bool {
+  let Some((expires_at, signature)) = token.split_once('.') else {
+    return false;
+  };
+  let (Ok(expires_at), Ok(signature)) =
+    (expires_at.parse::<i64>(), URL_SAFE_NO_PAD.decode(signature))
+  else {
+    return false;
+  };
+  expires_at > now.timestamp()
+    && access_signature(secret, view_id, password_hash, expires_at)
+      .verify_slice(&signature)
+      .is_ok()
+}
+
+/// Counts the password attempt of a guest, identified by the IP address. Attempts are counted
+/// before the password is hashed and are shared by all server nodes.
+async fn record_password_attempt(
+  redis: &RedisConnectionManager,
+  view_id: &Uuid,
+  client_ip: Option<IpAddr>,
+) -> Result<(), AppError> {
+  let key = format!(
+    "af:publish_password_attempts:{}:{}",
+    view_id,
+    client_ip.map(|ip| ip.to_string()).unwrap_or_default()
+  );
+  let (attempts,): (i64,) = redis::pipe()
+    .atomic()
+    .cmd("SET")
+    .arg(&key)
+    .arg(0)
+    .arg("EX")
+    .arg(PASSWORD_ATTEMPTS_WINDOW_SECS)
+    .arg("NX")
+    .ignore()
+    .incr(&key, 1)
+    .query_async(&mut redis.clone())
+    .await
+    .map_err(|err| AppError::Internal(err.into()))?;
+  if attempts > MAX_PASSWORD_ATTEMPTS {
+    return Err(AppError::PublishPasswordAttemptsExceeded);
+  }
+  Ok(())
+}
+
+/// Checks the password of a published page. Returns the granted access together with the access
+/// token, which should be passed back by the guest when requesting the published page.
+pub async fn unlock_published_collab(
+  pg_pool: &PgPool,
+  redis: &RedisConnectionManager,
+  jwt_secret: &Secret<String>,
+  publish_namespace: &str,
+  publish_name: &str,
+  password: String,
+  client_ip: Option<IpAddr>,
+) -> Result<(PublishedCollabAccess, String), AppError> {
+  let access = select_published_collab_access(pg_pool, publish_namespace, publish_name)
+    .await?
+    .ok_or_else(|| {
+      AppError::RecordNotFound(format!(
+        "Published collab not found for {}/{}",
+        publish_namespace, publish_name
+      ))
+    })?;
+  let password_hash = access.password_hash.unwrap_or_default();
+  if !password_hash.is_empty() {
+    record_password_attempt(redis, &access.view_id, client_ip).await?;
+    let hash = password_hash.clone();
+    let is_valid =
+      tokio::task::spawn_blocking(move || verify_publish_password(&hash, &password)).await?;
+    if !is_valid {
+      return Err(AppError::InvalidPassword(
+        "Incorrect publish password".to_string(),
+      ));
+    }
+  }
+  let expires_at = Utc::now() + Duration::seconds(PUBLISH_ACCESS_TTL_SECS);
+  let token = sign_publish_access(jwt_secret, &access.view_id, &password_hash, expires_at);
+  Ok((
+    PublishedCollabAccess {
+      view_id: access.view_id,
+      expires_at,
+    },
+    token,
+  ))
+}
+
+/// Verifies that the guest is allowed to access a published page. `access_token` returns
+/// the access token passed by the guest for the given view id, if any.
+pub async fn check_published_collab_access(
+  pg_pool: &PgPool,
+  jwt_secret: &Secret<String>,
+  publish_namespace: &str,
+  publish_name: &str,
+  access_token: impl FnOnce(&Uuid) -> Option<String>,
+) -> Result<(), AppError> {
+  let access =
+    match select_published_collab_access(pg_pool, publish_namespace, publish_name).await? {
+      Some(access) => access,
+      // let the caller report the missing collab
+      None => return Ok(()),
+    };
+  ensure_published_collab_access(jwt_secret, access, access_token)
+}
+
+/// Same as [check_published_collab_access], for the published page identified by its view id.
+/// Pages which were unpublished or have expired are reported as missing.
+pub async fn check_published_view_access(
+  pg_pool: &PgPool,
+  jwt_secret: &Secret<String>,
+  view_id: &Uuid,
+  access_token: impl FnOnce(&Uuid) -> Option<String>,
+) -> Result<(), AppError> {
+  let access = select_published_view_access(pg_pool, view_id)
+    .await?
+    .ok_or_else(|| AppError::RecordNotFound(format!("Published view {} not found", view_id)))?;
+  ensure_published_collab_access(jwt_secret, access, access_token)
+}
+
+fn ensure_published_collab_access(
+  jwt_secret: &Secret<String>,
+  access: AFPublishedCollabAccess,
+  access_token: impl FnOnce(&Uuid) -> Option<String>,
+) -> Result<(), AppError> {
+  let Some(password_hash) = access.password_hash else {
+    return Ok(());
+  };
+  match access_token(&access.view_id) {
+    Some(token)
+      if verify_publish_access(
+        jwt_secret,
+        &access.view_id,
+        &password_hash,
+        &token,
+        Utc::now(),
+      ) =>
+    {
+      Ok(())
+    },
+    _ => Err(AppError::PublishPasswordRequired),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn publish_password_roundtrip() {
+    let hash = hash_password("secret").unwrap();
+    assert!(verify_publish_password(&hash, "secret"));
+    assert!(!verify_publish_password(&hash, "Secret"));
+    assert!(hash_password("").is_err());
+  }
+
+  #[test]
+  fn publish_access_token_is_bound_to_view_and_password() {
+    let secret = Secret::new("jwt-secret".to_string());
+    let view_id = Uuid::new_v4();
+    let now = Utc::now();
+    let token = sign_publish_access(&secret, &view_id, "hash", now + Duration::seconds(60));
+
+    assert!(verify_publish_access(
+      &secret, &view_id, "hash", &token, now
+    ));
+    assert!(!verify_publish_access(
+      &secret,
+      &Uuid::new_v4(),
+      "hash",
+      &token,
+      now
+    ));
+    assert!(!verify_publish_access(
+      &secret, &view_id, "changed", &token, now
+    ));
+    assert!(!verify_publish_access(
+      &secret,
+      &view_id,
+      "hash",
+      &token,
+      now + Duration::seconds(61)
+    ));
+    assert!(!verify_publish_access(
+      &secret, &view_id, "hash", "garbage", now
+    ));
+  }
+}
@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use app_error::AppError;
+use chrono::{Days, NaiveDate, Utc};
+use database::publish::select_published_collab_workspace_view_id;
+use database::publish_analytics::{
+  delete_published_view_daily_salts_before, delete_published_view_visitors_before,
+  insert_published_view_visitors, select_published_view_breakdown,
+  select_published_view_daily_stats, select_workspace_published_daily_stats,
+  select_workspace_published_view_stats, upsert_published_view_daily_breakdown,
+  upsert_published_view_daily_salt, upsert_published_view_daily_stats, BREAKDOWN_COUNTRY,
+  BREAKDOWN_REFERRER,
+};
+use database_entity::dto::{PublishNamespaceAnalytics, PublishedViewAnalytics};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::time::interval;
+use tracing::{error, trace, warn};
+use uuid::Uuid;
+
+use crate::config::config::PublishAnalyticsSetting;
+
+use super::publish::get_workspace_publish_namespace;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const DEFAULT_ANALYTICS_DAYS: u32 = 30;
+const MAX_ANALYTICS_DAYS: u32 = 365;
+const MAX_BREAKDOWN_ENTRIES: i64 = 20;
+/// Maximum number of view events waiting to be flushed. Events are flushed as soon as that many
+/// are pending, and views beyond that are not recorded while the flush keeps failing.
+const MAX_PENDING_EVENTS: usize = 10_000;
+const DAILY_SALT_LEN: usize = 32;
+
+const BOT_USER_AGENT_MARKERS: [&str; 6] =
+  ["bot", "crawler", "spider", "slurp", "preview", "headless"];
+
+/// Information about a guest viewing a published page. None of it is stored as is.
+#[derive(Debug, Default)]
+pub struct PublishVisitor {
+  pub ip: Option<IpAddr>,
+  pub user_agent: Option<String>,
+  pub referrer: Option<String>,
+}
+
+#[derive(Debug)]
+struct PublishViewEvent {
+  publish_namespace: String,
+  publish_name: String,
+  day: NaiveDate,
+  ip: Option<IpAddr>,
+  user_agent: String,
+  referrer: Option<String>,
+  country: Option<String>,
+}
+
+/// Records views of published pages. Events are aggregated in memory and periodically flushed
+/// to Postgres by [PublishAnalyticsWorker].
+#[derive(Clone)]
+pub struct PublishAnalytics {
+  tx: Option<mpsc::Sender<PublishViewEvent>>,
+  geoip: Option<Arc<maxminddb::Reader<Vec<u8>>>>,
+}
+
+impl PublishAnalytics {
+  pub fn new(
+    pg_pool: PgPool,
+    setting: &PublishAnalyticsSetting,
+  ) -> (Self, Option<PublishAnalyticsWorker>) {
+    if !setting.enabled {
+      let analytics = Self {
+        tx: None,
+        geoip: None,
+      };
+      return (analytics, None);
+    }
+
+    let geoip = setting.geoip_db_path.as_ref().and_then(|path| {
+      match maxminddb::Reader::open_readfile(path) {
+        Ok(reader) => Some(Arc::new(reader)),
+        Err(err) => {
+          error!("Failed to open GeoIP database at {}: {}", path, err);
+          None
+        },
+      }
+    });
+    let (tx, rx) = mpsc::channel(MAX_PENDING_EVENTS);
+    let worker = PublishAnalyticsWorker {
+      pg_pool,
+      rx,
+      flush_interval_secs: setting.flush_interval_secs.max(1),
+    };
+    let analytics = Self {
+      tx: Some(tx),
+      geoip,
+    };
+    (analytics, Some(worker))
+  }
+
+  pub fn record_view(&self, publish_namespace: &str, publish_name: &str, visitor: PublishVisitor) {
+    let Some(tx) = &self.tx else {
+      return;
+    };
+    let user_agent = visitor.user_agent.unwrap_or_default();
+    if is_bot(&user_agent) {
+      return;
+    }
+
+    let event = PublishViewEvent {
+      publish_namespace: publish_namespace.to_string(),
+      publish_name: publish_name.to_string(),
+      day: Utc::now().date_naive(),
+      ip: visitor.ip,
+      user_agent,
+      referrer: visitor.referrer.as_deref().and_then(referrer_domain),
+      country: visitor.ip.and_then(|ip| self.lookup_country(ip)),
+    };
+    if let Err(err) = tx.try_send(event) {
+      trace!("Dropping published page view event: {}", err);
+    }
+  }
+
+  fn lookup_country(&self, ip: IpAddr) -> Option<String> {
+    let reader = self.geoip.as_ref()?;
+    let country = reader.lookup::<maxminddb::geoip2::Country>(ip).ok()?;
+    country
+      .country
+      .and_then(|country| country.iso_code)
+      .map(|code| code.to_string())
+  }
+}
+
+pub struct PublishAnalyticsWorker {
+  pg_pool: PgPool,
+  rx: mpsc::Receiver<PublishViewEvent>,
+  flush_interval_secs: u64,
+}
+
+impl PublishAnalyticsWorker {
+  pub async fn start_task(mut self) {
+    let mut interval = interval(Duration::from_secs(self.flush_interval_secs));
+    let mut events = Vec::with_capacity(MAX_PENDING_EVENTS);
+    loop {
+      tokio::select! {
+        // stop receiving while the flush of a full buffer keeps failing, the new events are
+        // then dropped by the sender
+        event = self.rx.recv(), if events.len() < MAX_PENDING_EVENTS => match event {
+          Some(event) => {
+            events.push(event);
+            if events.len() < MAX_PENDING_EVENTS {
+              continue;
+            }
+          },
+          None => break,
+        },
+        _ = interval.tick() => {},
+      }
+      // events are kept until they are flushed successfully
+      match self.flush(&events).await {
+        Ok(()) => events.clear(),
+        Err(err) => error!("Failed to flush published page view events: {}", err),
+      }
+    }
+  }
+
+  async fn flush(&self, events: &[PublishViewEvent]) -> Result<(), AppError> {
+    let today = Utc::now().date_naive();
+    // Visitor hashes are only needed to count unique visitors within a single day. Keep the ones
+    // from yesterday, since events might be flushed shortly after midnight. The salts are
+    // discarded with them, so the remaining hashes can't be linked to visitors.
+    if let Some(yesterday) = today.checked_sub_days(Days::new(1)) {
+      delete_published_view_visitors_before(&self.pg_pool, yesterday).await?;
+      delete_published_view_daily_salts_before(&self.pg_pool, yesterday).await?;
+    }
+    if events.is_empty() {
+      return Ok(());
+    }
+
+    // the salts are shared by all the server nodes, so that a visitor is counted once a day
+    let mut salts = HashMap::new();
+    for event in events {
+      if salts.contains_key(&event.day) {
+        continue;
+      }
+      let mut salt = vec![0; DAILY_SALT_LEN];
+      rand::thread_rng().fill_bytes(&mut salt);
+      let salt = upsert_published_view_daily_salt(&self.pg_pool, event.day, &salt).await?;
+      salts.insert(event.day, salt);
+    }
+
+    let mut keys = HashMap::new();
+    for event in events {
+      let key = (event.publish_namespace.clone(), event.publish_name.clone());
+      if keys.contains_key(&key) {
+        continue;
+      }
+      match select_published_collab_workspace_view_id(&self.pg_pool, &key.0, &key.1).await {
+        Ok(collab_key) => {
+          keys.insert(key, Some((collab_key.workspace_id, collab_key.view_id)));
+        },
+        Err(err) => {
+          warn!(
+            "Failed to resolve published page {}/{}: {}",
+            key.0, key.1, err
+          );
+          keys.insert(key, None);
+        },
+      }
+    }
+
+    let mut views: HashMap<(Uuid, Uuid, NaiveDate), (i64, i64)> = HashMap::new();
+    let mut breakdown: HashMap<(Uuid, Uuid, NaiveDate, &str, String), i64> = HashMap::new();
+    let mut visitor_view_ids = vec![];
+    let mut visitor_days = vec![];
+    let mut visitor_hashes = vec![];
+    let mut workspace_by_view = HashMap::new();
+    for event in events {
+      let key = (event.publish_namespace.clone(), event.publish_name.clone());
+      let Some(Some((workspace_id, view_id))) = keys.get(&key).copied() else {
+        continue;
+      };
+      workspace_by_view.insert(view_id, workspace_id);
+      views
+        .entry((workspace_id, view_id, event.day))
+        .or_default()
+        .0 += 1;
+      if let Some(referrer) = &event.referrer {
+        *breakdown
+          .entry((
+            workspace_id,
+            view_id,
+            event.day,
+            BREAKDOWN_REFERRER,
+            referrer.clone(),
+          ))
+          .or_default() += 1;
+      }
+      if let Some(country) = &event.country {
+        *breakdown
+          .entry((
+            workspace_id,
+            view_id,
+            event.day,
+            BREAKDOWN_COUNTRY,
+            country.clone(),
+          ))
+          .or_default() += 1;
+      }
+      visitor_view_ids.push(view_id);
+      visitor_days.push(event.day);
+      visitor_hashes.push(visitor_hash(
+        &salts[&event.day],
+        event.day,
+        event.ip.as_ref(),
+        &event.user_agent,
+      ));
+    }
+    if views.is_empty() {
+      return Ok(());
+    }
+
+    let mut txn = self.pg_pool.begin().await?;
+    let new_visitors =
+      insert_published_view_visitors(&mut txn, &visitor_view_ids, &visitor_days, &visitor_hashes)
+        .await?;
+    for (view_id, day) in new_visitors {
+      if let Some(workspace_id) = workspace_by_view.get(&view_id) {
+        views.entry((*workspace_id, view_id, day)).or_default().1 += 1;
+      }
+    }
+
+    let mut workspace_ids = Vec::with_capacity(views.len());
+    let mut view_ids = Vec::with_capacity(views.len());
+    let mut days = Vec::with_capacity(views.len());
+    let mut view_counts = Vec::with_capacity(views.len());
+    let mut unique_counts = Vec::with_capacity(views.len());
+    for ((workspace_id, view_id, day), (count, unique)) in views {
+      workspace_ids.push(workspace_id);
+      view_ids.push(view_id);
+      days.push(day);
+      view_counts.push(count);
+      unique_counts.push(unique);
+    }
+    upsert_published_view_daily_stats(
+      &mut txn,
+      &workspace_ids,
+      &view_ids,
+      &days,
+      &view_counts,
+      &unique_counts,
+    )
+    .await?;
+
+    if !breakdown.is_empty() {
+      let mut workspace_ids = Vec::with_capacity(breakdown.len());
+      let mut view_ids = Vec::with_capacity(breakdown.len());
+      let mut days = Vec::with_capacity(breakdown.len());
+      let mut dimensions = Vec::with_capacity(breakdown.len());
+      let mut values = Vec::with_capacity(breakdown.len());
+      let mut counts = Vec::with_capacity(breakdown.len());
+      for ((workspace_id, view_id, day, dimension, value), count) in breakdown {
+        workspace_ids.push(workspace_id);
+        view_ids.push(view_id);
+        days.push(day);
+        dimensions.push(dimension.to_string());
+        values.push(value);
+        counts.push(count);
+      }
+      upsert_published_view_daily_breakdown(
+        &mut txn,
+        &workspace_ids,
+        &view_ids,
+        &days,
+        &dimensions,
+        &values,
+        &counts,
+      )
+      .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+  }
+}
+
+pub async fn get_published_view_analytics(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  view_id: Uuid,
+  days: Option<u32>,
+) -> Result<PublishedViewAnalytics, AppError> {
+  let since = analytics_since(days)?;
+  let daily = select_published_view_daily_stats(pg_pool, &workspace_id, &view_id, since).await?;
+  let referrers = select_published_view_breakdown(
+    pg_pool,
+    &workspace_id,
+    &view_id,
+    since,
+    BREAKDOWN_REFERRER,
+    MAX_BREAKDOWN_ENTRIES,
+  )
+  .await?;
+  let countries = select_published_view_breakdown(
+    pg_pool,
+    &workspace_id,
+    &view_id,
+    since,
+    BREAKDOWN_COUNTRY,
+    MAX_BREAKDOWN_ENTRIES,
+  )
+  .await?;
+  Ok(PublishedViewAnalytics {
+    view_id,
+    views: daily.iter().map(|stats| stats.views).sum(),
+    unique_visitors: daily.iter().map(|stats| stats.unique_visitors).sum(),
+    daily,
+    referrers,
+    countries,
+  })
+}
+
+pub async fn get_publish_namespace_analytics(
+  pg_pool: &PgPool,
+  workspace_id: Uuid,
+  days: Option<u32>,
+) -> Result<PublishNamespaceAnalytics, AppError> {
+  let since = analytics_since(days)?;
+  let namespace = get_workspace_publish_namespace(pg_pool, &workspace_id).await?;
+  let daily = select_workspace_published_daily_stats(pg_pool, &workspace_id, since).await?;
+  let pages = select_workspace_published_view_stats(pg_pool, &workspace_id, since).await?;
+  Ok(PublishNamespaceAnalytics {
+    namespace,
+    views: daily.iter().map(|stats| stats.views).sum(),
+    unique_visitors: daily.iter().map(|stats| stats.unique_visitors).sum(),
+    daily,
+    pages,
+  })
+}
+
+fn analytics_since(days: Option<u32>) -> Result<NaiveDate, AppError> {
+  let days = days.unwrap_or(DEFAULT_ANALYTICS_DAYS);
+  if days == 0 || days > MAX_ANALYTICS_DAYS {
+    return Err(AppError::InvalidRequest(format!(
+      "days must be between 1 and {}",
+      MAX_ANALYTICS_DAYS
+    )));
+  }
+  let today = Utc::now().date_naive();
+  Ok(
+    today
+      .checked_sub_days(Days::new(u64::from(days - 1)))
+      .unwrap_or(today),
+  )
+}
+
+fn is_bot(user_agent: &str) -> bool {
+  if user_agent.is_empty() {
+    return true;
+  }
+  let user_agent = user_agent.to_lowercase();
+  BOT_USER_AGENT_MARKERS
+    .iter()
+    .any(|marker| user_agent.contains(marker))
+}
+
+/// The salt is random and changes every day, so visitors can't be tracked across days, and the
+/// hashes can't be reversed once the salt is discarded.
+fn visitor_hash(salt: &[u8], day: NaiveDate, ip: Option<&IpAddr>, user_agent: &str) -> Vec<u8> {
+  let mut mac = HmacSha256::new_from_slice(salt).expect("HMAC can take key of any size");
+  mac.update(day.to_string().as_bytes());
+  mac.update(b"\0");
+  if let Some(ip) = ip {
+    mac.update(ip.to_string().as_bytes());
+  }
+  mac.update(b"\0");
+  mac.update(user_agent.as_bytes());
+  mac.finalize().into_bytes().to_vec()
+}
+
+/// Only the domain of the referrer is kept, since the full URL may contain private information.
+fn referrer_domain(referrer: &str) -> Option<String> {
+  let url = url::Url::parse(referrer).ok()?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return None;
+  }
+  let host = url.host_str()?.to_lowercase();
+  let host = host.strip_prefix("www.").unwrap_or(&host);
+  Some(host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn referrer_domain_strips_path_and_www() {
+    assert_eq!(
+      referrer_domain("https://www.Example.com/some/path?q=secret"),
+      Some("example.com".to_string())
+    );
+    assert_eq!(
+      referrer_domain("http://news.ycombinator.com/item?id=1"),
+      Some("news.ycombinator.com".to_string())
+    );
+    assert_eq!(referrer_domain("android-app://com.slack"), None);
+    assert_eq!(referrer_domain("not a url"), None);
+  }
+
+  #[test]
+  fn visitor_hash_changes_daily() {
+    let ip: IpAddr = "203.0.113.7".parse().unwrap();
+    let day = NaiveDate::from_ymd_opt(2025, 8, 5).unwrap();
+    let next_day = day.succ_opt().unwrap();
+    let hash = visitor_hash(b"salt", day, Some(&ip), "Mozilla/5.0");
+    assert_eq!(hash, visitor_hash(b"salt", day, Some(&ip), "Mozilla/5.0"));
+    assert_ne!(
+      hash,
+      visitor_hash(b"salt", next_day, Some(&ip), "Mozilla/5.0")
+    );
+    assert_ne!(hash, visitor_hash(b"salt", day, Some(&ip), "curl/8.0"));
+    assert_ne!(hash, visitor_hash(b"other", day, Some(&ip), "Mozilla/5.0"));
+  }
+
+  #[test]
+  fn bots_are_not_counted() {
+    assert!(is_bot(""));
+    assert!(is_bot("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+    assert!(is_bot("Slackbot-LinkExpanding 1.0"));
+    assert!(!is_bot(
+      "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15"
+    ));
+  }
+}
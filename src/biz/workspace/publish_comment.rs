@@ -0,0 +1,165 @@
+use app_error::AppError;
+use chrono::{Duration, Utc};
+use database::publish_comment::{
+  delete_published_comment_blocked_user, insert_published_comment_blocked_user,
+  select_published_comment_blocklist, select_published_comment_policy,
+  select_published_comment_setting, select_published_comments_for_moderation,
+  select_view_id_of_repliable_comment, update_published_comment_status,
+  upsert_published_comment_setting,
+};
+use database::user::select_uid_from_uuid;
+use database::workspace::insert_comment_to_published_view;
+use database_entity::dto::{
+  PublishedCommentBlockedUser, PublishedCommentModerationItem,
+  PublishedCommentModerationQueryParams, PublishedCommentSetting, PublishedCommentStatus,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const DEFAULT_MODERATION_QUEUE_LIMIT: u32 = 50;
+const MAX_MODERATION_QUEUE_LIMIT: u32 = 200;
+
+/// Creates the comment of the user on the published view, if the user is allowed to post it. The
+/// comment is pending approval if the workspace requires it.
+pub async fn insert_published_comment(
+  pg_pool: &PgPool,
+  view_id: &Uuid,
+  reply_comment_id: &Option<Uuid>,
+  content: &str,
+  user_uuid: &Uuid,
+  rate_limit_per_minute: u32,
+) -> Result<(), AppError> {
+  let policy = select_published_comment_policy(pg_pool, view_id, user_uuid)
+    .await?
+    .ok_or_else(|| AppError::RecordNotFound(format!("Published view {} not found", view_id)))?;
+  if policy.is_blocked {
+    return Err(AppError::PublishCommenterBlocked);
+  }
+
+  if let Some(reply_comment_id) = reply_comment_id {
+    let reply_view_id = select_view_id_of_repliable_comment(pg_pool, reply_comment_id).await?;
+    if reply_view_id.as_ref() != Some(view_id) {
+      return Err(AppError::InvalidRequest(format!(
+        "Cannot reply to comment {}",
+        reply_comment_id
+      )));
+    }
+  }
+
+  // Workspace members don't need the approval of the owner, nor are they rate limited
+  let (status, rate_limit) = if policy.is_member {
+    (PublishedCommentStatus::Approved, None)
+  } else {
+    let status = if policy.require_approval {
+      PublishedCommentStatus::Pending
+    } else {
+      PublishedCommentStatus::Approved
+    };
+    let since = Utc::now() - Duration::minutes(1);
+    (status, Some((since, rate_limit_per_minute as i64)))
+  };
+  let inserted = insert_comment_to_published_view(
+    pg_pool,
+    view_id,
+    user_uuid,
+    content,
+    reply_comment_id,
+    status,
+    rate_limit,
+  )
+  .await?;
+  if !inserted {
+    return Err(AppError::PublishCommentRateLimited);
+  }
+  Ok(())
+}
+
+/// Lists the most recent comments on all the views published by the workspace.
+pub async fn list_published_comments_for_moderation(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  params: &PublishedCommentModerationQueryParams,
+) -> Result<Vec<PublishedCommentModerationItem>, AppError> {
+  let limit = params
+    .limit
+    .unwrap_or(DEFAULT_MODERATION_QUEUE_LIMIT)
+    .clamp(1, MAX_MODERATION_QUEUE_LIMIT);
+  let rows = select_published_comments_for_moderation(
+    pg_pool,
+    workspace_id,
+    params.status,
+    params.before,
+    limit as i64,
+  )
+  .await?;
+  Ok(rows.into_iter().map(|row| row.into()).collect())
+}
+
+pub async fn moderate_published_comment(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  comment_id: &Uuid,
+  status: PublishedCommentStatus,
+) -> Result<(), AppError> {
+  if !update_published_comment_status(pg_pool, workspace_id, comment_id, status).await? {
+    return Err(AppError::RecordNotFound(format!(
+      "Comment {} not found in the pages published by workspace {}",
+      comment_id, workspace_id
+    )));
+  }
+  Ok(())
+}
+
+pub async fn get_published_comment_setting(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<PublishedCommentSetting, AppError> {
+  select_published_comment_setting(pg_pool, workspace_id).await
+}
+
+pub async fn update_published_comment_setting(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  setting: &PublishedCommentSetting,
+) -> Result<(), AppError> {
+  upsert_published_comment_setting(pg_pool, workspace_id, setting).await
+}
+
+pub async fn list_published_comment_blocked_users(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<PublishedCommentBlockedUser>, AppError> {
+  let rows = select_published_comment_blocklist(pg_pool, workspace_id).await?;
+  Ok(rows.into_iter().map(|row| row.into()).collect())
+}
+
+pub async fn block_published_commenter(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  user_uuid: &Uuid,
+  reason: Option<&str>,
+  blocked_by: i64,
+) -> Result<(), AppError> {
+  let uid = select_uid_from_uuid(pg_pool, user_uuid).await?;
+  if uid == blocked_by {
+    return Err(AppError::InvalidRequest(
+      "Cannot block yourself from commenting".to_string(),
+    ));
+  }
+  insert_published_comment_blocked_user(pg_pool, workspace_id, uid, reason, blocked_by).await
+}
+
+pub async fn unblock_published_commenter(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  user_uuid: &Uuid,
+) -> Result<(), AppError> {
+  let uid = select_uid_from_uuid(pg_pool, user_uuid).await?;
+  if !delete_published_comment_blocked_user(pg_pool, workspace_id, uid).await? {
+    return Err(AppError::RecordNotFound(format!(
+      "User {} is not blocked",
+      user_uuid
+    )));
+  }
+  Ok(())
+}
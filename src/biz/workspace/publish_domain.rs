@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use app_error::AppError;
+use async_trait::async_trait;
+use database::pg_row::AFPublishDomainRow;
+use database::publish_domain::{
+  delete_publish_domain, insert_publish_domain, select_publish_domain, select_publish_domains,
+  select_workspace_id_for_publish_domain, update_publish_domain_verified,
+};
+use database_entity::dto::PublishInfoMeta;
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::TokioAsyncResolver;
+use shared_entity::dto::workspace_dto::{PublishDomain, PublishDomainResolution};
+use sqlx::PgPool;
+use tracing::warn;
+use url::Url;
+use uuid::Uuid;
+
+use super::publish::{
+  get_workspace_default_publish_view_info_meta, get_workspace_publish_namespace,
+};
+
+/// Maximum number of custom domains, verified or not, a workspace can claim.
+const MAX_DOMAINS_PER_WORKSPACE: usize = 10;
+const TXT_RECORD_PREFIX: &str = "_appflowy-challenge";
+const TXT_RECORD_VALUE_PREFIX: &str = "appflowy-site-verification=";
+
+/// Looks up DNS TXT records. Abstracted so that the verification can be stubbed in tests.
+#[async_trait]
+pub trait TxtResolver: Send + Sync {
+  async fn lookup_txt(&self, name: &str) -> Result<Vec<String>, AppError>;
+}
+
+pub struct HickoryTxtResolver {
+  resolver: TokioAsyncResolver,
+}
+
+impl HickoryTxtResolver {
+  pub fn new() -> Self {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf().unwrap_or_else(|err| {
+      warn!(
+        "Failed to read the system DNS configuration, using the default one: {}",
+        err
+      );
+      TokioAsyncResolver::tokio(Default::default(), Default::default())
+    });
+    Self { resolver }
+  }
+}
+
+impl Default for HickoryTxtResolver {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[async_trait]
+impl TxtResolver for HickoryTxtResolver {
+  async fn lookup_txt(&self, name: &str) -> Result<Vec<String>, AppError> {
+    match self.resolver.txt_lookup(name).await {
+      Ok(lookup) => Ok(
+        lookup
+          .iter()
+          .map(|txt| {
+            // A TXT record can be split into several character strings
+            txt
+              .txt_data()
+              .iter()
+              .map(|data| String::from_utf8_lossy(data).into_owned())
+              .collect::<String>()
+          })
+          .collect(),
+      ),
+      Err(err) => match err.kind() {
+        ResolveErrorKind::NoRecordsFound { .. } => Ok(vec![]),
+        _ => Err(AppError::PublishDomainVerificationFailed(format!(
+          "Failed to look up the TXT records of {}: {}",
+          name, err
+        ))),
+      },
+    }
+  }
+}
+
+/// Resolver answering from a fixed set of records, used to verify domains in tests.
+#[derive(Default)]
+pub struct StaticTxtResolver {
+  records: HashMap<String, Vec<String>>,
+}
+
+impl StaticTxtResolver {
+  pub fn new(records: HashMap<String, Vec<String>>) -> Self {
+    Self { records }
+  }
+}
+
+#[async_trait]
+impl TxtResolver for StaticTxtResolver {
+  async fn lookup_txt(&self, name: &str) -> Result<Vec<String>, AppError> {
+    Ok(self.records.get(name).cloned().unwrap_or_default())
+  }
+}
+
+pub fn txt_record_name(domain: &str) -> String {
+  format!("{}.{}", TXT_RECORD_PREFIX, domain)
+}
+
+pub fn txt_record_value(verification_token: &str) -> String {
+  format!("{}{}", TXT_RECORD_VALUE_PREFIX, verification_token)
+}
+
+/// Normalizes a hostname, as typed by a user or sent in the `Host` header, to the form stored in
+/// the database: lowercase, without port nor trailing dot.
+pub fn normalize_domain(domain: &str) -> Result<String, AppError> {
+  let domain = domain.trim().to_ascii_lowercase();
+  let domain = match domain.rsplit_once(':') {
+    Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host.to_string(),
+    _ => domain,
+  };
+  let domain = domain.strip_suffix('.').unwrap_or(&domain).to_string();
+
+  let invalid = || AppError::InvalidRequest(format!("Invalid domain: {}", domain));
+  if domain.is_empty() || domain.len() > 253 {
+    return Err(invalid());
+  }
+  let labels: Vec<&str> = domain.split('.').collect();
+  if labels.len() < 2 {
+    return Err(invalid());
+  }
+  for label in &labels {
+    if label.is_empty()
+      || label.len() > 63
+      || label.starts_with('-')
+      || label.ends_with('-')
+      || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+      return Err(invalid());
+    }
+  }
+  // Rejects IP addresses
+  if labels
+    .last()
+    .map(|tld| tld.chars().all(|c| c.is_ascii_digit()))
+    .unwrap_or(true)
+  {
+    return Err(invalid());
+  }
+  Ok(domain)
+}
+
+fn to_publish_domain(row: AFPublishDomainRow, namespace: &str) -> PublishDomain {
+  PublishDomain {
+    txt_record_name: txt_record_name(&row.domain),
+    txt_record_value: txt_record_value(&row.verification_token),
+    domain: row.domain,
+    namespace: namespace.to_string(),
+    verified: row.verified_at.is_some(),
+    verified_at: row.verified_at,
+    created_at: row.created_at,
+  }
+}
+
+fn is_reserved_domain(domain: &str, web_url: &str) -> bool {
+  Url::parse(web_url)
+    .ok()
+    .and_then(|url| url.host_str().map(|host| host.eq_ignore_ascii_case(domain)))
+    .unwrap_or(false)
+}
+
+pub async fn add_publish_domain(
+  pg_pool: &PgPool,
+  web_url: &str,
+  workspace_id: &Uuid,
+  domain: &str,
+) -> Result<PublishDomain, AppError> {
+  let domain = normalize_domain(domain)?;
+  if is_reserved_domain(&domain, web_url) {
+    return Err(AppError::InvalidRequest(format!(
+      "The domain {} cannot be used for a publish namespace",
+      domain
+    )));
+  }
+  let namespace = get_workspace_publish_namespace(pg_pool, workspace_id).await?;
+
+  if let Some(owner) = select_workspace_id_for_publish_domain(pg_pool, &domain).await? {
+    if &owner != workspace_id {
+      return Err(AppError::PublishDomainAlreadyTaken(domain));
+    }
+  }
+  let existing = select_publish_domains(pg_pool, workspace_id).await?;
+  if existing.len() >= MAX_DOMAINS_PER_WORKSPACE && !existing.iter().any(|d| d.domain == domain) {
+    return Err(AppError::InvalidRequest(format!(
+      "A workspace cannot have more than {} custom domains",
+      MAX_DOMAINS_PER_WORKSPACE
+    )));
+  }
+
+  let token = Uuid::new_v4().simple().to_string();
+  let row = insert_publish_domain(pg_pool, workspace_id, &domain, &token).await?;
+  Ok(to_publish_domain(row, &namespace))
+}
+
+pub async fn list_publish_domains(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<PublishDomain>, AppError> {
+  let rows = select_publish_domains(pg_pool, workspace_id).await?;
+  if rows.is_empty() {
+    return Ok(vec![]);
+  }
+  let namespace = get_workspace_publish_namespace(pg_pool, workspace_id).await?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| to_publish_domain(row, &namespace))
+      .collect(),
+  )
+}
+
+/// Marks the domain as verified if its TXT record contains the verification token.
+pub async fn verify_publish_domain(
+  pg_pool: &PgPool,
+  resolver: &dyn TxtResolver,
+  workspace_id: &Uuid,
+  domain: &str,
+) -> Result<PublishDomain, AppError> {
+  let domain = normalize_domain(domain)?;
+  let row = select_publish_domain(pg_pool, workspace_id, &domain)
+    .await?
+    .ok_or_else(|| AppError::RecordNotFound(format!("Custom domain {} not found", domain)))?;
+  let namespace = get_workspace_publish_namespace(pg_pool, workspace_id).await?;
+  if row.verified_at.is_some() {
+    return Ok(to_publish_domain(row, &namespace));
+  }
+
+  let record_name = txt_record_name(&domain);
+  let expected = txt_record_value(&row.verification_token);
+  let records = resolver.lookup_txt(&record_name).await?;
+  if !records.iter().any(|record| record.trim() == expected) {
+    return Err(AppError::PublishDomainVerificationFailed(format!(
+      "TXT record {} does not contain {}",
+      record_name, expected
+    )));
+  }
+
+  let row = update_publish_domain_verified(pg_pool, workspace_id, &domain).await?;
+  Ok(to_publish_domain(row, &namespace))
+}
+
+pub async fn remove_publish_domain(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  domain: &str,
+) -> Result<(), AppError> {
+  let domain = normalize_domain(domain)?;
+  if !delete_publish_domain(pg_pool, workspace_id, &domain).await? {
+    return Err(AppError::RecordNotFound(format!(
+      "Custom domain {} not found",
+      domain
+    )));
+  }
+  Ok(())
+}
+
+/// Resolves the `Host` of an incoming request to the publish namespace served under it.
+pub async fn resolve_publish_domain(
+  pg_pool: &PgPool,
+  host: &str,
+) -> Result<PublishDomainResolution, AppError> {
+  let domain = normalize_domain(host)?;
+  let workspace_id = select_workspace_id_for_publish_domain(pg_pool, &domain)
+    .await?
+    .ok_or_else(|| {
+      AppError::RecordNotFound(format!("No publish namespace found for domain {}", domain))
+    })?;
+  let namespace = get_workspace_publish_namespace(pg_pool, &workspace_id).await?;
+  let default_view = match get_workspace_default_publish_view_info_meta(pg_pool, &namespace).await {
+    Ok((info, meta)) => Some(PublishInfoMeta { info, meta }),
+    Err(AppError::RecordNotFound(_)) => None,
+    Err(err) => return Err(err),
+  };
+  Ok(PublishDomainResolution {
+    domain,
+    namespace,
+    default_view,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalize_domain_test() {
+    assert_eq!(
+      normalize_domain("Docs.Example.com").unwrap(),
+      "docs.example.com"
+    );
+    assert_eq!(
+      normalize_domain("docs.example.com:443").unwrap(),
+      "docs.example.com"
+    );
+    assert_eq!(
+      normalize_domain("docs.example.com.").unwrap(),
+      "docs.example.com"
+    );
+    assert!(normalize_domain("localhost").is_err());
+    assert!(normalize_domain("127.0.0.1").is_err());
+    assert!(normalize_domain("-bad.example.com").is_err());
+    assert!(normalize_domain("bad_label.example.com").is_err());
+    assert!(normalize_domain("example..com").is_err());
+    assert!(normalize_domain("https://example.com").is_err());
+  }
+
+  #[test]
+  fn reserved_domain_test() {
+    assert!(is_reserved_domain("appflowy.com", "https://AppFlowy.com"));
+    assert!(!is_reserved_domain(
+      "docs.example.com",
+      "https://appflowy.com"
+    ));
+  }
+
+  #[tokio::test]
+  async fn static_resolver_test() {
+    let resolver = StaticTxtResolver::new(HashMap::from([(
+      txt_record_name("docs.example.com"),
+      vec![txt_record_value("token")],
+    )]));
+    let records = resolver
+      .lookup_txt("_appflowy-challenge.docs.example.com")
+      .await
+      .unwrap();
+    assert_eq!(
+      records,
+      vec!["appflowy-site-verification=token".to_string()]
+    );
+    assert!(resolver
+      .lookup_txt("_appflowy-challenge.other.com")
+      .await
+      .unwrap()
+      .is_empty());
+  }
+}
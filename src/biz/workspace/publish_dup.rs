@@ -19,6 +19,7 @@ use database::file::BucketClient;
 use database::file::ResponseBlob;
 use database::publish::select_published_data_for_view_id;
 use database::publish::select_published_metadata_for_view_id;
+use database::publish::select_published_view_access;
 use database_entity::dto::CollabParams;
 use shared_entity::dto::publish_dto::PublishDatabaseDataWithNonUuidRelations;
 use shared_entity::dto::publish_dto::{PublishDatabaseData, PublishViewInfo, PublishViewMetaData};
@@ -110,6 +111,9 @@ pub struct PublishCollabDuplicator {
   dest_workspace_id: Uuid,
   /// view of workspace to duplicate into
   dest_view_id: Uuid,
+  /// published view chosen for the duplication, which the duplicator was given access to.
+  /// Other password protected views are not duplicated.
+  root_publish_view_id: Option<Uuid>,
   collab_update_publisher: Box<dyn CollabUpdatePublisher>,
   collab_metrics: Arc<CollabMetrics>,
 }
@@ -157,6 +161,7 @@ impl PublishCollabDuplicator {
       duplicator_uid: dest_uid,
       dest_workspace_id,
       dest_view_id,
+      root_publish_view_id: None,
       collab_update_publisher,
       collab_metrics,
     }
@@ -170,6 +175,7 @@ impl PublishCollabDuplicator {
     // new view after deep copy
     // this is the root of the document/database duplicated
     let root_view_id = gen_view_id();
+    self.root_publish_view_id = Some(publish_view_id);
     let mut root_view = match self.deep_copy(root_view_id, publish_view_id).await? {
       Some(v) => v,
       None => {
@@ -196,6 +202,7 @@ impl PublishCollabDuplicator {
       duplicator_uid,
       dest_workspace_id,
       dest_view_id,
+      root_publish_view_id: _,
       collab_update_publisher: collab_update_writer,
       collab_metrics,
     } = self;
@@ -1092,6 +1099,13 @@ impl PublishCollabDuplicator {
     &self,
     view_id: &uuid::Uuid,
   ) -> Result<Option<(PublishViewMetaData, Vec<u8>)>, AppError> {
+    if self.root_publish_view_id.as_ref() != Some(view_id) {
+      let access = select_published_view_access(&self.pg_pool, view_id).await?;
+      if access.is_some_and(|access| access.password_hash.is_some()) {
+        tracing::info!("Skipping password protected published view: {}", view_id);
+        return Ok(None);
+      }
+    }
     let result = select_published_metadata_for_view_id(&self.pg_pool, view_id).await?;
     match result {
       Some((workspace_id, js_val)) => {
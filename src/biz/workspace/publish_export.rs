@@ -0,0 +1,690 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use app_error::AppError;
+use aws_sdk_s3::primitives::ByteStream;
+use collab::core::collab::default_client_id;
+use collab_document::blocks::{Block, DocumentData};
+use collab_document::document::Document;
+use database::file::s3_client_impl::AwsS3BucketClientImpl;
+use database::file::{BucketClient, ResponseBlob};
+use database::pg_row::AFPublishedSiteExportRow;
+use database::publish_export::{
+  claim_next_published_site_export, insert_published_site_export, select_published_site_export,
+  select_published_site_export_in_progress, update_published_site_export_completed,
+  update_published_site_export_failed,
+};
+use serde_json::Value;
+use shared_entity::dto::publish_dto::PublishViewMetaData;
+use shared_entity::dto::workspace_dto::{PublishedSiteExport, ViewLayout};
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::document_walker::{plain_text, text_delta, BlockWalker};
+use super::publish::{get_workspace_publish_namespace, PublishedCollabStore};
+use super::publish_feed::{
+  list_published_feed_pages, published_page_url, xml_escape as escape, PublishedFeedPage,
+};
+use crate::biz::collab::utils::collab_from_doc_state;
+
+/// Exports left running for longer than this are assumed to be abandoned, and are retried.
+const STALE_EXPORT_SECS: i64 = 30 * 60;
+
+const SITE_STYLE: &str = "body{margin:0;font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',\
+Roboto,sans-serif;line-height:1.6;color:#1f2329}main{max-width:760px;margin:0 auto;padding:32px \
+16px}nav{font-size:14px;margin-bottom:24px}a{color:#00b5ff}img{max-width:100%}pre{background:\
+#f5f5f5;padding:12px;overflow-x:auto}blockquote{border-left:4px solid #00b5ff;margin:0;padding-left:\
+16px}.callout{background:#f5f5f5;padding:12px;border-radius:6px}.nested{margin-left:24px}ul.todo{\
+list-style:none;padding-left:0}";
+
+pub async fn request_published_site_export(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  uid: i64,
+  cdn_url: Option<&str>,
+) -> Result<PublishedSiteExport, AppError> {
+  let namespace = get_workspace_publish_namespace(pg_pool, workspace_id).await?;
+  if select_published_site_export_in_progress(pg_pool, workspace_id).await? {
+    return Err(AppError::InvalidRequest(
+      "An export of the published site is already in progress".to_string(),
+    ));
+  }
+  let row = insert_published_site_export(pg_pool, workspace_id, &namespace, uid).await?;
+  Ok(to_published_site_export(row, cdn_url))
+}
+
+pub async fn get_published_site_export(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  export_id: &Uuid,
+  cdn_url: Option<&str>,
+) -> Result<PublishedSiteExport, AppError> {
+  let row = select_published_site_export(pg_pool, workspace_id, export_id)
+    .await?
+    .ok_or_else(|| {
+      AppError::RecordNotFound(format!("Published site export {} not found", export_id))
+    })?;
+  Ok(to_published_site_export(row, cdn_url))
+}
+
+fn to_published_site_export(
+  row: AFPublishedSiteExportRow,
+  cdn_url: Option<&str>,
+) -> PublishedSiteExport {
+  let url = match (cdn_url, row.object_prefix.as_ref()) {
+    (Some(cdn_url), Some(prefix)) => Some(format!(
+      "{}/{}/index.html",
+      cdn_url.trim_end_matches('/'),
+      prefix
+    )),
+    _ => None,
+  };
+  PublishedSiteExport {
+    export_id: row.export_id,
+    namespace: row.namespace,
+    status: row.status.into(),
+    object_prefix: row.object_prefix,
+    url,
+    page_count: row.page_count,
+    error: row.error,
+    created_at: row.created_at,
+    completed_at: row.completed_at,
+  }
+}
+
+/// Renders all pages publicly published under a namespace to a self-contained static HTML
+/// bundle, and uploads it to the object storage, so that it can be served by a CDN.
+pub struct PublishedSiteExportWorker {
+  pub pg_pool: PgPool,
+  pub published_collab_store: Arc<dyn PublishedCollabStore>,
+  pub bucket_client: AwsS3BucketClientImpl,
+  pub appflowy_web_url: String,
+  pub interval_secs: u64,
+}
+
+impl PublishedSiteExportWorker {
+  pub async fn start_task(&self) {
+    let mut interval = interval(Duration::from_secs(self.interval_secs.max(1)));
+    loop {
+      interval.tick().await;
+      loop {
+        match claim_next_published_site_export(&self.pg_pool, STALE_EXPORT_SECS).await {
+          Ok(Some(export)) => self.run_export(export).await,
+          Ok(None) => break,
+          Err(err) => {
+            error!("Failed to claim published site export: {}", err);
+            break;
+          },
+        }
+      }
+    }
+  }
+
+  async fn run_export(&self, export: AFPublishedSiteExportRow) {
+    info!(
+      "Exporting published site for namespace {}, export_id: {}",
+      export.namespace, export.export_id
+    );
+    let result = match self.export_site(&export).await {
+      Ok((object_prefix, page_count)) => {
+        update_published_site_export_completed(
+          &self.pg_pool,
+          &export.export_id,
+          &object_prefix,
+          page_count,
+        )
+        .await
+      },
+      Err(err) => {
+        error!(
+          "Failed to export published site {}: {}",
+          export.export_id, err
+        );
+        update_published_site_export_failed(&self.pg_pool, &export.export_id, &err.to_string())
+          .await
+      },
+    };
+    if let Err(err) = result {
+      error!(
+        "Failed to update published site export {}: {}",
+        export.export_id, err
+      );
+    }
+  }
+
+  async fn export_site(
+    &self,
+    export: &AFPublishedSiteExportRow,
+  ) -> Result<(String, i32), AppError> {
+    let object_prefix = format!(
+      "published-site/{}/{}",
+      export.workspace_id, export.export_id
+    );
+    let pages = list_published_feed_pages(&self.pg_pool, &export.namespace).await?;
+    let pages_by_view_id = pages
+      .iter()
+      .map(|page| (page.view_id.to_string(), page))
+      .collect::<HashMap<_, _>>();
+    let mut renderer = SiteRenderer::new(export.workspace_id, &pages_by_view_id);
+
+    let mut exported_pages = vec![];
+    for page in &pages {
+      match self
+        .render_page(&mut renderer, &export.namespace, page)
+        .await
+      {
+        Ok(html) => {
+          let key = format!("{}/{}/index.html", object_prefix, page.publish_name);
+          self
+            .upload(&key, html.into_bytes(), HTML_CONTENT_TYPE)
+            .await?;
+          exported_pages.push(page);
+        },
+        Err(err) => warn!(
+          "Skipping published page {}/{} in site export: {}",
+          export.namespace, page.publish_name, err
+        ),
+      }
+    }
+
+    let index = render_index_html(&export.namespace, &exported_pages);
+    let key = format!("{}/index.html", object_prefix);
+    self
+      .upload(&key, index.into_bytes(), HTML_CONTENT_TYPE)
+      .await?;
+
+    for (path, object_key) in renderer.assets {
+      match self.bucket_client.get_blob(&object_key).await {
+        Ok(resp) => {
+          let content_type = resp
+            .content_type()
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+          let key = format!("{}/{}", object_prefix, path);
+          self.upload(&key, resp.to_blob(), &content_type).await?;
+        },
+        Err(err) => warn!(
+          "Failed to copy asset {} to site export: {}",
+          object_key, err
+        ),
+      }
+    }
+
+    Ok((object_prefix, exported_pages.len() as i32))
+  }
+
+  async fn render_page(
+    &self,
+    renderer: &mut SiteRenderer<'_>,
+    namespace: &str,
+    page: &PublishedFeedPage,
+  ) -> Result<String, AppError> {
+    let metadata = self
+      .published_collab_store
+      .get_collab_metadata(namespace, &page.publish_name)
+      .await?;
+    let metadata: PublishViewMetaData = serde_json::from_value(metadata)?;
+    let live_url = published_page_url(&self.appflowy_web_url, namespace, &page.publish_name)?;
+    let body = match metadata.view.layout {
+      ViewLayout::Document => {
+        let blob = self
+          .published_collab_store
+          .get_collab_blob_by_publish_namespace(namespace, &page.publish_name)
+          .await?;
+        let collab = collab_from_doc_state(blob, &page.view_id, default_client_id())?;
+        let document = Document::open(collab).map_err(|e| AppError::Unhandled(e.to_string()))?;
+        let data = document
+          .get_document_data()
+          .map_err(|e| AppError::Unhandled(e.to_string()))?;
+        renderer.render_document(&data)
+      },
+      _ => format!(
+        "<p>This page can be viewed on <a href=\"{}\">AppFlowy</a>.</p>",
+        escape(live_url.as_str())
+      ),
+    };
+    Ok(render_page_html(
+      namespace,
+      &page.title,
+      live_url.as_str(),
+      &body,
+    ))
+  }
+
+  async fn upload(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), AppError> {
+    self
+      .bucket_client
+      .put_blob_with_content_type(key, ByteStream::from(data), content_type)
+      .await
+  }
+}
+
+const HTML_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+
+fn render_page_html(namespace: &str, title: &str, canonical_url: &str, body: &str) -> String {
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+     <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+     <title>{title}</title>\n<link rel=\"canonical\" href=\"{canonical}\">\n\
+     <style>{style}</style>\n</head>\n<body>\n<main>\n\
+     <nav><a href=\"../index.html\">{namespace}</a></nav>\n<h1>{title}</h1>\n{body}</main>\n\
+     </body>\n</html>\n",
+    title = escape(title),
+    canonical = escape(canonical_url),
+    style = SITE_STYLE,
+    namespace = escape(namespace),
+    body = body,
+  )
+}
+
+fn render_index_html(namespace: &str, pages: &[&PublishedFeedPage]) -> String {
+  let mut body = String::from("<ul>\n");
+  for page in pages {
+    body.push_str(&format!(
+      "<li><a href=\"{}/index.html\">{}</a></li>\n",
+      escape(&page.publish_name),
+      escape(&page.title)
+    ));
+  }
+  body.push_str("</ul>\n");
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+     <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+     <title>{namespace}</title>\n<style>{style}</style>\n</head>\n<body>\n<main>\n\
+     <h1>{namespace}</h1>\n{body}</main>\n</body>\n</html>\n",
+    namespace = escape(namespace),
+    style = SITE_STYLE,
+    body = body,
+  )
+}
+
+/// Renders published documents to HTML. Pages are rendered to `{publish_name}/index.html`, and
+/// files uploaded to the workspace are copied to `assets/`.
+struct SiteRenderer<'a> {
+  workspace_id: Uuid,
+  /// Pages included in the export, by view id.
+  pages: &'a HashMap<String, &'a PublishedFeedPage>,
+  /// Object keys of the files to copy, by their path in the export.
+  assets: HashMap<String, String>,
+  walker: BlockWalker,
+}
+
+impl<'a> SiteRenderer<'a> {
+  fn new(workspace_id: Uuid, pages: &'a HashMap<String, &'a PublishedFeedPage>) -> Self {
+    Self {
+      workspace_id,
+      pages,
+      assets: HashMap::new(),
+      walker: BlockWalker::default(),
+    }
+  }
+
+  fn render_document(&mut self, data: &DocumentData) -> String {
+    self.walker.reset();
+    let mut html = String::new();
+    self.render_children(data, &data.page_id, &mut html);
+    html
+  }
+
+  fn render_children(&mut self, data: &DocumentData, block_id: &str, html: &mut String) {
+    let Some(children) = self.walker.enter(data, block_id) else {
+      return;
+    };
+    let mut open_list: Option<&str> = None;
+    for block in children {
+      let list = match block.ty.as_str() {
+        "bulleted_list" => Some("ul"),
+        "numbered_list" => Some("ol"),
+        "todo_list" => Some("ul class=\"todo\""),
+        _ => None,
+      };
+      if open_list != list {
+        if let Some(tag) = open_list {
+          html.push_str(&format!("</{}>\n", list_tag_name(tag)));
+        }
+        if let Some(tag) = list {
+          html.push_str(&format!("<{}>\n", tag));
+        }
+        open_list = list;
+      }
+      self.render_block(data, block, html);
+    }
+    if let Some(tag) = open_list {
+      html.push_str(&format!("</{}>\n", list_tag_name(tag)));
+    }
+    self.walker.leave();
+  }
+
+  fn render_nested_children(&mut self, data: &DocumentData, block: &Block, html: &mut String) {
+    let mut nested = String::new();
+    self.render_children(data, &block.id, &mut nested);
+    if !nested.is_empty() {
+      html.push_str("<div class=\"nested\">\n");
+      html.push_str(&nested);
+      html.push_str("</div>\n");
+    }
+  }
+
+  fn render_block(&mut self, data: &DocumentData, block: &Block, html: &mut String) {
+    let text = self.render_text(data, block);
+    match block.ty.as_str() {
+      "heading" => {
+        let level = block
+          .data
+          .get("level")
+          .and_then(Value::as_u64)
+          .unwrap_or(1)
+          .clamp(1, 6);
+        html.push_str(&format!("<h{level}>{text}</h{level}>\n"));
+        self.render_nested_children(data, block, html);
+      },
+      "bulleted_list" | "numbered_list" => {
+        html.push_str(&format!("<li>{}", text));
+        self.render_nested_children(data, block, html);
+        html.push_str("</li>\n");
+      },
+      "todo_list" => {
+        let checked = block
+          .data
+          .get("checked")
+          .and_then(Value::as_bool)
+          .unwrap_or(false);
+        html.push_str(&format!(
+          "<li><input type=\"checkbox\" disabled{}> {}",
+          if checked { " checked" } else { "" },
+          text
+        ));
+        self.render_nested_children(data, block, html);
+        html.push_str("</li>\n");
+      },
+      "quote" => {
+        html.push_str(&format!("<blockquote>\n<p>{}</p>\n", text));
+        self.render_children(data, &block.id, html);
+        html.push_str("</blockquote>\n");
+      },
+      "callout" => {
+        let icon = block
+          .data
+          .get("icon")
+          .and_then(Value::as_str)
+          .unwrap_or_default();
+        html.push_str(&format!(
+          "<div class=\"callout\">{} {}</div>\n",
+          escape(icon),
+          text
+        ));
+      },
+      "toggle_list" => {
+        html.push_str(&format!("<details>\n<summary>{}</summary>\n", text));
+        self.render_children(data, &block.id, html);
+        html.push_str("</details>\n");
+      },
+      "code" => {
+        let language = block
+          .data
+          .get("language")
+          .and_then(Value::as_str)
+          .unwrap_or_default();
+        html.push_str(&format!(
+          "<pre><code class=\"language-{}\">{}</code></pre>\n",
+          escape(language),
+          escape(&plain_text(data, block))
+        ));
+      },
+      "divider" => html.push_str("<hr>\n"),
+      "math_equation" => {
+        let formula = block
+          .data
+          .get("formula")
+          .and_then(Value::as_str)
+          .unwrap_or_default();
+        html.push_str(&format!("<pre class=\"math\">{}</pre>\n", escape(formula)));
+      },
+      "image" => {
+        if let Some(src) = block
+          .data
+          .get("url")
+          .and_then(Value::as_str)
+          .and_then(|url| self.resource_url(url))
+        {
+          html.push_str(&format!(
+            "<figure><img src=\"{}\" alt=\"\" loading=\"lazy\"></figure>\n",
+            escape(&src)
+          ));
+        }
+      },
+      "file" | "link_preview" | "bookmark" => {
+        if let Some(href) = block
+          .data
+          .get("url")
+          .and_then(Value::as_str)
+          .and_then(|url| self.resource_url(url))
+        {
+          let name = block
+            .data
+            .get("name")
+            .and_then(Value::as_str)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&href)
+            .to_string();
+          html.push_str(&format!(
+            "<p><a href=\"{}\">{}</a></p>\n",
+            escape(&href),
+            escape(&name)
+          ));
+        }
+      },
+      "subpage" | "grid" | "board" | "calendar" => {
+        let link = block
+          .data
+          .get("view_id")
+          .and_then(Value::as_str)
+          .and_then(|view_id| self.page_link(view_id));
+        if let Some(link) = link {
+          html.push_str(&format!("<p>{}</p>\n", link));
+        }
+      },
+      _ => {
+        if !text.is_empty() {
+          html.push_str(&format!("<p>{}</p>\n", text));
+        }
+        self.render_nested_children(data, block, html);
+      },
+    }
+  }
+
+  /// Renders the rich text of a block, stored as a delta in the text map.
+  fn render_text(&mut self, data: &DocumentData, block: &Block) -> String {
+    let Some(ops) = text_delta(data, block) else {
+      return String::new();
+    };
+    let mut html = String::new();
+    for op in ops {
+      let Some(insert) = op.get("insert").and_then(Value::as_str) else {
+        continue;
+      };
+      let attributes = op.get("attributes");
+      let attr = |name: &str| attributes.and_then(|attributes| attributes.get(name));
+      let is_set = |name: &str| attr(name).and_then(Value::as_bool).unwrap_or(false);
+
+      if let Some(mention) = attr("mention") {
+        html.push_str(&self.render_mention(mention));
+        continue;
+      }
+      let mut text = escape(insert).replace('\n', "<br>");
+      if is_set("code") {
+        text = format!("<code>{}</code>", text);
+      }
+      if is_set("bold") {
+        text = format!("<strong>{}</strong>", text);
+      }
+      if is_set("italic") {
+        text = format!("<em>{}</em>", text);
+      }
+      if is_set("underline") {
+        text = format!("<u>{}</u>", text);
+      }
+      if is_set("strikethrough") {
+        text = format!("<s>{}</s>", text);
+      }
+      if let Some(href) = attr("href")
+        .and_then(Value::as_str)
+        .and_then(|href| self.resource_url(href))
+      {
+        text = format!("<a href=\"{}\">{}</a>", escape(&href), text);
+      }
+      html.push_str(&text);
+    }
+    html
+  }
+
+  fn render_mention(&self, mention: &Value) -> String {
+    match mention.get("type").and_then(Value::as_str) {
+      Some("page") | Some("childPage") => {
+        let view_id = mention
+          .get("page_id")
+          .and_then(Value::as_str)
+          .unwrap_or_default();
+        self.page_link(view_id).unwrap_or_default()
+      },
+      Some("date") => mention
+        .get("date")
+        .and_then(Value::as_str)
+        .map(escape)
+        .unwrap_or_default(),
+      _ => String::new(),
+    }
+  }
+
+  /// Link to another page of the export, relative to a page.
+  fn page_link(&self, view_id: &str) -> Option<String> {
+    self.pages.get(view_id).map(|page| {
+      format!(
+        "<a href=\"../{}/index.html\">{}</a>",
+        escape(&page.publish_name),
+        escape(&page.title)
+      )
+    })
+  }
+
+  /// Files uploaded to the workspace are copied into the export, other links are kept as is.
+  /// Returns None for links which are not safe to include.
+  fn resource_url(&mut self, url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https" | "mailto") {
+      return None;
+    }
+    match workspace_file_object_key(&parsed, &self.workspace_id) {
+      Some(object_key) => {
+        let file_name = object_key.rsplit('/').next().unwrap_or_default();
+        let extension = file_name
+          .rsplit_once('.')
+          .map(|(_, ext)| format!(".{}", ext))
+          .unwrap_or_default();
+        let path = format!("assets/{:x}{}", md5::compute(&object_key), extension);
+        self.assets.insert(path.clone(), object_key);
+        Some(format!("../{}", path))
+      },
+      None => Some(url.to_string()),
+    }
+  }
+}
+
+fn list_tag_name(tag: &str) -> &str {
+  tag.split(' ').next().unwrap_or(tag)
+}
+
+/// Returns the object key of a file uploaded to the workspace through the file storage API,
+/// e.g. `/api/file_storage/{workspace_id}/v1/blob/{parent_dir}/{file_id}`.
+fn workspace_file_object_key(url: &url::Url, workspace_id: &Uuid) -> Option<String> {
+  let segments = url.path_segments()?.collect::<Vec<_>>();
+  let start = segments
+    .windows(2)
+    .position(|window| window == ["api", "file_storage"])?;
+  let rest = &segments[start + 2..];
+  if rest
+    .iter()
+    .any(|segment| segment.contains('%') || segment.is_empty())
+  {
+    return None;
+  }
+  match rest {
+    [ws, "v1", "blob", parent_dir, file_id] if *ws == workspace_id.to_string() => {
+      Some(format!("{}/{}/{}", ws, parent_dir, file_id))
+    },
+    [ws, file_id] if *ws == workspace_id.to_string() => Some(format!("{}/{}", ws, file_id)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use collab_document::blocks::DocumentMeta;
+
+  #[test]
+  fn only_files_of_the_workspace_are_copied() {
+    let workspace_id = Uuid::new_v4();
+    let url = url::Url::parse(&format!(
+      "https://cloud.example.com/api/file_storage/{}/v1/blob/parent/file.png",
+      workspace_id
+    ))
+    .unwrap();
+    assert_eq!(
+      workspace_file_object_key(&url, &workspace_id),
+      Some(format!("{}/parent/file.png", workspace_id))
+    );
+    assert_eq!(workspace_file_object_key(&url, &Uuid::new_v4()), None);
+
+    let url = url::Url::parse("https://images.example.com/photo.png").unwrap();
+    assert_eq!(workspace_file_object_key(&url, &workspace_id), None);
+  }
+
+  #[test]
+  fn unsafe_links_are_dropped() {
+    let pages = HashMap::new();
+    let mut renderer = SiteRenderer::new(Uuid::new_v4(), &pages);
+    assert_eq!(renderer.resource_url("javascript:alert(1)"), None);
+    assert_eq!(
+      renderer.resource_url("https://appflowy.io"),
+      Some("https://appflowy.io".to_string())
+    );
+    assert!(renderer.assets.is_empty());
+  }
+
+  #[test]
+  fn cyclic_blocks_are_rendered_once() {
+    let block = |id: &str, parent: &str| Block {
+      id: id.to_string(),
+      ty: "paragraph".to_string(),
+      data: HashMap::new(),
+      parent: parent.to_string(),
+      children: format!("{}_children", id),
+      external_id: None,
+      external_type: None,
+    };
+    // `a` lists the page as its own child
+    let data = DocumentData {
+      page_id: "page".to_string(),
+      blocks: HashMap::from([
+        ("page".to_string(), block("page", "")),
+        ("a".to_string(), block("a", "page")),
+      ]),
+      meta: DocumentMeta {
+        children_map: HashMap::from([
+          ("page_children".to_string(), vec!["a".to_string()]),
+          ("a_children".to_string(), vec!["page".to_string()]),
+        ]),
+        text_map: None,
+      },
+    };
+
+    let pages = HashMap::new();
+    let mut renderer = SiteRenderer::new(Uuid::new_v4(), &pages);
+    let html = renderer.render_document(&data);
+    assert_eq!(html.matches("class=\"nested\"").count(), 1);
+    assert_eq!(renderer.walker.depth(), 0);
+  }
+}
@@ -0,0 +1,265 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use database::pg_row::AFPublishedFeedEntry;
+use database::publish::{select_published_feed_entries, select_workspace_id_for_publish_namespace};
+use sqlx::PgPool;
+use url::Url;
+use uuid::Uuid;
+
+/// Maximum number of most recently published pages listed in the RSS and Atom feeds.
+const MAX_FEED_ITEMS: usize = 50;
+/// Maximum number of URLs allowed in a single sitemap.
+const MAX_SITEMAP_URLS: usize = 50_000;
+
+#[derive(Debug, Clone)]
+pub struct PublishedFeedPage {
+  pub view_id: Uuid,
+  pub publish_name: String,
+  pub title: String,
+  pub published_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+impl From<AFPublishedFeedEntry> for PublishedFeedPage {
+  fn from(entry: AFPublishedFeedEntry) -> Self {
+    let title = entry
+      .metadata
+      .get("view")
+      .and_then(|view| view.get("name"))
+      .and_then(|name| name.as_str())
+      .filter(|name| !name.trim().is_empty())
+      .unwrap_or(&entry.publish_name)
+      .to_string();
+    Self {
+      view_id: entry.view_id,
+      publish_name: entry.publish_name,
+      title,
+      published_at: entry.published_at,
+      updated_at: entry.updated_at,
+    }
+  }
+}
+
+/// Returns the pages publicly listed under the namespace, most recently published first.
+pub async fn list_published_feed_pages(
+  pg_pool: &PgPool,
+  publish_namespace: &str,
+) -> Result<Vec<PublishedFeedPage>, AppError> {
+  // Fails with RecordNotFound if the namespace does not exist
+  select_workspace_id_for_publish_namespace(pg_pool, publish_namespace).await?;
+  let entries = select_published_feed_entries(pg_pool, publish_namespace).await?;
+  Ok(entries.into_iter().map(PublishedFeedPage::from).collect())
+}
+
+/// URL of the namespace's home page on AppFlowy Web.
+pub fn published_namespace_url(web_url: &str, publish_namespace: &str) -> Result<Url, AppError> {
+  let mut url = Url::parse(web_url)
+    .map_err(|err| AppError::Internal(anyhow::anyhow!("Invalid AppFlowy Web URL: {}", err)))?;
+  url
+    .path_segments_mut()
+    .map_err(|_| AppError::Internal(anyhow::anyhow!("AppFlowy Web URL cannot be a base")))?
+    .pop_if_empty()
+    .push(publish_namespace);
+  Ok(url)
+}
+
+/// URL of the published page on AppFlowy Web.
+pub fn published_page_url(
+  web_url: &str,
+  publish_namespace: &str,
+  publish_name: &str,
+) -> Result<Url, AppError> {
+  let mut url = published_namespace_url(web_url, publish_namespace)?;
+  if let Ok(mut segments) = url.path_segments_mut() {
+    segments.push(publish_name);
+  }
+  Ok(url)
+}
+
+pub fn render_sitemap(
+  web_url: &str,
+  publish_namespace: &str,
+  pages: &[PublishedFeedPage],
+) -> Result<String, AppError> {
+  let mut xml = String::from(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+     <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+  );
+  for page in pages.iter().take(MAX_SITEMAP_URLS) {
+    let url = published_page_url(web_url, publish_namespace, &page.publish_name)?;
+    xml.push_str("  <url>\n");
+    xml.push_str(&format!("    <loc>{}</loc>\n", xml_escape(url.as_str())));
+    xml.push_str(&format!(
+      "    <lastmod>{}</lastmod>\n",
+      page.updated_at.format("%Y-%m-%d")
+    ));
+    xml.push_str("  </url>\n");
+  }
+  xml.push_str("</urlset>\n");
+  Ok(xml)
+}
+
+pub fn render_rss(
+  web_url: &str,
+  publish_namespace: &str,
+  pages: &[PublishedFeedPage],
+) -> Result<String, AppError> {
+  let home_url = published_namespace_url(web_url, publish_namespace)?;
+  let mut xml = String::from(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n  <channel>\n",
+  );
+  xml.push_str(&format!(
+    "    <title>{}</title>\n",
+    xml_escape(publish_namespace)
+  ));
+  xml.push_str(&format!(
+    "    <link>{}</link>\n",
+    xml_escape(home_url.as_str())
+  ));
+  xml.push_str(&format!(
+    "    <description>Pages published on AppFlowy by {}</description>\n",
+    xml_escape(publish_namespace)
+  ));
+  if let Some(latest) = pages.first() {
+    xml.push_str(&format!(
+      "    <lastBuildDate>{}</lastBuildDate>\n",
+      latest.published_at.to_rfc2822()
+    ));
+  }
+  for page in pages.iter().take(MAX_FEED_ITEMS) {
+    let url = published_page_url(web_url, publish_namespace, &page.publish_name)?;
+    xml.push_str("    <item>\n");
+    xml.push_str(&format!(
+      "      <title>{}</title>\n",
+      xml_escape(&page.title)
+    ));
+    xml.push_str(&format!(
+      "      <link>{}</link>\n",
+      xml_escape(url.as_str())
+    ));
+    xml.push_str(&format!(
+      "      <guid isPermaLink=\"false\">{}</guid>\n",
+      page.view_id
+    ));
+    xml.push_str(&format!(
+      "      <pubDate>{}</pubDate>\n",
+      page.published_at.to_rfc2822()
+    ));
+    xml.push_str("    </item>\n");
+  }
+  xml.push_str("  </channel>\n</rss>\n");
+  Ok(xml)
+}
+
+pub fn render_atom(
+  web_url: &str,
+  publish_namespace: &str,
+  pages: &[PublishedFeedPage],
+) -> Result<String, AppError> {
+  let home_url = published_namespace_url(web_url, publish_namespace)?;
+  let updated = pages
+    .iter()
+    .map(|page| page.updated_at)
+    .max()
+    .unwrap_or_else(Utc::now);
+  let mut xml = String::from(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+  );
+  xml.push_str(&format!(
+    "  <title>{}</title>\n",
+    xml_escape(publish_namespace)
+  ));
+  xml.push_str(&format!(
+    "  <link href=\"{}\"/>\n",
+    xml_escape(home_url.as_str())
+  ));
+  xml.push_str(&format!("  <id>{}</id>\n", xml_escape(home_url.as_str())));
+  xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+  xml.push_str(&format!(
+    "  <author><name>{}</name></author>\n",
+    xml_escape(publish_namespace)
+  ));
+  for page in pages.iter().take(MAX_FEED_ITEMS) {
+    let url = published_page_url(web_url, publish_namespace, &page.publish_name)?;
+    xml.push_str("  <entry>\n");
+    xml.push_str(&format!("    <title>{}</title>\n", xml_escape(&page.title)));
+    xml.push_str(&format!(
+      "    <link href=\"{}\"/>\n",
+      xml_escape(url.as_str())
+    ));
+    xml.push_str(&format!("    <id>urn:uuid:{}</id>\n", page.view_id));
+    xml.push_str(&format!(
+      "    <published>{}</published>\n",
+      page.published_at.to_rfc3339()
+    ));
+    xml.push_str(&format!(
+      "    <updated>{}</updated>\n",
+      page.updated_at.to_rfc3339()
+    ));
+    xml.push_str("  </entry>\n");
+  }
+  xml.push_str("</feed>\n");
+  Ok(xml)
+}
+
+pub fn xml_escape(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      // Control characters are not allowed in XML 1.0
+      c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {},
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn page(publish_name: &str, title: &str) -> PublishedFeedPage {
+    let ts = Utc.with_ymd_and_hms(2025, 8, 7, 9, 30, 0).unwrap();
+    PublishedFeedPage {
+      view_id: Uuid::nil(),
+      publish_name: publish_name.to_string(),
+      title: title.to_string(),
+      published_at: ts,
+      updated_at: ts,
+    }
+  }
+
+  #[test]
+  fn page_url_is_percent_encoded() {
+    let url = published_page_url("https://appflowy.com/", "my-ns", "résumé").unwrap();
+    assert_eq!(url.as_str(), "https://appflowy.com/my-ns/r%C3%A9sum%C3%A9");
+    let url = published_page_url("https://example.com/web", "my-ns", "page").unwrap();
+    assert_eq!(url.as_str(), "https://example.com/web/my-ns/page");
+  }
+
+  #[test]
+  fn sitemap_lists_all_pages() {
+    let pages = vec![page("first", "First"), page("second", "Second")];
+    let xml = render_sitemap("https://appflowy.com", "my-ns", &pages).unwrap();
+    assert!(xml.contains("<loc>https://appflowy.com/my-ns/first</loc>"));
+    assert!(xml.contains("<loc>https://appflowy.com/my-ns/second</loc>"));
+    assert!(xml.contains("<lastmod>2025-08-07</lastmod>"));
+  }
+
+  #[test]
+  fn feeds_escape_titles() {
+    let pages = vec![page("notes", "Tips & <tricks>")];
+    let rss = render_rss("https://appflowy.com", "my-ns", &pages).unwrap();
+    assert!(rss.contains("<title>Tips &amp; &lt;tricks&gt;</title>"));
+    assert!(rss.contains("Aug 2025 09:30:00 +0000</pubDate>"));
+    let atom = render_atom("https://appflowy.com", "my-ns", &pages).unwrap();
+    assert!(atom.contains("<title>Tips &amp; &lt;tricks&gt;</title>"));
+    assert!(atom.contains("<link href=\"https://appflowy.com/my-ns/notes\"/>"));
+  }
+}
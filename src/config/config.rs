@@ -2,6 +2,7 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 use anyhow::{anyhow, Context};
+use appflowy_collaborate::ws2::{RateLimit, RateLimitAction, RateLimitConfig, RateLimits};
 use async_openai::config::{AzureConfig, OpenAIConfig};
 use indexer::vector::embedder::get_open_ai_config;
 use infra::env_util::{get_env_var, get_env_var_opt};
@@ -29,6 +30,8 @@ pub struct Config {
   pub apple_oauth: AppleOAuthSetting,
   pub appflowy_web_url: String,
   pub notification: NotificationSetting,
+  pub user_data_export: UserDataExportSetting,
+  pub database_row_template: DatabaseRowTemplateSetting,
   pub open_ai_config: Option<OpenAIConfig>,
   pub azure_ai_config: Option<AzureConfig>,
   pub sms: SmsSetting,
@@ -155,6 +158,25 @@ pub enum PublishedCollabStorageBackend {
 #[derive(Clone, Debug)]
 pub struct PublishedCollabSetting {
   pub storage_backend: PublishedCollabStorageBackend,
+  pub analytics: PublishAnalyticsSetting,
+  pub site_export: PublishedSiteExportSetting,
+  /// Maximum number of comments a user can post on published pages per minute.
+  pub comment_rate_limit_per_minute: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct PublishedSiteExportSetting {
+  pub interval_secs: u64,
+  /// Base URL of the CDN serving the bucket, used to build the URL of exported sites.
+  pub cdn_url: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PublishAnalyticsSetting {
+  pub enabled: bool,
+  pub flush_interval_secs: u64,
+  /// Path to a MaxMind country database, used to resolve the country of a visitor.
+  pub geoip_db_path: Option<String>,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -189,6 +211,20 @@ pub struct NotificationSetting {
   pub email_notification_grace_period_secs: u64,
 }
 
+#[derive(Clone, Debug)]
+pub struct UserDataExportSetting {
+  pub interval_secs: u64,
+  /// How long the archive of an export can be downloaded. Presigned urls are valid for at most
+  /// 7 days.
+  pub link_expires_secs: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct DatabaseRowTemplateSetting {
+  /// How often the runs of the scheduled row templates, queued by the worker, are checked.
+  pub interval_secs: u64,
+}
+
 // Default values favor local development.
 pub fn get_configuration() -> Result<Config, anyhow::Error> {
   let (open_ai_config, azure_ai_config) = get_open_ai_config();
@@ -235,6 +271,7 @@ pub fn get_configuration() -> Result<Config, anyhow::Error> {
       heartbeat_interval: get_env_var("APPFLOWY_WEBSOCKET_HEARTBEAT_INTERVAL", "6").parse()?,
       client_timeout: get_env_var("APPFLOWY_WEBSOCKET_CLIENT_TIMEOUT", "60").parse()?,
       min_client_version: get_env_var("APPFLOWY_WEBSOCKET_CLIENT_MIN_VERSION", "0.5.0").parse()?,
+      rate_limit: get_rate_limit_config()?,
     },
     redis_uri: get_env_var("APPFLOWY_REDIS_URI", "redis://localhost:6379").into(),
     redis_worker_count: get_env_var("APPFLOWY_REDIS_WORKERS", "60").parse()?,
@@ -272,6 +309,29 @@ pub fn get_configuration() -> Result<Config, anyhow::Error> {
       storage_backend: get_env_var("APPFLOWY_PUBLISHED_COLLAB_STORAGE_BACKEND", "postgres")
         .as_str()
         .try_into()?,
+      analytics: PublishAnalyticsSetting {
+        enabled: get_env_var("APPFLOWY_PUBLISH_ANALYTICS_ENABLED", "true")
+          .parse()
+          .context("fail to get APPFLOWY_PUBLISH_ANALYTICS_ENABLED")?,
+        flush_interval_secs: get_env_var("APPFLOWY_PUBLISH_ANALYTICS_FLUSH_INTERVAL_SECS", "30")
+          .parse()
+          .context("fail to get APPFLOWY_PUBLISH_ANALYTICS_FLUSH_INTERVAL_SECS")?,
+        geoip_db_path: get_env_var_opt("APPFLOWY_PUBLISH_ANALYTICS_GEOIP_PATH")
+          .filter(|path| !path.is_empty()),
+      },
+      site_export: PublishedSiteExportSetting {
+        interval_secs: get_env_var("APPFLOWY_PUBLISH_SITE_EXPORT_INTERVAL_SECS", "10")
+          .parse()
+          .context("fail to get APPFLOWY_PUBLISH_SITE_EXPORT_INTERVAL_SECS")?,
+        cdn_url: get_env_var_opt("APPFLOWY_PUBLISH_SITE_EXPORT_CDN_URL")
+          .filter(|url| !url.is_empty()),
+      },
+      comment_rate_limit_per_minute: get_env_var(
+        "APPFLOWY_PUBLISH_COMMENT_RATE_LIMIT_PER_MINUTE",
+        "10",
+      )
+      .parse()
+      .context("fail to get APPFLOWY_PUBLISH_COMMENT_RATE_LIMIT_PER_MINUTE")?,
     },
     mailer: MailerSetting {
       smtp_host: get_env_var("APPFLOWY_MAILER_SMTP_HOST", "smtp.gmail.com"),
@@ -301,6 +361,19 @@ pub fn get_configuration() -> Result<Config, anyhow::Error> {
       )
       .parse()?,
     },
+    user_data_export: UserDataExportSetting {
+      interval_secs: get_env_var("APPFLOWY_USER_DATA_EXPORT_INTERVAL_SECS", "10")
+        .parse()
+        .context("fail to get APPFLOWY_USER_DATA_EXPORT_INTERVAL_SECS")?,
+      link_expires_secs: get_env_var("APPFLOWY_USER_DATA_EXPORT_LINK_EXPIRES_SECS", "604800")
+        .parse()
+        .context("fail to get APPFLOWY_USER_DATA_EXPORT_LINK_EXPIRES_SECS")?,
+    },
+    database_row_template: DatabaseRowTemplateSetting {
+      interval_secs: get_env_var("APPFLOWY_DATABASE_ROW_TEMPLATE_INTERVAL_SECS", "10")
+        .parse()
+        .context("fail to get APPFLOWY_DATABASE_ROW_TEMPLATE_INTERVAL_SECS")?,
+    },
     open_ai_config,
     azure_ai_config,
     sms: SmsSetting {
@@ -354,4 +427,56 @@ pub struct WebsocketSetting {
   pub heartbeat_interval: u8,
   pub client_timeout: u8,
   pub min_client_version: Version,
+  pub rate_limit: RateLimitConfig,
+}
+
+fn get_rate_limit_config() -> Result<RateLimitConfig, anyhow::Error> {
+  let default = RateLimitConfig::default();
+  let action = match get_env_var("APPFLOWY_WEBSOCKET_RATE_LIMIT_ACTION", "throttle")
+    .to_lowercase()
+    .as_str()
+  {
+    "throttle" => RateLimitAction::Throttle,
+    "disconnect" => RateLimitAction::Disconnect,
+    other => anyhow::bail!(
+      "{} is not a supported rate limit action. Use either `throttle` or `disconnect`.",
+      other
+    ),
+  };
+  Ok(RateLimitConfig {
+    enabled: get_env_var("APPFLOWY_WEBSOCKET_RATE_LIMIT_ENABLED", "true")
+      .parse()
+      .context("fail to get APPFLOWY_WEBSOCKET_RATE_LIMIT_ENABLED")?,
+    session: get_rate_limits("APPFLOWY_WEBSOCKET_SESSION", &default.session)?,
+    workspace: get_rate_limits("APPFLOWY_WEBSOCKET_WORKSPACE", &default.workspace)?,
+    action,
+    max_delay: std::time::Duration::from_millis(
+      get_env_var(
+        "APPFLOWY_WEBSOCKET_RATE_LIMIT_MAX_DELAY_MS",
+        &default.max_delay.as_millis().to_string(),
+      )
+      .parse()
+      .context("fail to get APPFLOWY_WEBSOCKET_RATE_LIMIT_MAX_DELAY_MS")?,
+    ),
+  })
+}
+
+fn get_rate_limits(prefix: &str, default: &RateLimits) -> Result<RateLimits, anyhow::Error> {
+  let get = |kind: &str, default: RateLimit| -> Result<RateLimit, anyhow::Error> {
+    let per_second_key = format!("{}_{}_PER_SEC", prefix, kind);
+    let burst_key = format!("{}_{}_BURST", prefix, kind);
+    Ok(RateLimit::new(
+      get_env_var(&per_second_key, &default.per_second.to_string())
+        .parse()
+        .with_context(|| format!("fail to get {}", per_second_key))?,
+      get_env_var(&burst_key, &default.burst.to_string())
+        .parse()
+        .with_context(|| format!("fail to get {}", burst_key))?,
+    ))
+  };
+  Ok(RateLimits {
+    updates: get("UPDATES", default.updates)?,
+    awareness: get("AWARENESS", default.awareness)?,
+    bytes: get("BYTES", default.bytes)?,
+  })
 }
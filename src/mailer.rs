@@ -6,6 +6,7 @@ pub const WORKSPACE_ACCESS_REQUEST_TEMPLATE_NAME: &str = "workspace_access_reque
 pub const WORKSPACE_ACCESS_REQUEST_APPROVED_NOTIFICATION_TEMPLATE_NAME: &str =
   "workspace_access_request_approved_notification";
 pub const PAGE_MENTION_NOTIFICATION_TEMPLATE_NAME: &str = "page_mention_notification";
+pub const USER_DATA_EXPORT_TEMPLATE_NAME: &str = "user_data_export";
 
 #[derive(Clone)]
 pub struct AFCloudMailer(Mailer);
@@ -107,6 +108,23 @@ impl AFCloudMailer {
       )
       .await
   }
+
+  pub async fn send_user_data_export(
+    &self,
+    email: &str,
+    param: UserDataExportMailerParam,
+  ) -> Result<(), anyhow::Error> {
+    self
+      .0
+      .send_email_template(
+        Some(param.username.clone()),
+        email,
+        USER_DATA_EXPORT_TEMPLATE_NAME,
+        param,
+        "Your AppFlowy data export is ready",
+      )
+      .await
+  }
 }
 
 async fn register_mailer(mailer: &mut Mailer) -> Result<(), anyhow::Error> {
@@ -119,6 +137,8 @@ async fn register_mailer(mailer: &mut Mailer) -> Result<(), anyhow::Error> {
   );
   let page_mention_notification_template =
     include_str!("../assets/mailer_templates/build_production/page_mention_notification.html");
+  let user_data_export_template =
+    include_str!("../assets/mailer_templates/build_production/user_data_export.html");
   let template_strings = HashMap::from([
     (WORKSPACE_INVITE_TEMPLATE_NAME, workspace_invite_template),
     (
@@ -133,6 +153,7 @@ async fn register_mailer(mailer: &mut Mailer) -> Result<(), anyhow::Error> {
       PAGE_MENTION_NOTIFICATION_TEMPLATE_NAME,
       page_mention_notification_template,
     ),
+    (USER_DATA_EXPORT_TEMPLATE_NAME, user_data_export_template),
   ]);
 
   for (template_name, template_string) in template_strings {
@@ -182,3 +203,10 @@ pub struct PageMentionNotificationMailerParam {
   pub mentioned_page_url: String,
   pub mentioned_at: String,
 }
+
+#[derive(serde::Serialize)]
+pub struct UserDataExportMailerParam {
+  pub username: String,
+  pub download_url: String,
+  pub expires_at: String,
+}
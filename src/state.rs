@@ -1,4 +1,5 @@
 use access_control::collab::{CollabAccessControl, RealtimeAccessControl};
+use access_control::database_row::DatabaseRowAccessControl;
 use access_control::workspace::WorkspaceAccessControl;
 use actix::Addr;
 use anyhow::anyhow;
@@ -16,7 +17,7 @@ use app_error::AppError;
 use appflowy_ai_client::client::AppFlowyAIClient;
 use appflowy_collaborate::collab::cache::CollabCache;
 use appflowy_collaborate::metrics::CollabMetrics;
-use appflowy_collaborate::ws2::WsServer;
+use appflowy_collaborate::ws2::{RateLimiters, SseSessions, WsServer};
 use appflowy_collaborate::CollabRealtimeMetrics;
 use collab_stream::awareness_gossip::AwarenessGossip;
 use collab_stream::metrics::CollabStreamMetrics;
@@ -32,6 +33,8 @@ use crate::api::metrics::{AppFlowyWebMetrics, PublishedCollabMetrics, RequestMet
 use crate::biz::chat::metrics::AIMetrics;
 use crate::biz::pg_listener::PgListeners;
 use crate::biz::workspace::publish::PublishedCollabStore;
+use crate::biz::workspace::publish_analytics::PublishAnalytics;
+use crate::biz::workspace::publish_domain::TxtResolver;
 use crate::config::config::Config;
 use crate::mailer::AFCloudMailer;
 
@@ -51,6 +54,7 @@ pub struct AppState {
   pub collab_access_control: Arc<dyn CollabAccessControl>,
   pub workspace_access_control: Arc<dyn WorkspaceAccessControl>,
   pub realtime_access_control: Arc<dyn RealtimeAccessControl>,
+  pub database_row_access_control: Arc<dyn DatabaseRowAccessControl>,
   pub bucket_storage: Arc<S3BucketStorage>,
   pub published_collab_store: Arc<dyn PublishedCollabStore>,
   pub bucket_client: AwsS3BucketClientImpl,
@@ -61,6 +65,10 @@ pub struct AppState {
   pub ai_client: AppFlowyAIClient,
   pub indexer_scheduler: Arc<IndexerScheduler>,
   pub ws_server: Addr<WsServer>,
+  pub sse_sessions: SseSessions,
+  pub ws_rate_limiters: RateLimiters,
+  pub publish_analytics: PublishAnalytics,
+  pub publish_domain_resolver: Arc<dyn TxtResolver>,
   pub sms_service: Option<Arc<crate::biz::sms::SmsService>>,
 }
 
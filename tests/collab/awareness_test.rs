@@ -63,6 +63,56 @@ async fn viewing_document_editing_users_test() {
   assert_num_connected_client_within_secs(&owner, &object_id, 2, 30).await;
 }
 
+#[tokio::test]
+async fn workspace_presence_test() {
+  let collab_type = CollabType::Unknown;
+  let mut owner = TestClient::new_user().await;
+  let mut guest = TestClient::new_user().await;
+
+  let workspace_id = owner.workspace_id().await;
+  owner
+    .invite_and_accepted_workspace_member(&workspace_id, &guest, AFRole::Member)
+    .await
+    .unwrap();
+
+  let object_id = owner
+    .create_and_edit_collab(workspace_id, collab_type)
+    .await;
+  guest
+    .open_collab(workspace_id, object_id, collab_type)
+    .await;
+  guest.wait_object_sync_complete(&object_id).await.unwrap();
+  sleep(Duration::from_secs(2)).await;
+
+  // guest opened the collab, so it should be visible as an active user
+  let guest_uid = guest.uid().await;
+  let presence = owner
+    .api_client
+    .get_workspace_presence(&workspace_id, Some(object_id))
+    .await
+    .unwrap();
+  assert_eq!(presence.objects.len(), 1);
+  assert_eq!(presence.objects[0].object_id, object_id);
+  assert!(presence.objects[0]
+    .active_users
+    .iter()
+    .any(|user| user.uid == guest_uid));
+
+  // simulate the guest close the collab
+  guest.clean_awareness_state(&object_id).await;
+  sleep(Duration::from_secs(2)).await;
+  let presence = owner
+    .api_client
+    .get_workspace_presence(&workspace_id, Some(object_id))
+    .await
+    .unwrap();
+  assert!(presence
+    .objects
+    .iter()
+    .flat_map(|object| object.active_users.iter())
+    .all(|user| user.uid != guest_uid));
+}
+
 async fn assert_num_connected_client_within_secs(
   client: &TestClient,
   object_id: &Uuid,
@@ -1,9 +1,23 @@
 use std::collections::HashMap;
 
-use client_api_test::{generate_unique_registered_user_client, workspace_id_from_client};
+use app_error::ErrorCode;
+use client_api_test::{
+  generate_unique_registered_user_client, workspace_id_from_client, TestClient,
+};
 use collab_database::entity::FieldType;
+use collab_database::rows::{meta_id_from_row_id, RowMetaKey};
+use collab_entity::CollabType;
+use database_entity::dto::{AFRole, QueryCollab, QueryCollabParams, QueryCollabResult};
 use serde_json::json;
-use shared_entity::dto::workspace_dto::AFInsertDatabaseField;
+use shared_entity::dto::workspace_dto::{
+  AFComputedField, AFCreateDatabaseRowTemplate, AFDatabaseRow, AFDatabaseRowDetail,
+  AFDatabaseRowGrants, AFInsertDatabaseField, AFMoveDatabaseField, AFRelatedDatabaseRow,
+  AFUpdateCalendarViewSettings, AFUpdateDatabaseField, AFUpdateDatabaseRowAccessRule,
+  AFUpdateDatabaseRowTemplate, AFUpdateDatabaseView, AddDatatabaseRow, BatchDatabaseRowsParams,
+  DatabaseRowChangeType, DatabaseRowFilter, DatabaseRowFilterCondition, DatabaseRowOperation,
+  DatabaseRowSort, ListDatabaseRowChangesParam, QueryDatabaseRowsParams, UpsertDatatabaseRow,
+};
+use uuid::Uuid;
 
 #[tokio::test]
 async fn database_row_upsert_with_doc() {
@@ -260,6 +274,101 @@ async fn database_fields_crud() {
   }
 }
 
+#[tokio::test]
+async fn database_fields_update_move_delete() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+
+  let field_id = c
+    .add_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &AFInsertDatabaseField {
+        name: "MyTextColumn".to_string(),
+        field_type: FieldType::RichText.into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  let row_id = c
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([(field_id.clone(), json!("https://appflowy.io"))]),
+      None,
+    )
+    .await
+    .unwrap();
+
+  // Rename and convert to url, keeping the cell data
+  c.update_database_field(
+    &workspace_id,
+    &todo_db.id,
+    &field_id,
+    &AFUpdateDatabaseField {
+      name: Some("MyUrlColumn".to_string()),
+      field_type: Some(FieldType::URL.into()),
+      ..Default::default()
+    },
+  )
+  .await
+  .unwrap();
+  let fields = c
+    .get_database_fields(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  let field = fields.iter().find(|field| field.id == field_id).unwrap();
+  assert_eq!(field.name, "MyUrlColumn");
+  assert_eq!(field.field_type, "URL");
+  let row_detail = &c
+    .list_database_row_details(&workspace_id, &todo_db.id, &[&row_id], false)
+    .await
+    .unwrap()[0];
+  assert_eq!(row_detail.cells["MyUrlColumn"], "https://appflowy.io");
+
+  // Move the field first, then after an unknown field
+  c.move_database_field(
+    &workspace_id,
+    &todo_db.id,
+    &field_id,
+    &AFMoveDatabaseField::default(),
+  )
+  .await
+  .unwrap();
+  let err = c
+    .move_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &field_id,
+      &AFMoveDatabaseField {
+        prev_field_id: Some("unknown_field".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+
+  // Delete the field, the primary field cannot be deleted
+  c.delete_database_field(&workspace_id, &todo_db.id, &field_id)
+    .await
+    .unwrap();
+  let fields = c
+    .get_database_fields(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  assert!(fields.iter().all(|field| field.id != field_id));
+  let primary_field = fields.iter().find(|field| field.is_primary).unwrap();
+  let err = c
+    .delete_database_field(&workspace_id, &todo_db.id, &primary_field.id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+}
+
 #[tokio::test]
 async fn database_fields_unsupported_field_type() {
   let (c, _user) = generate_unique_registered_user_client().await;
@@ -339,3 +448,1314 @@ async fn database_insert_row_with_doc() {
     Some("This is a document of a database row".to_string())
   );
 }
+
+#[tokio::test]
+async fn database_query_rows() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+
+  for (description, status) in [
+    ("query_alpha", "To Do"),
+    ("query_beta", "Doing"),
+    ("query_gamma", "To Do"),
+  ] {
+    c.add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([
+        (String::from("Description"), json!(description)),
+        (String::from("Status"), json!(status)),
+      ]),
+      None,
+    )
+    .await
+    .unwrap();
+  }
+
+  let text_filter = DatabaseRowFilter {
+    field: "Description".to_string(),
+    condition: DatabaseRowFilterCondition::TextContains {
+      value: "QUERY_".to_string(),
+    },
+  };
+  let mut params = QueryDatabaseRowsParams {
+    filters: vec![text_filter.clone()],
+    sorts: vec![DatabaseRowSort {
+      field: "Description".to_string(),
+      descending: true,
+    }],
+    limit: Some(2),
+    ..Default::default()
+  };
+  let first_page = c
+    .query_database_rows(&workspace_id, &todo_db.id, &params)
+    .await
+    .unwrap();
+  let descriptions = |rows: &[AFDatabaseRowDetail]| {
+    rows
+      .iter()
+      .map(|row| row.cells["Description"].as_str().unwrap().to_string())
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    descriptions(&first_page.rows),
+    vec!["query_gamma", "query_beta"]
+  );
+  assert!(first_page.next_cursor.is_some());
+
+  // a row added before the cursor does not shift the next page
+  c.add_database_item(
+    &workspace_id,
+    &todo_db.id,
+    HashMap::from([(String::from("Description"), json!("query_omega"))]),
+    None,
+  )
+  .await
+  .unwrap();
+  params.cursor = first_page.next_cursor;
+  let second_page = c
+    .query_database_rows(&workspace_id, &todo_db.id, &params)
+    .await
+    .unwrap();
+  assert_eq!(descriptions(&second_page.rows), vec!["query_alpha"]);
+  assert!(second_page.next_cursor.is_none());
+
+  // without sorts, the rows are paged in the order of the view
+  let mut params = QueryDatabaseRowsParams {
+    filters: vec![text_filter.clone()],
+    limit: Some(3),
+    ..Default::default()
+  };
+  let first_page = c
+    .query_database_rows(&workspace_id, &todo_db.id, &params)
+    .await
+    .unwrap();
+  assert_eq!(
+    descriptions(&first_page.rows),
+    vec!["query_alpha", "query_beta", "query_gamma"]
+  );
+  params.cursor = first_page.next_cursor;
+  let second_page = c
+    .query_database_rows(&workspace_id, &todo_db.id, &params)
+    .await
+    .unwrap();
+  assert_eq!(descriptions(&second_page.rows), vec!["query_omega"]);
+  assert!(second_page.next_cursor.is_none());
+
+  let params = QueryDatabaseRowsParams {
+    filters: vec![
+      text_filter,
+      DatabaseRowFilter {
+        field: "Status".to_string(),
+        condition: DatabaseRowFilterCondition::SelectOptionIs {
+          options: vec!["Doing".to_string()],
+        },
+      },
+    ],
+    ..Default::default()
+  };
+  let resp = c
+    .query_database_rows(&workspace_id, &todo_db.id, &params)
+    .await
+    .unwrap();
+  assert_eq!(descriptions(&resp.rows), vec!["query_beta"]);
+}
+
+#[tokio::test]
+async fn database_formula_fields() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+
+  for name in ["Price", "Quantity"] {
+    c.add_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &AFInsertDatabaseField {
+        name: name.to_string(),
+        field_type: FieldType::Number.into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  }
+  let total_field_id = c
+    .add_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &AFInsertDatabaseField {
+        name: "Total".to_string(),
+        field_type: FieldType::Number.into(),
+        computed: Some(AFComputedField::Formula {
+          expression: "{Price} * {Quantity}".to_string(),
+        }),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  c.add_database_field(
+    &workspace_id,
+    &todo_db.id,
+    &AFInsertDatabaseField {
+      name: "Label".to_string(),
+      field_type: FieldType::RichText.into(),
+      computed: Some(AFComputedField::Formula {
+        expression: "if({Total} >= 10, upper({Description}), {Description}) & \" x\" & {Quantity}"
+          .to_string(),
+      }),
+      ..Default::default()
+    },
+  )
+  .await
+  .unwrap();
+
+  // Values written to a computed field are ignored
+  let row_id = c
+    .upsert_database_item(
+      &workspace_id,
+      &todo_db.id,
+      "formula_row".to_string(),
+      HashMap::from([
+        (String::from("Description"), json!("formula")),
+        (String::from("Price"), json!(3)),
+        (String::from("Quantity"), json!(4)),
+        (String::from("Total"), json!(1)),
+      ]),
+      None,
+    )
+    .await
+    .unwrap();
+  let row_detail = &c
+    .list_database_row_details(&workspace_id, &todo_db.id, &[&row_id], false)
+    .await
+    .unwrap()[0];
+  assert_eq!(row_detail.cells["Total"], "12");
+  assert_eq!(row_detail.cells["Label"], "FORMULA x4");
+
+  // Formulas are evaluated again when the row changes, and can be filtered on
+  c.upsert_database_item(
+    &workspace_id,
+    &todo_db.id,
+    "formula_row".to_string(),
+    HashMap::from([(String::from("Quantity"), json!(2))]),
+    None,
+  )
+  .await
+  .unwrap();
+  let params = QueryDatabaseRowsParams {
+    filters: vec![DatabaseRowFilter {
+      field: "Total".to_string(),
+      condition: DatabaseRowFilterCondition::NumberRange {
+        min: Some(6.0),
+        max: Some(6.0),
+      },
+    }],
+    ..Default::default()
+  };
+  let resp = c
+    .query_database_rows(&workspace_id, &todo_db.id, &params)
+    .await
+    .unwrap();
+  assert_eq!(resp.rows.len(), 1);
+  assert_eq!(resp.rows[0].cells["Label"], "formula x2");
+
+  // Invalid formulas and references to unknown fields or to the field itself are rejected
+  for expression in ["{Price} *", "{Unknown} + 1", "{Total} + 1"] {
+    let err = c
+      .update_database_field(
+        &workspace_id,
+        &todo_db.id,
+        &total_field_id,
+        &AFUpdateDatabaseField {
+          computed: Some(AFComputedField::Formula {
+            expression: expression.to_string(),
+          }),
+          ..Default::default()
+        },
+      )
+      .await
+      .unwrap_err();
+    assert_eq!(err.code, ErrorCode::InvalidRequest, "{}", expression);
+  }
+}
+
+#[tokio::test]
+async fn database_delete_and_batch_rows() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+  let row_ids = |rows: Vec<AFDatabaseRow>| rows.into_iter().map(|row| row.id).collect::<Vec<_>>();
+
+  // Delete a single row
+  let row_id = c
+    .add_database_item(&workspace_id, &todo_db.id, HashMap::new(), None)
+    .await
+    .unwrap();
+  c.delete_database_item(&workspace_id, &todo_db.id, &row_id)
+    .await
+    .unwrap();
+  let db_row_ids = row_ids(
+    c.list_database_row_ids(&workspace_id, &todo_db.id)
+      .await
+      .unwrap(),
+  );
+  assert!(!db_row_ids.contains(&row_id));
+
+  // Deleting it again is not found
+  let err = c
+    .delete_database_item(&workspace_id, &todo_db.id, &row_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+
+  // Batch insert, upsert and delete
+  let existing_row_id = c
+    .add_database_item(&workspace_id, &todo_db.id, HashMap::new(), None)
+    .await
+    .unwrap();
+  let resp = c
+    .batch_database_items(
+      &workspace_id,
+      &todo_db.id,
+      &BatchDatabaseRowsParams {
+        operations: vec![
+          DatabaseRowOperation::Insert(AddDatatabaseRow {
+            cells: HashMap::from([(String::from("Description"), json!("batch_insert"))]),
+            document: None,
+          }),
+          DatabaseRowOperation::Upsert(UpsertDatatabaseRow {
+            pre_hash: "batch_pre_hash".to_string(),
+            cells: HashMap::from([(String::from("Description"), json!("batch_upsert"))]),
+            document: Some("batch row document".to_string()),
+          }),
+          DatabaseRowOperation::Delete {
+            row_id: existing_row_id.clone(),
+          },
+        ],
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(resp.row_ids.len(), 3);
+  assert_eq!(resp.row_ids[2], existing_row_id);
+
+  let db_row_ids = row_ids(
+    c.list_database_row_ids(&workspace_id, &todo_db.id)
+      .await
+      .unwrap(),
+  );
+  assert!(db_row_ids.contains(&resp.row_ids[0]));
+  assert!(db_row_ids.contains(&resp.row_ids[1]));
+  assert!(!db_row_ids.contains(&existing_row_id));
+
+  // Upserting with the same pre hash updates the same row
+  let upserted = c
+    .upsert_database_item(
+      &workspace_id,
+      &todo_db.id,
+      "batch_pre_hash".to_string(),
+      HashMap::from([(String::from("Description"), json!("batch_upsert_2"))]),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(upserted, resp.row_ids[1]);
+  let row_details = c
+    .list_database_row_details(
+      &workspace_id,
+      &todo_db.id,
+      &[&resp.row_ids[0], &resp.row_ids[1]],
+      true,
+    )
+    .await
+    .unwrap();
+  let description = |row_id: &str| {
+    row_details
+      .iter()
+      .find(|row| row.id == row_id)
+      .map(|row| row.cells["Description"].clone())
+      .unwrap()
+  };
+  assert_eq!(description(&resp.row_ids[0]), "batch_insert");
+  assert_eq!(description(&resp.row_ids[1]), "batch_upsert_2");
+
+  // A batch upserting the same row twice is rejected as a whole
+  let upsert = |description: &str| {
+    DatabaseRowOperation::Upsert(UpsertDatatabaseRow {
+      pre_hash: "batch_pre_hash".to_string(),
+      cells: HashMap::from([(String::from("Description"), json!(description))]),
+      document: None,
+    })
+  };
+  let err = c
+    .batch_database_items(
+      &workspace_id,
+      &todo_db.id,
+      &BatchDatabaseRowsParams {
+        operations: vec![
+          DatabaseRowOperation::Insert(AddDatatabaseRow {
+            cells: HashMap::new(),
+            document: None,
+          }),
+          upsert("batch_upsert_3"),
+          upsert("batch_upsert_4"),
+        ],
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+  let unchanged_row_ids = row_ids(
+    c.list_database_row_ids(&workspace_id, &todo_db.id)
+      .await
+      .unwrap(),
+  );
+  assert_eq!(unchanged_row_ids, db_row_ids);
+  let row_details = c
+    .list_database_row_details(&workspace_id, &todo_db.id, &[&resp.row_ids[1]], false)
+    .await
+    .unwrap();
+  assert_eq!(row_details[0].cells["Description"], "batch_upsert_2");
+}
+
+#[tokio::test]
+async fn database_csv_import_export() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+
+  let csv = "Key,Description,Status,Multiselect,Unknown\n\
+             csv_1,csv task 1,To Do,\"social, news\",x\n\
+             csv_2,csv task 2,Not a status,,y\n";
+  let dry_run = c
+    .import_database_csv(
+      &workspace_id,
+      &todo_db.id,
+      &ImportDatabaseCsvQuery {
+        dry_run: true,
+        pre_hash_column: Some("Key".to_string()),
+        import_id: None,
+      },
+      csv.to_string(),
+    )
+    .await
+    .unwrap();
+  assert!(dry_run.row_ids.is_empty());
+  assert_eq!(dry_run.unmapped_columns, vec!["Unknown"]);
+  assert_eq!(dry_run.invalid_values.len(), 1);
+  assert_eq!(dry_run.invalid_values[0].row, 2);
+  assert_eq!(dry_run.invalid_values[0].column, "Status");
+
+  // Import fails as long as there are invalid values
+  let err = c
+    .import_database_csv(
+      &workspace_id,
+      &todo_db.id,
+      &ImportDatabaseCsvQuery {
+        dry_run: false,
+        pre_hash_column: Some("Key".to_string()),
+        import_id: None,
+      },
+      csv.to_string(),
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+
+  let csv = csv.replace("Not a status", "Doing");
+  let query = ImportDatabaseCsvQuery {
+    dry_run: false,
+    pre_hash_column: Some("Key".to_string()),
+    import_id: None,
+  };
+  let imported = c
+    .import_database_csv(&workspace_id, &todo_db.id, &query, csv.clone())
+    .await
+    .unwrap();
+  assert_eq!(imported.row_ids.len(), 2);
+
+  // Importing again upserts the same rows
+  let reimported = c
+    .import_database_csv(&workspace_id, &todo_db.id, &query, csv)
+    .await
+    .unwrap();
+  assert_eq!(reimported.row_ids, imported.row_ids);
+
+  // Retrying an import of inserted rows writes the same rows
+  let query = ImportDatabaseCsvQuery {
+    dry_run: false,
+    pre_hash_column: None,
+    import_id: Some("csv-retry".to_string()),
+  };
+  let csv = "Description\ncsv task 3\ncsv task 4\n";
+  let inserted = c
+    .import_database_csv(&workspace_id, &todo_db.id, &query, csv.to_string())
+    .await
+    .unwrap();
+  let retried = c
+    .import_database_csv(&workspace_id, &todo_db.id, &query, csv.to_string())
+    .await
+    .unwrap();
+  assert_eq!(inserted.row_ids.len(), 2);
+  assert_eq!(retried.row_ids, inserted.row_ids);
+
+  let exported = c
+    .export_database_csv(
+      &workspace_id,
+      &todo_db.id,
+      &ExportDatabaseCsvQuery::default(),
+    )
+    .await
+    .unwrap();
+  let mut lines = exported.lines();
+  let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+  assert!(header.contains(&"Description"));
+  assert!(header.contains(&"Status"));
+  let exported_rows: Vec<&str> = lines.collect();
+  assert_eq!(
+    exported_rows
+      .iter()
+      .filter(|line| line.contains("csv task 1") && line.contains("\"social, news\""))
+      .count(),
+    1
+  );
+  assert_eq!(
+    exported_rows
+      .iter()
+      .filter(|line| line.contains("csv task 2") && line.contains("Doing"))
+      .count(),
+    1
+  );
+}
+
+#[tokio::test]
+async fn database_row_change_feed() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+  let description_field_id = c
+    .get_database_fields(&workspace_id, &todo_db.id)
+    .await
+    .unwrap()
+    .into_iter()
+    .find(|field| field.name == "Description")
+    .unwrap()
+    .id;
+  let cursor = c
+    .list_database_row_changes(
+      &workspace_id,
+      &todo_db.id,
+      &ListDatabaseRowChangesParam::default(),
+    )
+    .await
+    .unwrap()
+    .cursor;
+
+  // A row is created, updated twice and deleted
+  let row_id = c
+    .upsert_database_item(
+      &workspace_id,
+      &todo_db.id,
+      "change_feed_row".to_string(),
+      HashMap::from([(String::from("Description"), json!("feed_v1"))]),
+      None,
+    )
+    .await
+    .unwrap();
+  for description in ["feed_v2", "feed_v3"] {
+    c.upsert_database_item(
+      &workspace_id,
+      &todo_db.id,
+      "change_feed_row".to_string(),
+      HashMap::from([(String::from("Description"), json!(description))]),
+      None,
+    )
+    .await
+    .unwrap();
+  }
+  c.delete_database_item(&workspace_id, &todo_db.id, &row_id)
+    .await
+    .unwrap();
+
+  // The changes are recorded once the updates are flushed, one per update
+  let mut changes = vec![];
+  for _ in 0..90 {
+    let resp = c
+      .list_database_row_changes(
+        &workspace_id,
+        &todo_db.id,
+        &ListDatabaseRowChangesParam {
+          cursor: cursor.clone(),
+          limit: None,
+        },
+      )
+      .await
+      .unwrap();
+    changes = resp
+      .changes
+      .into_iter()
+      .filter(|change| change.row_id == row_id)
+      .collect();
+    if changes
+      .iter()
+      .any(|change| change.change_type == DatabaseRowChangeType::Deleted)
+    {
+      break;
+    }
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+  }
+  let change_types: Vec<DatabaseRowChangeType> =
+    changes.iter().map(|change| change.change_type).collect();
+  assert_eq!(
+    change_types,
+    vec![
+      DatabaseRowChangeType::Created,
+      DatabaseRowChangeType::Updated,
+      DatabaseRowChangeType::Updated,
+      DatabaseRowChangeType::Deleted,
+    ]
+  );
+  assert_eq!(changes[0].cells[&description_field_id], "feed_v1");
+  // only the changed cells of an updated row are returned, by field id
+  for (change, description) in changes[1..3].iter().zip(["feed_v2", "feed_v3"]) {
+    assert_eq!(change.cells.len(), 1);
+    assert_eq!(change.cells[&description_field_id], description);
+  }
+  assert!(changes[3].cells.is_empty());
+
+  // Reading again from the same cursor returns the same changes
+  let resp = c
+    .list_database_row_changes(
+      &workspace_id,
+      &todo_db.id,
+      &ListDatabaseRowChangesParam {
+        cursor: Some(changes[2].cursor.clone()),
+        limit: Some(1),
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(resp.changes.len(), 1);
+  assert_eq!(resp.changes[0].cursor, changes[3].cursor);
+}
+
+#[tokio::test]
+async fn database_view_settings() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+
+  for (description, status) in [("view_alpha", "To Do"), ("view_beta", "Doing")] {
+    c.add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([
+        (String::from("Description"), json!(description)),
+        (String::from("Status"), json!(status)),
+      ]),
+      None,
+    )
+    .await
+    .unwrap();
+  }
+
+  let views = c
+    .list_database_views(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  let inline_view = views.iter().find(|view| view.is_inline).unwrap();
+  let fields = c
+    .get_database_fields(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  let status_field = fields.iter().find(|field| field.name == "Status").unwrap();
+
+  let updated_view = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        name: Some("Team board".to_string()),
+        hidden_fields: Some(vec!["Status".to_string()]),
+        filters: Some(vec![DatabaseRowFilter {
+          field: "Status".to_string(),
+          condition: DatabaseRowFilterCondition::SelectOptionIs {
+            options: vec!["Doing".to_string()],
+          },
+        }]),
+        sorts: Some(vec![DatabaseRowSort {
+          field: "Description".to_string(),
+          descending: true,
+        }]),
+        group_by_field: Some("Status".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(updated_view.name, "Team board");
+  assert_eq!(updated_view.hidden_fields, vec![status_field.id.clone()]);
+  assert_eq!(updated_view.filters.len(), 1);
+  assert_eq!(updated_view.filters[0].field, status_field.id);
+  assert!(matches!(
+    &updated_view.filters[0].condition,
+    DatabaseRowFilterCondition::SelectOptionIs { options } if options.len() == 1
+  ));
+  assert_eq!(updated_view.sorts.len(), 1);
+  assert!(updated_view.sorts[0].descending);
+  assert_eq!(updated_view.group_by_field, Some(status_field.id.clone()));
+
+  let view = c
+    .get_database_view(&workspace_id, &todo_db.id, &inline_view.id)
+    .await
+    .unwrap();
+  assert_eq!(view.name, "Team board");
+  assert_eq!(view.field_order, inline_view.field_order);
+
+  // The saved filter is applied when querying the rows of the view
+  let resp = c
+    .query_database_rows(
+      &workspace_id,
+      &todo_db.id,
+      &QueryDatabaseRowsParams {
+        view_id: Some(inline_view.id.clone()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert!(resp
+    .rows
+    .iter()
+    .all(|row| row.cells["Status"].as_str() == Some("Doing")));
+  assert!(resp
+    .rows
+    .iter()
+    .any(|row| row.cells["Description"].as_str() == Some("view_beta")));
+
+  let primary_field = fields.iter().find(|field| field.is_primary).unwrap();
+  let err = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        hidden_fields: Some(vec![primary_field.id.clone()]),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+
+  let view = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        hidden_fields: Some(vec![]),
+        filters: Some(vec![]),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert!(view.hidden_fields.is_empty());
+  assert!(view.filters.is_empty());
+  assert_eq!(view.sorts.len(), 1);
+}
+
+#[tokio::test]
+async fn database_view_layout() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+  let views = c
+    .list_database_views(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  let inline_view = views.iter().find(|view| view.is_inline).unwrap();
+  let due_field_id = c
+    .add_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &AFInsertDatabaseField {
+        name: "Due".to_string(),
+        field_type: FieldType::DateTime.into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+  // A board must be grouped by a field
+  let err = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        layout: Some("Board".to_string()),
+        group_by_field: Some(String::new()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+  let view = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        layout: Some("Board".to_string()),
+        group_by_field: Some("Status".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(view.layout, "Board");
+  assert!(view.group_by_field.is_some());
+
+  // A calendar places the rows by a date field
+  let view = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        layout: Some("Calendar".to_string()),
+        calendar: Some(AFUpdateCalendarViewSettings {
+          date_field: Some("Due".to_string()),
+          first_day_of_week: Some(1),
+          show_weekends: Some(false),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(view.layout, "Calendar");
+  let calendar = view.calendar.unwrap();
+  assert_eq!(calendar.date_field, due_field_id);
+  assert_eq!(calendar.first_day_of_week, 1);
+  assert!(!calendar.show_weekends);
+
+  let err = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        calendar: Some(AFUpdateCalendarViewSettings {
+          date_field: Some("Description".to_string()),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+
+  // The calendar settings are kept when the view goes back to a grid
+  let view = c
+    .update_database_view(
+      &workspace_id,
+      &todo_db.id,
+      &inline_view.id,
+      &AFUpdateDatabaseView {
+        layout: Some("Grid".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(view.layout, "Grid");
+  assert_eq!(view.calendar.unwrap().first_day_of_week, 1);
+}
+
+#[tokio::test]
+async fn database_row_access_rule() {
+  let owner = TestClient::new_user().await;
+  let member = TestClient::new_user().await;
+  let workspace_id = owner.workspace_id().await;
+  owner
+    .invite_and_accepted_workspace_member(&workspace_id, &member, AFRole::Member)
+    .await
+    .unwrap();
+  let member_profile = member.get_user_profile().await;
+  let member_email = member_profile.email.clone().unwrap();
+
+  let databases = owner
+    .api_client
+    .list_databases(&workspace_id)
+    .await
+    .unwrap();
+  let todo_db = &databases[0];
+  let assigned_row_id = owner
+    .api_client
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([(String::from("Description"), json!(member_email))]),
+      None,
+    )
+    .await
+    .unwrap();
+  let private_row_id = owner
+    .api_client
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([(String::from("Description"), json!("owner only"))]),
+      Some("owner only document".to_string()),
+    )
+    .await
+    .unwrap();
+  let row_ids = [assigned_row_id.as_str(), private_row_id.as_str()];
+
+  // only the owners of the workspace manage the rules
+  let err = member
+    .api_client
+    .update_database_row_access(
+      &workspace_id,
+      &todo_db.id,
+      &AFUpdateDatabaseRowAccessRule {
+        user_field: Some("Description".to_string()),
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+  let rule = owner
+    .api_client
+    .update_database_row_access(
+      &workspace_id,
+      &todo_db.id,
+      &AFUpdateDatabaseRowAccessRule {
+        user_field: Some("Description".to_string()),
+      },
+    )
+    .await
+    .unwrap();
+  assert!(rule.user_field_id.is_some());
+  assert!(rule.grants.is_empty());
+
+  let rows = member
+    .api_client
+    .list_database_row_details(&workspace_id, &todo_db.id, &row_ids, false)
+    .await
+    .unwrap();
+  assert_eq!(
+    rows.iter().map(|row| row.id.as_str()).collect::<Vec<_>>(),
+    vec![assigned_row_id.as_str()]
+  );
+  let page = member
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &todo_db.id,
+      &QueryDatabaseRowsParams::default(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(
+    page
+      .rows
+      .iter()
+      .map(|row| row.id.as_str())
+      .collect::<Vec<_>>(),
+    vec![assigned_row_id.as_str()]
+  );
+
+  // the hidden rows cannot be read through their collabs, nor written
+  let private_row_uuid = Uuid::parse_str(&private_row_id).unwrap();
+  let assigned_row_uuid = Uuid::parse_str(&assigned_row_id).unwrap();
+  let err = member
+    .api_client
+    .get_collab(QueryCollabParams::new(
+      private_row_uuid,
+      CollabType::DatabaseRow,
+      workspace_id,
+    ))
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+  let private_document_id = Uuid::parse_str(&meta_id_from_row_id(
+    &private_row_uuid,
+    RowMetaKey::DocumentId,
+  ))
+  .unwrap();
+  let err = member
+    .api_client
+    .get_collab(QueryCollabParams::new(
+      private_document_id,
+      CollabType::Document,
+      workspace_id,
+    ))
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+  let results = member
+    .api_client
+    .batch_get_collab(
+      &workspace_id,
+      vec![
+        QueryCollab {
+          object_id: assigned_row_uuid,
+          collab_type: CollabType::DatabaseRow,
+        },
+        QueryCollab {
+          object_id: private_row_uuid,
+          collab_type: CollabType::DatabaseRow,
+        },
+      ],
+    )
+    .await
+    .unwrap()
+    .0;
+  assert!(matches!(
+    results[&assigned_row_uuid],
+    QueryCollabResult::Success { .. }
+  ));
+  assert!(matches!(
+    results[&private_row_uuid],
+    QueryCollabResult::Failed { .. }
+  ));
+  let err = member
+    .api_client
+    .delete_database_item(&workspace_id, &todo_db.id, &private_row_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+
+  let rows = owner
+    .api_client
+    .list_database_row_details(&workspace_id, &todo_db.id, &row_ids, false)
+    .await
+    .unwrap();
+  assert_eq!(rows.len(), 2);
+
+  let rule = owner
+    .api_client
+    .add_database_row_grants(
+      &workspace_id,
+      &todo_db.id,
+      &AFDatabaseRowGrants {
+        row_ids: vec![private_row_id.clone()],
+        uids: vec![member_profile.uid],
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(rule.grants.len(), 1);
+  let rows = member
+    .api_client
+    .list_database_row_details(&workspace_id, &todo_db.id, &row_ids, false)
+    .await
+    .unwrap();
+  assert_eq!(rows.len(), 2);
+
+  owner
+    .api_client
+    .remove_database_row_grants(
+      &workspace_id,
+      &todo_db.id,
+      &AFDatabaseRowGrants {
+        row_ids: vec![private_row_id.clone()],
+        uids: vec![member_profile.uid],
+      },
+    )
+    .await
+    .unwrap();
+  let rows = member
+    .api_client
+    .list_database_row_details(&workspace_id, &todo_db.id, &row_ids, false)
+    .await
+    .unwrap();
+  assert_eq!(rows.len(), 1);
+
+  owner
+    .api_client
+    .delete_database_row_access(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  let rows = member
+    .api_client
+    .list_database_row_details(&workspace_id, &todo_db.id, &row_ids, false)
+    .await
+    .unwrap();
+  assert_eq!(rows.len(), 2);
+}
+
+#[tokio::test]
+async fn database_row_template() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let databases = c.list_databases(&workspace_id).await.unwrap();
+  let todo_db = &databases[0];
+
+  let err = c
+    .create_database_row_template(
+      &workspace_id,
+      &todo_db.id,
+      &AFCreateDatabaseRowTemplate {
+        name: "Weekly standup".to_string(),
+        schedule: Some("0 9 * *".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+
+  let template = c
+    .create_database_row_template(
+      &workspace_id,
+      &todo_db.id,
+      &AFCreateDatabaseRowTemplate {
+        name: "Weekly standup".to_string(),
+        cells: HashMap::from([(String::from("Description"), json!("standup"))]),
+        row_doc_content: Some("What did you do last week?".to_string()),
+        schedule: Some("0 9 * * 1".to_string()),
+      },
+    )
+    .await
+    .unwrap();
+  assert!(template.next_run_at.is_some());
+  let templates = c
+    .list_database_row_templates(&workspace_id, &todo_db.id)
+    .await
+    .unwrap();
+  assert_eq!(templates.len(), 1);
+
+  let row_id = c
+    .add_database_row_from_template(&workspace_id, &todo_db.id, &template.id)
+    .await
+    .unwrap();
+  let row_detail = &c
+    .list_database_row_details(&workspace_id, &todo_db.id, &[&row_id], true)
+    .await
+    .unwrap()[0];
+  assert_eq!(row_detail.cells["Description"], "standup");
+  assert_eq!(
+    row_detail.doc,
+    Some(String::from("What did you do last week?"))
+  );
+
+  let template = c
+    .update_database_row_template(
+      &workspace_id,
+      &todo_db.id,
+      &template.id,
+      &AFUpdateDatabaseRowTemplate {
+        schedule: Some(String::new()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert!(template.schedule.is_none());
+  assert!(template.next_run_at.is_none());
+
+  c.delete_database_row_template(&workspace_id, &todo_db.id, &template.id)
+    .await
+    .unwrap();
+  let err = c
+    .add_database_row_from_template(&workspace_id, &todo_db.id, &template.id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn database_row_details_with_relations() {
+  let owner = TestClient::new_user().await;
+  let member = TestClient::new_user().await;
+  let workspace_id = owner.workspace_id().await;
+  owner
+    .invite_and_accepted_workspace_member(&workspace_id, &member, AFRole::Member)
+    .await
+    .unwrap();
+  let member_email = member.get_user_profile().await.email.unwrap();
+
+  let databases = owner
+    .api_client
+    .list_databases(&workspace_id)
+    .await
+    .unwrap();
+  let todo_db = &databases[0];
+  owner
+    .api_client
+    .add_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &AFInsertDatabaseField {
+        name: "Related".to_string(),
+        field_type: FieldType::Relation.into(),
+        type_option_data: Some(json!({ "database_id": todo_db.id })),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  let private_row_id = owner
+    .api_client
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([
+        (String::from("Description"), json!("owner only")),
+        (String::from("Status"), json!("To Do")),
+      ]),
+      None,
+    )
+    .await
+    .unwrap();
+  let assigned_row_id = owner
+    .api_client
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([(String::from("Description"), json!(member_email))]),
+      None,
+    )
+    .await
+    .unwrap();
+  let row_id = owner
+    .api_client
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([
+        (String::from("Description"), json!(member_email)),
+        (
+          String::from("Related"),
+          json!([assigned_row_id, private_row_id]),
+        ),
+      ]),
+      None,
+    )
+    .await
+    .unwrap();
+
+  // relation fields are left out unless they are expanded
+  let row_detail = &owner
+    .api_client
+    .list_database_row_details(&workspace_id, &todo_db.id, &[&row_id], false)
+    .await
+    .unwrap()[0];
+  assert!(!row_detail.cells.contains_key("Related"));
+
+  let row_detail = &owner
+    .api_client
+    .list_database_row_details_with_relations(
+      &workspace_id,
+      &todo_db.id,
+      &[&row_id],
+      false,
+      &["Status"],
+    )
+    .await
+    .unwrap()[0];
+  let related_rows: Vec<AFRelatedDatabaseRow> =
+    serde_json::from_value(row_detail.cells["Related"].clone()).unwrap();
+  assert_eq!(
+    related_rows
+      .iter()
+      .map(|row| row.id.as_str())
+      .collect::<Vec<_>>(),
+    vec![assigned_row_id.as_str(), private_row_id.as_str()]
+  );
+  assert_eq!(related_rows[1].primary, "owner only");
+  assert_eq!(related_rows[1].cells["Status"], "To Do");
+
+  // the related rows are checked against the row level rule of the related database
+  owner
+    .api_client
+    .update_database_row_access(
+      &workspace_id,
+      &todo_db.id,
+      &AFUpdateDatabaseRowAccessRule {
+        user_field: Some("Description".to_string()),
+      },
+    )
+    .await
+    .unwrap();
+  let row_detail = &member
+    .api_client
+    .list_database_row_details_with_relations(&workspace_id, &todo_db.id, &[&row_id], false, &[])
+    .await
+    .unwrap()[0];
+  let related_rows: Vec<AFRelatedDatabaseRow> =
+    serde_json::from_value(row_detail.cells["Related"].clone()).unwrap();
+  assert_eq!(related_rows.len(), 1);
+  assert_eq!(related_rows[0].id, assigned_row_id);
+  assert!(related_rows[0].cells.is_empty());
+
+  // databases of other workspaces are not expanded, even if their ids are known
+  let other_workspace_id = member.workspace_id().await;
+  let other_db = &member
+    .api_client
+    .list_databases(&other_workspace_id)
+    .await
+    .unwrap()[0];
+  let other_row_id = member
+    .api_client
+    .add_database_item(
+      &other_workspace_id,
+      &other_db.id,
+      HashMap::from([(String::from("Description"), json!("other workspace"))]),
+      None,
+    )
+    .await
+    .unwrap();
+  owner
+    .api_client
+    .add_database_field(
+      &workspace_id,
+      &todo_db.id,
+      &AFInsertDatabaseField {
+        name: "Foreign".to_string(),
+        field_type: FieldType::Relation.into(),
+        type_option_data: Some(json!({ "database_id": other_db.id })),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  let row_id = owner
+    .api_client
+    .add_database_item(
+      &workspace_id,
+      &todo_db.id,
+      HashMap::from([(String::from("Foreign"), json!([other_row_id]))]),
+      None,
+    )
+    .await
+    .unwrap();
+  let row_detail = &owner
+    .api_client
+    .list_database_row_details_with_relations(&workspace_id, &todo_db.id, &[&row_id], false, &[])
+    .await
+    .unwrap()[0];
+  let related_rows: Vec<AFRelatedDatabaseRow> =
+    serde_json::from_value(row_detail.cells["Foreign"].clone()).unwrap();
+  assert!(related_rows.is_empty());
+}
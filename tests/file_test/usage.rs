@@ -1,3 +1,4 @@
+use app_error::ErrorCode;
 use client_api_test::TestClient;
 
 #[tokio::test]
@@ -34,3 +35,32 @@ async fn workspace_usage_put_and_then_delete_blob_test() {
   let usage = client.get_workspace_usage().await;
   assert_eq!(usage.consumed_capacity, 0);
 }
+
+#[tokio::test]
+async fn workspace_orphaned_blobs_report_test() {
+  let client = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = client.workspace_id().await;
+  let mime = mime::TEXT_PLAIN_UTF_8;
+  let file_id = uuid::Uuid::new_v4().to_string();
+  client.upload_blob(&file_id, "123", &mime).await;
+
+  // Recently uploaded blobs are never reported, whether they are referenced or not
+  let report = client
+    .api_client
+    .get_workspace_orphaned_blobs(&workspace_id)
+    .await
+    .unwrap();
+  assert!(report.blobs.is_empty());
+  assert_eq!(report.total_size, 0);
+
+  // Only the owner can see the report
+  let other = TestClient::new_user_without_ws_conn().await;
+  let err = other
+    .api_client
+    .get_workspace_orphaned_blobs(&workspace_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions, "{:?}", err);
+
+  client.delete_file(&file_id).await;
+}
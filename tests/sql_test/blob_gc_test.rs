@@ -0,0 +1,138 @@
+use crate::sql_test::util::{create_test_collab_document, create_test_user, setup_db};
+use chrono::{Duration, Utc};
+use collab_entity::CollabType;
+use database::blob_gc::{
+  delete_blob_metadata_bulk, select_collectable_blob_ids, select_expired_orphaned_blob_ids,
+  select_file_names_referenced_by_history, select_orphaned_blobs, update_orphaned_blob_marks,
+};
+use database::history::ops::insert_history;
+use database::resource_usage::insert_blob_metadata;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test(migrations = false)]
+async fn mark_and_delete_orphaned_blobs_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  let user_uuid = Uuid::new_v4();
+  let name = user_uuid.to_string();
+  let email = format!("{}@appflowy.io", name);
+  let user = create_test_user(&pool, user_uuid, &email, &name)
+    .await
+    .unwrap();
+  let workspace_id = user.workspace_id;
+  let doc_id = Uuid::new_v4();
+  create_test_collab_document(&pool, &user.uid, &workspace_id, &doc_id).await;
+
+  let history_file_id = format!("{}_history.png", doc_id);
+  let orphan_file_id = format!("{}_orphan.png", doc_id);
+  for file_id in [&history_file_id, &orphan_file_id] {
+    insert_blob_metadata(&pool, file_id, &workspace_id, "image/png", 1024)
+      .await
+      .unwrap();
+  }
+  // blobs uploaded outside of a collab are never collected
+  insert_blob_metadata(
+    &pool,
+    "chat_attachment.png",
+    &workspace_id,
+    "image/png",
+    1024,
+  )
+  .await
+  .unwrap();
+
+  let mut candidates =
+    select_collectable_blob_ids(&pool, &workspace_id, Utc::now() + Duration::minutes(1))
+      .await
+      .unwrap();
+  candidates.sort();
+  let mut expected = vec![history_file_id.clone(), orphan_file_id.clone()];
+  expected.sort();
+  assert_eq!(candidates, expected);
+
+  // the document used to reference one of the blobs
+  let doc_state = format!(
+    "{{\"url\":\"http://localhost/api/file_storage/{}/v1/blob/{}/history.png\"}}",
+    workspace_id, doc_id
+  );
+  insert_history(
+    &workspace_id,
+    &doc_id,
+    doc_state.into_bytes(),
+    1,
+    None,
+    CollabType::Document,
+    Utc::now().timestamp(),
+    vec![],
+    pool.clone(),
+  )
+  .await
+  .unwrap();
+  let referenced = select_file_names_referenced_by_history(
+    &pool,
+    &workspace_id,
+    &["history.png".to_string(), "orphan.png".to_string()],
+  )
+  .await
+  .unwrap();
+  assert_eq!(referenced, vec!["history.png".to_string()]);
+
+  // marked blobs are only deleted once the grace period is over
+  update_orphaned_blob_marks(
+    &pool,
+    &workspace_id,
+    &[history_file_id.clone(), orphan_file_id.clone()],
+    Utc::now() + Duration::days(1),
+  )
+  .await
+  .unwrap();
+  assert_eq!(
+    select_orphaned_blobs(&pool, &workspace_id)
+      .await
+      .unwrap()
+      .len(),
+    2
+  );
+  assert!(select_expired_orphaned_blob_ids(&pool, &workspace_id)
+    .await
+    .unwrap()
+    .is_empty());
+
+  // a blob referenced again is unmarked, and marking the others again keeps their deletion time
+  update_orphaned_blob_marks(
+    &pool,
+    &workspace_id,
+    &[orphan_file_id.clone()],
+    Utc::now() - Duration::seconds(1),
+  )
+  .await
+  .unwrap();
+  let orphans = select_orphaned_blobs(&pool, &workspace_id).await.unwrap();
+  assert_eq!(orphans.len(), 1);
+  assert_eq!(orphans[0].file_id, orphan_file_id);
+  assert!(orphans[0].delete_after > Utc::now());
+
+  // expired marks are deleted with their blob
+  sqlx::query("UPDATE af_blob_orphan SET delete_after = NOW() - INTERVAL '1 second'")
+    .execute(&pool)
+    .await
+    .unwrap();
+  let expired = select_expired_orphaned_blob_ids(&pool, &workspace_id)
+    .await
+    .unwrap();
+  assert_eq!(expired, vec![orphan_file_id.clone()]);
+  let deleted = delete_blob_metadata_bulk(&pool, &workspace_id, &expired)
+    .await
+    .unwrap();
+  assert_eq!(deleted, 1);
+  assert!(select_orphaned_blobs(&pool, &workspace_id)
+    .await
+    .unwrap()
+    .is_empty());
+  let candidates =
+    select_collectable_blob_ids(&pool, &workspace_id, Utc::now() + Duration::minutes(1))
+      .await
+      .unwrap();
+  assert_eq!(candidates, vec![history_file_id]);
+}
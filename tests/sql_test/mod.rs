@@ -1,3 +1,4 @@
+mod blob_gc_test;
 mod chat_test;
 mod collab_embed_test;
 mod history_test;
@@ -0,0 +1,38 @@
+use app_error::ErrorCode;
+use client_api_test::*;
+use shared_entity::dto::auth_dto::UserDataExportStatus;
+use std::time::Duration;
+
+#[tokio::test]
+async fn user_data_export_test() {
+  let (client, _user) = generate_unique_registered_user_client().await;
+  let export = client.create_user_data_export().await.unwrap();
+  assert!(export.download_url.is_none());
+  let err = client.create_user_data_export().await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest, "{:?}", err);
+
+  // exports of other users are not visible
+  let (other_client, _other_user) = generate_unique_registered_user_client().await;
+  let err = other_client
+    .get_user_data_export(&export.export_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound, "{:?}", err);
+
+  let mut status = export.status;
+  for _ in 0..30 {
+    let export = client
+      .get_user_data_export(&export.export_id)
+      .await
+      .unwrap();
+    status = export.status;
+    if status == UserDataExportStatus::Completed {
+      assert!(export.download_url.is_some());
+      assert!(export.expires_at.is_some());
+      assert!(export.file_size > 0);
+      break;
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+  }
+  assert_eq!(status, UserDataExportStatus::Completed);
+}
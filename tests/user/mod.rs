@@ -1,3 +1,4 @@
+mod data_export;
 mod delete;
 mod refresh;
 mod sign_in;
@@ -1,2 +1,3 @@
 mod actor_test;
 mod conn_test;
+mod sse_test;
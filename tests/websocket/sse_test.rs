@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use app_error::ErrorCode;
+use client_api::Client;
+use client_api_test::generate_unique_registered_user_client;
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use shared_entity::response::AppResponse;
+use tokio::time::timeout;
+use uuid::Uuid;
+
+const CLIENT_ID: u64 = 4242;
+
+#[tokio::test]
+async fn sse_session_routes_posted_messages_test() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let (other, _other_user) = generate_unique_registered_user_client().await;
+  let workspace_id = c.get_workspaces().await.unwrap()[0].workspace_id;
+  let http = reqwest::Client::new();
+
+  // no session has been established yet
+  let code = post_message(&http, &c, workspace_id, b"message").await;
+  assert_eq!(code, ErrorCode::RecordNotFound);
+
+  let resp = http
+    .get(format!(
+      "{}/ws/v2/{}/sse?deviceId=sse-test&clientId={}",
+      c.base_url(),
+      workspace_id,
+      CLIENT_ID
+    ))
+    .bearer_auth(c.access_token().unwrap())
+    .header(ACCEPT, "text/event-stream")
+    .send()
+    .await
+    .unwrap();
+  assert!(resp.status().is_success());
+  let content_type = resp.headers()[CONTENT_TYPE].to_str().unwrap().to_string();
+  assert!(content_type.starts_with("text/event-stream"));
+  let mut events = resp.bytes_stream();
+  // the server sends keep-alive pings on its own
+  let event = timeout(Duration::from_secs(15), events.next())
+    .await
+    .unwrap()
+    .unwrap()
+    .unwrap();
+  assert!(!event.is_empty());
+
+  // messages are only accepted from the owner of the session
+  let code = post_message(&http, &other, workspace_id, b"message").await;
+  assert_eq!(code, ErrorCode::InvalidRequest);
+
+  // malformed message closes the session
+  let code = post_message(&http, &c, workspace_id, b"\xff\xff\xff\xff").await;
+  assert_eq!(code, ErrorCode::InvalidRequest);
+  let closed = timeout(Duration::from_secs(15), async {
+    while let Some(Ok(_)) = events.next().await {}
+  })
+  .await;
+  assert!(closed.is_ok(), "event stream should be closed");
+
+  let mut code = ErrorCode::Ok;
+  for _ in 0..10 {
+    code = post_message(&http, &c, workspace_id, b"message").await;
+    if code == ErrorCode::RecordNotFound {
+      break;
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+  }
+  assert_eq!(code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn sse_session_requires_access_token_test() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = c.get_workspaces().await.unwrap()[0].workspace_id;
+  let resp = reqwest::Client::new()
+    .get(format!(
+      "{}/ws/v2/{}/sse?deviceId=sse-test&clientId={}",
+      c.base_url(),
+      workspace_id,
+      CLIENT_ID
+    ))
+    .header(ACCEPT, "text/event-stream")
+    .send()
+    .await
+    .unwrap();
+  let resp: AppResponse<()> = resp.json().await.unwrap();
+  assert_eq!(resp.code, ErrorCode::UserUnAuthorized);
+}
+
+async fn post_message(
+  http: &reqwest::Client,
+  c: &Client,
+  workspace_id: Uuid,
+  body: &'static [u8],
+) -> ErrorCode {
+  let resp = http
+    .post(format!(
+      "{}/ws/v2/{}/sse/{}",
+      c.base_url(),
+      workspace_id,
+      CLIENT_ID
+    ))
+    .bearer_auth(c.access_token().unwrap())
+    .header(CONTENT_TYPE, "application/octet-stream")
+    .body(body)
+    .send()
+    .await
+    .unwrap();
+  let resp: AppResponse<()> = resp.json().await.unwrap();
+  resp.code
+}
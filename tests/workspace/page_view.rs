@@ -1,19 +1,24 @@
 use std::{collections::HashSet, time::Duration};
 
+use app_error::ErrorCode;
 use client_api::entity::{QueryCollab, QueryCollabParams};
 use client_api_test::{
   generate_unique_registered_user, generate_unique_registered_user_client, TestClient,
 };
+use collab::core::collab::default_client_id;
 use collab::core::origin::CollabClient;
+use collab_document::blocks::DocumentData;
+use collab_document::document::Document;
 use collab_entity::CollabType;
 use collab_folder::{CollabOrigin, Folder};
 use serde_json::{json, Value};
 use shared_entity::dto::workspace_dto::{
-  AddRecentPagesParams, AppendBlockToPageParams, CreateFolderViewParams,
+  AddRecentPagesParams, AppendBlockToPageParams, CreateFolderViewParams, CreateMarkdownPageParams,
   CreatePageDatabaseViewParams, CreatePageParams, CreateSpaceParams, DuplicatePageParams,
-  FavoritePageParams, IconType, MovePageParams, PublishPageParams, SpacePermission,
-  UpdatePageExtraParams, UpdatePageIconParams, UpdatePageNameParams, UpdatePageParams,
-  UpdateSpaceParams, ViewIcon, ViewLayout,
+  FavoritePageParams, IconType, InsertBlockParams, MoveBlockParams, MovePageParams,
+  PublishPageParams, SpacePermission, UpdateBlockParams, UpdatePageExtraParams,
+  UpdatePageIconParams, UpdatePageNameParams, UpdatePageParams, UpdateSpaceParams, ViewIcon,
+  ViewLayout,
 };
 use tokio::time::sleep;
 use uuid::Uuid;
@@ -343,6 +348,215 @@ async fn append_block_to_page() {
   .unwrap();
 }
 
+#[tokio::test]
+async fn create_and_append_markdown_page() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspaces = c.get_workspaces().await.unwrap();
+  let workspace_id = workspaces[0].workspace_id;
+  let folder_view = c
+    .get_workspace_folder(&workspace_id, Some(2), None)
+    .await
+    .unwrap();
+  let general_space = &folder_view
+    .children
+    .into_iter()
+    .find(|v| v.name == "General")
+    .unwrap();
+  let page = c
+    .create_workspace_page_view_from_markdown(
+      workspace_id,
+      &CreateMarkdownPageParams {
+        parent_view_id: general_space.view_id,
+        name: Some("Release notes".to_string()),
+        view_id: None,
+      },
+      "# v1.0\n\n- [x] Markdown import\n- [ ] Markdown export\n\n```rust\nfn main() {}\n```\n",
+    )
+    .await
+    .unwrap();
+  let block_types = |document_data: &DocumentData| {
+    document_data.meta.children_map[&document_data.blocks[&document_data.page_id].children]
+      .iter()
+      .map(|id| document_data.blocks[id].ty.clone())
+      .collect::<Vec<_>>()
+  };
+  let document_data = get_document_data(&c, workspace_id, page.view_id).await;
+  let created_block_types = block_types(&document_data);
+  for ty in ["heading", "todo_list", "code"] {
+    assert!(
+      created_block_types.iter().any(|t| t == ty),
+      "{:?}",
+      created_block_types
+    );
+  }
+
+  c.append_markdown_to_page(workspace_id, &page.view_id, "Shipped by **CI**.")
+    .await
+    .unwrap();
+  sleep(Duration::from_secs(1)).await;
+  let document_data = get_document_data(&c, workspace_id, page.view_id).await;
+  let appended_block_types = block_types(&document_data);
+  assert_eq!(appended_block_types.len(), created_block_types.len() + 1);
+  assert_eq!(appended_block_types.last().unwrap(), "paragraph");
+}
+
+async fn get_document_data(
+  c: &client_api::Client,
+  workspace_id: Uuid,
+  view_id: Uuid,
+) -> DocumentData {
+  let resp = c
+    .get_collab(QueryCollabParams {
+      workspace_id,
+      inner: QueryCollab {
+        object_id: view_id,
+        collab_type: CollabType::Document,
+      },
+    })
+    .await
+    .unwrap();
+  Document::open_with_options(
+    CollabOrigin::Empty,
+    resp.encode_collab.into(),
+    &view_id.to_string(),
+    default_client_id(),
+  )
+  .unwrap()
+  .get_document_data()
+  .unwrap()
+}
+
+#[tokio::test]
+async fn edit_page_block() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspaces = c.get_workspaces().await.unwrap();
+  let workspace_id = workspaces[0].workspace_id;
+  let folder_view = c
+    .get_workspace_folder(&workspace_id, Some(2), None)
+    .await
+    .unwrap();
+  let general_space = &folder_view
+    .children
+    .into_iter()
+    .find(|v| v.name == "General")
+    .unwrap();
+  let view_id = general_space
+    .children
+    .iter()
+    .find(|v| v.name == "Getting started")
+    .unwrap()
+    .view_id;
+  let first_block_id = c
+    .insert_block_to_page(
+      workspace_id,
+      &view_id,
+      &InsertBlockParams {
+        block: json!({
+          "type": "heading",
+          "data": { "level": 2, "delta": [{ "insert": "Summary" }] }
+        }),
+        parent_block_id: None,
+        prev_block_id: None,
+      },
+    )
+    .await
+    .unwrap()
+    .block_id;
+  let block_id = c
+    .insert_block_to_page(
+      workspace_id,
+      &view_id,
+      &InsertBlockParams {
+        block: json!({
+          "type": "paragraph",
+          "data": { "delta": [{ "insert": "Hello" }] }
+        }),
+        parent_block_id: None,
+        prev_block_id: Some(first_block_id.clone()),
+      },
+    )
+    .await
+    .unwrap()
+    .block_id;
+
+  let block = c
+    .get_page_block(workspace_id, &view_id, &block_id)
+    .await
+    .unwrap();
+  assert_eq!(block.ty, "paragraph");
+  assert_eq!(block.delta, Some(vec![json!({ "insert": "Hello" })]));
+  let page = c
+    .get_page_block(workspace_id, &view_id, &block.parent_id)
+    .await
+    .unwrap();
+  assert_eq!(page.children[0], first_block_id);
+  assert_eq!(page.children[1], block_id);
+
+  c.update_page_block(
+    workspace_id,
+    &view_id,
+    &block_id,
+    &UpdateBlockParams {
+      data: Some([("text_direction".to_string(), json!("rtl"))].into()),
+      delta: Some(vec![json!({ "retain": 5 }), json!({ "insert": " world" })]),
+    },
+  )
+  .await
+  .unwrap();
+  let block = c
+    .get_page_block(workspace_id, &view_id, &block_id)
+    .await
+    .unwrap();
+  assert_eq!(block.data.get("text_direction"), Some(&json!("rtl")));
+  assert_eq!(block.delta, Some(vec![json!({ "insert": "Hello world" })]));
+
+  // A block cannot be moved after itself
+  let err = c
+    .move_page_block(
+      workspace_id,
+      &view_id,
+      &block_id,
+      &MoveBlockParams {
+        parent_block_id: None,
+        prev_block_id: Some(block_id.clone()),
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest, "{:?}", err);
+  c.move_page_block(
+    workspace_id,
+    &view_id,
+    &block_id,
+    &MoveBlockParams {
+      parent_block_id: None,
+      prev_block_id: None,
+    },
+  )
+  .await
+  .unwrap();
+  let page = c
+    .get_page_block(workspace_id, &view_id, &page.id)
+    .await
+    .unwrap();
+  assert_eq!(page.children[0], block_id);
+  assert_eq!(page.children[1], first_block_id);
+
+  c.delete_page_block(workspace_id, &view_id, &block_id)
+    .await
+    .unwrap();
+  let err = c
+    .get_page_block(workspace_id, &view_id, &block_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound, "{:?}", err);
+  let err = c
+    .delete_page_block(workspace_id, &view_id, &page.id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest, "{:?}", err);
+}
+
 #[tokio::test]
 async fn create_new_chat_page() {
   let (c, _user) = generate_unique_registered_user_client().await;
@@ -1063,6 +1277,8 @@ async fn publish_page() {
           visible_database_view_ids: None,
           comments_enabled: None,
           duplicate_enabled: None,
+          password: None,
+          expires_at: None,
         },
       )
       .await
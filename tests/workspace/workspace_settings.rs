@@ -1,3 +1,4 @@
+use app_error::ErrorCode;
 use client_api::Client;
 use client_api_test::generate_unique_registered_user_client;
 use database_entity::dto::{AFRole, AFWorkspaceInvitationStatus, AFWorkspaceSettingsChange};
@@ -28,6 +29,44 @@ async fn get_and_set_workspace_by_owner() {
   assert!(settings.disable_search_indexing);
 }
 
+#[tokio::test]
+async fn set_workspace_trash_retention() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspaces = c.get_workspaces().await.unwrap();
+  let workspace_id = workspaces.first().unwrap().workspace_id.to_string();
+
+  let settings = c.get_workspace_settings(&workspace_id).await.unwrap();
+  assert_eq!(
+    settings.trash_retention_days, 0,
+    "trashed pages should be kept forever by default"
+  );
+
+  let settings = c
+    .update_workspace_settings(
+      &workspace_id,
+      &AFWorkspaceSettingsChange::new().trash_retention_days(30),
+    )
+    .await
+    .unwrap();
+  assert_eq!(settings.trash_retention_days, 30);
+
+  // Other settings are left untouched
+  let settings = c.get_workspace_settings(&workspace_id).await.unwrap();
+  assert_eq!(settings.trash_retention_days, 30);
+  assert!(!settings.disable_search_indexing);
+
+  let err = c
+    .update_workspace_settings(
+      &workspace_id,
+      &AFWorkspaceSettingsChange::new().trash_retention_days(u32::MAX),
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+  let settings = c.get_workspace_settings(&workspace_id).await.unwrap();
+  assert_eq!(settings.trash_retention_days, 30);
+}
+
 #[tokio::test]
 async fn get_and_set_workspace_by_non_owner() {
   // TODO: currently, workspace settings contains only AI preference, which is